@@ -0,0 +1,839 @@
+use crate::*;
+
+// 770
+const AlignStackNodeSize: usize = 5; // number of mem words to save alignment states
+
+// 780
+pub const SpanCode: HalfWord = 256; // distinct from any character
+pub const CrCode: HalfWord = 257; // distinct from span_code and from any character
+pub const CrCrCode: HalfWord = CrCode + 1; // this distinguishes \crcr from \cr
+pub const EndTemplateToken: HalfWord = cs_token_flag as HalfWord + FrozenEndTemplate;
+
+// 797
+const SpanNodeSize: usize = 2; // number of mem words for a span node
+
+impl TexState {
+    // 769
+    // the token lists of an alignrecord's templates, and the command that began its entry
+    fn u_part(&self, p: usize) -> HalfWord {
+        self.mem[p + HeightOffset].int() as HalfWord
+    }
+
+    fn set_u_part(&mut self, p: usize, value: HalfWord) {
+        self.mem[p + HeightOffset].set_int(value as i32);
+    }
+
+    fn v_part(&self, p: usize) -> HalfWord {
+        self.mem[p + DepthOffset].int() as HalfWord
+    }
+
+    fn set_v_part(&mut self, p: usize, value: HalfWord) {
+        self.mem[p + DepthOffset].set_int(value as i32);
+    }
+
+    fn extra_info(&self, p: usize) -> HalfWord {
+        self.info(p + ListOffset)
+    }
+
+    fn set_extra_info(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + ListOffset, value);
+    }
+
+    // 770
+    // the current preamble list
+    fn preamble(&self) -> HalfWord {
+        self.link(AlignHead)
+    }
+
+    fn set_preamble(&mut self, value: HalfWord) {
+        self.set_link(AlignHead, value);
+    }
+
+    // 772
+    // saves the state of the alignment in progress, if any, before a new one begins
+    fn push_alignment(&mut self) {
+        let p = self.get_node(AlignStackNodeSize);
+        let q = p as usize;
+        self.set_link(q, self.align_ptr);
+        self.set_info(q, self.cur_align);
+        self.set_llink(q, self.preamble());
+        self.set_rlink(q, self.cur_span);
+        self.mem[q + 2].set_int(self.cur_loop as i32);
+        self.mem[q + 3].set_int(self.align_state);
+        self.set_info(q + 4, self.cur_head);
+        self.set_link(q + 4, self.cur_tail);
+        self.align_ptr = p;
+        self.cur_head = self.get_avail();
+    }
+
+    fn pop_alignment(&mut self) {
+        self.free_avail(self.cur_head);
+        let p = self.align_ptr;
+        let q = p as usize;
+        self.cur_tail = self.link(q + 4);
+        self.cur_head = self.info(q + 4);
+        self.align_state = self.mem[q + 3].int();
+        self.cur_loop = self.mem[q + 2].int() as Pointer;
+        self.cur_span = self.rlink(q);
+        self.set_preamble(self.llink(q));
+        self.cur_align = self.info(q);
+        self.align_ptr = self.link(q);
+        self.free_node(p, AlignStackNodeSize);
+    }
+
+    // 774
+    // starts an \halign or \valign, scanning its preamble
+    pub fn init_align(&mut self) {
+        let save_cs_ptr = self.cur_cs; // \halign or \valign, usually
+        self.push_alignment();
+        self.align_state = -1000000; // enter a new alignment level
+        // 776: check for improper alignment in displayed math
+        if self.mode() == Mmode && (self.tail() != self.head() || self.incompleat_noad() != Null as i32) {
+            self.print_err("Improper ");
+            self.print_esc("halign");
+            self.print(" inside $$'s");
+            self.help(&["Displays can use special alignments (like \\eqalignno)",
+                "only if nothing but the alignment itself is between $$'s.",
+                "So I've deleted the formulas that preceded this alignment."]);
+            self.error();
+            self.flush_math();
+        }
+        self.push_nest(); // enter a new semantic level
+        // 775: change current mode to -vmode for \halign, -hmode for \valign
+        if self.mode() == Mmode {
+            self.set_mode(-Vmode);
+            let d = self.nest[self.nest_ptr - 2].aux_field.sc();
+            self.set_prev_depth(d);
+        } else if self.mode() > 0 {
+            self.set_mode(-self.mode());
+        }
+        self.scan_spec(AlignGroup, false);
+        // 777: scan the preamble and record it in the preamble list
+        self.set_preamble(Null);
+        self.cur_align = AlignHead as Pointer;
+        self.cur_loop = Null;
+        self.scanner_status = Aligning;
+        self.warning_index = save_cs_ptr;
+        self.align_state = -1000000; // at this point, cur_cmd=left_brace
+        loop {
+            // 778: append the current tabskip glue to the preamble list
+            let g = self.new_param_glue(TabSkipCode);
+            self.set_link(self.cur_align as usize, g);
+            self.cur_align = g;
+            if self.cur_cmd == CarRet {
+                break; // \cr ends the preamble
+            }
+            // 779: scan preamble text until cur_cmd is tab_mark or car_ret, looking
+            // for changes in the tabskip glue; append an alignrecord to the preamble list
+            // 783: scan the template <u_j>, putting the resulting token list in hold_head
+            let mut p = HoldHead;
+            self.set_link(p, Null);
+            loop {
+                self.get_preamble_token();
+                if self.cur_cmd == MacParam {
+                    break;
+                }
+                if self.cur_cmd <= CarRet && self.cur_cmd >= TabMark && self.align_state == -1000000 {
+                    if p == HoldHead && self.cur_loop == Null && self.cur_cmd == TabMark {
+                        self.cur_loop = self.cur_align;
+                    } else {
+                        self.print_err("Missing # inserted in alignment preamble");
+                        self.help(&["There should be exactly one # between &'s, when an",
+                            "\\halign or \\valign is being set up. In this case you had",
+                            "none, so I've put one in; maybe that will work."]);
+                        self.back_error();
+                        break;
+                    }
+                } else if self.cur_cmd != Spacer || p != HoldHead {
+                    let q = self.get_avail();
+                    self.set_link(p, q);
+                    p = q as usize;
+                    self.set_info(p, self.cur_tok);
+                }
+            }
+            let r = self.new_null_box(); // a new alignrecord
+            self.set_link(self.cur_align as usize, r);
+            self.cur_align = r;
+            self.set_info(r as usize, EndSpan as HalfWord);
+            self.set_width(r as usize, NullFlag);
+            self.set_u_part(r as usize, self.link(HoldHead));
+            // 784: scan the template <v_j>, putting the resulting token list in hold_head
+            let mut p = HoldHead;
+            self.set_link(p, Null);
+            loop {
+                self.get_preamble_token();
+                if self.cur_cmd <= CarRet && self.cur_cmd >= TabMark && self.align_state == -1000000 {
+                    break;
+                }
+                if self.cur_cmd == MacParam {
+                    self.print_err("Only one # is allowed per tab");
+                    self.help(&["There should be exactly one # between &'s, when an",
+                        "\\halign or \\valign is being set up. In this case you had",
+                        "more than one, so I'm ignoring all but the first."]);
+                    self.error();
+                    continue;
+                }
+                let q = self.get_avail();
+                self.set_link(p, q);
+                p = q as usize;
+                self.set_info(p, self.cur_tok);
+            }
+            let q = self.get_avail();
+            self.set_link(p, q);
+            self.set_info(q as usize, EndTemplateToken); // put \endtemplate at the end
+            self.set_v_part(r as usize, self.link(HoldHead));
+        }
+        self.scanner_status = Normal;
+        self.new_save_level(AlignGroup);
+        let e = self.equiv(EveryCrLoc as usize);
+        if e != Null {
+            self.begin_token_list(e, EveryCrText);
+        }
+        self.align_peek(); // look for \noalign or \omit
+    }
+
+    // 782
+    // gets the next token of a preamble, expanding \span and performing \tabskip assignments
+    fn get_preamble_token(&mut self) {
+        loop {
+            self.get_token();
+            while self.cur_chr == SpanCode && self.cur_cmd == TabMark {
+                self.get_token(); // this token will be expanded once
+                if self.cur_cmd > MaxCommand {
+                    self.expand();
+                    self.get_token();
+                }
+            }
+            if self.cur_cmd == Endv {
+                self.fatal_error("(interwoven alignment preambles are not allowed)");
+            }
+            if self.cur_cmd == AssignGlue && self.cur_chr == GlueBase + TabSkipCode {
+                self.scan_optional_equals();
+                self.scan_glue(GlueVal);
+                if self.int_par(GlobalDefsCode) > 0 {
+                    self.geq_define((GlueBase + TabSkipCode) as Pointer, GlueRef, self.cur_val as HalfWord);
+                } else {
+                    self.eq_define((GlueBase + TabSkipCode) as Pointer, GlueRef, self.cur_val as HalfWord);
+                }
+                continue;
+            }
+            return;
+        }
+    }
+
+    // 785
+    // looks at what follows a \cr: \noalign, the closing brace, or a new row
+    pub fn align_peek(&mut self) {
+        loop {
+            self.align_state = 1000000;
+            self.get_x_non_blank();
+            if self.cur_cmd == NoAlign {
+                self.scan_left_brace();
+                self.new_save_level(NoAlignGroup);
+                if self.mode() == -Vmode {
+                    self.normal_paragraph();
+                }
+            } else if self.cur_cmd == RightBrace {
+                self.fin_align();
+            } else if self.cur_cmd == CarRet && self.cur_chr == CrCrCode {
+                continue; // ignore \crcr
+            } else {
+                self.init_row(); // start a new row
+                self.init_col(); // start a new column and replace what we peeked at
+            }
+            return;
+        }
+    }
+
+    // 786
+    // starts a row, whose first entry begins with the current tabskip glue
+    fn init_row(&mut self) {
+        self.push_nest();
+        self.set_mode((-Hmode - Vmode) - self.mode());
+        if self.mode() == -Hmode {
+            self.set_space_factor(0);
+        } else {
+            self.set_prev_depth(0);
+        }
+        let g = self.new_glue(self.glue_ptr(self.preamble() as usize));
+        self.tail_append(g);
+        self.set_subtype(self.tail() as usize, (TabSkipCode + 1) as QuarterWord);
+        self.cur_align = self.link(self.preamble() as usize);
+        self.cur_tail = self.cur_head;
+        self.init_span(self.cur_align);
+    }
+
+    // 787
+    // starts the entry, possibly spanning several columns, that begins at alignrecord p
+    fn init_span(&mut self, p: Pointer) {
+        self.push_nest();
+        if self.mode() == -Hmode {
+            self.set_space_factor(1000);
+        } else {
+            self.set_prev_depth(IgnoreDepth);
+            self.normal_paragraph();
+        }
+        self.cur_span = p;
+    }
+
+    // 788
+    // starts a column, inserting its <u_j> template unless it began with \omit
+    fn init_col(&mut self) {
+        self.set_extra_info(self.cur_align as usize, self.cur_cmd as HalfWord);
+        if self.cur_cmd == Omit {
+            self.align_state = 0;
+        } else {
+            self.back_input();
+            self.begin_token_list(self.u_part(self.cur_align as usize), UTemplate);
+        } // now align_state=1000000
+    }
+
+    // 789
+    // when an entry has just ended, inserts its <v_j> template; cur_chr is
+    // remembered so that fin_col can tell whether & or \span or \cr ended it
+    pub fn insert_v_template(&mut self) {
+        if self.scanner_status == Aligning || self.cur_align == Null {
+            self.fatal_error("(interwoven alignment preambles are not allowed)");
+        }
+        let c = self.extra_info(self.cur_align as usize);
+        self.cur_cmd = c as QuarterWord;
+        self.set_extra_info(self.cur_align as usize, self.cur_chr);
+        if c == Omit as HalfWord {
+            self.begin_token_list(OmitTemplate as Pointer, VTemplate);
+        } else {
+            self.begin_token_list(self.v_part(self.cur_align as usize), VTemplate);
+        }
+        self.align_state = 1000000;
+    }
+
+    // 791
+    // finishes a column; true means the row has ended too
+    pub fn fin_col(&mut self) -> bool {
+        if self.cur_align == Null {
+            self.confusion("endv");
+        }
+        let q = self.link(self.cur_align as usize);
+        if q == Null {
+            self.confusion("endv");
+        }
+        if self.align_state < 500000 {
+            self.fatal_error("(interwoven alignment preambles are not allowed)");
+        }
+        let mut p = self.link(q as usize); // the alignrecord after the current one
+        // 792: if the preamble list has been traversed, check that the row has ended
+        if p == Null && self.extra_info(self.cur_align as usize) < CrCode {
+            if self.cur_loop != Null {
+                // 793: lengthen the preamble periodically
+                p = self.new_null_box(); // a new alignrecord
+                self.set_link(q as usize, p);
+                self.set_info(p as usize, EndSpan as HalfWord);
+                self.set_width(p as usize, NullFlag);
+                self.cur_loop = self.link(self.cur_loop as usize);
+                // 794: copy the templates from node cur_loop into node p
+                let u = self.copy_template(self.u_part(self.cur_loop as usize));
+                self.set_u_part(p as usize, u);
+                let v = self.copy_template(self.v_part(self.cur_loop as usize));
+                self.set_v_part(p as usize, v);
+                self.cur_loop = self.link(self.cur_loop as usize);
+                let g = self.new_glue(self.glue_ptr(self.cur_loop as usize));
+                self.set_link(p as usize, g);
+                self.set_subtype(g as usize, (TabSkipCode + 1) as QuarterWord);
+            } else {
+                self.print_err("Extra alignment tab has been changed to ");
+                self.print_esc("cr");
+                self.help(&["You have given more \\span or & marks than there were",
+                    "in the preamble to the \\halign or \\valign now in progress.",
+                    "So I'll assume that you meant to type \\cr instead."]);
+                self.set_extra_info(self.cur_align as usize, CrCode);
+                self.error();
+            }
+        }
+        if self.extra_info(self.cur_align as usize) != SpanCode {
+            self.unsave();
+            self.new_save_level(AlignGroup);
+            self.package_unset_column();
+            // 795: copy the tabskip glue between columns
+            let g = self.new_glue(self.glue_ptr(self.link(self.cur_align as usize) as usize));
+            self.tail_append(g);
+            self.set_subtype(self.tail() as usize, (TabSkipCode + 1) as QuarterWord);
+            if self.extra_info(self.cur_align as usize) >= CrCode {
+                return true;
+            }
+            self.init_span(p);
+        }
+        self.align_state = 1000000;
+        self.get_x_non_blank();
+        self.cur_align = p;
+        self.init_col();
+        false
+    }
+
+    // 794
+    // a copy of the template token list r
+    fn copy_template(&mut self, r: HalfWord) -> HalfWord {
+        let mut q = HoldHead;
+        let mut r = r;
+        while r != Null {
+            let a = self.get_avail();
+            self.set_link(q, a);
+            q = a as usize;
+            self.set_info(q, self.info(r as usize));
+            r = self.link(r as usize);
+        }
+        self.set_link(q, Null);
+        self.link(HoldHead)
+    }
+
+    // 796
+    // packages an unset box for the current column and records its width
+    fn package_unset_column(&mut self) {
+        let u; // a new unset box
+        let w; // its natural width
+        if self.mode() == -Hmode {
+            self.adjust_tail = self.cur_tail;
+            u = self.hpack(self.link(self.head() as usize), 0, Additional);
+            w = self.width(u as usize);
+            self.cur_tail = self.adjust_tail;
+            self.adjust_tail = Null;
+        } else {
+            u = self.vpackage(self.link(self.head() as usize), 0, Additional, 0);
+            w = self.height(u as usize);
+        }
+        let mut n = MinQuarterWord as HalfWord; // this represents a span count of 1
+        if self.cur_span != self.cur_align {
+            // 798: update width entry for spanned columns
+            let mut q = self.cur_span;
+            loop {
+                n += 1;
+                q = self.link(self.link(q as usize) as usize);
+                if q == self.cur_align {
+                    break;
+                }
+            }
+            if n > MaxQuarterWord as HalfWord {
+                self.confusion("256 spans"); // this can happen, but won't
+            }
+            q = self.cur_span;
+            while self.link(self.info(q as usize) as usize) < n {
+                q = self.info(q as usize);
+            }
+            if self.link(self.info(q as usize) as usize) > n {
+                let s = self.get_node(SpanNodeSize);
+                self.set_info(s as usize, self.info(q as usize));
+                self.set_link(s as usize, n);
+                self.set_info(q as usize, s);
+                self.set_width(s as usize, w);
+            } else if self.width(self.info(q as usize) as usize) < w {
+                self.set_width(self.info(q as usize) as usize, w);
+            }
+        } else if w > self.width(self.cur_align as usize) {
+            self.set_width(self.cur_align as usize, w);
+        }
+        let v = u as usize;
+        self.set_type(v, UnsetNode);
+        self.set_span_count(v, n as QuarterWord);
+        let o = TexState::glue_total_order(&self.total_stretch);
+        self.set_glue_order(v, o as u8);
+        self.set_glue_stretch(v, self.total_stretch[o as usize]);
+        let o = TexState::glue_total_order(&self.total_shrink);
+        self.set_glue_sign(v, o as u8);
+        self.set_glue_shrink(v, self.total_shrink[o as usize]);
+        self.pop_nest();
+        self.set_link(self.tail() as usize, u);
+        self.set_tail(u);
+    }
+
+    // 799
+    // finishes a row, packaging its columns into an unset box
+    pub fn fin_row(&mut self) {
+        let p; // the new unset box
+        if self.mode() == -Hmode {
+            p = self.hpack(self.link(self.head() as usize), 0, Additional);
+            self.pop_nest();
+            self.append_to_vlist(p);
+            if self.cur_head != self.cur_tail {
+                self.set_link(self.tail() as usize, self.link(self.cur_head as usize));
+                self.set_tail(self.cur_tail);
+            }
+        } else {
+            p = self.vpack(self.link(self.head() as usize), 0, Additional);
+            self.pop_nest();
+            self.set_link(self.tail() as usize, p);
+            self.set_tail(p);
+            self.set_space_factor(1000);
+        }
+        self.set_type(p as usize, UnsetNode);
+        self.set_glue_stretch(p as usize, 0);
+        let e = self.equiv(EveryCrLoc as usize);
+        if e != Null {
+            self.begin_token_list(e, EveryCrText);
+        }
+        self.align_peek();
+    } // note that glue_shrink(p)=0 since glue_shrink==shift_amount
+
+    // 800
+    // finishes the alignment: the column widths are found, the rows are set
+    // to them, and the whole list goes into the enclosing one
+    fn fin_align(&mut self) {
+        if self.cur_group.value() != AlignGroup {
+            self.confusion("align1");
+        }
+        self.unsave(); // that align_group was for individual entries
+        if self.cur_group.value() != AlignGroup {
+            self.confusion("align0");
+        }
+        self.unsave(); // that align_group was for the whole alignment
+        let o = if self.nest[self.nest_ptr - 1].mode_field == Mmode {
+            self.dimen_par(DisplayIndentCode)
+        } else {
+            0
+        }; // shift offset for unset boxes
+        // 801: go through the preamble list, determining the column widths and
+        // changing the alignrecords to dummy unset boxes
+        let mut q = self.link(self.preamble() as usize) as usize;
+        loop {
+            self.flush_list(self.u_part(q));
+            self.flush_list(self.v_part(q));
+            let p = self.link(self.link(q) as usize) as usize;
+            if self.width(q) == NullFlag {
+                // 802: nullify width(q) and the tabskip glue following this column
+                self.set_width(q, 0);
+                let r = self.link(q) as usize;
+                let s = self.glue_ptr(r);
+                if s != ZeroGlue as Pointer {
+                    self.add_glue_ref(ZeroGlue as Pointer);
+                    self.delete_glue_ref(s);
+                    self.set_glue_ptr(r, ZeroGlue as Pointer);
+                }
+            }
+            if self.info(q) != EndSpan as HalfWord {
+                // 803: merge the widths in the span nodes of q with those of p,
+                // destroying the span nodes of q
+                let t = self.width(q) + self.width(self.glue_ptr(self.link(q) as usize) as usize);
+                let mut r = self.info(q) as usize;
+                let mut s = EndSpan;
+                self.set_info(s, p as HalfWord);
+                let mut n = MinQuarterWord as HalfWord + 1;
+                loop {
+                    self.set_width(r, self.width(r) - t);
+                    let u = self.info(r) as usize;
+                    while self.link(r) > n {
+                        s = self.info(s) as usize;
+                        n = self.link(self.info(s) as usize) + 1;
+                    }
+                    if self.link(r) < n {
+                        self.set_info(r, self.info(s));
+                        self.set_info(s, r as HalfWord);
+                        self.set_link(r, self.link(r) - 1);
+                        s = r;
+                    } else {
+                        let i = self.info(s) as usize;
+                        if self.width(r) > self.width(i) {
+                            self.set_width(i, self.width(r));
+                        }
+                        self.free_node(r as Pointer, SpanNodeSize);
+                    }
+                    r = u;
+                    if r == EndSpan {
+                        break;
+                    }
+                }
+            }
+            self.set_type(q, UnsetNode);
+            self.set_span_count(q, MinQuarterWord);
+            self.set_height(q, 0);
+            self.set_depth(q, 0);
+            self.set_glue_order(q, Normal as u8);
+            self.set_glue_sign(q, Normal as u8);
+            self.set_glue_stretch(q, 0);
+            self.set_glue_shrink(q, 0);
+            q = p;
+            if q == Null as usize {
+                break;
+            }
+        }
+        // 804: package the preamble list, to determine the actual tabskip glue
+        // amounts, and let p point to this prototype box
+        self.save_ptr -= 2;
+        self.pack_begin_line = -self.mode_line();
+        let p = if self.mode() == -Vmode {
+            let rule_save = self.dimen_par(OverfullRuleCode);
+            self.set_dimen_par(OverfullRuleCode, 0); // prevent rule from being packaged
+            let p = self.hpack(self.preamble(), self.saved(1), self.saved(0) as QuarterWord);
+            self.set_dimen_par(OverfullRuleCode, rule_save);
+            p
+        } else {
+            let mut q = self.link(self.preamble() as usize);
+            while q != Null {
+                self.set_height(q as usize, self.width(q as usize));
+                self.set_width(q as usize, 0);
+                q = self.link(self.link(q as usize) as usize);
+            }
+            let p = self.vpack(self.preamble(), self.saved(1), self.saved(0) as QuarterWord);
+            let mut q = self.link(self.preamble() as usize);
+            while q != Null {
+                self.set_width(q as usize, self.height(q as usize));
+                self.set_height(q as usize, 0);
+                q = self.link(self.link(q as usize) as usize);
+            }
+            p
+        };
+        self.pack_begin_line = 0;
+        // 805: set the glue in all the unset boxes of the current list
+        let mut q = self.link(self.head() as usize);
+        let mut s = self.head();
+        while q != Null {
+            if !self.is_char_node(q as usize) {
+                if self.a_type(q as usize) == UnsetNode {
+                    self.set_unset_row(q as usize, p as usize, o);
+                } else if self.a_type(q as usize) == RuleNode {
+                    // 806: make the running dimensions in rule q extend to the
+                    // boundaries of the alignment
+                    let r = q as usize;
+                    if TexState::is_running(self.width(r)) {
+                        self.set_width(r, self.width(p as usize));
+                    }
+                    if TexState::is_running(self.height(r)) {
+                        self.set_height(r, self.height(p as usize));
+                    }
+                    if TexState::is_running(self.depth(r)) {
+                        self.set_depth(r, self.depth(p as usize));
+                    }
+                    if o != 0 {
+                        let r = self.link(q as usize);
+                        self.set_link(q as usize, Null);
+                        q = self.hpack(q, 0, Additional);
+                        self.set_shift_amount(q as usize, o);
+                        self.set_link(q as usize, r);
+                        self.set_link(s as usize, q);
+                    }
+                }
+            }
+            s = q;
+            q = self.link(q as usize);
+        }
+        self.flush_node_list(p);
+        self.pop_alignment();
+        // 812: insert the current list into its environment
+        let aux_save = self.curlist.aux_field;
+        let p = self.link(self.head() as usize);
+        let q = self.tail();
+        self.pop_nest();
+        if self.mode() == Mmode {
+            self.fin_display_alignment(p, q, aux_save);
+        } else {
+            self.curlist.aux_field = aux_save;
+            self.set_link(self.tail() as usize, p);
+            if p != Null {
+                self.set_tail(q);
+            }
+            if self.mode() == Vmode {
+                self.build_page();
+            }
+        }
+    }
+
+    // 807
+    // sets the unset row q to the widths of the prototype box p, shifted right by o
+    fn set_unset_row(&mut self, q: usize, p: usize, o: Scaled) {
+        if self.mode() == -Vmode {
+            self.set_type(q, HlistNode);
+            self.set_width(q, self.width(p));
+        } else {
+            self.set_type(q, VlistNode);
+            self.set_height(q, self.height(p));
+        }
+        self.set_glue_order(q, self.glue_order(p));
+        self.set_glue_sign(q, self.glue_sign(p));
+        self.set_glue_set(q, self.glue_set(p));
+        self.set_shift_amount(q, o);
+        let mut r = self.link(self.list_ptr(q) as usize) as usize;
+        let mut s = self.link(self.list_ptr(p) as usize) as usize;
+        loop {
+            // 808: set the glue in node r and change it from an unset node
+            let mut n = self.span_count(r);
+            let mut t = self.width(s);
+            let w = t;
+            let mut u = HoldHead;
+            while n > MinQuarterWord {
+                n -= 1;
+                // 809: append tabskip glue and an empty box to list u, and update
+                // s and t as the prototype nodes are passed
+                s = self.link(s) as usize;
+                let v = self.glue_ptr(s);
+                let g = self.new_glue(v);
+                self.set_link(u, g);
+                u = g as usize;
+                self.set_subtype(u, (TabSkipCode + 1) as QuarterWord);
+                let v = v as usize;
+                t += self.width(v);
+                if self.glue_sign(p) as i32 == Stretching && self.stretch_order(v) == self.glue_order(p) {
+                    t += self.glue_set(p).scale(self.stretch(v));
+                } else if self.glue_sign(p) as i32 == Shrinking && self.shrink_order(v) == self.glue_order(p) {
+                    t -= self.glue_set(p).scale(self.shrink(v));
+                }
+                s = self.link(s) as usize;
+                let b = self.new_null_box();
+                self.set_link(u, b);
+                u = b as usize;
+                t += self.width(s);
+                if self.mode() == -Vmode {
+                    self.set_width(u, self.width(s));
+                } else {
+                    self.set_type(u, VlistNode);
+                    self.set_height(u, self.width(s));
+                }
+            }
+            if self.mode() == -Vmode {
+                // 810: make the unset node r into an hlist_node of width w,
+                // setting the glue as if the width were t
+                self.set_height(r, self.height(q));
+                self.set_depth(r, self.depth(q));
+                let x = self.width(r);
+                self.set_unset_glue(r, t, x);
+                self.set_width(r, w);
+                self.set_type(r, HlistNode);
+            } else {
+                // 811: make the unset node r into a vlist_node of height w,
+                // setting the glue as if the height were t
+                self.set_width(r, self.width(q));
+                let x = self.height(r);
+                self.set_unset_glue(r, t, x);
+                self.set_height(r, w);
+                self.set_type(r, VlistNode);
+            }
+            self.set_shift_amount(r, 0);
+            if u != HoldHead {
+                // append blank boxes to account for spanned nodes
+                self.set_link(u, self.link(r));
+                self.set_link(r, self.link(HoldHead));
+                r = u;
+            }
+            r = self.link(self.link(r) as usize) as usize;
+            s = self.link(self.link(s) as usize) as usize;
+            if r == Null as usize {
+                break;
+            }
+        }
+    }
+
+    // 810
+    // sets the glue of the unset node r, whose natural size is x, as if its size were t
+    fn set_unset_glue(&mut self, r: usize, t: Scaled, x: Scaled) {
+        if t == x {
+            self.set_glue_sign(r, Normal as u8);
+            self.set_glue_order(r, Normal as u8);
+            self.set_glue_set(r, GlueRatio::ZERO);
+        } else if t > x {
+            self.set_glue_sign(r, Stretching as u8);
+            if self.glue_stretch(r) == 0 {
+                self.set_glue_set(r, GlueRatio::ZERO);
+            } else {
+                self.set_glue_set(r, GlueRatio::new(t - x, self.glue_stretch(r)));
+            }
+        } else {
+            self.set_glue_order(r, self.glue_sign(r));
+            self.set_glue_sign(r, Shrinking as u8);
+            if self.glue_shrink(r) == 0 {
+                self.set_glue_set(r, GlueRatio::ZERO);
+            } else if self.glue_order(r) == Normal as u8 && x - t > self.glue_shrink(r) {
+                self.set_glue_set(r, GlueRatio::ONE);
+            } else {
+                self.set_glue_set(r, GlueRatio::new(x - t, self.glue_shrink(r)));
+            }
+        }
+    }
+
+    // 1206
+    // puts the alignment p..q, which was all there was between $$'s, into the
+    // enclosing vertical list with the spacing of a display
+    fn fin_display_alignment(&mut self, p: Pointer, q: Pointer, aux_save: MemoryWord) {
+        self.do_assignments();
+        if self.cur_cmd != MathShift {
+            // 1207: pontificate about improper alignment in display
+            self.print_err("Missing $$ inserted");
+            self.help(&["Displays can use special alignments (like \\eqalignno)",
+                "only if nothing but the alignment itself is between $$'s."]);
+            self.back_error();
+        } else {
+            self.check_that_another_dollar_follows();
+        }
+        self.pop_nest();
+        let g = self.new_penalty(self.int_par(PreDisplayPenaltyCode));
+        self.tail_append(g);
+        let g = self.new_param_glue(AboveDisplaySkipCode);
+        self.tail_append(g);
+        self.set_link(self.tail() as usize, p);
+        if p != Null {
+            self.set_tail(q);
+        }
+        let g = self.new_penalty(self.int_par(PostDisplayPenaltyCode));
+        self.tail_append(g);
+        let g = self.new_param_glue(BelowDisplaySkipCode);
+        self.tail_append(g);
+        self.set_prev_depth(aux_save.sc());
+        self.resume_after_display();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::text_font;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn spanned_and_omitted_templates() {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\catcode`\\#=6 \\catcode`\\&=4
+\\font\\rm=text \\rm \\showboxdepth=100 \\showboxbreadth=100 \\scrollmode \\tabskip=1pt
+\\setbox0\\vbox{\\halign{(#)&#.\\cr a&bb\\cr \\omit c&\\omit d\\cr aaa\\span b\\cr}}\\showbox0
+\\end
+");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        let start = log.find("> \\box0=").expect(&log);
+        // the spanned entry of the last row is as wide as the first column and
+        // is followed by an empty box for the second, but it makes the second
+        // column wide enough for both
+        assert_eq!(log[start..].split("\n\n").next().unwrap(), concat!(
+            "> \\box0=\n",
+            "\\vbox(20.99995+0.0)x29.5\n",
+            ".\\hbox(6.99998+0.0)x29.5\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            "..\\hbox(6.99998+0.0)x10.0\n",
+            "...\\rm (\n",
+            "...\\rm a\n",
+            "...\\rm )\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            "..\\hbox(6.99998+0.0)x16.5\n",
+            "...\\rm b\n",
+            "...\\rm b\n",
+            "...\\rm .\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            ".\\glue(\\lineskip) 0.0\n",
+            ".\\hbox(6.99998+0.0)x29.5\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            "..\\hbox(6.99998+0.0)x10.0\n",
+            "...\\rm c\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            "..\\hbox(6.99998+0.0)x16.5\n",
+            "...\\rm d\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            ".\\glue(\\lineskip) 0.0\n",
+            ".\\hbox(6.99998+0.0)x29.5\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            "..\\hbox(6.99998+0.0)x10.0\n",
+            "...\\rm (\n",
+            "...\\rm a\n",
+            "...\\rm a\n",
+            "...\\rm a\n",
+            "...\\rm )\n",
+            "...\\rm b\n",
+            "...\\rm .\n",
+            "..\\glue(\\tabskip) 1.0\n",
+            "..\\hbox(0.0+0.0)x16.5\n",
+            "..\\glue(\\tabskip) 1.0",
+        ));
+    }
+}
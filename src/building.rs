@@ -0,0 +1,942 @@
+use crate::*;
+
+// 1058
+pub const FilCode: HalfWord = 0; // identifies \hfil and \vfil
+pub const FillCode: HalfWord = 1; // identifies \hfill and \vfill
+pub const SsCode: HalfWord = 2; // identifies \hss and \vss
+pub const FilNegCode: HalfWord = 3; // identifies \hfilneg and \vfilneg
+pub const SkipCode: HalfWord = 4; // identifies \hskip and \vskip
+pub const MskipCode: HalfWord = 5; // identifies \mskip
+
+// 1071
+pub const BoxFlag: i32 = 0o10000000000; // context code for `\setbox0'
+pub const GlobalBoxFlag: i32 = BoxFlag + 256; // context code for `\global\setbox0'
+pub const ShipOutFlag: i32 = BoxFlag + 512; // context code for `\shipout'
+pub const LeaderFlag: i32 = BoxFlag + 513; // context code for `\leaders'
+pub const BoxCode: HalfWord = 0; // chr_code for `\box'
+pub const CopyCode: HalfWord = 1; // chr_code for `\copy'
+pub const LastBoxCode: HalfWord = 2; // chr_code for `\lastbox'
+pub const VsplitCode: HalfWord = 3; // chr_code for `\vsplit'
+pub const VtopCode: HalfWord = 4; // chr_code for `\vtop'
+
+impl TexState {
+    // 1060
+    // appends the glue of \hskip, \vfil and their relatives
+    pub fn append_glue(&mut self) {
+        let s = self.cur_chr; // modifier of skip command
+        match s {
+            FilCode => self.cur_val = FilGlue as i32,
+            FillCode => self.cur_val = FillGlue as i32,
+            SsCode => self.cur_val = SsGlue as i32,
+            FilNegCode => self.cur_val = FilNegGlue as i32,
+            SkipCode => self.scan_glue(GlueVal),
+            MskipCode => self.scan_glue(MuVal),
+            _ => {}
+        } // now cur_val points to the glue specification
+        let g = self.new_glue(self.cur_val as Pointer);
+        self.tail_append(g);
+        if s >= SkipCode {
+            let v = self.cur_val as usize;
+            self.set_glue_ref_count(v, self.glue_ref_count(v) - 1);
+            if s > SkipCode {
+                self.set_subtype(self.tail() as usize, MuGlue);
+            }
+        }
+    }
+
+    // 1061
+    // appends the kern of \kern or \mkern
+    pub fn append_kern(&mut self) {
+        let s = self.cur_chr as QuarterWord; // subtype of the kern node
+        self.scan_dimen(s == MuGlue, false, false);
+        let k = self.new_kern(self.cur_val);
+        self.tail_append(k);
+        self.set_subtype(self.tail() as usize, s);
+    }
+
+    // 1064
+    // recovers from a group that is ended by the wrong kind of token
+    pub fn off_save(&mut self) {
+        if self.cur_group.value() == BottomLevel {
+            // 1066: drop current token and complain that it was unmatched
+            self.print_err("Extra ");
+            self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+            self.help(&["Things are pretty mixed up, but I think the worst is over."]);
+            self.error();
+        } else {
+            self.back_input();
+            let mut p = self.get_avail(); // inserted token
+            self.set_link(TempHead, p);
+            self.print_err("Missing ");
+            // 1065: prepare to insert a token that matches cur_group, and print what it is
+            match self.cur_group.value() {
+                SemiSimpleGroup => {
+                    self.set_info(p as usize, (cs_token_flag + FrozenEndGroup as i32) as HalfWord);
+                    self.print_esc("endgroup");
+                }
+                MathShiftGroup => {
+                    self.set_info(p as usize, (math_shift_token + '$' as i32) as HalfWord);
+                    self.print_char('$' as u8);
+                }
+                MathLeftGroup => {
+                    self.set_info(p as usize, (cs_token_flag + FrozenRight as i32) as HalfWord);
+                    let q = self.get_avail();
+                    self.set_link(p as usize, q);
+                    p = q;
+                    self.set_info(p as usize, (other_token + '.' as i32) as HalfWord);
+                    self.print_esc("right.");
+                }
+                _ => {
+                    self.set_info(p as usize, (right_brace_token + '}' as i32) as HalfWord);
+                    self.print_char('}' as u8);
+                }
+            }
+            self.print(" inserted");
+            self.ins_list(self.link(TempHead));
+            self.help(&["I've inserted something that you may have forgotten. (See the",
+                "<inserted text> above.)",
+                "With luck, this will get me unwedged. But if you",
+                "really didn't forget anything, try typing `2' now; then",
+                "my insertion and my current dilemma will both disappear."]);
+            self.error();
+        }
+    }
+
+    // 1068
+    // finishes the group that a right brace closes
+    pub fn handle_right_brace(&mut self) {
+        match self.cur_group.value() {
+            SimpleGroup => self.unsave(),
+            BottomLevel => {
+                self.print_err("Too many }'s");
+                self.help(&["You've closed more groups than you opened.",
+                    "Such booboos are generally harmless, so keep going."]);
+                self.error();
+            }
+            SemiSimpleGroup | MathShiftGroup | MathLeftGroup => self.extra_right_brace(),
+            // 1085: cases of handle_right_brace where a right_brace triggers a delayed action
+            HboxGroup => self.package(0),
+            AdjustedHboxGroup => {
+                self.adjust_tail = AdjustHead as Pointer;
+                self.package(0);
+            }
+            VboxGroup => {
+                self.end_graf();
+                self.package(0);
+            }
+            VtopGroup => {
+                self.end_graf();
+                self.package(VtopCode as SmallNumber);
+            }
+            // 1100
+            InsertGroup => {
+                self.end_graf();
+                let q = self.glue_par(SplitTopSkipCode);
+                self.add_glue_ref(q);
+                let d = self.dimen_par(SplitMaxDepthCode);
+                let f = self.int_par(FloatingPenaltyCode);
+                self.unsave();
+                self.save_ptr -= 1; // now saved(0) is the insertion number, or 255 for \vadjust
+                let p = self.vpack(self.link(self.head() as usize), 0, Additional);
+                self.pop_nest();
+                if self.saved(0) < 255 {
+                    let r = self.get_node(InsNodeSize);
+                    self.tail_append(r);
+                    let t = self.tail() as usize;
+                    self.set_type(t, InsNode);
+                    self.set_subtype(t, self.saved(0) as QuarterWord);
+                    self.set_height(t, self.height(p as usize) + self.depth(p as usize));
+                    self.set_ins_ptr(t, self.list_ptr(p as usize));
+                    self.set_split_top_ptr(t, q);
+                    self.set_depth(t, d);
+                    self.set_float_cost(t, f);
+                } else {
+                    let r = self.get_node(SmallNodeSize);
+                    self.tail_append(r);
+                    let t = self.tail() as usize;
+                    self.set_type(t, AdjustNode);
+                    self.set_subtype(t, 0); // the subtype is not used
+                    self.set_adjust_ptr(t, self.list_ptr(p as usize));
+                    self.delete_glue_ref(q);
+                }
+                self.free_node(p, BoxNodeSize);
+                if self.nest_ptr == 0 {
+                    self.build_page();
+                }
+            }
+            OutputGroup => self.resume_page_builder(),
+            // 1118
+            DiscGroup => self.build_discretionary(),
+            // 1132
+            AlignGroup => {
+                self.back_input();
+                self.cur_tok = (cs_token_flag + FrozenCr as i32) as HalfWord;
+                self.print_err("Missing ");
+                self.print_esc("cr");
+                self.print(" inserted");
+                self.help(&["I'm guessing that you meant to end an alignment here."]);
+                self.ins_error();
+            }
+            // 1133
+            NoAlignGroup => {
+                self.end_graf();
+                self.unsave();
+                self.align_peek();
+            }
+            // 1168
+            VcenterGroup => {
+                self.end_graf();
+                self.unsave();
+                self.save_ptr -= 2;
+                let p = self.vpack(self.link(self.head() as usize), self.saved(1), self.saved(0) as QuarterWord);
+                self.pop_nest();
+                let n = self.new_noad();
+                self.tail_append(n);
+                self.set_type(self.tail() as usize, VcenterNoad);
+                self.set_math_type(TexState::nucleus(self.tail() as usize), SubBox);
+                self.set_info(TexState::nucleus(self.tail() as usize), p);
+            }
+            // 1173
+            MathChoiceGroup => self.build_choices(),
+            // 1186
+            MathGroup => self.finish_math_group(),
+            _ => self.confusion("rightbrace"),
+        }
+    }
+
+    // 1069
+    // complains about a right brace that closes the wrong kind of group
+    pub fn extra_right_brace(&mut self) {
+        self.print_err("Extra }, or forgotten ");
+        match self.cur_group.value() {
+            SemiSimpleGroup => self.print_esc("endgroup"),
+            MathShiftGroup => self.print_char('$' as u8),
+            MathLeftGroup => self.print_esc("right"),
+            _ => {}
+        }
+        self.help(&["I've deleted a group-closing symbol because it seems to be",
+            "spurious, as in `$x}$'. But perhaps the } is legitimate and",
+            "you forgot something else, as in `\\hbox{$x}'. In such cases",
+            "the way to recover is to insert both the forgotten and the",
+            "deleted material, e.g., by typing `I$}'."]);
+        self.error();
+        self.align_state += 1;
+    }
+
+    // 1026
+    // resumes the page builder after an output routine has come to an end
+    pub fn resume_page_builder(&mut self) {
+        if self.cur_input.loc_field != Null
+            || (self.token_type() != OutputText && self.token_type() != BackedUp)
+        {
+            // 1027: recover from an unbalanced output routine
+            self.print_err("Unbalanced output routine");
+            self.help(&["Your sneaky output routine has problematic {'s and/or }'s.",
+                "I can't handle that very well; good luck."]);
+            self.error();
+            loop {
+                self.get_token();
+                if self.cur_input.loc_field == Null {
+                    break;
+                }
+            } // loops forever if reading from a file, since null=min_halfword<=0
+        }
+        self.end_token_list(); // conserve stack space in case more outputs are triggered
+        self.end_graf();
+        self.unsave();
+        self.output_active = false;
+        self.insert_penalties = 0;
+        // 1028: ensure that box 255 is empty after output
+        if self.box_reg(255) != Null {
+            self.print_err("Output routine didn't use all of ");
+            self.print_esc("box");
+            self.print_int(255);
+            self.help(&["Your \\output commands should empty \\box255,",
+                "e.g., by saying `\\shipout\\box255'.",
+                "Proceed; I'll discard its present contents."]);
+            self.box_error(255);
+        }
+        if self.tail() != self.head() {
+            // current list goes after heldover insertions
+            self.set_link(self.page_tail as usize, self.link(self.head() as usize));
+            self.page_tail = self.tail();
+        }
+        if self.link(PageHead) != Null {
+            // and both go before heldover contributions
+            if self.link(ContribHead) == Null {
+                self.set_contrib_tail(self.page_tail);
+            }
+            self.set_link(self.page_tail as usize, self.link(ContribHead));
+            self.set_link(ContribHead, self.link(PageHead));
+            self.set_link(PageHead, Null);
+            self.page_tail = PageHead as Pointer;
+        }
+        self.pop_nest();
+        self.build_page();
+    }
+
+    // 1075
+    // disposes of cur_box as box_context says: appends it to the current list,
+    // puts it in a box register, ships it out, or makes leaders of it
+    pub fn box_end(&mut self, box_context: i32) {
+        if box_context < BoxFlag {
+            // 1076: append box cur_box to the current list, shifted by box_context
+            if self.cur_box != Null {
+                self.set_shift_amount(self.cur_box as usize, box_context);
+                if self.mode().abs() == Vmode {
+                    self.append_to_vlist(self.cur_box);
+                    if self.adjust_tail != Null {
+                        if AdjustHead as Pointer != self.adjust_tail {
+                            self.set_link(self.tail() as usize, self.link(AdjustHead));
+                            self.set_tail(self.adjust_tail);
+                        }
+                        self.adjust_tail = Null;
+                    }
+                    if self.mode() > 0 {
+                        self.build_page();
+                    }
+                } else {
+                    if self.mode().abs() == Hmode {
+                        self.set_space_factor(1000);
+                    } else {
+                        let p = self.new_noad();
+                        self.set_math_type(TexState::nucleus(p as usize), SubBox);
+                        self.set_info(TexState::nucleus(p as usize), self.cur_box);
+                        self.cur_box = p;
+                    }
+                    self.tail_append(self.cur_box);
+                }
+            }
+        } else if box_context < ShipOutFlag {
+            // 1077: store cur_box in a box register
+            if box_context < GlobalBoxFlag {
+                self.eq_define((BoxBase as i32 - BoxFlag + box_context) as Pointer, BoxRef, self.cur_box);
+            } else {
+                self.geq_define((BoxBase as i32 - GlobalBoxFlag + box_context) as Pointer, BoxRef, self.cur_box);
+            }
+        } else if self.cur_box != Null {
+            if box_context > ShipOutFlag {
+                // 1078: append a new leader node that uses cur_box
+                self.get_next_non_blank_non_relax();
+                if (self.cur_cmd == Hskip && self.mode().abs() != Vmode)
+                    || (self.cur_cmd == Vskip && self.mode().abs() == Vmode)
+                {
+                    self.append_glue();
+                    self.set_subtype(self.tail() as usize, (box_context - (LeaderFlag - ALeaders as i32)) as QuarterWord);
+                    self.set_leader_ptr(self.tail() as usize, self.cur_box);
+                } else {
+                    self.print_err("Leaders not followed by proper glue");
+                    self.help(&["You should say `\\leaders <box or rule><hskip or vskip>'.",
+                        "I found the <box or rule>, but there's no suitable",
+                        "<hskip or vskip>, so I'm ignoring these leaders."]);
+                    self.back_error();
+                    self.flush_node_list(self.cur_box);
+                }
+            } else {
+                self.ship_out(self.cur_box);
+            }
+        }
+    }
+
+    // 404
+    // get the next non-blank non-relax non-call token
+    pub fn get_next_non_blank_non_relax(&mut self) {
+        loop {
+            self.get_x_token();
+            if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                break;
+            }
+        }
+    }
+
+    // 1079
+    // starts the box that cur_chr names, for the given context
+    pub fn begin_box(&mut self, box_context: i32) {
+        match self.cur_chr {
+            BoxCode => {
+                self.scan_eight_bit_int();
+                self.cur_box = self.box_reg(self.cur_val as HalfWord);
+                self.set_box_reg(self.cur_val as HalfWord, Null); // the box becomes void, at the same level
+            }
+            CopyCode => {
+                self.scan_eight_bit_int();
+                self.cur_box = self.copy_node_list(self.box_reg(self.cur_val as HalfWord));
+            }
+            LastBoxCode => {
+                // 1080: if the current list ends with a box node, delete it from
+                // the list and make cur_box point to it; otherwise set cur_box:=null
+                self.cur_box = Null;
+                if self.mode().abs() == Mmode {
+                    self.you_cant();
+                    self.help(&["Sorry; this \\lastbox will be void."]);
+                    self.error();
+                } else if self.mode() == Vmode && self.head() == self.tail() {
+                    self.you_cant();
+                    self.help(&["Sorry...I usually can't take things from the current page.",
+                        "This \\lastbox will therefore be void."]);
+                    self.error();
+                } else if !self.is_char_node(self.tail() as usize)
+                    && (self.a_type(self.tail() as usize) == HlistNode || self.a_type(self.tail() as usize) == VlistNode)
+                {
+                    // 1081: remove the last box, unless it's part of a discretionary
+                    if let Some(p) = self.node_before_tail() {
+                        self.cur_box = self.tail();
+                        self.set_shift_amount(self.cur_box as usize, 0);
+                        self.set_tail(p);
+                        self.set_link(p as usize, Null);
+                    }
+                }
+            }
+            VsplitCode => {
+                // 1082: split off part of a vertical box, make cur_box point to it
+                self.scan_eight_bit_int();
+                let n = self.cur_val as HalfWord;
+                if !self.scan_keyword("to") {
+                    self.print_err("Missing `to' inserted");
+                    self.help(&["I'm working on `\\vsplit<box number> to <dimen>';",
+                        "will look for the <dimen> next."]);
+                    self.error();
+                }
+                self.scan_normal_dimen();
+                self.cur_box = self.vsplit(n, self.cur_val);
+            }
+            _ => {
+                // 1083: initiate the construction of an hbox or vbox, then return
+                let mut k = self.cur_chr as i32 - VtopCode as i32; // 0 or vmode or hmode
+                self.set_saved(0, box_context);
+                if k == Hmode {
+                    if box_context < BoxFlag && self.mode().abs() == Vmode {
+                        self.scan_spec(AdjustedHboxGroup, true);
+                    } else {
+                        self.scan_spec(HboxGroup, true);
+                    }
+                } else {
+                    if k == Vmode {
+                        self.scan_spec(VboxGroup, true);
+                    } else {
+                        self.scan_spec(VtopGroup, true);
+                        k = Vmode;
+                    }
+                    self.normal_paragraph();
+                }
+                self.push_nest();
+                self.set_mode(-k);
+                if k == Vmode {
+                    self.set_prev_depth(IgnoreDepth);
+                    if self.equiv(EveryVboxLoc as usize) != Null {
+                        self.begin_token_list(self.equiv(EveryVboxLoc as usize), EveryVboxText);
+                    }
+                } else {
+                    self.set_space_factor(1000);
+                    if self.equiv(EveryHboxLoc as usize) != Null {
+                        self.begin_token_list(self.equiv(EveryHboxLoc as usize), EveryHboxText);
+                    }
+                }
+                return;
+            }
+        }
+        self.box_end(box_context); // in simple cases, we use the box immediately
+    }
+
+    // 1081
+    // the node before the tail of the current list, or None if the tail is part
+    // of a discretionary's replacement text
+    pub fn node_before_tail(&mut self) -> Option<Pointer> {
+        let mut q = self.head();
+        let mut p;
+        loop {
+            p = q;
+            if !self.is_char_node(q as usize) && self.a_type(q as usize) == DiscNode {
+                for _ in 1..=self.replace_count(q as usize) {
+                    p = self.link(p as usize);
+                }
+                if p == self.tail() {
+                    return None;
+                }
+            }
+            q = self.link(p as usize);
+            if q == self.tail() {
+                return Some(p);
+            }
+        }
+    }
+
+    // 1084
+    // the next input should specify a box or perhaps a rule
+    pub fn scan_box(&mut self, box_context: i32) {
+        self.get_next_non_blank_non_relax();
+        if self.cur_cmd == MakeBox {
+            self.begin_box(box_context);
+        } else if box_context >= LeaderFlag && (self.cur_cmd == Hrule || self.cur_cmd == Vrule) {
+            self.cur_box = self.scan_rule_spec();
+            self.box_end(box_context);
+        } else {
+            self.print_err("A <box> was supposed to be here");
+            self.help(&["I was expecting to see \\hbox or \\vbox or \\copy or \\box or",
+                "something like that. So you might find something missing in",
+                "your output. But keep trying; you can fix this later."]);
+            self.back_error();
+        }
+    }
+
+    // 1086
+    // finishes the hbox, vbox or vtop whose group has just ended
+    pub fn package(&mut self, c: SmallNumber) {
+        let d = self.dimen_par(BoxMaxDepthCode); // max depth plus box depth
+        self.unsave();
+        self.save_ptr -= 3;
+        if self.mode() == -Hmode {
+            self.cur_box = self.hpack(self.link(self.head() as usize), self.saved(2), self.saved(1) as QuarterWord);
+        } else {
+            self.cur_box = self.vpackage(self.link(self.head() as usize), self.saved(2), self.saved(1) as QuarterWord, d);
+            if c == VtopCode as SmallNumber {
+                // 1087: readjust the height and depth of cur_box, for \vtop
+                let mut h = 0; // height of box
+                let p = self.list_ptr(self.cur_box as usize); // first node in a box
+                if p != Null && self.a_type(p as usize) <= RuleNode {
+                    h = self.height(p as usize);
+                }
+                let b = self.cur_box as usize;
+                self.set_depth(b, self.depth(b) - h + self.height(b));
+                self.set_height(b, h);
+            }
+        }
+        self.pop_nest();
+        self.box_end(self.saved(0));
+    }
+
+    // 1091
+    // starts a paragraph, with or without an indentation box
+    pub fn new_graf(&mut self, indented: bool) {
+        self.set_prev_graf(0);
+        if self.mode() == Vmode || self.head() != self.tail() {
+            let g = self.new_param_glue(ParSkipCode);
+            self.tail_append(g);
+        }
+        self.push_nest();
+        self.set_mode(Hmode);
+        self.set_space_factor(1000);
+        self.set_cur_lang();
+        self.set_clang(self.cur_lang as HalfWord);
+        self.set_prev_graf((norm_min(self.int_par(LeftHyphenMinCode)) * 0o100
+            + norm_min(self.int_par(RightHyphenMinCode))) * 0o200000 + self.cur_lang as i32);
+        if indented {
+            let b = self.new_null_box();
+            self.set_tail(b);
+            self.set_link(self.head() as usize, b);
+            self.set_width(b as usize, self.par_indent());
+        }
+        if self.equiv(EveryParLoc as usize) != Null {
+            self.begin_token_list(self.equiv(EveryParLoc as usize), EveryParText);
+        }
+        if self.nest_ptr == 1 {
+            self.build_page(); // put par_skip glue on current page
+        }
+    }
+
+    // 1093
+    // \indent or \noindent in horizontal or math mode
+    pub fn indent_in_hmode(&mut self) {
+        if self.cur_chr > 0 {
+            // \indent
+            let mut p = self.new_null_box();
+            self.set_width(p as usize, self.par_indent());
+            if self.mode().abs() == Hmode {
+                self.set_space_factor(1000);
+            } else {
+                let q = self.new_noad();
+                self.set_math_type(TexState::nucleus(q as usize), SubBox);
+                self.set_info(TexState::nucleus(q as usize), p);
+                p = q;
+            }
+            self.tail_append(p);
+        }
+    }
+
+    // 1095
+    // ends the paragraph before a command that belongs to vertical mode
+    pub fn head_for_vmode(&mut self) {
+        if self.mode() < 0 {
+            if self.cur_cmd != Hrule {
+                self.off_save();
+            } else {
+                self.print_err("You can't use `");
+                self.print_esc("hrule");
+                self.print("' here except with leaders");
+                self.help(&["To put a horizontal rule in an hbox or an alignment,",
+                    "you should use \\leaders or \\hrulefill (see The TeXbook)."]);
+                self.error();
+            }
+        } else {
+            self.back_input();
+            self.cur_tok = self.par_token;
+            self.back_input();
+            self.cur_input.index_field = Inserted;
+        }
+    }
+
+    // 1096
+    // breaks the paragraph being built, if any, into lines
+    pub fn end_graf(&mut self) {
+        if self.mode() == Hmode {
+            if self.head() == self.tail() {
+                self.pop_nest(); // null paragraphs are ignored
+            } else {
+                self.line_break(self.int_par(WidowPenaltyCode));
+            }
+            self.normal_paragraph();
+            self.error_count = 0;
+        }
+    }
+
+    // 1099
+    // starts the vlist of \insert or \vadjust
+    pub fn begin_insert_or_adjust(&mut self) {
+        if self.cur_cmd == Vadjust {
+            self.cur_val = 255;
+        } else {
+            self.scan_eight_bit_int();
+            if self.cur_val == 255 {
+                self.print_err("You can't ");
+                self.print_esc("insert");
+                self.print_int(255);
+                self.help(&["I'm changing to \\insert0; box 255 is special."]);
+                self.error();
+                self.cur_val = 0;
+            }
+        }
+        self.set_saved(0, self.cur_val);
+        self.save_ptr += 1;
+        self.new_save_level(InsertGroup);
+        self.scan_left_brace();
+        self.normal_paragraph();
+        self.push_nest();
+        self.set_mode(-Vmode);
+        self.set_prev_depth(IgnoreDepth);
+    }
+
+    // 1103
+    // \penalty
+    pub fn append_penalty(&mut self) {
+        self.scan_int();
+        let p = self.new_penalty(self.cur_val);
+        self.tail_append(p);
+        if self.mode() == Vmode {
+            self.build_page();
+        }
+    }
+
+    // 1105
+    // \unpenalty, \unkern and \unskip
+    pub fn delete_last(&mut self) {
+        if self.mode() == Vmode && self.tail() == self.head() {
+            // 1106: apologize for inability to do the operation now, unless
+            // \unskip follows non-glue
+            if self.cur_chr != GlueNode as HalfWord || self.last_glue != MaxHalfWord {
+                self.you_cant();
+                self.help(&["Sorry...I usually can't take things from the current page.",
+                    "Try `I\\vskip-\\lastskip' instead."]);
+                if self.cur_chr == KernNode as HalfWord {
+                    self.helpline[1] = "Try `I\\kern-\\lastkern' instead.";
+                } else if self.cur_chr != GlueNode as HalfWord {
+                    self.helpline[1] = "Perhaps you can make the output routine do it.";
+                }
+                self.error();
+            }
+        } else if !self.is_char_node(self.tail() as usize) && self.a_type(self.tail() as usize) as HalfWord == self.cur_chr {
+            if let Some(p) = self.node_before_tail() {
+                self.set_link(p as usize, Null);
+                self.flush_node_list(self.tail());
+                self.set_tail(p);
+            }
+        }
+    }
+
+    // 1110
+    // \unhbox, \unhcopy, \unvbox and \unvcopy
+    pub fn unpackage(&mut self) {
+        let c = self.cur_chr; // should we copy?
+        self.scan_eight_bit_int();
+        let p = self.box_reg(self.cur_val as HalfWord); // the box
+        if p == Null {
+            return;
+        }
+        if self.mode().abs() == Mmode
+            || (self.mode().abs() == Vmode && self.a_type(p as usize) != VlistNode)
+            || (self.mode().abs() == Hmode && self.a_type(p as usize) != HlistNode)
+        {
+            self.print_err("Incompatible list can't be unboxed");
+            self.help(&["Sorry, Pandora. (You sneaky devil.)",
+                "I refuse to unbox an \\hbox in vertical mode or vice versa.",
+                "And I can't open any boxes in math mode."]);
+            self.error();
+            return;
+        }
+        if c == CopyCode {
+            let q = self.copy_node_list(self.list_ptr(p as usize));
+            self.set_link(self.tail() as usize, q);
+        } else {
+            self.set_link(self.tail() as usize, self.list_ptr(p as usize));
+            self.set_box_reg(self.cur_val as HalfWord, Null);
+            self.free_node(p, BoxNodeSize);
+        }
+        while self.link(self.tail() as usize) != Null {
+            self.set_tail(self.link(self.tail() as usize));
+        }
+    }
+
+    // 1113
+    // \/ in horizontal mode
+    pub fn append_italic_correction(&mut self) {
+        if self.tail() != self.head() {
+            let t = self.tail() as usize;
+            let p = if self.is_char_node(t) {
+                t
+            } else if self.a_type(t) == LigatureNode {
+                TexState::lig_char(t)
+            } else {
+                return;
+            }; // char_node at the tail of the current list
+            let f = self.font(p) as InternalFontNumber; // the font in the char_node
+            let k = self.new_kern(self.char_italic(f, self.char_info(f, self.character(p))));
+            self.tail_append(k);
+            self.set_subtype(self.tail() as usize, Explicit);
+        }
+    }
+
+    // 1117
+    // \- and \discretionary
+    pub fn append_discretionary(&mut self) {
+        let d = self.new_disc();
+        self.tail_append(d);
+        if self.cur_chr == 1 {
+            let f = self.cur_font() as InternalFontNumber;
+            let c = self.hyphen_char[f]; // hyphen character
+            if (0..256).contains(&c) {
+                let p = self.new_character(f, c as u8);
+                self.set_pre_break(self.tail() as usize, p);
+            }
+        } else {
+            self.save_ptr += 1;
+            self.set_saved(-1, 0);
+            self.new_save_level(DiscGroup);
+            self.scan_left_brace();
+            self.push_nest();
+            self.set_mode(-Hmode);
+            self.set_space_factor(1000);
+        }
+    }
+
+    // 1119
+    // finishes one of the three lists of a \discretionary
+    pub fn build_discretionary(&mut self) {
+        self.unsave();
+        // 1121: prune the current list, if necessary, until it contains only
+        // char_node, kern_node, hlist_node, vlist_node, rule_node, and
+        // ligature_node items; set n to the length of the list, and set q to
+        // the list's tail
+        let mut q = self.head();
+        let mut p = self.link(q as usize);
+        let mut n = 0; // length of discretionary list
+        while p != Null {
+            let t = self.a_type(p as usize);
+            if !self.is_char_node(p as usize) && t > RuleNode && t != KernNode && t != LigatureNode {
+                self.print_err("Improper discretionary list");
+                self.help(&["Discretionary lists must contain only boxes and kerns."]);
+                self.error();
+                self.begin_diagnostic();
+                self.print_nl("The following discretionary sublist has been deleted:");
+                self.show_box(p);
+                self.end_diagnostic(true);
+                self.flush_node_list(p);
+                self.set_link(q as usize, Null);
+                break;
+            }
+            q = p;
+            p = self.link(q as usize);
+            n += 1;
+        }
+        let p = self.link(self.head() as usize);
+        self.pop_nest();
+        match self.saved(-1) {
+            0 => self.set_pre_break(self.tail() as usize, p),
+            1 => self.set_post_break(self.tail() as usize, p),
+            _ => {
+                // 1120: attach list p to the current list, and record its length;
+                // then finish up and return
+                if n > 0 && self.mode().abs() == Mmode {
+                    self.print_err("Illegal math ");
+                    self.print_esc("discretionary");
+                    self.help(&["Sorry: The third part of a discretionary break must be",
+                        "empty, in math formulas. I had to delete your third part."]);
+                    self.flush_node_list(p);
+                    n = 0;
+                    self.error();
+                } else {
+                    self.set_link(self.tail() as usize, p);
+                }
+                if n <= MaxQuarterWord as i32 {
+                    self.set_replace_count(self.tail() as usize, n as QuarterWord);
+                } else {
+                    self.print_err("Discretionary list is too long");
+                    self.help(&["Wow---I never thought anybody would tweak me here.",
+                        "You can't seriously need such a huge discretionary list?"]);
+                    self.error();
+                }
+                if n > 0 {
+                    self.set_tail(q);
+                }
+                self.save_ptr -= 1;
+                return;
+            }
+        }
+        self.set_saved(-1, self.saved(-1) + 1);
+        self.new_save_level(DiscGroup);
+        self.scan_left_brace();
+        self.push_nest();
+        self.set_mode(-Hmode);
+        self.set_space_factor(1000);
+    }
+
+    // 1123
+    // \accent in horizontal mode
+    pub fn make_accent(&mut self) {
+        self.scan_char_num();
+        let mut f = self.cur_font() as InternalFontNumber; // relevant font
+        let mut p = self.new_character(f, self.cur_val as u8); // character node
+        if p != Null {
+            let x = self.x_height(f);
+            let s = self.slant(f); // amount of slant
+            let a = self.char_width(f, self.char_info(f, self.character(p as usize)));
+            self.do_assignments();
+            // 1124: create a character node q for the next character, but set
+            // q:=null if problems arise
+            let mut q = Null;
+            f = self.cur_font() as InternalFontNumber;
+            if self.cur_cmd == Letter || self.cur_cmd == OtherChar || self.cur_cmd == CharGiven {
+                q = self.new_character(f, self.cur_chr as u8);
+            } else if self.cur_cmd == CharNum {
+                self.scan_char_num();
+                q = self.new_character(f, self.cur_val as u8);
+            } else {
+                self.back_input();
+            }
+            if q != Null {
+                // 1125: append the accent with appropriate kerns, then set p:=q
+                let t = self.slant(f);
+                let i = self.char_info(f, self.character(q as usize));
+                let w = self.char_width(f, i);
+                let h = self.char_height(f, i.height_depth());
+                if h != x {
+                    // the accent must be shifted up or down
+                    p = self.hpack(p, 0, Additional);
+                    self.set_shift_amount(p as usize, x - h);
+                }
+                let delta = accent_delta(w, a, h, x, s, t);
+                let r = self.new_kern(delta);
+                self.set_subtype(r as usize, AccKern);
+                self.set_link(self.tail() as usize, r);
+                self.set_link(r as usize, p);
+                let k = self.new_kern(-a - delta);
+                self.set_tail(k);
+                self.set_subtype(k as usize, AccKern);
+                self.set_link(p as usize, k);
+                p = q;
+            }
+            self.tail_append(p);
+            self.set_space_factor(1000);
+        }
+    }
+
+    // 1127
+    // an alignment delimiter out of place, or a brace that unbalances a column
+    pub fn align_error(&mut self) {
+        if self.align_state.abs() > 2 {
+            // 1128: express consternation over the fact that no alignment is in progress
+            self.print_err("Misplaced ");
+            self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+            if self.cur_tok as i32 == tab_token + '&' as i32 {
+                self.help(&["I can't figure out why you would want to use a tab mark",
+                    "here. If you just want an ampersand, the remedy is",
+                    "simple: Just type `I\\&' now. But if some right brace",
+                    "up above has ended a previous alignment prematurely,",
+                    "you're probably due for more error messages, and you",
+                    "might try typing `S' now just to see what is salvageable."]);
+            } else {
+                self.help(&["I can't figure out why you would want to use a tab mark",
+                    "or \\cr or \\span just now. If something like a right brace",
+                    "up above has ended a previous alignment prematurely,",
+                    "you're probably due for more error messages, and you",
+                    "might try typing `S' now just to see what is salvageable."]);
+            }
+            self.error();
+        } else {
+            self.back_input();
+            if self.align_state < 0 {
+                self.print_err("Missing { inserted");
+                self.align_state += 1;
+                self.cur_tok = (left_brace_token + '{' as i32) as HalfWord;
+            } else {
+                self.print_err("Missing } inserted");
+                self.align_state -= 1;
+                self.cur_tok = (right_brace_token + '}' as i32) as HalfWord;
+            }
+            self.help(&["I've put in what seems to be necessary to fix",
+                "the current column of the current alignment.",
+                "Try to go on, since this might almost work."]);
+            self.ins_error();
+        }
+    }
+
+    // 1129
+    pub fn no_align_error(&mut self) {
+        self.print_err("Misplaced ");
+        self.print_esc("noalign");
+        self.help(&["I expect to see \\noalign only after the \\cr of",
+            "an alignment. Proceed, and I'll ignore this case."]);
+        self.error();
+    }
+
+    pub fn omit_error(&mut self) {
+        self.print_err("Misplaced ");
+        self.print_esc("omit");
+        self.help(&["I expect to see \\omit only after tab marks or the \\cr of",
+            "an alignment. Proceed, and I'll ignore this case."]);
+        self.error();
+    }
+
+    // 1131
+    // the end of a <v_j> template
+    pub fn do_endv(&mut self) {
+        self.base_ptr = self.input_ptr;
+        self.input_stack[self.base_ptr] = self.cur_input;
+        while self.input_stack[self.base_ptr].index_field != VTemplate
+            && self.input_stack[self.base_ptr].loc_field == Null
+            && self.input_stack[self.base_ptr].state_field == TokenList
+        {
+            self.base_ptr -= 1;
+        }
+        if self.input_stack[self.base_ptr].index_field != VTemplate
+            || self.input_stack[self.base_ptr].loc_field != Null
+            || self.input_stack[self.base_ptr].state_field != TokenList
+        {
+            self.fatal_error("(interwoven alignment preambles are not allowed)");
+        }
+        if self.cur_group.value() == AlignGroup {
+            self.end_graf();
+            if self.fin_col() {
+                self.fin_row();
+            }
+        } else {
+            self.off_save();
+        }
+    }
+
+    // 1135
+    pub fn cs_error(&mut self) {
+        self.print_err("Extra ");
+        self.print_esc("endcsname");
+        self.help(&["I'm ignoring this, since I wasn't doing a \\csname."]);
+        self.error();
+    }
+}
@@ -0,0 +1,660 @@
+use crate::*;
+
+// 207
+pub const Relax: QuarterWord = 0; // do nothing ( \relax )
+pub const Escape: QuarterWord = 0; // escape delimiter ( \ )
+pub const LeftBrace: QuarterWord = 1; // beginning of a group ( { )
+pub const RightBrace: QuarterWord = 2; // ending of a group ( } )
+pub const MathShift: QuarterWord = 3; // mathematics shift character ( $ )
+pub const TabMark: QuarterWord = 4; // alignment delimiter ( &, \span )
+pub const CarRet: QuarterWord = 5; // end of line ( carriage_return, \cr, \crcr )
+pub const OutParam: QuarterWord = 5; // output a macro parameter
+pub const MacParam: QuarterWord = 6; // macro parameter symbol ( # )
+pub const SupMark: QuarterWord = 7; // superscript ( ^ )
+pub const SubMark: QuarterWord = 8; // subscript ( _ )
+pub const Ignore: QuarterWord = 9; // characters to ignore ( ^^@ )
+pub const Endv: QuarterWord = 9; // end of <v_j> list in alignment template
+pub const Spacer: QuarterWord = 10; // characters equivalent to blank space ( \  )
+pub const Letter: QuarterWord = 11; // characters regarded as letters ( A..Z, a..z )
+pub const OtherChar: QuarterWord = 12; // none of the special character types
+pub const ActiveChar: QuarterWord = 13; // characters that invoke macros ( ~ )
+pub const ParEnd: QuarterWord = 13; // end of paragraph ( \par )
+pub const Match: QuarterWord = 13; // match a macro parameter
+pub const Comment: QuarterWord = 14; // characters that introduce comments ( % )
+pub const EndMatch: QuarterWord = 14; // end of parameters to macro
+pub const Stop: QuarterWord = 14; // end of job ( \end, \dump )
+pub const InvalidChar: QuarterWord = 15; // characters that shouldn't appear ( ^^? )
+pub const DelimNum: QuarterWord = 15; // specify delimiter numerically ( \delimiter )
+pub const MaxCharCode: QuarterWord = 15; // largest catcode for individual characters
+
+// 208
+pub const CharNum: QuarterWord = 16; // character specified numerically ( \char )
+pub const MathCharNum: QuarterWord = 17; // explicit math code ( \mathchar )
+pub const Mark: QuarterWord = 18; // mark definition ( \mark )
+pub const Xray: QuarterWord = 19; // peek inside of TeX ( \show, \showbox, etc. )
+pub const MakeBox: QuarterWord = 20; // make a box ( \box, \copy, \hbox, etc. )
+pub const Hmove: QuarterWord = 21; // horizontal motion ( \moveleft, \moveright )
+pub const Vmove: QuarterWord = 22; // vertical motion ( \raise, \lower )
+pub const UnHbox: QuarterWord = 23; // unglue a box ( \unhbox, \unhcopy )
+pub const UnVbox: QuarterWord = 24; // unglue a box ( \unvbox, \unvcopy )
+pub const RemoveItem: QuarterWord = 25; // nullify last item ( \unpenalty, \unkern, \unskip )
+pub const Hskip: QuarterWord = 26; // horizontal glue ( \hskip, \hfil, etc. )
+pub const Vskip: QuarterWord = 27; // vertical glue ( \vskip, \vfil, etc. )
+pub const Mskip: QuarterWord = 28; // math glue ( \mskip )
+pub const Kern: QuarterWord = 29; // fixed space ( \kern )
+pub const Mkern: QuarterWord = 30; // math kern ( \mkern )
+pub const LeaderShip: QuarterWord = 31; // use a box ( \shipout, \leaders, etc. )
+pub const Halign: QuarterWord = 32; // horizontal table alignment ( \halign )
+pub const Valign: QuarterWord = 33; // vertical table alignment ( \valign )
+pub const NoAlign: QuarterWord = 34; // temporary escape from alignment ( \noalign )
+pub const Vrule: QuarterWord = 35; // vertical rule ( \vrule )
+pub const Hrule: QuarterWord = 36; // horizontal rule ( \hrule )
+pub const Insert: QuarterWord = 37; // vlist inserted in box ( \insert )
+pub const Vadjust: QuarterWord = 38; // vlist inserted in enclosing paragraph ( \vadjust )
+pub const IgnoreSpaces: QuarterWord = 39; // gobble spacer tokens ( \ignorespaces )
+pub const AfterAssignment: QuarterWord = 40; // save till assignment is done ( \afterassignment )
+pub const AfterGroup: QuarterWord = 41; // save till group is done ( \aftergroup )
+pub const BreakPenalty: QuarterWord = 42; // additional badness ( \penalty )
+pub const StartPar: QuarterWord = 43; // begin paragraph ( \indent, \noindent )
+pub const ItalCorr: QuarterWord = 44; // italic correction ( \/ )
+pub const Accent: QuarterWord = 45; // attach accent in text ( \accent )
+pub const MathAccent: QuarterWord = 46; // attach accent in math ( \mathaccent )
+pub const Discretionary: QuarterWord = 47; // discretionary texts ( \-, \discretionary )
+pub const EqNo: QuarterWord = 48; // equation number ( \eqno, \leqno )
+pub const LeftRight: QuarterWord = 49; // variable delimiter ( \left, \right )
+pub const MathComp: QuarterWord = 50; // component of formula ( \mathbin, etc. )
+pub const LimitSwitch: QuarterWord = 51; // diddle limit conventions ( \displaylimits, etc. )
+pub const Above: QuarterWord = 52; // generalized fraction ( \above, \atop, etc. )
+pub const MathStyle: QuarterWord = 53; // style specification ( \displaystyle, etc. )
+pub const MathChoice: QuarterWord = 54; // choice specification ( \mathchoice )
+pub const NonScript: QuarterWord = 55; // conditional math glue ( \nonscript )
+pub const Vcenter: QuarterWord = 56; // vertically center a vbox ( \vcenter )
+pub const CaseShift: QuarterWord = 57; // force specific case ( \lowercase, \uppercase )
+pub const Message: QuarterWord = 58; // send to user ( \message, \errmessage )
+pub const Extension: QuarterWord = 59; // extensions to TeX ( \write, \special, etc. )
+pub const InStream: QuarterWord = 60; // files for reading ( \openin, \closein )
+pub const BeginGroup: QuarterWord = 61; // begin local grouping ( \begingroup )
+pub const EndGroup: QuarterWord = 62; // end local grouping ( \endgroup )
+pub const Omit: QuarterWord = 63; // omit alignment template ( \omit )
+pub const ExSpace: QuarterWord = 64; // explicit space ( \  )
+pub const NoBoundary: QuarterWord = 65; // suppress boundary ligatures ( \noboundary )
+pub const Radical: QuarterWord = 66; // square root and similar signs ( \radical )
+pub const EndCsName: QuarterWord = 67; // end control sequence ( \endcsname )
+pub const MinInternal: QuarterWord = 68; // the smallest code that can follow \the
+pub const CharGiven: QuarterWord = 68; // character code defined by \chardef
+pub const MathGiven: QuarterWord = 69; // math code defined by \mathchardef
+pub const LastItem: QuarterWord = 70; // most recent item ( \lastpenalty, \lastkern, \lastskip )
+pub const MaxNonPrefixedCommand: QuarterWord = 70; // largest command code that can't be \global
+
+// 209
+pub const ToksRegister: QuarterWord = 71; // token list register ( \toks )
+pub const AssignToks: QuarterWord = 72; // special token list ( \output, \everypar, etc. )
+pub const AssignInt: QuarterWord = 73; // user-defined integer ( \tolerance, \day, etc. )
+pub const AssignDimen: QuarterWord = 74; // user-defined length ( \hsize, etc. )
+pub const AssignGlue: QuarterWord = 75; // user-defined glue ( \baselineskip, etc. )
+pub const AssignMuGlue: QuarterWord = 76; // user-defined muglue ( \thinmuskip, etc. )
+pub const AssignFontDimen: QuarterWord = 77; // user-defined font dimension ( \fontdimen )
+pub const AssignFontInt: QuarterWord = 78; // user-defined font integer ( \hyphenchar, \skewchar )
+pub const SetAux: QuarterWord = 79; // specify state info ( \spacefactor, \prevdepth )
+pub const SetPrevGraf: QuarterWord = 80; // specify state info ( \prevgraf )
+pub const SetPageDimen: QuarterWord = 81; // specify state info ( \pagegoal, etc. )
+pub const SetPageInt: QuarterWord = 82; // specify state info ( \deadcycles, \insertpenalties )
+pub const SetBoxDimen: QuarterWord = 83; // change dimension of box ( \wd, \ht, \dp )
+pub const SetShape: QuarterWord = 84; // specify fancy paragraph shape ( \parshape )
+pub const DefCode: QuarterWord = 85; // define a character code ( \catcode, etc. )
+pub const DefFamily: QuarterWord = 86; // declare math fonts ( \textfont, etc. )
+pub const SetFont: QuarterWord = 87; // set current font ( font identifiers )
+pub const DefFont: QuarterWord = 88; // define a font file ( \font )
+pub const Register: QuarterWord = 89; // internal register ( \count, \dimen, etc. )
+pub const MaxInternal: QuarterWord = 89; // the largest code that can follow \the
+pub const Advance: QuarterWord = 90; // advance a register or parameter ( \advance )
+pub const Multiply: QuarterWord = 91; // multiply a register or parameter ( \multiply )
+pub const Divide: QuarterWord = 92; // divide a register or parameter ( \divide )
+pub const Prefix: QuarterWord = 93; // qualify a definition ( \global, \long, \outer )
+pub const Let: QuarterWord = 94; // assign a command code ( \let, \futurelet )
+pub const ShorthandDef: QuarterWord = 95; // code definition ( \chardef, \countdef, etc. )
+pub const ReadToCs: QuarterWord = 96; // read into a control sequence ( \read )
+pub const Def: QuarterWord = 97; // macro definition ( \def, \gdef, \xdef, \edef )
+pub const SetBox: QuarterWord = 98; // set a box ( \setbox )
+pub const HyphData: QuarterWord = 99; // hyphenation data ( \hyphenation, \patterns )
+pub const SetInteraction: QuarterWord = 100; // define level of interaction ( \batchmode, etc. )
+pub const MaxCommand: QuarterWord = 100; // the largest command code seen at big_switch
+
+// 210
+pub const UndefinedCs: QuarterWord = 101; // initial state of most eq_type fields
+pub const ExpandAfter: QuarterWord = 102; // special expansion ( \expandafter )
+pub const NoExpand: QuarterWord = 103; // special nonexpansion ( \noexpand )
+pub const Input: QuarterWord = 104; // input a source file ( \input, \endinput )
+pub const IfTest: QuarterWord = 105; // conditional text ( \if, \ifcase, etc. )
+pub const FiOrElse: QuarterWord = 106; // delimiters for conditionals ( \else, etc. )
+pub const CsName: QuarterWord = 107; // make a control sequence from tokens ( \csname )
+pub const Convert: QuarterWord = 108; // convert to text ( \number, \string, etc. )
+pub const The: QuarterWord = 109; // expand an internal quantity ( \the )
+pub const TopBotMark: QuarterWord = 110; // inserted mark ( \topmark, etc. )
+pub const Call: QuarterWord = 111; // non-long, non-outer control sequence
+pub const LongCall: QuarterWord = 112; // long, non-outer control sequence
+pub const OuterCall: QuarterWord = 113; // non-long, outer control sequence
+pub const LongOuterCall: QuarterWord = 114; // long, outer control sequence
+pub const EndTemplate: QuarterWord = 115; // end of an alignment template
+pub const DontExpand: QuarterWord = 116; // the following token was marked by \noexpand
+pub const GlueRef: QuarterWord = 117; // the equivalent points to a glue specification
+pub const ShapeRef: QuarterWord = 118; // the equivalent points to a parshape specification
+pub const BoxRef: QuarterWord = 119; // the equivalent points to a box node, or is null
+pub const Data: QuarterWord = 120; // the equivalent is simply a halfword number
+
+impl TexState {
+    // 298
+    fn chr_cmd(&mut self, s: &str, chr_code: HalfWord) {
+        self.print(s);
+        self.print_ASCII(chr_code as i32);
+    }
+
+    // prints the symbolic meaning of a command code and its modifier
+    pub fn print_cmd_chr(&mut self, cmd: QuarterWord, chr_code: HalfWord) {
+        match cmd {
+            LeftBrace => self.chr_cmd("begin-group character ", chr_code),
+            RightBrace => self.chr_cmd("end-group character ", chr_code),
+            MathShift => self.chr_cmd("math shift character ", chr_code),
+            MacParam => self.chr_cmd("macro parameter character ", chr_code),
+            SupMark => self.chr_cmd("superscript character ", chr_code),
+            SubMark => self.chr_cmd("subscript character ", chr_code),
+            TabMark => {
+                // 781
+                if chr_code == SpanCode {
+                    self.print_esc("span");
+                } else {
+                    self.chr_cmd("alignment tab character ", chr_code);
+                }
+            }
+            CarRet => {
+                if chr_code == CrCode {
+                    self.print_esc("cr");
+                } else {
+                    self.print_esc("crcr");
+                }
+            }
+            Endv => self.print("end of alignment template"),
+            Spacer => self.chr_cmd("blank space ", chr_code),
+            Letter => self.chr_cmd("the letter ", chr_code),
+            OtherChar => self.chr_cmd("the character ", chr_code),
+            // 227
+            AssignGlue | AssignMuGlue => {
+                if chr_code < SkipBase {
+                    self.print_skip_param(chr_code - GlueBase);
+                } else if chr_code < MuSkipBase {
+                    self.print_esc("skip");
+                    self.print_int((chr_code - SkipBase) as i32);
+                } else {
+                    self.print_esc("muskip");
+                    self.print_int((chr_code - MuSkipBase) as i32);
+                }
+            }
+            // 231
+            AssignToks => {
+                if chr_code >= ToksBase {
+                    self.print_esc("toks");
+                    self.print_int((chr_code - ToksBase) as i32);
+                } else {
+                    match chr_code {
+                        OutputRoutineLoc => self.print_esc("output"),
+                        EveryParLoc => self.print_esc("everypar"),
+                        EveryMathLoc => self.print_esc("everymath"),
+                        EveryDisplayLoc => self.print_esc("everydisplay"),
+                        EveryHboxLoc => self.print_esc("everyhbox"),
+                        EveryVboxLoc => self.print_esc("everyvbox"),
+                        EveryJobLoc => self.print_esc("everyjob"),
+                        EveryCrLoc => self.print_esc("everycr"),
+                        _ => self.print_esc("errhelp"),
+                    }
+                }
+            }
+            // 239
+            AssignInt => {
+                if chr_code < CountBase {
+                    self.print_param(chr_code - IntBase);
+                } else {
+                    self.print_esc("count");
+                    self.print_int((chr_code - CountBase) as i32);
+                }
+            }
+            // 249
+            AssignDimen => {
+                if chr_code < ScaledBase {
+                    self.print_length_param(chr_code - DimenBase);
+                } else {
+                    self.print_esc("dimen");
+                    self.print_int((chr_code - ScaledBase) as i32);
+                }
+            }
+            // 266
+            Accent => self.print_esc("accent"),
+            Advance => self.print_esc("advance"),
+            AfterAssignment => self.print_esc("afterassignment"),
+            AfterGroup => self.print_esc("aftergroup"),
+            AssignFontDimen => self.print_esc("fontdimen"),
+            BeginGroup => self.print_esc("begingroup"),
+            BreakPenalty => self.print_esc("penalty"),
+            CharNum => self.print_esc("char"),
+            CsName => self.print_esc("csname"),
+            DefFont => self.print_esc("font"),
+            DelimNum => self.print_esc("delimiter"),
+            Divide => self.print_esc("divide"),
+            EndCsName => self.print_esc("endcsname"),
+            EndGroup => self.print_esc("endgroup"),
+            ExSpace => self.print_esc(" "),
+            ExpandAfter => self.print_esc("expandafter"),
+            Halign => self.print_esc("halign"),
+            Hrule => self.print_esc("hrule"),
+            IgnoreSpaces => self.print_esc("ignorespaces"),
+            Insert => self.print_esc("insert"),
+            ItalCorr => self.print_esc("/"),
+            Mark => {
+                self.print_esc("mark");
+                if chr_code > 0 {
+                    self.print_char('s' as u8);
+                }
+            }
+            MathAccent => self.print_esc("mathaccent"),
+            MathCharNum => self.print_esc("mathchar"),
+            MathChoice => self.print_esc("mathchoice"),
+            Multiply => self.print_esc("multiply"),
+            NoAlign => self.print_esc("noalign"),
+            NoBoundary => self.print_esc("noboundary"),
+            NoExpand => self.print_esc("noexpand"),
+            NonScript => self.print_esc("nonscript"),
+            Omit => self.print_esc("omit"),
+            Radical => self.print_esc("radical"),
+            ReadToCs => self.print_esc("read"),
+            Relax => self.print_esc("relax"),
+            SetBox => self.print_esc("setbox"),
+            SetPrevGraf => self.print_esc("prevgraf"),
+            SetShape => self.print_esc("parshape"),
+            The => self.print_esc("the"),
+            ToksRegister => self.print_esc("toks"),
+            Vadjust => self.print_esc("vadjust"),
+            Valign => self.print_esc("valign"),
+            Vcenter => self.print_esc("vcenter"),
+            Vrule => self.print_esc("vrule"),
+            // 335
+            ParEnd => self.print_esc("par"),
+            // 412
+            Register => {
+                if chr_code == IntVal as HalfWord {
+                    self.print_esc("count");
+                } else if chr_code == DimenVal as HalfWord {
+                    self.print_esc("dimen");
+                } else if chr_code == GlueVal as HalfWord {
+                    self.print_esc("skip");
+                } else {
+                    self.print_esc("muskip");
+                }
+            }
+            // 417
+            SetAux => {
+                if chr_code == Vmode as HalfWord {
+                    self.print_esc("prevdepth");
+                } else {
+                    self.print_esc("spacefactor");
+                }
+            }
+            SetPageInt => {
+                if chr_code == 0 {
+                    self.print_esc("deadcycles");
+                } else {
+                    self.print_esc("insertpenalties");
+                }
+            }
+            SetBoxDimen => {
+                if chr_code == WidthOffset as HalfWord {
+                    self.print_esc("wd");
+                } else if chr_code == HeightOffset as HalfWord {
+                    self.print_esc("ht");
+                } else {
+                    self.print_esc("dp");
+                }
+            }
+            LastItem => match chr_code {
+                0 => self.print_esc("lastpenalty"),
+                1 => self.print_esc("lastkern"),
+                2 => self.print_esc("lastskip"),
+                InputLineNoCode => self.print_esc("inputlineno"),
+                _ => self.print_esc("badness"),
+            },
+            // 469
+            Convert => match chr_code {
+                NumberCode => self.print_esc("number"),
+                RomanNumeralCode => self.print_esc("romannumeral"),
+                StringCode => self.print_esc("string"),
+                FontNameCode => self.print_esc("fontname"),
+                JobNameCode => self.print_esc("jobname"),
+                _ => self.print_esc("meaning"),
+            },
+            // 488
+            IfTest => match chr_code {
+                IfCatCode => self.print_esc("ifcat"),
+                IfIntCode => self.print_esc("ifnum"),
+                IfDimCode => self.print_esc("ifdim"),
+                IfOddCode => self.print_esc("ifodd"),
+                IfVmodeCode => self.print_esc("ifvmode"),
+                IfHmodeCode => self.print_esc("ifhmode"),
+                IfMmodeCode => self.print_esc("ifmmode"),
+                IfInnerCode => self.print_esc("ifinner"),
+                IfVoidCode => self.print_esc("ifvoid"),
+                IfHboxCode => self.print_esc("ifhbox"),
+                IfVboxCode => self.print_esc("ifvbox"),
+                IfxCode => self.print_esc("ifx"),
+                IfEofCode => self.print_esc("ifeof"),
+                IfTrueCode => self.print_esc("iftrue"),
+                IfFalseCode => self.print_esc("iffalse"),
+                IfCaseCode => self.print_esc("ifcase"),
+                _ => self.print_esc("if"),
+            },
+            // 492
+            FiOrElse => {
+                if chr_code as i32 == fi_code {
+                    self.print_esc("fi");
+                } else if chr_code as i32 == or_code {
+                    self.print_esc("or");
+                } else {
+                    self.print_esc("else");
+                }
+            }
+            // 1273
+            InStream => {
+                if chr_code == 0 {
+                    self.print_esc("closein");
+                } else {
+                    self.print_esc("openin");
+                }
+            }
+            // 1251
+            HyphData => {
+                if chr_code == 1 {
+                    self.print_esc("patterns");
+                } else {
+                    self.print_esc("hyphenation");
+                }
+            }
+            // 1255
+            AssignFontInt => {
+                if chr_code == 0 {
+                    self.print_esc("hyphenchar");
+                } else {
+                    self.print_esc("skewchar");
+                }
+            }
+            // 1261
+            SetFont => {
+                self.print("select font ");
+                self.print_font_name_and_size(chr_code as InternalFontNumber);
+            }
+            // 1346
+            Extension => match chr_code as QuarterWord {
+                OpenNode => self.print_esc("openout"),
+                WriteNode => self.print_esc("write"),
+                CloseNode => self.print_esc("closeout"),
+                SpecialNode => self.print_esc("special"),
+                ImmediateCode => self.print_esc("immediate"),
+                SetLanguageCode => self.print_esc("setlanguage"),
+                _ => self.print("[unknown extension!]"),
+            },
+            // 377
+            Input => {
+                if chr_code == 0 {
+                    self.print_esc("input");
+                } else {
+                    self.print_esc("endinput");
+                }
+            }
+            // 1059
+            Hskip => match chr_code {
+                SkipCode => self.print_esc("hskip"),
+                FilCode => self.print_esc("hfil"),
+                FillCode => self.print_esc("hfill"),
+                SsCode => self.print_esc("hss"),
+                _ => self.print_esc("hfilneg"),
+            },
+            Vskip => match chr_code {
+                SkipCode => self.print_esc("vskip"),
+                FilCode => self.print_esc("vfil"),
+                FillCode => self.print_esc("vfill"),
+                SsCode => self.print_esc("vss"),
+                _ => self.print_esc("vfilneg"),
+            },
+            Mskip => self.print_esc("mskip"),
+            Kern => self.print_esc("kern"),
+            Mkern => self.print_esc("mkern"),
+            // 1072
+            Hmove => {
+                if chr_code == 1 {
+                    self.print_esc("moveleft");
+                } else {
+                    self.print_esc("moveright");
+                }
+            }
+            Vmove => {
+                if chr_code == 1 {
+                    self.print_esc("raise");
+                } else {
+                    self.print_esc("lower");
+                }
+            }
+            MakeBox => match chr_code {
+                BoxCode => self.print_esc("box"),
+                CopyCode => self.print_esc("copy"),
+                LastBoxCode => self.print_esc("lastbox"),
+                VsplitCode => self.print_esc("vsplit"),
+                VtopCode => self.print_esc("vtop"),
+                c if c == VtopCode + Vmode as HalfWord => self.print_esc("vbox"),
+                _ => self.print_esc("hbox"),
+            },
+            LeaderShip => match chr_code as QuarterWord {
+                ALeaders => self.print_esc("leaders"),
+                CLeaders => self.print_esc("cleaders"),
+                XLeaders => self.print_esc("xleaders"),
+                _ => self.print_esc("shipout"),
+            },
+            // 1089
+            StartPar => {
+                if chr_code == 0 {
+                    self.print_esc("noindent");
+                } else {
+                    self.print_esc("indent");
+                }
+            }
+            // 1108
+            RemoveItem => match chr_code as QuarterWord {
+                GlueNode => self.print_esc("unskip"),
+                KernNode => self.print_esc("unkern"),
+                _ => self.print_esc("unpenalty"),
+            },
+            UnHbox => {
+                if chr_code == CopyCode {
+                    self.print_esc("unhcopy");
+                } else {
+                    self.print_esc("unhbox");
+                }
+            }
+            UnVbox => {
+                if chr_code == CopyCode {
+                    self.print_esc("unvcopy");
+                } else {
+                    self.print_esc("unvbox");
+                }
+            }
+            // 1115
+            Discretionary => {
+                if chr_code == 1 {
+                    self.print_esc("-");
+                } else {
+                    self.print_esc("discretionary");
+                }
+            }
+            // 1143
+            EqNo => {
+                if chr_code == 1 {
+                    self.print_esc("leqno");
+                } else {
+                    self.print_esc("eqno");
+                }
+            }
+            // 1157
+            MathComp => match chr_code as QuarterWord {
+                OrdNoad => self.print_esc("mathord"),
+                OpNoad => self.print_esc("mathop"),
+                BinNoad => self.print_esc("mathbin"),
+                RelNoad => self.print_esc("mathrel"),
+                OpenNoad => self.print_esc("mathopen"),
+                CloseNoad => self.print_esc("mathclose"),
+                PunctNoad => self.print_esc("mathpunct"),
+                InnerNoad => self.print_esc("mathinner"),
+                UnderNoad => self.print_esc("underline"),
+                _ => self.print_esc("overline"),
+            },
+            LimitSwitch => match chr_code as QuarterWord {
+                Limits => self.print_esc("limits"),
+                NoLimits => self.print_esc("nolimits"),
+                _ => self.print_esc("displaylimits"),
+            },
+            // 1170
+            MathStyle => self.print_style(chr_code as i32),
+            // 1179
+            Above => match chr_code {
+                OverCode => self.print_esc("over"),
+                AtopCode => self.print_esc("atop"),
+                c if c == DelimitedCode + AboveCode => self.print_esc("abovewithdelims"),
+                c if c == DelimitedCode + OverCode => self.print_esc("overwithdelims"),
+                c if c == DelimitedCode + AtopCode => self.print_esc("atopwithdelims"),
+                _ => self.print_esc("above"),
+            },
+            // 1189
+            LeftRight => {
+                if chr_code == LeftNoad as HalfWord {
+                    self.print_esc("left");
+                } else {
+                    self.print_esc("right");
+                }
+            }
+            // 1209
+            Prefix => match chr_code {
+                1 => self.print_esc("long"),
+                2 => self.print_esc("outer"),
+                _ => self.print_esc("global"),
+            },
+            Def => match chr_code {
+                0 => self.print_esc("def"),
+                1 => self.print_esc("gdef"),
+                2 => self.print_esc("edef"),
+                _ => self.print_esc("xdef"),
+            },
+            // 1220
+            Let => {
+                if chr_code != Normal as HalfWord {
+                    self.print_esc("futurelet");
+                } else {
+                    self.print_esc("let");
+                }
+            }
+            // 1223
+            ShorthandDef => match chr_code {
+                CharDefCode => self.print_esc("chardef"),
+                MathCharDefCode => self.print_esc("mathchardef"),
+                CountDefCode => self.print_esc("countdef"),
+                DimenDefCode => self.print_esc("dimendef"),
+                SkipDefCode => self.print_esc("skipdef"),
+                MuSkipDefCode => self.print_esc("muskipdef"),
+                _ => self.print_esc("toksdef"),
+            },
+            CharGiven => {
+                self.print_esc("char");
+                self.print_hex(chr_code as i32);
+            }
+            MathGiven => {
+                self.print_esc("mathchar");
+                self.print_hex(chr_code as i32);
+            }
+            // 1231
+            DefCode => match chr_code {
+                CatCodeBase => self.print_esc("catcode"),
+                MathCodeBase => self.print_esc("mathcode"),
+                LcCodeBase => self.print_esc("lccode"),
+                UcCodeBase => self.print_esc("uccode"),
+                SfCodeBase => self.print_esc("sfcode"),
+                _ => self.print_esc("delcode"),
+            },
+            DefFamily => self.print_size((chr_code - MathFontBase) as i32),
+            // 1263
+            SetInteraction => match chr_code {
+                0 => self.print_esc("batchmode"),
+                1 => self.print_esc("nonstopmode"),
+                2 => self.print_esc("scrollmode"),
+                _ => self.print_esc("errorstopmode"),
+            },
+            // 1277
+            Message => {
+                if chr_code == 0 {
+                    self.print_esc("message");
+                } else {
+                    self.print_esc("errmessage");
+                }
+            }
+            // 1287
+            CaseShift => {
+                if chr_code == LcCodeBase {
+                    self.print_esc("lowercase");
+                } else {
+                    self.print_esc("uppercase");
+                }
+            }
+            // 1292
+            Xray => match chr_code {
+                ShowBoxCode => self.print_esc("showbox"),
+                ShowTheCode => self.print_esc("showthe"),
+                ShowListsCode => self.print_esc("showlists"),
+                _ => self.print_esc("show"),
+            },
+            // 1053
+            Stop => {
+                if chr_code == 1 {
+                    self.print_esc("dump");
+                } else {
+                    self.print_esc("end");
+                }
+            }
+            // 984
+            SetPageDimen => match chr_code {
+                0 => self.print_esc("pagegoal"),
+                1 => self.print_esc("pagetotal"),
+                2 => self.print_esc("pagestretch"),
+                3 => self.print_esc("pagefilstretch"),
+                4 => self.print_esc("pagefillstretch"),
+                5 => self.print_esc("pagefilllstretch"),
+                6 => self.print_esc("pageshrink"),
+                _ => self.print_esc("pagedepth"),
+            },
+            // 385
+            TopBotMark => {
+                match (chr_code as usize) % MarksCode {
+                    top_mark_code => self.print_esc("topmark"),
+                    first_mark_code => self.print_esc("firstmark"),
+                    bot_mark_code => self.print_esc("botmark"),
+                    split_first_mark_code => self.print_esc("splitfirstmark"),
+                    _ => self.print_esc("splitbotmark"),
+                }
+                if chr_code as usize >= MarksCode {
+                    self.print_char('s' as u8);
+                }
+            }
+            UndefinedCs => self.print("undefined"),
+            Call => self.print("macro"),
+            LongCall => self.print_esc("long macro"),
+            OuterCall => self.print_esc("outer macro"),
+            LongOuterCall => {
+                self.print_esc("long");
+                self.print_esc("outer macro");
+            }
+            EndTemplate => self.print_esc("outer endtemplate"),
+            _ => self.print("[unknown command code!]"),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use crate::*;
+
+// 487
+pub const IfCharCode: HalfWord = 0; // \if
+pub const IfCatCode: HalfWord = 1; // \ifcat
+pub const IfIntCode: HalfWord = 2; // \ifnum
+pub const IfDimCode: HalfWord = 3; // \ifdim
+pub const IfOddCode: HalfWord = 4; // \ifodd
+pub const IfVmodeCode: HalfWord = 5; // \ifvmode
+pub const IfHmodeCode: HalfWord = 6; // \ifhmode
+pub const IfMmodeCode: HalfWord = 7; // \ifmmode
+pub const IfInnerCode: HalfWord = 8; // \ifinner
+pub const IfVoidCode: HalfWord = 9; // \ifvoid
+pub const IfHboxCode: HalfWord = 10; // \ifhbox
+pub const IfVboxCode: HalfWord = 11; // \ifvbox
+pub const IfxCode: HalfWord = 12; // \ifx
+pub const IfEofCode: HalfWord = 13; // \ifeof
+pub const IfTrueCode: HalfWord = 14; // \iftrue
+pub const IfFalseCode: HalfWord = 15; // \iffalse
+pub const IfCaseCode: HalfWord = 16; // \ifcase
+
+impl TexState {
+    // 489
+    // line number at beginning of a conditional
+    pub fn if_line_field(&self, n: usize) -> i32 {
+        self.mem[n + 1].int()
+    }
+
+    pub fn set_if_line_field(&mut self, n: usize, value: i32) {
+        self.mem[n + 1].set_int(value);
+    }
+
+    // 494
+    // skips tokens until reaching \fi, \else or \or at the current level of \if...\fi nesting
+    pub fn pass_text(&mut self) {
+        let save_scanner_status = self.scanner_status; // scanner_status upon entry
+        self.scanner_status = Skipping;
+        let mut l = 0; // level of \if...\fi nesting
+        self.skip_line = self.line;
+        loop {
+            self.get_next();
+            if self.cur_cmd == FiOrElse {
+                if l == 0 {
+                    break;
+                }
+                if self.cur_chr as i32 == fi_code {
+                    l -= 1;
+                }
+            } else if self.cur_cmd == IfTest {
+                l += 1;
+            }
+        }
+        self.scanner_status = save_scanner_status;
+    }
+
+    // 495
+    fn push_cond(&mut self) {
+        let p = self.get_node(if_node_size as usize);
+        self.set_link(p as usize, self.cond_ptr);
+        self.set_type(p as usize, self.if_limit as u8);
+        self.set_subtype(p as usize, self.cur_if as u8);
+        self.set_if_line_field(p as usize, self.if_line);
+        self.cond_ptr = p;
+        self.cur_if = self.cur_chr as SmallNumber;
+        self.if_limit = if_code;
+        self.if_line = self.line;
+    }
+
+    pub fn pop_cond(&mut self) {
+        let p = self.cond_ptr;
+        self.if_line = self.if_line_field(p as usize);
+        self.cur_if = self.subtype(p as usize) as SmallNumber;
+        self.if_limit = self.a_type(p as usize) as i32;
+        self.cond_ptr = self.link(p as usize);
+        self.free_node(p, if_node_size as usize);
+    }
+
+    // 496
+    // changes the if_limit code of the conditional whose stack entry is p
+    fn change_if_limit(&mut self, l: i32, p: Pointer) {
+        if p == self.cond_ptr {
+            self.if_limit = l; // that's the easy case
+        } else {
+            let mut q = self.cond_ptr;
+            loop {
+                if q == Null {
+                    self.confusion("if");
+                }
+                if self.link(q as usize) == p {
+                    self.set_type(q as usize, l as u8);
+                    return;
+                }
+                q = self.link(q as usize);
+            }
+        }
+    }
+
+    // 498
+    // begins a conditional, skipping text if the condition is false
+    pub fn conditional(&mut self) {
+        self.push_cond();
+        let save_cond_ptr = self.cond_ptr; // cond_ptr corresponding to this conditional
+        let this_if = self.cur_chr; // type of this conditional
+        'common_ending: {
+            // 501: either process \ifcase or set b to the value of a boolean condition
+            let b = match this_if {
+                IfCharCode | IfCatCode => self.test_chars(this_if),
+                IfIntCode | IfDimCode => self.test_relation(this_if),
+                IfOddCode => {
+                    // 504: test if an integer is odd
+                    self.scan_int();
+                    self.cur_val % 2 != 0
+                }
+                IfVmodeCode => self.mode().abs() == Vmode,
+                IfHmodeCode => self.mode().abs() == Hmode,
+                IfMmodeCode => self.mode().abs() == Mmode,
+                IfInnerCode => self.mode() < 0,
+                IfVoidCode | IfHboxCode | IfVboxCode => {
+                    // 505: test box register status
+                    self.scan_eight_bit_int();
+                    let p = self.box_reg(self.cur_val as HalfWord);
+                    if this_if == IfVoidCode {
+                        p == Null
+                    } else if p == Null {
+                        false
+                    } else if this_if == IfHboxCode {
+                        self.a_type(p as usize) == HlistNode
+                    } else {
+                        self.a_type(p as usize) == VlistNode
+                    }
+                }
+                IfxCode => self.test_tokens(),
+                IfEofCode => {
+                    self.scan_four_bit_int();
+                    self.read_open[self.cur_val as usize] == closed
+                }
+                IfTrueCode => true,
+                IfFalseCode => false,
+                _ => {
+                    // 509: select the appropriate case and return or goto common_ending
+                    self.scan_int();
+                    let mut n = self.cur_val; // the number of cases to pass
+                    if self.int_par(TracingCommandsCode) > 1 {
+                        self.begin_diagnostic();
+                        self.print("{case ");
+                        self.print_int(n);
+                        self.print_char('}' as u8);
+                        self.end_diagnostic(false);
+                    }
+                    while n != 0 {
+                        self.pass_text();
+                        if self.cond_ptr == save_cond_ptr {
+                            if self.cur_chr as i32 == or_code {
+                                n -= 1;
+                            } else {
+                                break 'common_ending;
+                            }
+                        } else if self.cur_chr as i32 == fi_code {
+                            self.pop_cond();
+                        }
+                    }
+                    self.change_if_limit(or_code, save_cond_ptr);
+                    return; // wait for \or, \else, or \fi
+                }
+            };
+            if self.int_par(TracingCommandsCode) > 1 {
+                // 502: display the value of b
+                self.begin_diagnostic();
+                self.print(if b { "{true}" } else { "{false}" });
+                self.end_diagnostic(false);
+            }
+            if b {
+                self.change_if_limit(else_code, save_cond_ptr);
+                return; // wait for \else or \fi
+            }
+            // 500: skip to \else or \fi, then goto common_ending
+            loop {
+                self.pass_text();
+                if self.cond_ptr == save_cond_ptr {
+                    if self.cur_chr as i32 != or_code {
+                        break 'common_ending;
+                    }
+                    self.print_err("Extra ");
+                    self.print_esc("or");
+                    self.help(&["I'm ignoring this; it doesn't match any \\if."]);
+                    self.error();
+                } else if self.cur_chr as i32 == fi_code {
+                    self.pop_cond();
+                }
+            }
+        }
+        // common_ending:
+        if self.cur_chr as i32 == fi_code {
+            self.pop_cond();
+        } else {
+            self.if_limit = fi_code; // wait for \fi
+        }
+    }
+
+    // 503
+    // test relation between integers or dimensions
+    fn test_relation(&mut self, this_if: HalfWord) -> bool {
+        if this_if == IfIntCode {
+            self.scan_int();
+        } else {
+            self.scan_normal_dimen();
+        }
+        let n = self.cur_val;
+        self.get_x_non_blank();
+        let r = if self.cur_tok as i32 >= other_token + '<' as i32
+            && self.cur_tok as i32 <= other_token + '>' as i32
+        {
+            (self.cur_tok as i32 - other_token) as u8 as char
+        } else {
+            self.print_err("Missing = inserted for ");
+            self.print_cmd_chr(IfTest, this_if);
+            self.help(&["I was expecting to see `<', `=', or `>'. Didn't."]);
+            self.back_error();
+            '='
+        };
+        if this_if == IfIntCode {
+            self.scan_int();
+        } else {
+            self.scan_normal_dimen();
+        }
+        match r {
+            '<' => n < self.cur_val, // this case is a change from the second edition
+            '=' => n == self.cur_val,
+            _ => n > self.cur_val,
+        }
+    }
+
+    // 506
+    fn get_x_token_or_active_char(&mut self) {
+        self.get_x_token();
+        if self.cur_cmd == Relax && self.cur_chr == NoExpandFlag {
+            self.cur_cmd = ActiveChar;
+            self.cur_chr = self.cur_tok - cs_token_flag as HalfWord - ActiveBase;
+        }
+    }
+
+    // test if two characters match
+    fn test_chars(&mut self, this_if: HalfWord) -> bool {
+        self.get_x_token_or_active_char();
+        let (m, n) = if self.cur_cmd > ActiveChar || self.cur_chr > 255 {
+            (Relax, 256) // not a character
+        } else {
+            (self.cur_cmd, self.cur_chr)
+        };
+        self.get_x_token_or_active_char();
+        if self.cur_cmd > ActiveChar || self.cur_chr > 255 {
+            self.cur_cmd = Relax;
+            self.cur_chr = 256;
+        }
+        if this_if == IfCharCode {
+            n == self.cur_chr
+        } else {
+            m == self.cur_cmd
+        }
+    }
+
+    // 507
+    // test if two tokens match
+    fn test_tokens(&mut self) -> bool {
+        let save_scanner_status = self.scanner_status;
+        self.scanner_status = Normal;
+        self.get_next();
+        let n = self.cur_cs;
+        let p = self.cur_cmd;
+        let q = self.cur_chr;
+        self.get_next();
+        let b = if self.cur_cmd != p {
+            false
+        } else if self.cur_cmd < Call {
+            self.cur_chr == q
+        } else {
+            // 508: test if two macro texts match
+            let mut p = self.link(self.cur_chr as usize);
+            let mut q = self.link(self.equiv(n as usize) as usize); // omit reference counts
+            if p == q {
+                true
+            } else {
+                while p != Null && q != Null {
+                    if self.info(p as usize) != self.info(q as usize) {
+                        p = Null;
+                    } else {
+                        p = self.link(p as usize);
+                        q = self.link(q as usize);
+                    }
+                }
+                p == Null && q == Null
+            }
+        };
+        self.scanner_status = save_scanner_status;
+        b
+    }
+
+    // 510
+    // terminate the current conditional and skip to \fi
+    pub fn terminate_conditional(&mut self) {
+        if self.cur_chr as i32 > self.if_limit {
+            if self.if_limit == if_code {
+                self.insert_relax(); // condition not yet evaluated
+            } else {
+                self.print_err("Extra ");
+                self.print_cmd_chr(FiOrElse, self.cur_chr);
+                self.help(&["I'm ignoring this; it doesn't match any \\if."]);
+                self.error();
+            }
+        } else {
+            while self.cur_chr as i32 != fi_code {
+                self.pass_text(); // skip to \fi
+            }
+            self.pop_cond();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn incomplete_ifs_are_reported_at_the_end() {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", "\\iftrue\n\\ifcase 2 \\or\\else\\end\n");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]);
+        assert!(log.contains("(\\end occurred when \\ifcase on line 2 was incomplete)\n\
+                              (\\end occurred when \\iftrue on line 1 was incomplete)"), "{}", log);
+    }
+}
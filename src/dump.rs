@@ -0,0 +1,890 @@
+use crate::*;
+use std::io::Write;
+
+// 1299
+// the string pool check sum, computed like tangle's over the strings that
+// get_strings_started makes, so that formats are only loaded by a TeX whose
+// preloaded string numbers agree with the ones they were dumped with
+const CheckSumPrime: i64 = 0o3777777667;
+
+// the first bytes of every format file
+const FmtMagic: &[u8; 8] = b"RusTeXfm";
+
+// changes whenever the layout of format files does
+const FmtVersion: u32 = 1;
+
+// the engine that wrote a format; a format is only loaded by the same version
+const FmtEngine: &str = concat!("RusTeX ", env!("CARGO_PKG_VERSION"));
+
+// the sizes this TeX was built with, which must agree between the TeX that
+// dumps a format and the one that loads it
+fn fmt_sizes() -> [(&'static str, i64); 13] {
+    [
+        ("mem_bot", MemBot as i64),
+        ("mem_top", MemTop as i64),
+        ("mem_max", MemMax as i64),
+        ("eqtb_size", EqtbSize as i64),
+        ("hash_size", HashSize as i64),
+        ("hash_prime", HashPrime as i64),
+        ("hyph_size", HyphSize as i64),
+        ("pool_size", PoolSize as i64),
+        ("max_strings", MaxStrings as i64),
+        ("font_mem_size", FontMemSize as i64),
+        ("font_max", FontMax as i64),
+        ("trie_size", TrieSize as i64),
+        ("fixed_glue", cfg!(feature = "fixed-glue") as i64),
+    ]
+}
+
+// 64-bit FNV-1a, the content hash of a format's uncompressed body
+fn fmt_hash(data: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for &b in data {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+// 1305
+// a format file being written, one four-byte word at a time. Words go out in
+// little-endian order whatever the host, and the body is kept in memory so that
+// close can put the header, hash and compressed body on the file. A format file is:
+//   FmtMagic, FmtVersion, the length and bytes of FmtEngine,
+//   the number of sizes and each of fmt_sizes, as 64-bit integers,
+//   the content hash, the length of the body, and the body compressed with zlib
+pub struct FmtWriter {
+    f: Box<dyn Write>,
+    body: Vec<u8>,
+}
+
+impl FmtWriter {
+    pub fn new(f: Box<dyn Write>) -> FmtWriter {
+        FmtWriter { f, body: Vec::new() }
+    }
+
+    fn dump_bytes(&mut self, b: [u8; 4]) {
+        self.body.extend_from_slice(&b);
+    }
+
+    pub fn dump_wd(&mut self, w: MemoryWord) {
+        self.dump_bytes(w.data);
+    }
+
+    pub fn dump_int(&mut self, x: i32) {
+        self.dump_bytes(x.to_le_bytes());
+    }
+
+    pub fn dump_hh(&mut self, h: TwoHalves) {
+        self.dump_bytes(h.data);
+    }
+
+    pub fn dump_qqqq(&mut self, q: FourQuarters) {
+        self.dump_bytes([q.b0, q.b1, q.b2, q.b3]);
+    }
+
+    // 1329
+    // writes the file, telling whether all of it was written
+    pub fn close(mut self) -> bool {
+        let mut out = Vec::new();
+        out.extend_from_slice(FmtMagic);
+        out.extend_from_slice(&FmtVersion.to_le_bytes());
+        out.extend_from_slice(&(FmtEngine.len() as u32).to_le_bytes());
+        out.extend_from_slice(FmtEngine.as_bytes());
+        let sizes = fmt_sizes();
+        out.extend_from_slice(&(sizes.len() as u32).to_le_bytes());
+        for &(_, v) in sizes.iter() {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(&fmt_hash(&self.body).to_le_bytes());
+        out.extend_from_slice(&(self.body.len() as u64).to_le_bytes());
+        out.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(&self.body, 6));
+        self.f.write_all(&out).is_ok() && self.f.flush().is_ok()
+    }
+}
+
+// takes n bytes from the front of data
+fn take_bytes<'a>(data: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if data.len() < n {
+        return None;
+    }
+    let (b, rest) = data.split_at(n);
+    *data = rest;
+    Some(b)
+}
+
+fn take_u32(data: &mut &[u8]) -> Option<u32> {
+    let mut b = [0; 4];
+    b.copy_from_slice(take_bytes(data, 4)?);
+    Some(u32::from_le_bytes(b))
+}
+
+fn take_u64(data: &mut &[u8]) -> Option<u64> {
+    let mut b = [0; 8];
+    b.copy_from_slice(take_bytes(data, 8)?);
+    Some(u64::from_le_bytes(b))
+}
+
+// 1306
+// a format file being read; every undump gives None when the body ends early
+// or holds a value out of range, which makes load_fmt_file give up
+pub struct FmtReader {
+    body: Vec<u8>,
+    pos: usize,
+    too_small: Option<String>, // the size this TeX would have to increase to read the format
+}
+
+impl FmtReader {
+    // reads the whole file, checking that it was written by this build of TeX
+    // and has come through intact; the error says what is wrong with it
+    pub fn open(mut f: ByteFile) -> Result<FmtReader, String> {
+        let mut file = Vec::new();
+        f.read_to_end(&mut file).map_err(|e| format!("the format file could not be read ({})", e))?;
+        let mut data = &file[..];
+        let not_fmt = || "this is not a format file".to_string();
+        if take_bytes(&mut data, FmtMagic.len()) != Some(&FmtMagic[..]) {
+            return Err(not_fmt());
+        }
+        let version = take_u32(&mut data).ok_or_else(not_fmt)?;
+        if version != FmtVersion {
+            return Err(format!("the format file has layout version {}, but this TeX needs version {}",
+                version, FmtVersion));
+        }
+        let n = take_u32(&mut data).ok_or_else(not_fmt)? as usize;
+        let engine = String::from_utf8_lossy(take_bytes(&mut data, n).ok_or_else(not_fmt)?).into_owned();
+        if engine != FmtEngine {
+            return Err(format!("the format was made by {}, but this is {}", engine, FmtEngine));
+        }
+        let sizes = fmt_sizes();
+        if take_u32(&mut data).ok_or_else(not_fmt)? as usize != sizes.len() {
+            return Err(not_fmt());
+        }
+        for &(name, v) in sizes.iter() {
+            let x = take_u64(&mut data).ok_or_else(not_fmt)? as i64;
+            if x != v {
+                return Err(format!("the format was made with {}={}, but this TeX has {}={}", name, x, name, v));
+            }
+        }
+        let hash = take_u64(&mut data).ok_or_else(not_fmt)?;
+        let len = take_u64(&mut data).ok_or_else(not_fmt)? as usize;
+        let body = miniz_oxide::inflate::decompress_to_vec_zlib(data)
+            .map_err(|_| "the format file is damaged (it doesn't decompress)".to_string())?;
+        if body.len() != len || fmt_hash(&body) != hash {
+            return Err("the format file is damaged (its contents don't match their hash)".to_string());
+        }
+        Ok(FmtReader { body, pos: 0, too_small: None })
+    }
+
+    fn undump_bytes(&mut self) -> Option<[u8; 4]> {
+        let b = self.body.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        Some([b[0], b[1], b[2], b[3]])
+    }
+
+    pub fn undump_wd(&mut self) -> Option<MemoryWord> {
+        Some(MemoryWord { data: self.undump_bytes()? })
+    }
+
+    pub fn undump_int(&mut self) -> Option<i32> {
+        Some(i32::from_le_bytes(self.undump_bytes()?))
+    }
+
+    pub fn undump_hh(&mut self) -> Option<TwoHalves> {
+        Some(TwoHalves { data: self.undump_bytes()? })
+    }
+
+    pub fn undump_qqqq(&mut self) -> Option<FourQuarters> {
+        let b = self.undump_bytes()?;
+        Some(FourQuarters { b0: b[0], b1: b[1], b2: b[2], b3: b[3] })
+    }
+
+    // an integer in the range min..=max
+    pub fn undump(&mut self, min: i32, max: i32) -> Option<i32> {
+        let x = self.undump_int()?;
+        if x < min || x > max {
+            None
+        } else {
+            Some(x)
+        }
+    }
+
+    // an integer at least min that this TeX has room for, up to max
+    pub fn undump_size(&mut self, min: i32, max: i32, name: &str) -> Option<i32> {
+        let x = self.undump_int()?;
+        if x < min {
+            return None;
+        }
+        if x > max {
+            self.too_small = Some(name.to_string());
+            return None;
+        }
+        Some(x)
+    }
+
+    // true if nothing follows the closing check word
+    fn at_eof(&self) -> bool {
+        self.pos == self.body.len()
+    }
+}
+
+impl TexState {
+    // the check sum of the preloaded strings, standing in for tex.pool's
+    pub fn pool_check_sum(&self) -> i32 {
+        let mut a: i64 = 0;
+        for k in 0..self.str_start[TexputName as usize + 1] {
+            a = a + a + self.str_pool[k] as i64;
+            while a > CheckSumPrime {
+                a -= CheckSumPrime;
+            }
+        }
+        a as i32
+    }
+
+    // 524
+    // opens the format named after `&' on the first line, or else plain.fmt;
+    // loc is advanced past the format name
+    pub fn open_fmt_file(&mut self) -> Option<ByteFile> {
+        let mut j = self.cur_input.loc_field as usize; // the first space after the format file name
+        if self.buffer[j] == '&' as u8 {
+            self.cur_input.loc_field += 1;
+            j = self.cur_input.loc_field as usize;
+            self.buffer[self.last] = ' ' as u8;
+            while self.buffer[j] != ' ' as u8 {
+                j += 1;
+            }
+            self.pack_buffered_name(0, self.cur_input.loc_field as usize, j - 1);
+            if let Some(f) = self.w_open_in() {
+                self.cur_input.loc_field = j as HalfWord;
+                return Some(f);
+            }
+            self.wterm_ln("Sorry, I can't find that format; will try PLAIN.");
+            self.update_terminal();
+        }
+        // now pull out all the stops: try for the system plain file
+        self.pack_buffered_name(format_default_length - format_ext_length, 1, 0);
+        match self.w_open_in() {
+            Some(f) => {
+                self.cur_input.loc_field = j as HalfWord;
+                Some(f)
+            }
+            None => {
+                self.wterm_ln("I can't find the PLAIN format file!");
+                None
+            }
+        }
+    }
+
+    // 1337
+    // loads the format requested on the first line, unless one is preloaded and
+    // no `&' asks for another; false means TeX can't go on
+    pub fn load_format(&mut self) -> bool {
+        if self.format_ident == 0 || self.buffer[self.cur_input.loc_field as usize] == '&' as u8 {
+            if self.format_ident != 0 {
+                initialize(self); // erase preloaded format
+            }
+            let f = match self.open_fmt_file() {
+                Some(f) => f,
+                None => return false,
+            };
+            if !self.load_fmt_file(f) {
+                return false;
+            }
+            while self.cur_input.loc_field < self.cur_input.limit_field
+                && self.buffer[self.cur_input.loc_field as usize] == ' ' as u8
+            {
+                self.cur_input.loc_field += 1;
+            }
+        }
+        true
+    }
+
+    // 1302
+    // \dump: writes everything TeX knows into the format file
+    pub fn store_fmt_file(&mut self) {
+        // 1304: if dumping is not allowed, abort
+        if self.save_ptr != 0 {
+            self.print_err("You can't dump inside a group");
+            self.help(&["`{...\\dump}' is a no-no."]);
+            self.succumb();
+        }
+        let mut fmt_file = self.open_fmt_out();
+        self.dump_constants(&mut fmt_file);
+        self.dump_string_pool(&mut fmt_file);
+        self.dump_dynamic_memory(&mut fmt_file);
+        self.dump_eqtb(&mut fmt_file);
+        self.dump_font_info(&mut fmt_file);
+        self.dump_hyphenation(&mut fmt_file);
+        // 1326: dump a couple more things and the closing check word
+        fmt_file.dump_int(self.interaction.value());
+        fmt_file.dump_int(self.format_ident);
+        fmt_file.dump_int(69069);
+        self.set_int_par(TracingStatsCode, 0);
+        // 1329: close the format file
+        if !fmt_file.close() {
+            self.fatal_error("*** (the format file could not be written)");
+        }
+    }
+
+    // 1328
+    // creates the format_ident, opens the format file, and informs the user
+    // that dumping has begun
+    fn open_fmt_out(&mut self) -> FmtWriter {
+        self.selector = NewString;
+        self.print(" (preloaded format=");
+        self.print_str(self.job_name);
+        self.print_char(' ' as u8);
+        self.print_int(self.int_par(YearCode));
+        self.print_char('.' as u8);
+        self.print_int(self.int_par(MonthCode));
+        self.print_char('.' as u8);
+        self.print_int(self.int_par(DayCode));
+        self.print_char(')' as u8);
+        self.selector = if self.interaction == Interaction::BatchMode { LogOnly } else { TermAndLog };
+        self.str_room(1);
+        self.format_ident = self.make_string();
+        self.pack_job_name(ExtFmt);
+        let f = loop {
+            if let Some(f) = self.a_open_out() {
+                break f;
+            }
+            self.prompt_file_name("format file name", ExtFmt);
+        };
+        self.print_nl("Beginning to dump on file ");
+        let s = self.make_name_string();
+        self.slow_print(s);
+        self.flush_string();
+        self.print_nl("");
+        self.slow_print(self.format_ident);
+        FmtWriter::new(f)
+    }
+
+    // 1307
+    fn dump_constants(&mut self, fmt_file: &mut FmtWriter) {
+        fmt_file.dump_int(self.pool_check_sum());
+        fmt_file.dump_int(MemBot as i32);
+        fmt_file.dump_int(MemTop as i32);
+        fmt_file.dump_int(EqtbSize as i32);
+        fmt_file.dump_int(HashPrime);
+        fmt_file.dump_int(HyphSize);
+    }
+
+    // 1309
+    fn dump_string_pool(&mut self, fmt_file: &mut FmtWriter) {
+        fmt_file.dump_int(self.pool_ptr as i32);
+        fmt_file.dump_int(self.str_ptr);
+        for k in 0..=self.str_ptr as usize {
+            fmt_file.dump_int(self.str_start[k] as i32);
+        }
+        // the pool goes out four characters to a word, the last word overlapping
+        // the one before it if pool_ptr isn't a multiple of four
+        let mut k = 0;
+        while k + 4 < self.pool_ptr {
+            fmt_file.dump_qqqq(self.four_ascii(k));
+            k += 4;
+        }
+        fmt_file.dump_qqqq(self.four_ascii(self.pool_ptr - 4));
+        self.print_ln();
+        self.print_int(self.str_ptr);
+        self.print(" strings of total length ");
+        self.print_int(self.pool_ptr as i32);
+    }
+
+    fn four_ascii(&self, k: PoolPointer) -> FourQuarters {
+        FourQuarters {
+            b0: self.str_pool[k],
+            b1: self.str_pool[k + 1],
+            b2: self.str_pool[k + 2],
+            b3: self.str_pool[k + 3],
+        }
+    }
+
+    // 1311
+    // dumps the variable-size memory up to lo_mem_max, skipping the bodies of
+    // empty nodes, then the one-word memory from hi_mem_min
+    fn dump_dynamic_memory(&mut self, fmt_file: &mut FmtWriter) {
+        self.sort_avail();
+        self.var_used = 0;
+        fmt_file.dump_int(self.lo_mem_max as i32);
+        fmt_file.dump_int(self.rover as i32);
+        let mut p = MemBot;
+        let mut q = self.rover as usize;
+        let mut x = 0; // the number of words dumped
+        loop {
+            for k in p..=q + 1 {
+                fmt_file.dump_wd(self.mem[k]);
+            }
+            x += q + 2 - p;
+            self.var_used += (q - p) as i32;
+            p = q + self.node_size(q) as usize;
+            q = self.rlink(q) as usize;
+            if q == self.rover as usize {
+                break;
+            }
+        }
+        self.var_used += self.lo_mem_max as i32 - p as i32;
+        self.dyn_used = self.mem_end as i32 + 1 - self.hi_mem_min as i32;
+        for k in p..=self.lo_mem_max as usize {
+            fmt_file.dump_wd(self.mem[k]);
+        }
+        x += self.lo_mem_max as usize + 1 - p;
+        fmt_file.dump_int(self.hi_mem_min as i32);
+        fmt_file.dump_int(self.avail as i32);
+        for k in self.hi_mem_min as usize..=self.mem_end as usize {
+            fmt_file.dump_wd(self.mem[k]);
+        }
+        x += self.mem_end as usize + 1 - self.hi_mem_min as usize;
+        let mut p = self.avail;
+        while p != Null {
+            self.dyn_used -= 1;
+            p = self.link(p as usize);
+        }
+        fmt_file.dump_int(self.var_used);
+        fmt_file.dump_int(self.dyn_used);
+        self.print_ln();
+        self.print_int(x as i32);
+        self.print(" memory locations dumped; current usage is ");
+        self.print_int(self.var_used);
+        self.print_char('&' as u8);
+        self.print_int(self.dyn_used);
+    }
+
+    // 1313
+    fn dump_eqtb(&mut self, fmt_file: &mut FmtWriter) {
+        // 1315: regions 1 to 4, compressed into runs of identical entries
+        let same = |s: &TexState, j: usize| {
+            s.equiv(j) == s.equiv(j + 1) && s.eq_type(j) == s.eq_type(j + 1) && s.eq_level(j) == s.eq_level(j + 1)
+        };
+        let mut k = ActiveBase as usize;
+        while k < IntBase as usize {
+            self.dump_eqtb_run(fmt_file, &mut k, IntBase as usize - 1, same);
+        }
+        // 1316: regions 5 and 6, compared as integers
+        let same = |s: &TexState, j: usize| s.eqtb[j].int() == s.eqtb[j + 1].int();
+        while k <= EqtbSize as usize {
+            self.dump_eqtb_run(fmt_file, &mut k, EqtbSize as usize, same);
+        }
+        fmt_file.dump_int(self.par_loc as i32);
+        // 1318: dump the hash table
+        fmt_file.dump_int(self.hash_used as i32);
+        self.cs_count = FrozenControlSequence as i32 - 1 - self.hash_used as i32;
+        for p in HashBase..=self.hash_used {
+            if self.hash[p as usize].text() != 0 {
+                fmt_file.dump_int(p as i32);
+                fmt_file.dump_hh(self.hash[p as usize]);
+                self.cs_count += 1;
+            }
+        }
+        for p in self.hash_used + 1..UndefinedControlSequence {
+            fmt_file.dump_hh(self.hash[p as usize]);
+        }
+        fmt_file.dump_int(self.cs_count);
+        self.print_ln();
+        self.print_int(self.cs_count);
+        self.print(" multiletter control sequences");
+    }
+
+    // dumps the entries from k up to the next run of entries equal to their
+    // predecessor, which is only counted; last is the final entry of the region
+    fn dump_eqtb_run(&mut self, fmt_file: &mut FmtWriter, k: &mut usize, last: usize,
+                     same: impl Fn(&TexState, usize) -> bool) {
+        let mut j = *k;
+        while j < last && !same(self, j) {
+            j += 1;
+        }
+        let l = if j < last {
+            // found a run starting at j
+            j += 1;
+            let l = j;
+            while j < last && same(self, j) {
+                j += 1;
+            }
+            l
+        } else {
+            last + 1
+        };
+        fmt_file.dump_int((l - *k) as i32);
+        while *k < l {
+            fmt_file.dump_wd(self.eqtb[*k]);
+            *k += 1;
+        }
+        *k = j + 1;
+        fmt_file.dump_int((*k - l) as i32);
+    }
+
+    // 1320
+    fn dump_font_info(&mut self, fmt_file: &mut FmtWriter) {
+        fmt_file.dump_int(self.fmem_ptr as i32);
+        for k in 0..self.fmem_ptr {
+            fmt_file.dump_wd(self.font_info[k]);
+        }
+        fmt_file.dump_int(self.font_ptr as i32);
+        for k in NullFont..=self.font_ptr {
+            // 1322: dump the array info for internal font number k
+            fmt_file.dump_qqqq(self.font_check[k]);
+            fmt_file.dump_int(self.font_size[k]);
+            fmt_file.dump_int(self.font_dsize[k]);
+            fmt_file.dump_int(self.font_params[k] as i32);
+            fmt_file.dump_int(self.hyphen_char[k]);
+            fmt_file.dump_int(self.skew_char[k]);
+            fmt_file.dump_int(self.font_name[k]);
+            fmt_file.dump_int(self.font_area[k]);
+            fmt_file.dump_int(self.font_bc[k] as i32);
+            fmt_file.dump_int(self.font_ec[k] as i32);
+            fmt_file.dump_int(self.char_base[k]);
+            fmt_file.dump_int(self.width_base[k]);
+            fmt_file.dump_int(self.height_base[k]);
+            fmt_file.dump_int(self.depth_base[k]);
+            fmt_file.dump_int(self.italic_base[k]);
+            fmt_file.dump_int(self.lig_kern_base[k]);
+            fmt_file.dump_int(self.kern_base[k]);
+            fmt_file.dump_int(self.exten_base[k]);
+            fmt_file.dump_int(self.param_base[k]);
+            fmt_file.dump_int(self.font_glue[k] as i32);
+            fmt_file.dump_int(self.bchar_label[k]);
+            fmt_file.dump_int(self.font_bchar[k] as i32);
+            fmt_file.dump_int(self.font_false_bchar[k] as i32);
+            self.print_nl("\\font");
+            self.print_esc_str(font_id_text(&self.hash, k as HalfWord) as StrNumber);
+            self.print_char('=' as u8);
+            self.print_file_name(self.font_name[k], self.font_area[k], EmptyString);
+            if self.font_size[k] != self.font_dsize[k] {
+                self.print(" at ");
+                self.print_scaled(self.font_size[k]);
+                self.print("pt");
+            }
+        }
+        self.print_ln();
+        self.print_int(self.fmem_ptr as i32 - 7);
+        self.print(" words of font info for ");
+        self.print_int(self.font_ptr as i32 - FontBase);
+        self.print(" preloaded font");
+        if self.font_ptr != FontBase as InternalFontNumber + 1 {
+            self.print_char('s' as u8);
+        }
+    }
+
+    // 1324
+    fn dump_hyphenation(&mut self, fmt_file: &mut FmtWriter) {
+        fmt_file.dump_int(self.hyph_count);
+        for k in 0..=HyphSize as usize {
+            if self.hyph_word[k] != 0 {
+                fmt_file.dump_int(k as i32);
+                fmt_file.dump_int(self.hyph_word[k]);
+                fmt_file.dump_int(self.hyph_list[k] as i32);
+            }
+        }
+        self.print_ln();
+        self.print_int(self.hyph_count);
+        self.print(" hyphenation exception");
+        if self.hyph_count != 1 {
+            self.print_char('s' as u8);
+        }
+        if self.trie_not_ready {
+            self.init_trie();
+        }
+        fmt_file.dump_int(self.trie_max as i32);
+        for k in 0..=self.trie_max {
+            fmt_file.dump_hh(self.trie[k]);
+        }
+        fmt_file.dump_int(self.trie_op_ptr);
+        for k in 1..=self.trie_op_ptr as usize {
+            fmt_file.dump_int(self.hyf_distance[k]);
+            fmt_file.dump_int(self.hyf_num[k]);
+            fmt_file.dump_int(self.hyf_next[k] as i32);
+        }
+        self.print_nl("Hyphenation trie of length ");
+        self.print_int(self.trie_max as i32);
+        self.print(" has ");
+        self.print_int(self.trie_op_ptr);
+        self.print(" op");
+        if self.trie_op_ptr != 1 {
+            self.print_char('s' as u8);
+        }
+        self.print(" out of ");
+        self.print_int(TrieOpSize);
+        for k in (0..=255).rev() {
+            if self.trie_used[k] > MinQuarterWord {
+                self.print_nl("  ");
+                self.print_int(self.trie_used[k] as i32);
+                self.print(" for language ");
+                self.print_int(k as i32);
+                fmt_file.dump_int(k as i32);
+                fmt_file.dump_int(self.trie_used[k] as i32);
+            }
+        }
+    }
+
+    // 1303
+    // reads a format file made by store_fmt_file, giving false if it is bad
+    pub fn load_fmt_file(&mut self, f: ByteFile) -> bool {
+        match FmtReader::open(f) {
+            Ok(mut fmt_file) => {
+                if self.undump_fmt(&mut fmt_file).is_some() {
+                    return true; // it worked!
+                }
+                if let Some(name) = fmt_file.too_small.take() {
+                    self.wterm_ln(&format!("---! Must increase the {}", name));
+                }
+            }
+            Err(e) => self.wterm_ln(&format!("---! {}", e)),
+        }
+        self.wterm_ln("(Fatal format file error; I'm stymied)");
+        false
+    }
+
+    fn undump_fmt(&mut self, fmt_file: &mut FmtReader) -> Option<()> {
+        // 1308: undump constants for consistency check
+        if fmt_file.undump_int()? != self.pool_check_sum()
+            || fmt_file.undump_int()? != MemBot as i32
+            || fmt_file.undump_int()? != MemTop as i32
+            || fmt_file.undump_int()? != EqtbSize as i32
+            || fmt_file.undump_int()? != HashPrime
+            || fmt_file.undump_int()? != HyphSize
+        {
+            return None;
+        }
+        self.undump_string_pool(fmt_file)?;
+        self.undump_dynamic_memory(fmt_file)?;
+        self.undump_eqtb(fmt_file)?;
+        self.undump_font_info(fmt_file)?;
+        self.undump_hyphenation(fmt_file)?;
+        // 1327: undump a couple more things and the closing check word
+        self.interaction = match fmt_file.undump(Interaction::BatchMode.value(), Interaction::ErrorStopMode.value())? {
+            0 => Interaction::BatchMode,
+            1 => Interaction::NonstopMode,
+            2 => Interaction::ScrollMode,
+            _ => Interaction::ErrorStopMode,
+        };
+        self.format_ident = fmt_file.undump(0, self.str_ptr)?;
+        if fmt_file.undump_int()? != 69069 || !fmt_file.at_eof() {
+            return None;
+        }
+        Some(())
+    }
+
+    // 1321
+    fn undump_font_info(&mut self, fmt_file: &mut FmtReader) -> Option<()> {
+        self.fmem_ptr = fmt_file.undump_size(7, FontMemSize, "font mem size")? as FontIndex;
+        for k in 0..self.fmem_ptr {
+            self.font_info[k] = fmt_file.undump_wd()?;
+        }
+        self.font_ptr = fmt_file.undump_size(FontBase, FontMax, "font max")? as InternalFontNumber;
+        for k in NullFont..=self.font_ptr {
+            // 1323: undump the array info for internal font number k
+            self.font_check[k] = fmt_file.undump_qqqq()?;
+            self.font_size[k] = fmt_file.undump_int()?;
+            self.font_dsize[k] = fmt_file.undump_int()?;
+            self.font_params[k] = fmt_file.undump(MinHalfWord as i32, MaxHalfWord as i32)? as FontIndex;
+            self.hyphen_char[k] = fmt_file.undump_int()?;
+            self.skew_char[k] = fmt_file.undump_int()?;
+            self.font_name[k] = fmt_file.undump(0, self.str_ptr)?;
+            self.font_area[k] = fmt_file.undump(0, self.str_ptr)?;
+            self.font_bc[k] = fmt_file.undump(0, 255)? as u8;
+            self.font_ec[k] = fmt_file.undump(0, 255)? as u8;
+            self.char_base[k] = fmt_file.undump_int()?;
+            self.width_base[k] = fmt_file.undump_int()?;
+            self.height_base[k] = fmt_file.undump_int()?;
+            self.depth_base[k] = fmt_file.undump_int()?;
+            self.italic_base[k] = fmt_file.undump_int()?;
+            self.lig_kern_base[k] = fmt_file.undump_int()?;
+            self.kern_base[k] = fmt_file.undump_int()?;
+            self.exten_base[k] = fmt_file.undump_int()?;
+            self.param_base[k] = fmt_file.undump_int()?;
+            self.font_glue[k] = fmt_file.undump(MinHalfWord as i32, self.lo_mem_max as i32)? as Pointer;
+            self.bchar_label[k] = fmt_file.undump(0, self.fmem_ptr as i32 - 1)?;
+            self.font_bchar[k] = fmt_file.undump(MinQuarterWord as i32, NonChar as i32)? as HalfWord;
+            self.font_false_bchar[k] = fmt_file.undump(MinQuarterWord as i32, NonChar as i32)? as HalfWord;
+        }
+        Some(())
+    }
+
+    // 1325
+    fn undump_hyphenation(&mut self, fmt_file: &mut FmtReader) -> Option<()> {
+        self.hyph_count = fmt_file.undump(0, HyphSize)?;
+        for _ in 1..=self.hyph_count {
+            let j = fmt_file.undump(0, HyphSize)? as usize;
+            self.hyph_word[j] = fmt_file.undump(0, self.str_ptr)?;
+            self.hyph_list[j] = fmt_file.undump(MinHalfWord as i32, MaxHalfWord as i32)? as Pointer;
+        }
+        let mut j = fmt_file.undump_size(0, TrieSize, "trie size")?;
+        self.trie_max = j as TriePointer;
+        for k in 0..=j as usize {
+            self.trie[k] = fmt_file.undump_hh()?;
+        }
+        j = fmt_file.undump_size(0, TrieOpSize, "trie op size")?;
+        self.trie_op_ptr = j;
+        for k in 1..=j as usize {
+            self.hyf_distance[k] = fmt_file.undump(0, 63)?; // a small_number
+            self.hyf_num[k] = fmt_file.undump(0, 63)?;
+            self.hyf_next[k] = fmt_file.undump(MinQuarterWord as i32, MaxQuarterWord as i32)? as QuarterWord;
+        }
+        for k in 0..=255 {
+            self.trie_used[k] = MinQuarterWord;
+        }
+        let mut k = 256;
+        while j > 0 {
+            k = fmt_file.undump(0, k - 1)?;
+            let x = fmt_file.undump(1, j)?;
+            self.trie_used[k as usize] = x as QuarterWord;
+            j -= x;
+            self.op_start[k as usize] = j;
+        }
+        self.trie_not_ready = false;
+        Some(())
+    }
+
+    // 1310
+    fn undump_string_pool(&mut self, fmt_file: &mut FmtReader) -> Option<()> {
+        self.pool_ptr = fmt_file.undump_size(0, PoolSize, "string pool size")? as PoolPointer;
+        self.str_ptr = fmt_file.undump_size(0, MaxStrings, "max strings")?;
+        for k in 0..=self.str_ptr as usize {
+            self.str_start[k] = fmt_file.undump(0, self.pool_ptr as i32)? as PoolPointer;
+        }
+        let mut k = 0;
+        while k + 4 < self.pool_ptr {
+            self.undump_four_ascii(fmt_file, k)?;
+            k += 4;
+        }
+        self.undump_four_ascii(fmt_file, self.pool_ptr - 4)?;
+        self.init_str_ptr = self.str_ptr;
+        self.init_pool_ptr = self.pool_ptr;
+        Some(())
+    }
+
+    fn undump_four_ascii(&mut self, fmt_file: &mut FmtReader, k: PoolPointer) -> Option<()> {
+        let w = fmt_file.undump_qqqq()?;
+        self.str_pool[k] = w.b0;
+        self.str_pool[k + 1] = w.b1;
+        self.str_pool[k + 2] = w.b2;
+        self.str_pool[k + 3] = w.b3;
+        Some(())
+    }
+
+    // 1312
+    // mem_min is mem_bot in this implementation, so there is no extra low
+    // memory to add to the list of empty nodes
+    fn undump_dynamic_memory(&mut self, fmt_file: &mut FmtReader) -> Option<()> {
+        self.lo_mem_max = fmt_file.undump(LoMemStatMax as i32 + 1000, HiMemStatMin as i32 - 1)? as HalfWord;
+        self.rover = fmt_file.undump(LoMemStatMax as i32 + 1, self.lo_mem_max as i32)? as Pointer;
+        let mut p = MemBot;
+        let mut q = self.rover as usize;
+        loop {
+            for k in p..=q + 1 {
+                self.mem[k] = fmt_file.undump_wd()?;
+            }
+            p = q + self.node_size(q) as usize;
+            let r = self.rlink(q) as usize;
+            if p > self.lo_mem_max as usize || (q >= r && r != self.rover as usize) {
+                return None;
+            }
+            q = r;
+            if q == self.rover as usize {
+                break;
+            }
+        }
+        for k in p..=self.lo_mem_max as usize {
+            self.mem[k] = fmt_file.undump_wd()?;
+        }
+        self.hi_mem_min = fmt_file.undump(self.lo_mem_max as i32 + 1, HiMemStatMin as i32)? as HalfWord;
+        self.avail = fmt_file.undump(Null as i32, MemTop as i32)? as Pointer;
+        self.mem_end = MemTop as Pointer;
+        for k in self.hi_mem_min as usize..=self.mem_end as usize {
+            self.mem[k] = fmt_file.undump_wd()?;
+        }
+        self.var_used = fmt_file.undump_int()?;
+        self.dyn_used = fmt_file.undump_int()?;
+        Some(())
+    }
+
+    // 1314
+    fn undump_eqtb(&mut self, fmt_file: &mut FmtReader) -> Option<()> {
+        // 1317: undump regions 1 to 6 of eqtb
+        let mut k = ActiveBase as usize;
+        loop {
+            let x = fmt_file.undump_int()?;
+            if x < 1 || k + x as usize > EqtbSize as usize + 1 {
+                return None;
+            }
+            for j in k..k + x as usize {
+                self.eqtb[j] = fmt_file.undump_wd()?;
+            }
+            k += x as usize;
+            let x = fmt_file.undump_int()?;
+            if x < 0 || k + x as usize > EqtbSize as usize + 1 {
+                return None;
+            }
+            for j in k..k + x as usize {
+                self.eqtb[j] = self.eqtb[k - 1];
+            }
+            k += x as usize;
+            if k > EqtbSize as usize {
+                break;
+            }
+        }
+        self.par_loc = fmt_file.undump(HashBase as i32, FrozenControlSequence as i32)? as Pointer;
+        self.par_token = cs_token_flag as HalfWord + self.par_loc;
+        // 1319: undump the hash table
+        self.hash_used = fmt_file.undump(HashBase as i32, FrozenControlSequence as i32)? as Pointer;
+        let mut p = HashBase as i32 - 1;
+        loop {
+            p = fmt_file.undump(p + 1, self.hash_used as i32)?;
+            self.hash[p as usize] = fmt_file.undump_hh()?;
+            if p == self.hash_used as i32 {
+                break;
+            }
+        }
+        for p in self.hash_used + 1..UndefinedControlSequence {
+            self.hash[p as usize] = fmt_file.undump_hh()?;
+        }
+        self.cs_count = fmt_file.undump_int()?;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{typeset_to, OutputFormat, TerminalOutput, Vfs};
+
+    // a format in which \a is a macro and \count1 is 7
+    fn format() -> Vec<u8> {
+        let vfs = Vfs::new();
+        vfs.add_file("fmt.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\def\\a{macro}\\count1=7 \\dump\n");
+        typeset_to(&vfs, "fmt", OutputFormat::Dvi).remove("fmt.fmt").unwrap()
+    }
+
+    // what the terminal shows when a document is typeset with format fmt
+    fn terminal_with(fmt: Vec<u8>) -> String {
+        let vfs = Vfs::new();
+        vfs.add_file("fmt.fmt", fmt);
+        vfs.add_file("doc.tex", "\\show\\a \\showthe\\count1 \\end\n");
+        let outputs = typeset_to(&vfs, "&fmt doc", OutputFormat::Dvi);
+        String::from_utf8_lossy(&outputs[TerminalOutput]).into_owned()
+    }
+
+    #[test]
+    fn formats_are_loaded_as_they_were_dumped() {
+        let terminal = terminal_with(format());
+        assert!(terminal.contains("> \\a=macro:\n->macro.\n"), "{}", terminal);
+        assert!(terminal.contains("> 7.\n"), "{}", terminal);
+    }
+
+    #[test]
+    fn damaged_formats_are_refused() {
+        let fmt = format();
+        let engine_len = u32::from_le_bytes([fmt[12], fmt[13], fmt[14], fmt[15]]) as usize;
+        let sizes = 16 + engine_len + 4; // where the first of the sizes is
+        let hash = sizes + 13 * 8;
+        for &(k, message) in &[
+            (8, "the format file has layout version 2, but this TeX needs version 1"),
+            (16, "the format was made by SusTeX"),
+            (sizes, "the format was made with mem_bot=3, but this TeX has mem_bot=0"),
+            (hash, "the format file is damaged (its contents don't match their hash)"),
+        ] {
+            let mut bad = fmt.clone();
+            bad[k] ^= if k == 16 { b'R' ^ b'S' } else { 3 };
+            let terminal = terminal_with(bad);
+            assert!(terminal.contains(&format!("---! {}", message)), "{}", terminal);
+            assert!(terminal.contains("(Fatal format file error; I'm stymied)"), "{}", terminal);
+        }
+    }
+}
@@ -0,0 +1,975 @@
+use crate::*;
+
+// 586
+// DVI command codes
+pub const SetChar0: u8 = 0; // typeset character 0 and move right
+pub const Set1: u8 = 128; // typeset a character and move right
+pub const SetRule: u8 = 132; // typeset a rule and move right
+pub const Put1: u8 = 133; // typeset a character without moving
+pub const PutRule: u8 = 137; // typeset a rule
+pub const Nop: u8 = 138; // no operation
+pub const Bop: u8 = 139; // beginning of page
+pub const Eop: u8 = 140; // ending of page
+pub const Push: u8 = 141; // save the current positions
+pub const Pop: u8 = 142; // restore previous positions
+pub const Right1: u8 = 143; // move right
+pub const W0: u8 = 147; // move right by w
+pub const W1: u8 = 148; // move right and set w
+pub const X0: u8 = 152; // move right by x
+pub const X1: u8 = 153; // move right and set x
+pub const Down1: u8 = 157; // move down
+pub const Y0: u8 = 161; // move down by y
+pub const Y1: u8 = 162; // move down and set y
+pub const Z0: u8 = 166; // move down by z
+pub const Z1: u8 = 167; // move down and set z
+pub const FntNum0: u8 = 171; // set current font to 0
+pub const Fnt1: u8 = 235; // set current font
+pub const Xxx1: u8 = 239; // extension to DVI primitives
+pub const Xxx4: u8 = 242; // potentially long extension to DVI primitives
+pub const FntDef1: u8 = 243; // define the meaning of a font number
+pub const Pre: u8 = 247; // preamble
+pub const Post: u8 = 248; // postamble beginning
+pub const PostPost: u8 = 249; // postamble ending
+
+// 587
+pub const IdByte: u8 = 2; // identifies the kind of DVI files described here
+
+// 605
+const MovementNodeSize: usize = 3; // number of words per entry in the down and right stacks
+
+// 608
+// the info field of a movement node says what it could become
+const YHere: HalfWord = 1; // info when the movement entry points to a y command
+const ZHere: HalfWord = 2; // info when the movement entry points to a z command
+const YzOK: HalfWord = 3; // info corresponding to an unconstrained down command
+const YOK: HalfWord = 4; // info corresponding to a down that can't become a z
+const ZOK: HalfWord = 5; // info corresponding to a down that can't become a y
+const DFixed: HalfWord = 6; // info corresponding to a down that can't change
+
+// 611
+const NoneSeen: HalfWord = 0; // no y_here or z_here nodes have been encountered yet
+const YSeen: HalfWord = 6; // we have seen y_here but not z_here
+const ZSeen: HalfWord = 12; // we have seen z_here but not y_here
+
+impl TexState {
+    // 605
+    // the DVI byte number of a movement command
+    fn location(&self, p: usize) -> i32 {
+        self.mem[p + 2].int()
+    }
+
+    fn set_location(&mut self, p: usize, value: i32) {
+        self.mem[p + 2].set_int(value);
+    }
+
+    // 597
+    // writes dvi_buf[a..=b] to the DVI file
+    fn write_dvi(&mut self, a: usize, b: usize) {
+        if let Some(f) = self.dvi_file.as_mut() {
+            if f.write_all(&self.dvi_buf.data[a..=b]).is_err() {
+                self.dvi_file = None;
+            }
+        }
+        if self.dvi_file.is_none() {
+            self.fatal_error("*** (cannot write on the DVI file)");
+        }
+    }
+
+    // 598
+    pub fn dvi_out(&mut self, c: u8) {
+        self.dvi_buf[self.dvi_ptr] = c;
+        self.dvi_ptr += 1;
+        if self.dvi_ptr == self.dvi_limit {
+            self.dvi_swap();
+        }
+    }
+
+    // empties half of the buffer when it is full
+    fn dvi_swap(&mut self) {
+        if self.dvi_limit == DviBufSize as usize {
+            self.write_dvi(0, self.half_buf - 1);
+            self.dvi_limit = self.half_buf;
+            self.dvi_offset += DviBufSize;
+            self.dvi_ptr = 0;
+        } else {
+            self.write_dvi(self.half_buf, DviBufSize as usize - 1);
+            self.dvi_limit = DviBufSize as usize;
+        }
+        self.dvi_gone += self.half_buf as i32;
+    }
+
+    // 600
+    // outputs four bytes in two's complement notation, without overflow
+    pub fn dvi_four(&mut self, x: i32) {
+        for b in x.to_be_bytes().iter() {
+            self.dvi_out(*b);
+        }
+    }
+
+    // 601
+    // a pop that cancels a push at byte l, if the push is still in the buffer
+    fn dvi_pop(&mut self, l: i32) {
+        if l == self.dvi_offset + self.dvi_ptr as i32 && self.dvi_ptr > 0 {
+            self.dvi_ptr -= 1;
+        } else {
+            self.dvi_out(Pop);
+        }
+    }
+
+    // 602
+    fn dvi_font_def(&mut self, f: InternalFontNumber) {
+        self.dvi_out(FntDef1);
+        self.dvi_out((f - NullFont - 1) as u8);
+        let fc = self.font_check[f];
+        self.dvi_out(fc.b0);
+        self.dvi_out(fc.b1);
+        self.dvi_out(fc.b2);
+        self.dvi_out(fc.b3);
+        self.dvi_four(self.font_size[f]);
+        self.dvi_four(self.font_dsize[f]);
+        self.dvi_out(self.length(self.font_area[f]) as u8);
+        self.dvi_out(self.length(self.font_name[f]) as u8);
+        // 603: output the font name whose internal number is f
+        for c in self.str_bytes(self.font_area[f]).into_iter().chain(self.str_bytes(self.font_name[f])) {
+            self.dvi_out(c);
+        }
+    }
+
+    // 607
+    // produces a DVI command for some specified downward or rightward motion w;
+    // o is down1 or right1, and earlier movements may be turned into w, x, y or z
+    // commands so that this one can be shorter
+    fn movement(&mut self, w: Scaled, o: u8) {
+        let mut q = self.get_node(MovementNodeSize); // new node for the top of the stack
+        self.set_width(q as usize, w);
+        self.set_location(q as usize, self.dvi_offset + self.dvi_ptr as i32);
+        if o == Down1 {
+            self.set_link(q as usize, self.down_ptr);
+            self.down_ptr = q;
+        } else {
+            self.set_link(q as usize, self.right_ptr);
+            self.right_ptr = q;
+        }
+        // 611: look at the other stack entries until deciding what sort of DVI command to generate
+        let mut p = self.link(q as usize);
+        let mut mstate = NoneSeen;
+        let mut found = false;
+        while p != Null {
+            if self.width(p as usize) == w {
+                // 612: consider a node with matching width
+                match mstate + self.info(p as usize) {
+                    s if s == NoneSeen + YzOK || s == NoneSeen + YOK || s == ZSeen + YzOK || s == ZSeen + YOK => {
+                        if self.location(p as usize) < self.dvi_gone {
+                            break;
+                        }
+                        // 613: change buffered instruction to y or w
+                        self.change_buffered_movement(p, Y1 - Down1);
+                        self.set_info(p as usize, YHere);
+                        found = true;
+                        break;
+                    }
+                    s if s == NoneSeen + ZOK || s == YSeen + YzOK || s == YSeen + ZOK => {
+                        if self.location(p as usize) < self.dvi_gone {
+                            break;
+                        }
+                        // 614: change buffered instruction to z or x
+                        self.change_buffered_movement(p, Z1 - Down1);
+                        self.set_info(p as usize, ZHere);
+                        found = true;
+                        break;
+                    }
+                    s if s == NoneSeen + YHere || s == NoneSeen + ZHere || s == YSeen + ZHere || s == ZSeen + YHere => {
+                        found = true;
+                        break;
+                    }
+                    _ => {}
+                }
+            } else {
+                match mstate + self.info(p as usize) {
+                    s if s == NoneSeen + YHere => mstate = YSeen,
+                    s if s == NoneSeen + ZHere => mstate = ZSeen,
+                    s if s == YSeen + ZHere || s == ZSeen + YHere => break,
+                    _ => {}
+                }
+            }
+            p = self.link(p as usize);
+        }
+        if found {
+            // 609: generate a y0 or z0 command in order to reuse a previous appearance of w
+            self.set_info(q as usize, self.info(p as usize));
+            let y = self.info(q as usize) == YHere;
+            self.dvi_out(if y { o + (Y0 - Down1) } else { o + (Z0 - Down1) }); // y0 or w0, z0 or x0
+            let (unconstrained, fixed) = if y { (ZOK, YOK) } else { (YOK, ZOK) };
+            while self.link(q as usize) != p {
+                q = self.link(q as usize);
+                match self.info(q as usize) {
+                    YzOK => self.set_info(q as usize, unconstrained),
+                    i if i == fixed => self.set_info(q as usize, DFixed),
+                    _ => {}
+                }
+            }
+            return;
+        }
+        // 610: generate a down or right command for w
+        self.set_info(q as usize, YzOK);
+        if w.abs() >= 0o40000000 {
+            self.dvi_out(o + 3); // down4 or right4
+            self.dvi_four(w);
+        } else if w.abs() >= 0o100000 {
+            self.dvi_out(o + 2); // down3 or right3
+            let b = w.to_be_bytes();
+            self.dvi_out(b[1]);
+            self.dvi_out(b[2]);
+            self.dvi_out(b[3]);
+        } else if w.abs() >= 0o200 {
+            self.dvi_out(o + 1); // down2 or right2
+            let b = w.to_be_bytes();
+            self.dvi_out(b[2]);
+            self.dvi_out(b[3]);
+        } else {
+            self.dvi_out(o); // down1 or right1
+            self.dvi_out(w.to_be_bytes()[3]);
+        }
+    }
+
+    // turns the down or right command of movement node p, still in the buffer,
+    // into the y or w command (d=y1-down1) or the z or x command (d=z1-down1)
+    fn change_buffered_movement(&mut self, p: Pointer, d: u8) {
+        let mut k = self.location(p as usize) - self.dvi_offset;
+        if k < 0 {
+            k += DviBufSize;
+        }
+        self.dvi_buf[k as usize] += d;
+    }
+
+    // 615
+    // deletes movement nodes with location>=l
+    fn prune_movements(&mut self, l: i32) {
+        while self.down_ptr != Null {
+            if self.location(self.down_ptr as usize) < l {
+                break;
+            }
+            let p = self.down_ptr;
+            self.down_ptr = self.link(p as usize);
+            self.free_node(p, MovementNodeSize);
+        }
+        while self.right_ptr != Null {
+            if self.location(self.right_ptr as usize) < l {
+                return;
+            }
+            let p = self.right_ptr;
+            self.right_ptr = self.link(p as usize);
+            self.free_node(p, MovementNodeSize);
+        }
+    }
+
+    // 616
+    // when the marks are being collected there is no DVI position to move
+    fn synch_h(&mut self) {
+        if self.cur_h != self.dvi_h {
+            if self.page_marks.is_none() {
+                self.movement(self.cur_h - self.dvi_h, Right1);
+            }
+            self.dvi_h = self.cur_h;
+        }
+    }
+
+    fn synch_v(&mut self) {
+        if self.cur_v != self.dvi_v {
+            if self.page_marks.is_none() {
+                self.movement(self.cur_v - self.dvi_v, Down1);
+            }
+            self.dvi_v = self.cur_v;
+        }
+    }
+
+    // 619
+    // starts the output of a box, pushing the DVI position unless this is the
+    // outermost box; gives the DVI byte location upon entry
+    fn begin_box_out(&mut self) -> i32 {
+        if self.page_marks.is_some() {
+            return 0;
+        }
+        self.cur_s += 1;
+        if self.cur_s > 0 {
+            self.dvi_out(Push);
+        }
+        if self.cur_s > self.max_push {
+            self.max_push = self.cur_s;
+        }
+        self.dvi_offset + self.dvi_ptr as i32
+    }
+
+    // finishes the output of a box begun at DVI byte save_loc
+    fn end_box_out(&mut self, save_loc: i32) {
+        if self.page_marks.is_some() {
+            return;
+        }
+        self.prune_movements(save_loc);
+        if self.cur_s > 0 {
+            self.dvi_pop(save_loc);
+        }
+        self.cur_s -= 1;
+    }
+
+    // 621
+    // change font dvi_f to f
+    fn dvi_change_font(&mut self, f: InternalFontNumber) {
+        if !self.font_used[f] {
+            self.dvi_font_def(f);
+            self.font_used[f] = true;
+        }
+        if f <= 64 + NullFont {
+            self.dvi_out((f - NullFont - 1) as u8 + FntNum0);
+        } else {
+            self.dvi_out(Fnt1);
+            self.dvi_out((f - NullFont - 1) as u8);
+        }
+        self.dvi_f = f;
+    }
+
+    // typesets character c of a real font at the current position, moving right if advance
+    fn char_out(&mut self, f: InternalFontNumber, c: u8, advance: bool) {
+        self.synch_h();
+        self.synch_v();
+        match self.page_marks.as_mut() {
+            Some(marks) => marks.push(PageItem::Char { f, c, h: self.cur_h, v: self.cur_v }),
+            None => {
+                if f != self.dvi_f {
+                    self.dvi_change_font(f);
+                }
+                if advance {
+                    if c >= 128 {
+                        self.dvi_out(Set1);
+                    }
+                } else {
+                    self.dvi_out(Put1);
+                }
+                self.dvi_out(c);
+            }
+        }
+        if advance {
+            self.cur_h += self.char_width(f, self.char_info(f, c));
+            self.dvi_h = self.cur_h;
+        }
+    }
+
+    // typesets a rule of the given height and width with its bottom left corner
+    // at the current position, moving right past it if advance
+    fn rule_out(&mut self, height: Scaled, width: Scaled, advance: bool) {
+        self.synch_h();
+        self.synch_v();
+        match self.page_marks.as_mut() {
+            Some(marks) => marks.push(PageItem::Rule { h: self.cur_h, v: self.cur_v, width, height }),
+            None => {
+                self.dvi_out(if advance { SetRule } else { PutRule });
+                self.dvi_four(height);
+                self.dvi_four(width);
+            }
+        }
+        if advance {
+            self.dvi_h += width;
+        }
+    }
+
+    // outputs the text of a \special at the current position
+    fn special_out(&mut self, text: Vec<u8>) {
+        self.synch_h();
+        self.synch_v();
+        match self.page_marks.as_mut() {
+            Some(marks) => marks.push(PageItem::Special { h: self.cur_h, v: self.cur_v, text }),
+            None => self.dvi_special(&text),
+        }
+    }
+
+    // plays back the packet of a character of a virtual font at the current position
+    fn vf_out(&mut self, cmds: Vec<VfCommand>) {
+        let mut stack = Vec::new();
+        for cmd in cmds {
+            match cmd {
+                VfCommand::Char { f, c, advance } => self.char_out(f, c, advance),
+                VfCommand::Rule { height, width, advance } => {
+                    if height > 0 && width > 0 {
+                        self.rule_out(height, width, advance);
+                    }
+                    if advance {
+                        self.cur_h += width;
+                    }
+                }
+                VfCommand::Push => stack.push((self.cur_h, self.cur_v)),
+                VfCommand::Pop => {
+                    if let Some((h, v)) = stack.pop() {
+                        self.cur_h = h;
+                        self.cur_v = v;
+                    }
+                }
+                VfCommand::Right(d) => self.cur_h += d,
+                VfCommand::Down(d) => self.cur_v += d,
+                VfCommand::Special(text) => self.special_out(text),
+            }
+        }
+    }
+
+    // 1368
+    // outputs an xxx command with the text s
+    fn dvi_special(&mut self, s: &[u8]) {
+        if s.len() < 256 {
+            self.dvi_out(Xxx1);
+            self.dvi_out(s.len() as u8);
+        } else {
+            self.dvi_out(Xxx4);
+            self.dvi_four(s.len() as i32);
+        }
+        for b in s {
+            self.dvi_out(*b);
+        }
+    }
+
+    // 1366
+    // output the whatsit node p in an hlist or vlist
+    fn out_what(&mut self, p: usize) {
+        match self.subtype(p) {
+            OpenNode | WriteNode | CloseNode => self.out_write_whatsit(p),
+            SpecialNode => {
+                let text = self.special_text(p);
+                self.special_out(text);
+            }
+            LanguageNode => {}
+            _ => self.confusion("ext4"),
+        }
+    }
+
+    // 619
+    // output an hlist_node box
+    pub fn hlist_out(&mut self, this_box: Pointer) {
+        let mut cur_g: Scaled = 0; // rounded equivalent of cur_glue times the glue ratio
+        let mut cur_glue: Scaled = 0; // glue seen so far
+        let g_order = self.glue_order(this_box as usize) as GlueOrd;
+        let g_sign = self.glue_sign(this_box as usize) as i32;
+        let mut p = self.list_ptr(this_box as usize);
+        let save_loc = self.begin_box_out(); // DVI byte location upon entry
+        let base_line = self.cur_v; // the baseline coordinate for this box
+        let left_edge = self.cur_h; // the left coordinate for this box
+        while p != Null {
+            // 620: output node p for hlist_out and move to the next node
+            if self.is_char_node(p as usize) {
+                self.synch_h();
+                self.synch_v();
+                loop {
+                    let f = self.font(p as usize) as InternalFontNumber;
+                    let c = self.character(p as usize);
+                    self.hlist_char_out(f, c);
+                    p = self.link(p as usize);
+                    if !self.is_char_node(p as usize) {
+                        break;
+                    }
+                }
+                continue;
+            }
+            // 622: output the non-char_node p for hlist_out
+            let mut rule_ht = 0;
+            let mut rule_dp = 0;
+            let mut rule_wd = 0;
+            let mut fin_rule = false;
+            match self.a_type(p as usize) {
+                HlistNode | VlistNode => {
+                    // 623: output a box in an hlist
+                    if self.list_ptr(p as usize) == Null {
+                        self.cur_h += self.width(p as usize);
+                    } else {
+                        let save_h = self.dvi_h;
+                        let save_v = self.dvi_v;
+                        self.cur_v = base_line + self.shift_amount(p as usize);
+                        let edge = self.cur_h;
+                        if self.a_type(p as usize) == VlistNode {
+                            self.vlist_out(p);
+                        } else {
+                            self.hlist_out(p);
+                        }
+                        self.dvi_h = save_h;
+                        self.dvi_v = save_v;
+                        self.cur_h = edge + self.width(p as usize);
+                        self.cur_v = base_line;
+                    }
+                }
+                RuleNode => {
+                    rule_ht = self.height(p as usize);
+                    rule_dp = self.depth(p as usize);
+                    rule_wd = self.width(p as usize);
+                    fin_rule = true;
+                }
+                WhatsitNode => self.out_what(p as usize), // 1367
+                GlueNode => {
+                    // 625: move right or output leaders
+                    let g = self.glue_ptr(p as usize) as usize;
+                    rule_wd = self.width(g) - cur_g;
+                    if g_sign != Normal {
+                        if g_sign == Stretching {
+                            if self.stretch_order(g) as GlueOrd == g_order {
+                                cur_glue += self.stretch(g);
+                                cur_g = self.glue_set(this_box as usize).set_glue(cur_glue);
+                            }
+                        } else if self.shrink_order(g) as GlueOrd == g_order {
+                            cur_glue -= self.shrink(g);
+                            cur_g = self.glue_set(this_box as usize).set_glue(cur_glue);
+                        }
+                    }
+                    rule_wd += cur_g;
+                    if self.subtype(p as usize) >= ALeaders {
+                        // 626: output leaders in an hlist, goto fin_rule if a rule or to next_p if done
+                        let leader_box = self.leader_ptr(p as usize);
+                        if self.a_type(leader_box as usize) == RuleNode {
+                            rule_ht = self.height(leader_box as usize);
+                            rule_dp = self.depth(leader_box as usize);
+                            fin_rule = true;
+                        } else {
+                            let leader_wd = self.width(leader_box as usize);
+                            if leader_wd > 0 && rule_wd > 0 {
+                                rule_wd += 10; // compensate for floating-point rounding
+                                let edge = self.cur_h + rule_wd;
+                                let mut lx = 0;
+                                // 627: let cur_h be the position of the first box
+                                if self.subtype(p as usize) == ALeaders {
+                                    let save_h = self.cur_h;
+                                    self.cur_h = left_edge + leader_wd * ((self.cur_h - left_edge) / leader_wd);
+                                    if self.cur_h < save_h {
+                                        self.cur_h += leader_wd;
+                                    }
+                                } else {
+                                    let lq = rule_wd / leader_wd; // the number of box copies
+                                    let lr = rule_wd % leader_wd; // the remaining space
+                                    if self.subtype(p as usize) == CLeaders {
+                                        self.cur_h += lr / 2;
+                                    } else {
+                                        lx = lr / (lq + 1);
+                                        self.cur_h += (lr - (lq - 1) * lx) / 2;
+                                    }
+                                }
+                                while self.cur_h + leader_wd <= edge {
+                                    // 628: output a leader box at cur_h, then advance cur_h by leader_wd+lx
+                                    self.cur_v = base_line + self.shift_amount(leader_box as usize);
+                                    self.synch_v();
+                                    let save_v = self.dvi_v;
+                                    self.synch_h();
+                                    let save_h = self.dvi_h;
+                                    let outer_doing_leaders = self.doing_leaders;
+                                    self.doing_leaders = true;
+                                    if self.a_type(leader_box as usize) == VlistNode {
+                                        self.vlist_out(leader_box);
+                                    } else {
+                                        self.hlist_out(leader_box);
+                                    }
+                                    self.doing_leaders = outer_doing_leaders;
+                                    self.dvi_v = save_v;
+                                    self.dvi_h = save_h;
+                                    self.cur_v = base_line;
+                                    self.cur_h = save_h + leader_wd + lx;
+                                }
+                                self.cur_h = edge - 10;
+                                p = self.link(p as usize);
+                                continue;
+                            }
+                        }
+                    }
+                    if !fin_rule {
+                        self.cur_h += rule_wd; // move_past
+                    }
+                }
+                KernNode | MathNode => self.cur_h += self.width(p as usize),
+                LigatureNode => {
+                    // 652: make node p look like a char_node
+                    let q = TexState::lig_char(p as usize);
+                    let f = self.font(q) as InternalFontNumber;
+                    let c = self.character(q);
+                    self.synch_h();
+                    self.synch_v();
+                    self.hlist_char_out(f, c);
+                }
+                _ => {}
+            }
+            if fin_rule {
+                // 624: output a rule in an hlist
+                if TexState::is_running(rule_ht) {
+                    rule_ht = self.height(this_box as usize);
+                }
+                if TexState::is_running(rule_dp) {
+                    rule_dp = self.depth(this_box as usize);
+                }
+                rule_ht += rule_dp; // this is the rule thickness
+                if rule_ht > 0 && rule_wd > 0 {
+                    // we don't output empty rules
+                    self.cur_v = base_line + rule_dp;
+                    self.rule_out(rule_ht, rule_wd, true);
+                    self.cur_v = base_line;
+                }
+                self.cur_h += rule_wd; // move_past
+            }
+            p = self.link(p as usize);
+        }
+        self.end_box_out(save_loc);
+    }
+
+    // typesets a character of an hlist at cur_h, which the DVI position has caught up
+    // with, and moves past it; virtual fonts are replaced by the packets of their characters
+    fn hlist_char_out(&mut self, f: InternalFontNumber, c: u8) {
+        match self.vf_packet(f, c) {
+            Some(cmds) => {
+                self.vf_out(cmds);
+                self.cur_h += self.char_width(f, self.char_info(f, c));
+            }
+            None => self.char_out(f, c, true),
+        }
+    }
+
+    // 629
+    // output a vlist_node box
+    pub fn vlist_out(&mut self, this_box: Pointer) {
+        let mut cur_g: Scaled = 0; // rounded equivalent of cur_glue times the glue ratio
+        let mut cur_glue: Scaled = 0; // glue seen so far
+        let g_order = self.glue_order(this_box as usize) as GlueOrd;
+        let g_sign = self.glue_sign(this_box as usize) as i32;
+        let mut p = self.list_ptr(this_box as usize);
+        let save_loc = self.begin_box_out(); // DVI byte location upon entry
+        let left_edge = self.cur_h; // the left coordinate for this box
+        self.cur_v -= self.height(this_box as usize);
+        let top_edge = self.cur_v; // the top coordinate for this box
+        while p != Null {
+            // 630: output node p for vlist_out and move to the next node
+            if self.is_char_node(p as usize) {
+                self.confusion("vlistout");
+            }
+            // 631: output the non-char_node p for vlist_out
+            let mut rule_ht = 0;
+            let mut rule_dp = 0;
+            let mut rule_wd = 0;
+            let mut fin_rule = false;
+            match self.a_type(p as usize) {
+                HlistNode | VlistNode => {
+                    // 632: output a box in a vlist
+                    if self.list_ptr(p as usize) == Null {
+                        self.cur_v += self.height(p as usize) + self.depth(p as usize);
+                    } else {
+                        self.cur_v += self.height(p as usize);
+                        self.synch_v();
+                        let save_h = self.dvi_h;
+                        let save_v = self.dvi_v;
+                        self.cur_h = left_edge + self.shift_amount(p as usize);
+                        if self.a_type(p as usize) == VlistNode {
+                            self.vlist_out(p);
+                        } else {
+                            self.hlist_out(p);
+                        }
+                        self.dvi_h = save_h;
+                        self.dvi_v = save_v;
+                        self.cur_v = save_v + self.depth(p as usize);
+                        self.cur_h = left_edge;
+                    }
+                }
+                RuleNode => {
+                    rule_ht = self.height(p as usize);
+                    rule_dp = self.depth(p as usize);
+                    rule_wd = self.width(p as usize);
+                    fin_rule = true;
+                }
+                WhatsitNode => self.out_what(p as usize), // 1366
+                GlueNode => {
+                    // 634: move down or output leaders
+                    let g = self.glue_ptr(p as usize) as usize;
+                    rule_ht = self.width(g) - cur_g;
+                    if g_sign != Normal {
+                        if g_sign == Stretching {
+                            if self.stretch_order(g) as GlueOrd == g_order {
+                                cur_glue += self.stretch(g);
+                                cur_g = self.glue_set(this_box as usize).set_glue(cur_glue);
+                            }
+                        } else if self.shrink_order(g) as GlueOrd == g_order {
+                            cur_glue -= self.shrink(g);
+                            cur_g = self.glue_set(this_box as usize).set_glue(cur_glue);
+                        }
+                    }
+                    rule_ht += cur_g;
+                    if self.subtype(p as usize) >= ALeaders {
+                        // 635: output leaders in a vlist, goto fin_rule if a rule or to next_p if done
+                        let leader_box = self.leader_ptr(p as usize);
+                        if self.a_type(leader_box as usize) == RuleNode {
+                            rule_wd = self.width(leader_box as usize);
+                            rule_dp = 0;
+                            fin_rule = true;
+                        } else {
+                            let leader_ht = self.height(leader_box as usize) + self.depth(leader_box as usize);
+                            if leader_ht > 0 && rule_ht > 0 {
+                                rule_ht += 10; // compensate for floating-point rounding
+                                let edge = self.cur_v + rule_ht;
+                                let mut lx = 0;
+                                // 636: let cur_v be the position of the first box
+                                if self.subtype(p as usize) == ALeaders {
+                                    let save_v = self.cur_v;
+                                    self.cur_v = top_edge + leader_ht * ((self.cur_v - top_edge) / leader_ht);
+                                    if self.cur_v < save_v {
+                                        self.cur_v += leader_ht;
+                                    }
+                                } else {
+                                    let lq = rule_ht / leader_ht; // the number of box copies
+                                    let lr = rule_ht % leader_ht; // the remaining space
+                                    if self.subtype(p as usize) == CLeaders {
+                                        self.cur_v += lr / 2;
+                                    } else {
+                                        lx = lr / (lq + 1);
+                                        self.cur_v += (lr - (lq - 1) * lx) / 2;
+                                    }
+                                }
+                                while self.cur_v + leader_ht <= edge {
+                                    // 637: output a leader box at cur_v, then advance cur_v by leader_ht+lx
+                                    self.cur_h = left_edge + self.shift_amount(leader_box as usize);
+                                    self.synch_h();
+                                    let save_h = self.dvi_h;
+                                    self.cur_v += self.height(leader_box as usize);
+                                    self.synch_v();
+                                    let save_v = self.dvi_v;
+                                    let outer_doing_leaders = self.doing_leaders;
+                                    self.doing_leaders = true;
+                                    if self.a_type(leader_box as usize) == VlistNode {
+                                        self.vlist_out(leader_box);
+                                    } else {
+                                        self.hlist_out(leader_box);
+                                    }
+                                    self.doing_leaders = outer_doing_leaders;
+                                    self.dvi_v = save_v;
+                                    self.dvi_h = save_h;
+                                    self.cur_h = left_edge;
+                                    self.cur_v = save_v - self.height(leader_box as usize) + leader_ht + lx;
+                                }
+                                self.cur_v = edge - 10;
+                                p = self.link(p as usize);
+                                continue;
+                            }
+                        }
+                    }
+                    if !fin_rule {
+                        self.cur_v += rule_ht; // move_past
+                    }
+                }
+                KernNode => self.cur_v += self.width(p as usize),
+                _ => {}
+            }
+            if fin_rule {
+                // 633: output a rule in a vlist
+                if TexState::is_running(rule_wd) {
+                    rule_wd = self.width(this_box as usize);
+                }
+                rule_ht += rule_dp; // this is the rule thickness
+                self.cur_v += rule_ht;
+                if rule_ht > 0 && rule_wd > 0 {
+                    // we don't output empty rules
+                    self.rule_out(rule_ht, rule_wd, false);
+                }
+            }
+            p = self.link(p as usize);
+        }
+        self.end_box_out(save_loc);
+    }
+
+    // 638
+    // output the box p
+    pub fn ship_out(&mut self, p: Pointer) {
+        if self.int_par(TracingOutputCode) > 0 {
+            self.print_nl("");
+            self.print_ln();
+            self.print("Completed box being shipped out");
+        }
+        if self.term_offset > MaxPrintLine - 9 {
+            self.print_ln();
+        } else if self.term_offset > 0 || self.file_offset > 0 {
+            self.print_char(' ' as u8);
+        }
+        self.print_char('[' as u8);
+        let mut j = 9;
+        while self.count(j) == 0 && j > 0 {
+            j -= 1;
+        }
+        for k in 0..=j {
+            self.print_int(self.count(k));
+            if k < j {
+                self.print_char('.' as u8);
+            }
+        }
+        self.update_terminal();
+        if self.int_par(TracingOutputCode) > 0 {
+            self.print_char(']' as u8);
+            self.begin_diagnostic();
+            self.show_box(p);
+            self.end_diagnostic(true);
+        }
+        self.ship_box_out(p);
+        if self.int_par(TracingOutputCode) <= 0 {
+            self.print_char(']' as u8);
+        }
+        self.dead_cycles = 0;
+        self.update_terminal(); // progress report
+        // 639: flush the box from memory, showing statistics if requested
+        if self.int_par(TracingStatsCode) > 1 {
+            self.print_nl("Memory usage before: ");
+            self.print_int(self.var_used);
+            self.print_char('&' as u8);
+            self.print_int(self.dyn_used);
+            self.print_char(';' as u8);
+        }
+        self.flush_node_list(p);
+        if self.int_par(TracingStatsCode) > 1 {
+            self.print(" after: ");
+            self.print_int(self.var_used);
+            self.print_char('&' as u8);
+            self.print_int(self.dyn_used);
+            self.print("; still untouched: ");
+            self.print_int(self.hi_mem_min as i32 - self.lo_mem_max as i32 - 1);
+            self.print_ln();
+        }
+    }
+
+    // 640
+    fn ship_box_out(&mut self, p: Pointer) {
+        // 641: update the values of max_h and max_v; but if the page is too large, return
+        let h_offset = self.dimen_par(HOffsetCode);
+        let v_offset = self.dimen_par(VOffsetCode);
+        let (ht, dp, wd) = (self.height(p as usize), self.depth(p as usize), self.width(p as usize));
+        if ht > MaxDimen || dp > MaxDimen || ht + dp + v_offset > MaxDimen || wd + h_offset > MaxDimen {
+            self.print_err("Huge page cannot be shipped out");
+            self.help(&["The page just created is more than 18 feet tall or",
+                "more than 18 feet wide, so I suspect something went wrong."]);
+            self.error();
+            if self.int_par(TracingOutputCode) <= 0 {
+                self.begin_diagnostic();
+                self.print_nl("The following box has been deleted:");
+                self.show_box(p);
+                self.end_diagnostic(true);
+            }
+            return;
+        }
+        if ht + dp + v_offset > self.max_v {
+            self.max_v = ht + dp + v_offset;
+        }
+        if wd + h_offset > self.max_h {
+            self.max_h = wd + h_offset;
+        }
+        match self.output_format {
+            OutputFormat::Dvi => {}
+            OutputFormat::Pdf => return self.pdf_ship_out(p),
+            OutputFormat::Svg => return self.svg_ship_out(p),
+        }
+        // 617: initialize variables as ship_out begins
+        self.dvi_h = 0;
+        self.dvi_v = 0;
+        self.cur_h = h_offset;
+        self.dvi_f = NullFont;
+        self.ensure_dvi_open();
+        if self.total_pages == 0 {
+            self.dvi_out(Pre);
+            self.dvi_out(IdByte); // output the preamble
+            self.dvi_four(25400000);
+            self.dvi_four(473628672); // conversion ratio for sp
+            self.prepare_mag();
+            self.dvi_four(self.int_par(MagCode)); // magnification factor is frozen
+            let old_setting = self.selector;
+            self.selector = NewString;
+            self.print(" TeX output ");
+            self.print_int(self.int_par(YearCode));
+            self.print_char('.' as u8);
+            self.print_two(self.int_par(MonthCode));
+            self.print_char('.' as u8);
+            self.print_two(self.int_par(DayCode));
+            self.print_char(':' as u8);
+            self.print_two(self.int_par(TimeCode) / 60);
+            self.print_two(self.int_par(TimeCode) % 60);
+            self.selector = old_setting;
+            self.dvi_out(self.cur_length() as u8);
+            for s in self.str_start[self.str_ptr as usize]..self.pool_ptr {
+                self.dvi_out(self.str_pool[s]);
+            }
+            self.pool_ptr = self.str_start[self.str_ptr as usize]; // flush the current string
+        }
+        let page_loc = self.dvi_offset + self.dvi_ptr as i32;
+        self.dvi_out(Bop);
+        for k in 0..=9 {
+            self.dvi_four(self.count(k));
+        }
+        self.dvi_four(self.last_bop);
+        self.last_bop = page_loc;
+        self.cur_v = ht + v_offset;
+        if self.a_type(p as usize) == VlistNode {
+            self.vlist_out(p);
+        } else {
+            self.hlist_out(p);
+        }
+        self.dvi_out(Eop);
+        self.total_pages += 1;
+        self.cur_s = -1;
+    }
+
+    // 642
+    // finish the DVI file
+    pub fn finish_dvi_file(&mut self) {
+        while self.cur_s > -1 {
+            if self.cur_s > 0 {
+                self.dvi_out(Pop);
+            } else {
+                self.dvi_out(Eop);
+                self.total_pages += 1;
+            }
+            self.cur_s -= 1;
+        }
+        if self.total_pages == 0 {
+            self.print_nl("No pages of output.");
+            return;
+        }
+        self.dvi_out(Post); // beginning of the postamble
+        self.dvi_four(self.last_bop);
+        self.last_bop = self.dvi_offset + self.dvi_ptr as i32 - 5; // post location
+        self.dvi_four(25400000);
+        self.dvi_four(473628672);
+        self.prepare_mag();
+        self.dvi_four(self.int_par(MagCode)); // magnification factor
+        self.dvi_four(self.max_v);
+        self.dvi_four(self.max_h);
+        self.dvi_out((self.max_push / 256) as u8);
+        self.dvi_out((self.max_push % 256) as u8);
+        self.dvi_out(((self.total_pages / 256) % 256) as u8);
+        self.dvi_out((self.total_pages % 256) as u8);
+        // 643: output the font definitions for all fonts that were used
+        let mut f = self.font_ptr;
+        while f > NullFont {
+            if self.font_used[f] {
+                self.dvi_font_def(f);
+            }
+            f -= 1;
+        }
+        self.dvi_out(PostPost);
+        self.dvi_four(self.last_bop);
+        self.dvi_out(IdByte);
+        let mut k = 4 + ((DviBufSize as usize - self.dvi_ptr) % 4); // the number of 223's
+        while k > 0 {
+            self.dvi_out(223);
+            k -= 1;
+        }
+        // 599: empty the last bytes out of dvi_buf
+        if self.dvi_limit == self.half_buf {
+            self.write_dvi(self.half_buf, DviBufSize as usize - 1);
+        }
+        if self.dvi_ptr > 0 {
+            self.write_dvi(0, self.dvi_ptr - 1);
+        }
+        self.print_nl("Output written on ");
+        self.slow_print(self.output_file_name);
+        self.print(" (");
+        self.print_int(self.total_pages);
+        self.print(" page");
+        if self.total_pages != 1 {
+            self.print_char('s' as u8);
+        }
+        self.print(", ");
+        self.print_int(self.dvi_offset + self.dvi_ptr as i32);
+        self.print(" bytes).");
+        if let Some(mut f) = self.dvi_file.take() {
+            let _ = f.flush();
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use crate::*;
+use std::fmt::Write as FmtWrite;
+
+// A DVI file read back into memory, after the fashion of dvitype: the reader
+// checks the preamble, the chain of bop back-pointers, the nesting of push
+// and pop against the postamble's max_push, the font definitions and the
+// postamble itself, and gives up on the first thing that is wrong. Two files
+// that differ only in how their movements were encoded make the same marks,
+// which is what DviFile::semantic_diff compares.
+
+// a font definition, from fnt_def or the postamble
+#[derive(Debug, Clone, PartialEq)]
+pub struct DviFontDef {
+    pub number: i32,
+    pub checksum: u32,
+    pub scale: Scaled,
+    pub design_size: Scaled,
+    pub area: Vec<u8>,
+    pub name: Vec<u8>,
+}
+
+// one command within a page; the w, x, y and z movements are None when they
+// move by the amount already in their register
+#[derive(Debug, Clone, PartialEq)]
+pub enum DviCommand {
+    SetChar(u32),
+    PutChar(u32),
+    SetRule { height: Scaled, width: Scaled },
+    PutRule { height: Scaled, width: Scaled },
+    Nop,
+    Push,
+    Pop,
+    Right(Scaled),
+    W(Option<Scaled>),
+    X(Option<Scaled>),
+    Down(Scaled),
+    Y(Option<Scaled>),
+    Z(Option<Scaled>),
+    Font(i32),
+    FontDef(DviFontDef),
+    Special(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DviPage {
+    pub offset: usize, // the byte number of the page's bop
+    pub counts: [i32; 10], // \count0 to \count9
+    pub prev: i32, // the byte number of the previous bop, or -1
+    pub commands: Vec<(usize, DviCommand)>, // each command with its byte number
+}
+
+// a mark on a page, at a position measured from the page's reference point
+#[derive(Debug, Clone, PartialEq)]
+pub enum DviMark {
+    Char { font: i32, c: u32, h: Scaled, v: Scaled },
+    Rule { h: Scaled, v: Scaled, height: Scaled, width: Scaled }, // (h,v) is the bottom left corner
+    Special { h: Scaled, v: Scaled, text: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DviPostamble {
+    pub offset: usize, // the byte number of post
+    pub max_v: Scaled,
+    pub max_h: Scaled,
+    pub max_push: i32,
+    pub total_pages: i32,
+    pub fonts: Vec<DviFontDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DviFile {
+    pub num: i32,
+    pub den: i32,
+    pub mag: i32,
+    pub comment: Vec<u8>,
+    pub pages: Vec<DviPage>,
+    pub postamble: DviPostamble,
+}
+
+// the bytes of a DVI file, read from the front
+struct DviBytes<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DviBytes<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.data.get(self.pos).ok_or_else(|| format!("the file ends in the middle of a command at byte {}", self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<Vec<u8>, String> {
+        let b = self.data.get(self.pos..self.pos + n).ok_or_else(|| format!("the file ends in the middle of a command at byte {}", self.pos))?;
+        self.pos += n;
+        Ok(b.to_vec())
+    }
+
+    // an unsigned quantity of n bytes
+    fn unsigned(&mut self, n: u8) -> Result<u32, String> {
+        let mut x = 0;
+        for _ in 0..n {
+            x = (x << 8) + self.byte()? as u32;
+        }
+        Ok(x)
+    }
+
+    // a signed quantity of n bytes, in two's complement
+    fn signed(&mut self, n: u8) -> Result<i32, String> {
+        let x = self.unsigned(n)?;
+        let shift = 32 - 8 * n as u32;
+        Ok(((x << shift) as i32) >> shift)
+    }
+
+    // the parameters of fnt_def1..fnt_def4, which has font numbers of n bytes
+    fn font_def(&mut self, n: u8) -> Result<DviFontDef, String> {
+        let number = if n == 4 { self.signed(4)? } else { self.unsigned(n)? as i32 };
+        let checksum = self.unsigned(4)?;
+        let scale = self.signed(4)?;
+        let design_size = self.signed(4)?;
+        let a = self.byte()? as usize;
+        let l = self.byte()? as usize;
+        Ok(DviFontDef { number, checksum, scale, design_size,
+                        area: self.bytes(a)?, name: self.bytes(l)? })
+    }
+}
+
+impl DviFile {
+    // reads and checks a whole DVI file; the error says what is wrong with it
+    pub fn parse(data: &[u8]) -> Result<DviFile, String> {
+        let mut d = DviBytes { data, pos: 0 };
+        if d.byte()? != Pre || d.byte()? != IdByte {
+            return Err("the file doesn't begin with the preamble of a DVI file".to_string());
+        }
+        let num = d.signed(4)?;
+        let den = d.signed(4)?;
+        let mag = d.signed(4)?;
+        if num <= 0 || den <= 0 || mag <= 0 {
+            return Err(format!("the preamble's num {}, den {} and mag {} must all be positive", num, den, mag));
+        }
+        let k = d.byte()? as usize;
+        let comment = d.bytes(k)?;
+        let mut pages = Vec::new();
+        let mut fonts: Vec<DviFontDef> = Vec::new(); // those defined in the pages
+        let mut prev = -1;
+        let mut max_depth = 0;
+        // 239: between pages only nop, fnt_def and bop or post may appear
+        let post = loop {
+            let offset = d.pos;
+            match d.byte()? {
+                Nop => {}
+                o @ FntDef1..=246 => {
+                    let def = d.font_def(o - FntDef1 + 1)?;
+                    DviFile::define_font(&mut fonts, def, offset)?;
+                }
+                Bop => {
+                    let page = DviFile::read_page(&mut d, offset, &mut fonts, &mut max_depth)?;
+                    if page.prev != prev {
+                        return Err(format!("the bop at byte {} points back to byte {}, not {}", offset, page.prev, prev));
+                    }
+                    prev = offset as i32;
+                    pages.push(page);
+                }
+                Post => break offset,
+                o => return Err(format!("byte {} is command {}, which can't come between pages", offset, o)),
+            }
+        };
+        // 240: the postamble
+        if d.signed(4)? != prev {
+            return Err(format!("the postamble's pointer to the last bop should be {}", prev));
+        }
+        if (d.signed(4)?, d.signed(4)?, d.signed(4)?) != (num, den, mag) {
+            return Err("the postamble's num, den and mag don't match the preamble's".to_string());
+        }
+        let max_v = d.signed(4)?;
+        let max_h = d.signed(4)?;
+        let max_push = d.unsigned(2)? as i32;
+        let total_pages = d.unsigned(2)? as i32;
+        if max_depth > max_push {
+            return Err(format!("the pages push {} levels deep, but the postamble allows only {}", max_depth, max_push));
+        }
+        if total_pages != pages.len() as i32 % 65536 {
+            return Err(format!("the postamble says there are {} pages, but there are {}", total_pages, pages.len()));
+        }
+        let mut post_fonts = Vec::new();
+        loop {
+            let offset = d.pos;
+            match d.byte()? {
+                Nop => {}
+                o @ FntDef1..=246 => {
+                    let def = d.font_def(o - FntDef1 + 1)?;
+                    DviFile::define_font(&mut post_fonts, def, offset)?;
+                }
+                PostPost => break,
+                o => return Err(format!("byte {} is command {}, which can't come in the postamble", offset, o)),
+            }
+        }
+        for f in fonts.iter() {
+            if !post_fonts.contains(f) {
+                return Err(format!("font {} is defined in the pages but not in the postamble", f.number));
+            }
+        }
+        if d.signed(4)? != post as i32 || d.byte()? != IdByte {
+            return Err("post_post doesn't point back to the postamble".to_string());
+        }
+        let trailer = &data[d.pos..];
+        if trailer.len() < 4 || trailer.len() > 7 || trailer.iter().any(|&b| b != 223) || !data.len().is_multiple_of(4) {
+            return Err("the file doesn't end with four to seven 223's that make its length a multiple of 4".to_string());
+        }
+        Ok(DviFile {
+            num, den, mag, comment, pages,
+            postamble: DviPostamble { offset: post, max_v, max_h, max_push,
+                                      total_pages, fonts: post_fonts },
+        })
+    }
+
+    // adds a font definition, which may repeat an earlier one but not contradict it
+    fn define_font(fonts: &mut Vec<DviFontDef>, def: DviFontDef, offset: usize) -> Result<(), String> {
+        match fonts.iter().find(|f| f.number == def.number) {
+            Some(f) if *f != def => Err(format!("the definition of font {} at byte {} differs from the earlier one", def.number, offset)),
+            Some(_) => Ok(()),
+            None => {
+                fonts.push(def);
+                Ok(())
+            }
+        }
+    }
+
+    // reads a page whose bop is at the given offset, up to and including its eop
+    fn read_page(d: &mut DviBytes, offset: usize, fonts: &mut Vec<DviFontDef>, max_depth: &mut i32) -> Result<DviPage, String> {
+        let mut counts = [0; 10];
+        for c in counts.iter_mut() {
+            *c = d.signed(4)?;
+        }
+        let prev = d.signed(4)?;
+        let mut commands = Vec::new();
+        let mut depth = 0;
+        loop {
+            let pos = d.pos;
+            let o = d.byte()?;
+            let cmd = match o {
+                SetChar0..Set1 => DviCommand::SetChar(o as u32),
+                128..=131 => DviCommand::SetChar(d.unsigned(o - Set1 + 1)?),
+                SetRule => DviCommand::SetRule { height: d.signed(4)?, width: d.signed(4)? },
+                133..=136 => DviCommand::PutChar(d.unsigned(o - Put1 + 1)?),
+                PutRule => DviCommand::PutRule { height: d.signed(4)?, width: d.signed(4)? },
+                Nop => DviCommand::Nop,
+                Eop => {
+                    if depth != 0 {
+                        return Err(format!("the page at byte {} ends with {} pushes not popped", offset, depth));
+                    }
+                    break;
+                }
+                Push => {
+                    depth += 1;
+                    if depth > *max_depth {
+                        *max_depth = depth;
+                    }
+                    DviCommand::Push
+                }
+                Pop => {
+                    if depth == 0 {
+                        return Err(format!("the pop at byte {} has no push to match it", pos));
+                    }
+                    depth -= 1;
+                    DviCommand::Pop
+                }
+                143..=146 => DviCommand::Right(d.signed(o - Right1 + 1)?),
+                W0 => DviCommand::W(None),
+                148..=151 => DviCommand::W(Some(d.signed(o - W1 + 1)?)),
+                X0 => DviCommand::X(None),
+                153..=156 => DviCommand::X(Some(d.signed(o - X1 + 1)?)),
+                157..=160 => DviCommand::Down(d.signed(o - Down1 + 1)?),
+                Y0 => DviCommand::Y(None),
+                162..=165 => DviCommand::Y(Some(d.signed(o - Y1 + 1)?)),
+                Z0 => DviCommand::Z(None),
+                167..=170 => DviCommand::Z(Some(d.signed(o - Z1 + 1)?)),
+                171..=234 => DviCommand::Font((o - FntNum0) as i32),
+                235..=238 => {
+                    let n = o - Fnt1 + 1;
+                    DviCommand::Font(if n == 4 { d.signed(4)? } else { d.unsigned(n)? as i32 })
+                }
+                Xxx1..=Xxx4 => {
+                    let k = d.unsigned(o - Xxx1 + 1)? as usize;
+                    DviCommand::Special(d.bytes(k)?)
+                }
+                FntDef1..=246 => {
+                    let def = d.font_def(o - FntDef1 + 1)?;
+                    DviFile::define_font(fonts, def.clone(), pos)?;
+                    DviCommand::FontDef(def)
+                }
+                _ => return Err(format!("byte {} is command {}, which can't come within a page", pos, o)),
+            };
+            if let DviCommand::Font(k) = cmd {
+                if !fonts.iter().any(|f| f.number == k) {
+                    return Err(format!("font {} is selected at byte {} before it is defined", k, pos));
+                }
+            }
+            commands.push((pos, cmd));
+        }
+        Ok(DviPage { offset, counts, prev, commands })
+    }
+
+    // the definition of font k
+    pub fn font(&self, k: i32) -> Option<&DviFontDef> {
+        self.postamble.fonts.iter().find(|f| f.number == k)
+    }
+
+    // a listing of the file in the manner of dvitype, one command per line
+    pub fn listing(&self) -> String {
+        let mut s = String::new();
+        let _ = writeln!(s, "numerator/denominator={}/{}, magnification={}", self.num, self.den, self.mag);
+        let _ = writeln!(s, "'{}'", String::from_utf8_lossy(&self.comment));
+        for page in self.pages.iter() {
+            let counts: Vec<String> = page.counts.iter().map(|c| c.to_string()).collect();
+            let mut j = 9;
+            while j > 0 && page.counts[j] == 0 {
+                j -= 1;
+            }
+            let _ = writeln!(s, "\n{}: beginning of page {}", page.offset, counts[..=j].join("."));
+            for (pos, cmd) in page.commands.iter() {
+                let _ = writeln!(s, "{}: {}", pos, DviFile::show_command(cmd));
+            }
+            let _ = writeln!(s, "eop");
+        }
+        let p = &self.postamble;
+        let _ = writeln!(s, "\nPostamble starts at byte {}.", p.offset);
+        let _ = writeln!(s, "maxv={}, maxh={}, maxstackdepth={}, totalpages={}", p.max_v, p.max_h, p.max_push, p.total_pages);
+        for f in p.fonts.iter() {
+            let _ = writeln!(s, "Font {}: {}", f.number, DviFile::show_font(f));
+        }
+        s
+    }
+
+    fn show_font(f: &DviFontDef) -> String {
+        let mut s = String::from_utf8_lossy(&f.area).into_owned();
+        s.push_str(&String::from_utf8_lossy(&f.name));
+        if f.scale != f.design_size && f.design_size > 0 {
+            s.push_str(&format!(" scaled {}", (1000.0 * f.scale as f64 / f.design_size as f64).round() as i32));
+        }
+        s
+    }
+
+    fn show_command(cmd: &DviCommand) -> String {
+        let reg = |r: &str, x: &Option<Scaled>| match x {
+            None => format!("{}0", r),
+            Some(x) => format!("{} {}", r, x),
+        };
+        match cmd {
+            DviCommand::SetChar(c) => format!("setchar{}", c),
+            DviCommand::PutChar(c) => format!("put {}", c),
+            DviCommand::SetRule { height, width } => format!("setrule height {}, width {}", height, width),
+            DviCommand::PutRule { height, width } => format!("putrule height {}, width {}", height, width),
+            DviCommand::Nop => "nop".to_string(),
+            DviCommand::Push => "push".to_string(),
+            DviCommand::Pop => "pop".to_string(),
+            DviCommand::Right(x) => format!("right {}", x),
+            DviCommand::W(x) => reg("w", x),
+            DviCommand::X(x) => reg("x", x),
+            DviCommand::Down(x) => format!("down {}", x),
+            DviCommand::Y(x) => reg("y", x),
+            DviCommand::Z(x) => reg("z", x),
+            DviCommand::Font(k) => format!("fntnum{}", k),
+            DviCommand::FontDef(f) => format!("fntdef {}: {}", f.number, DviFile::show_font(f)),
+            DviCommand::Special(t) => format!("xxx '{}'", String::from_utf8_lossy(t)),
+        }
+    }
+
+    // the first difference between the marks on the pages of this file and
+    // of another, given the widths of the characters of each; None if they
+    // make the same pages
+    pub fn semantic_diff(&self, other: &DviFile, width: &dyn Fn(&DviFontDef, u32) -> Scaled,
+                         other_width: &dyn Fn(&DviFontDef, u32) -> Scaled) -> Option<String> {
+        if self.pages.len() != other.pages.len() {
+            return Some(format!("there are {} pages, not {}", self.pages.len(), other.pages.len()));
+        }
+        for (k, (a, b)) in self.pages.iter().zip(other.pages.iter()).enumerate() {
+            if a.counts != b.counts {
+                return Some(format!("page {} has counts {:?}, not {:?}", k + 1, a.counts, b.counts));
+            }
+            let ma = self.marks(a, width);
+            let mb = other.marks(b, other_width);
+            for (j, (x, y)) in ma.iter().zip(mb.iter()).enumerate() {
+                let same = match (x, y) {
+                    (DviMark::Char { font: f, c, h, v }, DviMark::Char { font: g, c: d, h: hh, v: vv }) => {
+                        c == d && h == hh && v == vv && self.font(*f).map(|f| (&f.name, f.scale)) == other.font(*g).map(|g| (&g.name, g.scale))
+                    }
+                    _ => x == y,
+                };
+                if !same {
+                    return Some(format!("mark {} on page {} is {:?}, not {:?}", j + 1, k + 1, x, y));
+                }
+            }
+            if ma.len() != mb.len() {
+                return Some(format!("page {} has {} marks, not {}", k + 1, ma.len(), mb.len()));
+            }
+        }
+        None
+    }
+
+    // the marks made by a page, given the widths of the characters of its fonts
+    pub fn marks(&self, page: &DviPage, width: &dyn Fn(&DviFontDef, u32) -> Scaled) -> Vec<DviMark> {
+        let mut marks = Vec::new();
+        let (mut h, mut v, mut w, mut x, mut y, mut z) = (0, 0, 0, 0, 0, 0);
+        let mut f = None;
+        let mut stack = Vec::new();
+        for (_, cmd) in page.commands.iter() {
+            match cmd {
+                DviCommand::SetChar(c) | DviCommand::PutChar(c) => {
+                    if let Some(def) = f.and_then(|k| self.font(k)) {
+                        marks.push(DviMark::Char { font: def.number, c: *c, h, v });
+                        if let DviCommand::SetChar(_) = cmd {
+                            h += width(def, *c);
+                        }
+                    }
+                }
+                DviCommand::SetRule { height: a, width: b } | DviCommand::PutRule { height: a, width: b } => {
+                    if *a > 0 && *b > 0 {
+                        marks.push(DviMark::Rule { h, v, height: *a, width: *b });
+                    }
+                    if let DviCommand::SetRule { .. } = cmd {
+                        h += b;
+                    }
+                }
+                DviCommand::Nop | DviCommand::FontDef(_) => {}
+                DviCommand::Push => stack.push((h, v, w, x, y, z)),
+                DviCommand::Pop => {
+                    if let Some(s) = stack.pop() {
+                        let (sh, sv, sw, sx, sy, sz) = s;
+                        h = sh;
+                        v = sv;
+                        w = sw;
+                        x = sx;
+                        y = sy;
+                        z = sz;
+                    }
+                }
+                DviCommand::Right(a) => h += a,
+                DviCommand::W(a) => {
+                    w = a.unwrap_or(w);
+                    h += w;
+                }
+                DviCommand::X(a) => {
+                    x = a.unwrap_or(x);
+                    h += x;
+                }
+                DviCommand::Down(a) => v += a,
+                DviCommand::Y(a) => {
+                    y = a.unwrap_or(y);
+                    v += y;
+                }
+                DviCommand::Z(a) => {
+                    z = a.unwrap_or(z);
+                    v += z;
+                }
+                DviCommand::Font(k) => f = Some(*k),
+                DviCommand::Special(t) => marks.push(DviMark::Special { h, v, text: t.clone() }),
+            }
+        }
+        marks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    const Pt: Scaled = 65536;
+
+    // a font metric file with the single character A, half an em wide and
+    // 0.75em high, in a font whose design size is 10pt
+    fn tiny_tfm() -> Vec<u8> {
+        let fix = |x: f64| ((x * (1 << 20) as f64) as i32).to_be_bytes();
+        let mut tfm = Vec::new();
+        // lf, lh, bc, ec, nw, nh, nd, ni, nl, nk, ne, np
+        for &h in &[22u16, 2, 65, 65, 2, 2, 1, 1, 0, 0, 0, 7] {
+            tfm.extend_from_slice(&h.to_be_bytes());
+        }
+        tfm.extend_from_slice(&0x1234_5678u32.to_be_bytes()); // the checksum
+        tfm.extend_from_slice(&fix(10.0)); // the design size
+        tfm.extend_from_slice(&[1, 0x10, 0, 0]); // char_info for A
+        for &x in &[0.0, 0.5, 0.0, 0.75, 0.0, 0.0] {
+            tfm.extend_from_slice(&fix(x)); // the widths, heights, depth and italic correction
+        }
+        for &x in &[0.0, 0.25, 0.0, 0.0, 0.5, 1.0, 0.0] {
+            tfm.extend_from_slice(&fix(x)); // the parameters
+        }
+        tfm
+    }
+
+    // the DVI file for two pages: the first has nested boxes with characters,
+    // a rule, glue and a special, and the second has \count1=7 and another special
+    fn two_pages() -> Vec<u8> {
+        let vfs = Vfs::new();
+        vfs.add_file("tiny.tfm", tiny_tfm());
+        vfs.add_file("doc.tex", &br"\catcode`\{=1 \catcode`\}=2 \font\f=tiny
+\shipout\vbox{\hbox{\f A\vrule width 1pt height 2pt\hskip 3pt\special{one}}\hbox{\hbox{\f AA}}}
+\count1=7 \shipout\hbox{\special{two}}
+\end
+"[..]);
+        typeset_to(&vfs, "doc", OutputFormat::Dvi).remove("doc.dvi").unwrap()
+    }
+
+    #[test]
+    fn shipped_pages_read_back() {
+        let data = two_pages();
+        let dvi = DviFile::parse(&data).unwrap();
+        assert_eq!((dvi.num, dvi.den, dvi.mag), (25400000, 473628672, 1000));
+        assert_eq!(dvi.pages.len(), 2);
+        assert_eq!(dvi.pages[0].prev, -1);
+        assert_eq!(dvi.pages[1].prev, dvi.pages[0].offset as i32);
+        assert_eq!(dvi.pages[1].counts[1], 7);
+        assert_eq!(dvi.postamble.total_pages, 2);
+        assert_eq!(dvi.postamble.max_push, 2);
+        let tiny = DviFontDef { number: 0, checksum: 0x1234_5678, scale: 10 * Pt, design_size: 10 * Pt,
+                                area: Vec::new(), name: b"tiny".to_vec() };
+        assert_eq!(dvi.postamble.fonts, vec![tiny]);
+        let marks = dvi.marks(&dvi.pages[0], &|_, _| 5 * Pt);
+        let v = 15 * Pt / 2; // the baseline of the first line, the height of A below the top
+        assert_eq!(marks[0], DviMark::Char { font: 0, c: 'A' as u32, h: 0, v });
+        assert_eq!(marks[1], DviMark::Rule { h: 5 * Pt, v, height: 2 * Pt, width: Pt });
+        assert_eq!(marks[2], DviMark::Special { h: 9 * Pt, v, text: b"one".to_vec() });
+        assert_eq!(dvi.marks(&dvi.pages[1], &|_, _| 0), vec![DviMark::Special { h: 0, v: 0, text: b"two".to_vec() }]);
+    }
+
+    #[test]
+    fn a_bad_back_pointer_is_reported() {
+        let mut data = two_pages();
+        let dvi = DviFile::parse(&data).unwrap();
+        let bop = dvi.pages[1].offset;
+        data[bop + 41..bop + 45].copy_from_slice(&0i32.to_be_bytes());
+        assert_eq!(DviFile::parse(&data).unwrap_err(),
+                   format!("the bop at byte {} points back to byte 0, not {}", bop, dvi.pages[0].offset));
+    }
+
+    #[test]
+    fn an_unbalanced_pop_is_reported() {
+        let mut data = two_pages();
+        let dvi = DviFile::parse(&data).unwrap();
+        let commands = &dvi.pages[0].commands;
+        let push = commands.iter().find(|(_, c)| *c == DviCommand::Push).unwrap().0;
+        let pop = commands.iter().find(|(_, c)| *c == DviCommand::Pop).unwrap().0;
+        data[push] = Nop;
+        assert_eq!(DviFile::parse(&data).unwrap_err(), format!("the pop at byte {} has no push to match it", pop));
+    }
+
+    #[test]
+    fn the_file_must_end_with_four_to_seven_223s() {
+        let padding = "the file doesn't end with four to seven 223's that make its length a multiple of 4";
+        let data = two_pages();
+        let n = data.iter().rev().take_while(|&&b| b == 223).count();
+        assert!((4..=7).contains(&n));
+        let body = &data[..data.len() - n];
+        assert_eq!(DviFile::parse(body).unwrap_err(), padding);
+        let mut long = data.clone();
+        long.extend_from_slice(&[223; 4]);
+        assert_eq!(DviFile::parse(&long).unwrap_err(), padding);
+        let mut bad = data.clone();
+        *bad.last_mut().unwrap() = 0;
+        assert_eq!(DviFile::parse(&bad).unwrap_err(), padding);
+    }
+}
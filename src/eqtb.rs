@@ -0,0 +1,854 @@
+use crate::*;
+
+// 225
+const SkipParNames: [&str; 18] = [
+    "lineskip", "baselineskip", "parskip", "abovedisplayskip", "belowdisplayskip",
+    "abovedisplayshortskip", "belowdisplayshortskip", "leftskip", "rightskip", "topskip",
+    "splittopskip", "tabskip", "spaceskip", "xspaceskip", "parfillskip",
+    "thinmuskip", "medmuskip", "thickmuskip",
+];
+
+// 237
+const IntParNames: [&str; 55] = [
+    "pretolerance", "tolerance", "linepenalty", "hyphenpenalty", "exhyphenpenalty",
+    "clubpenalty", "widowpenalty", "displaywidowpenalty", "brokenpenalty", "binoppenalty",
+    "relpenalty", "predisplaypenalty", "postdisplaypenalty", "interlinepenalty",
+    "doublehyphendemerits", "finalhyphendemerits", "adjdemerits", "mag", "delimiterfactor",
+    "looseness", "time", "day", "month", "year", "showboxbreadth", "showboxdepth", "hbadness",
+    "vbadness", "pausing", "tracingonline", "tracingmacros", "tracingstats", "tracingparagraphs",
+    "tracingpages", "tracingoutput", "tracinglostchars", "tracingcommands", "tracingrestores",
+    "uchyph", "outputpenalty", "maxdeadcycles", "hangafter", "floatingpenalty", "globaldefs", "fam",
+    "escapechar", "defaulthyphenchar", "defaultskewchar", "endlinechar", "newlinechar",
+    "language", "lefthyphenmin", "righthyphenmin", "holdinginserts", "errorcontextlines",
+];
+
+// 247
+const LengthParNames: [&str; 21] = [
+    "parindent", "mathsurround", "lineskiplimit", "hsize", "vsize", "maxdepth", "splitmaxdepth",
+    "boxmaxdepth", "hfuzz", "vfuzz", "delimitershortfall", "nulldelimiterspace", "scriptspace",
+    "predisplaysize", "displaywidth", "displayindent", "overfullrule", "hangindent", "hoffset",
+    "voffset", "emergencystretch",
+];
+
+// 230
+const ToksParNames: [&str; 9] = [
+    "output", "everypar", "everymath", "everydisplay", "everyhbox", "everyvbox", "everyjob",
+    "everycr", "errhelp",
+];
+
+// 232
+pub const VarCode: HalfWord = 0o70000; // math code meaning ``use the current family''
+
+// 268
+pub const RestoreOldValue: QuarterWord = 0; // save_type when a value should be restored later
+pub const RestoreZero: QuarterWord = 1; // save_type when an undefined entry should be restored
+pub const InsertToken: QuarterWord = 2; // save_type when a token is being saved for later use
+pub const LevelBoundary: QuarterWord = 3; // save_type corresponding to beginning of group
+
+impl TexState {
+    // 225
+    pub fn print_skip_param(&mut self, n: HalfWord) {
+        match SkipParNames.get(n as usize) {
+            Some(s) => self.print_esc(s),
+            None => self.print("[unknown glue parameter!]"),
+        }
+    }
+
+    // 237
+    pub fn print_param(&mut self, n: HalfWord) {
+        match IntParNames.get(n as usize) {
+            Some(s) => self.print_esc(s),
+            None => self.print("[unknown integer parameter!]"),
+        }
+    }
+
+    // 247
+    pub fn print_length_param(&mut self, n: HalfWord) {
+        match LengthParNames.get(n as usize) {
+            Some(s) => self.print_esc(s),
+            None => self.print("[unknown dimen parameter!]"),
+        }
+    }
+
+    // 222, 228, 232, 240, 250, 258
+    // initialize the table of equivalents, done by INITEX only
+    pub fn init_eqtb(&mut self) {
+        let ucs = UndefinedControlSequence as usize;
+        self.set_eq_type(ucs, UndefinedCs);
+        self.set_equiv(ucs, Null);
+        self.set_eq_level(ucs, LevelZero as QuarterWord);
+        for k in ActiveBase as usize..ucs {
+            self.eqtb[k] = self.eqtb[ucs];
+        }
+        // 228
+        self.set_equiv(GlueBase as usize, ZeroGlue as HalfWord);
+        self.set_eq_level(GlueBase as usize, LevelOne as QuarterWord);
+        self.set_eq_type(GlueBase as usize, GlueRef);
+        for k in GlueBase as usize + 1..LocalBase as usize {
+            self.eqtb[k] = self.eqtb[GlueBase as usize];
+        }
+        let c = self.glue_ref_count(ZeroGlue) + LocalBase - GlueBase;
+        self.set_glue_ref_count(ZeroGlue, c);
+        // 232
+        self.set_equiv(ParShapeLoc as usize, Null);
+        self.set_eq_type(ParShapeLoc as usize, ShapeRef);
+        self.set_eq_level(ParShapeLoc as usize, LevelOne as QuarterWord);
+        for k in OutputRoutineLoc as usize..=ToksBase as usize + 255 {
+            self.eqtb[k] = self.eqtb[ucs];
+        }
+        self.set_box_reg(0, Null);
+        self.set_eq_type(BoxBase as usize, BoxRef);
+        self.set_eq_level(BoxBase as usize, LevelOne as QuarterWord);
+        for k in BoxBase as usize + 1..=BoxBase as usize + 255 {
+            self.eqtb[k] = self.eqtb[BoxBase as usize];
+        }
+        self.set_equiv(CurFontLoc as usize, FontBase as HalfWord); // null_font
+        self.set_eq_type(CurFontLoc as usize, Data);
+        self.set_eq_level(CurFontLoc as usize, LevelOne as QuarterWord);
+        for k in MathFontBase as usize..=MathFontBase as usize + 47 {
+            self.eqtb[k] = self.eqtb[CurFontLoc as usize];
+        }
+        self.set_equiv(CatCodeBase as usize, 0);
+        self.set_eq_type(CatCodeBase as usize, Data);
+        self.set_eq_level(CatCodeBase as usize, LevelOne as QuarterWord);
+        for k in CatCodeBase as usize + 1..IntBase as usize {
+            self.eqtb[k] = self.eqtb[CatCodeBase as usize];
+        }
+        for k in 0..=255 {
+            self.set_cat_code(k, OtherChar as HalfWord);
+            self.set_math_code(k, k);
+            self.set_sf_code(k, 1000);
+        }
+        self.set_cat_code(13, CarRet as HalfWord); // carriage_return
+        self.set_cat_code(' ' as HalfWord, Spacer as HalfWord);
+        self.set_cat_code('\\' as HalfWord, 0); // escape
+        self.set_cat_code('%' as HalfWord, Comment as HalfWord);
+        self.set_cat_code(127, InvalidChar as HalfWord); // invalid_code
+        self.set_cat_code(0, Ignore as HalfWord); // null_code
+        for k in '0' as HalfWord..='9' as HalfWord {
+            self.set_math_code(k, k + VarCode);
+        }
+        for k in 'A' as HalfWord..='Z' as HalfWord {
+            let l = k + 'a' as HalfWord - 'A' as HalfWord;
+            self.set_cat_code(k, Letter as HalfWord);
+            self.set_cat_code(l, Letter as HalfWord);
+            self.set_math_code(k, k + VarCode + 0x100);
+            self.set_math_code(l, l + VarCode + 0x100);
+            self.set_lc_code(k, l);
+            self.set_lc_code(l, l);
+            self.set_uc_code(k, k);
+            self.set_uc_code(l, k);
+            self.set_sf_code(k, 999);
+        }
+        // 240
+        for k in IntBase as usize..DelCodeBase as usize {
+            self.eqtb[k].set_int(0);
+        }
+        self.set_int_par(MagCode, 1000);
+        self.set_int_par(ToleranceCode, 10000);
+        self.set_int_par(HangAfterCode, 1);
+        self.set_int_par(MaxDeadCyclesCode, 25);
+        self.set_int_par(EscapeCharCode, '\\' as i32);
+        self.set_int_par(EndLineCharCode, 13); // carriage_return
+        for k in 0..=255 {
+            self.set_del_code(k, -1);
+        }
+        self.set_del_code('.' as HalfWord, 0); // this null delimiter is used in error recovery
+        // 250
+        for k in DimenBase as usize..=EqtbSize as usize {
+            self.eqtb[k].set_sc(0);
+        }
+        // 258
+        self.hash_used = FrozenControlSequence; // nothing is used
+        self.cs_count = 0;
+        self.set_eq_type(FrozenDontExpand as usize, DontExpand);
+    }
+
+    // 241
+    // sets \time, \day, \month and \year from the clock, in UTC; source_date_epoch,
+    // when set, takes the place of the clock so that runs can be reproduced
+    pub fn fix_date_and_time(&mut self) {
+        let secs = match self.source_date_epoch {
+            Some(s) => s,
+            None => match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+                Ok(d) => d.as_secs() as i64,
+                Err(_) => 0,
+            },
+        };
+        // the civil date of a day count, as in Howard Hinnant's civil_from_days
+        let z = secs.div_euclid(86400) + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        self.set_int_par(TimeCode, (secs.rem_euclid(86400) / 60) as i32); // minutes since midnight
+        self.set_int_par(DayCode, day as i32);
+        self.set_int_par(MonthCode, month as i32);
+        self.set_int_par(YearCode, year as i32);
+    }
+
+    // 259
+    // search the hash table for the identifier in buffer[j..j+l)
+    pub fn id_lookup(&mut self, j: usize, l: usize) -> Pointer {
+        // 261: compute the hash code h
+        let mut h = self.buffer[j] as i32;
+        for k in j + 1..j + l {
+            h = h + h + self.buffer[k] as i32;
+            while h >= HashPrime {
+                h -= HashPrime;
+            }
+        }
+        let mut p = h as usize + HashBase as usize; // we start searching here
+        loop {
+            let t = self.hash[p].text();
+            if t > 0 && self.length(t as StrNumber) == l && self.str_eq_buf(t as StrNumber, j) {
+                return p as Pointer;
+            }
+            if self.hash[p].next() == 0 {
+                if self.no_new_control_sequence {
+                    return UndefinedControlSequence;
+                }
+                // 260: insert a new control sequence after p, then make p point to it
+                if self.hash[p].text() > 0 {
+                    loop {
+                        if hash_is_full(self.hash_used, HashBase) {
+                            self.overflow("hash size", HashSize);
+                        }
+                        self.hash_used -= 1;
+                        if self.hash[self.hash_used as usize].text() == 0 {
+                            break;
+                        }
+                    }
+                    self.hash[p].set_next(self.hash_used);
+                    p = self.hash_used as usize;
+                }
+                self.str_room(l);
+                let d = self.cur_length();
+                while self.pool_ptr > self.str_start[self.str_ptr as usize] {
+                    // move current string up to make room for another
+                    self.pool_ptr -= 1;
+                    self.str_pool[self.pool_ptr + l] = self.str_pool[self.pool_ptr];
+                }
+                for k in j..j + l {
+                    self.append_char(self.buffer[k]);
+                }
+                let s = self.make_string();
+                self.hash[p].set_text(s as HalfWord);
+                self.pool_ptr += d;
+                self.cs_count += 1;
+                return p as Pointer;
+            }
+            p = self.hash[p].next() as usize;
+        }
+    }
+
+    // 262
+    // prints a purported control sequence
+    pub fn print_cs(&mut self, p: i32) {
+        if p < HashBase as i32 {
+            // single character
+            if p >= SingleBase as i32 {
+                if p == NullCs as i32 {
+                    self.print_esc("csname");
+                    self.print_esc("endcsname");
+                    self.print_char(' ' as u8);
+                } else {
+                    self.print_esc_str(p - SingleBase as i32);
+                    if self.cat_code((p - SingleBase as i32) as HalfWord) == Letter as HalfWord {
+                        self.print_char(' ' as u8);
+                    }
+                }
+            } else if p < ActiveBase as i32 {
+                self.print_esc("IMPOSSIBLE.");
+            } else {
+                self.print_str(p - ActiveBase as i32);
+            }
+        } else if p >= UndefinedControlSequence as i32 {
+            self.print_esc("IMPOSSIBLE.");
+        } else if self.hash[p as usize].text() as StrNumber >= self.str_ptr {
+            self.print_esc("NONEXISTENT.");
+        } else {
+            self.print_esc_str(self.hash[p as usize].text() as StrNumber);
+            self.print_char(' ' as u8);
+        }
+    }
+
+    // 263
+    // prints a control sequence
+    pub fn sprint_cs(&mut self, p: Pointer) {
+        if p < HashBase {
+            if p < SingleBase {
+                self.print_str((p - ActiveBase) as StrNumber);
+            } else if p < NullCs {
+                self.print_esc_str((p - SingleBase) as StrNumber);
+            } else {
+                self.print_esc("csname");
+                self.print_esc("endcsname");
+            }
+        } else {
+            self.print_esc_str(self.hash[p as usize].text() as StrNumber);
+        }
+    }
+
+    // 264
+    // enters a primitive into eqtb and hash, leaving its location in cur_val
+    pub fn primitive(&mut self, s: &str, c: QuarterWord, o: HalfWord) {
+        if s.len() == 1 {
+            self.cur_val = s.as_bytes()[0] as i32 + SingleBase as i32;
+        } else {
+            // we will move s into the (possibly non-empty) buffer
+            let l = s.len();
+            for (j, b) in s.bytes().enumerate() {
+                self.buffer[j] = b;
+            }
+            self.cur_val = self.id_lookup(0, l) as i32; // no_new_control_sequence is false
+        }
+        let p = self.cur_val as usize;
+        self.set_eq_level(p, LevelOne as QuarterWord);
+        self.set_eq_type(p, c);
+        self.set_equiv(p, o);
+    }
+
+    // gives the frozen control sequence f the name and meaning of the primitive just entered
+    fn freeze_primitive(&mut self, f: HalfWord) {
+        let t = self.hash[self.cur_val as usize].text();
+        self.hash[f as usize].set_text(t);
+        self.eqtb[f as usize] = self.eqtb[self.cur_val as usize];
+    }
+
+    // 1336
+    // calls primitive for each primitive
+    pub fn init_prim(&mut self) {
+        self.no_new_control_sequence = false;
+        // 226
+        for (k, s) in SkipParNames.iter().enumerate() {
+            let c = if k < ThinMuSkipCode as usize { AssignGlue } else { AssignMuGlue };
+            self.primitive(s, c, GlueBase + k as HalfWord);
+        }
+        // 230
+        for (k, s) in ToksParNames.iter().enumerate() {
+            self.primitive(s, AssignToks, OutputRoutineLoc + k as HalfWord);
+        }
+        // 238
+        for (k, s) in IntParNames.iter().enumerate() {
+            self.primitive(s, AssignInt, IntBase + k as HalfWord);
+        }
+        // 248
+        for (k, s) in LengthParNames.iter().enumerate() {
+            self.primitive(s, AssignDimen, DimenBase + k as HalfWord);
+        }
+        // 258
+        let s = self.make_str("notexpanded:");
+        self.hash[FrozenDontExpand as usize].set_text(s as HalfWord);
+        // 265
+        self.primitive(" ", ExSpace, 0);
+        self.primitive("/", ItalCorr, 0);
+        self.primitive("accent", Accent, 0);
+        self.primitive("advance", Advance, 0);
+        self.primitive("afterassignment", AfterAssignment, 0);
+        self.primitive("aftergroup", AfterGroup, 0);
+        self.primitive("begingroup", BeginGroup, 0);
+        self.primitive("char", CharNum, 0);
+        self.primitive("csname", CsName, 0);
+        self.primitive("delimiter", DelimNum, 0);
+        self.primitive("divide", Divide, 0);
+        self.primitive("endcsname", EndCsName, 0);
+        self.primitive("endgroup", EndGroup, 0);
+        self.freeze_primitive(FrozenEndGroup);
+        self.primitive("expandafter", ExpandAfter, 0);
+        self.primitive("font", DefFont, 0);
+        self.primitive("fontdimen", AssignFontDimen, 0);
+        self.primitive("halign", Halign, 0);
+        self.primitive("hrule", Hrule, 0);
+        self.primitive("ignorespaces", IgnoreSpaces, 0);
+        self.primitive("insert", Insert, 0);
+        self.primitive("mark", Mark, 0);
+        self.primitive("mathaccent", MathAccent, 0);
+        self.primitive("mathchar", MathCharNum, 0);
+        self.primitive("mathchoice", MathChoice, 0);
+        self.primitive("multiply", Multiply, 0);
+        self.primitive("noalign", NoAlign, 0);
+        self.primitive("noboundary", NoBoundary, 0);
+        self.primitive("noexpand", NoExpand, 0);
+        self.primitive("nonscript", NonScript, 0);
+        self.primitive("omit", Omit, 0);
+        self.primitive("parshape", SetShape, 0);
+        self.primitive("penalty", BreakPenalty, 0);
+        self.primitive("prevgraf", SetPrevGraf, 0);
+        self.primitive("radical", Radical, 0);
+        self.primitive("read", ReadToCs, 0);
+        self.primitive("relax", Relax, 256); // cf. scan_file_name
+        self.freeze_primitive(FrozenRelax);
+        self.primitive("setbox", SetBox, 0);
+        self.primitive("the", The, 0);
+        self.primitive("toks", ToksRegister, 0);
+        self.primitive("vadjust", Vadjust, 0);
+        self.primitive("valign", Valign, 0);
+        self.primitive("vcenter", Vcenter, 0);
+        self.primitive("vrule", Vrule, 0);
+        // 468
+        self.primitive("number", Convert, NumberCode);
+        self.primitive("romannumeral", Convert, RomanNumeralCode);
+        self.primitive("string", Convert, StringCode);
+        self.primitive("meaning", Convert, MeaningCode);
+        self.primitive("fontname", Convert, FontNameCode);
+        self.primitive("jobname", Convert, JobNameCode);
+        // 411
+        self.primitive("count", Register, IntVal as HalfWord);
+        self.primitive("dimen", Register, DimenVal as HalfWord);
+        self.primitive("skip", Register, GlueVal as HalfWord);
+        self.primitive("muskip", Register, MuVal as HalfWord);
+        // 416
+        self.primitive("spacefactor", SetAux, Hmode as HalfWord);
+        self.primitive("prevdepth", SetAux, Vmode as HalfWord);
+        self.primitive("deadcycles", SetPageInt, 0);
+        self.primitive("insertpenalties", SetPageInt, 1);
+        self.primitive("wd", SetBoxDimen, WidthOffset as HalfWord);
+        self.primitive("ht", SetBoxDimen, HeightOffset as HalfWord);
+        self.primitive("dp", SetBoxDimen, DepthOffset as HalfWord);
+        self.primitive("lastpenalty", LastItem, IntVal as HalfWord);
+        self.primitive("lastkern", LastItem, DimenVal as HalfWord);
+        self.primitive("lastskip", LastItem, GlueVal as HalfWord);
+        self.primitive("inputlineno", LastItem, InputLineNoCode);
+        self.primitive("badness", LastItem, BadnessCode);
+        // 334
+        self.primitive("par", ParEnd, 256); // cf. scan_file_name
+        self.par_loc = self.cur_val as Pointer;
+        self.par_token = cs_token_flag as HalfWord + self.par_loc;
+        // 376
+        self.primitive("input", Input, 0);
+        self.primitive("endinput", Input, 1);
+        // 384
+        self.primitive("topmark", TopBotMark, top_mark_code as HalfWord);
+        self.primitive("firstmark", TopBotMark, first_mark_code as HalfWord);
+        self.primitive("botmark", TopBotMark, bot_mark_code as HalfWord);
+        self.primitive("splitfirstmark", TopBotMark, split_first_mark_code as HalfWord);
+        self.primitive("splitbotmark", TopBotMark, split_bot_mark_code as HalfWord);
+        self.primitive("marks", Mark, MarksCode as HalfWord);
+        self.primitive("topmarks", TopBotMark, (top_mark_code + MarksCode) as HalfWord);
+        self.primitive("firstmarks", TopBotMark, (first_mark_code + MarksCode) as HalfWord);
+        self.primitive("botmarks", TopBotMark, (bot_mark_code + MarksCode) as HalfWord);
+        self.primitive("splitfirstmarks", TopBotMark, (split_first_mark_code + MarksCode) as HalfWord);
+        self.primitive("splitbotmarks", TopBotMark, (split_bot_mark_code + MarksCode) as HalfWord);
+        // 487
+        self.primitive("if", IfTest, IfCharCode);
+        self.primitive("ifcat", IfTest, IfCatCode);
+        self.primitive("ifnum", IfTest, IfIntCode);
+        self.primitive("ifdim", IfTest, IfDimCode);
+        self.primitive("ifodd", IfTest, IfOddCode);
+        self.primitive("ifvmode", IfTest, IfVmodeCode);
+        self.primitive("ifhmode", IfTest, IfHmodeCode);
+        self.primitive("ifmmode", IfTest, IfMmodeCode);
+        self.primitive("ifinner", IfTest, IfInnerCode);
+        self.primitive("ifvoid", IfTest, IfVoidCode);
+        self.primitive("ifhbox", IfTest, IfHboxCode);
+        self.primitive("ifvbox", IfTest, IfVboxCode);
+        self.primitive("ifx", IfTest, IfxCode);
+        self.primitive("ifeof", IfTest, IfEofCode);
+        self.primitive("iftrue", IfTest, IfTrueCode);
+        self.primitive("iffalse", IfTest, IfFalseCode);
+        self.primitive("ifcase", IfTest, IfCaseCode);
+        // 491
+        self.primitive("fi", FiOrElse, fi_code as HalfWord);
+        self.freeze_primitive(FrozenFi);
+        self.primitive("or", FiOrElse, or_code as HalfWord);
+        self.primitive("else", FiOrElse, else_code as HalfWord);
+        // 553
+        self.primitive("nullfont", SetFont, NullFont as HalfWord);
+        self.freeze_primitive(FrozenNullFont);
+        self.font_name[NullFont] = self.hash[FrozenNullFont as usize].text() as StrNumber;
+        // 780
+        self.primitive("span", TabMark, SpanCode);
+        self.primitive("cr", CarRet, CrCode);
+        self.freeze_primitive(FrozenCr);
+        self.primitive("crcr", CarRet, CrCrCode);
+        let s = self.make_str("endtemplate") as HalfWord;
+        self.hash[FrozenEndTemplate as usize].set_text(s);
+        self.hash[FrozenEndv as usize].set_text(s);
+        self.set_eq_type(FrozenEndv as usize, Endv);
+        self.set_equiv(FrozenEndv as usize, NullList as HalfWord);
+        self.set_eq_level(FrozenEndv as usize, LevelOne as QuarterWord);
+        self.eqtb[FrozenEndTemplate as usize] = self.eqtb[FrozenEndv as usize];
+        self.set_eq_type(FrozenEndTemplate as usize, EndTemplate);
+        // 1250
+        self.primitive("hyphenation", HyphData, 0);
+        self.primitive("patterns", HyphData, 1);
+        // 1254
+        self.primitive("hyphenchar", AssignFontInt, 0);
+        self.primitive("skewchar", AssignFontInt, 1);
+        // 1272
+        self.primitive("openin", InStream, 1);
+        self.primitive("closein", InStream, 0);
+        // 1344
+        self.primitive("openout", Extension, OpenNode as HalfWord);
+        self.primitive("write", Extension, WriteNode as HalfWord);
+        self.write_loc = self.cur_val as Pointer;
+        self.primitive("closeout", Extension, CloseNode as HalfWord);
+        self.primitive("special", Extension, SpecialNode as HalfWord);
+        self.primitive("immediate", Extension, ImmediateCode as HalfWord);
+        self.primitive("setlanguage", Extension, SetLanguageCode as HalfWord);
+        // 1052
+        self.primitive("end", Stop, 0);
+        self.primitive("dump", Stop, 1);
+        // 1058
+        self.primitive("hskip", Hskip, SkipCode);
+        self.primitive("hfil", Hskip, FilCode);
+        self.primitive("hfill", Hskip, FillCode);
+        self.primitive("hss", Hskip, SsCode);
+        self.primitive("hfilneg", Hskip, FilNegCode);
+        self.primitive("vskip", Vskip, SkipCode);
+        self.primitive("vfil", Vskip, FilCode);
+        self.primitive("vfill", Vskip, FillCode);
+        self.primitive("vss", Vskip, SsCode);
+        self.primitive("vfilneg", Vskip, FilNegCode);
+        self.primitive("mskip", Mskip, MskipCode);
+        self.primitive("kern", Kern, Explicit as HalfWord);
+        self.primitive("mkern", Mkern, MuGlue as HalfWord);
+        // 1071
+        self.primitive("moveleft", Hmove, 1);
+        self.primitive("moveright", Hmove, 0);
+        self.primitive("raise", Vmove, 1);
+        self.primitive("lower", Vmove, 0);
+        self.primitive("box", MakeBox, BoxCode);
+        self.primitive("copy", MakeBox, CopyCode);
+        self.primitive("lastbox", MakeBox, LastBoxCode);
+        self.primitive("vsplit", MakeBox, VsplitCode);
+        self.primitive("vtop", MakeBox, VtopCode);
+        self.primitive("vbox", MakeBox, VtopCode + Vmode as HalfWord);
+        self.primitive("hbox", MakeBox, VtopCode + Hmode as HalfWord);
+        self.primitive("shipout", LeaderShip, ALeaders as HalfWord - 1); // ship_out_flag=leader_flag-1
+        self.primitive("leaders", LeaderShip, ALeaders as HalfWord);
+        self.primitive("cleaders", LeaderShip, CLeaders as HalfWord);
+        self.primitive("xleaders", LeaderShip, XLeaders as HalfWord);
+        // 1088
+        self.primitive("indent", StartPar, 1);
+        self.primitive("noindent", StartPar, 0);
+        // 1107
+        self.primitive("unpenalty", RemoveItem, PenaltyNode as HalfWord);
+        self.primitive("unkern", RemoveItem, KernNode as HalfWord);
+        self.primitive("unskip", RemoveItem, GlueNode as HalfWord);
+        self.primitive("unhbox", UnHbox, BoxCode);
+        self.primitive("unhcopy", UnHbox, CopyCode);
+        self.primitive("unvbox", UnVbox, BoxCode);
+        self.primitive("unvcopy", UnVbox, CopyCode);
+        // 1114
+        self.primitive("-", Discretionary, 1);
+        self.primitive("discretionary", Discretionary, 0);
+        // 1141
+        self.primitive("eqno", EqNo, 0);
+        self.primitive("leqno", EqNo, 1);
+        // 1156
+        self.primitive("mathord", MathComp, OrdNoad as HalfWord);
+        self.primitive("mathop", MathComp, OpNoad as HalfWord);
+        self.primitive("mathbin", MathComp, BinNoad as HalfWord);
+        self.primitive("mathrel", MathComp, RelNoad as HalfWord);
+        self.primitive("mathopen", MathComp, OpenNoad as HalfWord);
+        self.primitive("mathclose", MathComp, CloseNoad as HalfWord);
+        self.primitive("mathpunct", MathComp, PunctNoad as HalfWord);
+        self.primitive("mathinner", MathComp, InnerNoad as HalfWord);
+        self.primitive("underline", MathComp, UnderNoad as HalfWord);
+        self.primitive("overline", MathComp, OverNoad as HalfWord);
+        self.primitive("displaylimits", LimitSwitch, Normal as HalfWord);
+        self.primitive("limits", LimitSwitch, Limits as HalfWord);
+        self.primitive("nolimits", LimitSwitch, NoLimits as HalfWord);
+        // 1169
+        self.primitive("displaystyle", MathStyle, DisplayStyle as HalfWord);
+        self.primitive("textstyle", MathStyle, TextStyle as HalfWord);
+        self.primitive("scriptstyle", MathStyle, ScriptStyle as HalfWord);
+        self.primitive("scriptscriptstyle", MathStyle, ScriptScriptStyle as HalfWord);
+        // 1178
+        self.primitive("above", Above, AboveCode);
+        self.primitive("over", Above, OverCode);
+        self.primitive("atop", Above, AtopCode);
+        self.primitive("abovewithdelims", Above, DelimitedCode + AboveCode);
+        self.primitive("overwithdelims", Above, DelimitedCode + OverCode);
+        self.primitive("atopwithdelims", Above, DelimitedCode + AtopCode);
+        // 1188
+        self.primitive("left", LeftRight, LeftNoad as HalfWord);
+        self.primitive("right", LeftRight, RightNoad as HalfWord);
+        self.freeze_primitive(FrozenRight);
+        // 1208
+        self.primitive("long", Prefix, 1);
+        self.primitive("outer", Prefix, 2);
+        self.primitive("global", Prefix, 4);
+        self.primitive("def", Def, 0);
+        self.primitive("gdef", Def, 1);
+        self.primitive("edef", Def, 2);
+        self.primitive("xdef", Def, 3);
+        // 1219
+        self.primitive("let", Let, Normal as HalfWord);
+        self.primitive("futurelet", Let, Normal as HalfWord + 1);
+        // 1222
+        self.primitive("chardef", ShorthandDef, CharDefCode);
+        self.primitive("mathchardef", ShorthandDef, MathCharDefCode);
+        self.primitive("countdef", ShorthandDef, CountDefCode);
+        self.primitive("dimendef", ShorthandDef, DimenDefCode);
+        self.primitive("skipdef", ShorthandDef, SkipDefCode);
+        self.primitive("muskipdef", ShorthandDef, MuSkipDefCode);
+        self.primitive("toksdef", ShorthandDef, ToksDefCode);
+        // 1230
+        self.primitive("catcode", DefCode, CatCodeBase);
+        self.primitive("mathcode", DefCode, MathCodeBase);
+        self.primitive("lccode", DefCode, LcCodeBase);
+        self.primitive("uccode", DefCode, UcCodeBase);
+        self.primitive("sfcode", DefCode, SfCodeBase);
+        self.primitive("delcode", DefCode, DelCodeBase);
+        self.primitive("textfont", DefFamily, MathFontBase);
+        self.primitive("scriptfont", DefFamily, MathFontBase + ScriptSize as HalfWord);
+        self.primitive("scriptscriptfont", DefFamily, MathFontBase + ScriptScriptSize as HalfWord);
+        // 1262
+        self.primitive("batchmode", SetInteraction, 0);
+        self.primitive("nonstopmode", SetInteraction, 1);
+        self.primitive("scrollmode", SetInteraction, 2);
+        self.primitive("errorstopmode", SetInteraction, 3);
+        // 1276
+        self.primitive("message", Message, 0);
+        self.primitive("errmessage", Message, 1);
+        // 1286
+        self.primitive("lowercase", CaseShift, LcCodeBase);
+        self.primitive("uppercase", CaseShift, UcCodeBase);
+        // 1291
+        self.primitive("show", Xray, ShowCode);
+        self.primitive("showbox", Xray, ShowBoxCode);
+        self.primitive("showthe", Xray, ShowTheCode);
+        self.primitive("showlists", Xray, ShowListsCode);
+        // 983
+        self.primitive("pagegoal", SetPageDimen, 0);
+        self.primitive("pagetotal", SetPageDimen, 1);
+        self.primitive("pagestretch", SetPageDimen, 2);
+        self.primitive("pagefilstretch", SetPageDimen, 3);
+        self.primitive("pagefillstretch", SetPageDimen, 4);
+        self.primitive("pagefilllstretch", SetPageDimen, 5);
+        self.primitive("pageshrink", SetPageDimen, 6);
+        self.primitive("pagedepth", SetPageDimen, 7);
+        self.no_new_control_sequence = true;
+    }
+
+    // 268
+    fn save_type(&self, n: usize) -> QuarterWord {
+        self.save_stack[n].hh_b0()
+    }
+
+    fn set_save_type(&mut self, n: usize, value: QuarterWord) {
+        self.save_stack[n].set_hh_b0(value);
+    }
+
+    fn save_level(&self, n: usize) -> QuarterWord {
+        self.save_stack[n].hh_b1()
+    }
+
+    fn set_save_level(&mut self, n: usize, value: QuarterWord) {
+        self.save_stack[n].set_hh_b1(value);
+    }
+
+    fn save_index(&self, n: usize) -> HalfWord {
+        self.save_stack[n].hh_rh()
+    }
+
+    fn set_save_index(&mut self, n: usize, value: HalfWord) {
+        self.save_stack[n].set_hh_rh(value);
+    }
+
+    // 274
+    // saved(k) is the integer k words above save_ptr
+    pub fn saved(&self, k: i32) -> i32 {
+        self.save_stack[(self.save_ptr + k) as usize].int()
+    }
+
+    pub fn set_saved(&mut self, k: i32, value: i32) {
+        self.save_stack[(self.save_ptr + k) as usize].set_int(value);
+    }
+
+    // 273
+    fn check_full_save_stack(&mut self) {
+        if self.save_ptr > self.max_save_stack {
+            self.max_save_stack = self.save_ptr;
+            if self.max_save_stack > SaveSize - 6 {
+                self.overflow("save size", SaveSize);
+            }
+        }
+    }
+
+    // 274
+    // begin a new level of grouping
+    pub fn new_save_level(&mut self, c: QuarterWord) {
+        self.check_full_save_stack();
+        let s = self.save_ptr as usize;
+        self.set_save_type(s, LevelBoundary);
+        self.set_save_level(s, self.cur_group.value());
+        self.set_save_index(s, self.cur_boundary as HalfWord);
+        if self.cur_level == MaxQuarterWord {
+            self.overflow("grouping levels", (MaxQuarterWord - MinQuarterWord) as i32);
+        } // quit if (cur_level+1) is too big to be stored in eqtb
+        self.cur_boundary = self.save_ptr;
+        self.cur_level += 1;
+        self.save_ptr += 1;
+        self.cur_group = GroupCode::from(c);
+    }
+
+    // 275
+    // gets ready to forget w
+    pub fn eq_destroy(&mut self, w: MemoryWord) {
+        match w.hh_b0() {
+            Call | LongCall | OuterCall | LongOuterCall => self.delete_token_ref(w.hh_rh()),
+            GlueRef => self.delete_glue_ref(w.hh_rh()),
+            ShapeRef => {
+                // we need to free a \parshape block
+                let q = w.hh_rh();
+                if q != Null {
+                    let s = self.info(q as usize) as usize;
+                    self.free_node(q, s + s + 1);
+                } // such a block is 2n+1 words long, where n=info(q)
+            }
+            BoxRef => self.flush_node_list(w.hh_rh()),
+            _ => {}
+        }
+    }
+
+    // 276
+    // saves eqtb[p]
+    pub fn eq_save(&mut self, p: Pointer, l: QuarterWord) {
+        self.check_full_save_stack();
+        if l as i32 == LevelZero {
+            let s = self.save_ptr as usize;
+            self.set_save_type(s, RestoreZero);
+        } else {
+            self.save_stack[self.save_ptr as usize] = self.eqtb[p as usize];
+            self.save_ptr += 1;
+            let s = self.save_ptr as usize;
+            self.set_save_type(s, RestoreOldValue);
+        }
+        let s = self.save_ptr as usize;
+        self.set_save_level(s, l);
+        self.set_save_index(s, p);
+        self.save_ptr += 1;
+    }
+
+    // 277
+    // new data for eqtb
+    pub fn eq_define(&mut self, p: Pointer, t: QuarterWord, e: HalfWord) {
+        let q = p as usize;
+        if self.eq_level(q) == self.cur_level {
+            self.eq_destroy(self.eqtb[q]);
+        } else if self.cur_level as i32 > LevelOne {
+            self.eq_save(p, self.eq_level(q));
+        }
+        self.set_eq_level(q, self.cur_level);
+        self.set_eq_type(q, t);
+        self.set_equiv(q, e);
+    }
+
+    // 278
+    pub fn eq_word_define(&mut self, p: Pointer, w: i32) {
+        if self.xeq_level[p as usize] != self.cur_level {
+            self.eq_save(p, self.xeq_level[p as usize]);
+            self.xeq_level[p as usize] = self.cur_level;
+        }
+        self.eqtb[p as usize].set_int(w);
+    }
+
+    // 279
+    // global eq_define
+    pub fn geq_define(&mut self, p: Pointer, t: QuarterWord, e: HalfWord) {
+        let q = p as usize;
+        self.eq_destroy(self.eqtb[q]);
+        self.set_eq_level(q, LevelOne as QuarterWord);
+        self.set_eq_type(q, t);
+        self.set_equiv(q, e);
+    }
+
+    // global eq_word_define
+    pub fn geq_word_define(&mut self, p: Pointer, w: i32) {
+        self.eqtb[p as usize].set_int(w);
+        self.xeq_level[p as usize] = LevelOne as QuarterWord;
+    }
+
+    // 280
+    pub fn save_for_after(&mut self, t: HalfWord) {
+        if self.cur_level as i32 > LevelOne {
+            self.check_full_save_stack();
+            let s = self.save_ptr as usize;
+            self.set_save_type(s, InsertToken);
+            self.set_save_level(s, LevelZero as QuarterWord);
+            self.set_save_index(s, t);
+            self.save_ptr += 1;
+        }
+    }
+
+    // 281
+    // pops the top level off the save stack
+    pub fn unsave(&mut self) {
+        if self.cur_level as i32 > LevelOne {
+            self.cur_level -= 1;
+            // 282: clear off top level from save_stack
+            let mut l: QuarterWord = 0;
+            loop {
+                self.save_ptr -= 1;
+                let s = self.save_ptr as usize;
+                if self.save_type(s) == LevelBoundary {
+                    break;
+                }
+                let p = self.save_index(s);
+                if self.save_type(s) == InsertToken {
+                    // 326: insert token p into TeX's input
+                    let t = self.cur_tok;
+                    self.cur_tok = p;
+                    self.back_input();
+                    self.cur_tok = t;
+                } else {
+                    if self.save_type(s) == RestoreOldValue {
+                        l = self.save_level(s);
+                        self.save_ptr -= 1;
+                    } else {
+                        self.save_stack[s] = self.eqtb[UndefinedControlSequence as usize];
+                    }
+                    // 283: store save_stack[save_ptr] in eqtb[p], unless eqtb[p] holds a global value
+                    let s = self.save_ptr as usize;
+                    if p < IntBase {
+                        if self.eq_level(p as usize) as i32 == LevelOne {
+                            self.eq_destroy(self.save_stack[s]); // destroy the saved value
+                        } else {
+                            self.eq_destroy(self.eqtb[p as usize]); // destroy the current value
+                            self.eqtb[p as usize] = self.save_stack[s]; // restore the saved value
+                        }
+                    } else if self.xeq_level[p as usize] as i32 != LevelOne {
+                        self.eqtb[p as usize] = self.save_stack[s];
+                        self.xeq_level[p as usize] = l;
+                    }
+                }
+            }
+            let s = self.save_ptr as usize;
+            self.cur_group = GroupCode::from(self.save_level(s));
+            self.cur_boundary = self.save_index(s) as i32;
+        } else {
+            self.confusion("curlevel"); // unsave is not used when cur_group=bottom_level
+        }
+    }
+
+    // 288
+    pub fn prepare_mag(&mut self) {
+        let mag = self.int_par(MagCode);
+        if self.mag_set > 0 && mag != self.mag_set {
+            self.print_err("Incompatible magnification (");
+            self.print_int(mag);
+            self.print(");");
+            self.print_nl(" the previous value will be retained");
+            self.help(&["I can handle only one magnification ratio per job. So I've",
+                "reverted to the magnification you used earlier on this run."]);
+            self.int_error(self.mag_set);
+            self.geq_word_define((IntBase + MagCode) as Pointer, self.mag_set); // mag:=mag_set
+        }
+        let mag = self.int_par(MagCode);
+        if mag <= 0 || mag > 32768 {
+            self.print_err("Illegal magnification has been changed to 1000");
+            self.help(&["The magnification ratio must be between 1 and 32768."]);
+            self.int_error(mag);
+            self.geq_word_define((IntBase + MagCode) as Pointer, 1000);
+        }
+        self.mag_set = self.int_par(MagCode);
+    }
+}
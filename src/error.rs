@@ -0,0 +1,300 @@
+use crate::*;
+
+impl TexState {
+    // 73
+    // prints the beginning of an error message
+    pub fn print_err(&mut self, s: &str) {
+        self.print_nl("! ");
+        self.print(s);
+    }
+
+    // 79
+    // sets the help lines, in the order in which they are to be printed
+    pub fn help(&mut self, lines: &[&'static str]) {
+        for (k, l) in lines.iter().enumerate() {
+            self.helpline[k] = l;
+        }
+        self.help_ptr = lines.len() as u8;
+    }
+
+    // 81
+    // terminates the run: unwinds to the end_of_TEX label of TeX's main body,
+    // which closes the files
+    pub fn jump_out(&mut self) -> ! {
+        std::panic::resume_unwind(Box::new(EndOfTex))
+    }
+
+    // 82
+    // completes the job of error reporting
+    pub fn error(&mut self) {
+        if self.history < History::ErrorMessageIssued {
+            self.history = History::ErrorMessageIssued;
+        }
+        self.print_char('.' as u8);
+        self.show_context();
+        if self.interaction == Interaction::ErrorStopMode {
+            // 83: get user's advice and return
+            loop {
+                if self.interaction != Interaction::ErrorStopMode {
+                    return;
+                }
+                self.clear_for_error_prompt();
+                self.prompt_input("? ");
+                if self.last == self.first {
+                    return;
+                }
+                let mut c = self.buffer[self.first];
+                if c >= 'a' as u8 {
+                    c = c + 'A' as u8 - 'a' as u8; // convert to uppercase
+                }
+                // 84: interpret code c and return if done
+                match c {
+                    b'0'..=b'9' if self.deletions_allowed => {
+                        self.delete_tokens(c);
+                        continue;
+                    }
+                    b'E' if self.base_ptr > 0 && self.input_stack[self.base_ptr].name_field >= 256 => {
+                        self.print_nl("You want to edit file ");
+                        self.slow_print(self.input_stack[self.base_ptr].name_field as StrNumber);
+                        self.print(" at line ");
+                        self.print_int(self.line);
+                        self.interaction = Interaction::ScrollMode;
+                        self.jump_out();
+                    }
+                    b'H' => {
+                        // 89: print the help information and continue
+                        if self.use_err_help {
+                            self.give_err_help();
+                            self.use_err_help = false;
+                        } else {
+                            if self.help_ptr == 0 {
+                                self.help(&["Sorry, I don't know how to help in this situation.",
+                                    "Maybe you should try asking a human?"]);
+                            }
+                            for k in 0..self.help_ptr as usize {
+                                self.print(self.helpline[k]);
+                                self.print_ln();
+                            }
+                        }
+                        self.help(&["Sorry, I already gave what help I could...",
+                            "Maybe you should try asking a human?",
+                            "An error might have occurred before I noticed any problems.",
+                            "``If all else fails, read the instructions.''"]);
+                        continue;
+                    }
+                    b'I' => {
+                        // 87: introduce new material from the terminal and return
+                        self.begin_file_reading();
+                        if self.last > self.first + 1 {
+                            self.cur_input.loc_field = (self.first + 1) as HalfWord;
+                            self.buffer[self.first] = ' ' as u8;
+                        } else {
+                            self.prompt_input("insert>");
+                            self.cur_input.loc_field = self.first as HalfWord;
+                        }
+                        self.first = self.last;
+                        self.cur_input.limit_field = (self.last - 1) as HalfWord; // no end_line_char ends this line
+                        return;
+                    }
+                    b'Q' | b'R' | b'S' => {
+                        // 86: change the interaction level and return
+                        self.error_count = 0;
+                        self.print("OK, entering ");
+                        match c {
+                            b'Q' => {
+                                self.interaction = Interaction::BatchMode;
+                                self.print_esc("batchmode");
+                                self.selector -= 1;
+                            }
+                            b'R' => {
+                                self.interaction = Interaction::NonstopMode;
+                                self.print_esc("nonstopmode");
+                            }
+                            _ => {
+                                self.interaction = Interaction::ScrollMode;
+                                self.print_esc("scrollmode");
+                            }
+                        }
+                        self.print("...");
+                        self.print_ln();
+                        self.update_terminal();
+                        return;
+                    }
+                    b'X' => {
+                        self.interaction = Interaction::ScrollMode;
+                        self.jump_out();
+                    }
+                    _ => {}
+                }
+                // 85: print the menu of available options
+                self.print("Type <return> to proceed, S to scroll future error messages,");
+                self.print_nl("R to run without stopping, Q to run quietly,");
+                self.print_nl("I to insert something, ");
+                if self.base_ptr > 0 && self.input_stack[self.base_ptr].name_field >= 256 {
+                    self.print("E to edit your file,");
+                }
+                if self.deletions_allowed {
+                    self.print_nl("1 or ... or 9 to ignore the next 1 to 9 tokens of input,");
+                }
+                self.print_nl("H for help, X to quit.");
+            }
+        }
+        self.error_count += 1;
+        if self.error_count == 100 {
+            self.print_nl("(That makes 100 errors; please try again.)");
+            self.history = History::FatalErrorStop;
+            self.jump_out();
+        }
+        // 90: put help message on the transcript file
+        if self.interaction > Interaction::BatchMode {
+            self.selector -= 1; // avoid terminal output
+        }
+        if self.use_err_help {
+            self.print_ln();
+            self.give_err_help();
+        } else {
+            for k in 0..self.help_ptr as usize {
+                self.print_nl(self.helpline[k]);
+            }
+            self.help_ptr = 0;
+        }
+        self.print_ln();
+        if self.interaction > Interaction::BatchMode {
+            self.selector += 1; // re-enable terminal output
+        }
+        self.print_ln();
+    }
+
+    // 88
+    // deletes the number of tokens typed by the user, then goes back to the prompt
+    fn delete_tokens(&mut self, c: ASCIICode) {
+        let s1 = self.cur_tok;
+        let s2 = self.cur_cmd;
+        let s3 = self.cur_chr;
+        let s4 = self.align_state;
+        self.align_state = 1000000;
+        self.OK_to_interrupt = false;
+        let mut c = if self.last > self.first + 1
+            && self.buffer[self.first + 1] >= '0' as u8 && self.buffer[self.first + 1] <= '9' as u8
+        {
+            (c - '0' as u8) as i32 * 10 + (self.buffer[self.first + 1] - '0' as u8) as i32
+        } else {
+            (c - '0' as u8) as i32
+        };
+        while c > 0 {
+            self.get_token(); // one-level recursive call of error is possible
+            c -= 1;
+        }
+        self.cur_tok = s1;
+        self.cur_cmd = s2;
+        self.cur_chr = s3;
+        self.align_state = s4;
+        self.OK_to_interrupt = true;
+        self.help(&["I have just deleted some text, as you asked.",
+            "You can now delete more, or insert, or whatever."]);
+        self.show_context();
+    }
+
+    // 91
+    pub fn int_error(&mut self, n: i32) {
+        self.print(" (");
+        self.print_int(n);
+        self.print_char(')' as u8);
+        self.error();
+    }
+
+    // 92
+    pub fn normalize_selector(&mut self) {
+        if self.log_opened {
+            self.selector = TermAndLog;
+        } else {
+            self.selector = TermOnly;
+        }
+        if self.interaction == Interaction::BatchMode {
+            self.selector -= 1;
+        }
+    }
+
+    // 93
+    // prints TeX's last words before dying
+    pub fn succumb(&mut self) -> ! {
+        if self.interaction == Interaction::ErrorStopMode {
+            self.interaction = Interaction::ScrollMode; // no more interaction
+        }
+        if self.log_opened {
+            self.error();
+        }
+        self.history = History::FatalErrorStop;
+        self.jump_out();
+    }
+
+    // prints s, and that's it
+    pub fn fatal_error(&mut self, s: &'static str) -> ! {
+        self.normalize_selector();
+        self.print_err("Emergency stop");
+        self.help(&[s]);
+        self.succumb();
+    }
+
+    // 94
+    // stop due to finiteness
+    pub fn overflow(&mut self, s: &str, n: i32) -> ! {
+        self.normalize_selector();
+        self.print_err("TeX capacity exceeded, sorry [");
+        self.print(s);
+        self.print_char('=' as u8);
+        self.print_int(n);
+        self.print_char(']' as u8);
+        self.help(&["If you really absolutely need more capacity,",
+            "you can ask a wizard to enlarge me."]);
+        self.succumb();
+    }
+
+    // 95
+    // consistency check was violated
+    pub fn confusion(&mut self, s: &str) -> ! {
+        self.normalize_selector();
+        if self.history < History::ErrorMessageIssued {
+            self.print_err("This can't happen (");
+            self.print(s);
+            self.print_char(')' as u8);
+            self.help(&["I'm broken. Please show this to someone who can fix can fix"]);
+        } else {
+            self.print_err("I can't go on meeting you like this");
+            self.help(&["One of your faux pas seems to have wounded me deeply...",
+                "in fact, I'm barely conscious. Please fix it and try again."]);
+        }
+        self.succumb();
+    }
+
+    // 96
+    // look for an interrupt typed by the user
+    pub fn check_interrupt(&mut self) {
+        if self.interrupt != 0 {
+            self.pause_for_instructions();
+        }
+    }
+
+    // 98
+    pub fn pause_for_instructions(&mut self) {
+        if self.OK_to_interrupt {
+            self.interaction = Interaction::ErrorStopMode;
+            if self.selector == LogOnly || self.selector == NoPrint {
+                self.selector += 1;
+            }
+            self.print_err("Interruption");
+            self.help(&["You rang?",
+                "Try to insert an instruction for me (e.g., `I\\showlists'),",
+                "unless you just want to quit by typing `X'."]);
+            self.deletions_allowed = false;
+            self.error();
+            self.deletions_allowed = true;
+            self.interrupt = 0;
+        }
+    }
+
+    // 1284
+    pub fn give_err_help(&mut self) {
+        self.token_show(self.err_help());
+    }
+}
@@ -0,0 +1,438 @@
+use crate::*;
+
+impl TexState {
+    // 366
+    // expands the current token, which is known to be expandable
+    pub fn expand(&mut self) {
+        let cv_backup = self.cur_val; // to save the global quantity
+        let cvl_backup = self.cur_val_level;
+        let radix_backup = self.radix;
+        let co_backup = self.cur_order;
+        let backup_backup = self.link(BackupHead); // to save link(backup_head)
+        if self.cur_cmd < Call {
+            // 367: expand a nonmacro
+            if self.int_par(TracingCommandsCode) > 1 {
+                self.show_cur_cmd_chr();
+            }
+            match self.cur_cmd {
+                TopBotMark => self.insert_mark_tokens(),
+                ExpandAfter => {
+                    // 368: expand the token after the next token
+                    self.get_token();
+                    let t = self.cur_tok;
+                    self.get_token();
+                    if self.cur_cmd > MaxCommand {
+                        self.expand();
+                    } else {
+                        self.back_input();
+                    }
+                    self.cur_tok = t;
+                    self.back_input();
+                }
+                NoExpand => {
+                    // 369: suppress expansion of the next token
+                    let save_scanner_status = self.scanner_status;
+                    self.scanner_status = Normal;
+                    self.get_token();
+                    self.scanner_status = save_scanner_status;
+                    let t = self.cur_tok;
+                    self.back_input(); // now start and loc point to the backed-up token t
+                    if t as i32 >= cs_token_flag {
+                        let p = self.get_avail();
+                        self.set_info(p as usize, cs_token_flag as HalfWord + FrozenDontExpand);
+                        self.set_link(p as usize, self.cur_input.loc_field);
+                        self.cur_input.start_field = p;
+                        self.cur_input.loc_field = p;
+                    }
+                }
+                CsName => self.manufacture_cs_name(),
+                Input => {
+                    // 378: initiate or terminate input from a file
+                    if self.cur_chr > 0 {
+                        self.force_eof = true;
+                    } else if self.name_in_progress {
+                        self.insert_relax();
+                    } else {
+                        self.start_input();
+                    }
+                }
+                Convert => self.conv_toks(), // this procedure is discussed in Part 27 below
+                The => self.ins_the_toks(), // this procedure is discussed in Part 27 below
+                IfTest => self.conditional(), // this procedure is discussed in Part 28 below
+                FiOrElse => self.terminate_conditional(),
+                _ => {
+                    // 370: complain about an undefined macro
+                    self.print_err("Undefined control sequence");
+                    self.help(&["The control sequence at the end of the top line",
+                        "of your error message was never \\def'ed. If you have",
+                        "misspelled it (e.g., `\\hobx'), type `I' and the correct",
+                        "spelling (e.g., `I\\hbox'). Otherwise just continue,",
+                        "and I'll forget about whatever was undefined."]);
+                    self.error();
+                }
+            }
+        } else if self.cur_cmd < EndTemplate {
+            self.macro_call();
+        } else {
+            // 375: insert a token containing frozen_endv
+            self.cur_tok = cs_token_flag as HalfWord + FrozenEndv;
+            self.back_input();
+        }
+        self.cur_val = cv_backup;
+        self.cur_val_level = cvl_backup;
+        self.radix = radix_backup;
+        self.cur_order = co_backup;
+        self.set_link(BackupHead, backup_backup);
+    }
+
+    // 372
+    // manufacture a control sequence name from the tokens up to \endcsname
+    fn manufacture_cs_name(&mut self) {
+        let r = self.get_avail();
+        let mut p = r; // head of the list of characters
+        loop {
+            self.get_x_token();
+            if self.cur_cs == 0 {
+                p = self.store_new_token(p, self.cur_tok);
+            }
+            if self.cur_cs != 0 {
+                break;
+            }
+        }
+        if self.cur_cmd != EndCsName {
+            // 373: complain about missing \endcsname
+            self.print_err("Missing ");
+            self.print_esc("endcsname");
+            self.print(" inserted");
+            self.help(&["The control sequence marked <to be read again> should",
+                "not appear between \\csname and \\endcsname."]);
+            self.back_error();
+        }
+        // 374: look up the characters of list r in the hash table, and set cur_cs
+        let mut j = self.first;
+        p = self.link(r as usize);
+        while p != Null {
+            if j >= self.max_buf_stack {
+                self.max_buf_stack = j + 1;
+                if self.max_buf_stack == BufSize as usize {
+                    self.overflow("buffer size", BufSize);
+                }
+            }
+            self.buffer[j] = (self.info(p as usize) % 0o400) as u8;
+            j += 1;
+            p = self.link(p as usize);
+        }
+        if j > self.first + 1 {
+            self.no_new_control_sequence = false;
+            self.cur_cs = self.id_lookup(self.first, j - self.first);
+            self.no_new_control_sequence = true;
+        } else if j == self.first {
+            self.cur_cs = NullCs; // the list is empty
+        } else {
+            self.cur_cs = SingleBase + self.buffer[self.first] as HalfWord; // the list has length one
+        }
+        self.flush_list(r);
+        if self.eq_type(self.cur_cs as usize) == UndefinedCs {
+            self.eq_define(self.cur_cs, Relax, 256); // N.B.: The save_stack might change
+        } // the control sequence will now match `\relax'
+        self.cur_tok = self.cur_cs + cs_token_flag as HalfWord;
+        self.back_input();
+    }
+
+    // 379
+    // inserts \relax after the current control sequence, as a protection
+    pub fn insert_relax(&mut self) {
+        self.cur_tok = cs_token_flag as HalfWord + self.cur_cs;
+        self.back_input();
+        self.cur_tok = cs_token_flag as HalfWord + FrozenRelax;
+        self.back_input();
+        self.cur_input.index_field = Inserted;
+    }
+
+    // 380
+    // sets cur_cmd, cur_chr, cur_tok, and expands macros
+    pub fn get_x_token(&mut self) {
+        loop {
+            self.get_next();
+            if self.cur_cmd <= MaxCommand {
+                break;
+            }
+            if self.cur_cmd >= Call {
+                if self.cur_cmd < EndTemplate {
+                    self.macro_call();
+                } else {
+                    self.cur_cs = FrozenEndv;
+                    self.cur_cmd = Endv;
+                    break; // cur_chr=null_list
+                }
+            } else {
+                self.expand();
+            }
+        }
+        if self.cur_cs == 0 {
+            self.cur_tok = (self.cur_cmd as HalfWord * 0o400) + self.cur_chr;
+        } else {
+            self.cur_tok = cs_token_flag as HalfWord + self.cur_cs;
+        }
+    }
+
+    // 381
+    // get_x_token without the initial get_next
+    pub fn x_token(&mut self) {
+        while self.cur_cmd > MaxCommand {
+            self.expand();
+            self.get_next();
+        }
+        if self.cur_cs == 0 {
+            self.cur_tok = (self.cur_cmd as HalfWord * 0o400) + self.cur_chr;
+        } else {
+            self.cur_tok = cs_token_flag as HalfWord + self.cur_cs;
+        }
+    }
+
+    // 389
+    // invokes a user-defined control sequence
+    pub fn macro_call(&mut self) {
+        let save_scanner_status = self.scanner_status; // scanner_status upon entry
+        let save_warning_index = self.warning_index; // warning_index upon entry
+        self.warning_index = self.cur_cs;
+        self.expand_macro();
+        self.scanner_status = save_scanner_status;
+        self.warning_index = save_warning_index;
+    }
+
+    fn expand_macro(&mut self) {
+        let ref_count = self.cur_chr; // start of the token list
+        let mut r = self.link(ref_count as usize); // current node in the macro's token list
+        let mut n = 0; // the number of parameters scanned
+        if self.int_par(TracingMacrosCode) > 0 {
+            // 401: show the text of the macro being expanded
+            self.begin_diagnostic();
+            self.print_ln();
+            self.print_cs(self.warning_index as i32);
+            self.token_show(ref_count);
+            self.end_diagnostic(false);
+        }
+        if self.info(r as usize) != end_match_token as HalfWord {
+            // 391: scan the parameters and make link(r) point to the macro body; but
+            // return if an illegal \par is detected
+            self.scanner_status = Matching;
+            let mut unbalance = 0; // unmatched left braces in current parameter
+            self.long_state = self.eq_type(self.cur_cs as usize);
+            if self.long_state >= OuterCall {
+                self.long_state -= 2;
+            }
+            let mut p = TempHead as Pointer; // current node in parameter token list being built
+            let mut s; // backup pointer for parameter matching
+            let mut m = 0; // the number of tokens or groups (usually)
+            let mut rbrace_ptr = Null; // one step before the last right_brace token
+            let mut match_chr = 0; // character used in parameter
+            loop {
+                self.set_link(TempHead, Null);
+                let i = self.info(r as usize) as i32;
+                if !(match_token..=match_token + 255).contains(&i) {
+                    s = Null;
+                } else {
+                    match_chr = (i - match_token) as ASCIICode;
+                    s = self.link(r as usize);
+                    r = s;
+                    p = TempHead as Pointer;
+                    m = 0;
+                }
+                // 392: scan a parameter until its delimiter string has been found; or,
+                // if s=null, simply scan the delimiter string
+                'resume: loop {
+                    self.get_token(); // set cur_tok to the next token of input
+                    if self.cur_tok == self.info(r as usize) {
+                        // 394: advance r; goto found if the parameter delimiter has been
+                        // fully matched, otherwise goto continue
+                        r = self.link(r as usize);
+                        let i = self.info(r as usize) as i32;
+                        if (match_token..=end_match_token).contains(&i) {
+                            if (self.cur_tok as i32) < left_brace_limit {
+                                self.align_state -= 1;
+                            }
+                            break 'resume;
+                        }
+                        continue 'resume;
+                    }
+                    // 397: contribute the recently matched tokens to the current parameter,
+                    // and goto continue if a partial match is still in effect;
+                    // but abort if s=null
+                    if s != r {
+                        if s == Null {
+                            // 398: report an improper use of the macro and abort
+                            self.print_err("Use of ");
+                            self.sprint_cs(self.warning_index);
+                            self.print(" doesn't match its definition");
+                            self.help(&["If you say, e.g., `\\def\\a1{...}', then you must always",
+                                "put `1' after `\\a', since control sequence names are",
+                                "made up of letters only. The macro here has not been",
+                                "followed by the required stuff, so I'm ignoring it."]);
+                            self.error();
+                            return;
+                        }
+                        let mut t = s;
+                        loop {
+                            p = self.store_new_token(p, self.info(t as usize));
+                            m += 1;
+                            let mut u = self.link(t as usize);
+                            let mut v = s;
+                            loop {
+                                if u == r {
+                                    if self.cur_tok != self.info(v as usize) {
+                                        break;
+                                    }
+                                    r = self.link(v as usize);
+                                    continue 'resume;
+                                }
+                                if self.info(u as usize) != self.info(v as usize) {
+                                    break;
+                                }
+                                u = self.link(u as usize);
+                                v = self.link(v as usize);
+                            }
+                            t = self.link(t as usize);
+                            if t == r {
+                                break;
+                            }
+                        }
+                        r = s; // at this point, no tokens are recently matched
+                    }
+                    if self.cur_tok == self.par_token && self.long_state != LongCall {
+                        self.report_runaway_argument(n, unbalance);
+                        return;
+                    }
+                    if (self.cur_tok as i32) < right_brace_limit {
+                        if (self.cur_tok as i32) < left_brace_limit {
+                            // 399: contribute an entire group to the current parameter
+                            unbalance = 1;
+                            loop {
+                                p = self.store_new_token(p, self.cur_tok);
+                                self.get_token();
+                                if self.cur_tok == self.par_token && self.long_state != LongCall {
+                                    self.report_runaway_argument(n, unbalance);
+                                    return;
+                                }
+                                if (self.cur_tok as i32) < right_brace_limit {
+                                    if (self.cur_tok as i32) < left_brace_limit {
+                                        unbalance += 1;
+                                    } else {
+                                        unbalance -= 1;
+                                        if unbalance == 0 {
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+                            rbrace_ptr = p;
+                            p = self.store_new_token(p, self.cur_tok);
+                        } else {
+                            // 395: report an extra right brace and goto continue
+                            self.back_input();
+                            self.print_err("Argument of ");
+                            self.sprint_cs(self.warning_index);
+                            self.print(" has an extra }");
+                            self.help(&["I've run across a `}' that doesn't seem to match anything.",
+                                "For example, `\\def\\a#1{...}' and `\\a}' would produce",
+                                "this error. If you simply proceed now, the `\\par' that",
+                                "I've just inserted will cause me to report a runaway",
+                                "argument that might be the root of the problem. But if",
+                                "your `}' was spurious, just type `2' and it will go away."]);
+                            self.align_state += 1;
+                            self.long_state = Call;
+                            self.cur_tok = self.par_token;
+                            self.ins_error();
+                            continue 'resume;
+                        } // a white lie; the \par won't always trigger a runaway
+                    } else {
+                        // 393: store the current token, but goto continue if it is
+                        // a blank space that would become an undelimited parameter
+                        if self.cur_tok as i32 == space_token {
+                            let i = self.info(r as usize) as i32;
+                            if (match_token..=end_match_token).contains(&i) {
+                                continue 'resume;
+                            }
+                        }
+                        p = self.store_new_token(p, self.cur_tok);
+                    }
+                    m += 1;
+                    let i = self.info(r as usize) as i32;
+                    if !(match_token..=end_match_token).contains(&i) {
+                        continue 'resume;
+                    }
+                    break 'resume;
+                }
+                // found:
+                if s != Null {
+                    // 400: tidy up the parameter just scanned, and tuck it away
+                    if m == 1 && (self.info(p as usize) as i32) < right_brace_limit && p != TempHead as Pointer {
+                        self.set_link(rbrace_ptr as usize, Null);
+                        self.free_avail(p);
+                        p = self.link(TempHead);
+                        self.pstack[n] = self.link(p as usize);
+                        self.free_avail(p);
+                    } else {
+                        self.pstack[n] = self.link(TempHead);
+                    }
+                    n += 1;
+                    if self.int_par(TracingMacrosCode) > 0 {
+                        self.begin_diagnostic();
+                        self.print_nl("");
+                        self.print_str(match_chr as StrNumber);
+                        self.print_int(n as i32);
+                        self.print("<-");
+                        self.show_token_list(self.pstack[n - 1] as i32, Null as i32, 1000);
+                        self.end_diagnostic(false);
+                    }
+                }
+                // now info(r) is a token whose command code is either match or end_match
+                if self.info(r as usize) == end_match_token as HalfWord {
+                    break;
+                }
+            }
+        }
+        // 390: feed the macro body and its parameters to the scanner
+        while self.cur_input.state_field == TokenList
+            && self.cur_input.loc_field == Null
+            && self.token_type() != VTemplate
+        {
+            self.end_token_list(); // conserve stack space
+        }
+        self.begin_token_list(ref_count, Macro);
+        self.cur_input.name_field = self.warning_index;
+        self.cur_input.loc_field = self.link(r as usize);
+        if n > 0 {
+            if self.param_ptr + n > self.max_param_stack {
+                self.max_param_stack = self.param_ptr + n;
+                if self.max_param_stack > ParamSize as usize {
+                    self.overflow("parameter stack size", ParamSize);
+                }
+            }
+            for m in 0..n {
+                self.param_stack[self.param_ptr + m] = self.pstack[m];
+            }
+            self.param_ptr += n;
+        }
+    }
+
+    // 396
+    // report a runaway argument and abort
+    fn report_runaway_argument(&mut self, n: usize, unbalance: i32) {
+        if self.long_state == Call {
+            self.runaway();
+            self.print_err("Paragraph ended before ");
+            self.sprint_cs(self.warning_index);
+            self.print(" was complete");
+            self.help(&["I suspect you've forgotten a `}', causing me to apply this",
+                "control sequence to too much text. How can we recover?",
+                "My plan is to forget the whole thing and hope for the best."]);
+            self.back_error();
+        }
+        self.pstack[n] = self.link(TempHead);
+        self.align_state -= unbalance;
+        for m in 0..=n {
+            self.flush_list(self.pstack[m]);
+        }
+    }
+}
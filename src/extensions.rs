@@ -0,0 +1,394 @@
+use crate::*;
+
+// 1341
+// the subtypes of whatsit nodes
+pub const OpenNode: QuarterWord = 0; // subtype in whatsits that represent files to \openout
+pub const WriteNode: QuarterWord = 1; // subtype in whatsits that represent things to \write
+pub const CloseNode: QuarterWord = 2; // subtype in whatsits that represent streams to \closeout
+pub const SpecialNode: QuarterWord = 3; // subtype in whatsits that represent \special things
+pub const LanguageNode: QuarterWord = 4; // subtype in whatsits that change the current language
+pub const WriteNodeSize: usize = 2; // number of words in a write/whatsit node
+pub const OpenNodeSize: usize = 3; // number of words in an open/whatsit node
+pub const ImmediateCode: QuarterWord = 4; // command modifier for \immediate
+pub const SetLanguageCode: QuarterWord = 5; // command modifier for \setlanguage
+
+impl TexState {
+    // 1341
+    // reference count of a token list to write
+    pub fn write_tokens(&self, p: usize) -> HalfWord {
+        self.link(p + 1)
+    }
+
+    pub fn set_write_tokens(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 1, value);
+    }
+
+    // the write stream of a whatsit, which a special node doesn't use
+    pub fn write_stream(&self, p: usize) -> HalfWord {
+        self.info(p + 1)
+    }
+
+    fn set_write_stream(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 1, value);
+    }
+
+    // the string number of the file name to open
+    pub fn open_name(&self, p: usize) -> HalfWord {
+        self.link(p + 1)
+    }
+
+    fn set_open_name(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 1, value);
+    }
+
+    // the string number of the file area for open_name
+    pub fn open_area(&self, p: usize) -> HalfWord {
+        self.info(p + 2)
+    }
+
+    fn set_open_area(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 2, value);
+    }
+
+    // the string number of the file extension for open_name
+    pub fn open_ext(&self, p: usize) -> HalfWord {
+        self.link(p + 2)
+    }
+
+    fn set_open_ext(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 2, value);
+    }
+
+    // the language number of a language whatsit
+    pub fn what_lang(&self, p: usize) -> HalfWord {
+        self.link(p + 1)
+    }
+
+    pub fn set_what_lang(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 1, value);
+    }
+
+    // the \lefthyphenmin in force with a language whatsit
+    pub fn what_lhm(&self, p: usize) -> QuarterWord {
+        self.a_type(p + 1)
+    }
+
+    pub fn set_what_lhm(&mut self, p: usize, value: QuarterWord) {
+        self.set_type(p + 1, value);
+    }
+
+    // the \righthyphenmin in force with a language whatsit
+    pub fn what_rhm(&self, p: usize) -> QuarterWord {
+        self.subtype(p + 1)
+    }
+
+    pub fn set_what_rhm(&mut self, p: usize, value: QuarterWord) {
+        self.set_subtype(p + 1, value);
+    }
+
+    // 1348
+    // the \openout, \write, \special and other extension commands
+    pub fn do_extension(&mut self) {
+        match self.cur_chr as QuarterWord {
+            OpenNode => {
+                // 1351: implement \openout
+                self.new_write_whatsit(OpenNodeSize);
+                self.scan_optional_equals();
+                self.scan_file_name();
+                self.set_open_name(self.tail() as usize, self.cur_name as HalfWord);
+                self.set_open_area(self.tail() as usize, self.cur_area as HalfWord);
+                self.set_open_ext(self.tail() as usize, self.cur_ext as HalfWord);
+            }
+            WriteNode => {
+                // 1352: implement \write
+                let k = self.cur_cs;
+                self.new_write_whatsit(WriteNodeSize);
+                self.cur_cs = k;
+                self.scan_toks(false, false);
+                self.set_write_tokens(self.tail() as usize, self.def_ref);
+            }
+            CloseNode => {
+                // 1353: implement \closeout
+                self.new_write_whatsit(WriteNodeSize);
+                self.set_write_tokens(self.tail() as usize, Null);
+            }
+            SpecialNode => self.make_special(),
+            ImmediateCode => self.immediate(),
+            SetLanguageCode => self.set_language(),
+            _ => self.confusion("ext1"),
+        }
+    }
+
+    // 1349
+    // appends a whatsit node of subtype s and size w to the current list
+    pub fn new_whatsit(&mut self, s: QuarterWord, w: usize) {
+        let p = self.get_node(w);
+        self.set_type(p as usize, WhatsitNode);
+        self.set_subtype(p as usize, s);
+        self.tail_append(p);
+    }
+
+    // 1350
+    // appends a whatsit for \openout, \write or \closeout and scans its stream number
+    fn new_write_whatsit(&mut self, w: usize) {
+        self.new_whatsit(self.cur_chr as QuarterWord, w);
+        if w != WriteNodeSize {
+            self.scan_four_bit_int();
+        } else {
+            self.scan_int();
+            if self.cur_val < 0 {
+                self.cur_val = 17;
+            } else if self.cur_val > 15 {
+                self.cur_val = 16;
+            }
+        }
+        self.set_write_stream(self.tail() as usize, self.cur_val as HalfWord);
+    }
+
+    // 1354
+    // implement \special
+    pub fn make_special(&mut self) {
+        self.new_whatsit(SpecialNode, WriteNodeSize);
+        self.set_write_stream(self.tail() as usize, Null);
+        self.scan_toks(false, true);
+        self.set_write_tokens(self.tail() as usize, self.def_ref);
+    }
+
+    // 1355
+    fn print_write_whatsit(&mut self, s: &str, p: usize) {
+        self.print_esc(s);
+        if self.write_stream(p) < 16 {
+            self.print_int(self.write_stream(p) as i32);
+        } else if self.write_stream(p) == 16 {
+            self.print_char('*' as u8);
+        } else {
+            self.print_char('-' as u8);
+        }
+    }
+
+    // 1356
+    // display the whatsit node p
+    pub fn display_whatsit(&mut self, p: usize) {
+        match self.subtype(p) {
+            OpenNode => {
+                self.print_write_whatsit("openout", p);
+                self.print_char('=' as u8);
+                self.print_file_name(self.open_name(p) as StrNumber, self.open_area(p) as StrNumber,
+                                     self.open_ext(p) as StrNumber);
+            }
+            WriteNode => {
+                self.print_write_whatsit("write", p);
+                self.print_mark(self.write_tokens(p));
+            }
+            CloseNode => self.print_write_whatsit("closeout", p),
+            SpecialNode => {
+                self.print_esc("special");
+                self.print_mark(self.write_tokens(p));
+            }
+            LanguageNode => {
+                self.print_esc("setlanguage");
+                self.print_int(self.what_lang(p) as i32);
+                self.print(" (hyphenmin ");
+                self.print_int(self.what_lhm(p) as i32);
+                self.print_char(',' as u8);
+                self.print_int(self.what_rhm(p) as i32);
+                self.print_char(')' as u8);
+            }
+            _ => self.print("whatsit?"),
+        }
+    }
+
+    // 1357
+    // a copy of the whatsit node p, and the number of its words to copy
+    pub fn copy_whatsit(&mut self, p: usize) -> (Pointer, usize) {
+        match self.subtype(p) {
+            OpenNode => (self.get_node(OpenNodeSize), OpenNodeSize),
+            WriteNode | SpecialNode => {
+                let r = self.get_node(WriteNodeSize);
+                self.add_token_ref(self.write_tokens(p));
+                (r, WriteNodeSize)
+            }
+            CloseNode | LanguageNode => (self.get_node(SmallNodeSize), SmallNodeSize),
+            _ => self.confusion("ext2"),
+        }
+    }
+
+    // 1358
+    // wipe out the whatsit node p
+    pub fn flush_whatsit(&mut self, p: Pointer) {
+        match self.subtype(p as usize) {
+            OpenNode => self.free_node(p, OpenNodeSize),
+            WriteNode | SpecialNode => {
+                self.delete_token_ref(self.write_tokens(p as usize));
+                self.free_node(p, WriteNodeSize);
+            }
+            CloseNode | LanguageNode => self.free_node(p, SmallNodeSize),
+            _ => self.confusion("ext3"),
+        }
+    }
+
+    // 1368
+    // the text of a \special, as its token list prints
+    pub fn special_text(&mut self, p: usize) -> Vec<u8> {
+        let old_setting = self.selector;
+        self.selector = NewString;
+        self.show_token_list(self.link(self.write_tokens(p) as usize) as i32, Null as i32,
+                             PoolSize - self.pool_ptr as i32);
+        self.selector = old_setting;
+        self.str_room(1);
+        let s = (self.str_start[self.str_ptr as usize]..self.pool_ptr).map(|k| self.str_pool[k]).collect();
+        self.pool_ptr = self.str_start[self.str_ptr as usize];
+        s
+    }
+
+    // 1370
+    // writes the token list of the \write node p to its stream
+    fn write_out(&mut self, p: usize) {
+        // 1371: expand macros in the token list and make link(def_ref) point to the result
+        let mut q = self.get_avail();
+        self.set_info(q as usize, (right_brace_token + '}' as i32) as HalfWord);
+        let r = self.get_avail();
+        self.set_link(q as usize, r);
+        self.set_info(r as usize, (cs_token_flag + EndWrite as i32) as HalfWord);
+        self.ins_list(q);
+        self.begin_token_list(self.write_tokens(p), WriteText);
+        q = self.get_avail();
+        self.set_info(q as usize, (left_brace_token + '{' as i32) as HalfWord);
+        self.ins_list(q);
+        // now we're ready to scan `{<token list>} \endwrite'
+        let old_mode = self.mode();
+        self.set_mode(0); // disable \prevdepth, \spacefactor, \lastskip, \prevgraf
+        self.cur_cs = self.write_loc;
+        self.scan_toks(false, true); // expand macros, etc.
+        self.get_token();
+        if self.cur_tok as i32 != cs_token_flag + EndWrite as i32 {
+            // 1372: recover from an unbalanced write command
+            self.print_err("Unbalanced write command");
+            self.help(&["On this page there's a \\write with fewer real {'s than }'s.",
+                "I can't handle that very well; good luck."]);
+            self.error();
+            loop {
+                self.get_token();
+                if self.cur_tok as i32 == cs_token_flag + EndWrite as i32 {
+                    break;
+                }
+            }
+        }
+        self.set_mode(old_mode);
+        self.end_token_list(); // conserve stack space
+        let old_setting = self.selector;
+        let j = self.write_stream(p) as usize;
+        if self.write_open[j] {
+            self.selector = j as i32;
+        } else {
+            // write to the terminal if file is not open
+            if j == 17 && self.selector == TermAndLog {
+                self.selector = LogOnly;
+            }
+            self.print_nl("");
+        }
+        self.token_show(self.def_ref);
+        self.print_ln();
+        self.flush_list(self.def_ref);
+        self.selector = old_setting;
+    }
+
+    // 1374
+    // does the work queued up in the \openout, \write or \closeout node p as it is shipped out
+    pub fn out_write_whatsit(&mut self, p: usize) {
+        if self.doing_leaders {
+            return;
+        }
+        let j = self.write_stream(p) as usize;
+        if self.subtype(p) == WriteNode {
+            self.write_out(p);
+        } else {
+            if self.write_open[j] {
+                self.write_file[j] = None;
+            }
+            if self.subtype(p) == CloseNode {
+                self.write_open[j] = false;
+            } else if j < 16 {
+                self.cur_name = self.open_name(p) as StrNumber;
+                self.cur_area = self.open_area(p) as StrNumber;
+                self.cur_ext = self.open_ext(p) as StrNumber;
+                if self.cur_ext == EmptyString {
+                    self.cur_ext = ExtTex;
+                }
+                self.pack_cur_name();
+                loop {
+                    self.write_file[j] = self.a_open_out();
+                    if self.write_file[j].is_some() {
+                        break;
+                    }
+                    self.prompt_file_name("output file name", ExtTex);
+                }
+                self.write_open[j] = true;
+            }
+        }
+    }
+
+    // 1375
+    // implement \immediate
+    fn immediate(&mut self) {
+        self.get_x_token();
+        if self.cur_cmd == Extension && self.cur_chr <= CloseNode as HalfWord {
+            let p = self.tail();
+            self.do_extension(); // append a whatsit node
+            self.out_write_whatsit(self.tail() as usize); // do the action immediately
+            self.flush_node_list(self.tail());
+            self.set_tail(p);
+            self.set_link(p as usize, Null);
+        } else {
+            self.back_input();
+        }
+    }
+
+    // 1376
+    // puts a language whatsit on the current list if \language has changed
+    pub fn fix_language(&mut self) {
+        let l = self.int_par(LanguageCode);
+        let l = if l <= 0 || l > 255 { 0 } else { l as HalfWord }; // the new current language
+        if l != self.clang() {
+            self.new_whatsit(LanguageNode, SmallNodeSize);
+            self.set_what_lang(self.tail() as usize, l);
+            self.set_clang(l);
+            self.set_what_lhm(self.tail() as usize, norm_min(self.int_par(LeftHyphenMinCode)) as QuarterWord);
+            self.set_what_rhm(self.tail() as usize, norm_min(self.int_par(RightHyphenMinCode)) as QuarterWord);
+        }
+    }
+
+    // 1377
+    // implement \setlanguage
+    fn set_language(&mut self) {
+        if self.mode().abs() != Hmode {
+            self.report_illegal_case();
+        } else {
+            self.new_whatsit(LanguageNode, SmallNodeSize);
+            self.scan_int();
+            let l = if self.cur_val <= 0 || self.cur_val > 255 { 0 } else { self.cur_val as HalfWord };
+            self.set_clang(l);
+            self.set_what_lang(self.tail() as usize, l);
+            self.set_what_lhm(self.tail() as usize, norm_min(self.int_par(LeftHyphenMinCode)) as QuarterWord);
+            self.set_what_rhm(self.tail() as usize, norm_min(self.int_par(RightHyphenMinCode)) as QuarterWord);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn characters_bring_back_the_language_in_force() {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", "\\scrollmode\\language=3 \\indent a\\setlanguage5 b\\showlists\\end\n");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]);
+        assert!(log.contains("### horizontal mode entered at line 1 (language3:hyphenmin1,1)\n\
+                              \\hbox(0.0+0.0)x0.0\n\
+                              \\setlanguage5 (hyphenmin 1,1)\n\
+                              \\setlanguage3 (hyphenmin 1,1)\n\
+                              spacefactor 1000, current language 3\n"), "{}", log);
+    }
+}
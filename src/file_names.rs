@@ -0,0 +1,413 @@
+use crate::*;
+use std::io::{BufReader, Read, Write};
+
+// 514
+// the names TeX gives to files it makes up; they are the first strings after the
+// empty string, so that their numbers are known in advance
+pub const FileNameStrings: [&str; 10] = [".tex", ".log", ".dvi", ".fmt", ".tfm", ".vf", ".pdf", ".pfb", ".svg",
+    "texput"];
+pub const ExtTex: StrNumber = EmptyString + 1;
+pub const ExtLog: StrNumber = EmptyString + 2;
+pub const ExtDvi: StrNumber = EmptyString + 3;
+pub const ExtFmt: StrNumber = EmptyString + 4;
+pub const ExtTfm: StrNumber = EmptyString + 5;
+pub const ExtVf: StrNumber = EmptyString + 6;
+pub const ExtPdf: StrNumber = EmptyString + 7;
+pub const ExtPfb: StrNumber = EmptyString + 8;
+pub const ExtSvg: StrNumber = EmptyString + 9;
+pub const TexputName: StrNumber = EmptyString + 10;
+
+impl TexState {
+    // makes r responsible for finding input files and making output files,
+    // for instance a Vfs so that a document can be typeset from memory
+    pub fn set_resolver(&mut self, r: Box<dyn FileResolver>) {
+        self.resolver = r;
+    }
+
+    // 27
+    // opens name_of_file for input, searching for it as the resolver sees fit;
+    // name_of_file becomes the name under which the file was found
+    fn open_in(&mut self, kind: FileKind) -> Option<Box<dyn Read>> {
+        let (name, f) = self.resolver.open_in(&self.name_of_file, kind)?;
+        self.name_of_file = name;
+        Some(f)
+    }
+
+    // open a text file for input
+    pub fn a_open_in(&mut self) -> Option<AlphaFile> {
+        self.open_in(FileKind::Tex).map(|f| Box::new(BufReader::new(f)) as AlphaFile)
+    }
+
+    // open a binary file for input
+    pub fn b_open_in(&mut self) -> Option<ByteFile> {
+        self.open_in(FileKind::Tfm).map(|f| Box::new(BufReader::new(f)) as ByteFile)
+    }
+
+    // open a word file for input
+    pub fn w_open_in(&mut self) -> Option<ByteFile> {
+        self.open_in(FileKind::Fmt).map(|f| Box::new(BufReader::new(f)) as ByteFile)
+    }
+
+    // open an OpenType or TrueType font file
+    pub fn ot_open_in(&mut self) -> Option<ByteFile> {
+        self.open_in(FileKind::OpenType).map(|f| Box::new(BufReader::new(f)) as ByteFile)
+    }
+
+    // open a virtual font file
+    pub fn vf_open_in(&mut self) -> Option<ByteFile> {
+        self.open_in(FileKind::VirtualFont).map(|f| Box::new(BufReader::new(f)) as ByteFile)
+    }
+
+    // the whole of the file for font f, of the given kind and with extension e,
+    // for output drivers that embed fonts
+    pub fn read_font_file(&mut self, f: InternalFontNumber, e: StrNumber, kind: FileKind) -> Option<Vec<u8>> {
+        self.pack_file_name(self.font_name[f], self.font_area[f], e);
+        let mut file = self.open_in(kind)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).ok()?;
+        Some(data)
+    }
+
+    // open a file for output; text, binary and word files are all alike here
+    pub fn a_open_out(&mut self) -> Option<Box<dyn Write>> {
+        self.resolver.open_out(&self.name_of_file)
+    }
+
+    // 515
+    pub fn begin_name(&mut self) {
+        self.area_delimiter = 0;
+        self.ext_delimiter = 0;
+        self.quoted_filename = false;
+    }
+
+    // 516
+    // appends c to the name being scanned, unless c ends the name; spaces are
+    // allowed between double quotes, which are not themselves part of the name
+    pub fn more_name(&mut self, c: ASCIICode) -> bool {
+        if c == ' ' as u8 && !self.quoted_filename {
+            false
+        } else if c == '"' as u8 {
+            self.quoted_filename = !self.quoted_filename;
+            true
+        } else {
+            self.str_room(1);
+            self.append_char(c); // contribute c to the current string
+            if c == '/' as u8 {
+                self.area_delimiter = self.cur_length();
+                self.ext_delimiter = 0;
+            } else if c == '.' as u8 {
+                self.ext_delimiter = self.cur_length(); // the extension starts at the last dot
+            }
+            true
+        }
+    }
+
+    // 517
+    // splits the string just scanned into cur_area, cur_name and cur_ext
+    pub fn end_name(&mut self) {
+        if self.str_ptr + 3 > MaxStrings {
+            self.overflow("number of strings", MaxStrings - self.init_str_ptr);
+        }
+        let s = self.str_ptr as usize;
+        if self.area_delimiter == 0 {
+            self.cur_area = EmptyString;
+        } else {
+            self.cur_area = self.str_ptr;
+            self.str_start[s + 1] = self.str_start[s] + self.area_delimiter;
+            self.str_ptr += 1;
+        }
+        if self.ext_delimiter == 0 {
+            self.cur_ext = EmptyString;
+            self.cur_name = self.make_string();
+        } else {
+            let s = self.str_ptr as usize;
+            self.cur_name = self.str_ptr;
+            self.str_start[s + 1] = self.str_start[s] + self.ext_delimiter - self.area_delimiter - 1;
+            self.str_ptr += 1;
+            self.cur_ext = self.make_string();
+        }
+    }
+
+    // 518
+    // prints a file name, quoting it if it contains spaces
+    pub fn print_file_name(&mut self, n: StrNumber, a: StrNumber, e: StrNumber) {
+        let must_quote = [a, n, e].iter().any(|&s| self.str_bytes(s).contains(&(' ' as u8)));
+        if must_quote {
+            self.print_char('"' as u8);
+        }
+        for &s in &[a, n, e] {
+            for c in self.str_bytes(s) {
+                if c != '"' as u8 {
+                    self.print_str(c as StrNumber);
+                }
+            }
+        }
+        if must_quote {
+            self.print_char('"' as u8);
+        }
+    }
+
+    // 519
+    // sets name_of_file to the concatenation of area a, name n and extension e
+    pub fn pack_file_name(&mut self, n: StrNumber, a: StrNumber, e: StrNumber) {
+        let mut name = Vec::new();
+        for &s in &[a, n, e] {
+            for c in self.str_bytes(s) {
+                name.push(self.xchr[c as usize]);
+            }
+        }
+        self.name_of_file = String::from_utf8_lossy(&name).into_owned();
+    }
+
+    pub fn pack_cur_name(&mut self) {
+        self.pack_file_name(self.cur_name, self.cur_area, self.cur_ext);
+    }
+
+    // a new string holding name n followed by extension e
+    pub fn name_with_ext(&mut self, n: StrNumber, e: StrNumber) -> StrNumber {
+        let name = [self.str_bytes(n), self.str_bytes(e)].concat();
+        self.str_room(name.len());
+        for c in name {
+            self.append_char(c);
+        }
+        self.make_string()
+    }
+
+    // 523
+    // sets name_of_file to the first n characters of TEX_format_default, then
+    // buffer[a..=b], then the format extension
+    pub fn pack_buffered_name(&mut self, n: usize, a: usize, b: usize) {
+        let mut name = String::new();
+        for j in 1..=n {
+            name.push(self.TEX_format_default[j]);
+        }
+        for j in a..=b {
+            name.push(self.xchr[self.buffer[j] as usize] as char);
+        }
+        for j in format_default_length - format_ext_length + 1..=format_default_length {
+            name.push(self.TEX_format_default[j]);
+        }
+        self.name_of_file = name;
+    }
+
+    // 525
+    // the string number of name_of_file, or "?" if there is no room for it
+    pub fn make_name_string(&mut self) -> StrNumber {
+        let name = self.name_of_file.clone().into_bytes();
+        if self.pool_ptr + name.len() > PoolSize as usize || self.str_ptr == MaxStrings || self.cur_length() > 0 {
+            '?' as StrNumber
+        } else {
+            for c in name {
+                self.append_char(self.xord[c as usize]);
+            }
+            self.make_string()
+        }
+    }
+
+    // 526
+    // scans a file name in the input
+    pub fn scan_file_name(&mut self) {
+        self.name_in_progress = true;
+        self.begin_name();
+        self.get_x_non_blank();
+        loop {
+            if self.cur_cmd > OtherChar || self.cur_chr > 255 {
+                // not a character
+                self.back_input();
+                break;
+            }
+            if !self.more_name(self.cur_chr as ASCIICode) {
+                break;
+            }
+            self.get_x_token();
+        }
+        self.end_name();
+        self.name_in_progress = false;
+    }
+
+    // 529
+    // sets name_of_file to the job name with extension s
+    pub fn pack_job_name(&mut self, s: StrNumber) {
+        self.cur_area = EmptyString;
+        self.cur_ext = s;
+        self.cur_name = self.job_name;
+        self.pack_cur_name();
+    }
+
+    // 530
+    // asks the user for another file name when the one given will not do
+    pub fn prompt_file_name(&mut self, s: &str, e: StrNumber) {
+        if s == "input file name" {
+            self.print_err("I can't find file `");
+        } else {
+            self.print_err("I can't write on file `");
+        }
+        self.print_file_name(self.cur_name, self.cur_area, self.cur_ext);
+        self.print("'.");
+        if e == ExtTex {
+            self.show_context();
+        }
+        self.print_nl("Please type another ");
+        self.print(s);
+        if self.interaction < Interaction::ScrollMode {
+            self.fatal_error("*** (job aborted, file error in nonstop mode)");
+        }
+        self.prompt_input(": ");
+        // 531: scan file name in the buffer
+        self.begin_name();
+        let mut k = self.first;
+        while self.buffer[k] == ' ' as u8 && k < self.last {
+            k += 1;
+        }
+        while k < self.last {
+            if !self.more_name(self.buffer[k]) {
+                break;
+            }
+            k += 1;
+        }
+        self.end_name();
+        if self.cur_ext == EmptyString {
+            self.cur_ext = e;
+        }
+        self.pack_cur_name();
+    }
+
+    // 532
+    // opens the DVI file, if it isn't open already
+    pub fn ensure_dvi_open(&mut self) {
+        if self.output_file_name == 0 {
+            if self.job_name == 0 {
+                self.open_log_file();
+            }
+            self.pack_job_name(ExtDvi);
+            loop {
+                self.dvi_file = self.a_open_out();
+                if self.dvi_file.is_some() {
+                    break;
+                }
+                self.prompt_file_name("file name for output", ExtDvi);
+            }
+            self.output_file_name = self.make_name_string();
+        }
+    }
+
+    // 534
+    // opens the transcript file and writes its first lines
+    pub fn open_log_file(&mut self) {
+        let old_setting = self.selector; // selector upon entry
+        if self.job_name == 0 {
+            self.job_name = TexputName;
+        }
+        self.pack_job_name(ExtLog);
+        loop {
+            self.log_file = self.a_open_out();
+            if self.log_file.is_some() {
+                break;
+            }
+            // 535: try to get a different log file name
+            self.selector = TermOnly;
+            self.prompt_file_name("transcript file name", ExtLog);
+        }
+        self.log_name = self.make_name_string();
+        self.selector = LogOnly;
+        self.log_opened = true;
+        // 536: print the banner line, including the date and time
+        for c in banner.bytes() {
+            self.wlog(c);
+        }
+        self.slow_print(self.format_ident);
+        self.print("  ");
+        self.print_int(self.int_par(DayCode));
+        self.print_char(' ' as u8);
+        let months = b"JANFEBMARAPRMAYJUNJULAUGSEPOCTNOVDEC";
+        let m = self.int_par(MonthCode);
+        if (1..=12).contains(&m) {
+            for &c in &months[3 * (m as usize - 1)..3 * m as usize] {
+                self.wlog(c);
+            }
+        }
+        self.print_char(' ' as u8);
+        self.print_int(self.int_par(YearCode));
+        self.print_char(' ' as u8);
+        self.print_two(self.int_par(TimeCode) / 60);
+        self.print_char(':' as u8);
+        self.print_two(self.int_par(TimeCode) % 60);
+        self.input_stack[self.input_ptr] = self.cur_input; // make sure bottom level is in memory
+        self.print_nl("**");
+        let mut l = self.input_stack[0].limit_field as usize; // last position of first line
+        if self.buffer[l] as i32 == self.end_line_char() {
+            l -= 1;
+        }
+        for k in self.input_stack[0].start_field as usize..=l {
+            self.print_str(self.buffer[k] as StrNumber);
+        }
+        self.print_ln(); // now the transcript file contains the first line of input
+        self.selector = old_setting + 2; // log_only or term_and_log
+    }
+
+    // 537
+    // \input is being invoked: open the file and read its first line
+    pub fn start_input(&mut self) {
+        self.scan_file_name(); // set cur_name to desired file name
+        if self.cur_ext == EmptyString {
+            self.cur_ext = ExtTex;
+        }
+        self.pack_cur_name();
+        loop {
+            self.begin_file_reading(); // set up cur_file and new level of input
+            if let Some(f) = self.a_open_in() {
+                self.input_file[self.cur_input.index_field as usize] = Some(f);
+                break;
+            }
+            self.end_file_reading(); // remove the level that didn't work
+            self.prompt_file_name("input file name", ExtTex);
+        }
+        self.cur_input.name_field = self.make_name_string() as HalfWord;
+        if self.job_name == 0 {
+            self.job_name = self.cur_name;
+            self.open_log_file();
+        } // open_log_file doesn't show_context, so limit and loc needn't be set to meaningful values yet
+        if self.term_offset + self.length(self.cur_input.name_field as StrNumber) as i32 > MaxPrintLine - 2 {
+            self.print_ln();
+        } else if self.term_offset > 0 || self.file_offset > 0 {
+            self.print_char(' ' as u8);
+        }
+        self.print_char('(' as u8);
+        self.open_parens += 1;
+        self.slow_print(self.cur_input.name_field as StrNumber);
+        self.update_terminal();
+        self.cur_input.state_field = NewLine;
+        if self.cur_input.name_field as StrNumber == self.str_ptr - 1 {
+            // conserve string pool space
+            self.flush_string();
+            self.cur_input.name_field = self.cur_name as HalfWord;
+        }
+        // 538: read the first line of the new file
+        self.line = 1;
+        self.input_cur_file();
+        self.firm_up_the_line();
+        if self.end_line_char_inactive() {
+            self.cur_input.limit_field -= 1;
+        } else {
+            self.buffer[self.cur_input.limit_field as usize] = self.end_line_char() as ASCIICode;
+        }
+        self.first = self.cur_input.limit_field as usize + 1;
+        self.cur_input.loc_field = self.cur_input.start_field;
+    }
+
+    // inputs the next line of cur_file into the buffer, or returns false at its end
+    pub fn input_cur_file(&mut self) -> bool {
+        let index = self.cur_input.index_field as usize;
+        match self.input_file[index].take() {
+            Some(mut f) => {
+                let b = self.input_ln(&mut f);
+                self.input_file[index] = Some(f);
+                b
+            }
+            None => {
+                self.last = self.first;
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,802 @@
+use crate::*;
+
+// 548
+pub type InternalFontNumber = usize; // font_base..font_max
+pub type FontIndex = usize; // 0..font_mem_size
+
+// 544
+pub const LigTag: u8 = 1; // character has a ligature/kerning program
+pub const ListTag: u8 = 2; // character has a successor in a charlist
+pub const ExtTag: u8 = 3; // character is extensible
+
+// 545
+pub const StopFlag: u8 = 128; // value indicating `STOP' in a lig/kern program
+pub const KernFlag: u8 = 128; // op code for a kern step
+
+// 547
+pub const SlantCode: i32 = 1;
+pub const SpaceCode: i32 = 2;
+pub const SpaceStretchCode: i32 = 3;
+pub const SpaceShrinkCode: i32 = 4;
+pub const XHeightCode: i32 = 5;
+pub const QuadCode: i32 = 6;
+pub const ExtraSpaceCode: i32 = 7;
+
+// 549
+pub const NonChar: HalfWord = 256; // a halfword code that can't match a real character
+pub const NonAddress: i32 = 0; // a spurious bchar_label
+
+// 552
+pub const NullFont: InternalFontNumber = FontBase as InternalFontNumber;
+
+// 557
+pub const KernBaseOffset: i32 = 256 * (128 + MinQuarterWord as i32);
+
+// 554
+// the parts of a char_info word
+impl FourQuarters {
+    pub fn char_exists(&self) -> bool {
+        self.b0 > MinQuarterWord
+    }
+
+    pub fn height_depth(&self) -> u8 {
+        self.b1
+    }
+
+    pub fn char_tag(&self) -> u8 {
+        self.b2 % 4
+    }
+
+    // 545: the parts of a lig/kern instruction
+    pub fn skip_byte(&self) -> u8 {
+        self.b0
+    }
+
+    pub fn next_char(&self) -> u8 {
+        self.b1
+    }
+
+    pub fn op_byte(&self) -> u8 {
+        self.b2
+    }
+
+    pub fn rem_byte(&self) -> u8 {
+        self.b3
+    }
+
+    // 546: the parts of an extensible recipe
+    pub fn ext_top(&self) -> u8 {
+        self.b0
+    }
+
+    pub fn ext_mid(&self) -> u8 {
+        self.b1
+    }
+
+    pub fn ext_bot(&self) -> u8 {
+        self.b2
+    }
+
+    pub fn ext_rep(&self) -> u8 {
+        self.b3
+    }
+}
+
+// 564
+// the bytes of a TFM file, read in order as by fget and fbyte; running off
+// the end of the file is reported like any other malformation
+struct TfmFile<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> TfmFile<'a> {
+    fn byte(&mut self) -> Result<i32, ()> {
+        let b = *self.data.get(self.pos).ok_or(())?;
+        self.pos += 1;
+        Ok(b as i32)
+    }
+
+    // a sixteen-bit quantity that must not be negative
+    fn sixteen(&mut self) -> Result<i32, ()> {
+        let a = self.byte()?;
+        if a > 127 {
+            return Err(());
+        }
+        Ok(a * 256 + self.byte()?)
+    }
+
+    fn four_quarters(&mut self) -> Result<FourQuarters, ()> {
+        Ok(FourQuarters {
+            b0: self.byte()? as u8,
+            b1: self.byte()? as u8,
+            b2: self.byte()? as u8,
+            b3: self.byte()? as u8,
+        })
+    }
+
+    // 571: a fix_word scaled by z, using alpha and beta from section 572
+    fn scaled(&mut self, z: Scaled, alpha: i32, beta: i32) -> Result<Scaled, ()> {
+        let a = self.byte()?;
+        let b = self.byte()?;
+        let c = self.byte()?;
+        let d = self.byte()?;
+        let sw = ((((d * z) / 0o400) + (c * z)) / 0o400 + (b * z)) / beta;
+        match a {
+            0 => Ok(sw),
+            255 => Ok(sw - alpha),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TexState {
+    // 552
+    // initialize the null font, done by INITEX only; its name is set by init_prim
+    pub fn init_null_font(&mut self) {
+        let f = NullFont;
+        self.font_ptr = NullFont;
+        self.fmem_ptr = 7;
+        self.font_area[f] = EmptyString;
+        self.hyphen_char[f] = '-' as i32;
+        self.skew_char[f] = -1;
+        self.bchar_label[f] = NonAddress;
+        self.font_bchar[f] = NonChar;
+        self.font_false_bchar[f] = NonChar;
+        self.font_bc[f] = 1;
+        self.font_ec[f] = 0;
+        self.font_size[f] = 0;
+        self.font_dsize[f] = 0;
+        self.char_base[f] = 0;
+        self.width_base[f] = 0;
+        self.height_base[f] = 0;
+        self.depth_base[f] = 0;
+        self.italic_base[f] = 0;
+        self.lig_kern_base[f] = 0;
+        self.kern_base[f] = 0;
+        self.exten_base[f] = 0;
+        self.font_glue[f] = Null;
+        self.font_params[f] = 7;
+        self.param_base[f] = -1;
+        for k in 0..=6 {
+            self.font_info[k].set_sc(0);
+        }
+    }
+
+    // 554
+    pub fn char_info(&self, f: InternalFontNumber, c: u8) -> FourQuarters {
+        self.font_info[(self.char_base[f] + c as i32) as usize].qqqq()
+    }
+
+    pub fn char_width(&self, f: InternalFontNumber, ci: FourQuarters) -> Scaled {
+        self.font_info[(self.width_base[f] + ci.b0 as i32) as usize].sc()
+    }
+
+    pub fn char_italic(&self, f: InternalFontNumber, ci: FourQuarters) -> Scaled {
+        self.font_info[(self.italic_base[f] + ci.b2 as i32 / 4) as usize].sc()
+    }
+
+    pub fn char_height(&self, f: InternalFontNumber, hd: u8) -> Scaled {
+        self.font_info[(self.height_base[f] + hd as i32 / 16) as usize].sc()
+    }
+
+    pub fn char_depth(&self, f: InternalFontNumber, hd: u8) -> Scaled {
+        self.font_info[(self.depth_base[f] + hd as i32 % 16) as usize].sc()
+    }
+
+    // 557
+    pub fn lig_kern_start(&self, f: InternalFontNumber, ci: FourQuarters) -> i32 {
+        self.lig_kern_base[f] + ci.rem_byte() as i32
+    }
+
+    pub fn lig_kern_restart(&self, f: InternalFontNumber, i: FourQuarters) -> i32 {
+        self.lig_kern_base[f] + 256 * i.op_byte() as i32 + i.rem_byte() as i32 + 32768 - KernBaseOffset
+    }
+
+    pub fn char_kern(&self, f: InternalFontNumber, i: FourQuarters) -> Scaled {
+        self.font_info[(self.kern_base[f] + 256 * i.op_byte() as i32 + i.rem_byte() as i32) as usize].sc()
+    }
+
+    // 558
+    pub fn param(&self, n: i32, f: InternalFontNumber) -> Scaled {
+        self.font_info[(n + self.param_base[f]) as usize].sc()
+    }
+
+    pub fn slant(&self, f: InternalFontNumber) -> Scaled {
+        self.param(SlantCode, f)
+    }
+
+    pub fn space(&self, f: InternalFontNumber) -> Scaled {
+        self.param(SpaceCode, f)
+    }
+
+    pub fn space_stretch(&self, f: InternalFontNumber) -> Scaled {
+        self.param(SpaceStretchCode, f)
+    }
+
+    pub fn space_shrink(&self, f: InternalFontNumber) -> Scaled {
+        self.param(SpaceShrinkCode, f)
+    }
+
+    pub fn x_height(&self, f: InternalFontNumber) -> Scaled {
+        self.param(XHeightCode, f)
+    }
+
+    pub fn quad(&self, f: InternalFontNumber) -> Scaled {
+        self.param(QuadCode, f)
+    }
+
+    pub fn extra_space(&self, f: InternalFontNumber) -> Scaled {
+        self.param(ExtraSpaceCode, f)
+    }
+
+    // 560
+    // loads the TFM file for font nom in area aire at size s, where s is
+    // -1000 times the magnification if negative; u is the user's font identifier.
+    // Names ending in .otf or .ttf are OpenType or TrueType fonts instead.
+    // The result is null_font if the font can't be loaded
+    pub fn read_font_info(&mut self, u: Pointer, nom: StrNumber, aire: StrNumber, s: Scaled) -> InternalFontNumber {
+        let opentype = is_opentype_name(&self.str_bytes(nom));
+        // 563: open tfm_file for input
+        let mut data = Vec::new();
+        let file = if opentype {
+            self.pack_file_name(nom, aire, EmptyString);
+            self.ot_open_in()
+        } else {
+            self.pack_file_name(nom, aire, ExtTfm);
+            self.b_open_in()
+        };
+        let file_opened = match file {
+            Some(mut f) => f.read_to_end(&mut data).is_ok(),
+            None => false,
+        };
+        if file_opened {
+            let g = if opentype {
+                self.read_opentype(&data, u, nom, aire, s)
+            } else {
+                self.read_tfm(&data, u, nom, aire, s)
+            };
+            if let Ok(g) = g {
+                return g;
+            }
+        }
+        // 561: report that the font won't be loaded
+        self.start_font_error_message(u, nom, aire, s);
+        match (file_opened, opentype) {
+            (true, false) => self.print(" not loadable: Bad metric (TFM) file"),
+            (false, false) => self.print(" not loadable: Metric (TFM) file not found"),
+            (true, true) => self.print(" not loadable: Bad OpenType font file"),
+            (false, true) => self.print(" not loadable: OpenType font file not found"),
+        }
+        self.help(&["I wasn't able to read the size data for this font,",
+            "so I will ignore the font specification.",
+            "[Wizards can fix TFM files using TFtoPL/PLtoTF.]",
+            "You might try inserting a different font spec;",
+            "e.g., type `I\\font<same font id>=<substitute font name>'."]);
+        self.error();
+        NullFont
+    }
+
+    fn start_font_error_message(&mut self, u: Pointer, nom: StrNumber, aire: StrNumber, s: Scaled) {
+        self.print_err("Font ");
+        self.sprint_cs(u);
+        self.print_char('=' as u8);
+        self.print_file_name(nom, aire, EmptyString);
+        if s >= 0 {
+            self.print(" at ");
+            self.print_scaled(s);
+            self.print("pt");
+        } else if s != -1000 {
+            self.print(" scaled ");
+            self.print_int(-s);
+        }
+    }
+
+    // 567
+    // apologizes for not loading a font
+    pub fn no_room_for_font(&mut self, u: Pointer, nom: StrNumber, aire: StrNumber, s: Scaled) {
+        self.start_font_error_message(u, nom, aire, s);
+        self.print(" not loaded: Not enough room left");
+        self.help(&["I'm afraid I won't be able to make use of this font,",
+            "because my memory for character-size data is too small.",
+            "If you're really stuck, ask a wizard to enlarge me.",
+            "Or maybe try `I\\font<same font id>=<name of loaded font>'."]);
+        self.error();
+    }
+
+    // 562
+    // reads and checks the font data; Err means the file is malformed, and
+    // null_font is given if there's no room for the font
+    fn read_tfm(&mut self, tfm: &[u8], u: Pointer, nom: StrNumber, aire: StrNumber, s: Scaled)
+                -> Result<InternalFontNumber, ()> {
+        let mut tfm = TfmFile { data: tfm, pos: 0 };
+        // 565: read the TFM size fields
+        let mut lf = tfm.sixteen()?;
+        let mut lh = tfm.sixteen()?;
+        let mut bc = tfm.sixteen()?;
+        let mut ec = tfm.sixteen()?;
+        if bc > ec + 1 || ec > 255 {
+            return Err(());
+        }
+        if bc > 255 {
+            // bc=256 and ec=255
+            bc = 1;
+            ec = 0;
+        }
+        let nw = tfm.sixteen()?;
+        let nh = tfm.sixteen()?;
+        let nd = tfm.sixteen()?;
+        let ni = tfm.sixteen()?;
+        let nl = tfm.sixteen()?;
+        let nk = tfm.sixteen()?;
+        let ne = tfm.sixteen()?;
+        let np = tfm.sixteen()?;
+        if lf != 6 + lh + (ec - bc + 1) + nw + nh + nd + ni + nl + nk + ne + np {
+            return Err(());
+        }
+        if nw == 0 || nh == 0 || nd == 0 || ni == 0 {
+            return Err(());
+        }
+        // 566: use size fields to allocate font information
+        lf = lf - 6 - lh; // lf words should be loaded into font_info
+        if np < 7 {
+            lf += 7 - np; // at least seven parameters will appear
+        }
+        if self.font_ptr == FontMax as InternalFontNumber || self.fmem_ptr + lf as usize > FontMemSize as usize {
+            self.no_room_for_font(u, nom, aire, s);
+            return Ok(NullFont);
+        }
+        let f = self.font_ptr + 1;
+        let fmem_ptr = self.fmem_ptr as i32;
+        self.char_base[f] = fmem_ptr - bc;
+        self.width_base[f] = self.char_base[f] + ec + 1;
+        self.height_base[f] = self.width_base[f] + nw;
+        self.depth_base[f] = self.height_base[f] + nh;
+        self.italic_base[f] = self.depth_base[f] + nd;
+        self.lig_kern_base[f] = self.italic_base[f] + ni;
+        self.kern_base[f] = self.lig_kern_base[f] + nl - KernBaseOffset;
+        self.exten_base[f] = self.kern_base[f] + KernBaseOffset + nk;
+        self.param_base[f] = self.exten_base[f] + ne;
+        // 568: read the TFM header
+        if lh < 2 {
+            return Err(());
+        }
+        self.font_check[f] = tfm.four_quarters()?;
+        let mut z = tfm.sixteen()?; // this rejects a negative design size
+        z = z * 0o400 + tfm.byte()?;
+        z = z * 0o20 + tfm.byte()? / 0o20;
+        if z < unity {
+            return Err(());
+        }
+        while lh > 2 {
+            tfm.four_quarters()?; // ignore the rest of the header
+            lh -= 1;
+        }
+        self.font_dsize[f] = z;
+        if s != -1000 {
+            if s >= 0 {
+                z = s;
+            } else {
+                z = self.xn_over_d(z, -s, 1000);
+            }
+        }
+        self.font_size[f] = z;
+        // 569: read character data
+        let check_byte_range = |c: i32| if c < bc || c > ec { Err(()) } else { Ok(()) };
+        for k in fmem_ptr..self.width_base[f] {
+            let qw = tfm.four_quarters()?;
+            self.font_info[k as usize].set_qqqq(qw);
+            let (a, b, c, mut d) = (qw.b0 as i32, qw.b1 as i32, qw.b2 as i32, qw.b3 as i32);
+            if a >= nw || b / 0o20 >= nh || b % 0o20 >= nd || c / 4 >= ni {
+                return Err(());
+            }
+            match qw.char_tag() {
+                LigTag if d >= nl => return Err(()),
+                ExtTag if d >= ne => return Err(()),
+                ListTag => {
+                    // 570: check for charlist cycle
+                    check_byte_range(d)?;
+                    let current_character = k + bc - fmem_ptr; // the character being worked on
+                    while d < current_character {
+                        let qw = self.char_info(f, d as u8); // not adjusted, since char_base[f] is final
+                        if qw.char_tag() != ListTag {
+                            break;
+                        }
+                        d = qw.rem_byte() as i32; // next character on the list
+                    }
+                    if d == current_character {
+                        return Err(()); // yes, there's a cycle
+                    }
+                }
+                _ => {} // no_tag
+            }
+        }
+        // 571: read box dimensions
+        // 572: replace z by z' and compute alpha, beta
+        let mut alpha = 16;
+        while z >= 0o40000000 {
+            z /= 2;
+            alpha += alpha;
+        }
+        let beta = 256 / alpha;
+        alpha *= z;
+        for k in self.width_base[f]..self.lig_kern_base[f] {
+            let sw = tfm.scaled(z, alpha, beta)?;
+            self.font_info[k as usize].set_sc(sw);
+        }
+        if self.font_info[self.width_base[f] as usize].sc() != 0 // width[0] must be zero
+            || self.font_info[self.height_base[f] as usize].sc() != 0 // height[0] must be zero
+            || self.font_info[self.depth_base[f] as usize].sc() != 0 // depth[0] must be zero
+            || self.font_info[self.italic_base[f] as usize].sc() != 0 // italic[0] must be zero
+        {
+            return Err(());
+        }
+        // 573: read ligature/kern program
+        let check_existence = |s: &TexState, c: i32| {
+            check_byte_range(c)?;
+            if s.char_info(f, c as u8).char_exists() {
+                Ok(())
+            } else {
+                Err(())
+            }
+        };
+        let mut bch_label = 0o77777; // left boundary start location, or infinity
+        let mut bchar = 256; // right boundary character, or 256
+        if nl > 0 {
+            let mut a = 0;
+            let mut c = 0;
+            let mut d = 0;
+            for k in self.lig_kern_base[f]..self.kern_base[f] + KernBaseOffset {
+                let qw = tfm.four_quarters()?;
+                self.font_info[k as usize].set_qqqq(qw);
+                a = qw.b0 as i32;
+                let b = qw.b1 as i32;
+                c = qw.b2 as i32;
+                d = qw.b3 as i32;
+                if a > 128 {
+                    if 256 * c + d >= nl {
+                        return Err(());
+                    }
+                    if a == 255 && k == self.lig_kern_base[f] {
+                        bchar = b;
+                    }
+                } else {
+                    if b != bchar {
+                        check_existence(self, b)?;
+                    }
+                    if c < 128 {
+                        check_existence(self, d)?; // check ligature
+                    } else if 256 * (c - 128) + d >= nk {
+                        return Err(()); // check kern
+                    }
+                    if a < 128 && k - self.lig_kern_base[f] + a + 1 >= nl {
+                        return Err(());
+                    }
+                }
+            }
+            if a == 255 {
+                bch_label = 256 * c + d;
+            }
+        }
+        for k in self.kern_base[f] + KernBaseOffset..self.exten_base[f] {
+            let sw = tfm.scaled(z, alpha, beta)?;
+            self.font_info[k as usize].set_sc(sw);
+        }
+        // 574: read extensible character recipes
+        for k in self.exten_base[f]..self.param_base[f] {
+            let qw = tfm.four_quarters()?;
+            self.font_info[k as usize].set_qqqq(qw);
+            for &c in &[qw.b0, qw.b1, qw.b2] {
+                if c != 0 {
+                    check_existence(self, c as i32)?;
+                }
+            }
+            check_existence(self, qw.b3 as i32)?;
+        }
+        // 575: read font parameters
+        for k in 1..=np {
+            let sw = if k == 1 {
+                // the slant parameter is a pure number
+                let mut sw = tfm.byte()?;
+                if sw > 127 {
+                    sw -= 256;
+                }
+                sw = sw * 0o400 + tfm.byte()?;
+                sw = sw * 0o400 + tfm.byte()?;
+                sw * 0o20 + tfm.byte()? / 0o20
+            } else {
+                tfm.scaled(z, alpha, beta)?
+            };
+            self.font_info[(self.param_base[f] + k - 1) as usize].set_sc(sw);
+        }
+        for k in np + 1..=7 {
+            self.font_info[(self.param_base[f] + k - 1) as usize].set_sc(0);
+        }
+        // 576: make final adjustments
+        self.font_params[f] = if np >= 7 { np as FontIndex } else { 7 };
+        self.hyphen_char[f] = self.int_par(DefaultHyphenCharCode);
+        self.skew_char[f] = self.int_par(DefaultSkewCharCode);
+        self.bchar_label[f] = if bch_label < nl { bch_label + self.lig_kern_base[f] } else { NonAddress };
+        self.font_bchar[f] = bchar as HalfWord;
+        self.font_false_bchar[f] = bchar as HalfWord;
+        if bchar <= ec && bchar >= bc && self.char_info(f, bchar as u8).char_exists() {
+            self.font_false_bchar[f] = NonChar;
+        }
+        self.font_name[f] = nom;
+        self.font_area[f] = aire;
+        self.font_bc[f] = bc as u8;
+        self.font_ec[f] = ec as u8;
+        self.font_glue[f] = Null;
+        self.param_base[f] -= 1;
+        self.fmem_ptr += lf as usize;
+        self.font_ptr = f;
+        Ok(f)
+    }
+
+    // 577
+    // sets cur_val to the font named next in the input
+    pub fn scan_font_ident(&mut self) {
+        self.get_x_non_blank();
+        let f = if self.cur_cmd == DefFont {
+            self.cur_font() as InternalFontNumber
+        } else if self.cur_cmd == SetFont {
+            self.cur_chr as InternalFontNumber
+        } else if self.cur_cmd == DefFamily {
+            let m = self.cur_chr;
+            self.scan_four_bit_int();
+            self.equiv((m as i32 + self.cur_val) as usize) as InternalFontNumber
+        } else {
+            self.print_err("Missing font identifier");
+            self.help(&["I was looking for a control sequence whose",
+                "current meaning has been defined by \\font."]);
+            self.back_error();
+            NullFont
+        };
+        self.cur_val = f as i32;
+    }
+
+    // 578
+    // sets cur_val to the font_info location of the \fontdimen that follows;
+    // writing is set when the parameter is about to be changed
+    pub fn find_font_dimen(&mut self, writing: bool) {
+        self.scan_int();
+        let n = self.cur_val; // the parameter number
+        self.scan_font_ident();
+        let f = self.cur_val as InternalFontNumber;
+        if n <= 0 {
+            self.cur_val = self.fmem_ptr as i32;
+        } else {
+            if writing && (SpaceCode..=SpaceShrinkCode).contains(&n) && self.font_glue[f] != Null {
+                self.delete_glue_ref(self.font_glue[f]);
+                self.font_glue[f] = Null; // force a new glue spec to be built
+            }
+            if n as FontIndex > self.font_params[f] {
+                if f < self.font_ptr {
+                    self.cur_val = self.fmem_ptr as i32;
+                } else {
+                    // 580: increase the number of parameters in the last font
+                    loop {
+                        if self.fmem_ptr == FontMemSize as FontIndex {
+                            self.overflow("font memory", FontMemSize);
+                        }
+                        self.font_info[self.fmem_ptr].set_sc(0);
+                        self.fmem_ptr += 1;
+                        self.font_params[f] += 1;
+                        if n as FontIndex == self.font_params[f] {
+                            break;
+                        }
+                    }
+                    self.cur_val = self.fmem_ptr as i32 - 1; // this equals param_base[f]+font_params[f]
+                }
+            } else {
+                self.cur_val = n + self.param_base[f];
+            }
+        }
+        // 579: issue an error message if cur_val=fmem_ptr
+        if self.cur_val == self.fmem_ptr as i32 {
+            self.print_err("Font ");
+            self.print_esc_str(font_id_text(&self.hash, f as HalfWord) as StrNumber);
+            self.print(" has only ");
+            self.print_int(self.font_params[f] as i32);
+            self.print(" fontdimen parameters");
+            self.help(&["To increase the number of font parameters, you must",
+                "use \\fontdimen immediately after the \\font is loaded."]);
+            self.error();
+        }
+    }
+
+    // 581
+    pub fn char_warning(&mut self, f: InternalFontNumber, c: u8) {
+        if self.int_par(TracingLostCharsCode) > 0 {
+            self.begin_diagnostic();
+            self.print_nl("Missing character: There is no ");
+            self.print_ASCII(c as i32);
+            self.print(" in font ");
+            self.slow_print(self.font_name[f]);
+            self.print_char('!' as u8);
+            self.end_diagnostic(false);
+        }
+    }
+
+    // 582
+    // a character node for c in font f, or null if the font has no such character
+    pub fn new_character(&mut self, f: InternalFontNumber, c: u8) -> Pointer {
+        if self.font_bc[f] <= c && self.font_ec[f] >= c && self.char_info(f, c).char_exists() {
+            let p = self.get_avail();
+            self.set_font(p as usize, f as u8);
+            self.set_character(p as usize, c);
+            return p;
+        }
+        self.char_warning(f, c);
+        Null
+    }
+
+    // prints the name of font f, and its size if that isn't the design size
+    pub fn print_font_name_and_size(&mut self, f: InternalFontNumber) {
+        self.slow_print(self.font_name[f]);
+        if self.font_size[f] != self.font_dsize[f] {
+            self.print(" at ");
+            self.print_scaled(self.font_size[f]);
+            self.print("pt");
+        }
+    }
+
+    // 1253
+    // \fontdimen n \font = dimen
+    pub fn assign_font_dimen(&mut self) {
+        self.find_font_dimen(true);
+        let k = self.cur_val as FontIndex;
+        self.scan_optional_equals();
+        self.scan_normal_dimen();
+        self.font_info[k].set_sc(self.cur_val);
+    }
+
+    // \hyphenchar or \skewchar \font = number
+    pub fn assign_font_int(&mut self) {
+        let n = self.cur_chr;
+        self.scan_font_ident();
+        let f = self.cur_val as InternalFontNumber;
+        self.scan_optional_equals();
+        self.scan_int();
+        if n == 0 {
+            self.hyphen_char[f] = self.cur_val;
+        } else {
+            self.skew_char[f] = self.cur_val;
+        }
+    }
+
+    // 1257
+    // \font\cs=name, with an optional `at' or `scaled' size
+    pub fn new_font(&mut self, global: bool) {
+        if self.job_name == 0 {
+            self.open_log_file(); // avoid confusing texput with the font name
+        }
+        self.get_r_token();
+        let u = self.cur_cs; // user's font identifier
+        let t = if u >= HashBase {
+            self.hash[u as usize].text() as StrNumber
+        } else if u >= SingleBase && u != NullCs {
+            (u - SingleBase) as StrNumber
+        } else {
+            // name the frozen font identifier FONT or FONTx
+            let old_setting = self.selector;
+            self.selector = NewString;
+            self.print("FONT");
+            if u != NullCs {
+                self.print_str((u - ActiveBase) as StrNumber);
+            }
+            self.selector = old_setting;
+            self.str_room(1);
+            self.make_string()
+        };
+        if global {
+            self.geq_define(u, SetFont, NullFont as HalfWord);
+        } else {
+            self.eq_define(u, SetFont, NullFont as HalfWord);
+        }
+        self.scan_optional_equals();
+        self.scan_file_name();
+        if is_opentype_name(&self.str_bytes(self.cur_ext)) {
+            // keep the extension, which tells read_font_info what kind of font this is
+            self.cur_name = self.name_with_ext(self.cur_name, self.cur_ext);
+        }
+        // 1258: scan the font size specification
+        self.name_in_progress = true; // this keeps cur_name from being changed
+        let mut s: Scaled; // stated `at' size, or negative of scaled magnification
+        if self.scan_keyword("at") {
+            // 1259: put the (positive) `at' size into s
+            self.scan_normal_dimen();
+            s = self.cur_val;
+            if s <= 0 || s >= 0o1000000000 {
+                self.print_err("Improper `at' size (");
+                self.print_scaled(s);
+                self.print("pt), replaced by 10pt");
+                self.help(&["I can only handle fonts at positive sizes that are",
+                    "less than 2048pt, so I've changed what you said to 10pt."]);
+                self.error();
+                s = 10 * unity;
+            }
+        } else if self.scan_keyword("scaled") {
+            self.scan_int();
+            s = -self.cur_val;
+            if self.cur_val <= 0 || self.cur_val > 32768 {
+                self.print_err("Illegal magnification has been changed to 1000");
+                self.help(&["The magnification ratio must be between 1 and 32768."]);
+                self.int_error(self.cur_val);
+                s = -1000;
+            }
+        } else {
+            s = -1000;
+        }
+        self.name_in_progress = false;
+        // 1260: if this font has already been loaded, use its internal number
+        let flushable_string = self.str_ptr - 1; // string not yet referenced
+        let mut found = None;
+        for f in FontBase as InternalFontNumber + 1..=self.font_ptr {
+            if self.str_eq_str(self.font_name[f], self.cur_name) && self.str_eq_str(self.font_area[f], self.cur_area) {
+                if self.cur_name == flushable_string {
+                    self.flush_string();
+                    self.cur_name = self.font_name[f];
+                }
+                let same_size = if s > 0 {
+                    s == self.font_size[f]
+                } else {
+                    self.font_size[f] == self.xn_over_d(self.font_dsize[f], -s, 1000)
+                };
+                if same_size {
+                    found = Some(f);
+                    break;
+                }
+            }
+        }
+        let f = match found {
+            Some(f) => f,
+            None => self.read_font_info(u, self.cur_name, self.cur_area, s),
+        };
+        self.set_equiv(u as usize, f as HalfWord);
+        self.eqtb[FontIdBase as usize + f] = self.eqtb[u as usize];
+        set_font_id_text(&mut self.hash, f as HalfWord, t as HalfWord);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::{text_font, LigKern};
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    fn log_of(vfs: &Vfs) -> String {
+        let outputs = typeset_to(vfs, "doc", OutputFormat::Dvi);
+        String::from_utf8_lossy(&outputs["doc.log"]).into_owned()
+    }
+
+    #[test]
+    fn a_malformed_tfm_file_is_not_loaded() {
+        let vfs = Vfs::new();
+        let mut tfm = text_font().bytes();
+        tfm[2..4].copy_from_slice(&1000u16.to_be_bytes()); // a header longer than the file
+        vfs.add_file("bad.tfm", tfm);
+        vfs.add_file("doc.tex", &b"\\font\\x=bad \\font\\y=missing scaled 2000 \\end\n"[..]);
+        let log = log_of(&vfs);
+        assert!(log.contains("! Font \\x=bad not loadable: Bad metric (TFM) file.\n"), "{}", log);
+        assert!(log.contains("! Font \\y=missing scaled 2000 not loadable: Metric (TFM) file not found.\n"), "{}", log);
+    }
+
+    #[test]
+    fn tfm_metrics_parameters_ligatures_and_kerns() {
+        let vfs = Vfs::new();
+        let tfm = text_font()
+            .lig_kern(b'f', &[LigKern::Lig(b'i', b'1'), LigKern::Kern(b'o', -0.0625)])
+            .italic(b'f', 0.1);
+        vfs.add_file("text.tfm", tfm.bytes());
+        vfs.add_file("doc.tex", &br"\catcode`\{=1 \catcode`\}=2 \font\f=text at 20pt \f
+\setbox0\hbox{fi}\showthe\wd0 \setbox0\hbox{fo}\showthe\wd0 \setbox0\hbox{f\/}\showthe\wd0
+\showthe\fontdimen6\f \fontdimen2\f=3pt \showthe\fontdimen2\f
+\hyphenchar\f=`- \skewchar\f=`= \showthe\hyphenchar\f \showthe\skewchar\f
+\end
+"[..]);
+        let log = log_of(&vfs);
+        for w in &["> 10.0pt.", "> 18.75pt.", "> 12.0pt.", "> 20.0pt.", "> 3.0pt.", "> 45.", "> 61."] {
+            assert!(log.contains(w), "{} {}", w, log);
+        }
+    }
+}
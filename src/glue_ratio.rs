@@ -0,0 +1,258 @@
+use crate::*;
+
+// 109
+// Glue ratios are the only place TeX's output depends on real arithmetic. By
+// default they are stored as f32; with the `fixed-glue' feature they are kept
+// as the exact fraction x/s that hpack and vpack compute, in two words of a box
+// node, so that every glue setting, and hence every DVI position, is computed
+// with integer operations only and without any loss of precision.
+
+const billion: f64 = 1000000000.0; // 625
+
+#[cfg(not(feature = "fixed-glue"))]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct GlueRatio(f32);
+
+#[cfg(feature = "fixed-glue")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlueRatio {
+    num: Scaled,
+    den: Scaled, // always positive
+}
+
+// the number of words a glue ratio occupies in a box node
+#[cfg(not(feature = "fixed-glue"))]
+pub const GlueRatioSize: usize = 1;
+#[cfg(feature = "fixed-glue")]
+pub const GlueRatioSize: usize = 2;
+
+#[cfg(not(feature = "fixed-glue"))]
+impl GlueRatio {
+    pub const ZERO: GlueRatio = GlueRatio(0.0); // set_glue_ratio_zero
+    pub const ONE: GlueRatio = GlueRatio(1.0); // set_glue_ratio_one
+
+    // unfloat(x/s), given s<>0
+    pub fn new(x: Scaled, s: Scaled) -> GlueRatio {
+        GlueRatio((x as f64 / s as f64) as f32)
+    }
+
+    // float(g)
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64
+    }
+
+    pub fn from_bits(bits: u32) -> GlueRatio {
+        GlueRatio(f32::from_bits(bits))
+    }
+
+    pub fn to_bits(self) -> u32 {
+        self.0.to_bits()
+    }
+
+    // 186: tiny values are not printed, since some Pascal compilers could not handle them
+    pub fn is_unprintable(self) -> bool {
+        (self.to_bits() as i32).abs() < 0o4000000
+    }
+
+    // round(float(g)*s)
+    pub fn scale(self, s: Scaled) -> Scaled {
+        (self.0 as f64 * s as f64).round() as Scaled
+    }
+
+    // 625: round(vet_glue(float(g)*s)), where the product is kept within a billion
+    pub fn set_glue(self, s: Scaled) -> Scaled {
+        let glue_temp = self.0 as f64 * s as f64;
+        glue_temp.clamp(-billion, billion).round() as Scaled
+    }
+}
+
+#[cfg(feature = "fixed-glue")]
+impl GlueRatio {
+    pub const ZERO: GlueRatio = GlueRatio { num: 0, den: 1 }; // set_glue_ratio_zero
+    pub const ONE: GlueRatio = GlueRatio { num: 1, den: 1 }; // set_glue_ratio_one
+
+    // unfloat(x/s), given s<>0, kept as the fraction itself
+    pub fn new(x: Scaled, s: Scaled) -> GlueRatio {
+        if s < 0 {
+            GlueRatio { num: -x, den: -s }
+        } else {
+            GlueRatio { num: x, den: s }
+        }
+    }
+
+    // float(g)
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    // the two words of a box node that hold the ratio
+    pub fn from_parts(num: Scaled, den: Scaled) -> GlueRatio {
+        GlueRatio { num, den }
+    }
+
+    pub fn to_parts(self) -> (Scaled, Scaled) {
+        (self.num, self.den)
+    }
+
+    // 186: an exact ratio can always be printed
+    pub fn is_unprintable(self) -> bool {
+        false
+    }
+
+    // round(float(g)*s)
+    pub fn scale(self, s: Scaled) -> Scaled {
+        round_quotient(self.num as i64 * s as i64, self.den as i64) as Scaled
+    }
+
+    // 625: round(vet_glue(float(g)*s)), where the product is kept within a billion
+    pub fn set_glue(self, s: Scaled) -> Scaled {
+        let limit = billion as i64;
+        round_quotient(self.num as i64 * s as i64, self.den as i64).clamp(-limit, limit) as Scaled
+    }
+}
+
+// 1125
+// the kern that centers an accent of width a over a character of width w and
+// height h, when the accent was made for height x; s and t are the slants of the
+// accent's font and the character's. This is real arithmetic in tex.web too, so
+// fixed glue does it exactly with integers.
+#[cfg(not(feature = "fixed-glue"))]
+pub fn accent_delta(w: Scaled, a: Scaled, h: Scaled, x: Scaled, s: Scaled, t: Scaled) -> Scaled {
+    let (s, t) = (s as f64 / 65536.0, t as f64 / 65536.0);
+    ((w - a) as f64 / 2.0 + h as f64 * t - x as f64 * s).round() as Scaled
+}
+
+#[cfg(feature = "fixed-glue")]
+pub fn accent_delta(w: Scaled, a: Scaled, h: Scaled, x: Scaled, s: Scaled, t: Scaled) -> Scaled {
+    let n = (w - a) as i64 * 32768 + h as i64 * t as i64 - x as i64 * s as i64;
+    round_quotient(n, 65536) as Scaled
+}
+
+// a/b rounded to the nearest integer, halves away from zero as Pascal's round does
+#[cfg(feature = "fixed-glue")]
+fn round_quotient(a: i64, b: i64) -> i64 {
+    let (a, b) = if b < 0 { (-a, -b) } else { (a, b) };
+    if a >= 0 {
+        (a + b / 2) / b
+    } else {
+        -((-a + b / 2) / b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::test_fonts::Tfm;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    // round(x*w/s), computed exactly
+    fn exact_glue(x: Scaled, s: Scaled, w: Scaled) -> Scaled {
+        let (a, b) = (x as i128 * w as i128, s as i128);
+        let q = (2 * a.abs() + b.abs()) / (2 * b.abs());
+        (if (a < 0) != (b < 0) { -q } else { q }) as Scaled
+    }
+
+    #[test]
+    fn dyadic_ratios_are_exact() {
+        assert_eq!(GlueRatio::ZERO.set_glue(5 * unity), 0);
+        assert_eq!(GlueRatio::ONE.set_glue(5 * unity), 5 * unity);
+        assert_eq!(GlueRatio::new(unity, 2 * unity).set_glue(3 * unity), 98304);
+        assert_eq!(GlueRatio::new(3 * unity, 4 * unity).set_glue(10 * unity), 491520);
+        assert_eq!(GlueRatio::new(-unity, 8 * unity).set_glue(unity), -8192);
+        assert_eq!(GlueRatio::new(5 * unity, unity).scale(-3 * unity), -15 * unity);
+        assert_eq!(GlueRatio::new(unity, 4 * unity).to_f64(), 0.25);
+    }
+
+    // both representations stay within a rounding unit of the exact product, so
+    // fixed-point and floating-point glue agree to within 2^-16 of the glue
+    #[test]
+    fn typical_ratios_agree_with_exact_arithmetic() {
+        let cases = [
+            (unity, 3 * unity, 6 * unity),
+            (-unity, 3 * unity, 6 * unity),
+            (12345, 67890, 100 * unity),
+            (7 * unity, 11 * unity, -unity),
+            (400 * unity, 3 * unity, 2 * unity),
+            (1, 65536, 65536000),
+        ];
+        for &(x, s, w) in cases.iter() {
+            let g = GlueRatio::new(x, s);
+            let exact = exact_glue(x, s, w);
+            let tolerance = w.abs() / unity + 1;
+            assert!((g.set_glue(w) - exact).abs() <= tolerance, "{}/{} of {}", x, s, w);
+            assert!((g.scale(w) - exact).abs() <= tolerance, "{}/{} of {}", x, s, w);
+        }
+    }
+
+    #[test]
+    fn accents_are_centered_and_slanted() {
+        assert_eq!(accent_delta(10 * unity, 4 * unity, 5 * unity, 5 * unity, 0, 0), 3 * unity);
+        assert_eq!(accent_delta(3, 0, 0, 0, 0, 0), 2);
+        assert_eq!(accent_delta(0, 3, 0, 0, 0, 0), -2);
+        assert_eq!(accent_delta(0, 0, 8 * unity, 4 * unity, unity / 4, unity / 4), unity);
+    }
+
+    #[test]
+    fn extreme_glue_is_kept_within_a_billion() {
+        assert_eq!(GlueRatio::new(30000, 1).set_glue(100000), 1000000000);
+        assert_eq!(GlueRatio::new(-30000, 1).set_glue(100000), -1000000000);
+        assert_eq!(GlueRatio::new(30000, 1).set_glue(-100000), -1000000000);
+    }
+
+    // \hbox to 1pt{\hskip 0pt plus 1sp} stretches its glue by a ratio of 65536
+    #[test]
+    fn large_ratios_do_not_saturate() {
+        assert_eq!(GlueRatio::new(unity, 1).set_glue(1), unity);
+        assert_eq!(GlueRatio::new(unity, 1).to_f64(), 65536.0);
+        assert_eq!(GlueRatio::new(-unity, 1).scale(3), -3 * unity);
+    }
+
+    #[cfg(not(feature = "fixed-glue"))]
+    #[test]
+    fn float_ratios_do_not_saturate() {
+        assert_eq!(GlueRatio::new(40000, 1).to_f64(), 40000.0);
+        assert_eq!(GlueRatio::new(40000, 1).set_glue(unity), 1000000000);
+    }
+
+    // what glue_page ships, as TeX with floating-point glue computes it
+    const GluePageDvi: &[u8] = &[
+        247, 2, 1, 131, 146, 192, 28, 59, 0, 0, 0, 0, 3, 232, 27, 32, 84, 101, 88, 32,
+        111, 117, 116, 112, 117, 116, 32, 50, 48, 48, 48, 46, 48, 49, 46, 48, 49, 58, 48, 48,
+        48, 48, 139, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 255, 255, 255, 255, 159, 9, 127, 255, 141, 243, 0, 18, 52, 86, 120, 0, 10,
+        0, 0, 0, 10, 0, 0, 0, 7, 115, 108, 97, 110, 116, 101, 100, 171, 65, 145, 5, 128,
+        1, 120, 141, 145, 15, 32, 1, 159, 253, 128, 0, 94, 142, 145, 12, 128, 1, 65, 142, 159,
+        9, 255, 255, 141, 120, 145, 2, 0, 0, 65, 150, 252, 0, 0, 94, 147, 120, 142, 159, 80,
+        127, 255, 140, 248, 0, 0, 0, 42, 1, 131, 146, 192, 28, 59, 0, 0, 0, 0, 3, 232,
+        0, 100, 0, 0, 0, 37, 0, 0, 0, 2, 0, 1, 243, 0, 18, 52, 86, 120, 0, 10,
+        0, 0, 0, 10, 0, 0, 0, 7, 115, 108, 97, 110, 116, 101, 100, 249, 0, 0, 0, 163,
+        2, 223, 223, 223, 223, 223, 223, 223,
+    ];
+
+    // a page with stretched and shrunk glue in both directions and a slanted
+    // accent; fixed and floating glue must put everything in the same places
+    fn glue_page() -> Vec<u8> {
+        let tfm = Tfm::new(10.0, &[0.25, 0.3, 0.2, 0.1, 0.45, 1.0, 0.1])
+            .char(b'A', 0.7, 0.7, 0.0)
+            .char(b'x', 0.5, 0.45, 0.0)
+            .char(b'^', 0.3, 0.7, 0.0);
+        let vfs = Vfs::new();
+        vfs.add_file("slanted.tfm", tfm.bytes());
+        vfs.add_file("doc.tex", &br"\catcode`\{=1 \catcode`\}=2 \time=0 \day=1 \month=1 \year=2000
+\font\f=slanted \f
+\shipout\vbox to 100pt{\hbox to 37pt{A\hskip 1pt plus 3pt x\hskip 2pt plus 7pt\accent94A}
+\vskip 3pt plus 11pt
+\hbox to 10pt{x\hskip 5pt minus 3pt A\hskip 2pt minus 7pt\accent94x}
+\vskip 0pt plus 1fil
+\hbox to 1pt{\hskip 0pt plus 1sp\vrule width 1sp}\kern3sp}
+\end
+"[..]);
+        typeset_to(&vfs, "doc", OutputFormat::Dvi).remove("doc.dvi").unwrap()
+    }
+
+    #[test]
+    fn fixed_and_floating_glue_ship_the_same_page() {
+        assert_eq!(glue_page(), GluePageDvi);
+    }
+}
@@ -0,0 +1,806 @@
+use crate::*;
+
+// 1091
+// a hyphen-min parameter forced into the range 1..63
+pub fn norm_min(h: i32) -> i32 {
+    h.clamp(1, 63)
+}
+
+impl TexState {
+    // 891
+    // sets cur_lang to the current \language, or zero if it is out of range
+    pub fn set_cur_lang(&mut self) {
+        let l = self.int_par(LanguageCode);
+        self.cur_lang = if l <= 0 || l > 255 { 0 } else { l as ASCIICode };
+    }
+
+    // 894
+    // tries to hyphenate the word following the glue node cur_p
+    pub fn try_hyphenate_following_word(&mut self) {
+        let mut prev_s = self.cur_p; // the node before s
+        let mut s = self.link(prev_s as usize); // miscellaneous pointer
+        if s == Null {
+            return;
+        }
+        // 896: skip to node ha, or return if no hyphenation should be attempted
+        loop {
+            let p = s as usize;
+            let mut c = None;
+            if self.is_char_node(p) {
+                c = Some(self.character(p));
+                self.hf = self.font(p) as InternalFontNumber;
+            } else {
+                match self.a_type(p) {
+                    LigatureNode => {
+                        let q = self.lig_ptr(p) as usize;
+                        if q != Null as usize {
+                            c = Some(self.character(q));
+                            self.hf = self.font(q) as InternalFontNumber;
+                        }
+                    }
+                    KernNode if self.subtype(p) == Normal as u8 => {}
+                    WhatsitNode => {
+                        // 1363: advance past a whatsit node in the pre-hyphenation loop
+                        if self.subtype(p) == LanguageNode {
+                            self.cur_lang = self.what_lang(p) as ASCIICode;
+                            self.l_hyf = self.what_lhm(p) as i32;
+                            self.r_hyf = self.what_rhm(p) as i32;
+                        }
+                    }
+                    _ => return,
+                }
+            }
+            if let Some(c) = c {
+                let l = self.lc_code(c as HalfWord);
+                if l != 0 {
+                    if l == c as HalfWord || self.int_par(UcHyphCode) > 0 {
+                        break;
+                    }
+                    return;
+                }
+            }
+            prev_s = s;
+            s = self.link(prev_s as usize);
+        }
+        self.hyf_char = self.hyphen_char[self.hf];
+        if self.hyf_char < 0 || self.hyf_char > 255 {
+            return;
+        }
+        self.ha = prev_s;
+        if self.l_hyf + self.r_hyf > 63 {
+            return;
+        }
+        // 897: skip to node hb, putting letters into hu and hc
+        self.hn = 0;
+        loop {
+            let p = s as usize;
+            if self.is_char_node(p) {
+                if self.font(p) as InternalFontNumber != self.hf {
+                    break;
+                }
+                let c = self.character(p);
+                self.hyf_bchar = c as HalfWord;
+                if self.lc_code(c as HalfWord) == 0 || self.hn == 63 {
+                    break;
+                }
+                self.hb = s;
+                self.hn += 1;
+                self.hu[self.hn] = c as HalfWord;
+                self.hc[self.hn] = self.lc_code(c as HalfWord);
+                self.hyf_bchar = NonChar;
+            } else if self.a_type(p) == LigatureNode {
+                // 898: move the characters of a ligature node to hu and hc;
+                // but stop if they are not all letters
+                if self.font(TexState::lig_char(p)) as InternalFontNumber != self.hf {
+                    break;
+                }
+                let mut j = self.hn;
+                let mut q = self.lig_ptr(p);
+                if q > Null {
+                    self.hyf_bchar = self.character(q as usize) as HalfWord;
+                }
+                while q > Null {
+                    let c = self.character(q as usize);
+                    if self.lc_code(c as HalfWord) == 0 || j == 63 {
+                        break;
+                    }
+                    j += 1;
+                    self.hu[j] = c as HalfWord;
+                    self.hc[j] = self.lc_code(c as HalfWord);
+                    q = self.link(q as usize);
+                }
+                if q > Null {
+                    break;
+                }
+                self.hb = s;
+                self.hn = j;
+                self.hyf_bchar = if self.subtype(p) % 2 == 1 { self.font_bchar[self.hf] } else { NonChar };
+            } else if self.a_type(p) == KernNode && self.subtype(p) == Normal as u8 {
+                self.hb = s;
+                self.hyf_bchar = self.font_bchar[self.hf];
+            } else {
+                break;
+            }
+            s = self.link(p);
+        }
+        // 899: check that the nodes following hb permit hyphenation and that at
+        // least l_hyf+r_hyf letters have been found, otherwise return
+        if (self.hn as i32) < self.l_hyf + self.r_hyf {
+            return; // l_hyf and r_hyf are >=1
+        }
+        loop {
+            let p = s as usize;
+            if !self.is_char_node(p) {
+                match self.a_type(p) {
+                    LigatureNode => {}
+                    KernNode => {
+                        if self.subtype(p) != Normal as u8 {
+                            break;
+                        }
+                    }
+                    WhatsitNode | GlueNode | PenaltyNode | InsNode | AdjustNode | MarkNode => break,
+                    _ => return,
+                }
+            }
+            s = self.link(p);
+        }
+        self.hyphenate();
+    }
+
+    // 895
+    // finds hyphen positions in the word hc[1..hn] and inserts discretionaries
+    // between ha and hb accordingly
+    fn hyphenate(&mut self) {
+        // 923: find hyphen locations for the word in hc, or return
+        let hn = self.hn;
+        for j in 0..=hn {
+            self.hyf[j] = 0;
+        }
+        if !self.find_hyph_exception() {
+            let l = self.cur_lang as usize + 1;
+            if self.trie_char(l) != self.cur_lang {
+                return; // no patterns for cur_lang
+            }
+            self.hc[0] = 0;
+            self.hc[hn + 1] = 0;
+            self.hc[hn + 2] = 256; // insert delimiters
+            let root = self.trie_link(l) as usize;
+            for j in 0..=hn + 1 - self.r_hyf as usize {
+                let mut z = root + self.hc[j] as usize;
+                let mut l = j;
+                while self.hc[l] == self.trie_char(z) as HalfWord {
+                    if self.trie_op(z) != MinQuarterWord {
+                        // 924: store maximum values in the hyf table
+                        let mut v = self.trie_op(z) as usize;
+                        loop {
+                            v += self.op_start[self.cur_lang as usize] as usize;
+                            let i = l - self.hyf_distance[v] as usize;
+                            if self.hyf_num[v] as u8 > self.hyf[i] {
+                                self.hyf[i] = self.hyf_num[v] as u8;
+                            }
+                            v = self.hyf_next[v] as usize;
+                            if v == MinQuarterWord as usize {
+                                break;
+                            }
+                        }
+                    }
+                    l += 1;
+                    z = self.trie_link(z) as usize + self.hc[l] as usize;
+                }
+            }
+        }
+        // found:
+        for j in 0..self.l_hyf as usize {
+            self.hyf[j] = 0;
+        }
+        for j in 0..self.r_hyf as usize {
+            self.hyf[hn - j] = 0;
+        }
+        // 902: if no hyphens were found, return
+        if !(self.l_hyf as usize..=hn - self.r_hyf as usize).any(|j| self.hyf[j] % 2 == 1) {
+            return;
+        }
+        // 903: replace nodes ha..hb by a sequence of nodes that includes the discretionary hyphens
+        let q = self.link(self.hb as usize);
+        self.set_link(self.hb as usize, Null);
+        let r = self.link(self.ha as usize);
+        self.set_link(self.ha as usize, Null);
+        let bchar = self.hyf_bchar;
+        let ha = self.ha as usize;
+        let found2 = if self.is_char_node(ha) {
+            self.font(ha) as InternalFontNumber != self.hf
+        } else if self.a_type(ha) == LigatureNode {
+            self.font(TexState::lig_char(ha)) as InternalFontNumber != self.hf
+        } else {
+            !self.is_char_node(r as usize) && self.a_type(r as usize) == LigatureNode && self.subtype(r as usize) > 1
+        };
+        let mut s; // the node that will precede the reconstituted word
+        let mut j; // the first position of hu to be reconstituted
+        if found2 {
+            s = self.ha;
+            j = 0;
+            self.hu[0] = 256;
+            self.init_lig = false;
+            self.init_list = Null;
+        } else if self.is_char_node(ha) || self.a_type(ha) == LigatureNode {
+            if self.is_char_node(ha) {
+                self.init_list = self.ha;
+                self.init_lig = false;
+                self.hu[0] = self.character(ha) as HalfWord;
+            } else {
+                self.init_list = self.lig_ptr(ha);
+                self.init_lig = true;
+                self.init_lft = self.subtype(ha) > 1;
+                self.hu[0] = self.character(TexState::lig_char(ha)) as HalfWord;
+                if self.init_list == Null && self.init_lft {
+                    self.hu[0] = 256;
+                    self.init_lig = false;
+                } // in this case a ligature will be reconstructed from scratch
+                self.free_node(self.ha, SmallNodeSize);
+            }
+            s = self.cur_p; // we have cur_p<>ha because type(cur_p)=glue_node
+            while self.link(s as usize) != self.ha {
+                s = self.link(s as usize);
+            }
+            j = 0;
+        } else {
+            // no punctuation found; look for left boundary
+            s = self.ha;
+            j = 1;
+            self.init_list = Null;
+        }
+        // common_ending:
+        self.flush_node_list(r);
+        // 913: reconstitute nodes for the hyphenated word, inserting discretionary hyphens
+        loop {
+            let mut l = j;
+            j = self.reconstitute(j, hn, bchar, self.hyf_char as HalfWord) + 1;
+            if self.hyphen_passed == 0 {
+                self.set_link(s as usize, self.link(HoldHead));
+                while self.link(s as usize) > Null {
+                    s = self.link(s as usize);
+                }
+                if self.hyf[j - 1] % 2 == 1 {
+                    l = j;
+                    self.hyphen_passed = j - 1;
+                    self.set_link(HoldHead, Null);
+                }
+            }
+            if self.hyphen_passed > 0 {
+                // 914: create and append a discretionary node as an alternative to the
+                // unhyphenated word, and continue to develop both branches until they
+                // become equivalent
+                loop {
+                    let r = self.get_node(SmallNodeSize);
+                    self.set_link(r as usize, self.link(HoldHead));
+                    self.set_type(r as usize, DiscNode);
+                    let mut major_tail = r; // the end of lists in the main and discretionary branches being reconstructed
+                    let mut r_count = 0; // the number of nodes in the main branch after the break
+                    while self.link(major_tail as usize) > Null {
+                        major_tail = self.link(major_tail as usize);
+                        r_count += 1;
+                    }
+                    let mut i = self.hyphen_passed;
+                    self.hyf[i] = 0;
+                    // 915: put the characters hu[l..i] and a hyphen into pre_break(r)
+                    let mut minor_tail = Null;
+                    self.set_pre_break(r as usize, Null);
+                    let hyf_node = self.new_character(self.hf, self.hyf_char as u8); // the hyphen, if it exists
+                    let mut c = 0; // character temporarily replaced by a hyphen
+                    if hyf_node != Null {
+                        i += 1;
+                        c = self.hu[i];
+                        self.hu[i] = self.hyf_char as HalfWord;
+                        self.free_avail(hyf_node);
+                    }
+                    while l <= i {
+                        l = self.reconstitute(l, i, self.font_bchar[self.hf], NonChar) + 1;
+                        if self.link(HoldHead) > Null {
+                            if minor_tail == Null {
+                                self.set_pre_break(r as usize, self.link(HoldHead));
+                            } else {
+                                self.set_link(minor_tail as usize, self.link(HoldHead));
+                            }
+                            minor_tail = self.link(HoldHead);
+                            while self.link(minor_tail as usize) > Null {
+                                minor_tail = self.link(minor_tail as usize);
+                            }
+                        }
+                    }
+                    if hyf_node != Null {
+                        self.hu[i] = c; // restore the character in the hyphen position
+                        l = i;
+                    }
+                    // 916: put the characters hu[i+1..] into post_break(r), appending to
+                    // this list and to major_tail until synchronization has been achieved
+                    minor_tail = Null;
+                    self.set_post_break(r as usize, Null);
+                    let mut c_loc = 0; // where that character came from
+                    if self.bchar_label[self.hf] != NonAddress {
+                        // put left boundary at beginning of new line
+                        l -= 1;
+                        c = self.hu[l];
+                        c_loc = l;
+                        self.hu[l] = 256;
+                    }
+                    while l < j {
+                        loop {
+                            l = self.reconstitute(l, hn, bchar, NonChar) + 1;
+                            if c_loc > 0 {
+                                self.hu[c_loc] = c;
+                                c_loc = 0;
+                            }
+                            if self.link(HoldHead) > Null {
+                                if minor_tail == Null {
+                                    self.set_post_break(r as usize, self.link(HoldHead));
+                                } else {
+                                    self.set_link(minor_tail as usize, self.link(HoldHead));
+                                }
+                                minor_tail = self.link(HoldHead);
+                                while self.link(minor_tail as usize) > Null {
+                                    minor_tail = self.link(minor_tail as usize);
+                                }
+                            }
+                            if l >= j {
+                                break;
+                            }
+                        }
+                        while l > j {
+                            // 917: append characters of hu[j..] to major_tail, advancing j
+                            j = self.reconstitute(j, hn, bchar, NonChar) + 1;
+                            self.set_link(major_tail as usize, self.link(HoldHead));
+                            while self.link(major_tail as usize) > Null {
+                                major_tail = self.link(major_tail as usize);
+                                r_count += 1;
+                            }
+                        }
+                    }
+                    // 918: move pointer s to the end of the current list, and set
+                    // replace_count(r) appropriately
+                    if r_count > 127 {
+                        // we have to forget the discretionary hyphen
+                        self.set_link(s as usize, self.link(r as usize));
+                        self.set_link(r as usize, Null);
+                        self.flush_node_list(r);
+                    } else {
+                        self.set_link(s as usize, r);
+                        self.set_replace_count(r as usize, r_count as QuarterWord);
+                    }
+                    s = major_tail;
+                    self.hyphen_passed = j - 1;
+                    self.set_link(HoldHead, Null);
+                    if self.hyf[j - 1].is_multiple_of(2) {
+                        break;
+                    }
+                }
+            }
+            if j > hn {
+                break;
+            }
+        }
+        self.set_link(s as usize, q);
+        self.flush_list(self.init_list);
+    }
+
+    // 906
+    // builds a list of characters, ligatures and kerns for hu[j..n] at hold_head,
+    // returning the index of the last character consumed
+    fn reconstitute(&mut self, j: usize, n: usize, bchar: HalfWord, hchar: HalfWord) -> usize {
+        let mut j = j;
+        let mut bchar = bchar;
+        let mut hchar = hchar;
+        self.hyphen_passed = 0;
+        let mut t = HoldHead as Pointer; // a node being appended to
+        let mut w: Scaled = 0; // a kern to be inserted
+        self.set_link(HoldHead, Null);
+        // at this point ligature_present=lft_hit=rt_hit=false
+        // 908: set up data structures with the cursor following position j
+        self.cur_l = self.hu[j];
+        self.cur_q = t;
+        if j == 0 {
+            self.ligature_present = self.init_lig;
+            let mut p = self.init_list;
+            if self.ligature_present {
+                self.lft_hit = self.init_lft;
+            }
+            while p > Null {
+                self.append_charnode_to_t(&mut t, self.character(p as usize));
+                p = self.link(p as usize);
+            }
+        } else if self.cur_l < NonChar {
+            self.append_charnode_to_t(&mut t, self.cur_l as u8);
+        }
+        self.lig_stack = Null;
+        let mut cur_rh = self.set_cur_r(j, n, bchar, hchar); // hyphen character for ligature testing
+        'continue_: loop {
+            // 909: if there's a ligature or kern at the cursor position, update the
+            // data structures, possibly advancing j; continue until the cursor moves
+            'done: {
+                let mut k; // position of current lig/kern instruction
+                let mut q; // character information or a lig/kern instruction
+                if self.cur_l == NonChar {
+                    k = self.bchar_label[self.hf];
+                    if k == NonAddress {
+                        break 'done;
+                    }
+                    q = self.font_info[k as usize].qqqq();
+                } else {
+                    q = self.char_info(self.hf, self.cur_l as u8);
+                    if q.char_tag() != LigTag {
+                        break 'done;
+                    }
+                    k = self.lig_kern_start(self.hf, q);
+                    q = self.font_info[k as usize].qqqq();
+                    if q.skip_byte() > StopFlag {
+                        k = self.lig_kern_restart(self.hf, q);
+                        q = self.font_info[k as usize].qqqq();
+                    }
+                } // now k is the starting address of the lig/kern program
+                let test_char = if cur_rh < NonChar { cur_rh } else { self.cur_r };
+                loop {
+                    if q.next_char() as HalfWord == test_char && q.skip_byte() <= StopFlag {
+                        if cur_rh < NonChar {
+                            self.hyphen_passed = j;
+                            hchar = NonChar;
+                            cur_rh = NonChar;
+                            continue 'continue_;
+                        }
+                        if hchar < NonChar && self.hyf[j] % 2 == 1 {
+                            self.hyphen_passed = j;
+                            hchar = NonChar;
+                        }
+                        if q.op_byte() < KernFlag {
+                            // 911: carry out a ligature replacement, updating the cursor
+                            // structure and possibly advancing j
+                            if self.cur_l == NonChar {
+                                self.lft_hit = true;
+                            }
+                            if j == n && self.lig_stack == Null {
+                                self.rt_hit = true;
+                            }
+                            self.check_interrupt(); // allow a way out in case there's an infinite ligature loop
+                            match q.op_byte() {
+                                1 | 5 => {
+                                    // =:|, =:|>
+                                    self.cur_l = q.rem_byte() as HalfWord;
+                                    self.ligature_present = true;
+                                }
+                                2 | 6 => {
+                                    // |=:, |=:>
+                                    self.cur_r = q.rem_byte() as HalfWord;
+                                    if self.lig_stack > Null {
+                                        self.set_character(self.lig_stack as usize, self.cur_r as u8);
+                                    } else {
+                                        self.lig_stack = self.new_lig_item(self.cur_r as QuarterWord);
+                                        if j == n {
+                                            bchar = NonChar;
+                                        } else {
+                                            let p = self.get_avail();
+                                            self.set_lig_ptr(self.lig_stack as usize, p);
+                                            self.set_character(p as usize, self.hu[j + 1] as u8);
+                                            self.set_font(p as usize, self.hf as u8);
+                                        }
+                                    }
+                                }
+                                3 => {
+                                    // |=:|
+                                    self.cur_r = q.rem_byte() as HalfWord;
+                                    let p = self.lig_stack;
+                                    self.lig_stack = self.new_lig_item(self.cur_r as QuarterWord);
+                                    self.set_link(self.lig_stack as usize, p);
+                                }
+                                7 | 11 => {
+                                    // |=:|>, |=:|>>
+                                    self.wrap_lig(false, &mut t);
+                                    self.cur_q = t;
+                                    self.cur_l = q.rem_byte() as HalfWord;
+                                    self.ligature_present = true;
+                                }
+                                _ => {
+                                    // =:
+                                    self.cur_l = q.rem_byte() as HalfWord;
+                                    self.ligature_present = true;
+                                    if self.lig_stack > Null {
+                                        if self.pop_lig_stack(&mut j, &mut t) {
+                                            cur_rh = self.set_cur_r(j, n, bchar, hchar);
+                                        }
+                                    } else if j == n {
+                                        break 'done;
+                                    } else {
+                                        self.append_charnode_to_t(&mut t, self.cur_r as u8);
+                                        j += 1;
+                                        cur_rh = self.set_cur_r(j, n, bchar, hchar);
+                                    }
+                                }
+                            }
+                            if q.op_byte() > 4 && q.op_byte() != 7 {
+                                break 'done;
+                            }
+                            continue 'continue_;
+                        }
+                        w = self.char_kern(self.hf, q);
+                        break 'done; // this kern will be inserted below
+                    }
+                    if q.skip_byte() >= StopFlag {
+                        if cur_rh == NonChar {
+                            break 'done;
+                        }
+                        cur_rh = NonChar;
+                        continue 'continue_;
+                    }
+                    k += q.skip_byte() as i32 + 1;
+                    q = self.font_info[k as usize].qqqq();
+                }
+            }
+            // 910: append a ligature and/or kern to the translation; continue if the
+            // stack of inserted ligatures is nonempty
+            self.wrap_lig(self.rt_hit, &mut t);
+            if w != 0 {
+                let k = self.new_kern(w);
+                self.set_link(t as usize, k);
+                t = k;
+                w = 0;
+            }
+            if self.lig_stack > Null {
+                self.cur_q = t;
+                self.cur_l = self.character(self.lig_stack as usize) as HalfWord;
+                self.ligature_present = true;
+                if self.pop_lig_stack(&mut j, &mut t) {
+                    cur_rh = self.set_cur_r(j, n, bchar, hchar);
+                }
+                continue;
+            }
+            break;
+        }
+        j
+    }
+
+    // 907
+    // appends a character node for c in font hf after t
+    fn append_charnode_to_t(&mut self, t: &mut Pointer, c: u8) {
+        let p = self.get_avail();
+        self.set_link(*t as usize, p);
+        *t = p;
+        self.set_font(p as usize, self.hf as u8);
+        self.set_character(p as usize, c);
+    }
+
+    // sets cur_r to the character following position j, returning the
+    // hyphen character to test if a hyphen is allowed there
+    fn set_cur_r(&mut self, j: usize, n: usize, bchar: HalfWord, hchar: HalfWord) -> HalfWord {
+        self.cur_r = if j < n { self.hu[j + 1] } else { bchar };
+        if self.hyf[j] % 2 == 1 { hchar } else { NonChar }
+    }
+
+    // 910
+    // packages the characters after cur_q into a ligature, if one is present
+    fn wrap_lig(&mut self, rt: bool, t: &mut Pointer) {
+        if self.ligature_present {
+            let p = self.new_ligature(self.hf as QuarterWord, self.cur_l as QuarterWord, self.link(self.cur_q as usize));
+            if self.lft_hit {
+                self.set_subtype(p as usize, 2);
+                self.lft_hit = false;
+            }
+            if rt && self.lig_stack == Null {
+                self.set_subtype(p as usize, self.subtype(p as usize) + 1);
+                self.rt_hit = false;
+            }
+            self.set_link(self.cur_q as usize, p);
+            *t = p;
+            self.ligature_present = false;
+        }
+    }
+
+    // removes the top of the lig_stack, moving its character to the translation;
+    // true if the stack became empty, in which case cur_r is yet to be set
+    fn pop_lig_stack(&mut self, j: &mut usize, t: &mut Pointer) -> bool {
+        if self.lig_ptr(self.lig_stack as usize) > Null {
+            // this is a charnode for hu[j+1]
+            self.set_link(*t as usize, self.lig_ptr(self.lig_stack as usize));
+            *t = self.link(*t as usize);
+            *j += 1;
+        }
+        let p = self.lig_stack;
+        self.lig_stack = self.link(p as usize);
+        self.free_node(p, SmallNodeSize);
+        if self.lig_stack == Null {
+            return true;
+        }
+        self.cur_r = self.character(self.lig_stack as usize) as HalfWord;
+        false
+    }
+
+    // 930
+    // looks for the word hc[1..hn] in the exception table, setting hyf to its
+    // hyphen positions if it is there
+    fn find_hyph_exception(&mut self) -> bool {
+        let mut h = self.hc[1] as i32; // an index into hyph_word and hyph_list
+        self.hn += 1;
+        self.hc[self.hn] = self.cur_lang as HalfWord;
+        for j in 2..=self.hn {
+            h = (h + h + self.hc[j] as i32) % HyphSize;
+        }
+        let found = loop {
+            // 931: if the string hyph_word[h] is less than hc[1..hn] then stop; but
+            // if the two strings are equal, set hyf to the hyphen positions
+            let k = self.hyph_word[h as usize];
+            if k == 0 || self.length(k) < self.hn {
+                break false;
+            }
+            if self.length(k) == self.hn {
+                let mut cmp = 0;
+                for (u, j) in (self.str_start[k as usize]..).zip(1..=self.hn) {
+                    let c = self.str_pool[u] as HalfWord;
+                    if c != self.hc[j] {
+                        cmp = if c < self.hc[j] { -1 } else { 1 };
+                        break;
+                    }
+                }
+                if cmp < 0 {
+                    break false;
+                }
+                if cmp == 0 {
+                    // 932: insert hyphens as specified in hyph_list[h]
+                    let mut s = self.hyph_list[h as usize];
+                    while s != Null {
+                        self.hyf[self.info(s as usize) as usize] = 1;
+                        s = self.link(s as usize);
+                    }
+                    break true;
+                }
+            }
+            if h > 0 {
+                h -= 1;
+            } else {
+                h = HyphSize;
+            }
+        };
+        self.hn -= 1;
+        found
+    }
+
+    // 934
+    // enters new exceptions from a \hyphenation list
+    pub fn new_hyph_exceptions(&mut self) {
+        self.scan_left_brace(); // a left brace must follow \hyphenation
+        self.set_cur_lang();
+        // 935: enter as many hyphenation exceptions as are listed,
+        // until coming to a right brace
+        let mut n: usize = 0; // length of current word
+        let mut p = Null; // head of a list of hyphen positions
+        loop {
+            self.get_x_token();
+            loop {
+                // reswitch:
+                match self.cur_cmd {
+                    Letter | OtherChar | CharGiven => {
+                        // 937: append a new letter or hyphen
+                        if self.cur_chr == '-' as HalfWord {
+                            // 938: append the value n to list p
+                            if n < 63 {
+                                let q = self.get_avail();
+                                self.set_link(q as usize, p);
+                                self.set_info(q as usize, n as HalfWord);
+                                p = q;
+                            }
+                        } else if self.lc_code(self.cur_chr) == 0 {
+                            self.print_err("Not a letter");
+                            self.help(&["Letters in \\hyphenation words must have \\lccode>0.",
+                                "Proceed; I'll ignore the character I just read."]);
+                            self.error();
+                        } else if n < 63 {
+                            n += 1;
+                            self.hc[n] = self.lc_code(self.cur_chr);
+                        }
+                    }
+                    CharNum => {
+                        self.scan_char_num();
+                        self.cur_chr = self.cur_val as HalfWord;
+                        self.cur_cmd = CharGiven;
+                        continue;
+                    }
+                    Spacer | RightBrace => {
+                        if n > 1 {
+                            self.enter_hyph_exception(n, p);
+                        }
+                        if self.cur_cmd == RightBrace {
+                            return;
+                        }
+                        n = 0;
+                        p = Null;
+                    }
+                    _ => {
+                        // 936: give improper \hyphenation error
+                        self.print_err("Improper ");
+                        self.print_esc("hyphenation");
+                        self.print(" will be flushed");
+                        self.help(&["Hyphenation exceptions must contain only letters",
+                            "and hyphens. But continue; I'll forgive and forget."]);
+                        self.error();
+                    }
+                }
+                break;
+            }
+        }
+    }
+
+    // 939
+    // enters the word hc[1..n] with hyphen positions p into the exception table
+    fn enter_hyph_exception(&mut self, n: usize, p: Pointer) {
+        let n = n + 1;
+        self.hc[n] = self.cur_lang as HalfWord;
+        self.str_room(n);
+        let mut h: i32 = 0;
+        for j in 1..=n {
+            h = (h + h + self.hc[j] as i32) % HyphSize;
+            self.append_char(self.hc[j] as ASCIICode);
+        }
+        let mut s = self.make_string();
+        let mut p = p;
+        // 940: insert the pair (s,p) into the exception table
+        if self.hyph_count == HyphSize {
+            self.overflow("exception dictionary", HyphSize);
+        }
+        self.hyph_count += 1;
+        while self.hyph_word[h as usize] != 0 {
+            // 941: if the string hyph_word[h] is less than or equal to s,
+            // interchange (hyph_word[h],hyph_list[h]) with (s,p)
+            let k = self.hyph_word[h as usize];
+            let smaller = if self.length(k) != self.length(s) {
+                self.length(k) < self.length(s)
+            } else {
+                let u = self.str_start[k as usize];
+                let v = self.str_start[s as usize];
+                (0..self.length(k))
+                    .find(|&i| self.str_pool[u + i] != self.str_pool[v + i])
+                    .is_none_or(|i| self.str_pool[u + i] < self.str_pool[v + i])
+            };
+            if smaller {
+                std::mem::swap(&mut self.hyph_list[h as usize], &mut p);
+                std::mem::swap(&mut self.hyph_word[h as usize], &mut s);
+            }
+            if h > 0 {
+                h -= 1;
+            } else {
+                h = HyphSize;
+            }
+        }
+        self.hyph_word[h as usize] = s;
+        self.hyph_list[h as usize] = p;
+    }
+
+    // 1252
+    // \hyphenation and \patterns
+    pub fn do_hyph_data(&mut self) {
+        if self.cur_chr == 1 {
+            self.new_patterns();
+        } else {
+            self.new_hyph_exceptions();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::text_font;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn patterns_and_exceptions() {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\catcode`\\#=6
+\\defaulthyphenchar=`- \\font\\rm=text \\rm \\lefthyphenmin=2 \\righthyphenmin=2
+\\def\\showhyphens#1{\\setbox0\\vbox{\\parfillskip=0pt \\hsize=1000pt \\pretolerance=-1 \\tolerance=-1 \\hbadness=0 \\showboxdepth=0 \\ #1}}
+\\patterns{1ba 1na a1n}\\hyphenation{ba-nan-as}
+\\showhyphens{banana bananas Banana} \\uchyph=1 \\showhyphens{Banana}
+\\end
+");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        // the exception overrides the patterns, and capitalized words are only
+        // hyphenated when \\uchyph is positive
+        assert!(log.contains("[] \\rm ba-na-na ba-nan-as Banana\n"), "{}", log);
+        assert!(log.contains("[] \\rm Ba-na-na\n"), "{}", log);
+    }
+}
@@ -0,0 +1,910 @@
+use crate::*;
+
+// 300
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InStateRecord {
+    pub state_field: QuarterWord,
+    pub index_field: QuarterWord,
+    pub start_field: HalfWord,
+    pub loc_field: HalfWord,
+    pub limit_field: HalfWord,
+    pub name_field: HalfWord,
+}
+
+// 303
+pub const TokenList: QuarterWord = 0; // state code when scanning a token list
+pub const MidLine: QuarterWord = 1; // state code when scanning a line of characters
+pub const SkipBlanks: QuarterWord = 2 + MaxCharCode; // state code when ignoring blanks
+pub const NewLine: QuarterWord = 3 + MaxCharCode + MaxCharCode; // state code at start of line
+
+// 305
+pub const Skipping: i32 = 1; // scanner_status when passing conditional text
+pub const Defining: i32 = 2; // scanner_status when reading a macro definition
+pub const Matching: i32 = 3; // scanner_status when reading macro arguments
+pub const Aligning: i32 = 4; // scanner_status when reading an alignment preamble
+pub const Absorbing: i32 = 5; // scanner_status when reading a balanced text
+
+// 307
+pub const Parameter: QuarterWord = 0; // token_type code for parameter
+pub const UTemplate: QuarterWord = 1; // token_type code for <u_j> template
+pub const VTemplate: QuarterWord = 2; // token_type code for <v_j> template
+pub const BackedUp: QuarterWord = 3; // token_type code for text to be reread
+pub const Inserted: QuarterWord = 4; // token_type code for inserted texts
+pub const Macro: QuarterWord = 5; // token_type code for defined control sequences
+pub const OutputText: QuarterWord = 6; // token_type code for output routines
+pub const EveryParText: QuarterWord = 7; // token_type code for \everypar
+pub const EveryMathText: QuarterWord = 8; // token_type code for \everymath
+pub const EveryDisplayText: QuarterWord = 9; // token_type code for \everydisplay
+pub const EveryHboxText: QuarterWord = 10; // token_type code for \everyhbox
+pub const EveryVboxText: QuarterWord = 11; // token_type code for \everyvbox
+pub const EveryJobText: QuarterWord = 12; // token_type code for \everyjob
+pub const EveryCrText: QuarterWord = 13; // token_type code for \everycr
+pub const MarkText: QuarterWord = 14; // token_type code for \topmark, etc.
+pub const WriteText: QuarterWord = 15; // token_type code for \write
+
+// 358
+pub const NoExpandFlag: HalfWord = 257; // this characterizes a special variant of relax
+
+// 352
+fn is_hex(c: ASCIICode) -> bool {
+    (c >= '0' as u8 && c <= '9' as u8) || (c >= 'a' as u8 && c <= 'f' as u8)
+}
+
+fn hex_value(c: ASCIICode) -> HalfWord {
+    if c <= '9' as u8 {
+        (c - '0' as u8) as HalfWord
+    } else {
+        (c - 'a' as u8 + 10) as HalfWord
+    }
+}
+
+impl TexState {
+    // 304
+    // are we reading from the terminal?
+    pub fn terminal_input(&self) -> bool {
+        self.cur_input.name_field == 0
+    }
+
+    // 307
+    // type of current token list
+    pub fn token_type(&self) -> QuarterWord {
+        self.cur_input.index_field
+    }
+
+    // base of macro parameters in param_stack
+    pub fn param_start(&self) -> HalfWord {
+        self.cur_input.limit_field
+    }
+
+    // 306
+    pub fn runaway(&mut self) {
+        if self.scanner_status > Skipping {
+            self.print_nl("Runaway ");
+            let p = match self.scanner_status {
+                Defining => {
+                    self.print("definition");
+                    self.def_ref as usize
+                }
+                Matching => {
+                    self.print("argument");
+                    TempHead
+                }
+                Aligning => {
+                    self.print("preamble");
+                    HoldHead
+                }
+                _ => {
+                    self.print("text");
+                    self.def_ref as usize
+                }
+            };
+            self.print_char('?' as u8);
+            self.print_ln();
+            self.show_token_list(self.link(p) as i32, Null as i32, ErrorLine - 10);
+        }
+    }
+
+    // 311
+    // prints where the scanner is
+    pub fn show_context(&mut self) {
+        self.base_ptr = self.input_ptr;
+        self.input_stack[self.base_ptr] = self.cur_input; // store current state
+        let mut nn = -1;
+        let mut bottom_line = false;
+        loop {
+            self.cur_input = self.input_stack[self.base_ptr]; // enter into the context
+            if self.cur_input.state_field != TokenList
+                && (self.cur_input.name_field > 17 || self.base_ptr == 0)
+            {
+                bottom_line = true;
+            }
+            if self.base_ptr == self.input_ptr || bottom_line || nn < self.int_par(ErrorContextLinesCode) {
+                // 312: display the current context
+                if self.base_ptr == self.input_ptr
+                    || self.cur_input.state_field != TokenList
+                    || self.token_type() != BackedUp
+                    || self.cur_input.loc_field != Null
+                {
+                    // we omit backed-up token lists that have already been read
+                    self.tally = 0; // get ready to count characters
+                    let old_setting = self.selector;
+                    let l;
+                    if self.cur_input.state_field != TokenList {
+                        // 313: print location of current line
+                        if self.cur_input.name_field <= 17 {
+                            if self.terminal_input() {
+                                if self.base_ptr == 0 {
+                                    self.print_nl("<*>");
+                                } else {
+                                    self.print_nl("<insert> ");
+                                }
+                            } else {
+                                self.print_nl("<read ");
+                                if self.cur_input.name_field == 17 {
+                                    self.print_char('*' as u8);
+                                } else {
+                                    self.print_int(self.cur_input.name_field as i32 - 1);
+                                }
+                                self.print_char('>' as u8);
+                            }
+                        } else {
+                            self.print_nl("l.");
+                            if self.cur_input.index_field as i32 == self.in_open {
+                                self.print_int(self.line);
+                            } else {
+                                self.print_int(self.line_stack[self.cur_input.index_field as usize + 1]);
+                            }
+                        }
+                        self.print_char(' ' as u8);
+                        // 318: pseudoprint the line
+                        l = self.begin_pseudoprint();
+                        let limit = self.cur_input.limit_field as usize;
+                        let j = if self.buffer[limit] as i32 == self.end_line_char() {
+                            limit
+                        } else {
+                            limit + 1
+                        }; // determine the effective end of the line
+                        for i in self.cur_input.start_field as usize..j {
+                            if i == self.cur_input.loc_field as usize {
+                                self.set_trick_count();
+                            }
+                            self.print_str(self.buffer[i] as StrNumber);
+                        }
+                    } else {
+                        // 314: print type of token list
+                        match self.token_type() {
+                            Parameter => self.print_nl("<argument> "),
+                            UTemplate | VTemplate => self.print_nl("<template> "),
+                            BackedUp => {
+                                if self.cur_input.loc_field == Null {
+                                    self.print_nl("<recently read> ");
+                                } else {
+                                    self.print_nl("<to be read again> ");
+                                }
+                            }
+                            Inserted => self.print_nl("<inserted text> "),
+                            Macro => {
+                                self.print_ln();
+                                self.print_cs(self.cur_input.name_field as i32);
+                            }
+                            OutputText => self.print_nl("<output> "),
+                            EveryParText => self.print_nl("<everypar> "),
+                            EveryMathText => self.print_nl("<everymath> "),
+                            EveryDisplayText => self.print_nl("<everydisplay> "),
+                            EveryHboxText => self.print_nl("<everyhbox> "),
+                            EveryVboxText => self.print_nl("<everyvbox> "),
+                            EveryJobText => self.print_nl("<everyjob> "),
+                            EveryCrText => self.print_nl("<everycr> "),
+                            MarkText => self.print_nl("<mark> "),
+                            WriteText => self.print_nl("<write> "),
+                            _ => self.print_nl("?"), // this should never happen
+                        }
+                        // 319: pseudoprint the token list
+                        l = self.begin_pseudoprint();
+                        let start = self.cur_input.start_field;
+                        let loc = self.cur_input.loc_field as i32;
+                        if self.token_type() < Macro {
+                            self.show_token_list(start as i32, loc, 100000);
+                        } else {
+                            self.show_token_list(self.link(start as usize) as i32, loc, 100000); // avoid reference count
+                        }
+                    }
+                    self.selector = old_setting; // stop pseudoprinting
+                    // 317: print two lines using the tricky pseudoprinted information
+                    if self.trick_count == 1000000 {
+                        self.set_trick_count(); // set_trick_count must be performed
+                    }
+                    let m = if self.tally < self.trick_count {
+                        self.tally - self.first_count
+                    } else {
+                        self.trick_count - self.first_count
+                    }; // context on line 2
+                    let (p, n) = if l + self.first_count <= HalfErrorLine {
+                        (0, l + self.first_count)
+                    } else {
+                        self.print("...");
+                        (l + self.first_count - HalfErrorLine + 3, HalfErrorLine)
+                    };
+                    for q in p..self.first_count {
+                        self.print_char(self.trick_buf[(q % ErrorLine) as usize]);
+                    }
+                    self.print_ln();
+                    for _ in 0..n {
+                        self.print_char(' ' as u8); // print n spaces to begin line 2
+                    }
+                    let p = if m + n <= ErrorLine {
+                        self.first_count + m
+                    } else {
+                        self.first_count + (ErrorLine - n - 3)
+                    };
+                    for q in self.first_count..p {
+                        self.print_char(self.trick_buf[(q % ErrorLine) as usize]);
+                    }
+                    if m + n > ErrorLine {
+                        self.print("...");
+                    }
+                    nn += 1;
+                }
+            } else if nn == self.int_par(ErrorContextLinesCode) {
+                self.print_nl("...");
+                nn += 1; // omitted if error_context_lines<0
+            }
+            if bottom_line {
+                break;
+            }
+            self.base_ptr -= 1;
+        }
+        self.cur_input = self.input_stack[self.input_ptr]; // restore original state
+    }
+
+    // 316
+    // starts pseudoprinting, returning the number of characters already printed
+    fn begin_pseudoprint(&mut self) -> i32 {
+        let l = self.tally;
+        self.tally = 0;
+        self.selector = Pseudo;
+        self.trick_count = 1000000;
+        l
+    }
+
+    pub fn set_trick_count(&mut self) {
+        self.first_count = self.tally;
+        self.trick_count = self.tally + 1 + ErrorLine - HalfErrorLine;
+        if self.trick_count < ErrorLine {
+            self.trick_count = ErrorLine;
+        }
+    }
+
+    // 321
+    // enter a new input level, save the old
+    pub fn push_input(&mut self) {
+        if self.input_ptr > self.max_in_stack {
+            self.max_in_stack = self.input_ptr;
+            if self.input_ptr == StackSize as usize {
+                self.overflow("input stack size", StackSize);
+            }
+        }
+        self.input_stack[self.input_ptr] = self.cur_input; // stack the record
+        self.input_ptr += 1;
+    }
+
+    // 322
+    // leave an input level, re-enter the old
+    pub fn pop_input(&mut self) {
+        self.input_ptr -= 1;
+        self.cur_input = self.input_stack[self.input_ptr];
+    }
+
+    // 323
+    // starts a new level of token-list input
+    pub fn begin_token_list(&mut self, p: Pointer, t: QuarterWord) {
+        self.push_input();
+        self.cur_input.state_field = TokenList;
+        self.cur_input.start_field = p;
+        self.cur_input.index_field = t;
+        if t >= Macro {
+            // the token list starts with a reference count
+            self.add_token_ref(p);
+            if t == Macro {
+                self.cur_input.limit_field = self.param_ptr as HalfWord;
+            } else {
+                self.cur_input.loc_field = self.link(p as usize);
+                if self.int_par(TracingMacrosCode) > 1 {
+                    self.begin_diagnostic();
+                    self.print_nl("");
+                    match t {
+                        MarkText => self.print_esc("mark"),
+                        WriteText => self.print_esc("write"),
+                        _ => self.print_cmd_chr(AssignToks, (t - OutputText) as HalfWord + OutputRoutineLoc),
+                    }
+                    self.print("->");
+                    self.token_show(p);
+                    self.end_diagnostic(false);
+                }
+            }
+        } else {
+            self.cur_input.loc_field = p;
+        }
+    }
+
+    // inserts a simple token list
+    pub fn ins_list(&mut self, p: Pointer) {
+        self.begin_token_list(p, Inserted);
+    }
+
+    // backs up a simple token list
+    pub fn back_list(&mut self, p: Pointer) {
+        self.begin_token_list(p, BackedUp);
+    }
+
+    // 324
+    // leave a token-list input level
+    pub fn end_token_list(&mut self) {
+        if self.token_type() >= BackedUp {
+            // token list to be deleted
+            if self.token_type() <= Inserted {
+                self.flush_list(self.cur_input.start_field);
+            } else {
+                self.delete_token_ref(self.cur_input.start_field); // update reference count
+                if self.token_type() == Macro {
+                    // parameters must be flushed
+                    while self.param_ptr > self.param_start() as usize {
+                        self.param_ptr -= 1;
+                        self.flush_list(self.param_stack[self.param_ptr]);
+                    }
+                }
+            }
+        } else if self.token_type() == UTemplate {
+            if self.align_state > 500000 {
+                self.align_state = 0;
+            } else {
+                self.fatal_error("(interwoven alignment preambles are not allowed)");
+            }
+        }
+        self.pop_input();
+        self.check_interrupt();
+    }
+
+    // 325
+    // undoes one token of input
+    pub fn back_input(&mut self) {
+        while self.cur_input.state_field == TokenList
+            && self.cur_input.loc_field == Null
+            && self.token_type() != VTemplate
+        {
+            self.end_token_list(); // conserve stack space
+        }
+        let p = self.get_avail();
+        self.set_info(p as usize, self.cur_tok);
+        if (self.cur_tok as i32) < right_brace_limit {
+            if (self.cur_tok as i32) < left_brace_limit {
+                self.align_state -= 1;
+            } else {
+                self.align_state += 1;
+            }
+        }
+        self.push_input();
+        self.cur_input.state_field = TokenList;
+        self.cur_input.start_field = p;
+        self.cur_input.index_field = BackedUp;
+        self.cur_input.loc_field = p; // that was back_list(p), without procedure overhead
+    }
+
+    // 327
+    // back up one token and call error
+    pub fn back_error(&mut self) {
+        self.OK_to_interrupt = false;
+        self.back_input();
+        self.OK_to_interrupt = true;
+        self.error();
+    }
+
+    // back up one inserted token and call error
+    pub fn ins_error(&mut self) {
+        self.OK_to_interrupt = false;
+        self.back_input();
+        self.cur_input.index_field = Inserted;
+        self.OK_to_interrupt = true;
+        self.error();
+    }
+
+    // 328
+    // starts a new level of input for lines of characters
+    pub fn begin_file_reading(&mut self) {
+        if self.in_open == MaxInOpen {
+            self.overflow("text input levels", MaxInOpen);
+        }
+        if self.first == BufSize as usize {
+            self.overflow("buffer size", BufSize);
+        }
+        self.in_open += 1;
+        self.push_input();
+        self.cur_input.index_field = self.in_open as QuarterWord;
+        self.line_stack[self.in_open as usize] = self.line;
+        self.cur_input.start_field = self.first as HalfWord;
+        self.cur_input.state_field = MidLine;
+        self.cur_input.name_field = 0; // terminal_input is now true
+    }
+
+    // 329
+    // leave a level of input for lines of characters
+    pub fn end_file_reading(&mut self) {
+        self.first = self.cur_input.start_field as usize;
+        self.line = self.line_stack[self.cur_input.index_field as usize];
+        if self.cur_input.name_field > 17 {
+            self.input_file[self.cur_input.index_field as usize] = None;
+        }
+        self.pop_input();
+        self.in_open -= 1;
+    }
+
+    // 330
+    // removes completed error-inserted lines from memory
+    pub fn clear_for_error_prompt(&mut self) {
+        while self.cur_input.state_field != TokenList
+            && self.terminal_input()
+            && self.input_ptr > 0
+            && self.cur_input.loc_field > self.cur_input.limit_field
+        {
+            self.end_file_reading();
+        }
+        self.print_ln();
+    }
+
+    // 336
+    // the current token is \outer, or the file has ended in the middle of something
+    pub fn check_outer_validity(&mut self) {
+        if self.scanner_status != Normal {
+            self.deletions_allowed = false;
+            // 337: back up an outer control sequence so that it can be reread
+            if self.cur_cs != 0 {
+                if self.cur_input.state_field == TokenList
+                    || self.cur_input.name_field < 1
+                    || self.cur_input.name_field > 17
+                {
+                    let p = self.get_avail();
+                    self.set_info(p as usize, cs_token_flag as HalfWord + self.cur_cs);
+                    self.back_list(p); // prepare to read the control sequence again
+                }
+                self.cur_cmd = Spacer;
+                self.cur_chr = ' ' as HalfWord; // replace it by a space
+            }
+            if self.scanner_status > Skipping {
+                // 338: tell the user what has run away and try to recover
+                self.runaway(); // print a definition, argument, or preamble
+                if self.cur_cs == 0 {
+                    self.print_err("File ended");
+                } else {
+                    self.cur_cs = 0;
+                    self.print_err("Forbidden control sequence found");
+                }
+                self.print(" while scanning ");
+                // 339: print either `definition' or `use' or `preamble' or `text',
+                // and insert tokens that should lead to recovery
+                let mut p = self.get_avail();
+                match self.scanner_status {
+                    Defining => {
+                        self.print("definition");
+                        self.set_info(p as usize, (right_brace_token + '}' as i32) as HalfWord);
+                    }
+                    Matching => {
+                        self.print("use");
+                        self.set_info(p as usize, self.par_token);
+                        self.long_state = OuterCall;
+                    }
+                    Aligning => {
+                        self.print("preamble");
+                        self.set_info(p as usize, (right_brace_token + '}' as i32) as HalfWord);
+                        let q = p;
+                        p = self.get_avail();
+                        self.set_link(p as usize, q);
+                        self.set_info(p as usize, cs_token_flag as HalfWord + FrozenCr);
+                        self.align_state = -1000000;
+                    }
+                    _ => {
+                        self.print("text");
+                        self.set_info(p as usize, (right_brace_token + '}' as i32) as HalfWord);
+                    }
+                }
+                self.ins_list(p);
+                self.print(" of ");
+                self.sprint_cs(self.warning_index);
+                self.help(&["I suspect you have forgotten a `}', causing me",
+                    "to read past where you wanted me to stop.",
+                    "I'll try to recover; but if the error is serious,",
+                    "you'd better type `E' or `X' now and fix your file."]);
+                self.error();
+            } else {
+                self.print_err("Incomplete ");
+                self.print_cmd_chr(IfTest, self.cur_if as HalfWord);
+                self.print("; all text was ignored after line ");
+                self.print_int(self.skip_line);
+                self.help(&["A forbidden control sequence occurred in skipped text.",
+                    "This kind of error happens when you say `\\if...' and forget",
+                    "the matching `\\fi'. I've inserted a `\\fi'; this might work."]);
+                if self.cur_cs != 0 {
+                    self.cur_cs = 0;
+                } else {
+                    self.helpline[0] = "The file ended while I was skipping conditional text.";
+                }
+                self.cur_tok = cs_token_flag as HalfWord + FrozenFi;
+                self.ins_error();
+            }
+            self.deletions_allowed = true;
+        }
+    }
+
+    // 341
+    // sets cur_cmd, cur_chr, cur_cs to next token
+    pub fn get_next(&mut self) {
+        'restart: loop {
+            self.cur_cs = 0;
+            if self.cur_input.state_field != TokenList {
+                // 343: input from external file, goto restart if no input found
+                'switch: loop {
+                    if self.cur_input.loc_field <= self.cur_input.limit_field {
+                        // current line not yet finished
+                        self.cur_chr = self.buffer[self.cur_input.loc_field as usize] as HalfWord;
+                        self.cur_input.loc_field += 1;
+                        'reswitch: loop {
+                            self.cur_cmd = self.cat_code(self.cur_chr) as QuarterWord;
+                            // 344: change state if necessary, and goto switch if the current
+                            // character should be ignored, or goto reswitch if the current
+                            // character changes to another
+                            let state = self.cur_input.state_field;
+                            match self.cur_cmd {
+                                Ignore => continue 'switch,
+                                Spacer => {
+                                    if state != MidLine {
+                                        continue 'switch;
+                                    }
+                                    // 348: enter skip_blanks state, emit a space
+                                    self.cur_input.state_field = SkipBlanks;
+                                    self.cur_chr = ' ' as HalfWord;
+                                }
+                                Escape => self.scan_control_sequence(),
+                                ActiveChar => {
+                                    // 350: process an active-character control sequence
+                                    self.cur_cs = self.cur_chr + ActiveBase;
+                                    self.cur_cmd = self.eq_type(self.cur_cs as usize);
+                                    self.cur_chr = self.equiv(self.cur_cs as usize);
+                                    self.cur_input.state_field = MidLine;
+                                    if self.cur_cmd >= OuterCall {
+                                        self.check_outer_validity();
+                                    }
+                                }
+                                SupMark => {
+                                    // 352: if this sup_mark starts an expanded character,
+                                    // goto reswitch, otherwise set state:=mid_line
+                                    let loc = self.cur_input.loc_field as usize;
+                                    let limit = self.cur_input.limit_field as usize;
+                                    if self.cur_chr == self.buffer[loc] as HalfWord && loc < limit {
+                                        let c = self.buffer[loc + 1];
+                                        if c < 0o200 {
+                                            // yes we have an expanded char
+                                            self.cur_input.loc_field += 2;
+                                            let loc = loc + 2;
+                                            if is_hex(c) && loc <= limit {
+                                                let cc = self.buffer[loc];
+                                                if is_hex(cc) {
+                                                    self.cur_input.loc_field += 1;
+                                                    self.cur_chr = 16 * hex_value(c) + hex_value(cc);
+                                                    continue 'reswitch;
+                                                }
+                                            }
+                                            self.cur_chr = if c < 0o100 {
+                                                (c + 0o100) as HalfWord
+                                            } else {
+                                                (c - 0o100) as HalfWord
+                                            };
+                                            continue 'reswitch;
+                                        }
+                                    }
+                                    self.cur_input.state_field = MidLine;
+                                }
+                                InvalidChar => {
+                                    // 346: decry the invalid character and goto restart
+                                    self.print_err("Text line contains an invalid character");
+                                    self.help(&["A funny symbol that I can't read has just been input.",
+                                        "Continue, and I'll forget that it ever happened."]);
+                                    self.deletions_allowed = false;
+                                    self.error();
+                                    self.deletions_allowed = true;
+                                    continue 'restart;
+                                }
+                                // 347: handle situations involving spaces, braces, changes of state
+                                CarRet => {
+                                    self.cur_input.loc_field = self.cur_input.limit_field + 1;
+                                    if state == MidLine {
+                                        // 349: finish line, emit a space
+                                        self.cur_cmd = Spacer;
+                                        self.cur_chr = ' ' as HalfWord;
+                                    } else if state == SkipBlanks {
+                                        continue 'switch;
+                                    } else {
+                                        // 351: finish line, emit a \par
+                                        self.cur_cs = self.par_loc;
+                                        self.cur_cmd = self.eq_type(self.cur_cs as usize);
+                                        self.cur_chr = self.equiv(self.cur_cs as usize);
+                                        if self.cur_cmd >= OuterCall {
+                                            self.check_outer_validity();
+                                        }
+                                    }
+                                }
+                                Comment => {
+                                    // 350: finish line, goto switch
+                                    self.cur_input.loc_field = self.cur_input.limit_field + 1;
+                                    continue 'switch;
+                                }
+                                LeftBrace => {
+                                    self.cur_input.state_field = MidLine;
+                                    self.align_state += 1;
+                                }
+                                RightBrace => {
+                                    self.cur_input.state_field = MidLine;
+                                    self.align_state -= 1;
+                                }
+                                MathShift | TabMark | MacParam | SubMark | Letter | OtherChar => {
+                                    self.cur_input.state_field = MidLine;
+                                }
+                                _ => {}
+                            }
+                            break 'switch;
+                        }
+                    } else {
+                        self.cur_input.state_field = NewLine;
+                        // 360: move to next line of file, then goto restart if there is no next
+                        // line, or return if a \read line has finished
+                        if self.cur_input.name_field > 17 {
+                            // 362: read next line of file into buffer, or goto restart if the
+                            // file has ended
+                            self.line += 1;
+                            self.first = self.cur_input.start_field as usize;
+                            if !self.force_eof {
+                                if self.input_cur_file() {
+                                    self.firm_up_the_line(); // this sets limit
+                                } else {
+                                    self.force_eof = true;
+                                }
+                            }
+                            if self.force_eof {
+                                self.print_char(')' as u8);
+                                self.open_parens -= 1;
+                                self.update_terminal(); // show user that file has been read
+                                self.force_eof = false;
+                                self.end_file_reading(); // resume previous level
+                                self.check_outer_validity();
+                                continue 'restart;
+                            }
+                            if self.end_line_char_inactive() {
+                                self.cur_input.limit_field -= 1;
+                            } else {
+                                self.buffer[self.cur_input.limit_field as usize] = self.end_line_char() as u8;
+                            }
+                            self.first = self.cur_input.limit_field as usize + 1;
+                            self.cur_input.loc_field = self.cur_input.start_field; // ready to read
+                        } else {
+                            if !self.terminal_input() {
+                                // \read line has ended
+                                self.cur_cmd = 0;
+                                self.cur_chr = 0;
+                                return;
+                            }
+                            if self.input_ptr > 0 {
+                                // text was inserted during error recovery
+                                self.end_file_reading();
+                                continue 'restart; // resume previous level
+                            }
+                            if self.interaction > Interaction::NonstopMode {
+                                if self.end_line_char_inactive() {
+                                    self.cur_input.limit_field += 1;
+                                }
+                                if self.cur_input.limit_field == self.cur_input.start_field {
+                                    // previous line was empty
+                                    self.print_nl("(Please type a command or say `\\end')");
+                                }
+                                self.print_ln();
+                                self.first = self.cur_input.start_field as usize;
+                                self.prompt_input("*"); // input on-line into buffer
+                                self.cur_input.limit_field = self.last as HalfWord;
+                                if self.end_line_char_inactive() {
+                                    self.cur_input.limit_field -= 1;
+                                } else {
+                                    self.buffer[self.cur_input.limit_field as usize] = self.end_line_char() as u8;
+                                }
+                                self.first = self.cur_input.limit_field as usize + 1;
+                                self.cur_input.loc_field = self.cur_input.start_field;
+                            } else {
+                                // nonstop mode, which is intended for overnight batch processing,
+                                // never waits for on-line input
+                                self.fatal_error("*** (job aborted, no legal \\end found)");
+                            }
+                        }
+                        self.check_interrupt();
+                    }
+                }
+            } else {
+                // 357: input from token list, goto restart if end of list or
+                // if a parameter needs to be expanded
+                if self.cur_input.loc_field != Null {
+                    // list not exhausted
+                    let t = self.info(self.cur_input.loc_field as usize);
+                    self.cur_input.loc_field = self.link(self.cur_input.loc_field as usize); // move to next
+                    if t as i32 >= cs_token_flag {
+                        // a control sequence token
+                        self.cur_cs = t - cs_token_flag as HalfWord;
+                        self.cur_cmd = self.eq_type(self.cur_cs as usize);
+                        self.cur_chr = self.equiv(self.cur_cs as usize);
+                        if self.cur_cmd >= OuterCall {
+                            if self.cur_cmd == DontExpand {
+                                // 358: get the next token, suppressing expansion
+                                self.cur_cs = self.info(self.cur_input.loc_field as usize) - cs_token_flag as HalfWord;
+                                self.cur_input.loc_field = Null;
+                                self.cur_cmd = self.eq_type(self.cur_cs as usize);
+                                self.cur_chr = self.equiv(self.cur_cs as usize);
+                                if self.cur_cmd > MaxCommand {
+                                    self.cur_cmd = Relax;
+                                    self.cur_chr = NoExpandFlag;
+                                }
+                            } else {
+                                self.check_outer_validity();
+                            }
+                        }
+                    } else {
+                        self.cur_cmd = (t / 0o400) as QuarterWord;
+                        self.cur_chr = t % 0o400;
+                        match self.cur_cmd {
+                            LeftBrace => self.align_state += 1,
+                            RightBrace => self.align_state -= 1,
+                            OutParam => {
+                                // 359: insert macro parameter and goto restart
+                                let p = self.param_stack[(self.param_start() + self.cur_chr) as usize - 1];
+                                self.begin_token_list(p, Parameter);
+                                continue 'restart;
+                            }
+                            _ => {}
+                        }
+                    }
+                } else {
+                    // we are done with this token list
+                    self.end_token_list();
+                    continue 'restart; // resume previous level
+                }
+            }
+            // 342: if an alignment entry has just ended, take appropriate action
+            if self.cur_cmd <= CarRet && self.cur_cmd >= TabMark && self.align_state == 0 {
+                self.insert_v_template();
+                continue 'restart;
+            }
+            return;
+        }
+    }
+
+    // 354
+    // scans a control sequence after an escape character, and sets state
+    fn scan_control_sequence(&mut self) {
+        if self.cur_input.loc_field > self.cur_input.limit_field {
+            self.cur_cs = NullCs; // state is irrelevant in this case
+        } else {
+            'start_cs: loop {
+                let loc = self.cur_input.loc_field as usize;
+                let mut k = loc;
+                self.cur_chr = self.buffer[k] as HalfWord;
+                let mut cat = self.cat_code(self.cur_chr) as QuarterWord;
+                k += 1;
+                if cat == Letter || cat == Spacer {
+                    self.cur_input.state_field = SkipBlanks;
+                } else {
+                    self.cur_input.state_field = MidLine;
+                }
+                if cat == Letter && k <= self.cur_input.limit_field as usize {
+                    // 356: scan ahead in the buffer until finding a nonletter
+                    loop {
+                        self.cur_chr = self.buffer[k] as HalfWord;
+                        cat = self.cat_code(self.cur_chr) as QuarterWord;
+                        k += 1;
+                        if !(cat == Letter && k <= self.cur_input.limit_field as usize) {
+                            break;
+                        }
+                    }
+                    if self.reduce_expanded_code(k, cat) {
+                        continue 'start_cs;
+                    }
+                    if cat != Letter {
+                        k -= 1; // now k points to first nonletter
+                    }
+                    if k > loc + 1 {
+                        // multiletter control sequence has been scanned
+                        self.cur_cs = self.id_lookup(loc, k - loc);
+                        self.cur_input.loc_field = k as HalfWord;
+                        break 'start_cs;
+                    }
+                } else if self.reduce_expanded_code(k, cat) {
+                    continue 'start_cs;
+                }
+                self.cur_cs = SingleBase + self.buffer[loc] as HalfWord;
+                self.cur_input.loc_field += 1;
+                break 'start_cs;
+            }
+        }
+        self.cur_cmd = self.eq_type(self.cur_cs as usize);
+        self.cur_chr = self.equiv(self.cur_cs as usize);
+        if self.cur_cmd >= OuterCall {
+            self.check_outer_validity();
+        }
+    }
+
+    // 355
+    // if an expanded code is present at buffer[k-1], reduce it and return true
+    fn reduce_expanded_code(&mut self, k: usize, cat: QuarterWord) -> bool {
+        let limit = self.cur_input.limit_field as usize;
+        if self.buffer[k] as HalfWord == self.cur_chr && cat == SupMark && k < limit {
+            let c = self.buffer[k + 1];
+            if c < 0o200 {
+                // yes, one is indeed present
+                let mut d = 2;
+                if is_hex(c) && k + 2 <= limit {
+                    let cc = self.buffer[k + 2];
+                    if is_hex(cc) {
+                        d += 1;
+                    }
+                }
+                if d > 2 {
+                    self.cur_chr = 16 * hex_value(c) + hex_value(self.buffer[k + 2]);
+                    self.buffer[k - 1] = self.cur_chr as u8;
+                } else if c < 0o100 {
+                    self.buffer[k - 1] = c + 0o100;
+                } else {
+                    self.buffer[k - 1] = c - 0o100;
+                }
+                self.cur_input.limit_field -= d as HalfWord;
+                self.first -= d;
+                let mut k = k;
+                while k <= self.cur_input.limit_field as usize {
+                    self.buffer[k] = self.buffer[k + d];
+                    k += 1;
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    // 363
+    // sets limit to last, letting the user edit the line first if \pausing is positive
+    pub fn firm_up_the_line(&mut self) {
+        self.cur_input.limit_field = self.last as HalfWord;
+        if self.int_par(PausingCode) > 0 && self.interaction > Interaction::NonstopMode {
+            self.print_ln();
+            let start = self.cur_input.start_field as usize;
+            let limit = self.cur_input.limit_field as usize;
+            if start < limit {
+                for k in start..limit {
+                    self.print_str(self.buffer[k] as StrNumber);
+                }
+            }
+            self.first = limit;
+            self.prompt_input("=>"); // wait for user response
+            if self.last > self.first {
+                for k in self.first..self.last {
+                    // move line down in buffer
+                    self.buffer[k + start - self.first] = self.buffer[k];
+                }
+                self.cur_input.limit_field = (start + self.last - self.first) as HalfWord;
+            }
+        }
+    }
+
+    // 365
+    // sets cur_cmd, cur_chr, cur_tok
+    pub fn get_token(&mut self) {
+        self.no_new_control_sequence = false;
+        self.get_next();
+        self.no_new_control_sequence = true;
+        if self.cur_cs == 0 {
+            self.cur_tok = (self.cur_cmd as HalfWord * 0o400) + self.cur_chr;
+        } else {
+            self.cur_tok = cs_token_flag as HalfWord + self.cur_cs;
+        }
+    }
+}
@@ -0,0 +1,2725 @@
+// the port keeps tex.web's names: constants are written in PascalCase, a few fields keep
+// their mixed case, and character codes are written as `'x' as u8'
+#![allow(non_upper_case_globals, non_snake_case, non_camel_case_types, clippy::char_lit_as_u8)]
+
+use std::collections::{BTreeMap, HashMap};
+use std::char;
+use std::io::{BufRead, Read, Write};
+use std::marker::Copy;
+use std::ops::{Index, IndexMut};
+use byteorder::{ByteOrder, LittleEndian};
+
+mod strings;
+mod print;
+mod error;
+mod memory;
+mod nodes;
+mod commands;
+mod eqtb;
+mod input;
+mod tokens;
+mod nest;
+mod expand;
+mod marks;
+mod page_builder;
+mod scanning;
+mod scaled;
+mod glue_ratio;
+mod conditional;
+mod read;
+mod resolver;
+mod file_names;
+mod vfs;
+mod dump;
+mod font_metrics;
+mod opentype;
+mod dvi;
+mod dvi_reader;
+mod packaging;
+mod math;
+mod align;
+mod line_break;
+mod hyphenate;
+mod trie;
+mod virtual_font;
+mod extensions;
+mod page_items;
+mod pdf;
+mod svg;
+mod main_control;
+mod building;
+mod math_lists;
+mod mode_independent;
+#[cfg(test)]
+mod test_fonts;
+
+use strings::*;
+use print::*;
+use nodes::*;
+use commands::*;
+use input::*;
+use tokens::*;
+use eqtb::*;
+use nest::*;
+use marks::*;
+use page_builder::*;
+use scanning::*;
+use scaled::*;
+pub use scaled::Sp;
+use glue_ratio::*;
+use conditional::*;
+pub use resolver::{FileKind, FileResolver, PathResolver};
+use file_names::*;
+use font_metrics::*;
+use opentype::*;
+use dvi::*;
+use packaging::*;
+use math::*;
+use align::*;
+use line_break::*;
+use hyphenate::*;
+use trie::*;
+use virtual_font::*;
+use extensions::*;
+use page_items::*;
+pub use page_items::OutputFormat;
+use pdf::*;
+use svg::*;
+pub use vfs::Vfs;
+pub use dvi_reader::{DviCommand, DviFile, DviFontDef, DviMark, DviPage, DviPostamble};
+use building::*;
+use math_lists::*;
+use mode_independent::*;
+
+
+/* 
+ * Here are types of TeX
+ */
+struct Array<T:Default+Clone> {
+    min: usize,
+    data: Vec<T>,
+}
+
+impl<T:Default+Clone> Array<T> {
+    pub fn new(min:usize, max:usize) -> Array<T> {
+        Array {
+            min,
+            data: vec![Default::default(); max - min + 1],
+        }
+    }
+}
+
+impl<T:Default+Clone> Index<usize> for Array<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &Self::Output {
+        &self.data[i - self.min]
+    }
+}
+
+impl<T:Default+Clone> IndexMut<usize> for Array<T> {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        &mut self.data[i - self.min]
+    }
+}
+
+// 2
+const banner: &str = "This is TeX, Version 3.1415926"; // printed when TEX starts
+
+// 18
+type ASCIICode = u8;
+
+// 19
+type TextChar = u8; // The data type of characters in text files
+
+// 25
+type EightBits = u8; // Unsigned one-byte quantity
+type AlphaFile = Box<dyn BufRead>; // Files that contain textual data
+type ByteFile = Box<dyn Read>; // Files that contain binary data
+
+// 36
+const MemMax: usize = 30000;
+const MemMin: usize = 0;
+const BufSize: i32 = 500;
+const ErrorLine: i32 = 72;
+const HalfErrorLine: i32 = 42;
+const MaxPrintLine: i32 = 79;
+const StackSize: i32 = 200;
+const MaxInOpen: i32 = 6;
+const FontMax: i32 = 75;
+const FontMemSize: i32 = 20000;
+const ParamSize: i32 = 60;
+const NestSize: usize = 40;
+const MaxStrings: i32 = 3000;
+const PoolSize: i32 = 32000;
+const SaveSize: i32 = 600;
+const TrieSize: i32 = 8000;
+const TrieOpSize: i32 = 500;
+const DviBufSize: i32 = 800;
+const FileNameSize: i32 = 4096; // file names are kept in Strings; this only bounds the format default
+
+// 38
+type PoolPointer = usize; // 0 .. pool_size, for variables that point into str_pool
+type StrNumber = i32; // 0 .. max_strings, for variables that point into str_start
+type PackedASCIICode = u8; // 0 .. 255, elements of str_pool array
+
+// 12 (compiler constants)
+const MemBot: usize = 0;
+const MemTop: usize = 30000;
+const FontBase: i32 = 0;
+const HashSize: i32 = 2100;
+const HashPrime: i32 = 1777;
+const HyphSize: i32 = 307;
+
+const Empty: i32 = 0; // symbolic name for a null constant
+
+
+// 73
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Interaction
+{
+    BatchMode,
+    NonstopMode,
+    ScrollMode,
+    ErrorStopMode,
+}
+
+impl Interaction {
+    fn value(&self) -> i32 {
+        match self {
+            Interaction::BatchMode => 0,
+            Interaction::NonstopMode => 1,
+            Interaction::ScrollMode => 2,
+            Interaction::ErrorStopMode => 3,
+        }
+    }
+}
+
+// 76
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum History
+{
+    Spotless,
+    WarningIssued,
+    ErrorMessageIssued,
+    FatalErrorStop,
+}
+
+
+// 101
+const unity: i32 = 0o200000; // 2^16, represents 1.00000
+const two: i32 = 0o400000; // 2^17, represents 2.00000
+type Scaled = i32;
+type SmallNumber = i32;
+
+
+// 110
+const MinQuarterWord: QuarterWord = 0;
+const MaxQuarterWord: QuarterWord = 255;
+const MinHalfWord: HalfWord = 0;
+const MaxHalfWord: HalfWord = 65535;
+
+// 113
+type QuarterWord = u8;  // 0..255
+type HalfWord = u16;    // 0..65535
+
+#[derive(Debug, Clone, Copy, Default)]
+struct TwoHalves {
+    data: [u8; 4],
+}
+
+impl TwoHalves {
+    pub fn new() -> TwoHalves {
+        TwoHalves {
+            data: [0; 4]
+        }
+    }
+
+    pub fn rh(&self) -> HalfWord {
+        LittleEndian::read_u16(&self.data[2..4])
+    }
+
+    pub fn set_rh(&mut self, value: HalfWord) {
+        LittleEndian::write_u16(&mut self.data[2..4], value);
+    }
+
+    pub fn lh(&self) -> HalfWord {
+        LittleEndian::read_u16(&self.data[0..2])
+    }
+
+    pub fn set_lh(&mut self, value: HalfWord) {
+        LittleEndian::write_u16(&mut self.data[0..2], value);
+    }
+
+    pub fn b0(&self) -> QuarterWord {
+        self.data[0]
+    }
+
+    pub fn set_b0(&mut self, value: QuarterWord) {
+        self.data[0] = value;
+    }
+
+    pub fn b1(&self) -> QuarterWord {
+        self.data[1]
+    }
+
+    pub fn set_b1(&mut self, value: QuarterWord) {
+        self.data[1] = value;
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FourQuarters {
+	b0: QuarterWord,
+	b1: QuarterWord,
+	b2: QuarterWord,
+	b3: QuarterWord,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MemoryWord {
+    data: [u8; 4],
+}
+
+impl MemoryWord {
+    pub fn new_i32(value: i32) -> MemoryWord {
+        let mut ret: MemoryWord = Default::default();
+        LittleEndian::write_i32(&mut ret.data, value);
+        ret
+    }
+
+    pub fn int(&self) -> i32 {
+        LittleEndian::read_i32(&self.data)
+    }
+
+    pub fn sc(&self) -> i32 {
+        LittleEndian::read_i32(&self.data)
+    }
+
+    #[cfg(not(feature = "fixed-glue"))]
+    pub fn gr(&self) -> GlueRatio {
+        GlueRatio::from_bits(LittleEndian::read_u32(&self.data))
+    }
+
+    pub fn set_int(&mut self, value: i32) {
+        LittleEndian::write_i32(&mut self.data, value);
+    }
+
+    pub fn set_sc(&mut self, value: i32) {
+        LittleEndian::write_i32(&mut self.data, value);
+    }
+
+    #[cfg(not(feature = "fixed-glue"))]
+    pub fn set_gr(&mut self, value: GlueRatio) {
+        LittleEndian::write_u32(&mut self.data, value.to_bits());
+    }
+
+    // b0 and b1 share the bytes of lh, so that type/subtype never clobber the link field
+    pub fn hh_b0(&self) -> u8 {
+        self.data[0]
+    }
+
+    pub fn set_hh_b0(&mut self, value: u8) {
+        self.data[0] = value;
+    }
+
+    pub fn hh_b1(&self) -> u8 {
+        self.data[1]
+    }
+
+    pub fn set_hh_b1(&mut self, value: u8) {
+        self.data[1] = value;
+    }
+
+    pub fn hh_rh(&self) -> u16 {
+        LittleEndian::read_u16(&self.data[2..4])
+    }
+
+    pub fn set_hh_rh(&mut self, value: u16) {
+        LittleEndian::write_u16(&mut self.data[2..4], value);
+    }
+
+    pub fn hh_lh(&self) -> u16 {
+        LittleEndian::read_u16(&self.data[0..2])
+    }
+
+    pub fn set_hh_lh(&mut self, value: u16) {
+        LittleEndian::write_u16(&mut self.data[0..2], value);
+    }
+
+    pub fn qqqq(&self) -> FourQuarters {
+        FourQuarters {
+            b0: self.data[0],
+            b1: self.data[1],
+            b2: self.data[2],
+            b3: self.data[3],
+        }
+    }
+
+    pub fn set_qqqq(&mut self, value: FourQuarters) {
+        self.data = [value.b0, value.b1, value.b2, value.b3];
+    }
+}
+
+
+// 115
+type Pointer = HalfWord;
+const Null: HalfWord = MinHalfWord; // the null pointer
+
+// 118
+impl TexState {
+    fn link(&self, n:usize) -> HalfWord {
+        self.mem[n].hh_rh()
+    }
+
+    fn info(&self, n:usize) -> HalfWord {
+        self.mem[n].hh_lh()
+    }
+
+    fn set_link(&mut self, n:usize, value: HalfWord) {
+        self.mem[n].set_hh_rh(value);
+    }
+
+    fn set_info(&mut self, n:usize, value: HalfWord) {
+        self.mem[n].set_hh_lh(value);
+    }
+}
+
+//133
+impl TexState {
+    // identifies what kind of node this is
+    fn a_type(&self, n:usize) -> u8{
+        self.mem[n].hh_b0()
+    }
+
+    // secondary identification in some cases
+    fn subtype(&self, n:usize) -> u8{
+        self.mem[n].hh_b1()
+    }
+
+    fn set_type(&mut self, n:usize, value: u8) {
+        self.mem[n].set_hh_b0(value);
+    }
+
+    fn set_subtype(&mut self, n:usize, value: u8) {
+        self.mem[n].set_hh_b1(value);
+    }
+
+    // does the argument point to a char node?
+    fn is_char_node(&self, n:usize) -> bool {
+        n >= self.hi_mem_min as usize
+    }
+
+    // the font code in a char node
+    fn font(&self, n:usize) -> u8 {
+        self.a_type(n)
+    }
+
+    // the character code in a char node
+    fn character(&self, n:usize) -> u8 {
+        self.subtype(n)
+    }
+
+    fn set_font(&mut self, n:usize, value: u8) {
+        self.set_type(n, value);
+    }
+
+    fn set_character(&mut self, n:usize, value: u8) {
+        self.set_subtype(n, value);
+    }
+}
+
+// 135
+const HlistNode: QuarterWord = 0;
+const BoxNodeSize: usize = GlueOffset + GlueRatioSize; // number of words to allocate for a box node
+const WidthOffset: usize = 1; // position of width field in a box node
+const DepthOffset: usize = 2; // position of depth field in a box node
+const HeightOffset: usize = 3; // position of height field in a box node
+
+
+impl TexState {
+    // width of the box, in sp
+    fn width(&self, n:usize) -> i32 {
+        self.mem[n + WidthOffset].sc()
+    }
+
+    // depth of the box, in sp
+    fn depth(&self, n:usize) -> i32 {
+        self.mem[n + DepthOffset].sc()
+    }
+
+    // height of the box, in sp
+    fn height(&self, n:usize) -> i32 {
+        self.mem[n + HeightOffset].sc()
+    }
+
+    // repositioning distance, in sp
+    fn shift_amount(&self, n:usize) -> i32 {
+        self.mem[n + 4].sc()
+    }
+
+    // beginning of the list inside the box
+    fn list_ptr(&self, n:usize) -> HalfWord {
+        self.link(n + ListOffset)
+    }
+
+    // applicable order of infinity
+    fn glue_order(&self, n:usize) -> u8 {
+        self.subtype(n + ListOffset)
+    }
+
+    // stretching or shrinking
+    fn glue_sign(&self, n:usize) -> u8 {
+        self.a_type(n + ListOffset)
+    }
+
+    // a word of type glue_ratio for glue setting
+    #[cfg(not(feature = "fixed-glue"))]
+    fn glue_set(&self, n:usize) -> GlueRatio {
+        self.mem[n+GlueOffset].gr()
+    }
+
+    // with fixed glue, the numerator and denominator of the ratio
+    #[cfg(feature = "fixed-glue")]
+    fn glue_set(&self, n:usize) -> GlueRatio {
+        GlueRatio::from_parts(self.mem[n + GlueOffset].sc(), self.mem[n + GlueOffset + 1].sc())
+    }
+
+    fn set_width(&mut self, n:usize, value: Scaled) {
+        self.mem[n + WidthOffset].set_sc(value);
+    }
+
+    fn set_depth(&mut self, n:usize, value: Scaled) {
+        self.mem[n + DepthOffset].set_sc(value);
+    }
+
+    fn set_height(&mut self, n:usize, value: Scaled) {
+        self.mem[n + HeightOffset].set_sc(value);
+    }
+
+    fn set_shift_amount(&mut self, n:usize, value: Scaled) {
+        self.mem[n + 4].set_sc(value);
+    }
+
+    fn set_list_ptr(&mut self, n:usize, value: HalfWord) {
+        self.set_link(n + ListOffset, value);
+    }
+
+    fn set_glue_order(&mut self, n:usize, value: u8) {
+        self.set_subtype(n + ListOffset, value);
+    }
+
+    fn set_glue_sign(&mut self, n:usize, value: u8) {
+        self.set_type(n + ListOffset, value);
+    }
+
+    #[cfg(not(feature = "fixed-glue"))]
+    fn set_glue_set(&mut self, n:usize, value: GlueRatio) {
+        self.mem[n + GlueOffset].set_gr(value);
+    }
+
+    #[cfg(feature = "fixed-glue")]
+    fn set_glue_set(&mut self, n:usize, value: GlueRatio) {
+        let (num, den) = value.to_parts();
+        self.mem[n + GlueOffset].set_sc(num);
+        self.mem[n + GlueOffset + 1].set_sc(den);
+    }
+}
+
+const ListOffset: usize = 5; // position of list_ptr field in a box node
+ 
+const Normal: i32 = 0; // the most common case when several cases are named
+const Stretching: i32 = 1; // glue setting applies to the stretch components
+const Shrinking: i32 = 2; // glue setting applies to the shrink components
+const GlueOffset: usize = 6; // position of glue_set in a box node
+
+// glue 150
+const GlueSpecSize: usize = 4; // number of words to allocate for a glue specification
+
+impl TexState {
+    // reference count of a glue specification
+    fn glue_ref_count(&self, n:usize) -> HalfWord {
+        self.link(n)
+    }
+
+    fn set_glue_ref_count(&mut self, n:usize, value: HalfWord) {
+        self.set_link(n, value);
+    }
+
+    // the stretchability of this glob of glue
+    fn stretch(&self, n:usize) -> Scaled {
+        self.mem[n + 2].sc()
+    }
+
+    fn set_stretch(&mut self, n:usize, value: Scaled) {
+        self.mem[n + 2].set_sc(value);
+    }
+
+    // the shrinkability of this glob of glue
+    fn shrink(&self, n:usize) -> Scaled {
+        self.mem[n + 3].sc()
+    }
+
+    fn set_shrink(&mut self, n:usize, value: Scaled) {
+        self.mem[n + 3].set_sc(value);
+    }
+
+    // order of infinity for stretching
+    fn stretch_order(&self, n:usize) -> u8 {
+        self.a_type(n)
+    }
+
+    fn set_stretch_order(&mut self, n:usize, value: u8) {
+        self.set_type(n, value);
+    }
+
+    // order of infinity for shrinking
+    fn shrink_order(&self, n:usize) -> u8 {
+        self.subtype(n)
+    }
+
+    fn set_shrink_order(&mut self, n:usize, value: u8) {
+        self.set_subtype(n, value);
+    }
+}
+const fil: i32 = 1; // first-order infinity
+const fill: i32 = 2; // second-order infinity
+const filll: i32 = 3; // third-order infinity
+
+type GlueOrd = i32; // normal .. filll, infinity to the 0, 1, 2, 3 power
+
+
+// 162
+const ZeroGlue: usize = MemBot; // specification for 0pt plus 0pt minus 0pt
+const FilGlue: usize = ZeroGlue + GlueSpecSize; // 0pt plus 1fil minus 0pt
+const FillGlue: usize = FilGlue + GlueSpecSize; // 0pt plus 1fill minus 0pt
+const SsGlue: usize = FillGlue + GlueSpecSize; // 0pt plus 1fil minus 1fil
+const FilNegGlue: usize = SsGlue + GlueSpecSize; // 0pt plus -1fil minus 0pt
+const LoMemStatMax: usize = FilNegGlue + GlueSpecSize - 1; // largest statically allocated word in the variable-size mem
+
+const PageInsHead: usize = MemTop; // list of insertion data for current page
+const ContribHead: usize = MemTop - 1; // vlist items not yet on current page
+const PageHead: usize = MemTop - 2; // vlist for current page
+const TempHead: usize = MemTop - 3; // head of a temporary list of some kind
+const HoldHead: usize = MemTop - 4; // head of a temporary list of another kind
+const AdjustHead: usize = MemTop - 5; // head of adjustment list returned by hpack
+const Active: usize = MemTop - 7; // head of active list in line_break, needs two words
+const AlignHead: usize = MemTop - 8; // head of preamble list for alignments
+const EndSpan: usize = MemTop - 9; // tail of spanned-width lists
+const OmitTemplate: usize = MemTop - 10; // a constant token list
+const NullList: usize = MemTop - 11; // permanently empty list
+const LigTrick: usize = MemTop - 12; // a ligature masquerading as a char_node
+const Garbage: usize = MemTop - 12; // used for scrap information
+const BackupHead: usize = MemTop - 13; // head of token list built by scan_keyword
+const HiMemStatMin: usize = MemTop - 13; // smallest statically allocated word in the one-word mem
+const HiMemStatUsage: usize = 14; // the number of one-word nodes always present
+
+
+// 212
+#[derive(Debug, Clone, Default, Copy)]
+struct ListStateRecord {
+    mode_field: i32,
+    head_field: HalfWord,
+    tail_field: HalfWord,
+    pg_field: i32,
+    ml_field: i32,
+    aux_field: MemoryWord,
+}
+
+impl ListStateRecord {
+    pub fn new() -> ListStateRecord {
+        ListStateRecord {
+            mode_field: 0,
+            head_field: 0,
+            tail_field: 0,
+            pg_field: 0,
+            ml_field: 0,
+            aux_field: Default::default(),
+        }
+    }
+}
+
+// 221
+impl TexState {
+    // level of definition
+    fn eq_level(&self, s:usize) -> QuarterWord {
+        self.eqtb[s].hh_b1()
+    }
+
+    fn set_eq_level(&mut self, s:usize, value: QuarterWord) {
+        self.eqtb[s].set_hh_b1(value);
+    }
+
+    // command code for equivalent
+    fn eq_type(&self, s:usize) -> QuarterWord {
+        self.eqtb[s].hh_b0()
+    }
+
+    fn set_eq_type(&mut self, s:usize, value: QuarterWord) {
+        self.eqtb[s].set_hh_b0(value);
+    }
+
+    // equivalent value
+    fn equiv(&self, s:usize) -> HalfWord {
+        self.eqtb[s].hh_rh()
+    }
+
+    fn set_equiv(&mut self, s:usize, value: HalfWord) {
+        self.eqtb[s].set_hh_rh(value);
+    }
+}
+const LevelZero: i32 = MinQuarterWord as i32; // level for undefined quantities
+const LevelOne: i32 = LevelZero + 1; // outermost level for defined quantities
+
+
+// 222
+const ActiveBase: HalfWord = 1;
+const SingleBase: HalfWord = ActiveBase + 256;
+const NullCs: HalfWord = SingleBase + 256;
+const HashBase: HalfWord = NullCs + 1;
+const FrozenControlSequence: HalfWord = HashBase + HashSize as HalfWord;
+const FrozenProtection: HalfWord = FrozenControlSequence;
+const FrozenCr: HalfWord = FrozenControlSequence + 1;
+const FrozenEndGroup: HalfWord = FrozenControlSequence + 2;
+const FrozenRight: HalfWord = FrozenControlSequence + 3;
+const FrozenFi: HalfWord = FrozenControlSequence + 4;
+const FrozenEndTemplate: HalfWord = FrozenControlSequence + 5;
+const FrozenEndv: HalfWord = FrozenControlSequence + 6;
+const FrozenRelax: HalfWord = FrozenControlSequence + 7;
+const EndWrite: HalfWord = FrozenControlSequence + 8;
+const FrozenDontExpand: HalfWord = FrozenControlSequence + 9;
+const FrozenNullFont: HalfWord = FrozenControlSequence + 10;
+const FontIdBase: HalfWord = FrozenNullFont - FontBase as HalfWord;
+const UndefinedControlSequence: HalfWord = FrozenNullFont + 257;
+const GlueBase: HalfWord = UndefinedControlSequence + 1;
+
+// Region 3 of eqtb
+// 224
+const LineSkipCode: HalfWord = 0;
+const BaselineSkipCode: HalfWord = 1;
+const ParSkipCode: HalfWord = 2;
+const AboveDisplaySkipCode: HalfWord = 3;
+const BelowDisplaySkipCode: HalfWord = 4;
+const AboveDisplayShortSkipCode: HalfWord = 5;
+const BelowDisplayShortSkipCode: HalfWord = 6;
+const LeftSkipCode: HalfWord = 7;
+const RightSkipCode: HalfWord = 8;
+const TopSkipCode: HalfWord = 9;
+const SplitTopSkipCode: HalfWord = 10;
+const TabSkipCode: HalfWord = 11;
+const SpaceSkipCode: HalfWord = 12;
+const XspaceSkipCode: HalfWord = 13;
+const ParFillSkipCode: HalfWord = 14;
+const ThinMuSkipCode: HalfWord = 15;
+const MedMuSkipCode: HalfWord = 16;
+const ThickMuSkipCode: HalfWord = 17;
+
+
+const GluePars: HalfWord = 18; // total number of glue parameters
+const SkipBase: HalfWord = GlueBase + GluePars; // table of 256 ``skip'' registers
+const MuSkipBase: HalfWord = SkipBase + 256; // table of 256 ``muskip'' registers
+const LocalBase: HalfWord = MuSkipBase + 256; // beginning of region 4
+
+
+// region 4 of eqtb
+// 230
+const ParShapeLoc: HalfWord = LocalBase;
+const OutputRoutineLoc: HalfWord = LocalBase + 1;
+const EveryParLoc: HalfWord = LocalBase + 2;
+const EveryMathLoc: HalfWord = LocalBase + 3;
+const EveryDisplayLoc: HalfWord = LocalBase + 4;
+const EveryHboxLoc: HalfWord = LocalBase + 5;
+const EveryVboxLoc: HalfWord = LocalBase + 6;
+const EveryJobLoc: HalfWord = LocalBase + 7;
+const EveryCrLoc: HalfWord = LocalBase + 8;
+const ErrHelpLoc: HalfWord = LocalBase + 9;
+const ToksBase: HalfWord = LocalBase + 10;
+const BoxBase: HalfWord = ToksBase + 256;
+const CurFontLoc: HalfWord = BoxBase + 256;
+
+
+const MathFontBase: HalfWord = CurFontLoc + 1; // table of 48 math font numbers
+const CatCodeBase: HalfWord = MathFontBase + 48; // table of 256 command codes (the ``catcodes'')
+const LcCodeBase: HalfWord = CatCodeBase + 256; // table of 256 lowercase mappings
+const UcCodeBase: HalfWord = LcCodeBase + 256; // table of 256 uppercase mappings
+const SfCodeBase: HalfWord = UcCodeBase + 256; // table of 256 spacefactor mappings
+const MathCodeBase: HalfWord = SfCodeBase + 256; // table of 256 math mode mappings
+const IntBase: HalfWord = MathCodeBase + 256; // beginning of region 5
+
+// 236
+const PretoleranceCode: HalfWord = 0;
+const ToleranceCode: HalfWord = 1;
+const LinePenaltyCode: HalfWord = 2;
+const HyphenPenaltyCode: HalfWord = 3;
+const ExHyphenPenaltyCode: HalfWord = 4;
+const ClubPenaltyCode: HalfWord = 5;
+const WidowPenaltyCode: HalfWord = 6;
+const DisplayWidowPenaltyCode: HalfWord = 7;
+const BrokenPenaltyCode: HalfWord = 8;
+const BinOpPenaltyCode: HalfWord = 9;
+const RelPenaltyCode: HalfWord = 10;
+const PreDisplayPenaltyCode: HalfWord = 11;
+const PostDisplayPenaltyCode: HalfWord = 12;
+const InterLinePenaltyCode: HalfWord = 13;
+const DoubleHyphenDemeritsCode: HalfWord = 14;
+const FinalHyphenDemeritsCode: HalfWord = 15;
+const AdjDemeritsCode: HalfWord = 16;
+const MagCode: HalfWord = 17;
+const DelimiterFactorCode: HalfWord = 18;
+const LoosenessCode: HalfWord = 19;
+const TimeCode: HalfWord = 20;
+const DayCode: HalfWord = 21;
+const MonthCode: HalfWord = 22;
+const YearCode: HalfWord = 23;
+const ShowBoxBreadthCode: HalfWord = 24;
+const ShowBoxDepthCode: HalfWord = 25;
+const HbadnessCode: HalfWord = 26;
+const VbadnessCode: HalfWord = 27;
+const PausingCode: HalfWord = 28;
+const TracingOnlineCode: HalfWord = 29;
+const TracingMacrosCode: HalfWord = 30;
+const TracingStatsCode: HalfWord = 31;
+const TracingParagraphsCode: HalfWord = 32;
+const TracingPagesCode: HalfWord = 33;
+const TracingOutputCode: HalfWord = 34;
+const TracingLostCharsCode: HalfWord = 35;
+const TracingCommandsCode: HalfWord = 36;
+// 37 is \tracingrestores, which is accepted and stored but shows nothing
+const UcHyphCode: HalfWord = 38;
+const OutputPenaltyCode: HalfWord = 39;
+const MaxDeadCyclesCode: HalfWord = 40;
+const HangAfterCode: HalfWord = 41;
+const FloatingPenaltyCode: HalfWord = 42;
+const GlobalDefsCode: HalfWord = 43;
+const CurFamCode: HalfWord = 44;
+const EscapeCharCode: HalfWord = 45;
+const DefaultHyphenCharCode: HalfWord = 46;
+const DefaultSkewCharCode: HalfWord = 47;
+const EndLineCharCode: HalfWord = 48;
+const NewLineCharCode: HalfWord = 49;
+const LanguageCode: HalfWord = 50;
+const LeftHyphenMinCode: HalfWord = 51;
+const RightHyphenMinCode: HalfWord = 52;
+const HoldingInsertsCode: HalfWord = 53;
+const ErrorContextLinesCode: HalfWord = 54;
+
+const IntPars: HalfWord = 55; // total number of integer parameters
+const CountBase: HalfWord = IntBase + IntPars; // 256 user \count registers
+const DelCodeBase: HalfWord = CountBase + 256; // 256 delimiter code mappings
+const DimenBase: HalfWord = DelCodeBase + 256; // beginning of region 6
+
+const ScaledBase: HalfWord = DimenBase + DimenPars; // table of 256 user-defined \dimen registers
+
+const EqtbSize: HalfWord = ScaledBase + 255; // largest subscript of eqtb
+
+// 247
+const ParIndentCode: HalfWord = 0; // indentation of paragraphs
+const MathSurroundCode: HalfWord = 1; // space around math in text
+const LineSkipLimitCode: HalfWord = 2; // threshold for line_skip instead of baseline_skip
+const HsizeCode: HalfWord = 3; // line width in horizontal mode
+const VsizeCode: HalfWord = 4; // page height in vertical mode
+const MaxDepthCode: HalfWord = 5; // maximum depth of boxes on main pages
+const SplitMaxDepthCode: HalfWord = 6; // maximum depth of boxes on split pages
+const BoxMaxDepthCode: HalfWord = 7; // maximum depth of explicit vboxes
+const HfuzzCode: HalfWord = 8; // tolerance for overfull hbox messages
+const VfuzzCode: HalfWord = 9; // tolerance for overfull vbox messages
+const DelimiterShortfallCode: HalfWord = 10; // maximum amount uncovered by variable delimiters
+const NullDelimiterSpaceCode: HalfWord = 11; // blank space in null delimiters
+const ScriptSpaceCode: HalfWord = 12; // extra space after subscript or superscript
+const PreDisplaySizeCode: HalfWord = 13; // length of text preceding a display
+const DisplayWidthCode: HalfWord = 14; // length of line for displayed equation
+const DisplayIndentCode: HalfWord = 15; // indentation of line for dispalyed equation
+const OverfullRuleCode: HalfWord = 16; // width of rule that identifies overfull hboxes
+const HangIndentCode: HalfWord = 17; // amount of hanging indentation
+const HOffsetCode: HalfWord = 18; // amount of horizontal offset when shipping pages out
+const VOffsetCode: HalfWord = 19; // amount of vertical offset when shipping pages out
+const EmergencyStretchCode: HalfWord = 20; // reduces badness on final pass of line-breaking
+
+const DimenPars: HalfWord = 21; // total number of dimension parameters
+
+
+/*
+#define dimen(s) (eqtb[scaled_base+s].sc)
+#define dimen_par(s) (eqtb[dimen_base+s].sc)
+#define par_indent dimen_par(par_indent_code)
+#define math_surround dimen_par(math_surround_code)
+#define line_skip_limit dimen_par(line_skip_limit_code)
+#define hsize dimen_par(hsize_code)
+#define vsize dimen_par(vsize_code)
+#define max_depth dimen_par(max_depth_code)
+#define split_max_depth dimen_par(split_max_depth_code)
+#define box_max_depth dimen_par(box_max_depth_code)
+#define hfuzz dimen_par(hfuzz_code)
+#define vfuzz dimen_par(vfuzz_code)
+#define delimiter_shortfall dimen_par(delimiter_shortfall_code)
+#define null_delimiter_space dimen_par(null_delimiter_space_code)
+#define script_space dimen_par(script_space_code)
+#define pre_display_size dimen_par(pre_display_size_code)
+#define display_width dimen_par(display_width_code)
+#define display_indent dimen_par(display_indent_code)
+#define overfull_rule dimen_par(overfull_rule_code)
+#define hang_indent dimen_par(hang_indent_code)
+#define h_offset dimen_par(h_offset_code)
+#define v_offset dimen_par(v_offset_code)
+#define emergency_stretch dimen_par(emergency_stretch_code)
+*/
+
+
+// 269
+enum GroupCode
+{
+	BottomLevel, // group code for the outside world
+	SimpleGroup, // group code for local structure only
+	HboxGroup, // code for `\hbox{...}'
+	AdjustedHboxGroup, // code for `\hbox{...}' in vertical mode
+	VboxGroup, // code for `\vbox{...}'
+	VtopGroup, // code for `\vtop{...}'
+	AlignGroup, // code for `\halign{...}', `\valign{...}'
+	NoAlignGroup, // code for `\noalign{...}'
+	OutputGroup, // code for output routine
+	MathGroup, // code for, e.g., `^{...}'
+	DiscGroup, // code for `\discretionary{...}{...}{...}'
+	InsertGroup, // code for `\insert{...}', `\vadjust{...}'
+	VcenterGroup, // code for `\vcenter{...}'
+	MathChoiceGroup, // code for `\mathchoice{...}{...}{...}'
+	SemiSimpleGroup, // code for `\begingroup...\endgroup'
+	MathShiftGroup, // code for `$...$'
+	MathLeftGroup, // code for `\left...\right'
+}
+
+const BottomLevel: QuarterWord = 0;
+const SimpleGroup: QuarterWord = 1;
+const HboxGroup: QuarterWord = 2;
+const AdjustedHboxGroup: QuarterWord = 3;
+const VboxGroup: QuarterWord = 4;
+const VtopGroup: QuarterWord = 5;
+const AlignGroup: QuarterWord = 6;
+const NoAlignGroup: QuarterWord = 7;
+const OutputGroup: QuarterWord = 8;
+const MathGroup: QuarterWord = 9;
+const DiscGroup: QuarterWord = 10;
+const InsertGroup: QuarterWord = 11;
+const VcenterGroup: QuarterWord = 12;
+const MathChoiceGroup: QuarterWord = 13;
+const SemiSimpleGroup: QuarterWord = 14;
+const MathShiftGroup: QuarterWord = 15;
+const MathLeftGroup: QuarterWord = 16;
+
+
+impl GroupCode {
+    fn value(&self) -> QuarterWord {
+        match self {
+            GroupCode::BottomLevel => BottomLevel,
+            GroupCode::SimpleGroup => SimpleGroup,
+            GroupCode::HboxGroup => HboxGroup,
+            GroupCode::AdjustedHboxGroup => AdjustedHboxGroup,
+            GroupCode::VboxGroup => VboxGroup,
+            GroupCode::VtopGroup => VtopGroup,
+            GroupCode::AlignGroup => AlignGroup,
+            GroupCode::NoAlignGroup => NoAlignGroup,
+            GroupCode::OutputGroup => OutputGroup,
+            GroupCode::MathGroup => MathGroup,
+            GroupCode::DiscGroup => DiscGroup,
+            GroupCode::InsertGroup => InsertGroup,
+            GroupCode::VcenterGroup => VcenterGroup,
+            GroupCode::MathChoiceGroup => MathChoiceGroup,
+            GroupCode::SemiSimpleGroup => SemiSimpleGroup,
+            GroupCode::MathShiftGroup => MathShiftGroup,
+            GroupCode::MathLeftGroup => MathLeftGroup,
+        }
+    }
+
+    fn from(value:QuarterWord) -> GroupCode {
+        match value {
+            BottomLevel =>  GroupCode::BottomLevel,
+            SimpleGroup =>  GroupCode::SimpleGroup,
+            HboxGroup   =>  GroupCode::HboxGroup,
+            AdjustedHboxGroup   =>  GroupCode::AdjustedHboxGroup,
+            VboxGroup   =>  GroupCode::VboxGroup,
+            VtopGroup   =>  GroupCode::VtopGroup,
+            AlignGroup  =>  GroupCode::AlignGroup,
+            NoAlignGroup    =>  GroupCode::NoAlignGroup,
+            OutputGroup =>  GroupCode::OutputGroup,
+            MathGroup   =>  GroupCode::MathGroup,
+            DiscGroup   =>  GroupCode::DiscGroup,
+            InsertGroup =>  GroupCode::InsertGroup,
+            VcenterGroup    =>  GroupCode::VcenterGroup,
+            MathChoiceGroup =>  GroupCode::MathChoiceGroup,
+            SemiSimpleGroup =>  GroupCode::SemiSimpleGroup,
+            MathShiftGroup  =>  GroupCode::MathShiftGroup,
+            MathLeftGroup   =>  GroupCode::MathLeftGroup,
+            _ => panic!("GroupCode no found")
+        }
+    }
+}
+
+/**
+ * Main TexState
+ */
+impl TexState {
+    fn dimen(&self, s: HalfWord) -> i32{
+        self.eqtb[(ScaledBase + s)as usize].int()
+    }
+
+    fn dimen_par(&self, s: HalfWord) -> i32{
+        self.eqtb[(DimenBase + s)as usize].int()
+    }
+
+    fn par_indent(&self) -> i32{
+        self.dimen_par(ParIndentCode)
+    }
+
+    fn set_dimen_par(&mut self, s: HalfWord, value: Scaled) {
+        self.eqtb[(DimenBase + s) as usize].set_sc(value);
+    }
+
+    // 224
+    fn skip(&self, s: HalfWord) -> HalfWord {
+        self.equiv((SkipBase + s) as usize)
+    }
+
+    fn mu_skip(&self, s: HalfWord) -> HalfWord {
+        self.equiv((MuSkipBase + s) as usize)
+    }
+
+    fn glue_par(&self, s: HalfWord) -> HalfWord {
+        self.equiv((GlueBase + s) as usize)
+    }
+
+    // 230
+    fn par_shape_ptr(&self) -> HalfWord {
+        self.equiv(ParShapeLoc as usize)
+    }
+
+    fn output_routine(&self) -> HalfWord {
+        self.equiv(OutputRoutineLoc as usize)
+    }
+
+    fn err_help(&self) -> HalfWord {
+        self.equiv(ErrHelpLoc as usize)
+    }
+
+    fn box_reg(&self, s: HalfWord) -> HalfWord {
+        self.equiv((BoxBase + s) as usize)
+    }
+
+    fn set_box_reg(&mut self, s: HalfWord, value: HalfWord) {
+        self.set_equiv((BoxBase + s) as usize, value);
+    }
+
+    fn cur_font(&self) -> HalfWord {
+        self.equiv(CurFontLoc as usize)
+    }
+
+    fn fam_fnt(&self, s: HalfWord) -> HalfWord {
+        self.equiv((MathFontBase + s) as usize)
+    }
+
+    fn cat_code(&self, s: HalfWord) -> HalfWord {
+        self.equiv((CatCodeBase + s) as usize)
+    }
+
+    fn lc_code(&self, s: HalfWord) -> HalfWord {
+        self.equiv((LcCodeBase + s) as usize)
+    }
+
+    fn sf_code(&self, s: HalfWord) -> HalfWord {
+        self.equiv((SfCodeBase + s) as usize)
+    }
+
+    fn math_code(&self, s: HalfWord) -> HalfWord {
+        self.equiv((MathCodeBase + s) as usize)
+    }
+
+    fn set_cat_code(&mut self, s: HalfWord, value: HalfWord) {
+        self.set_equiv((CatCodeBase + s) as usize, value);
+    }
+
+    fn set_lc_code(&mut self, s: HalfWord, value: HalfWord) {
+        self.set_equiv((LcCodeBase + s) as usize, value);
+    }
+
+    fn set_uc_code(&mut self, s: HalfWord, value: HalfWord) {
+        self.set_equiv((UcCodeBase + s) as usize, value);
+    }
+
+    fn set_sf_code(&mut self, s: HalfWord, value: HalfWord) {
+        self.set_equiv((SfCodeBase + s) as usize, value);
+    }
+
+    fn set_math_code(&mut self, s: HalfWord, value: HalfWord) {
+        self.set_equiv((MathCodeBase + s) as usize, value);
+    }
+
+    // 236
+    fn int_par(&self, s: HalfWord) -> i32 {
+        self.eqtb[(IntBase + s) as usize].int()
+    }
+
+    fn set_int_par(&mut self, s: HalfWord, value: i32) {
+        self.eqtb[(IntBase + s) as usize].set_int(value);
+    }
+
+    fn count(&self, s: HalfWord) -> i32 {
+        self.eqtb[(CountBase + s) as usize].int()
+    }
+
+    fn del_code(&self, s: HalfWord) -> i32 {
+        self.eqtb[(DelCodeBase + s) as usize].int()
+    }
+
+    fn set_del_code(&mut self, s: HalfWord, value: i32) {
+        self.eqtb[(DelCodeBase + s) as usize].set_int(value);
+    }
+
+    fn escape_char(&self) -> i32 {
+        self.int_par(EscapeCharCode)
+    }
+
+    fn end_line_char(&self) -> i32 {
+        self.int_par(EndLineCharCode)
+    }
+
+    fn new_line_char(&self) -> i32 {
+        self.int_par(NewLineCharCode)
+    }
+
+    fn tracing_online(&self) -> i32 {
+        self.int_par(TracingOnlineCode)
+    }
+
+    // 237
+    fn end_line_char_inactive(&self) -> bool {
+        self.end_line_char() < 0 || self.end_line_char() > 255
+    }
+}
+
+// 256
+impl TwoHalves {
+    // link for coalesced lists
+    fn next(&self) -> HalfWord {
+        self.lh()
+    }
+
+    fn set_next(&mut self, value: HalfWord) {
+        self.set_lh(value);
+    }
+
+    // string number for control sequence name
+    fn text(&self) -> HalfWord {
+        self.rh()
+    }
+
+    fn set_text(&mut self, value: HalfWord) {
+        self.set_rh(value)
+    }
+}
+
+// test if all positions are occupied
+fn hash_is_full(hash_used: HalfWord, hash_base: HalfWord) -> bool {
+    hash_used == hash_base
+}
+
+// a frozen font identifier's name
+fn font_id_text(hash: &Array<TwoHalves>, n: HalfWord) -> HalfWord {
+    hash[(FontIdBase + n) as usize].rh()
+}
+
+fn set_font_id_text(hash: &mut Array<TwoHalves>, n: HalfWord, value: HalfWord) {
+    hash[(FontIdBase + n) as usize].set_rh(value);
+}
+
+
+// 289
+const cs_token_flag: i32 = 0o7777; // amount added to the eqtb location in a token that stands for a control
+                                 // sequence; is a multiple of 256, less 1
+const left_brace_token: i32 = 0o400; // 2^8*left_brace
+const left_brace_limit: i32 = 0o1000; // 2^8*(left_brace+1)
+const right_brace_token: i32 = 0o1000; // 2^8*right_brace
+const right_brace_limit: i32 = 0o1400; // 2^8*(right_brace+1)
+const math_shift_token: i32 = 0o1400; // 2^8*math_shift
+const tab_token: i32 = 0o2000; // 2^8*tab_mark
+const out_param_token: i32 = 0o2400; // 2^8*out_param
+const space_token: i32 = 0o5040; // 2^8*spacer + " "
+const letter_token: i32 = 0o5400; // 2^8*letter
+const other_token: i32 = 0o6000; // 2^8*other_char
+const match_token: i32 = 0o6400; // 2^8*match
+const end_match_token: i32 = 0o7000; // 2^8*end_match
+
+// 382
+const top_mark_code: usize = 0; // the mark in effect at the previous page break
+const first_mark_code: usize = 1; // the first mark between top_mark and bot_mark
+const bot_mark_code: usize = 2; // the mark in effect at the current page break
+const split_first_mark_code: usize = 3; // the first mark found by \vsplit
+const split_bot_mark_code: usize = 4; // the last mark found by \vsplit
+
+macro_rules! top_mark {
+    ($state:expr) => {
+        $state.cur_mark[top_mark_code]
+    };
+}
+
+macro_rules! first_mark {
+    ($state:expr) => {
+        $state.cur_mark[first_mark_code]
+    };
+}
+
+macro_rules! bot_mark {
+    ($state:expr) => {
+        $state.cur_mark[bot_mark_code]
+    };
+}
+
+macro_rules! split_first_mark {
+    ($state:expr) => {
+        $state.cur_mark[split_first_mark_code]
+    };
+}
+
+macro_rules! split_bot_mark {
+    ($state:expr) => {
+        $state.cur_mark[split_bot_mark_code]
+    };
+}
+
+// 410
+const IntVal: i32 = 0; // integer values
+const DimenVal: i32 = 1; // dimension values
+const GlueVal: i32 = 2; // glue specifications
+const MuVal: i32 = 3; // math glue specifications
+const IdentVal: i32 = 4; // font identifier
+const TokVal: i32 = 5; // token lists
+
+
+// 438
+const octal_token: i32 = other_token + /*'*/39; // apostrophe, indicates an octal constant
+const hex_token: i32 = other_token + /*"*/34; // double quote, indicates a hex constant
+const alpha_token: i32 = other_token + /*`*/96; // reverse apostrophe, precedes alpha constants
+const point_token: i32 = other_token + /*.*/46; // decimal point
+const continental_point_token: i32 = other_token + /*,*/44; // decimal point, Eurostyle
+
+
+// 480
+const closed: i32 = 2; // not open, or at end of file
+const just_open: i32 = 1; // newly opened, first line not yet read
+
+// 489
+const if_node_size: i32 = 2; // number of words in stack entry for conditionals
+// #define if_line_field(s) mem[s+1].an_int
+const if_code: i32 = 1; // code for \if... being evaluated
+const fi_code: i32 = 2; // code for \fi
+const else_code: i32 = 3; // code for \else
+const or_code: i32 = 4; // code for \or
+
+// 520
+const format_default_length: usize = 9; // length of TEX_format_default string ; NOTE: used to be 20
+const format_ext_length: usize = 4; // length of its `.fmt' part
+// #define format_extension /*.fmt*/1297 // the extension as a WEB constant
+
+pub(crate) struct TexState
+{
+    ready_already: i32,
+    // 20
+    xord: Array<ASCIICode>,
+    xchr: Array<TextChar>,
+
+    // 76
+    deletions_allowed: bool,
+    set_box_allowed: bool,
+    history: History,
+    error_count: i32,
+
+    // 30
+    buffer: Array<u8>, // lines of characters being read
+    first: usize, // the first unused position in buffer
+    last: usize, // end of the line just input to buffer
+    max_buf_stack: usize, // largest index used in buffer
+
+    // 32
+    term_in: Box<dyn BufRead>, // the terminal as an input file
+    term_out: Box<dyn Write>, // the terminal as an output file
+
+    // 37
+    first_line: Option<Vec<u8>>, // the first line of input, when the command line supplies it
+    interaction_option: Option<Interaction>, // the interaction level asked for by the caller, if any
+
+    // 241
+    source_date_epoch: Option<i64>, // the time to use instead of the clock, in seconds since 1970
+
+    // 39
+    str_pool: Array<PackedASCIICode>, // the characters
+    str_start: Array<PoolPointer>, // the starting pointers
+    pool_ptr: PoolPointer, // first unused position in str_pool
+    str_ptr: StrNumber, // number of the current string being created
+    init_pool_ptr: PoolPointer, // the starting value of pool_ptr
+    init_str_ptr: StrNumber, // the starting value of str_ptr
+
+    // 54
+    log_file: Option<Box<dyn Write>>, // transcript of TeX session
+    selector: i32, // where to print a message
+    dig: [u8; 23], // digits in a number being output
+    tally: i32, // the number of characters recently printed
+    term_offset: i32, // the number of characters on the current terminal line
+    file_offset: i32, // the number of characters on the current file line
+    trick_buf: [ASCIICode; ErrorLine as usize + 1], // circular buffer for pseudoprinting
+    trick_count: i32, // threshold for pseudoprinting, explained later
+    first_count: i32, // another variable for pseudoprinting
+    old_setting: i32, // saved selector for begin_diagnostic
+
+    // 26
+    name_of_file: String, // the name of the file about to be opened
+    resolver: Box<dyn FileResolver>, // where input files are found and output files are made
+
+    // 512
+    cur_name: StrNumber, // name of file just scanned
+    cur_area: StrNumber, // file area just scanned, or ""
+    cur_ext: StrNumber, // file extension just scanned, or ""
+
+    // 513
+    area_delimiter: PoolPointer, // the most recent `/', if any
+    ext_delimiter: PoolPointer, // the most recent `.', if any
+    quoted_filename: bool, // are we inside double quotes in a file name?
+
+    // 527
+    name_in_progress: bool, // is a file name being scanned?
+    job_name: StrNumber, // principal file name
+
+    // 528
+    log_opened: bool, // has the transcript file been opened?
+    log_name: StrNumber, // full name of the log file
+    dvi_file: Option<Box<dyn Write>>, // the device-independent output goes here
+    output_file_name: StrNumber, // full name of the output file
+    output_format: OutputFormat, // whether pages go to the DVI file, the PDF file or SVG files
+    pdf: PdfDocument, // the objects of the PDF file, written when the job ends
+    svg: SvgOutput,
+
+    // 1299
+    format_ident: StrNumber, // format identifier
+
+    // 79
+    helpline:[&'static str;6],
+    help_ptr: u8, // 0..6
+    use_err_help: bool,
+
+    // 96
+    interrupt: i32,
+    OK_to_interrupt: bool,
+
+    // 104
+    arith_error: bool, // has arithmetic overflow occurred recently?
+    remainder: Scaled, // amount subtracted to get an exact division
+
+    // 73
+    interaction: Interaction,
+
+    // 116
+    mem: Array<MemoryWord>, // the big dynamic storage area
+    lo_mem_max: HalfWord, // the largest location of variable-size memory in use
+    hi_mem_min: HalfWord, // the smallest location of one-word memory in use
+
+    // 117
+    var_used: i32,
+    dyn_used: i32, // how much memory is in use
+
+    // 118
+    avail: Pointer, // head of the list of available one-word nodes
+    mem_end: Pointer, // the last one-word node used in mem
+
+    // 115
+    temp_ptr: Pointer, // a pointer variable for occasional emergency use
+
+    // 124
+    rover: Pointer, // points to some node in the list of empties
+
+    // 173
+    font_in_short_display: i32, // an internal font number
+
+    // 181
+    depth_threshold: i32, // maximum nesting depth in box displays
+    breadth_max: i32, // maximum number of items shown at the same list level
+
+    // 165
+    // debug
+    _free: [bool;MemMax],
+    was_mem_end: HalfWord,
+    was_lo_max: HalfWord,
+    was_hi_min: HalfWord,
+    panicking: bool,
+
+    // 213
+    nest: [ListStateRecord; NestSize],
+    nest_ptr: usize, // 0..nestsize
+    max_nest_stack: usize, // 0..nestsize
+    curlist:ListStateRecord,
+    shown_mode: i32, // -203..203
+
+    // 253
+    eqtb: Array<MemoryWord>,
+    xeq_level: Array<QuarterWord>,
+
+    // 256
+    hash: Array<TwoHalves>, // the hash table <hash_base, undefined_control_sequence - 1>
+    hash_used: Pointer, // allocation pointer for hash
+    no_new_control_sequence: bool, // are new identifiers legal?
+    cs_count: i32, // total number of known identifiers
+
+    // 271
+    save_stack: Array<MemoryWord>, // Array<memory_word, 0, save_size>
+    save_ptr: i32, // first unused entry on save_stack
+    max_save_stack: i32, // maximum usage of save stack
+    cur_level: QuarterWord, // current nesting level for groups
+    cur_group: GroupCode, // current group type
+    cur_boundary: i32, // where the current level begins
+
+    // 286
+    mag_set: i32, // if nonzero, this magnification should be used henceforth
+
+    // 297
+    cur_cmd: EightBits, // current command set by get_next
+    cur_chr: HalfWord, // operand of current command
+    cur_cs: Pointer, // control sequence found here, zero if none found
+    cur_tok: HalfWord, // packed representative of cur_cmd and cur_chr
+
+    // 301
+    input_stack: Array<InStateRecord>,
+    input_ptr: usize, // first unused location of input_stack
+    max_in_stack: usize, // largest value of input_ptr when pushing
+    cur_input: InStateRecord, // the ``top'' input state
+
+    // 304
+    in_open: i32, // the number of lines in the buffer, less one
+    open_parens: i32, // the number of open text files
+    line: i32, // current line number in the current source file
+    line_stack: Array<i32>,
+    input_file: Vec<Option<AlphaFile>>,
+
+    // 305
+    scanner_status: i32, // can a subfile end now?
+    warning_index: Pointer, // identifier relevant to non-outer control sequence
+    def_ref: Pointer, // reference count of token list being defined
+
+    // 308
+    param_stack: Array<Pointer>, // token list pointers for parameters
+    param_ptr: usize, // first unused entry in param_stack
+    max_param_stack: usize, // largest value of param_ptr
+
+    // 309
+    align_state: i32, // group level with respect to current alignment
+
+    // 310
+    base_ptr: usize, // shallowest level shown by show_context
+
+    // 333
+    par_loc: Pointer, // location of `\par' in eqtb
+    par_token: HalfWord, // token representing `\par'
+
+    // 361
+    force_eof: bool, // should the next \input be aborted early?
+
+    // 382
+    cur_mark: Array<Pointer>, // token list for marks <top_mark_code, split_bot_mark_code>
+    mark_classes: HashMap<HalfWord, [Pointer; 5]>, // the marks of every nonzero \marks class
+
+    // 387
+    long_state: QuarterWord, // governs the acceptance of \par
+
+    // 388
+    pstack: [Pointer; 9], // arguments supplied to a macro
+
+    // 410
+    cur_val: i32, // value returned by numeric scanners
+    cur_val_level: i32, // int_val..tok_val, the ``level'' of this value
+
+    // 438
+    radix: SmallNumber, // scan_int sets this to 8, 10, 16, or zero
+
+    // 447
+    cur_order: GlueOrd, // order of infinity found by scan_dimen
+
+    // 480
+    read_file: Vec<Option<AlphaFile>>, // used for \read
+    read_open: Array<i32>, // normal..closed, state of read_file[n]
+
+    // 489
+    cond_ptr: Pointer, // top of the condition stack
+    if_limit: i32, // normal..or_code, upper bound on fi_or_else codes
+    cur_if: SmallNumber, // type of conditional being worked on
+    if_line: i32, // line where that conditional began
+
+    // 493
+    skip_line: i32, // skipping began here
+
+    // 520
+    TEX_format_default: Array<char>,
+
+    // 549
+    font_info: Array<MemoryWord>, // the big collection of font data
+    fmem_ptr: FontIndex, // first unused word of font_info
+    font_ptr: InternalFontNumber, // largest internal font number in use
+    font_check: Array<FourQuarters>, // check sum
+    font_size: Array<Scaled>, // ``at'' size
+    font_dsize: Array<Scaled>, // ``design'' size
+    font_params: Array<FontIndex>, // how many font parameters are present
+    font_name: Array<StrNumber>, // name of the font
+    font_area: Array<StrNumber>, // area of the font
+    font_bc: Array<u8>, // beginning (smallest) character code
+    font_ec: Array<u8>, // ending (largest) character code
+    font_glue: Array<Pointer>, // glue specification for interword space, null if not allocated
+    font_used: Array<bool>, // has a character from this font actually appeared in the output?
+    font_vf: Array<VfStatus>, // the character packets of virtual fonts, read when first shipped out
+    font_math: Array<MathStatus>, // the MATH tables of OpenType fonts, read when first used in a formula
+    hyphen_char: Array<i32>, // current \hyphenchar values
+    skew_char: Array<i32>, // current \skewchar values
+    bchar_label: Array<i32>, // start of lig_kern program for left boundary character, non_address if there is none
+    font_bchar: Array<HalfWord>, // right boundary character, non_char if there is none
+    font_false_bchar: Array<HalfWord>, // font_bchar if it doesn't exist in the font, otherwise non_char
+
+    // 550
+    char_base: Array<i32>, // base addresses for char_info
+    width_base: Array<i32>, // base addresses for widths
+    height_base: Array<i32>, // base addresses for heights
+    depth_base: Array<i32>, // base addresses for depths
+    italic_base: Array<i32>, // base addresses for italic corrections
+    lig_kern_base: Array<i32>, // base addresses for ligature/kerning programs
+    kern_base: Array<i32>, // base addresses for kerns
+    exten_base: Array<i32>, // base addresses for extensible recipes
+    param_base: Array<i32>, // base addresses for font parameters
+
+    // 555
+    null_character: FourQuarters, // nonexistent character information
+
+    // 592
+    total_pages: i32, // the number of pages that have been shipped out
+    max_v: Scaled, // maximum height-plus-depth of pages shipped so far
+    max_h: Scaled, // maximum width of pages shipped so far
+    max_push: i32, // deepest nesting of push commands encountered so far
+    last_bop: i32, // location of previous bop in the DVI output
+    dead_cycles: i32, // the number of times output has been called since the last shipout
+    doing_leaders: bool, // are we inside a leader box?
+
+    // 595
+    dvi_buf: Array<u8>, // buffer for DVI output
+    half_buf: usize, // half of dvi_buf_size
+    dvi_limit: usize, // end of the current half buffer
+    dvi_ptr: usize, // the next available buffer address
+    dvi_offset: i32, // dvi_buf_size times the number of times the output buffer has been fully emptied
+    dvi_gone: i32, // the number of bytes already output to dvi_file
+
+    // 605
+    down_ptr: Pointer, // heads of the down stack of movements
+    right_ptr: Pointer, // heads of the right stack of movements
+
+    // 616
+    dvi_h: Scaled, // a DVI reader program thinks we are here
+    dvi_v: Scaled,
+    cur_h: Scaled, // TeX thinks we are here
+    cur_v: Scaled,
+    dvi_f: InternalFontNumber, // the current font
+    cur_s: i32, // current depth of output box nesting, initially -1
+    page_marks: Option<Vec<PageItem>>, // what hlist_out and vlist_out make, when it doesn't go to the DVI file
+
+    // 814
+    just_box: Pointer, // the hlist_node for the last line of the new paragraph
+
+    // 821
+    passive: Pointer, // most recent node on passive list
+    printed_node: Pointer, // most recent node that has been printed
+    pass_number: i32, // the number of passive nodes allocated on this pass
+
+    // 823
+    cur_active_width: [Scaled; 7], // distance from current active node
+    background: [Scaled; 7], // length of an ``empty'' line
+    break_width: [Scaled; 7], // length being computed after current break
+
+    // 825
+    no_shrink_error_yet: bool, // have we complained about infinite shrinkage?
+
+    // 828
+    cur_p: Pointer, // the current breakpoint under consideration
+    second_pass: bool, // is this our second attempt to break this paragraph?
+    final_pass: bool, // is this our final attempt to break this paragraph?
+    threshold: i32, // maximum badness on feasible lines
+
+    // 833
+    minimal_demerits: [i32; 4], // best total demerits known for current line class and position, given the fitness
+    minimum_demerits: i32, // best total demerits known for current line class and position
+    best_place: [Pointer; 4], // how to achieve minimal_demerits
+    best_pl_line: [i32; 4], // corresponding line number
+
+    // 839
+    disc_width: Scaled, // the length of discretionary material preceding a break
+
+    // 847
+    easy_line: i32, // line numbers > easy_line are equivalent in break nodes
+    last_special_line: i32, // line numbers > last_special_line all have the same width
+    first_width: Scaled, // the width of all lines <= last_special_line, if no \parshape has been specified
+    second_width: Scaled, // the width of all lines > last_special_line
+    first_indent: Scaled, // left margin to go with first_width
+    second_indent: Scaled, // left margin to go with second_width
+
+    // 872
+    best_bet: Pointer, // use this passive node and its predecessors
+    fewest_demerits: i32, // the demerits associated with best_bet
+    best_line: i32, // line number following the last line of the new paragraph
+    actual_looseness: i32, // the difference between line_number(best_bet) and the optimum best_line
+
+    // 892
+    hc: [HalfWord; 66], // word to be hyphenated
+    hn: usize, // the number of positions occupied in hc
+    ha: Pointer, // nodes ha..hb should be replaced by the hyphenated result
+    hb: Pointer,
+    hf: InternalFontNumber, // font number of the letters in hc
+    hu: [HalfWord; 64], // like hc, before conversion to lowercase
+    hyf_char: i32, // hyphen character of the relevant font
+    cur_lang: ASCIICode, // current hyphenation table of interest
+    init_cur_lang: ASCIICode,
+    l_hyf: i32, // limits on fragment sizes
+    r_hyf: i32,
+    init_l_hyf: i32,
+    init_r_hyf: i32,
+    hyf_bchar: HalfWord, // boundary character after c_n
+
+    // 900
+    hyf: [u8; 65], // odd values indicate discretionary hyphens
+    init_list: Pointer, // list of punctuation characters preceding the word
+    init_lig: bool, // does init_list represent a ligature?
+    init_lft: bool, // if so, did the ligature involve a left boundary?
+
+    // 905
+    hyphen_passed: usize, // first hyphen in a ligature, if any
+
+    // 907
+    cur_l: HalfWord, // characters before and after the cursor
+    cur_r: HalfWord,
+    cur_q: Pointer, // where a ligature should be detached
+    lig_stack: Pointer, // unfinished business to the right of the cursor
+    ligature_present: bool, // should the current character be a ligature?
+    lft_hit: bool, // did we hit a ligature with a boundary character?
+    rt_hit: bool,
+
+    // 921
+    trie: Array<TwoHalves>, // trie_link, trie_char, trie_op
+    hyf_distance: Array<SmallNumber>, // position k-j of n_j
+    hyf_num: Array<SmallNumber>, // value of n_j
+    hyf_next: Array<QuarterWord>, // continuation code
+    op_start: [i32; 256], // offset for current language
+
+    // 926
+    hyph_word: Array<StrNumber>, // exception words
+    hyph_list: Array<Pointer>, // lists of hyphen positions
+    hyph_count: i32, // the number of words in the exception dictionary
+
+    // 943
+    trie_op_hash: Array<i32>, // trie op codes for quadruples, offset by trie_op_size
+    trie_used: [QuarterWord; 256], // largest opcode used so far for this language
+    trie_op_lang: Array<ASCIICode>, // language part of a hashed quadruple
+    trie_op_val: Array<QuarterWord>, // opcode corresponding to a hashed quadruple
+    trie_op_ptr: i32, // number of stored ops so far
+
+    // 947
+    trie_c: Array<PackedASCIICode>, // characters to match
+    trie_o: Array<QuarterWord>, // operations to perform
+    trie_l: Array<TriePointer>, // left subtrie links
+    trie_r: Array<TriePointer>, // right subtrie links
+    trie_ptr: TriePointer, // the number of nodes in the trie
+    trie_hash: Array<TriePointer>, // used to identify equivalent subtries
+
+    // 950
+    trie_taken: Array<bool>, // does a family start here?
+    trie_min: [TriePointer; 256], // the first possible slot for each character
+    trie_max: TriePointer, // largest location used in trie
+    trie_not_ready: bool, // is the trie still in linked form?
+
+    // 970
+    active_width: [Scaled; 7], // distance from first active node to cur_p
+    best_height_plus_depth: Scaled, // height of the best box, without stretching or shrinking
+
+    // 980
+    page_tail: HalfWord,
+    page_contents: u8, // 0..2
+    page_max_depth: Scaled,
+    best_page_break: HalfWord,
+    least_page_cost: i32,
+    best_size: Scaled,
+
+    // 982
+    page_so_far: Array<Scaled>, // 0..7
+    last_glue: HalfWord,
+    last_penalty: i32,
+    last_kern: Scaled,
+    insert_penalties: i32,
+
+    // 646
+    total_stretch: [Scaled; 4], // glue totals for each order of infinity in the box being packed
+    total_shrink: [Scaled; 4],
+    last_badness: i32, // badness of the most recently packaged box
+
+    // 647
+    adjust_tail: Pointer, // tail of adjustment list
+
+    // 661
+    pack_begin_line: i32, // source file line where the current paragraph or alignment began; negative for alignments
+
+    // 684
+    empty_field: TwoHalves, // an empty noad field
+    null_delimiter: FourQuarters, // a delimiter with no characters
+
+    // 719
+    cur_mlist: Pointer, // beginning of mlist to be translated
+    cur_style: SmallNumber, // style code at current place in the list
+    cur_size: SmallNumber, // size code corresponding to cur_style
+    cur_mu: Scaled, // the math unit width corresponding to cur_size
+    mlist_penalties: bool, // should mlist_to_hlist insert penalties?
+
+    // 724
+    cur_f: InternalFontNumber, // the font field of a math_char
+    cur_c: QuarterWord, // the character field of a math_char
+    cur_i: FourQuarters, // the char_info of a math_char, or a lig/kern instruction
+
+    // 770
+    align_ptr: Pointer, // most recently pushed-down alignment stack node
+    cur_align: Pointer, // current position in preamble list
+    cur_span: Pointer, // start of currently spanned columns in preamble list
+    cur_loop: Pointer, // place to copy when extending a periodic preamble
+    cur_head: Pointer, // adjustment list head
+    cur_tail: Pointer, // adjustment list tail
+
+    // 989
+    output_active: bool, // are we in the midst of an output routine?
+
+    // 1032
+    cancel_boundary: bool, // should the left boundary be ignored?
+    ins_disc: bool, // should we insert a discretionary node?
+
+    // 1074
+    cur_box: Pointer, // box to be placed into its context
+
+    // 1266
+    after_token: HalfWord, // zero, or a saved token
+
+    // 1281
+    long_help_seen: bool, // has the long \errmessage help been used?
+
+    // 1342
+    write_file: Vec<Option<Box<dyn Write>>>, // the \write files
+    write_open: [bool; 18], // are the \write files open?
+    write_loc: Pointer, // eqtb address of \write
+}
+
+impl TexState
+{
+    const format_default_length: i32 = 9;
+
+    
+    pub fn new() -> TexState
+    {
+        TexState {
+            ready_already: 0,
+            // 20
+            xord: Array::new(0, 255),
+            xchr: Array::new(0, 255),
+
+            // 76
+            deletions_allowed: false,
+            set_box_allowed: false,
+            history: History::FatalErrorStop,
+            error_count: 0,
+
+            // 30
+            buffer: Array::new(0, BufSize as usize),
+            first: 0,
+            last: 0,
+            max_buf_stack: 0,
+
+            // 32
+            term_in: Box::new(std::io::empty()),
+            term_out: Box::new(std::io::sink()),
+
+            // 37
+            first_line: None,
+            interaction_option: None,
+
+            // 241
+            source_date_epoch: None,
+
+            // 39
+            str_pool: Array::new(0, PoolSize as usize),
+            str_start: Array::new(0, MaxStrings as usize),
+            pool_ptr: 0,
+            str_ptr: 0,
+            init_pool_ptr: 0,
+            init_str_ptr: 0,
+
+            // 54
+            log_file: None,
+            selector: TermOnly,
+            dig: [0; 23],
+            tally: 0,
+            term_offset: 0,
+            file_offset: 0,
+            trick_buf: [0; ErrorLine as usize + 1],
+            trick_count: 0,
+            first_count: 0,
+            old_setting: 0,
+
+            // 26
+            name_of_file: String::new(),
+            resolver: Box::new(PathResolver::new()),
+
+            // 512
+            cur_name: 0,
+            cur_area: 0,
+            cur_ext: 0,
+
+            // 513
+            area_delimiter: 0,
+            ext_delimiter: 0,
+            quoted_filename: false,
+
+            // 527
+            name_in_progress: false,
+            job_name: 0,
+
+            // 528
+            log_opened: false,
+            log_name: 0,
+            dvi_file: None,
+            output_file_name: 0,
+            output_format: OutputFormat::Dvi,
+            pdf: PdfDocument::default(),
+            svg: SvgOutput::default(),
+
+            // 1299
+            format_ident: 0,
+
+            // 79
+            helpline:[""; 6],
+            help_ptr: 0,
+            use_err_help: false,
+
+            // 96
+            interrupt: 0,
+            OK_to_interrupt: false,
+
+            // 104
+            arith_error: false,
+            remainder: 0,
+
+            // 116
+            mem: Array::new(MemMin, MemMax), // the big dynamic storage area
+            lo_mem_max: 0, // the largest location of variable-size memory in use
+            hi_mem_min: 0, // the smallest location of one-word memory in use
+
+            // 117
+            var_used: 0,
+            dyn_used: 0, // how much memory is in use
+
+            // 118
+            avail: 0,
+            mem_end: 0,
+            
+            // 73
+            interaction: Interaction::BatchMode,
+
+            // 115
+            temp_ptr: Null,
+
+            // 124
+            rover: Null,
+
+            // 173
+            font_in_short_display: 0,
+
+            // 181
+            depth_threshold: 0,
+            breadth_max: 0,
+
+            // 165
+            // debug
+            _free: [false;MemMax],
+            was_mem_end: 0,
+            was_lo_max: 0,
+            was_hi_min: 0,
+            panicking: false,
+
+            // 213
+            nest: [ListStateRecord::new(); NestSize],
+            nest_ptr: 0,
+            max_nest_stack: 0,
+            curlist: ListStateRecord::new(),
+            shown_mode: 0,
+
+            // 253
+            eqtb: Array::new(ActiveBase as usize, EqtbSize as usize),
+            xeq_level: Array::new(IntBase as usize, EqtbSize as usize),
+
+            // 256
+            hash: Array::new(HashBase as usize, UndefinedControlSequence as usize - 1),
+            hash_used: 0,
+            no_new_control_sequence: false,
+            cs_count: 0,
+
+            // 271
+            save_stack: Array::new(0, SaveSize as usize),
+            save_ptr: 0,
+            max_save_stack: 0,
+            cur_level: 0,
+            cur_group: GroupCode::AlignGroup,
+            cur_boundary: 0,
+
+            
+            // 286
+            mag_set: 0, // if nonzero, this magnification should be used henceforth
+
+            // 297
+            cur_cmd: 0,
+            cur_chr: 0,
+            cur_cs: Null,
+            cur_tok: 0,
+
+            // 301
+            input_stack: Array::new(0, StackSize as usize),
+            input_ptr: 0,
+            max_in_stack: 0,
+            cur_input: InStateRecord::default(),
+
+            // 304
+            in_open: 0,
+            open_parens: 0,
+            line: 0,
+            line_stack: Array::new(0, MaxInOpen as usize + 1),
+            input_file: (0..=MaxInOpen).map(|_| None).collect(),
+
+            // 305
+            scanner_status: Normal,
+            warning_index: Null,
+            def_ref: Null,
+
+            // 308
+            param_stack: Array::new(0, ParamSize as usize),
+            param_ptr: 0,
+            max_param_stack: 0,
+
+            // 309
+            align_state: 1000000,
+
+            // 310
+            base_ptr: 0,
+
+            // 333
+            par_loc: Null,
+            par_token: 0,
+
+            // 361
+            force_eof: false,
+
+            // 382
+            cur_mark: Array::new(top_mark_code, split_bot_mark_code),
+            mark_classes: HashMap::new(),
+
+            // 387
+            long_state: 0,
+
+            // 388
+            pstack: [Null; 9],
+
+            // 410
+            cur_val: 0,
+            cur_val_level: 0,
+
+            // 438
+            radix: 0,
+
+            // 447
+            cur_order: 0,
+
+            // 480
+            read_file: (0..=16).map(|_| None).collect(), // used for \read
+            read_open: Array::new(0, 16), // normal..closed, state of read_file[n]
+            
+            // 489
+            cond_ptr: Null,
+            if_limit: 0,
+            cur_if: 0,
+            if_line: 0,
+
+            // 493
+            skip_line: 0,
+
+            // 520
+            TEX_format_default:  Array::new(1, format_default_length+1),
+
+            // 549
+            font_info: Array::new(0, FontMemSize as usize),
+            fmem_ptr: 0,
+            font_ptr: 0,
+            font_check: Array::new(FontBase as usize, FontMax as usize),
+            font_size: Array::new(FontBase as usize, FontMax as usize),
+            font_dsize: Array::new(FontBase as usize, FontMax as usize),
+            font_params: Array::new(FontBase as usize, FontMax as usize),
+            font_name: Array::new(FontBase as usize, FontMax as usize),
+            font_area: Array::new(FontBase as usize, FontMax as usize),
+            font_bc: Array::new(FontBase as usize, FontMax as usize),
+            font_ec: Array::new(FontBase as usize, FontMax as usize),
+            font_glue: Array::new(FontBase as usize, FontMax as usize),
+            font_used: Array::new(FontBase as usize, FontMax as usize),
+            font_vf: Array::new(FontBase as usize, FontMax as usize),
+            font_math: Array::new(FontBase as usize, FontMax as usize),
+            hyphen_char: Array::new(FontBase as usize, FontMax as usize),
+            skew_char: Array::new(FontBase as usize, FontMax as usize),
+            bchar_label: Array::new(FontBase as usize, FontMax as usize),
+            font_bchar: Array::new(FontBase as usize, FontMax as usize),
+            font_false_bchar: Array::new(FontBase as usize, FontMax as usize),
+
+            // 550
+            char_base: Array::new(FontBase as usize, FontMax as usize),
+            width_base: Array::new(FontBase as usize, FontMax as usize),
+            height_base: Array::new(FontBase as usize, FontMax as usize),
+            depth_base: Array::new(FontBase as usize, FontMax as usize),
+            italic_base: Array::new(FontBase as usize, FontMax as usize),
+            lig_kern_base: Array::new(FontBase as usize, FontMax as usize),
+            kern_base: Array::new(FontBase as usize, FontMax as usize),
+            exten_base: Array::new(FontBase as usize, FontMax as usize),
+            param_base: Array::new(FontBase as usize, FontMax as usize),
+
+            // 555
+            null_character: FourQuarters::default(),
+
+            // 592
+            total_pages: 0,
+            max_v: 0,
+            max_h: 0,
+            max_push: 0,
+            last_bop: -1,
+            dead_cycles: 0,
+            doing_leaders: false,
+
+            // 595
+            dvi_buf: Array::new(0, DviBufSize as usize),
+            half_buf: 0,
+            dvi_limit: 0,
+            dvi_ptr: 0,
+            dvi_offset: 0,
+            dvi_gone: 0,
+
+            // 605
+            down_ptr: Null,
+            right_ptr: Null,
+
+            // 616
+            dvi_h: 0,
+            dvi_v: 0,
+            cur_h: 0,
+            cur_v: 0,
+            dvi_f: NullFont,
+            cur_s: -1,
+            page_marks: None,
+
+            // 814
+            just_box: Null,
+
+            // 821
+            passive: Null,
+            printed_node: Null,
+            pass_number: 0,
+
+            // 823
+            cur_active_width: [0; 7],
+            background: [0; 7],
+            break_width: [0; 7],
+
+            // 825
+            no_shrink_error_yet: true,
+
+            // 828
+            cur_p: Null,
+            second_pass: false,
+            final_pass: false,
+            threshold: 0,
+
+            // 833
+            minimal_demerits: [0; 4],
+            minimum_demerits: 0,
+            best_place: [Null; 4],
+            best_pl_line: [0; 4],
+
+            // 839
+            disc_width: 0,
+
+            // 847
+            easy_line: 0,
+            last_special_line: 0,
+            first_width: 0,
+            second_width: 0,
+            first_indent: 0,
+            second_indent: 0,
+
+            // 872
+            best_bet: Null,
+            fewest_demerits: 0,
+            best_line: 0,
+            actual_looseness: 0,
+
+            // 892
+            hc: [0; 66],
+            hn: 0,
+            ha: Null,
+            hb: Null,
+            hf: NullFont,
+            hu: [0; 64],
+            hyf_char: 0,
+            cur_lang: 0,
+            init_cur_lang: 0,
+            l_hyf: 0,
+            r_hyf: 0,
+            init_l_hyf: 0,
+            init_r_hyf: 0,
+            hyf_bchar: NonChar,
+
+            // 900
+            hyf: [0; 65],
+            init_list: Null,
+            init_lig: false,
+            init_lft: false,
+
+            // 905
+            hyphen_passed: 0,
+
+            // 907
+            cur_l: 0,
+            cur_r: 0,
+            cur_q: Null,
+            lig_stack: Null,
+            ligature_present: false,
+            lft_hit: false,
+            rt_hit: false,
+
+            // 921
+            trie: Array::new(0, TrieSize as usize),
+            hyf_distance: Array::new(1, TrieOpSize as usize),
+            hyf_num: Array::new(1, TrieOpSize as usize),
+            hyf_next: Array::new(1, TrieOpSize as usize),
+            op_start: [0; 256],
+
+            // 926
+            hyph_word: Array::new(0, HyphSize as usize),
+            hyph_list: Array::new(0, HyphSize as usize),
+            hyph_count: 0,
+
+            // 943
+            trie_op_hash: Array::new(0, 2 * TrieOpSize as usize),
+            trie_used: [MinQuarterWord; 256],
+            trie_op_lang: Array::new(1, TrieOpSize as usize),
+            trie_op_val: Array::new(1, TrieOpSize as usize),
+            trie_op_ptr: 0,
+
+            // 947
+            trie_c: Array::new(0, TrieSize as usize),
+            trie_o: Array::new(0, TrieSize as usize),
+            trie_l: Array::new(0, TrieSize as usize),
+            trie_r: Array::new(0, TrieSize as usize),
+            trie_ptr: 0,
+            trie_hash: Array::new(0, TrieSize as usize),
+
+            // 950
+            trie_taken: Array::new(1, TrieSize as usize),
+            trie_min: [0; 256],
+            trie_max: 0,
+            trie_not_ready: true,
+
+            // 970
+            active_width: [0; 7],
+            best_height_plus_depth: 0,
+
+            // 980
+            page_tail: 0,
+            page_contents: 0, // 0..2
+            page_max_depth: 0,
+            best_page_break: 0,
+            least_page_cost: 0,
+            best_size: 0,
+
+            // 982
+            page_so_far: Array::new(0, 7), // 0..7
+            last_glue: 0,
+            last_penalty: 0,
+            last_kern: 0,
+            insert_penalties: 0,
+
+            // 646
+            total_stretch: [0; 4],
+            total_shrink: [0; 4],
+            last_badness: 0,
+            adjust_tail: Null,
+            pack_begin_line: 0,
+
+            // 684
+            empty_field: TwoHalves::new(),
+            null_delimiter: FourQuarters::default(),
+
+            // 719
+            cur_mlist: Null,
+            cur_style: 0,
+            cur_size: 0,
+            cur_mu: 0,
+            mlist_penalties: false,
+
+            // 724
+            cur_f: NullFont,
+            cur_c: 0,
+            cur_i: FourQuarters::default(),
+
+            // 770
+            align_ptr: Null,
+            cur_align: Null,
+            cur_span: Null,
+            cur_loop: Null,
+            cur_head: Null,
+            cur_tail: Null,
+
+            // 989
+            output_active: false,
+
+            // 1032
+            cancel_boundary: false,
+            ins_disc: false,
+
+            // 1074
+            cur_box: Null,
+
+            // 1266
+            after_token: 0,
+
+            // 1281
+            long_help_seen: false,
+
+            // 1342
+            write_file: (0..16).map(|_| None).collect(),
+            write_open: [false; 18],
+            write_loc: Null,
+        }
+    }
+}
+
+/*
+@d wlog(#)==write(log_file,#)
+@d wlog_ln(#)==write_ln(log_file,#)
+@d wlog_cr==write_ln(log_file)
+*/
+
+
+
+fn initialize(state: &mut TexState) {
+    
+    // 21
+    state.xchr[32] = ' ' as u8;
+    state.xchr[33] = '!' as u8;   state.xchr[34] = '"' as u8;   state.xchr[35] = '#' as u8;   state.xchr[36] = '$' as u8;  state.xchr[37] = '%' as u8;
+    state.xchr[38] = '&' as u8;   state.xchr[39] = '\'' as u8;  state.xchr[40] = '(' as u8;   state.xchr[41] = ')' as u8;  state.xchr[42] = '*' as u8;
+    state.xchr[43] = '+' as u8;   state.xchr[44] = ',' as u8;   state.xchr[45] = '-' as u8;   state.xchr[46] = '.' as u8;  state.xchr[47] = '/' as u8;
+    state.xchr[48] = '0' as u8;   state.xchr[49] = '1' as u8;   state.xchr[50] = '2' as u8;   state.xchr[51] = '3' as u8;  state.xchr[52] = '4' as u8;
+    state.xchr[53] = '5' as u8;   state.xchr[54] = '6' as u8;   state.xchr[55] = '7' as u8;   state.xchr[56] = '8' as u8;  state.xchr[57] = '9' as u8;
+    state.xchr[58] = ':' as u8;   state.xchr[59] = ';' as u8;   state.xchr[60] = '<' as u8;   state.xchr[61] = '=' as u8;  state.xchr[62] = '>' as u8;
+    state.xchr[63] = '?' as u8;   state.xchr[64] = '@' as u8;   state.xchr[65] = 'A' as u8;   state.xchr[66] = 'B' as u8;  state.xchr[67] = 'C' as u8;
+    state.xchr[68] = 'D' as u8;   state.xchr[69] = 'E' as u8;   state.xchr[70] = 'F' as u8;   state.xchr[71] = 'G' as u8;  state.xchr[72] = 'H' as u8;
+    state.xchr[73] = 'I' as u8;   state.xchr[74] = 'J' as u8;   state.xchr[75] = 'K' as u8;   state.xchr[76] = 'L' as u8;  state.xchr[77] = 'M' as u8;
+    state.xchr[78] = 'N' as u8;   state.xchr[79] = 'O' as u8;   state.xchr[80] = 'P' as u8;   state.xchr[81] = 'Q' as u8;  state.xchr[82] = 'R' as u8;
+    state.xchr[83] = 'S' as u8;   state.xchr[84] = 'T' as u8;   state.xchr[85] = 'U' as u8;   state.xchr[86] = 'V' as u8;  state.xchr[87] = 'W' as u8;
+    state.xchr[88] = 'X' as u8;   state.xchr[89] = 'Y' as u8;   state.xchr[90] = 'Z' as u8;   state.xchr[91] = '[' as u8;  state.xchr[92] = '\\' as u8;
+    state.xchr[93] = ']' as u8;   state.xchr[94] = '^' as u8;   state.xchr[95] = '_' as u8;   state.xchr[96] = '`' as u8;  state.xchr[97] = 'a' as u8;
+    state.xchr[98] = 'b' as u8;   state.xchr[99] = 'c' as u8;   state.xchr[100] = 'd' as u8;  state.xchr[101] = 'e' as u8;
+    state.xchr[102] = 'f' as u8;  state.xchr[103] = 'g' as u8;  state.xchr[104] = 'h' as u8;  state.xchr[105] = 'i' as u8;
+    state.xchr[106] = 'j' as u8;  state.xchr[107] = 'k' as u8;  state.xchr[108] = 'l' as u8;  state.xchr[109] = 'm' as u8;
+    state.xchr[110] = 'n' as u8;  state.xchr[111] = 'o' as u8;  state.xchr[112] = 'p' as u8;  state.xchr[113] = 'q' as u8;
+    state.xchr[114] = 'r' as u8;  state.xchr[115] = 's' as u8;  state.xchr[116] = 't' as u8;  state.xchr[117] = 'u' as u8;
+    state.xchr[118] = 'v' as u8;  state.xchr[119] = 'w' as u8;  state.xchr[120] = 'x' as u8;  state.xchr[121] = 'y' as u8;
+    state.xchr[122] = 'z' as u8;  state.xchr[123] = '{' as u8;  state.xchr[124] = '|' as u8;  state.xchr[125] = '}' as u8;
+    state.xchr[126] = '~' as u8;
+    // 23
+    for i in 0..=31 { state.xchr[i] = ' ' as u8; }
+    for i in 127..=255 { state.xchr[i] = ' ' as u8; }
+
+    // 24
+    for i in 0..=255 { state.xord[i] = 127; }
+    for i in 128..=255 { state.xord[state.xchr[i] as usize] = i as u8; }
+    for i in 0..=126 { state.xord[state.xchr[i] as usize] = i as u8; }
+    // 74
+    state.interaction = Interaction::ErrorStopMode;
+    // 77
+    state.deletions_allowed = true;
+    state.set_box_allowed = true;
+    state.error_count = 0;
+    // 80
+	state.help_ptr = 0;
+	state.use_err_help = false;
+
+	// 97
+	state.interrupt = 0;
+	state.OK_to_interrupt = true;
+
+    // 166
+    state.was_mem_end = MemMin as HalfWord;
+    state.was_lo_max = MemMin as HalfWord;
+    state.was_hi_min = MemMax as HalfWord;
+    state.panicking = false;
+
+    // 215
+    state.nest_ptr = 0;
+    state.max_nest_stack = 0;
+    state.curlist.mode_field = 1;
+    state.curlist.head_field = 29999;
+    state.curlist.tail_field = 29999;
+    state.curlist.aux_field = MemoryWord::new_i32(-65536000);
+    state.curlist.ml_field = 0;
+    state.curlist.pg_field = 0;
+    state.shown_mode = 0;
+
+    // 991
+    state.page_contents = Empty as u8;
+    state.page_tail = PageHead as HalfWord;
+    state.set_link(PageHead, Null);
+    
+    state.last_glue = MaxHalfWord;
+    state.last_penalty = 0;
+    state.last_kern = 0;
+    state.page_so_far[7] = 0;
+    state.page_max_depth = 0;
+
+    // 254
+    for k in IntBase..=EqtbSize {
+        state.xeq_level[k as usize] = 1;
+    }
+
+    
+	// 257
+    state.no_new_control_sequence = true;
+    state.hash[HashBase as usize].set_next(0);
+    state.hash[HashBase as usize].set_next(0);
+    state.hash[HashBase as usize].set_text(0);
+    for k in HashBase + 1 ..= UndefinedControlSequence - 1 {
+        state.hash[k as usize] = state.hash[HashBase as usize];
+    }	
+
+	// 272
+    state.save_ptr = 0;
+    state.cur_level = LevelOne as QuarterWord;
+    state.cur_group = GroupCode::from(BottomLevel);
+    state.cur_boundary = 0;
+	state.max_save_stack = 0;
+
+	// 287
+	state.mag_set = 0;
+
+
+	// 383
+    top_mark!(state) = Null;
+    first_mark!(state) = Null;
+    bot_mark!(state) = Null;
+    split_first_mark!(state) = Null;
+	split_bot_mark!(state) = Null;
+
+	// 439
+    state.cur_val = 0;
+    state.cur_val_level = IntVal;
+    state.radix = 0;
+    state.cur_order = Normal;
+
+    // 481
+    for k in 0..=16 {
+        state.read_open[k] = closed;
+    }	
+
+	// 490
+	state.cond_ptr = Null;
+	state.if_limit = Normal; state.cur_if = 0; state.if_line = 0;
+
+    // 521
+    for (i, c) in "plain.fmt".chars().enumerate() {
+        state.TEX_format_default[i + 1] = c;
+    }
+
+	// 551
+	for k in FontBase..=FontMax {
+        state.font_used[k as usize] = false;
+    }
+
+	// 556
+	state.null_character.b0 = MinQuarterWord; state.null_character.b1 = MinQuarterWord;
+	state.null_character.b2 = MinQuarterWord; state.null_character.b3 = MinQuarterWord;
+
+
+	// 593
+	state.total_pages = 0; state.max_v = 0; state.max_h = 0; state.max_push = 0; state.last_bop = -1;
+	state.doing_leaders = false; state.dead_cycles = 0; state.cur_s = -1;
+
+	// 596
+	state.half_buf = DviBufSize as usize / 2; state.dvi_limit = DviBufSize as usize; state.dvi_ptr = 0;
+	state.dvi_offset = 0; state.dvi_gone = 0;
+
+	// 606
+	state.down_ptr = Null; state.right_ptr = Null;
+
+	// 648
+	state.adjust_tail = Null; state.last_badness = 0;
+
+	// 662
+	state.pack_begin_line = 0;
+
+	// 685
+	state.empty_field.set_rh(Empty as HalfWord); state.empty_field.set_lh(Null);
+	state.null_delimiter.b0 = 0; state.null_delimiter.b1 = MinQuarterWord;
+	state.null_delimiter.b2 = 0; state.null_delimiter.b3 = MinQuarterWord;
+
+	// 771
+	state.align_ptr = Null; state.cur_align = Null; state.cur_span = Null; state.cur_loop = Null;
+	state.cur_head = Null; state.cur_tail = Null;
+
+	// 928
+	for z in 0..=HyphSize as usize {
+		state.hyph_word[z] = 0; state.hyph_list[z] = Null;
+	}
+	state.hyph_count = 0;
+
+	// 946
+	for k in 0..256 {
+		state.trie_used[k] = MinQuarterWord;
+	}
+	state.trie_op_ptr = 0;
+
+	// 951
+	state.trie_not_ready = true; state.trie_l[0] = 0; state.trie_c[0] = 0; state.trie_ptr = 0;
+
+	// 990
+	state.output_active = false;
+	state.insert_penalties = 0;
+
+	// 1033
+	state.ligature_present = false;
+	state.cancel_boundary = false;
+	state.lft_hit = false;
+	state.rt_hit = false;
+	state.ins_disc = false;
+
+
+	// 1267
+	state.after_token = 0;
+
+	// 1282
+	state.long_help_seen = false;
+
+	// 1300
+	state.format_ident = 0;
+
+	// 1343
+	for k in 0..=17 {
+        state.write_open[k] = false;
+    }
+
+    // 164, 222, 552: initialize table entries (done by INITEX only)
+    state.init_mem();
+    state.init_eqtb();
+    state.init_null_font();
+}
+
+impl TexState {
+    // 1335
+    // reports unfinished groups and conditionals at the end of the job
+    fn final_cleanup(&mut self) {
+        let c = self.cur_chr; // 0 for \end, 1 for \dump
+        if self.job_name == 0 {
+            self.open_log_file();
+        }
+        while self.input_ptr > 0 {
+            if self.cur_input.state_field == TokenList {
+                self.end_token_list();
+            } else {
+                self.end_file_reading();
+            }
+        }
+        while self.open_parens > 0 {
+            self.print(" )");
+            self.open_parens -= 1;
+        }
+        if self.cur_level > LevelOne as QuarterWord {
+            self.print_nl("(");
+            self.print_esc("end occurred ");
+            self.print("inside a group at level ");
+            self.print_int(self.cur_level as i32 - LevelOne);
+            self.print_char(')' as u8);
+        }
+        while self.cond_ptr != Null {
+            self.print_nl("(");
+            self.print_esc("end occurred ");
+            self.print("when ");
+            self.print_cmd_chr(IfTest, self.cur_if as HalfWord);
+            if self.if_line != 0 {
+                self.print(" on line ");
+                self.print_int(self.if_line);
+            }
+            self.print(" was incomplete)");
+            self.if_line = self.if_line_field(self.cond_ptr as usize);
+            self.cur_if = self.subtype(self.cond_ptr as usize) as SmallNumber;
+            self.temp_ptr = self.cond_ptr;
+            self.cond_ptr = self.link(self.cond_ptr as usize);
+            self.free_node(self.temp_ptr, if_node_size as usize);
+        }
+        if self.history != History::Spotless
+            && (self.history == History::WarningIssued || self.interaction < Interaction::ErrorStopMode)
+            && self.selector == TermAndLog
+        {
+            self.selector = TermOnly;
+            self.print_nl("(see the transcript file for additional information)");
+            self.selector = TermAndLog;
+        }
+        if c == 1 {
+            self.destroy_marks();
+            if self.last_glue != MaxHalfWord {
+                self.delete_glue_ref(self.last_glue);
+            }
+            self.store_fmt_file();
+        }
+    }
+
+    // 1333
+    fn close_files_and_terminate(&mut self) {
+        // 1378: finish the extensions
+        for k in 0..16 {
+            if self.write_open[k] {
+                self.write_file[k] = None;
+            }
+        }
+        self.set_int_par(NewLineCharCode, -1);
+        self.update_terminal();
+        match self.output_format {
+            OutputFormat::Dvi => self.finish_dvi_file(),
+            OutputFormat::Pdf => self.finish_pdf_file(),
+            OutputFormat::Svg => self.finish_svg_files(),
+        }
+        if self.log_opened {
+            self.wlog_cr();
+            self.log_file = None;
+            self.selector -= 2;
+        }
+        self.print_ln();
+    }
+}
+
+// the payload of the unwinding that jump_out starts
+pub struct EndOfTex;
+
+impl TexState {
+    // 1332
+    // the INITEX part of TeX's main body: checks the constants and fills the
+    // tables; false means TeX can't go on
+    // the checks of 14 and 111 compare constants that may sit at the ends of their types
+    #[allow(clippy::absurd_extreme_comparisons)]
+    fn init_tex(&mut self) -> bool {
+        self.history = History::FatalErrorStop; // in case we quit during initialization
+        if self.ready_already == 314159 {
+            return true;
+        }
+        // 14: check the ``constant'' values for consistency
+        let mut bad = 0;
+        if HalfErrorLine < 30 || HalfErrorLine > ErrorLine - 15 { bad = 1; }
+        if MaxPrintLine < 60 { bad = 2; }
+        if DviBufSize % 8 != 0 { bad = 3; }
+        if MemBot + 1100 > MemTop { bad = 4; }
+        if HashPrime > HashSize { bad = 5; }
+        if MaxInOpen >= 128 { bad = 6; }
+        if MemTop < 256 + 11 { bad = 7; }
+        // 111
+        if MemMin > MemBot || MemMax < MemTop { bad = 10; }
+        if MinQuarterWord > 0 || MaxQuarterWord < 127 { bad = 11; }
+        if MinHalfWord > 0 || MaxHalfWord < 32767 { bad = 12; }
+        if (MinQuarterWord as HalfWord) < MinHalfWord
+            || MaxQuarterWord as HalfWord > MaxHalfWord { bad = 13; }
+        if MemMin < MinHalfWord as usize
+            || MemMax >= MaxHalfWord as usize
+            || MemBot - MemMin > MaxHalfWord as usize + 1
+        {
+            bad = 14;
+        }
+        if FontBase < MinQuarterWord as i32 || FontMax > MaxQuarterWord as i32 { bad = 15; }
+        if FontMax > FontBase + 256 { bad = 16; }
+        if SaveSize > MaxHalfWord as i32 { bad = 18; }
+        if MaxQuarterWord - MinQuarterWord < 255 { bad = 19; }
+        // 290
+        if cs_token_flag + UndefinedControlSequence as i32 > MaxHalfWord as i32 { bad = 21; }
+        // 522
+        if TexState::format_default_length > FileNameSize { bad = 31; }
+        // 1249
+        if (2 * MaxHalfWord as usize) < MemTop - MemMin { bad = 41; }
+        if bad > 0 {
+            self.wterm_ln(&format!("Ouch---my internal constants have been clobbered!---case {}", bad));
+            return false;
+        }
+        initialize(self);
+        if !self.get_strings_started() {
+            return false;
+        }
+        self.init_prim(); // call primitive for each primitive
+        // 1216
+        let s = self.make_str("inaccessible");
+        self.hash[FrozenProtection as usize].set_text(s as HalfWord);
+        // 1301
+        self.format_ident = self.make_str(" (INITEX)");
+        // 1369
+        let s = self.make_str("endwrite");
+        self.hash[EndWrite as usize].set_text(s as HalfWord);
+        self.set_eq_level(EndWrite as usize, LevelOne as QuarterWord);
+        self.set_eq_type(EndWrite as usize, OuterCall);
+        self.set_equiv(EndWrite as usize, Null);
+        self.init_str_ptr = self.str_ptr;
+        self.init_pool_ptr = self.pool_ptr;
+        self.fix_date_and_time();
+        self.ready_already = 314159;
+        true
+    }
+
+    // 1332
+    // the rest of TeX's main body, from start_of_TEX to final_end: reads the first
+    // line, loads the format it asks for, and gives control to the chief executive
+    fn run_tex(&mut self) {
+        let finished = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            // 55
+            self.selector = TermOnly;
+            self.tally = 0;
+            self.term_offset = 0;
+            self.file_offset = 0;
+            // 61
+            self.wterm(banner);
+            if self.format_ident == 0 {
+                self.wterm_ln(" (no format preloaded)");
+            } else {
+                self.slow_print(self.format_ident);
+                self.print_ln();
+            }
+            self.update_terminal();
+            // 528
+            self.job_name = 0;
+            self.name_in_progress = false;
+            self.log_opened = false;
+            // 533
+            self.output_file_name = 0;
+            // 1337: get the first line of input and prepare to start
+            if !self.init_input_routines() || !self.load_format() {
+                return false; // goto final_end
+            }
+            if let Some(i) = self.interaction_option {
+                self.interaction = i; // the caller's choice outranks the format's
+            }
+            if self.end_line_char_inactive() {
+                self.cur_input.limit_field -= 1;
+            } else {
+                self.buffer[self.cur_input.limit_field as usize] = self.end_line_char() as u8;
+            }
+            self.fix_date_and_time();
+            // 75: initialize the print selector based on interaction
+            self.selector = if self.interaction == Interaction::BatchMode { NoPrint } else { TermOnly };
+            let loc = self.cur_input.loc_field;
+            if loc < self.cur_input.limit_field && self.cat_code(self.buffer[loc as usize] as HalfWord) != Escape as HalfWord {
+                self.start_input(); // \input assumed
+            }
+            self.history = History::Spotless; // ready to go!
+            self.main_control(); // come to life
+            self.final_cleanup(); // prepare for death
+            true
+        }));
+        match finished {
+            Ok(false) => {}
+            Ok(true) => self.close_files_and_terminate(), // end_of_TEX
+            Err(e) => {
+                if !e.is::<EndOfTex>() {
+                    std::panic::resume_unwind(e);
+                }
+                self.close_files_and_terminate();
+            }
+        }
+        self.ready_already = 0; // final_end
+    }
+
+    // 331
+    // initialize the input routines; false means the terminal had nothing to say
+    fn init_input_routines(&mut self) -> bool {
+        self.input_ptr = 0;
+        self.max_in_stack = 0;
+        self.in_open = 0;
+        self.open_parens = 0;
+        self.max_buf_stack = 0;
+        self.param_ptr = 0;
+        self.max_param_stack = 0;
+        for k in 0..=BufSize as usize {
+            self.buffer[k] = 0;
+        }
+        self.scanner_status = Normal;
+        self.warning_index = Null;
+        self.first = 1;
+        self.cur_input.state_field = NewLine;
+        self.cur_input.start_field = 1;
+        self.cur_input.index_field = 0;
+        self.line = 0;
+        self.cur_input.name_field = 0;
+        self.force_eof = false;
+        self.align_state = 1000000;
+        if !self.init_terminal() {
+            return false;
+        }
+        self.cur_input.limit_field = self.last as HalfWord;
+        self.first = self.last + 1; // init_terminal has set loc and last
+        true
+    }
+}
+
+// the exit status of the run
+fn do_final_end(state: &TexState) -> i32 {
+    if state.history <= History::WarningIssued { 0 } else { 1 }
+}
+
+// runs TeX as the command line asks, with the words after the program name as
+// the first line of input, and returns the exit status; the terminal is stdin
+// and stdout, and the environment supplies the search paths, the output format
+// (TEXOUTPUT) and the time (SOURCE_DATE_EPOCH)
+pub fn run(args: &[String]) -> i32 {
+    let mut state = TexState::new();
+    state.set_terminal(Box::new(std::io::BufReader::new(std::io::stdin())), Box::new(std::io::stdout()));
+    state.set_resolver(Box::new(PathResolver::from_env()));
+    state.set_output_format(OutputFormat::from_env());
+    state.source_date_epoch = std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|s| s.trim().parse().ok());
+    if !args.is_empty() {
+        state.first_line = Some(args.join(" ").into_bytes());
+    }
+    if state.init_tex() {
+        state.run_tex();
+    }
+    do_final_end(&state)
+}
+
+// the name under which typeset returns what TeX wrote on the terminal; it can't
+// clash with a file, since TeX gives the files it writes an extension
+pub const TerminalOutput: &str = "<terminal>";
+
+// typesets from memory: TeX reads the files in vfs, starting with first_line as if
+// it had been typed on the command line, and the files it writes are returned by
+// name, together with the terminal output under TerminalOutput; pages go out as DVI
+pub fn typeset(vfs: &Vfs, first_line: &str) -> BTreeMap<String, Vec<u8>> {
+    typeset_to(vfs, first_line, OutputFormat::Dvi)
+}
+
+// the same, but pages go out in the given format; TeX runs in \nonstopmode, since
+// nobody is at the terminal, and nothing is taken from the environment
+pub fn typeset_to(vfs: &Vfs, first_line: &str, format: OutputFormat) -> BTreeMap<String, Vec<u8>> {
+    let mut state = TexState::new();
+    if let Some(terminal) = vfs.clone().open_out(TerminalOutput) {
+        state.set_terminal(Box::new(std::io::empty()), terminal);
+    }
+    state.set_resolver(Box::new(vfs.clone()));
+    state.set_output_format(format);
+    state.interaction_option = Some(Interaction::NonstopMode);
+    state.first_line = Some(first_line.as_bytes().to_vec());
+    if state.init_tex() {
+        state.run_tex();
+    }
+    vfs.take_outputs()
+}
@@ -0,0 +1,1108 @@
+use crate::*;
+
+// 817
+pub const TightFit: QuarterWord = 3; // fitness classification for lines shrinking 0.5 to 1.0 of their shrinkability
+pub const DecentFit: QuarterWord = 2; // fitness classification for all other lines
+pub const LooseFit: QuarterWord = 1; // fitness classification for lines stretching 0.5 to 1.0 of their stretchability
+pub const VeryLooseFit: QuarterWord = 0; // fitness classification for lines stretching more than their stretchability
+
+// 819
+pub const ActiveNodeSize: usize = 3; // number of words in active nodes
+pub const Unhyphenated: QuarterWord = 0; // the type of a normal active break node
+pub const Hyphenated: QuarterWord = 1; // the type of an active node that breaks at a disc_node
+pub const LastActive: Pointer = Active as Pointer; // the active list ends where it begins
+
+// 821
+pub const PassiveNodeSize: usize = 2; // number of words in passive nodes
+
+// 822
+pub const DeltaNodeSize: usize = 7; // number of words in a delta node
+pub const DeltaNode: QuarterWord = 2; // type field in a delta node
+
+impl TexState {
+    // 819
+    // very_loose_fit..tight_fit on final line for this break
+    pub fn fitness(&self, p: usize) -> QuarterWord {
+        self.subtype(p)
+    }
+
+    pub fn set_fitness(&mut self, p: usize, value: QuarterWord) {
+        self.set_subtype(p, value);
+    }
+
+    // pointer to the corresponding passive node
+    pub fn break_node(&self, p: usize) -> HalfWord {
+        self.rlink(p)
+    }
+
+    pub fn set_break_node(&mut self, p: usize, value: HalfWord) {
+        self.set_rlink(p, value);
+    }
+
+    // line that begins at this breakpoint
+    pub fn line_number(&self, p: usize) -> HalfWord {
+        self.llink(p)
+    }
+
+    pub fn set_line_number(&mut self, p: usize, value: HalfWord) {
+        self.set_llink(p, value);
+    }
+
+    // the quantity that TeX minimizes
+    pub fn total_demerits(&self, p: usize) -> i32 {
+        self.mem[p + 2].int()
+    }
+
+    pub fn set_total_demerits(&mut self, p: usize, value: i32) {
+        self.mem[p + 2].set_int(value);
+    }
+
+    // 821
+    // in passive node, points to position of this breakpoint
+    pub fn cur_break(&self, p: usize) -> HalfWord {
+        self.rlink(p)
+    }
+
+    pub fn set_cur_break(&mut self, p: usize, value: HalfWord) {
+        self.set_rlink(p, value);
+    }
+
+    // points to passive node that should precede this one
+    pub fn prev_break(&self, p: usize) -> HalfWord {
+        self.llink(p)
+    }
+
+    pub fn set_prev_break(&mut self, p: usize, value: HalfWord) {
+        self.set_llink(p, value);
+    }
+
+    // new name for prev_break after links are reversed
+    pub fn next_break(&self, p: usize) -> HalfWord {
+        self.prev_break(p)
+    }
+
+    pub fn set_next_break(&mut self, p: usize, value: HalfWord) {
+        self.set_prev_break(p, value);
+    }
+
+    // serial number for symbolic identification
+    pub fn serial(&self, p: usize) -> HalfWord {
+        self.info(p)
+    }
+
+    pub fn set_serial(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p, value);
+    }
+
+    // 841, 842, 870, 871
+    // the width of a node that may appear in a discretionary, s being the
+    // name of the caller for the confusion message
+    fn disc_node_width(&mut self, p: usize, s: &str) -> Scaled {
+        if self.is_char_node(p) {
+            let f = self.font(p) as InternalFontNumber;
+            return self.char_width(f, self.char_info(f, self.character(p)));
+        }
+        match self.a_type(p) {
+            LigatureNode => {
+                let c = TexState::lig_char(p);
+                let f = self.font(c) as InternalFontNumber;
+                self.char_width(f, self.char_info(f, self.character(c)))
+            }
+            HlistNode | VlistNode | RuleNode | KernNode => self.width(p),
+            _ => self.confusion(s),
+        }
+    }
+
+    // 815
+    // breaks the horizontal list of the current paragraph into lines and
+    // appends them to the enclosing vertical list
+    pub fn line_break(&mut self, final_widow_penalty: i32) {
+        self.pack_begin_line = self.mode_line(); // this is for over/underfull box messages
+        // 816: get ready to start line breaking
+        self.set_link(TempHead, self.link(self.head() as usize));
+        let tail = self.tail() as usize;
+        if self.is_char_node(tail) || self.a_type(tail) != GlueNode {
+            let p = self.new_penalty(InfPenalty);
+            self.tail_append(p);
+        } else {
+            self.set_type(tail, PenaltyNode);
+            self.delete_glue_ref(self.glue_ptr(tail));
+            self.flush_node_list(self.leader_ptr(tail));
+            self.set_penalty(tail, InfPenalty);
+        }
+        let p = self.new_param_glue(ParFillSkipCode);
+        self.set_link(self.tail() as usize, p);
+        self.init_cur_lang = (self.prev_graf() % 0o200000) as ASCIICode;
+        self.init_l_hyf = self.prev_graf() / 0o20000000;
+        self.init_r_hyf = (self.prev_graf() / 0o200000) % 0o100;
+        self.pop_nest();
+        // 827: get ready to start line breaking
+        self.no_shrink_error_yet = true;
+        for &c in [LeftSkipCode, RightSkipCode].iter() {
+            let q = self.glue_par(c);
+            if self.shrink_order(q as usize) != Normal as u8 && self.shrink(q as usize) != 0 {
+                let q = self.finite_shrink(q);
+                self.set_equiv((GlueBase + c) as usize, q);
+            }
+        }
+        let q = self.glue_par(LeftSkipCode) as usize;
+        let r = self.glue_par(RightSkipCode) as usize;
+        self.background = [0; 7];
+        self.background[1] = self.width(q) + self.width(r);
+        self.background[2 + self.stretch_order(q) as usize] += self.stretch(q);
+        self.background[2 + self.stretch_order(r) as usize] += self.stretch(r);
+        self.background[6] = self.shrink(q) + self.shrink(r);
+        // 834
+        self.minimum_demerits = AwfulBad;
+        self.minimal_demerits = [AwfulBad; 4];
+        // 848
+        if self.par_shape_ptr() == Null {
+            let hang_indent = self.dimen_par(HangIndentCode);
+            if hang_indent == 0 {
+                self.last_special_line = 0;
+                self.second_width = self.dimen_par(HsizeCode);
+                self.second_indent = 0;
+            } else {
+                // 849: set line length parameters in preparation for hanging indentation
+                let hang_after = self.int_par(HangAfterCode);
+                let hsize = self.dimen_par(HsizeCode);
+                self.last_special_line = hang_after.abs();
+                let (narrow_width, narrow_indent) = (hsize - hang_indent.abs(), hang_indent.max(0));
+                if hang_after < 0 {
+                    self.first_width = narrow_width;
+                    self.first_indent = narrow_indent;
+                    self.second_width = hsize;
+                    self.second_indent = 0;
+                } else {
+                    self.first_width = hsize;
+                    self.first_indent = 0;
+                    self.second_width = narrow_width;
+                    self.second_indent = narrow_indent;
+                }
+            }
+        } else {
+            let p = self.par_shape_ptr() as usize;
+            self.last_special_line = self.info(p) as i32 - 1;
+            self.second_width = self.mem[p + 2 * (self.last_special_line as usize + 1)].sc();
+            self.second_indent = self.mem[p + 2 * self.last_special_line as usize + 1].sc();
+        }
+        self.easy_line = if self.int_par(LoosenessCode) == 0 {
+            self.last_special_line
+        } else {
+            MaxHalfWord as i32
+        };
+        self.find_optimal_breakpoints();
+        // 876: break the paragraph at the chosen breakpoints, justify the resulting
+        // lines to the correct widths, and append them to the current vertical list
+        self.post_line_break(final_widow_penalty);
+        self.clean_up_break_nodes();
+        self.pack_begin_line = 0;
+    }
+
+    // 826
+    // the glue spec p with its infinite shrinkability made finite, complaining once per paragraph
+    fn finite_shrink(&mut self, p: Pointer) -> Pointer {
+        if self.no_shrink_error_yet {
+            self.no_shrink_error_yet = false;
+            if self.int_par(TracingParagraphsCode) > 0 {
+                self.end_diagnostic(true);
+            }
+            self.print_err("Infinite glue shrinkage found in a paragraph");
+            self.help(&["The paragraph just ended includes some glue that has",
+                "infinite shrinkability, e.g., `\\hskip 0pt minus 1fil'.",
+                "Such glue doesn't belong there---it allows a paragraph",
+                "of any length to fit on one line. But it's safe to proceed,",
+                "since the offensive shrinkability has been made finite."]);
+            self.error();
+            if self.int_par(TracingParagraphsCode) > 0 {
+                self.begin_diagnostic();
+            }
+        }
+        let q = self.new_spec(p);
+        self.set_shrink_order(q as usize, Normal as u8);
+        self.delete_glue_ref(p);
+        q
+    }
+
+    // 863
+    // runs the passes over the paragraph until a satisfactory set of breaks is found
+    fn find_optimal_breakpoints(&mut self) {
+        let tracing = self.int_par(TracingParagraphsCode) > 0;
+        self.threshold = self.int_par(PretoleranceCode);
+        if self.threshold >= 0 {
+            if tracing {
+                self.begin_diagnostic();
+                self.print_nl("@firstpass");
+            }
+            self.second_pass = false;
+            self.final_pass = false;
+        } else {
+            self.threshold = self.int_par(ToleranceCode);
+            self.second_pass = true;
+            self.final_pass = self.dimen_par(EmergencyStretchCode) <= 0;
+            if tracing {
+                self.begin_diagnostic();
+            }
+        }
+        loop {
+            if self.threshold > InfBad {
+                self.threshold = InfBad;
+            }
+            if self.second_pass {
+                // 891: initialize for hyphenating a paragraph
+                if self.trie_not_ready {
+                    self.init_trie();
+                }
+                self.cur_lang = self.init_cur_lang;
+                self.l_hyf = self.init_l_hyf;
+                self.r_hyf = self.init_r_hyf;
+            }
+            // 864: create an active breakpoint representing the beginning of the paragraph
+            let q = self.get_node(ActiveNodeSize) as usize;
+            self.set_type(q, Unhyphenated);
+            self.set_fitness(q, DecentFit);
+            self.set_link(q, LastActive);
+            self.set_break_node(q, Null);
+            self.set_line_number(q, (self.prev_graf() + 1) as HalfWord);
+            self.set_total_demerits(q, 0);
+            self.set_link(Active, q as Pointer);
+            self.active_width = self.background;
+            self.passive = Null;
+            self.printed_node = TempHead as Pointer;
+            self.pass_number = 0;
+            self.font_in_short_display = NullFont as i32;
+            self.cur_p = self.link(TempHead);
+            let mut auto_breaking = true; // is node cur_p outside a formula?
+            let mut prev_p = self.cur_p; // helps to determine when glue nodes are breakpoints
+            while self.cur_p != Null && self.link(Active) != LastActive {
+                // 866: call try_break if cur_p is a legal breakpoint; on the second pass,
+                // also try to hyphenate the next word, if cur_p is a glue node; then
+                // advance cur_p to the next node of the paragraph that could possibly
+                // be a legal breakpoint
+                if self.is_char_node(self.cur_p as usize) {
+                    // 867: advance cur_p to the node following the present string of characters
+                    prev_p = self.cur_p;
+                    while self.is_char_node(self.cur_p as usize) {
+                        let p = self.cur_p as usize;
+                        let f = self.font(p) as InternalFontNumber;
+                        self.active_width[1] += self.char_width(f, self.char_info(f, self.character(p)));
+                        self.cur_p = self.link(p);
+                    }
+                }
+                let p = self.cur_p as usize;
+                match self.a_type(p) {
+                    HlistNode | VlistNode | RuleNode => self.active_width[1] += self.width(p),
+                    WhatsitNode => {
+                        // 1362: advance past a whatsit node in the line_break loop
+                        if self.subtype(p) == LanguageNode {
+                            self.cur_lang = self.what_lang(p) as ASCIICode;
+                            self.l_hyf = self.what_lhm(p) as i32;
+                            self.r_hyf = self.what_rhm(p) as i32;
+                        }
+                    }
+                    GlueNode => {
+                        // 868: if node cur_p is a legal breakpoint, try to break there
+                        if auto_breaking {
+                            let q = prev_p as usize;
+                            if self.is_char_node(q)
+                                || self.a_type(q) < MathNode
+                                || (self.a_type(q) == KernNode && self.subtype(q) != Explicit)
+                            {
+                                self.try_break(0, Unhyphenated);
+                            }
+                        }
+                        let mut q = self.glue_ptr(p);
+                        if self.shrink_order(q as usize) != Normal as u8 && self.shrink(q as usize) != 0 {
+                            q = self.finite_shrink(q);
+                            self.set_glue_ptr(p, q);
+                        }
+                        let q = q as usize;
+                        self.active_width[1] += self.width(q);
+                        self.active_width[2 + self.stretch_order(q) as usize] += self.stretch(q);
+                        self.active_width[6] += self.shrink(q);
+                        if self.second_pass && auto_breaking {
+                            // 894: try to hyphenate the following word
+                            self.try_hyphenate_following_word();
+                        }
+                    }
+                    KernNode => {
+                        if self.subtype(p) == Explicit {
+                            self.kern_break(auto_breaking);
+                        } else {
+                            self.active_width[1] += self.width(p);
+                        }
+                    }
+                    LigatureNode => {
+                        let c = TexState::lig_char(p);
+                        let f = self.font(c) as InternalFontNumber;
+                        self.active_width[1] += self.char_width(f, self.char_info(f, self.character(c)));
+                    }
+                    DiscNode => {
+                        // 869: try to break after a discretionary fragment, then goto done5
+                        let mut s = self.pre_break(p);
+                        self.disc_width = 0;
+                        if s == Null {
+                            self.try_break(self.int_par(ExHyphenPenaltyCode), Hyphenated);
+                        } else {
+                            while s != Null {
+                                self.disc_width += self.disc_node_width(s as usize, "disc3");
+                                s = self.link(s as usize);
+                            }
+                            self.active_width[1] += self.disc_width;
+                            self.try_break(self.int_par(HyphenPenaltyCode), Hyphenated);
+                            self.active_width[1] -= self.disc_width;
+                        }
+                        let mut r = self.replace_count(p);
+                        let mut s = self.link(p);
+                        while r > 0 {
+                            self.active_width[1] += self.disc_node_width(s as usize, "disc4");
+                            r -= 1;
+                            s = self.link(s as usize);
+                        }
+                        prev_p = self.cur_p;
+                        self.cur_p = s;
+                        continue;
+                    }
+                    MathNode => {
+                        auto_breaking = self.subtype(p) == After;
+                        self.kern_break(auto_breaking);
+                    }
+                    PenaltyNode => self.try_break(self.penalty(p), Unhyphenated),
+                    MarkNode | InsNode | AdjustNode => {}
+                    _ => self.confusion("paragraph"),
+                }
+                prev_p = self.cur_p;
+                self.cur_p = self.link(self.cur_p as usize);
+            }
+            if self.cur_p == Null {
+                // 873: try the final line break at the end of the paragraph,
+                // and goto done if the desired breakpoints have been found
+                self.try_break(EjectPenalty, Hyphenated);
+                if self.link(Active) != LastActive {
+                    // 874: find an active node with fewest demerits
+                    let mut r = self.link(Active);
+                    self.fewest_demerits = AwfulBad;
+                    while r != LastActive {
+                        if self.a_type(r as usize) != DeltaNode && self.total_demerits(r as usize) < self.fewest_demerits {
+                            self.fewest_demerits = self.total_demerits(r as usize);
+                            self.best_bet = r;
+                        }
+                        r = self.link(r as usize);
+                    }
+                    self.best_line = self.line_number(self.best_bet as usize) as i32;
+                    let looseness = self.int_par(LoosenessCode);
+                    if looseness == 0 {
+                        break;
+                    }
+                    // 875: find the best active node for the desired looseness
+                    let mut r = self.link(Active);
+                    self.actual_looseness = 0;
+                    while r != LastActive {
+                        if self.a_type(r as usize) != DeltaNode {
+                            let line_diff = self.line_number(r as usize) as i32 - self.best_line;
+                            if (line_diff < self.actual_looseness && looseness <= line_diff)
+                                || (line_diff > self.actual_looseness && looseness >= line_diff)
+                            {
+                                self.best_bet = r;
+                                self.actual_looseness = line_diff;
+                                self.fewest_demerits = self.total_demerits(r as usize);
+                            } else if line_diff == self.actual_looseness
+                                && self.total_demerits(r as usize) < self.fewest_demerits
+                            {
+                                self.best_bet = r;
+                                self.fewest_demerits = self.total_demerits(r as usize);
+                            }
+                        }
+                        r = self.link(r as usize);
+                    }
+                    self.best_line = self.line_number(self.best_bet as usize) as i32;
+                    if self.actual_looseness == looseness || self.final_pass {
+                        break;
+                    }
+                }
+            }
+            self.clean_up_break_nodes();
+            if !self.second_pass {
+                if tracing {
+                    self.print_nl("@secondpass");
+                }
+                self.threshold = self.int_par(ToleranceCode);
+                self.second_pass = true;
+                self.final_pass = self.dimen_par(EmergencyStretchCode) <= 0;
+            } else {
+                // if no line breaks, try again with hyphenation
+                if tracing {
+                    self.print_nl("@emergencypass");
+                }
+                self.background[2] += self.dimen_par(EmergencyStretchCode);
+                self.final_pass = true;
+            }
+        }
+        if tracing {
+            self.end_diagnostic(true);
+            self.normalize_selector();
+        }
+    }
+
+    // 866
+    // an explicit kern or math node is a legal breakpoint when glue follows it
+    fn kern_break(&mut self, auto_breaking: bool) {
+        let p = self.cur_p as usize;
+        let q = self.link(p) as usize;
+        if !self.is_char_node(q) && auto_breaking && self.a_type(q) == GlueNode {
+            self.try_break(0, Unhyphenated);
+        }
+        self.active_width[1] += self.width(p);
+    }
+
+    // 865
+    // removes the active and passive nodes of a pass
+    fn clean_up_break_nodes(&mut self) {
+        let mut q = self.link(Active);
+        while q != LastActive {
+            self.cur_p = self.link(q as usize);
+            if self.a_type(q as usize) == DeltaNode {
+                self.free_node(q, DeltaNodeSize);
+            } else {
+                self.free_node(q, ActiveNodeSize);
+            }
+            q = self.cur_p;
+        }
+        q = self.passive;
+        while q != Null {
+            self.cur_p = self.link(q as usize);
+            self.free_node(q, PassiveNodeSize);
+            q = self.cur_p;
+        }
+    }
+
+    // 829
+    // tests whether cur_p is a feasible breakpoint with penalty pi, updating the active list
+    fn try_break(&mut self, pi: i32, break_type: QuarterWord) {
+        let mut pi = pi;
+        // 831: make sure that pi is in the proper range
+        if pi.abs() >= InfPenalty {
+            if pi > 0 {
+                self.update_printed_node();
+                return;
+            }
+            pi = EjectPenalty;
+        }
+        let mut no_break_yet = true; // have we computed break_width yet?
+        let mut prev_r = Active as Pointer; // stays a step behind r
+        let mut prev_prev_r = Null; // a step behind prev_r, if type(prev_r)=delta_node
+        let mut old_l: i32 = 0; // maximum line number in current equivalence class of lines
+        let mut line_width: Scaled = 0; // the current line will be justified to this width
+        self.cur_active_width = self.active_width;
+        loop {
+            let mut r = self.link(prev_r as usize);
+            // 832: if node r is of type delta_node, update cur_active_width,
+            // set prev_r and prev_prev_r, then goto continue
+            if self.a_type(r as usize) == DeltaNode {
+                for k in 1..=6 {
+                    self.cur_active_width[k] += self.mem[r as usize + k].sc();
+                }
+                prev_prev_r = prev_r;
+                prev_r = r;
+                continue;
+            }
+            // 835: if a line number class has ended, create new active nodes for the
+            // best feasible breaks in that class; then return if r=last_active,
+            // otherwise compute the new line_width
+            let l = self.line_number(r as usize) as i32;
+            if l > old_l {
+                // now we are no longer in the inner loop
+                if self.minimum_demerits < AwfulBad && (old_l != self.easy_line || r == LastActive) {
+                    // 836: create new active nodes for the best feasible breaks just found
+                    if no_break_yet {
+                        no_break_yet = false;
+                        self.compute_break_width(break_type);
+                    }
+                    // 843: insert a delta node to prepare for breaks at cur_p
+                    if self.a_type(prev_r as usize) == DeltaNode {
+                        // modify an existing delta node
+                        for k in 1..=6 {
+                            let w = self.mem[prev_r as usize + k].sc() - self.cur_active_width[k] + self.break_width[k];
+                            self.mem[prev_r as usize + k].set_sc(w);
+                        }
+                    } else if prev_r == Active as Pointer {
+                        // no delta node needed at the beginning
+                        self.active_width = self.break_width;
+                    } else {
+                        let q = self.get_node(DeltaNodeSize);
+                        self.set_link(q as usize, r);
+                        self.set_type(q as usize, DeltaNode);
+                        self.set_subtype(q as usize, 0); // the subtype is not used
+                        for k in 1..=6 {
+                            self.mem[q as usize + k].set_sc(self.break_width[k] - self.cur_active_width[k]);
+                        }
+                        self.set_link(prev_r as usize, q);
+                        prev_prev_r = prev_r;
+                        prev_r = q;
+                    }
+                    let adj_demerits = self.int_par(AdjDemeritsCode);
+                    if adj_demerits.abs() >= AwfulBad - self.minimum_demerits {
+                        self.minimum_demerits = AwfulBad - 1;
+                    } else {
+                        self.minimum_demerits += adj_demerits.abs();
+                    }
+                    for fit_class in VeryLooseFit..=TightFit {
+                        let k = fit_class as usize;
+                        if self.minimal_demerits[k] <= self.minimum_demerits {
+                            // 845: insert a new active node from best_place[fit_class] to cur_p
+                            let q = self.get_node(PassiveNodeSize);
+                            self.set_link(q as usize, self.passive);
+                            self.passive = q;
+                            self.set_cur_break(q as usize, self.cur_p);
+                            self.pass_number += 1;
+                            self.set_serial(q as usize, self.pass_number as HalfWord);
+                            self.set_prev_break(q as usize, self.best_place[k]);
+                            let q = self.get_node(ActiveNodeSize);
+                            self.set_break_node(q as usize, self.passive);
+                            self.set_line_number(q as usize, (self.best_pl_line[k] + 1) as HalfWord);
+                            self.set_fitness(q as usize, fit_class);
+                            self.set_type(q as usize, break_type);
+                            self.set_total_demerits(q as usize, self.minimal_demerits[k]);
+                            self.set_link(q as usize, r);
+                            self.set_link(prev_r as usize, q);
+                            prev_r = q;
+                            if self.int_par(TracingParagraphsCode) > 0 {
+                                self.print_break_node(q as usize, fit_class, break_type);
+                            }
+                        }
+                        self.minimal_demerits[k] = AwfulBad;
+                    }
+                    self.minimum_demerits = AwfulBad;
+                    // 844: insert a delta node to prepare for the next active node
+                    if r != LastActive {
+                        let q = self.get_node(DeltaNodeSize);
+                        self.set_link(q as usize, r);
+                        self.set_type(q as usize, DeltaNode);
+                        self.set_subtype(q as usize, 0); // the subtype is not used
+                        for k in 1..=6 {
+                            self.mem[q as usize + k].set_sc(self.cur_active_width[k] - self.break_width[k]);
+                        }
+                        self.set_link(prev_r as usize, q);
+                        prev_prev_r = prev_r;
+                        prev_r = q;
+                    }
+                }
+                if r == LastActive {
+                    self.update_printed_node();
+                    return;
+                }
+                // 850: compute the new line width
+                if l > self.easy_line {
+                    line_width = self.second_width;
+                    old_l = MaxHalfWord as i32 - 1;
+                } else {
+                    old_l = l;
+                    line_width = if l > self.last_special_line {
+                        self.second_width
+                    } else if self.par_shape_ptr() == Null {
+                        self.first_width
+                    } else {
+                        self.mem[self.par_shape_ptr() as usize + 2 * l as usize].sc()
+                    };
+                }
+            }
+            // 851: consider the demerits for a line from r to cur_p; deactivate node r
+            // if it should no longer be active; then goto continue if a line from r
+            // to cur_p is infeasible, otherwise record a new feasible break
+            let mut artificial_demerits = false; // has d been forced to zero?
+            let shortfall = line_width - self.cur_active_width[1]; // used in badness calculations
+            let (b, fit_class) = if shortfall > 0 {
+                // 852: set the value of b to the badness for stretching the line,
+                // and compute the corresponding fit_class
+                if self.cur_active_width[3] != 0 || self.cur_active_width[4] != 0 || self.cur_active_width[5] != 0 {
+                    (0, DecentFit)
+                } else if shortfall > 7230584 && self.cur_active_width[2] < 1663497 {
+                    (InfBad, VeryLooseFit)
+                } else {
+                    let b = badness(shortfall, self.cur_active_width[2]);
+                    if b > 99 {
+                        (b, VeryLooseFit)
+                    } else if b > 12 {
+                        (b, LooseFit)
+                    } else {
+                        (b, DecentFit)
+                    }
+                }
+            } else {
+                // 853: set the value of b to the badness for shrinking the line,
+                // and compute the corresponding fit_class
+                let b = if -shortfall > self.cur_active_width[6] {
+                    InfBad + 1
+                } else {
+                    badness(-shortfall, self.cur_active_width[6])
+                };
+                (b, if b > 12 { TightFit } else { DecentFit })
+            };
+            let node_r_stays_active; // should node r remain in the active list?
+            let mut deactivate = false;
+            if b > InfBad || pi == EjectPenalty {
+                // 854: prepare to deactivate node r, and goto deactivate unless there is
+                // a reason to consider lines of text from r to cur_p
+                if self.final_pass && self.minimum_demerits == AwfulBad
+                    && self.link(r as usize) == LastActive && prev_r == Active as Pointer
+                {
+                    artificial_demerits = true; // set demerits zero, this break is forced
+                } else if b > self.threshold {
+                    deactivate = true;
+                }
+                node_r_stays_active = false;
+            } else {
+                prev_r = r;
+                if b > self.threshold {
+                    continue;
+                }
+                node_r_stays_active = true;
+            }
+            if !deactivate {
+                // 855: record a new feasible break
+                let mut d = if artificial_demerits {
+                    0
+                } else {
+                    // 859: compute the demerits, d, from r to cur_p
+                    let mut d = self.int_par(LinePenaltyCode) + b;
+                    d = if d.abs() >= 10000 { 100000000 } else { d * d };
+                    if pi != 0 {
+                        if pi > 0 {
+                            d += pi * pi;
+                        } else if pi > EjectPenalty {
+                            d -= pi * pi;
+                        }
+                    }
+                    if break_type == Hyphenated && self.a_type(r as usize) == Hyphenated {
+                        if self.cur_p != Null {
+                            d += self.int_par(DoubleHyphenDemeritsCode);
+                        } else {
+                            d += self.int_par(FinalHyphenDemeritsCode);
+                        }
+                    }
+                    if (fit_class as i32 - self.fitness(r as usize) as i32).abs() > 1 {
+                        d += self.int_par(AdjDemeritsCode);
+                    }
+                    d
+                };
+                if self.int_par(TracingParagraphsCode) > 0 {
+                    self.print_feasible_break(r as usize, b, pi, d, artificial_demerits);
+                }
+                d += self.total_demerits(r as usize); // this is the minimum total demerits from the beginning to cur_p via r
+                let k = fit_class as usize;
+                if d <= self.minimal_demerits[k] {
+                    self.minimal_demerits[k] = d;
+                    self.best_place[k] = self.break_node(r as usize);
+                    self.best_pl_line[k] = l;
+                    if d < self.minimum_demerits {
+                        self.minimum_demerits = d;
+                    }
+                }
+                if node_r_stays_active {
+                    continue; // prev_r has been set to r
+                }
+            }
+            // 860: deactivate node r
+            self.set_link(prev_r as usize, self.link(r as usize));
+            self.free_node(r, ActiveNodeSize);
+            if prev_r == Active as Pointer {
+                // 861: update the active widths, since the first active node has been deleted
+                r = self.link(Active);
+                if self.a_type(r as usize) == DeltaNode {
+                    for k in 1..=6 {
+                        self.active_width[k] += self.mem[r as usize + k].sc();
+                    }
+                    self.cur_active_width = self.active_width;
+                    self.set_link(Active, self.link(r as usize));
+                    self.free_node(r, DeltaNodeSize);
+                }
+            } else if self.a_type(prev_r as usize) == DeltaNode {
+                r = self.link(prev_r as usize);
+                if r == LastActive {
+                    for k in 1..=6 {
+                        self.cur_active_width[k] -= self.mem[prev_r as usize + k].sc();
+                    }
+                    self.set_link(prev_prev_r as usize, LastActive);
+                    self.free_node(prev_r, DeltaNodeSize);
+                    prev_r = prev_prev_r;
+                } else if self.a_type(r as usize) == DeltaNode {
+                    for k in 1..=6 {
+                        self.cur_active_width[k] += self.mem[r as usize + k].sc();
+                    }
+                    for k in 1..=6 {
+                        let w = self.mem[prev_r as usize + k].sc() + self.mem[r as usize + k].sc();
+                        self.mem[prev_r as usize + k].set_sc(w);
+                    }
+                    self.set_link(prev_r as usize, self.link(r as usize));
+                    self.free_node(r, DeltaNodeSize);
+                }
+            }
+        }
+    }
+
+    // 837
+    // computes the values of break_width, the background less whatever is
+    // discarded after a break at cur_p
+    fn compute_break_width(&mut self, break_type: QuarterWord) {
+        self.break_width = self.background;
+        let mut s = self.cur_p;
+        if break_type > Unhyphenated && self.cur_p != Null {
+            // 840: compute the discretionary break_width values
+            let p = self.cur_p as usize;
+            let mut t = self.replace_count(p);
+            let mut v = p;
+            s = self.post_break(p);
+            while t > 0 {
+                t -= 1;
+                v = self.link(v) as usize;
+                // 841: subtract the width of node v from break_width
+                self.break_width[1] -= self.disc_node_width(v, "disc1");
+            }
+            while s != Null {
+                // 842: add the width of node s to break_width
+                self.break_width[1] += self.disc_node_width(s as usize, "disc2");
+                s = self.link(s as usize);
+            }
+            self.break_width[1] += self.disc_width;
+            if self.post_break(p) == Null {
+                s = self.link(v); // nodes may be discardable after the break
+            }
+        }
+        while s != Null {
+            let p = s as usize;
+            if self.is_char_node(p) {
+                return;
+            }
+            match self.a_type(p) {
+                GlueNode => {
+                    // 838: subtract glue from break_width
+                    let v = self.glue_ptr(p) as usize;
+                    self.break_width[1] -= self.width(v);
+                    self.break_width[2 + self.stretch_order(v) as usize] -= self.stretch(v);
+                    self.break_width[6] -= self.shrink(v);
+                }
+                PenaltyNode => {}
+                MathNode => self.break_width[1] -= self.width(p),
+                KernNode => {
+                    if self.subtype(p) != Explicit {
+                        return;
+                    }
+                    self.break_width[1] -= self.width(p);
+                }
+                _ => return,
+            }
+            s = self.link(p);
+        }
+    }
+
+    // 846
+    // prints a symbolic description of the new break node q
+    fn print_break_node(&mut self, q: usize, fit_class: QuarterWord, break_type: QuarterWord) {
+        self.print_nl("@@");
+        self.print_int(self.serial(self.passive as usize) as i32);
+        self.print(": line ");
+        self.print_int(self.line_number(q) as i32 - 1);
+        self.print_char('.' as u8);
+        self.print_int(fit_class as i32);
+        if break_type == Hyphenated {
+            self.print_char('-' as u8);
+        }
+        self.print(" t=");
+        self.print_int(self.total_demerits(q));
+        self.print(" -> @@");
+        if self.prev_break(self.passive as usize) == Null {
+            self.print_char('0' as u8);
+        } else {
+            self.print_int(self.serial(self.prev_break(self.passive as usize) as usize) as i32);
+        }
+    }
+
+    // 856
+    // prints a symbolic description of the feasible break from r to cur_p
+    fn print_feasible_break(&mut self, r: usize, b: i32, pi: i32, d: i32, artificial_demerits: bool) {
+        if self.printed_node != self.cur_p {
+            // 857: print the list between printed_node and cur_p,
+            // then set printed_node:=cur_p
+            self.print_nl("");
+            if self.cur_p == Null {
+                self.short_display(self.link(self.printed_node as usize));
+            } else {
+                let save_link = self.link(self.cur_p as usize);
+                self.set_link(self.cur_p as usize, Null);
+                self.print_nl("");
+                self.short_display(self.link(self.printed_node as usize));
+                self.set_link(self.cur_p as usize, save_link);
+            }
+            self.printed_node = self.cur_p;
+        }
+        self.print_nl("@");
+        if self.cur_p == Null {
+            self.print_esc("par");
+        } else {
+            match self.a_type(self.cur_p as usize) {
+                GlueNode => {}
+                PenaltyNode => self.print_esc("penalty"),
+                DiscNode => self.print_esc("discretionary"),
+                KernNode => self.print_esc("kern"),
+                _ => self.print_esc("math"),
+            }
+        }
+        self.print(" via @@");
+        if self.break_node(r) == Null {
+            self.print_char('0' as u8);
+        } else {
+            self.print_int(self.serial(self.break_node(r) as usize) as i32);
+        }
+        self.print(" b=");
+        if b > InfBad {
+            self.print_char('*' as u8);
+        } else {
+            self.print_int(b);
+        }
+        self.print(" p=");
+        self.print_int(pi);
+        self.print(" d=");
+        if artificial_demerits {
+            self.print_char('*' as u8);
+        } else {
+            self.print_int(d);
+        }
+    }
+
+    // 858
+    // moves printed_node past the replacement text of a discretionary already shown
+    fn update_printed_node(&mut self) {
+        if self.cur_p == self.printed_node && self.cur_p != Null && self.a_type(self.cur_p as usize) == DiscNode {
+            let mut t = self.replace_count(self.cur_p as usize);
+            while t > 0 {
+                t -= 1;
+                self.printed_node = self.link(self.printed_node as usize);
+            }
+        }
+    }
+
+    // 877
+    // breaks the paragraph at the breakpoints chosen by line_break and
+    // appends the justified lines to the vertical list
+    fn post_line_break(&mut self, final_widow_penalty: i32) {
+        // 878: reverse the links of the relevant passive nodes, setting cur_p to the first breakpoint
+        let mut q = self.break_node(self.best_bet as usize);
+        self.cur_p = Null;
+        loop {
+            let r = q;
+            q = self.prev_break(q as usize);
+            self.set_next_break(r as usize, self.cur_p);
+            self.cur_p = r;
+            if q == Null {
+                break;
+            }
+        }
+        let mut cur_line = self.prev_graf() + 1; // the current line number being justified
+        loop {
+            // 880: justify the line ending at breakpoint cur_p, and append it to the
+            // current vertical list, together with associated penalties and other insertions
+            // 881: modify the end of the line to reflect the nature of the break and to
+            // include \rightskip; also set the proper value of disc_break
+            let mut q = self.cur_break(self.cur_p as usize);
+            let mut disc_break = false; // was the current break at a discretionary node?
+            let mut post_disc_break = false; // and did it have a nonempty post-break part?
+            let mut right_skip_done = false;
+            if q != Null {
+                // q cannot be a char_node
+                let t = q as usize;
+                if self.a_type(t) == GlueNode {
+                    self.delete_glue_ref(self.glue_ptr(t));
+                    self.set_glue_ptr(t, self.glue_par(RightSkipCode));
+                    self.set_subtype(t, (RightSkipCode + 1) as QuarterWord);
+                    self.add_glue_ref(self.glue_par(RightSkipCode));
+                    right_skip_done = true;
+                } else if self.a_type(t) == DiscNode {
+                    // 882: change discretionary to compulsory and set disc_break:=true
+                    let mut t = self.replace_count(q as usize);
+                    // 883: destroy the t nodes following q, and make r point to the following node
+                    let mut r;
+                    if t == 0 {
+                        r = self.link(q as usize);
+                    } else {
+                        r = q;
+                        while t > 1 {
+                            r = self.link(r as usize);
+                            t -= 1;
+                        }
+                        let s = self.link(r as usize);
+                        r = self.link(s as usize);
+                        self.set_link(s as usize, Null);
+                        self.flush_node_list(self.link(q as usize));
+                        self.set_replace_count(q as usize, 0);
+                    }
+                    if self.post_break(q as usize) != Null {
+                        // 884: transplant the post-break list
+                        let mut s = self.post_break(q as usize);
+                        while self.link(s as usize) != Null {
+                            s = self.link(s as usize);
+                        }
+                        self.set_link(s as usize, r);
+                        r = self.post_break(q as usize);
+                        self.set_post_break(q as usize, Null);
+                        post_disc_break = true;
+                    }
+                    if self.pre_break(q as usize) != Null {
+                        // 885: transplant the pre-break list
+                        let mut s = self.pre_break(q as usize);
+                        self.set_link(q as usize, s);
+                        while self.link(s as usize) != Null {
+                            s = self.link(s as usize);
+                        }
+                        self.set_pre_break(q as usize, Null);
+                        q = s;
+                    }
+                    self.set_link(q as usize, r);
+                    disc_break = true;
+                } else if self.a_type(t) == MathNode || self.a_type(t) == KernNode {
+                    self.set_width(t, 0);
+                }
+            } else {
+                q = TempHead as Pointer;
+                while self.link(q as usize) != Null {
+                    q = self.link(q as usize);
+                }
+            }
+            if !right_skip_done {
+                // 886: put the \rightskip glue after node q
+                let r = self.new_param_glue(RightSkipCode);
+                self.set_link(r as usize, self.link(q as usize));
+                self.set_link(q as usize, r);
+                q = r;
+            }
+            // 887: put the \leftskip glue at the left and detach this line
+            let r = self.link(q as usize);
+            self.set_link(q as usize, Null);
+            q = self.link(TempHead);
+            self.set_link(TempHead, r);
+            if self.glue_par(LeftSkipCode) as usize != ZeroGlue {
+                let r = self.new_param_glue(LeftSkipCode);
+                self.set_link(r as usize, q);
+                q = r;
+            }
+            // 889: call the packaging subroutine, setting just_box to the justified box
+            let (cur_width, cur_indent) = if cur_line > self.last_special_line {
+                (self.second_width, self.second_indent)
+            } else if self.par_shape_ptr() == Null {
+                (self.first_width, self.first_indent)
+            } else {
+                let p = self.par_shape_ptr() as usize + 2 * cur_line as usize;
+                (self.mem[p].sc(), self.mem[p - 1].sc())
+            };
+            self.adjust_tail = AdjustHead as Pointer;
+            self.just_box = self.hpack(q, cur_width, Exactly);
+            self.set_shift_amount(self.just_box as usize, cur_indent);
+            // 888: append the new box to the current vertical list, followed by the
+            // list of special nodes taken out of the box by the packager
+            self.append_to_vlist(self.just_box);
+            if AdjustHead as Pointer != self.adjust_tail {
+                self.set_link(self.tail() as usize, self.link(AdjustHead));
+                self.set_tail(self.adjust_tail);
+            }
+            self.adjust_tail = Null;
+            // 890: append a penalty node, if a nonzero penalty is appropriate
+            if cur_line + 1 != self.best_line {
+                let mut pen = self.int_par(InterLinePenaltyCode);
+                if cur_line == self.prev_graf() + 1 {
+                    pen += self.int_par(ClubPenaltyCode);
+                }
+                if cur_line + 2 == self.best_line {
+                    pen += final_widow_penalty;
+                }
+                if disc_break {
+                    pen += self.int_par(BrokenPenaltyCode);
+                }
+                if pen != 0 {
+                    let r = self.new_penalty(pen);
+                    self.tail_append(r);
+                }
+            }
+            cur_line += 1;
+            self.cur_p = self.next_break(self.cur_p as usize);
+            if self.cur_p != Null && !post_disc_break {
+                // 879: prune unwanted nodes at the beginning of the next line
+                let mut r = TempHead as Pointer;
+                let mut q;
+                loop {
+                    q = self.link(r as usize);
+                    if q == self.cur_break(self.cur_p as usize) || self.is_char_node(q as usize) {
+                        break;
+                    }
+                    let t = self.a_type(q as usize);
+                    if t < MathNode || (t == KernNode && self.subtype(q as usize) != Explicit) {
+                        break;
+                    }
+                    r = q; // now type(q)=glue_node, kern_node, math_node, or penalty_node
+                }
+                if r != TempHead as Pointer {
+                    self.set_link(r as usize, Null);
+                    self.flush_node_list(self.link(TempHead));
+                    self.set_link(TempHead, q);
+                }
+            }
+            if self.cur_p == Null {
+                break;
+            }
+        }
+        if cur_line != self.best_line || self.link(TempHead) != Null {
+            self.confusion("line breaking");
+        }
+        self.set_prev_graf(self.best_line - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::text_font;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn paragraphs_break_where_demerits_are_least() {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2
+\\font\\rm=text \\rm
+\\hsize=40pt \\parindent=0pt \\parfillskip=0pt plus 1fil \\tolerance=1000 \\spaceskip=3pt plus 10pt minus 1pt \\linepenalty=10 \\tracingparagraphs=1
+\\setbox0\\vbox{aaa a aa aaaa a aa aaa a\\par}
+\\end
+");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        // the first pass is skipped since \\pretolerance is zero; the best
+        // paragraph has lines of badness 1, 12 and 0
+        let start = log.find("@firstpass").expect(&log);
+        assert_eq!(log[start..].split("\n\n").next().unwrap(), concat!(
+            "@firstpass\n",
+            "@secondpass\n",
+            "[]\\rm aaa a \n",
+            "@ via @@0 b=488 p=0 d=248004\n",
+            "@@1: line 1.0 t=248004 -> @@0\n",
+            "aa \n",
+            "@ via @@0 b=1 p=0 d=121\n",
+            "@@2: line 1.2 t=121 -> @@0\n",
+            "aaaa \n",
+            "@ via @@1 b=34 p=0 d=1936\n",
+            "@@3: line 2.1 t=249940 -> @@1\n",
+            "a \n",
+            "@ via @@1 b=12 p=0 d=484\n",
+            "@ via @@2 b=172 p=0 d=33124\n",
+            "@@4: line 2.0 t=33245 -> @@2\n",
+            "aa \n",
+            "@ via @@2 b=12 p=0 d=484\n",
+            "@@5: line 2.2 t=605 -> @@2\n",
+            "aaa \n",
+            "@ via @@3 b=1 p=0 d=121\n",
+            "@ via @@4 b=172 p=0 d=33124\n",
+            "@@6: line 3.0 t=66369 -> @@4\n",
+            "a \n",
+            "@\\par via @@4 b=0 p=-10000 d=100\n",
+            "@\\par via @@5 b=0 p=-10000 d=100\n",
+            "@\\par via @@6 b=0 p=-10000 d=100\n",
+            "@@7: line 3.2- t=705 -> @@5",
+        ));
+    }
+}
@@ -0,0 +1,705 @@
+use crate::*;
+
+// 1034
+// the labels of the main loop, which appends characters, ligatures and kerns
+// to the current hlist
+#[derive(Clone, Copy)]
+enum MainLoop {
+    Wrapup, // main_loop_wrapup
+    Move, // main_loop_move
+    Move1, // main_loop_move+1
+    Move2, // main_loop_move+2
+    Lookahead, // main_loop_lookahead
+    Lookahead1, // main_loop_lookahead+1
+    LigLoop, // main_lig_loop
+    LigLoop1, // main_lig_loop+1
+    LigLoop2, // main_lig_loop+2
+    MoveLig, // main_loop_move_lig
+}
+
+impl TexState {
+    // 1030
+    // the chief executive: fetches each command and sees that it is carried out
+    pub fn main_control(&mut self) {
+        if self.equiv(EveryJobLoc as usize) != Null {
+            self.begin_token_list(self.equiv(EveryJobLoc as usize), EveryJobText);
+        }
+        let mut reswitch = false;
+        loop {
+            if !reswitch {
+                self.get_x_token(); // big_switch
+            }
+            reswitch = false;
+            // 1031: give diagnostic information, if requested
+            if self.interrupt != 0 && self.OK_to_interrupt {
+                self.back_input();
+                self.check_interrupt();
+                continue;
+            }
+            if self.int_par(TracingCommandsCode) > 0 {
+                self.show_cur_cmd_chr();
+            }
+            match (self.mode().abs(), self.cur_cmd) {
+                (Hmode, Letter) | (Hmode, OtherChar) | (Hmode, CharGiven) => reswitch = self.main_loop(),
+                (Hmode, CharNum) => {
+                    self.scan_char_num();
+                    self.cur_chr = self.cur_val as HalfWord;
+                    reswitch = self.main_loop();
+                }
+                (Hmode, NoBoundary) => {
+                    self.get_x_token();
+                    if self.cur_cmd == Letter || self.cur_cmd == OtherChar || self.cur_cmd == CharGiven
+                        || self.cur_cmd == CharNum
+                    {
+                        self.cancel_boundary = true;
+                    }
+                    reswitch = true;
+                }
+                (Hmode, Spacer) => {
+                    if self.space_factor() == 1000 {
+                        self.append_normal_space();
+                    } else {
+                        self.app_space();
+                    }
+                }
+                (Hmode, ExSpace) | (Mmode, ExSpace) => self.append_normal_space(),
+                // 1045
+                (_, Relax) | (Vmode, Spacer) | (Mmode, Spacer) | (Mmode, NoBoundary) => {}
+                (_, IgnoreSpaces) => {
+                    // 406: get the next non-blank non-call token
+                    self.get_x_non_blank();
+                    reswitch = true;
+                }
+                (Vmode, Stop) if self.its_all_over() => {
+                    return; // this is the only way out
+                }
+                // 1048: forbidden cases detected in main_control
+                (Vmode, Vmove) | (Hmode, Hmove) | (Mmode, Hmove) | (_, LastItem) | (Vmode, Vadjust)
+                | (Vmode, ItalCorr) | (Vmode, EqNo) | (Hmode, EqNo) | (_, MacParam) => self.report_illegal_case(),
+                // 1046: math-only cases in non-math modes, or vice versa
+                (Vmode, SupMark) | (Hmode, SupMark) | (Vmode, SubMark) | (Hmode, SubMark)
+                | (Vmode, MathCharNum) | (Hmode, MathCharNum) | (Vmode, MathGiven) | (Hmode, MathGiven)
+                | (Vmode, MathComp) | (Hmode, MathComp) | (Vmode, DelimNum) | (Hmode, DelimNum)
+                | (Vmode, LeftRight) | (Hmode, LeftRight) | (Vmode, Above) | (Hmode, Above)
+                | (Vmode, Radical) | (Hmode, Radical) | (Vmode, MathStyle) | (Hmode, MathStyle)
+                | (Vmode, MathChoice) | (Hmode, MathChoice) | (Vmode, Vcenter) | (Hmode, Vcenter)
+                | (Vmode, NonScript) | (Hmode, NonScript) | (Vmode, Mkern) | (Hmode, Mkern)
+                | (Vmode, LimitSwitch) | (Hmode, LimitSwitch) | (Vmode, Mskip) | (Hmode, Mskip)
+                | (Vmode, MathAccent) | (Hmode, MathAccent) | (Mmode, Endv) | (Mmode, ParEnd)
+                | (Mmode, Stop) | (Mmode, Vskip) | (Mmode, UnVbox) | (Mmode, Valign) | (Mmode, Hrule) => {
+                    self.insert_dollar_sign();
+                }
+                // 1056: cases of main_control that build boxes and lists
+                (Vmode, Hrule) | (Hmode, Vrule) | (Mmode, Vrule) => {
+                    let p = self.scan_rule_spec();
+                    self.tail_append(p);
+                    if self.mode().abs() == Vmode {
+                        self.set_prev_depth(IgnoreDepth);
+                    } else if self.mode().abs() == Hmode {
+                        self.set_space_factor(1000);
+                    }
+                }
+                // 1057
+                (Vmode, Vskip) | (Hmode, Hskip) | (Mmode, Hskip) | (Mmode, Mskip) => self.append_glue(),
+                (_, Kern) | (Mmode, Mkern) => self.append_kern(),
+                // 1063
+                (Vmode, LeftBrace) | (Hmode, LeftBrace) => self.new_save_level(SimpleGroup),
+                (_, BeginGroup) => self.new_save_level(SemiSimpleGroup),
+                (_, EndGroup) => {
+                    if self.cur_group.value() == SemiSimpleGroup {
+                        self.unsave();
+                    } else {
+                        self.off_save();
+                    }
+                }
+                // 1067
+                (_, RightBrace) => self.handle_right_brace(),
+                // 1073
+                (Vmode, Hmove) | (Hmode, Vmove) | (Mmode, Vmove) => {
+                    let t = self.cur_chr;
+                    self.scan_normal_dimen();
+                    if t == 0 {
+                        self.scan_box(self.cur_val);
+                    } else {
+                        self.scan_box(-self.cur_val);
+                    }
+                }
+                (_, LeaderShip) => self.scan_box(LeaderFlag - ALeaders as i32 + self.cur_chr as i32),
+                (_, MakeBox) => self.begin_box(0),
+                // 1090
+                (Vmode, StartPar) => self.new_graf(self.cur_chr > 0),
+                (Vmode, Letter) | (Vmode, OtherChar) | (Vmode, CharNum) | (Vmode, CharGiven)
+                | (Vmode, MathShift) | (Vmode, UnHbox) | (Vmode, Vrule) | (Vmode, Accent)
+                | (Vmode, Discretionary) | (Vmode, Hskip) | (Vmode, Valign) | (Vmode, ExSpace)
+                | (Vmode, NoBoundary) => {
+                    self.back_input();
+                    self.new_graf(true);
+                }
+                // 1092
+                (Hmode, StartPar) | (Mmode, StartPar) => self.indent_in_hmode(),
+                // 1094
+                (Vmode, ParEnd) => {
+                    self.normal_paragraph();
+                    if self.mode() > 0 {
+                        self.build_page();
+                    }
+                }
+                (Hmode, ParEnd) => {
+                    if self.align_state < 0 {
+                        self.off_save(); // this tries to recover from an alignment that didn't end properly
+                    }
+                    self.end_graf(); // this takes us to the enclosing mode, if mode>0
+                    if self.mode() == Vmode {
+                        self.build_page();
+                    }
+                }
+                (Hmode, Stop) | (Hmode, Vskip) | (Hmode, Hrule) | (Hmode, UnVbox) | (Hmode, Halign) => {
+                    self.head_for_vmode();
+                }
+                // 1097
+                (_, Insert) | (Hmode, Vadjust) | (Mmode, Vadjust) => self.begin_insert_or_adjust(),
+                (_, Mark) => self.make_mark(),
+                // 1102
+                (_, BreakPenalty) => self.append_penalty(),
+                // 1104
+                (_, RemoveItem) => self.delete_last(),
+                // 1109
+                (Vmode, UnVbox) | (Hmode, UnHbox) | (Mmode, UnHbox) => self.unpackage(),
+                // 1112
+                (Hmode, ItalCorr) => self.append_italic_correction(),
+                (Mmode, ItalCorr) => {
+                    let k = self.new_kern(0);
+                    self.tail_append(k);
+                }
+                // 1116
+                (Hmode, Discretionary) | (Mmode, Discretionary) => self.append_discretionary(),
+                // 1122
+                (Hmode, Accent) => self.make_accent(),
+                // 1126
+                (_, CarRet) | (_, TabMark) => self.align_error(),
+                (_, NoAlign) => self.no_align_error(),
+                (_, Omit) => self.omit_error(),
+                // 1130
+                (Vmode, Halign) | (Hmode, Valign) => self.init_align(),
+                (Mmode, Halign) if self.privileged() => {
+                    if self.cur_group.value() == MathShiftGroup {
+                        self.init_align();
+                    } else {
+                        self.off_save();
+                    }
+                }
+                (Vmode, Endv) | (Hmode, Endv) => self.do_endv(),
+                // 1134
+                (_, EndCsName) => self.cs_error(),
+                // 1137
+                (Hmode, MathShift) => self.init_math(),
+                // 1140
+                (Mmode, EqNo) if self.privileged() => {
+                    if self.cur_group.value() == MathShiftGroup {
+                        self.start_eq_no();
+                    } else {
+                        self.off_save();
+                    }
+                }
+                // 1150
+                (Mmode, LeftBrace) => {
+                    let p = self.new_noad();
+                    self.tail_append(p);
+                    self.back_input();
+                    self.scan_math(TexState::nucleus(self.tail() as usize));
+                }
+                // 1154
+                (Mmode, Letter) | (Mmode, OtherChar) | (Mmode, CharGiven) => {
+                    self.set_math_char(self.math_code(self.cur_chr) as i32);
+                }
+                (Mmode, CharNum) => {
+                    self.scan_char_num();
+                    self.cur_chr = self.cur_val as HalfWord;
+                    self.set_math_char(self.math_code(self.cur_chr) as i32);
+                }
+                (Mmode, MathCharNum) => {
+                    self.scan_fifteen_bit_int();
+                    self.set_math_char(self.cur_val);
+                }
+                (Mmode, MathGiven) => self.set_math_char(self.cur_chr as i32),
+                (Mmode, DelimNum) => {
+                    self.scan_twenty_seven_bit_int();
+                    self.set_math_char(self.cur_val / 0o10000);
+                }
+                // 1158
+                (Mmode, MathComp) => {
+                    let p = self.new_noad();
+                    self.tail_append(p);
+                    self.set_type(self.tail() as usize, self.cur_chr as QuarterWord);
+                    self.scan_math(TexState::nucleus(self.tail() as usize));
+                }
+                (Mmode, LimitSwitch) => self.math_limit_switch(),
+                // 1162
+                (Mmode, Radical) => self.math_radical(),
+                // 1164
+                (Mmode, Accent) | (Mmode, MathAccent) => self.math_ac(),
+                // 1167
+                (Mmode, Vcenter) => {
+                    self.scan_spec(VcenterGroup, false);
+                    self.normal_paragraph();
+                    self.push_nest();
+                    self.set_mode(-Vmode);
+                    self.set_prev_depth(IgnoreDepth);
+                    if self.equiv(EveryVboxLoc as usize) != Null {
+                        self.begin_token_list(self.equiv(EveryVboxLoc as usize), EveryVboxText);
+                    }
+                }
+                // 1171
+                (Mmode, MathStyle) => {
+                    let p = self.new_style(self.cur_chr as SmallNumber);
+                    self.tail_append(p);
+                }
+                (Mmode, NonScript) => {
+                    let p = self.new_glue(ZeroGlue as Pointer);
+                    self.tail_append(p);
+                    self.set_subtype(self.tail() as usize, CondMathGlue);
+                }
+                (Mmode, MathChoice) => self.append_choices(),
+                // 1175
+                (Mmode, SubMark) | (Mmode, SupMark) => self.sub_sup(),
+                // 1180
+                (Mmode, Above) => self.math_fraction(),
+                // 1190
+                (Mmode, LeftRight) => self.math_left_right(),
+                // 1193
+                (Mmode, MathShift) => {
+                    if self.cur_group.value() == MathShiftGroup {
+                        self.after_math();
+                    } else {
+                        self.off_save();
+                    }
+                }
+                // 1210: cases of main_control that don't depend on mode
+                (_, ToksRegister) | (_, AssignToks) | (_, AssignInt) | (_, AssignDimen) | (_, AssignGlue)
+                | (_, AssignMuGlue) | (_, AssignFontDimen) | (_, AssignFontInt) | (_, SetAux)
+                | (_, SetPrevGraf) | (_, SetPageDimen) | (_, SetPageInt) | (_, SetBoxDimen) | (_, SetShape)
+                | (_, DefCode) | (_, DefFamily) | (_, SetFont) | (_, DefFont) | (_, Register) | (_, Advance)
+                | (_, Multiply) | (_, Divide) | (_, Prefix) | (_, Let) | (_, ShorthandDef) | (_, ReadToCs)
+                | (_, Def) | (_, SetBox) | (_, HyphData) | (_, SetInteraction) => self.prefixed_command(),
+                // 1268
+                (_, AfterAssignment) => {
+                    self.get_token();
+                    self.after_token = self.cur_tok;
+                }
+                // 1271
+                (_, AfterGroup) => {
+                    self.get_token();
+                    self.save_for_after(self.cur_tok);
+                }
+                // 1274
+                (_, InStream) => self.open_or_close_in(),
+                // 1276
+                (_, Message) => self.issue_message(),
+                // 1285
+                (_, CaseShift) => self.shift_case(),
+                // 1290
+                (_, Xray) => self.show_whatever(),
+                // 1347: cases of main_control that are for extensions to TeX
+                (_, Extension) => self.do_extension(),
+                _ => {}
+            }
+        }
+    }
+
+    // 1034
+    // appends the character cur_chr and the following characters (if any) to the
+    // current hlist in the current font; true means the command that ended the
+    // run of characters is in cur_cmd and cur_chr, ready for reswitch
+    pub fn main_loop(&mut self) -> bool {
+        self.adjust_space_factor();
+        let main_f = self.cur_font() as InternalFontNumber; // the current font
+        let mut bchar = self.font_bchar[main_f]; // right boundary character of current font, or non_char
+        let false_bchar = self.font_false_bchar[main_f]; // nonexistent character matching bchar, or non_char
+        if self.mode() > 0 && self.int_par(LanguageCode) != self.clang() as i32 {
+            self.fix_language();
+        }
+        self.lig_stack = self.get_avail();
+        self.set_font(self.lig_stack as usize, main_f as u8);
+        self.cur_l = self.cur_chr;
+        self.set_character(self.lig_stack as usize, self.cur_l as u8);
+        self.cur_q = self.tail();
+        let mut main_i = self.null_character; // character information bytes for cur_l
+        let mut main_j = self.null_character; // ligature/kern command
+        let mut main_k; // index into font_info
+        if self.cancel_boundary {
+            self.cancel_boundary = false;
+            main_k = NonAddress;
+        } else {
+            main_k = self.bchar_label[main_f];
+        }
+        let mut label = if main_k == NonAddress {
+            MainLoop::Move2 // no left boundary processing
+        } else {
+            self.cur_r = self.cur_l;
+            self.cur_l = NonChar;
+            MainLoop::LigLoop1 // compute ligature/kern program for left boundary
+        };
+        loop {
+            label = match label {
+                MainLoop::Wrapup => {
+                    // 1035: make a ligature node, if ligature_present; insert a null
+                    // discretionary, if appropriate
+                    self.wrapup(main_f, self.rt_hit);
+                    MainLoop::Move
+                }
+                MainLoop::Move => {
+                    // 1036: if the cursor is immediately followed by the right boundary,
+                    // goto reswitch; if it's followed by an invalid character, goto
+                    // big_switch; otherwise move the cursor one step to the right and
+                    // goto main_lig_loop
+                    if self.lig_stack == Null {
+                        return true;
+                    }
+                    self.cur_q = self.tail();
+                    self.cur_l = self.character(self.lig_stack as usize) as HalfWord;
+                    MainLoop::Move1
+                }
+                MainLoop::Move1 => {
+                    if self.is_char_node(self.lig_stack as usize) {
+                        MainLoop::Move2
+                    } else {
+                        MainLoop::MoveLig
+                    }
+                }
+                MainLoop::Move2 => {
+                    if self.cur_chr < self.font_bc[main_f] as HalfWord || self.cur_chr > self.font_ec[main_f] as HalfWord {
+                        self.char_warning(main_f, self.cur_chr as u8);
+                        self.free_avail(self.lig_stack);
+                        return false;
+                    }
+                    main_i = self.char_info(main_f, self.cur_l as u8);
+                    if !main_i.char_exists() {
+                        self.char_warning(main_f, self.cur_chr as u8);
+                        self.free_avail(self.lig_stack);
+                        return false;
+                    }
+                    self.set_link(self.tail() as usize, self.lig_stack);
+                    self.set_tail(self.lig_stack); // main_loop_lookahead is next
+                    MainLoop::Lookahead
+                }
+                MainLoop::MoveLig => {
+                    // 1037: move the cursor past a pseudo-ligature, then goto
+                    // main_loop_lookahead or main_lig_loop
+                    let main_p = self.lig_ptr(self.lig_stack as usize);
+                    if main_p > Null {
+                        self.tail_append(main_p); // append a single character
+                    }
+                    self.temp_ptr = self.lig_stack;
+                    self.lig_stack = self.link(self.temp_ptr as usize);
+                    self.free_node(self.temp_ptr, SmallNodeSize);
+                    main_i = self.char_info(main_f, self.cur_l as u8);
+                    self.ligature_present = true;
+                    if self.lig_stack == Null {
+                        if main_p > Null {
+                            MainLoop::Lookahead
+                        } else {
+                            self.cur_r = bchar;
+                            MainLoop::LigLoop
+                        }
+                    } else {
+                        self.cur_r = self.character(self.lig_stack as usize) as HalfWord;
+                        MainLoop::LigLoop
+                    }
+                }
+                MainLoop::Lookahead => {
+                    // 1038: look ahead for another character, or leave lig_stack empty
+                    // if there's none there
+                    self.get_next(); // set only cur_cmd and cur_chr, for speed
+                    if self.cur_cmd == Letter || self.cur_cmd == OtherChar || self.cur_cmd == CharGiven {
+                        MainLoop::Lookahead1
+                    } else {
+                        self.x_token(); // now expand and set cur_cmd, cur_chr, cur_tok
+                        if self.cur_cmd == Letter || self.cur_cmd == OtherChar || self.cur_cmd == CharGiven {
+                            MainLoop::Lookahead1
+                        } else if self.cur_cmd == CharNum {
+                            self.scan_char_num();
+                            self.cur_chr = self.cur_val as HalfWord;
+                            MainLoop::Lookahead1
+                        } else {
+                            if self.cur_cmd == NoBoundary {
+                                bchar = NonChar;
+                            }
+                            self.cur_r = bchar;
+                            self.lig_stack = Null;
+                            MainLoop::LigLoop
+                        }
+                    }
+                }
+                MainLoop::Lookahead1 => {
+                    self.adjust_space_factor();
+                    self.lig_stack = self.get_avail();
+                    self.set_font(self.lig_stack as usize, main_f as u8);
+                    self.cur_r = self.cur_chr;
+                    self.set_character(self.lig_stack as usize, self.cur_r as u8);
+                    if self.cur_r == false_bchar {
+                        self.cur_r = NonChar; // this prevents spurious ligatures
+                    }
+                    MainLoop::LigLoop
+                }
+                MainLoop::LigLoop => {
+                    // 1039: if there's a ligature/kern command relevant to cur_l and
+                    // cur_r, adjust the text appropriately; exit to main_loop_wrapup
+                    if main_i.char_tag() != LigTag || self.cur_r == NonChar {
+                        MainLoop::Wrapup
+                    } else {
+                        main_k = self.lig_kern_start(main_f, main_i);
+                        main_j = self.font_info[main_k as usize].qqqq();
+                        if main_j.skip_byte() <= StopFlag {
+                            MainLoop::LigLoop2
+                        } else {
+                            main_k = self.lig_kern_restart(main_f, main_j);
+                            MainLoop::LigLoop1
+                        }
+                    }
+                }
+                MainLoop::LigLoop1 => {
+                    main_j = self.font_info[main_k as usize].qqqq();
+                    MainLoop::LigLoop2
+                }
+                MainLoop::LigLoop2 => {
+                    if main_j.next_char() as HalfWord == self.cur_r && main_j.skip_byte() <= StopFlag {
+                        // 1040: do ligature or kern command, returning to main_lig_loop
+                        // or main_loop_wrapup or main_loop_move
+                        if main_j.op_byte() >= KernFlag {
+                            self.wrapup(main_f, self.rt_hit);
+                            let k = self.new_kern(self.char_kern(main_f, main_j));
+                            self.tail_append(k);
+                            MainLoop::Move
+                        } else {
+                            if self.cur_l == NonChar {
+                                self.lft_hit = true;
+                            } else if self.lig_stack == Null {
+                                self.rt_hit = true;
+                            }
+                            self.check_interrupt(); // allow a way out in case there's an infinite ligature loop
+                            let mut next = None;
+                            match main_j.op_byte() {
+                                1 | 5 => {
+                                    // =:|, =:|>
+                                    self.cur_l = main_j.rem_byte() as HalfWord;
+                                    main_i = self.char_info(main_f, self.cur_l as u8);
+                                    self.ligature_present = true;
+                                }
+                                2 | 6 => {
+                                    // |=:, |=:>
+                                    self.cur_r = main_j.rem_byte() as HalfWord;
+                                    if self.lig_stack == Null {
+                                        // right boundary character is being consumed
+                                        self.lig_stack = self.new_lig_item(self.cur_r as QuarterWord);
+                                        bchar = NonChar;
+                                    } else if self.is_char_node(self.lig_stack as usize) {
+                                        // link(lig_stack)=null
+                                        let main_p = self.lig_stack;
+                                        self.lig_stack = self.new_lig_item(self.cur_r as QuarterWord);
+                                        self.set_lig_ptr(self.lig_stack as usize, main_p);
+                                    } else {
+                                        self.set_character(self.lig_stack as usize, self.cur_r as u8);
+                                    }
+                                }
+                                3 => {
+                                    // |=:|
+                                    self.cur_r = main_j.rem_byte() as HalfWord;
+                                    let main_p = self.lig_stack;
+                                    self.lig_stack = self.new_lig_item(self.cur_r as QuarterWord);
+                                    self.set_link(self.lig_stack as usize, main_p);
+                                }
+                                7 | 11 => {
+                                    // |=:|>, |=:|>>
+                                    self.wrapup(main_f, false);
+                                    self.cur_q = self.tail();
+                                    self.cur_l = main_j.rem_byte() as HalfWord;
+                                    main_i = self.char_info(main_f, self.cur_l as u8);
+                                    self.ligature_present = true;
+                                }
+                                _ => {
+                                    // =:
+                                    self.cur_l = main_j.rem_byte() as HalfWord;
+                                    self.ligature_present = true;
+                                    next = Some(if self.lig_stack == Null { MainLoop::Wrapup } else { MainLoop::Move1 });
+                                }
+                            }
+                            match next {
+                                Some(l) => l,
+                                None if main_j.op_byte() > 4 && main_j.op_byte() != 7 => MainLoop::Wrapup,
+                                None if self.cur_l < NonChar => MainLoop::LigLoop,
+                                None => {
+                                    main_k = self.bchar_label[main_f];
+                                    MainLoop::LigLoop1
+                                }
+                            }
+                        }
+                    } else if main_j.skip_byte() == 0 {
+                        main_k += 1;
+                        MainLoop::LigLoop1
+                    } else if main_j.skip_byte() >= StopFlag {
+                        MainLoop::Wrapup
+                    } else {
+                        main_k += main_j.skip_byte() as i32 + 1;
+                        MainLoop::LigLoop1
+                    }
+                }
+            }
+        }
+    }
+
+    // 1034
+    // updates the space factor for the character cur_chr
+    pub fn adjust_space_factor(&mut self) {
+        let main_s = self.sf_code(self.cur_chr); // space factor value
+        if main_s == 1000 {
+            self.set_space_factor(1000);
+        } else if main_s < 1000 {
+            if main_s > 0 {
+                self.set_space_factor(main_s);
+            }
+        } else if self.space_factor() < 1000 {
+            self.set_space_factor(1000);
+        } else {
+            self.set_space_factor(main_s);
+        }
+    }
+
+    // 1035
+    // packages the characters after cur_q into a ligature; rt says whether a
+    // right boundary was hit
+    pub fn pack_lig(&mut self, main_f: InternalFontNumber, rt: bool) {
+        let main_p = self.new_ligature(main_f as QuarterWord, self.cur_l as QuarterWord, self.link(self.cur_q as usize));
+        if self.lft_hit {
+            self.set_subtype(main_p as usize, 2);
+            self.lft_hit = false;
+        }
+        if rt && self.lig_stack == Null {
+            self.set_subtype(main_p as usize, self.subtype(main_p as usize) + 1);
+            self.rt_hit = false;
+        }
+        self.set_link(self.cur_q as usize, main_p);
+        self.set_tail(main_p);
+        self.ligature_present = false;
+    }
+
+    // finishes the character or ligature at the cursor, adding a null
+    // discretionary after an explicit hyphen
+    pub fn wrapup(&mut self, main_f: InternalFontNumber, rt: bool) {
+        if self.cur_l < NonChar {
+            if self.link(self.cur_q as usize) > Null
+                && self.character(self.tail() as usize) as i32 == self.hyphen_char[main_f]
+            {
+                self.ins_disc = true;
+            }
+            if self.ligature_present {
+                self.pack_lig(main_f, rt);
+            }
+            if self.ins_disc {
+                self.ins_disc = false;
+                if self.mode() > 0 {
+                    let d = self.new_disc();
+                    self.tail_append(d);
+                }
+            }
+        }
+    }
+
+    // 1042
+    // the glue specification for text spaces in the current font
+    pub fn font_space_glue(&mut self) -> Pointer {
+        let f = self.cur_font() as InternalFontNumber;
+        let mut main_p = self.font_glue[f];
+        if main_p == Null {
+            main_p = self.new_spec(ZeroGlue as Pointer);
+            self.set_width(main_p as usize, self.space(f));
+            self.set_stretch(main_p as usize, self.space_stretch(f));
+            self.set_shrink(main_p as usize, self.space_shrink(f));
+            self.font_glue[f] = main_p;
+        }
+        main_p
+    }
+
+    // 1041
+    // appends a normal inter-word space to the current list
+    pub fn append_normal_space(&mut self) {
+        if self.glue_par(SpaceSkipCode) == ZeroGlue as Pointer {
+            let main_p = self.font_space_glue();
+            self.temp_ptr = self.new_glue(main_p);
+        } else {
+            self.temp_ptr = self.new_param_glue(SpaceSkipCode);
+        }
+        self.tail_append(self.temp_ptr);
+    }
+
+    // 1043
+    // handles spaces when space_factor<>1000
+    pub fn app_space(&mut self) {
+        let q = if self.space_factor() >= 2000 && self.glue_par(XspaceSkipCode) != ZeroGlue as Pointer {
+            self.new_param_glue(XspaceSkipCode)
+        } else {
+            let main_p = if self.glue_par(SpaceSkipCode) != ZeroGlue as Pointer {
+                self.glue_par(SpaceSkipCode)
+            } else {
+                self.font_space_glue()
+            };
+            let main_p = self.new_spec(main_p);
+            // 1044: modify the glue specification in main_p according to the space factor
+            let m = main_p as usize;
+            if self.space_factor() >= 2000 {
+                self.set_width(m, self.width(m) + self.extra_space(self.cur_font() as InternalFontNumber));
+            }
+            let s = self.xn_over_d(self.stretch(m), self.space_factor() as i32, 1000);
+            self.set_stretch(m, s);
+            let s = self.xn_over_d(self.shrink(m), 1000, self.space_factor() as i32);
+            self.set_shrink(m, s);
+            let q = self.new_glue(main_p);
+            self.set_glue_ref_count(main_p as usize, Null);
+            q
+        };
+        self.tail_append(q);
+    }
+
+    // 1047
+    // inserts a math shift before a math-only command, or after one that can't
+    // appear in math mode
+    pub fn insert_dollar_sign(&mut self) {
+        self.back_input();
+        self.cur_tok = (math_shift_token + '$' as i32) as HalfWord;
+        self.print_err("Missing $ inserted");
+        self.help(&["I've inserted a begin-math/end-math symbol since I think",
+            "you left one out. Proceed, with fingers crossed."]);
+        self.ins_error();
+    }
+
+    // 1051
+    // false, after complaining, if the current mode is an inner one
+    pub fn privileged(&mut self) -> bool {
+        if self.mode() > 0 {
+            true
+        } else {
+            self.report_illegal_case();
+            false
+        }
+    }
+
+    // 1054
+    // tells whether \end or \dump may end the job now; otherwise ejects the
+    // residual material first
+    pub fn its_all_over(&mut self) -> bool {
+        if self.privileged() {
+            if PageHead as Pointer == self.page_tail && self.head() == self.tail() && self.dead_cycles == 0 {
+                return true;
+            }
+            self.back_input(); // we will try to end again after ejecting residual material
+            let b = self.new_null_box();
+            self.tail_append(b);
+            self.set_width(self.tail() as usize, self.dimen_par(HsizeCode));
+            let g = self.new_glue(FillGlue as Pointer);
+            self.tail_append(g);
+            let p = self.new_penalty(-0o10000000000);
+            self.tail_append(p);
+            self.build_page(); // append \hbox to \hsize{}\vfill\penalty-'10000000000
+        }
+        false
+    }
+}
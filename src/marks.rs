@@ -0,0 +1,217 @@
+use crate::*;
+
+// 384
+pub const MarksCode: usize = 5; // add this for \topmarks etc.
+
+// 1101
+pub const MaxMarkClass: i32 = 32767; // the largest class allowed by \marks
+
+impl TexState {
+    // 141
+    // the mark class of a mark node, zero for \mark
+    pub fn mark_class(&self, p: usize) -> HalfWord {
+        self.info(p + 1)
+    }
+
+    pub fn set_mark_class(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 1, value);
+    }
+
+    // 382
+    // the current mark of type t in class c; class zero lives in cur_mark
+    pub fn class_mark(&self, c: HalfWord, t: usize) -> Pointer {
+        if c == 0 {
+            self.cur_mark[t]
+        } else {
+            match self.mark_classes.get(&c) {
+                Some(m) => m[t],
+                None => Null,
+            }
+        }
+    }
+
+    pub fn set_class_mark(&mut self, c: HalfWord, t: usize, p: Pointer) {
+        if c == 0 {
+            self.cur_mark[t] = p;
+        } else {
+            self.mark_classes.entry(c).or_insert([Null; 5])[t] = p;
+        }
+    }
+
+    // the classes that currently hold marks, class zero always first
+    fn mark_class_list(&self) -> Vec<HalfWord> {
+        let mut l: Vec<HalfWord> = self.mark_classes.keys().cloned().collect();
+        l.sort();
+        l.insert(0, 0);
+        l
+    }
+
+    // 1101
+    // scans the class number that follows \marks, \topmarks, etc.
+    pub fn scan_mark_class(&mut self) -> HalfWord {
+        self.scan_int();
+        if self.cur_val < 0 || self.cur_val > MaxMarkClass {
+            self.print_err("Bad mark class");
+            self.help(&["A mark class must be between 0 and 32767.",
+                "I changed this one to zero."]);
+            self.int_error(self.cur_val);
+            self.cur_val = 0;
+        }
+        self.cur_val as HalfWord
+    }
+
+    // appends a mark node for \mark or \marks to the current list
+    pub fn make_mark(&mut self) {
+        let c = if self.cur_chr == 0 { 0 } else { self.scan_mark_class() };
+        self.scan_toks(false, true);
+        let p = self.get_node(SmallNodeSize);
+        self.set_mark_class(p as usize, c);
+        self.set_type(p as usize, MarkNode);
+        self.set_subtype(p as usize, 0); // the subtype is not used
+        self.set_mark_ptr(p as usize, self.def_ref);
+        self.tail_append(p);
+    }
+
+    // 386
+    // insert the token list for the current mark, as requested by \topmark etc.
+    pub fn insert_mark_tokens(&mut self) {
+        let t = self.cur_chr as usize % MarksCode;
+        let c = if self.cur_chr as usize >= MarksCode { self.scan_mark_class() } else { 0 };
+        let p = self.class_mark(c, t);
+        if p != Null {
+            self.begin_token_list(p, MarkText);
+        }
+    }
+
+    // 977
+    // discard the split marks of every class before a new \vsplit
+    pub fn clear_split_marks(&mut self) {
+        for c in self.mark_class_list() {
+            let p = self.class_mark(c, split_first_mark_code);
+            if p != Null {
+                self.delete_token_ref(p);
+                self.set_class_mark(c, split_first_mark_code, Null);
+                self.delete_token_ref(self.class_mark(c, split_bot_mark_code));
+                self.set_class_mark(c, split_bot_mark_code, Null);
+            }
+        }
+    }
+
+    // 979
+    // update the split marks for mark node p
+    pub fn update_split_marks(&mut self, p: Pointer) {
+        let c = self.mark_class(p as usize);
+        let m = self.mark_ptr(p as usize);
+        if self.class_mark(c, split_first_mark_code) == Null {
+            self.set_class_mark(c, split_first_mark_code, m);
+            self.set_class_mark(c, split_bot_mark_code, m);
+            self.set_token_ref_count(m as usize, self.token_ref_count(m as usize) + 2);
+        } else {
+            self.delete_token_ref(self.class_mark(c, split_bot_mark_code));
+            self.set_class_mark(c, split_bot_mark_code, m);
+            self.add_token_ref(m);
+        }
+    }
+
+    // 1012
+    // the previous bot marks become the top marks as a new page is fired up
+    pub fn start_page_marks(&mut self) {
+        for c in self.mark_class_list() {
+            let b = self.class_mark(c, bot_mark_code);
+            if b != Null {
+                let t = self.class_mark(c, top_mark_code);
+                if t != Null {
+                    self.delete_token_ref(t);
+                }
+                self.set_class_mark(c, top_mark_code, b);
+                self.add_token_ref(b);
+                let f = self.class_mark(c, first_mark_code);
+                if f != Null {
+                    self.delete_token_ref(f);
+                }
+                self.set_class_mark(c, first_mark_code, Null);
+            }
+        }
+    }
+
+    // 1014
+    // update the values of first_mark and bot_mark for mark node p
+    pub fn update_page_marks(&mut self, p: Pointer) {
+        let c = self.mark_class(p as usize);
+        let m = self.mark_ptr(p as usize);
+        if self.class_mark(c, first_mark_code) == Null {
+            self.set_class_mark(c, first_mark_code, m);
+            self.add_token_ref(m);
+        }
+        let b = self.class_mark(c, bot_mark_code);
+        if b != Null {
+            self.delete_token_ref(b);
+        }
+        self.set_class_mark(c, bot_mark_code, m);
+        self.add_token_ref(m);
+    }
+
+    // 1012
+    // a page without marks of its own inherits the top mark as its first mark
+    pub fn finish_page_marks(&mut self) {
+        for c in self.mark_class_list() {
+            let t = self.class_mark(c, top_mark_code);
+            if t != Null && self.class_mark(c, first_mark_code) == Null {
+                self.set_class_mark(c, first_mark_code, t);
+                self.add_token_ref(t);
+            }
+        }
+    }
+
+    // 1335
+    // flushes the marks of every class before the format is dumped, as e-TeX's
+    // destroy_marks does; the format keeps none of them
+    pub fn destroy_marks(&mut self) {
+        for c in self.mark_class_list() {
+            for t in top_mark_code..=split_bot_mark_code {
+                let p = self.class_mark(c, t);
+                if p != Null {
+                    self.delete_token_ref(p);
+                    self.set_class_mark(c, t, Null);
+                }
+            }
+        }
+        self.mark_classes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    fn log_of(body: &str) -> String {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", format!("\\catcode`\\{{=1 \\catcode`\\}}=2 \\scrollmode\n{}\n\\end\n", body));
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        String::from_utf8_lossy(&outputs["doc.log"]).into_owned()
+    }
+
+    #[test]
+    fn marks_of_each_class_follow_the_pages() {
+        let log = log_of("\\vsize=100pt \\maxdepth=0pt
+\\output={\\message{[\\topmark|\\firstmark|\\botmark|\\topmarks1|\\firstmarks1|\\botmarks1]}\\shipout\\box255}
+\\hrule\\mark{a}\\marks1{x}\\hrule\\mark{b}\\marks1{y}\\hrule\\marks1{z}\\penalty-10000
+\\hrule\\mark{c}\\hrule\\penalty-10000
+\\hrule\\penalty-10000");
+        // the second page has no marks of class 1, so its first and bottom
+        // marks are the top mark, which is the last mark of the first page
+        assert!(log.contains("[|a|b||x|z] [0] [b|c|c|z|z|z] [0] [c|c|c|z|z|z] [0]"), "{}", log);
+    }
+
+    #[test]
+    fn vsplit_finds_the_split_marks() {
+        let log = log_of("\\setbox0\\vbox{\\hrule height 10pt\\marks2{p}\\mark{m}\\penalty0\\hrule height 10pt\\marks2{q}\\penalty0\\hrule height 10pt\\marks2{r}}
+\\setbox1\\vsplit0 to 20pt
+\\message{[\\splitfirstmark|\\splitbotmark|\\splitfirstmarks2|\\splitbotmarks2]}
+\\setbox1\\vsplit0 to 20pt
+\\message{[\\splitfirstmark|\\splitbotmark|\\splitfirstmarks2|\\splitbotmarks2]}");
+        // the second piece has no \\mark, so its split marks of class 0 are empty
+        assert!(log.contains("[m|m|p|q]"), "{}", log);
+        assert!(log.contains("[||r|r]"), "{}", log);
+    }
+}
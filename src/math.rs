@@ -0,0 +1,1918 @@
+use crate::*;
+use std::rc::Rc;
+
+// 681
+pub const NoadSize: usize = 4; // number of words in a normal noad
+pub const MathChar: HalfWord = 1; // math_type when the attribute is simple
+pub const SubBox: HalfWord = 2; // math_type when the attribute is a box
+pub const SubMlist: HalfWord = 3; // math_type when the attribute is a formula
+pub const MathTextChar: HalfWord = 4; // math_type when italic correction is dubious
+
+// 682
+pub const OrdNoad: QuarterWord = UnsetNode + 3; // type of a noad classified Ord
+pub const OpNoad: QuarterWord = OrdNoad + 1; // type of a noad classified Op
+pub const BinNoad: QuarterWord = OrdNoad + 2; // type of a noad classified Bin
+pub const RelNoad: QuarterWord = OrdNoad + 3; // type of a noad classified Rel
+pub const OpenNoad: QuarterWord = OrdNoad + 4; // type of a noad classified Open
+pub const CloseNoad: QuarterWord = OrdNoad + 5; // type of a noad classified Close
+pub const PunctNoad: QuarterWord = OrdNoad + 6; // type of a noad classified Punct
+pub const InnerNoad: QuarterWord = OrdNoad + 7; // type of a noad classified Inner
+pub const Limits: QuarterWord = 1; // subtype of op_noad whose scripts are to be above, below
+pub const NoLimits: QuarterWord = 2; // subtype of op_noad whose scripts are to be normal
+
+// 683
+pub const RadicalNoad: QuarterWord = InnerNoad + 1; // type of a noad for square roots
+pub const RadicalNoadSize: usize = 5; // number of mem words in a radical noad
+pub const FractionNoad: QuarterWord = RadicalNoad + 1; // type of a noad for generalized fractions
+pub const FractionNoadSize: usize = 6; // number of mem words in a fraction noad
+pub const DefaultCode: Scaled = 0o10000000000; // denotes default_rule_thickness
+
+// 687
+pub const UnderNoad: QuarterWord = FractionNoad + 1; // type of a noad for underlining
+pub const OverNoad: QuarterWord = UnderNoad + 1; // type of a noad for overlining
+pub const AccentNoad: QuarterWord = OverNoad + 1; // type of a noad for accented subformulas
+pub const AccentNoadSize: usize = 5; // number of mem words in an accent noad
+pub const VcenterNoad: QuarterWord = AccentNoad + 1; // type of a noad for \vcenter
+pub const LeftNoad: QuarterWord = VcenterNoad + 1; // type of a noad for \left
+pub const RightNoad: QuarterWord = LeftNoad + 1; // type of a noad for \right
+
+// 688
+pub const StyleNode: QuarterWord = UnsetNode + 1; // type of a style node
+pub const StyleNodeSize: usize = 3; // number of words in a style node
+pub const DisplayStyle: SmallNumber = 0; // subtype for \displaystyle
+pub const TextStyle: SmallNumber = 2; // subtype for \textstyle
+pub const ScriptStyle: SmallNumber = 4; // subtype for \scriptstyle
+pub const ScriptScriptStyle: SmallNumber = 6; // subtype for \scriptscriptstyle
+pub const Cramped: SmallNumber = 1; // add this to an uncramped style if you want to cramp it
+
+// 689
+pub const ChoiceNode: QuarterWord = UnsetNode + 2; // type of a choice node
+
+// 699
+pub const TextSize: SmallNumber = 0; // size code for the largest size in a family
+pub const ScriptSize: SmallNumber = 16; // size code for the medium size in a family
+pub const ScriptScriptSize: SmallNumber = 32; // size code for the smallest size in a family
+
+// 764
+// the spacing between adjacent atoms, indexed by the types of the left and
+// right atoms; 0 means no space, 1 a conditional thin space, 2 a thin space,
+// 3 a conditional medium space, 4 a conditional thick space, and * a
+// combination that can't occur
+const MathSpacing: &[u8; 64] = b"02340001\
+                                 22*40001\
+                                 33**3**3\
+                                 44*04004\
+                                 00*00000\
+                                 02340001\
+                                 11*11111\
+                                 12341011";
+
+// 727
+// how far the first pass of mlist_to_hlist has got with a node or noad
+#[derive(Clone, Copy, PartialEq)]
+enum MlistStep {
+    Convert, // the nucleus and scripts are still to be turned into an hlist
+    CheckDimensions, // new_hlist(q) is ready and contributes to max_h and max_d
+    DoneWithNoad,
+    DoneWithNode,
+}
+
+// what is known about a font's MATH table
+#[derive(Clone, Default)]
+pub enum MathStatus {
+    #[default]
+    Unchecked, // not looked for yet
+    Absent, // the font isn't an OpenType math font, so its parameters and kerns are used
+    Present(Rc<OpenTypeMath>),
+}
+
+// the MATH table constants standing in for the parameters of family 2 fonts
+fn mathsy_constant(n: i32) -> Option<usize> {
+    match n {
+        8 => Some(MathFractionNumeratorDisplayStyleShiftUp), // num1
+        9 => Some(MathFractionNumeratorShiftUp), // num2
+        10 => Some(MathStackTopShiftUp), // num3
+        11 => Some(MathFractionDenominatorDisplayStyleShiftDown), // denom1
+        12 => Some(MathFractionDenominatorShiftDown), // denom2
+        13 | 14 => Some(MathSuperscriptShiftUp), // sup1, sup2
+        15 => Some(MathSuperscriptShiftUpCramped), // sup3
+        16 | 17 => Some(MathSubscriptShiftDown), // sub1, sub2
+        18 => Some(MathSuperscriptBaselineDropMax), // sup_drop
+        19 => Some(MathSubscriptBaselineDropMin), // sub_drop
+        22 => Some(MathAxisHeight), // axis_height
+        _ => None,
+    }
+}
+
+// the MATH table constants standing in for the parameters of family 3 fonts
+fn mathex_constant(n: i32) -> Option<usize> {
+    match n {
+        8 => Some(MathFractionRuleThickness), // default_rule_thickness
+        9 => Some(MathUpperLimitGapMin), // big_op_spacing1
+        10 => Some(MathLowerLimitGapMin), // big_op_spacing2
+        11 => Some(MathUpperLimitBaselineRiseMin), // big_op_spacing3
+        12 => Some(MathLowerLimitBaselineDropMin), // big_op_spacing4
+        _ => None,
+    }
+}
+
+// 702
+fn cramped_style(c: SmallNumber) -> SmallNumber {
+    2 * (c / 2) + Cramped
+}
+
+fn sub_style(c: SmallNumber) -> SmallNumber {
+    2 * (c / 4) + ScriptStyle + Cramped
+}
+
+fn sup_style(c: SmallNumber) -> SmallNumber {
+    2 * (c / 4) + ScriptStyle + (c % 2)
+}
+
+fn num_style(c: SmallNumber) -> SmallNumber {
+    c + 2 - 2 * (c / 6)
+}
+
+fn denom_style(c: SmallNumber) -> SmallNumber {
+    2 * (c / 2) + Cramped + 2 - 2 * (c / 6)
+}
+
+impl TexState {
+    // 681
+    // the nucleus, superscript and subscript fields of a noad
+    pub fn nucleus(p: usize) -> usize {
+        p + 1
+    }
+
+    pub fn supscr(p: usize) -> usize {
+        p + 2
+    }
+
+    pub fn subscr(p: usize) -> usize {
+        p + 3
+    }
+
+    // the kind of a noad field: empty, math_char, sub_box, sub_mlist or math_text_char
+    pub fn math_type(&self, p: usize) -> HalfWord {
+        self.link(p)
+    }
+
+    pub fn set_math_type(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p, value);
+    }
+
+    // the family of a math_char field
+    pub fn fam(&self, p: usize) -> QuarterWord {
+        self.font(p)
+    }
+
+    pub fn set_fam(&mut self, p: usize, value: QuarterWord) {
+        self.set_font(p, value);
+    }
+
+    // 683
+    // the delimiter fields of radical and fraction noads
+    pub fn left_delimiter(p: usize) -> usize {
+        p + 4
+    }
+
+    pub fn right_delimiter(p: usize) -> usize {
+        p + 5
+    }
+
+    pub fn small_fam(&self, p: usize) -> QuarterWord {
+        self.mem[p].qqqq().b0
+    }
+
+    pub fn small_char(&self, p: usize) -> QuarterWord {
+        self.mem[p].qqqq().b1
+    }
+
+    pub fn large_fam(&self, p: usize) -> QuarterWord {
+        self.mem[p].qqqq().b2
+    }
+
+    pub fn large_char(&self, p: usize) -> QuarterWord {
+        self.mem[p].qqqq().b3
+    }
+
+    // the thickness of a fraction's rule, or default_code
+    pub fn thickness(&self, p: usize) -> Scaled {
+        self.width(p)
+    }
+
+    pub fn set_thickness(&mut self, p: usize, value: Scaled) {
+        self.set_width(p, value);
+    }
+
+    pub fn numerator(p: usize) -> usize {
+        TexState::supscr(p)
+    }
+
+    pub fn denominator(p: usize) -> usize {
+        TexState::subscr(p)
+    }
+
+    // 687
+    // the accent character of an accent noad, and the delimiter of a \left or \right
+    pub fn accent_chr(p: usize) -> usize {
+        p + 4
+    }
+
+    pub fn delimiter(p: usize) -> usize {
+        TexState::nucleus(p)
+    }
+
+    pub fn scripts_allowed(&self, p: usize) -> bool {
+        self.a_type(p) >= OrdNoad && self.a_type(p) < LeftNoad
+    }
+
+    // 689
+    // the four alternatives of a choice node
+    pub fn display_mlist(&self, p: usize) -> HalfWord {
+        self.info(p + 1)
+    }
+
+    pub fn set_display_mlist(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 1, value);
+    }
+
+    pub fn text_mlist(&self, p: usize) -> HalfWord {
+        self.link(p + 1)
+    }
+
+    pub fn set_text_mlist(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 1, value);
+    }
+
+    pub fn script_mlist(&self, p: usize) -> HalfWord {
+        self.info(p + 2)
+    }
+
+    pub fn set_script_mlist(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 2, value);
+    }
+
+    pub fn script_script_mlist(&self, p: usize) -> HalfWord {
+        self.link(p + 2)
+    }
+
+    pub fn set_script_script_mlist(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 2, value);
+    }
+
+    // 725
+    // the translation of a noad into an hlist, kept where its nucleus was
+    fn new_hlist(&self, p: usize) -> Pointer {
+        self.mem[TexState::nucleus(p)].int() as Pointer
+    }
+
+    fn set_new_hlist(&mut self, p: usize, value: Pointer) {
+        self.mem[TexState::nucleus(p)].set_int(value as i32);
+    }
+
+    // 686
+    // makes the noad field p empty
+    pub fn set_empty_field(&mut self, p: usize) {
+        self.mem[p].set_hh_rh(self.empty_field.rh());
+        self.mem[p].set_hh_lh(self.empty_field.lh());
+    }
+
+    pub fn new_noad(&mut self) -> Pointer {
+        let p = self.get_node(NoadSize);
+        let q = p as usize;
+        self.set_type(q, OrdNoad);
+        self.set_subtype(q, Normal as QuarterWord);
+        self.set_empty_field(TexState::nucleus(q));
+        self.set_empty_field(TexState::subscr(q));
+        self.set_empty_field(TexState::supscr(q));
+        p
+    }
+
+    // 688
+    // creates a style node
+    pub fn new_style(&mut self, s: SmallNumber) -> Pointer {
+        let p = self.get_node(StyleNodeSize);
+        self.set_type(p as usize, StyleNode);
+        self.set_subtype(p as usize, s as QuarterWord);
+        self.set_width(p as usize, 0);
+        self.set_depth(p as usize, 0); // the width and depth are not used
+        p
+    }
+
+    // 689
+    // creates a choice node
+    pub fn new_choice(&mut self) -> Pointer {
+        let p = self.get_node(StyleNodeSize);
+        let q = p as usize;
+        self.set_type(q, ChoiceNode);
+        self.set_subtype(q, 0); // the subtype is not used
+        self.set_display_mlist(q, Null);
+        self.set_text_mlist(q, Null);
+        self.set_script_mlist(q, Null);
+        self.set_script_script_mlist(q, Null);
+        p
+    }
+
+    // 691
+    // prints family and character
+    pub fn print_fam_and_char(&mut self, p: usize) {
+        self.print_esc("fam");
+        self.print_int(self.fam(p) as i32);
+        self.print_char(' ' as u8);
+        self.print_ASCII(self.character(p) as i32);
+    }
+
+    // prints a delimiter as a 24-bit hex value
+    pub fn print_delimiter(&mut self, p: usize) {
+        let mut a = self.small_fam(p) as i32 * 256 + self.small_char(p) as i32;
+        a = a * 0x1000 + self.large_fam(p) as i32 * 256 + self.large_char(p) as i32;
+        if a < 0 {
+            self.print_int(a); // this should never happen
+        } else {
+            self.print_hex(a);
+        }
+    }
+
+    // 692
+    // display a noad field
+    fn print_subsidiary_data(&mut self, p: usize, c: u8) {
+        if self.cur_length() as i32 >= self.depth_threshold {
+            if self.math_type(p) != Empty as HalfWord {
+                self.print(" []");
+            }
+        } else {
+            self.append_char(c); // include c in the recursion history
+            match self.math_type(p) {
+                MathChar => {
+                    self.print_ln();
+                    self.print_current_string();
+                    self.print_fam_and_char(p);
+                }
+                SubBox => self.show_node_list(self.info(p)), // recursive call
+                SubMlist => {
+                    if self.info(p) == Null {
+                        self.print_ln();
+                        self.print_current_string();
+                        self.print("{}");
+                    } else {
+                        self.show_node_list(self.info(p)); // recursive call
+                    }
+                }
+                _ => {}
+            }
+            self.flush_char(); // remove c from the recursion history
+        }
+    }
+
+    // 694
+    pub fn print_style(&mut self, c: i32) {
+        match c / 2 {
+            0 => self.print_esc("displaystyle"), // display_style=0
+            1 => self.print_esc("textstyle"), // text_style=2
+            2 => self.print_esc("scriptstyle"), // script_style=4
+            3 => self.print_esc("scriptscriptstyle"), // script_script_style=6
+            _ => self.print("Unknown style!"),
+        }
+    }
+
+    // 690
+    // display a style node, choice node or noad
+    pub fn display_noad(&mut self, p: usize) {
+        match self.a_type(p) {
+            StyleNode => self.print_style(self.subtype(p) as i32),
+            ChoiceNode => {
+                // 695: display choice node p
+                self.print_esc("mathchoice");
+                for &(c, l) in &[('D', self.display_mlist(p)), ('T', self.text_mlist(p)),
+                                 ('S', self.script_mlist(p)), ('s', self.script_script_mlist(p))] {
+                    self.append_char(c as u8);
+                    self.show_box(l);
+                    self.flush_char();
+                }
+            }
+            FractionNoad => {
+                // 697: display fraction noad p
+                self.print_esc("fraction, thickness ");
+                if self.thickness(p) == DefaultCode {
+                    self.print("= default");
+                } else {
+                    self.print_scaled(self.thickness(p));
+                }
+                let l = TexState::left_delimiter(p);
+                if self.small_fam(l) != 0 || self.small_char(l) != MinQuarterWord || self.large_fam(l) != 0
+                    || self.large_char(l) != MinQuarterWord {
+                    self.print(", left-delimiter ");
+                    self.print_delimiter(l);
+                }
+                let r = TexState::right_delimiter(p);
+                if self.small_fam(r) != 0 || self.small_char(r) != MinQuarterWord || self.large_fam(r) != 0
+                    || self.large_char(r) != MinQuarterWord {
+                    self.print(", right-delimiter ");
+                    self.print_delimiter(r);
+                }
+                self.print_subsidiary_data(TexState::numerator(p), '\\' as u8);
+                self.print_subsidiary_data(TexState::denominator(p), '/' as u8);
+            }
+            _ => {
+                // 696: display normal noad p
+                match self.a_type(p) {
+                    OrdNoad => self.print_esc("mathord"),
+                    OpNoad => self.print_esc("mathop"),
+                    BinNoad => self.print_esc("mathbin"),
+                    RelNoad => self.print_esc("mathrel"),
+                    OpenNoad => self.print_esc("mathopen"),
+                    CloseNoad => self.print_esc("mathclose"),
+                    PunctNoad => self.print_esc("mathpunct"),
+                    InnerNoad => self.print_esc("mathinner"),
+                    OverNoad => self.print_esc("overline"),
+                    UnderNoad => self.print_esc("underline"),
+                    VcenterNoad => self.print_esc("vcenter"),
+                    RadicalNoad => {
+                        self.print_esc("radical");
+                        self.print_delimiter(TexState::left_delimiter(p));
+                    }
+                    AccentNoad => {
+                        self.print_esc("accent");
+                        self.print_fam_and_char(TexState::accent_chr(p));
+                    }
+                    LeftNoad => {
+                        self.print_esc("left");
+                        self.print_delimiter(TexState::delimiter(p));
+                    }
+                    RightNoad => {
+                        self.print_esc("right");
+                        self.print_delimiter(TexState::delimiter(p));
+                    }
+                    _ => {}
+                }
+                if self.subtype(p) != Normal as QuarterWord {
+                    if self.subtype(p) == Limits {
+                        self.print_esc("limits");
+                    } else {
+                        self.print_esc("nolimits");
+                    }
+                }
+                if self.a_type(p) < LeftNoad {
+                    self.print_subsidiary_data(TexState::nucleus(p), '.' as u8);
+                }
+                self.print_subsidiary_data(TexState::supscr(p), '^' as u8);
+                self.print_subsidiary_data(TexState::subscr(p), '_' as u8);
+            }
+        }
+    }
+
+    // 698
+    // wipe out a style node, choice node or noad
+    pub fn flush_noad(&mut self, p: Pointer) {
+        let r = p as usize;
+        match self.a_type(r) {
+            StyleNode => self.free_node(p, StyleNodeSize),
+            ChoiceNode => {
+                self.flush_node_list(self.display_mlist(r));
+                self.flush_node_list(self.text_mlist(r));
+                self.flush_node_list(self.script_mlist(r));
+                self.flush_node_list(self.script_script_mlist(r));
+                self.free_node(p, StyleNodeSize);
+            }
+            LeftNoad | RightNoad => self.free_node(p, NoadSize),
+            FractionNoad => {
+                self.flush_node_list(self.info(TexState::numerator(r)));
+                self.flush_node_list(self.info(TexState::denominator(r)));
+                self.free_node(p, FractionNoadSize);
+            }
+            _ => {
+                for &f in &[TexState::nucleus(r), TexState::supscr(r), TexState::subscr(r)] {
+                    if self.math_type(f) >= SubBox {
+                        self.flush_node_list(self.info(f));
+                    }
+                }
+                if self.a_type(r) == RadicalNoad {
+                    self.free_node(p, RadicalNoadSize);
+                } else if self.a_type(r) == AccentNoad {
+                    self.free_node(p, AccentNoadSize);
+                } else {
+                    self.free_node(p, NoadSize);
+                }
+            }
+        }
+    }
+
+    // 699
+    pub fn print_size(&mut self, s: i32) {
+        if s == TextSize {
+            self.print_esc("textfont");
+        } else if s == ScriptSize {
+            self.print_esc("scriptfont");
+        } else {
+            self.print_esc("scriptscriptfont");
+        }
+    }
+
+    // looks for the MATH table of font f the first time it's needed
+    fn check_font_math(&mut self, f: InternalFontNumber) {
+        if let MathStatus::Unchecked = self.font_math[f] {
+            let mut status = MathStatus::Absent;
+            if f != NullFont && is_opentype_name(&self.str_bytes(self.font_name[f])) {
+                if let Some(m) = self.read_font_file(f, EmptyString, FileKind::OpenType).and_then(|d| opentype_math(&d)) {
+                    status = MathStatus::Present(Rc::new(m));
+                }
+            }
+            self.font_math[f] = status;
+        }
+    }
+
+    // looks for the MATH tables of the fonts in families 2 and 3
+    pub fn check_math_fonts(&mut self) {
+        for &s in &[TextSize, ScriptSize, ScriptScriptSize] {
+            for &k in &[2, 3] {
+                let f = self.fam_fnt((k + s) as HalfWord) as InternalFontNumber;
+                self.check_font_math(f);
+            }
+        }
+    }
+
+    // the top accent attachment of character c in font f, if f has a MATH
+    // table; characters the table doesn't mention are centered over width w
+    fn accent_attachment(&self, f: InternalFontNumber, c: QuarterWord, w: Scaled) -> Option<Scaled> {
+        match &self.font_math[f] {
+            MathStatus::Present(m) => Some(m.top_accent(c, self.font_size[f]).unwrap_or(half(w))),
+            _ => None,
+        }
+    }
+
+    // 700
+    // parameter n of the symbol font (family 2) in size s, or what stands for
+    // it in the MATH table of an OpenType font
+    fn mathsy(&self, n: i32, s: SmallNumber) -> Scaled {
+        let f = self.fam_fnt((2 + s) as HalfWord) as InternalFontNumber;
+        if let MathStatus::Present(m) = &self.font_math[f] {
+            let z = self.font_size[f];
+            match n {
+                // the MATH table has nothing for the delimiters of generalized
+                // fractions, so they are as big as in cmsy10
+                20 => return (z as i64 * 239 / 100) as Scaled, // delim1
+                21 => return (z as i64 * 101 / 100) as Scaled, // delim2
+                _ => if let Some(k) = mathsy_constant(n) {
+                    return m.constant(k, z);
+                },
+            }
+        }
+        self.param(n, f)
+    }
+
+    pub fn math_x_height(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(5, s) // height of x
+    }
+
+    pub fn math_quad(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(6, s) // 18mu
+    }
+
+    fn num1(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(8, s) // numerator shift-up in display styles
+    }
+
+    fn num2(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(9, s) // numerator shift-up in non-display, non-\atop
+    }
+
+    fn num3(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(10, s) // numerator shift-up in non-display \atop
+    }
+
+    fn denom1(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(11, s) // denominator shift-down in display styles
+    }
+
+    fn denom2(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(12, s) // denominator shift-down in non-display styles
+    }
+
+    fn sup1(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(13, s) // superscript shift-up in uncramped display style
+    }
+
+    fn sup2(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(14, s) // superscript shift-up in uncramped non-display
+    }
+
+    fn sup3(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(15, s) // superscript shift-up in cramped styles
+    }
+
+    fn sub1(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(16, s) // subscript shift-down if superscript is absent
+    }
+
+    fn sub2(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(17, s) // subscript shift-down if superscript is present
+    }
+
+    fn sup_drop(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(18, s) // superscript baseline below top of large box
+    }
+
+    fn sub_drop(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(19, s) // subscript baseline below bottom of large box
+    }
+
+    fn delim1(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(20, s) // size of \atopwithdelims delimiters in display styles
+    }
+
+    fn delim2(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(21, s) // size of \atopwithdelims delimiters in non-displays
+    }
+
+    pub fn axis_height(&self, s: SmallNumber) -> Scaled {
+        self.mathsy(22, s) // height of fraction lines above the baseline
+    }
+
+    // 701
+    // parameter n of the extension font (family 3) in the current size, or
+    // what stands for it in the MATH table of an OpenType font
+    fn mathex(&self, n: i32) -> Scaled {
+        let f = self.fam_fnt((3 + self.cur_size) as HalfWord) as InternalFontNumber;
+        if let MathStatus::Present(m) = &self.font_math[f] {
+            return mathex_constant(n).map_or(0, |k| m.constant(k, self.font_size[f])); // big_op_spacing5 is zero
+        }
+        self.param(n, f)
+    }
+
+    fn default_rule_thickness(&self) -> Scaled {
+        self.mathex(8) // thickness of \over bars
+    }
+
+    fn big_op_spacing(&self, k: i32) -> Scaled {
+        self.mathex(8 + k) // big_op_spacing1 to big_op_spacing5
+    }
+
+    // 703
+    // set up the values of cur_size and cur_mu, based on cur_style
+    fn set_cur_size(&mut self) {
+        self.check_math_fonts();
+        if self.cur_style < ScriptStyle {
+            self.cur_size = TextSize;
+        } else {
+            self.cur_size = 16 * ((self.cur_style - TextStyle) / 2);
+        }
+        self.cur_mu = self.x_over_n(self.math_quad(self.cur_size), 18);
+    }
+
+    // 704
+    // a rule of height t, for fractions and overlines
+    fn fraction_rule(&mut self, t: Scaled) -> Pointer {
+        let p = self.new_rule();
+        self.set_height(p as usize, t);
+        self.set_depth(p as usize, 0);
+        p
+    }
+
+    // 705
+    // box b with a rule of thickness t above it, separated by clearance k and
+    // with t of space above the rule
+    fn overbar(&mut self, b: Pointer, k: Scaled, t: Scaled) -> Pointer {
+        let p = self.new_kern(k);
+        self.set_link(p as usize, b);
+        let q = self.fraction_rule(t);
+        self.set_link(q as usize, p);
+        let p = self.new_kern(t);
+        self.set_link(p as usize, q);
+        self.vpack(p, 0, Additional)
+    }
+
+    // 709
+    // a box containing character c of font f
+    fn char_box(&mut self, f: InternalFontNumber, c: QuarterWord) -> Pointer {
+        let q = self.char_info(f, c);
+        let hd = q.height_depth();
+        let b = self.new_null_box();
+        self.set_width(b as usize, self.char_width(f, q) + self.char_italic(f, q));
+        self.set_height(b as usize, self.char_height(f, hd));
+        self.set_depth(b as usize, self.char_depth(f, hd));
+        let p = self.get_avail();
+        self.set_character(p as usize, c);
+        self.set_font(p as usize, f as QuarterWord);
+        self.set_list_ptr(b as usize, p);
+        b
+    }
+
+    // 711
+    // adds character c to the top of vlist box b
+    fn stack_into_box(&mut self, b: Pointer, f: InternalFontNumber, c: QuarterWord) {
+        let p = self.char_box(f, c);
+        self.set_link(p as usize, self.list_ptr(b as usize));
+        self.set_list_ptr(b as usize, p);
+        self.set_height(b as usize, self.height(p as usize));
+    }
+
+    // 712
+    fn height_plus_depth(&self, f: InternalFontNumber, c: QuarterWord) -> Scaled {
+        let hd = self.char_info(f, c).height_depth();
+        self.char_height(f, hd) + self.char_depth(f, hd)
+    }
+
+    // 706
+    // a box holding delimiter d in size s, at least v tall if possible
+    pub fn var_delimiter(&mut self, d: usize, s: SmallNumber, v: Scaled) -> Pointer {
+        let mut f = NullFont; // the font of the best character found so far
+        let mut c: QuarterWord = 0; // and its code
+        let mut w: Scaled = 0; // and its height plus depth
+        let mut large_attempt = false;
+        let mut z = self.small_fam(d) as SmallNumber;
+        let mut x = self.small_char(d);
+        'found: loop {
+            // 707: look at the variants of (z,x); set f and c whenever a better
+            // character is found; stop as soon as a large enough variant is encountered
+            if z != 0 || x != MinQuarterWord {
+                z += s + 16;
+                loop {
+                    z -= 16;
+                    let g = self.fam_fnt(z as HalfWord) as InternalFontNumber;
+                    if g != NullFont {
+                        // 708: look at the list of characters starting with x in font g
+                        let mut y = x;
+                        if y >= self.font_bc[g] && y <= self.font_ec[g] {
+                            loop {
+                                let q = self.char_info(g, y);
+                                if !q.char_exists() {
+                                    break;
+                                }
+                                if q.char_tag() == ExtTag {
+                                    f = g;
+                                    c = y;
+                                    break 'found;
+                                }
+                                let u = self.height_plus_depth(g, y);
+                                if u > w {
+                                    f = g;
+                                    c = y;
+                                    w = u;
+                                    if u >= v {
+                                        break 'found;
+                                    }
+                                }
+                                if q.char_tag() != ListTag {
+                                    break;
+                                }
+                                y = q.rem_byte();
+                            }
+                        }
+                    }
+                    if z < 16 {
+                        break;
+                    }
+                }
+            }
+            if large_attempt {
+                break; // there were none large enough
+            }
+            large_attempt = true;
+            z = self.large_fam(d) as SmallNumber;
+            x = self.large_char(d);
+        }
+        let b = if f != NullFont {
+            // 710: make variable b point to a box for (f,c)
+            let q = self.char_info(f, c);
+            if q.char_tag() == ExtTag {
+                // 713: construct an extensible character in a new box b, using
+                // recipe rem_byte(q) and font f
+                let b = self.new_null_box();
+                self.set_type(b as usize, VlistNode);
+                let r = self.font_info[(self.exten_base[f] + q.rem_byte() as i32) as usize].qqqq();
+                // 714: compute the minimum suitable height, w, and the corresponding
+                // number of extension steps, n; also set width(b)
+                let c = r.ext_rep();
+                let u = self.height_plus_depth(f, c);
+                let mut w = 0;
+                let q = self.char_info(f, c);
+                self.set_width(b as usize, self.char_width(f, q) + self.char_italic(f, q));
+                for &c in &[r.ext_bot(), r.ext_mid(), r.ext_top()] {
+                    if c != MinQuarterWord {
+                        w += self.height_plus_depth(f, c);
+                    }
+                }
+                let mut n = 0;
+                if u > 0 {
+                    while w < v {
+                        w += u;
+                        n += 1;
+                        if r.ext_mid() != MinQuarterWord {
+                            w += u;
+                        }
+                    }
+                }
+                if r.ext_bot() != MinQuarterWord {
+                    self.stack_into_box(b, f, r.ext_bot());
+                }
+                for _ in 0..n {
+                    self.stack_into_box(b, f, r.ext_rep());
+                }
+                if r.ext_mid() != MinQuarterWord {
+                    self.stack_into_box(b, f, r.ext_mid());
+                    for _ in 0..n {
+                        self.stack_into_box(b, f, r.ext_rep());
+                    }
+                }
+                if r.ext_top() != MinQuarterWord {
+                    self.stack_into_box(b, f, r.ext_top());
+                }
+                self.set_depth(b as usize, w - self.height(b as usize));
+                b
+            } else {
+                self.char_box(f, c)
+            }
+        } else {
+            let b = self.new_null_box();
+            self.set_width(b as usize, self.dimen_par(NullDelimiterSpaceCode)); // use this width if no delimiter was found
+            b
+        };
+        let shift = half(self.height(b as usize) - self.depth(b as usize)) - self.axis_height(s);
+        self.set_shift_amount(b as usize, shift);
+        b
+    }
+
+    // 715
+    // box b changed to width w, centering its contents
+    fn rebox(&mut self, b: Pointer, w: Scaled) -> Pointer {
+        let mut b = b;
+        if self.width(b as usize) != w && self.list_ptr(b as usize) != Null {
+            if self.a_type(b as usize) == VlistNode {
+                b = self.hpack(b, 0, Additional);
+            }
+            let mut p = self.list_ptr(b as usize);
+            if self.is_char_node(p as usize) && self.link(p as usize) == Null {
+                let f = self.font(p as usize) as InternalFontNumber;
+                let v = self.char_width(f, self.char_info(f, self.character(p as usize)));
+                if v != self.width(b as usize) {
+                    let k = self.new_kern(self.width(b as usize) - v);
+                    self.set_link(p as usize, k);
+                }
+            }
+            self.free_node(b, BoxNodeSize);
+            let b = self.new_glue(SsGlue as Pointer);
+            self.set_link(b as usize, p);
+            while self.link(p as usize) != Null {
+                p = self.link(p as usize);
+            }
+            let g = self.new_glue(SsGlue as Pointer);
+            self.set_link(p as usize, g);
+            self.hpack(b, w, Exactly)
+        } else {
+            self.set_width(b as usize, w);
+            b
+        }
+    }
+
+    // 716
+    // multiplies x by n+f/2^16, with n and f from the preceding x_over_n
+    fn mu_mult(&mut self, n: i32, f: Scaled, x: Scaled) -> Scaled {
+        let y = self.xn_over_d(x, f, 0o200000);
+        self.nx_plus_y(n, x, y)
+    }
+
+    // the integer and fractional parts of m, in units of mu
+    fn mu_parts(&mut self, m: Scaled) -> (i32, Scaled) {
+        let mut n = self.x_over_n(m, 0o200000);
+        let mut f = self.remainder;
+        if f < 0 {
+            n -= 1;
+            f += 0o200000;
+        }
+        (n, f)
+    }
+
+    // a glue specification in pt for the mu glue g, given that 1mu is m
+    fn math_glue(&mut self, g: usize, m: Scaled) -> Pointer {
+        let (n, f) = self.mu_parts(m);
+        let p = self.get_node(GlueSpecSize);
+        let q = p as usize;
+        let w = self.mu_mult(n, f, self.width(g)); // convert mu to pt
+        self.set_width(q, w);
+        self.set_stretch_order(q, self.stretch_order(g));
+        let s = if self.stretch_order(q) == Normal as u8 { self.mu_mult(n, f, self.stretch(g)) } else { self.stretch(g) };
+        self.set_stretch(q, s);
+        self.set_shrink_order(q, self.shrink_order(g));
+        let s = if self.shrink_order(q) == Normal as u8 { self.mu_mult(n, f, self.shrink(g)) } else { self.shrink(g) };
+        self.set_shrink(q, s);
+        p
+    }
+
+    // 717
+    // converts the \mkern p to an ordinary kern, given that 1mu is m
+    fn math_kern(&mut self, p: usize, m: Scaled) {
+        if self.subtype(p) == MuGlue {
+            let (n, f) = self.mu_parts(m);
+            let w = self.mu_mult(n, f, self.width(p));
+            self.set_width(p, w);
+            self.set_subtype(p, Explicit);
+        }
+    }
+
+    // 718
+    // discards the current math list
+    pub fn flush_math(&mut self) {
+        self.flush_node_list(self.link(self.head() as usize));
+        self.flush_node_list(self.incompleat_noad() as Pointer);
+        self.set_link(self.head() as usize, Null);
+        self.set_tail(self.head());
+        self.set_incompleat_noad(Null as i32);
+    }
+
+    // 720
+    // the noad field p as a box in style s, with no shift and no needless italic correction
+    fn clean_box(&mut self, p: usize, s: SmallNumber) -> Pointer {
+        let q = match self.math_type(p) {
+            MathChar | SubMlist => {
+                if self.math_type(p) == MathChar {
+                    self.cur_mlist = self.new_noad();
+                    self.mem[TexState::nucleus(self.cur_mlist as usize)] = self.mem[p];
+                } else {
+                    self.cur_mlist = self.info(p);
+                }
+                let save_style = self.cur_style;
+                self.cur_style = s;
+                self.mlist_penalties = false;
+                self.mlist_to_hlist(); // recursive call
+                self.cur_style = save_style; // restore the style
+                self.set_cur_size();
+                self.link(TempHead)
+            }
+            SubBox => self.info(p),
+            _ => self.new_null_box(),
+        };
+        let x = if self.is_char_node(q as usize) || q == Null {
+            self.hpack(q, 0, Additional)
+        } else if self.link(q as usize) == Null && self.a_type(q as usize) <= VlistNode && self.shift_amount(q as usize) == 0 {
+            q // it's already clean
+        } else {
+            self.hpack(q, 0, Additional)
+        };
+        // 721: simplify a trivial box
+        let q = self.list_ptr(x as usize);
+        if self.is_char_node(q as usize) {
+            let r = self.link(q as usize);
+            if r != Null && self.link(r as usize) == Null && !self.is_char_node(r as usize)
+                && self.a_type(r as usize) == KernNode {
+                // unneeded italic correction
+                self.free_node(r, SmallNodeSize);
+                self.set_link(q as usize, Null);
+            }
+        }
+        x
+    }
+
+    // 722
+    // unpack the math_char field a into cur_f, cur_c and cur_i
+    fn fetch(&mut self, a: usize) {
+        self.cur_c = self.character(a);
+        self.cur_f = self.fam_fnt((self.fam(a) as SmallNumber + self.cur_size) as HalfWord) as InternalFontNumber;
+        if self.cur_f == NullFont {
+            // 723: complain about an undefined family and set cur_i null
+            self.print_err("");
+            self.print_size(self.cur_size);
+            self.print_char(' ' as u8);
+            self.print_int(self.fam(a) as i32);
+            self.print(" is undefined (character ");
+            self.print_ASCII(self.cur_c as i32);
+            self.print_char(')' as u8);
+            self.help(&["Somewhere in the math formula just ended, you used the",
+                "stated character from an undefined font family. For example,",
+                "plain TeX doesn't allow \\it or \\sl in subscripts. Proceed,",
+                "and I'll try to forget that I needed that character."]);
+            self.error();
+            self.cur_i = self.null_character;
+            self.set_math_type(a, Empty as HalfWord);
+        } else {
+            if self.cur_c >= self.font_bc[self.cur_f] && self.cur_c <= self.font_ec[self.cur_f] {
+                self.cur_i = self.char_info(self.cur_f, self.cur_c);
+            } else {
+                self.cur_i = self.null_character;
+            }
+            if !self.cur_i.char_exists() {
+                self.char_warning(self.cur_f, self.cur_c);
+                self.set_math_type(a, Empty as HalfWord);
+                self.cur_i = self.null_character;
+            }
+        }
+    }
+
+    // 734
+    fn make_over(&mut self, q: usize) {
+        let b = self.clean_box(TexState::nucleus(q), cramped_style(self.cur_style));
+        let t = self.default_rule_thickness();
+        let p = self.overbar(b, 3 * t, t);
+        self.set_info(TexState::nucleus(q), p);
+        self.set_math_type(TexState::nucleus(q), SubBox);
+    }
+
+    // 735
+    fn make_under(&mut self, q: usize) {
+        let x = self.clean_box(TexState::nucleus(q), self.cur_style);
+        let t = self.default_rule_thickness();
+        let p = self.new_kern(3 * t);
+        self.set_link(x as usize, p);
+        let r = self.fraction_rule(t);
+        self.set_link(p as usize, r);
+        let y = self.vpack(x, 0, Additional);
+        let delta = self.height(y as usize) + self.depth(y as usize) + t;
+        self.set_height(y as usize, self.height(x as usize));
+        self.set_depth(y as usize, delta - self.height(y as usize));
+        self.set_info(TexState::nucleus(q), y);
+        self.set_math_type(TexState::nucleus(q), SubBox);
+    }
+
+    // 736
+    fn make_vcenter(&mut self, q: usize) {
+        let v = self.info(TexState::nucleus(q)) as usize;
+        if self.a_type(v) != VlistNode {
+            self.confusion("vcenter");
+        }
+        let delta = self.height(v) + self.depth(v);
+        self.set_height(v, self.axis_height(self.cur_size) + half(delta));
+        self.set_depth(v, delta - self.height(v));
+    }
+
+    // 737
+    fn make_radical(&mut self, q: usize) {
+        let x = self.clean_box(TexState::nucleus(q), cramped_style(self.cur_style));
+        let t = self.default_rule_thickness();
+        let mut clr = if self.cur_style < TextStyle {
+            // display style
+            t + self.math_x_height(self.cur_size).abs() / 4
+        } else {
+            t + t.abs() / 4
+        };
+        let (hx, dx) = (self.height(x as usize), self.depth(x as usize));
+        let y = self.var_delimiter(TexState::left_delimiter(q), self.cur_size, hx + dx + clr + t);
+        let delta = self.depth(y as usize) - (hx + dx + clr);
+        if delta > 0 {
+            clr += half(delta); // increase the actual clearance
+        }
+        self.set_shift_amount(y as usize, -(hx + clr));
+        let o = self.overbar(x, clr, self.height(y as usize));
+        self.set_link(y as usize, o);
+        let b = self.hpack(y, 0, Additional);
+        self.set_info(TexState::nucleus(q), b);
+        self.set_math_type(TexState::nucleus(q), SubBox);
+    }
+
+    // 738
+    fn make_math_accent(&mut self, q: usize) {
+        self.fetch(TexState::accent_chr(q));
+        if !self.cur_i.char_exists() {
+            return;
+        }
+        let mut i = self.cur_i; // character information for the accent
+        let mut c = self.cur_c;
+        let f = self.cur_f;
+        self.check_font_math(f);
+        // 740: compute the amount of skew
+        let mut s: Scaled = 0;
+        let mut base = None; // the font and character of the nucleus, if it is one
+        if self.math_type(TexState::nucleus(q)) == MathChar {
+            self.fetch(TexState::nucleus(q));
+            self.check_font_math(self.cur_f);
+            base = Some((self.cur_f, self.cur_c));
+            if self.cur_i.char_tag() == LigTag {
+                let mut a = self.lig_kern_start(self.cur_f, self.cur_i);
+                self.cur_i = self.font_info[a as usize].qqqq();
+                if self.cur_i.skip_byte() > StopFlag {
+                    a = self.lig_kern_restart(self.cur_f, self.cur_i);
+                    self.cur_i = self.font_info[a as usize].qqqq();
+                }
+                loop {
+                    if self.cur_i.next_char() as i32 == self.skew_char[self.cur_f] {
+                        if self.cur_i.op_byte() >= KernFlag && self.cur_i.skip_byte() <= StopFlag {
+                            s = self.char_kern(self.cur_f, self.cur_i);
+                        }
+                        break;
+                    }
+                    if self.cur_i.skip_byte() >= StopFlag {
+                        break;
+                    }
+                    a += self.cur_i.skip_byte() as i32 + 1;
+                    self.cur_i = self.font_info[a as usize].qqqq();
+                }
+            }
+        }
+        let mut x = self.clean_box(TexState::nucleus(q), cramped_style(self.cur_style));
+        let w = self.width(x as usize);
+        let mut h = self.height(x as usize);
+        // 741: switch to a larger accent if available and appropriate
+        while i.char_tag() == ListTag {
+            let y = i.rem_byte();
+            i = self.char_info(f, y);
+            if !i.char_exists() || self.char_width(f, i) > w {
+                break;
+            }
+            c = y;
+        }
+        let mut delta = if h < self.x_height(f) { h } else { self.x_height(f) };
+        if (self.math_type(TexState::supscr(q)) != Empty as HalfWord || self.math_type(TexState::subscr(q)) != Empty as HalfWord)
+            && self.math_type(TexState::nucleus(q)) == MathChar {
+            // 742: swap the subscript and superscript into box x
+            self.flush_node_list(x);
+            let n = self.new_noad() as usize;
+            self.mem[TexState::nucleus(n)] = self.mem[TexState::nucleus(q)];
+            self.mem[TexState::supscr(n)] = self.mem[TexState::supscr(q)];
+            self.mem[TexState::subscr(n)] = self.mem[TexState::subscr(q)];
+            self.set_empty_field(TexState::supscr(q));
+            self.set_empty_field(TexState::subscr(q));
+            self.set_math_type(TexState::nucleus(q), SubMlist);
+            self.set_info(TexState::nucleus(q), n as Pointer);
+            x = self.clean_box(TexState::nucleus(q), self.cur_style);
+            delta += self.height(x as usize) - h;
+            h = self.height(x as usize);
+        }
+        let y = self.char_box(f, c);
+        // an accent from a font with a MATH table goes where its attachment
+        // point is over the nucleus's
+        let shift = match self.accent_attachment(f, c, self.width(y as usize)) {
+            Some(a) => base.and_then(|(g, d)| self.accent_attachment(g, d, w)).unwrap_or(s + half(w)) - a,
+            None => s + half(w - self.width(y as usize)),
+        };
+        self.set_shift_amount(y as usize, shift);
+        self.set_width(y as usize, 0);
+        let p = self.new_kern(-delta);
+        self.set_link(p as usize, x);
+        self.set_link(y as usize, p);
+        let y = self.vpack(y, 0, Additional);
+        self.set_width(y as usize, self.width(x as usize));
+        if self.height(y as usize) < h {
+            // 739: make the height of box y equal to h
+            let p = self.new_kern(h - self.height(y as usize));
+            self.set_link(p as usize, self.list_ptr(y as usize));
+            self.set_list_ptr(y as usize, p);
+            self.set_height(y as usize, h);
+        }
+        self.set_info(TexState::nucleus(q), y);
+        self.set_math_type(TexState::nucleus(q), SubBox);
+    }
+
+    // 743
+    fn make_fraction(&mut self, q: usize) {
+        if self.thickness(q) == DefaultCode {
+            self.set_thickness(q, self.default_rule_thickness());
+        }
+        // 744: create equal-width boxes x and z for the numerator and denominator,
+        // and compute the default amounts shift_up and shift_down by which they
+        // are displaced from the baseline
+        let mut x = self.clean_box(TexState::numerator(q), num_style(self.cur_style));
+        let mut z = self.clean_box(TexState::denominator(q), denom_style(self.cur_style));
+        if self.width(x as usize) < self.width(z as usize) {
+            x = self.rebox(x, self.width(z as usize));
+        } else {
+            z = self.rebox(z, self.width(x as usize));
+        }
+        let (mut shift_up, mut shift_down);
+        if self.cur_style < TextStyle {
+            // display style
+            shift_up = self.num1(self.cur_size);
+            shift_down = self.denom1(self.cur_size);
+        } else {
+            shift_down = self.denom2(self.cur_size);
+            shift_up = if self.thickness(q) != 0 { self.num2(self.cur_size) } else { self.num3(self.cur_size) };
+        }
+        let (hx, dx) = (self.height(x as usize), self.depth(x as usize));
+        let (hz, dz) = (self.height(z as usize), self.depth(z as usize));
+        let axis = self.axis_height(self.cur_size);
+        let delta;
+        if self.thickness(q) == 0 {
+            // 745: adjust shift_up and shift_down for the case of no fraction line
+            let clr = if self.cur_style < TextStyle { 7 * self.default_rule_thickness() } else { 3 * self.default_rule_thickness() };
+            delta = half(clr - ((shift_up - dx) - (hz - shift_down)));
+            if delta > 0 {
+                shift_up += delta;
+                shift_down += delta;
+            }
+        } else {
+            // 746: adjust shift_up and shift_down for the case of a fraction line
+            let clr = if self.cur_style < TextStyle { 3 * self.thickness(q) } else { self.thickness(q) };
+            delta = half(self.thickness(q));
+            let delta1 = clr - ((shift_up - dx) - (axis + delta));
+            let delta2 = clr - ((axis - delta) - (hz - shift_down));
+            if delta1 > 0 {
+                shift_up += delta1;
+            }
+            if delta2 > 0 {
+                shift_down += delta2;
+            }
+        }
+        // 747: construct a vlist box for the fraction, according to shift_up and shift_down
+        let v = self.new_null_box();
+        self.set_type(v as usize, VlistNode);
+        self.set_height(v as usize, shift_up + hx);
+        self.set_depth(v as usize, dz + shift_down);
+        self.set_width(v as usize, self.width(x as usize)); // this also equals width(z)
+        let p = if self.thickness(q) == 0 {
+            let p = self.new_kern((shift_up - dx) - (hz - shift_down));
+            self.set_link(p as usize, z);
+            p
+        } else {
+            let y = self.fraction_rule(self.thickness(q));
+            let p = self.new_kern((axis - delta) - (hz - shift_down));
+            self.set_link(y as usize, p);
+            self.set_link(p as usize, z);
+            let p = self.new_kern((shift_up - dx) - (axis + delta));
+            self.set_link(p as usize, y);
+            p
+        };
+        self.set_link(x as usize, p);
+        self.set_list_ptr(v as usize, x);
+        // 748: put the fraction into a box with its delimiters, and make new_hlist(q) point to it
+        let delta = if self.cur_style < TextStyle { self.delim1(self.cur_size) } else { self.delim2(self.cur_size) };
+        let x = self.var_delimiter(TexState::left_delimiter(q), self.cur_size, delta);
+        self.set_link(x as usize, v);
+        let z = self.var_delimiter(TexState::right_delimiter(q), self.cur_size, delta);
+        self.set_link(v as usize, z);
+        let h = self.hpack(x, 0, Additional);
+        self.set_new_hlist(q, h);
+    }
+
+    // 749
+    // prepares a large operator, returning its italic correction
+    fn make_op(&mut self, q: usize) -> Scaled {
+        if self.subtype(q) == Normal as QuarterWord && self.cur_style < TextStyle {
+            self.set_subtype(q, Limits);
+        }
+        let delta;
+        if self.math_type(TexState::nucleus(q)) == MathChar {
+            self.fetch(TexState::nucleus(q));
+            if self.cur_style < TextStyle && self.cur_i.char_tag() == ListTag {
+                // make it larger
+                let c = self.cur_i.rem_byte();
+                let i = self.char_info(self.cur_f, c);
+                if i.char_exists() {
+                    self.cur_c = c;
+                    self.cur_i = i;
+                    self.set_character(TexState::nucleus(q), c);
+                }
+            }
+            delta = self.char_italic(self.cur_f, self.cur_i);
+            let x = self.clean_box(TexState::nucleus(q), self.cur_style);
+            if self.math_type(TexState::subscr(q)) != Empty as HalfWord && self.subtype(q) != Limits {
+                self.set_width(x as usize, self.width(x as usize) - delta); // remove italic correction
+            }
+            let shift = half(self.height(x as usize) - self.depth(x as usize)) - self.axis_height(self.cur_size);
+            self.set_shift_amount(x as usize, shift); // center vertically
+            self.set_math_type(TexState::nucleus(q), SubBox);
+            self.set_info(TexState::nucleus(q), x);
+        } else {
+            delta = 0;
+        }
+        if self.subtype(q) == Limits {
+            // 750: construct a box with limits above and below it, skewed by delta
+            let x = self.clean_box(TexState::supscr(q), sup_style(self.cur_style));
+            let y = self.clean_box(TexState::nucleus(q), self.cur_style);
+            let z = self.clean_box(TexState::subscr(q), sub_style(self.cur_style));
+            let v = self.new_null_box();
+            self.set_type(v as usize, VlistNode);
+            let w = self.width(y as usize).max(self.width(x as usize)).max(self.width(z as usize));
+            self.set_width(v as usize, w);
+            let x = self.rebox(x, w);
+            let y = self.rebox(y, w);
+            let z = self.rebox(z, w);
+            self.set_shift_amount(x as usize, half(delta));
+            self.set_shift_amount(z as usize, -self.shift_amount(x as usize));
+            self.set_height(v as usize, self.height(y as usize));
+            self.set_depth(v as usize, self.depth(y as usize));
+            // 751: attach the limits to y and adjust height(v), depth(v) to account
+            // for their presence
+            if self.math_type(TexState::supscr(q)) == Empty as HalfWord {
+                self.free_node(x, BoxNodeSize);
+                self.set_list_ptr(v as usize, y);
+            } else {
+                let shift_up = (self.big_op_spacing(3) - self.depth(x as usize)).max(self.big_op_spacing(1));
+                let p = self.new_kern(shift_up);
+                self.set_link(p as usize, y);
+                self.set_link(x as usize, p);
+                let p = self.new_kern(self.big_op_spacing(5));
+                self.set_link(p as usize, x);
+                self.set_list_ptr(v as usize, p);
+                let h = self.height(v as usize) + self.big_op_spacing(5) + self.height(x as usize) + self.depth(x as usize) + shift_up;
+                self.set_height(v as usize, h);
+            }
+            if self.math_type(TexState::subscr(q)) == Empty as HalfWord {
+                self.free_node(z, BoxNodeSize);
+            } else {
+                let shift_down = (self.big_op_spacing(4) - self.height(z as usize)).max(self.big_op_spacing(2));
+                let p = self.new_kern(shift_down);
+                self.set_link(y as usize, p);
+                self.set_link(p as usize, z);
+                let p = self.new_kern(self.big_op_spacing(5));
+                self.set_link(z as usize, p);
+                let d = self.depth(v as usize) + self.big_op_spacing(5) + self.height(z as usize) + self.depth(z as usize) + shift_down;
+                self.set_depth(v as usize, d);
+            }
+            self.set_new_hlist(q, v);
+        }
+        delta
+    }
+
+    // 752
+    // looks for ligatures and kerns between the ordinary noad q and the noad after it
+    fn make_ord(&mut self, q: usize) {
+        'restart: loop {
+            if self.math_type(TexState::subscr(q)) != Empty as HalfWord || self.math_type(TexState::supscr(q)) != Empty as HalfWord
+                || self.math_type(TexState::nucleus(q)) != MathChar {
+                return;
+            }
+            let p = self.link(q) as usize;
+            if p == Null as usize || self.a_type(p) < OrdNoad || self.a_type(p) > PunctNoad
+                || self.math_type(TexState::nucleus(p)) != MathChar
+                || self.fam(TexState::nucleus(p)) != self.fam(TexState::nucleus(q)) {
+                return;
+            }
+            self.set_math_type(TexState::nucleus(q), MathTextChar);
+            self.fetch(TexState::nucleus(q));
+            if self.cur_i.char_tag() != LigTag {
+                return;
+            }
+            let mut a = self.lig_kern_start(self.cur_f, self.cur_i);
+            self.cur_c = self.character(TexState::nucleus(p));
+            self.cur_i = self.font_info[a as usize].qqqq();
+            if self.cur_i.skip_byte() > StopFlag {
+                a = self.lig_kern_restart(self.cur_f, self.cur_i);
+                self.cur_i = self.font_info[a as usize].qqqq();
+            }
+            loop {
+                // 753: if instruction cur_i is a kern with cur_c, attach the kern after q;
+                // or if it is a ligature with cur_c, combine noads q and p appropriately;
+                // then return if the cursor has moved past a noad, or restart
+                if self.cur_i.next_char() == self.cur_c && self.cur_i.skip_byte() <= StopFlag {
+                    if self.cur_i.op_byte() >= KernFlag {
+                        let k = self.new_kern(self.char_kern(self.cur_f, self.cur_i));
+                        self.set_link(k as usize, self.link(q));
+                        self.set_link(q, k);
+                        return;
+                    }
+                    self.check_interrupt(); // allow a way out of infinite ligature loop
+                    match self.cur_i.op_byte() {
+                        1 | 5 => self.set_character(TexState::nucleus(q), self.cur_i.rem_byte()), // =:|, =:|>
+                        2 | 6 => self.set_character(TexState::nucleus(p), self.cur_i.rem_byte()), // |=:, |=:>
+                        3 | 7 | 11 => {
+                            // |=:|, |=:|>, |=:|>>
+                            let r = self.new_noad() as usize;
+                            self.set_character(TexState::nucleus(r), self.cur_i.rem_byte());
+                            self.set_fam(TexState::nucleus(r), self.fam(TexState::nucleus(q)));
+                            self.set_link(q, r as Pointer);
+                            self.set_link(r, p as Pointer);
+                            if self.cur_i.op_byte() < 11 {
+                                self.set_math_type(TexState::nucleus(r), MathChar);
+                            } else {
+                                self.set_math_type(TexState::nucleus(r), MathTextChar); // prevent combination
+                            }
+                        }
+                        _ => {
+                            // =:
+                            self.set_link(q, self.link(p));
+                            self.set_character(TexState::nucleus(q), self.cur_i.rem_byte());
+                            self.mem[TexState::subscr(q)] = self.mem[TexState::subscr(p)];
+                            self.mem[TexState::supscr(q)] = self.mem[TexState::supscr(p)];
+                            self.free_node(p as Pointer, NoadSize);
+                        }
+                    }
+                    if self.cur_i.op_byte() > 3 {
+                        return;
+                    }
+                    self.set_math_type(TexState::nucleus(q), MathChar);
+                    continue 'restart;
+                }
+                if self.cur_i.skip_byte() >= StopFlag {
+                    return;
+                }
+                a += self.cur_i.skip_byte() as i32 + 1;
+                self.cur_i = self.font_info[a as usize].qqqq();
+            }
+        }
+    }
+
+    // 756
+    // attaches the sub/superscripts of noad q to new_hlist(q), given the
+    // italic correction delta of the nucleus
+    fn make_scripts(&mut self, q: usize, delta: Scaled) {
+        let p = self.new_hlist(q);
+        let (mut shift_up, mut shift_down);
+        if self.is_char_node(p as usize) {
+            shift_up = 0;
+            shift_down = 0;
+        } else {
+            let z = self.hpack(p, 0, Additional);
+            let t = if self.cur_style < ScriptStyle { ScriptSize } else { ScriptScriptSize };
+            shift_up = self.height(z as usize) - self.sup_drop(t);
+            shift_down = self.depth(z as usize) + self.sub_drop(t);
+            self.free_node(z, BoxNodeSize);
+        }
+        let script_space = self.dimen_par(ScriptSpaceCode);
+        let mut x;
+        if self.math_type(TexState::supscr(q)) == Empty as HalfWord {
+            // 757: construct a subscript box x when there is no superscript
+            x = self.clean_box(TexState::subscr(q), sub_style(self.cur_style));
+            self.set_width(x as usize, self.width(x as usize) + script_space);
+            shift_down = shift_down.max(self.sub1(self.cur_size));
+            let clr = self.height(x as usize) - (self.math_x_height(self.cur_size) * 4).abs() / 5;
+            shift_down = shift_down.max(clr);
+            self.set_shift_amount(x as usize, shift_down);
+        } else {
+            // 758: construct a superscript box x
+            x = self.clean_box(TexState::supscr(q), sup_style(self.cur_style));
+            self.set_width(x as usize, self.width(x as usize) + script_space);
+            let clr = if self.cur_style % 2 == 1 {
+                self.sup3(self.cur_size)
+            } else if self.cur_style < TextStyle {
+                self.sup1(self.cur_size)
+            } else {
+                self.sup2(self.cur_size)
+            };
+            shift_up = shift_up.max(clr);
+            let clr = self.depth(x as usize) + self.math_x_height(self.cur_size).abs() / 4;
+            shift_up = shift_up.max(clr);
+            if self.math_type(TexState::subscr(q)) == Empty as HalfWord {
+                self.set_shift_amount(x as usize, -shift_up);
+            } else {
+                // 759: construct a sub/superscript combination box x, with the
+                // superscript offset by delta
+                let y = self.clean_box(TexState::subscr(q), sub_style(self.cur_style));
+                self.set_width(y as usize, self.width(y as usize) + script_space);
+                shift_down = shift_down.max(self.sub2(self.cur_size));
+                let clr = 4 * self.default_rule_thickness() - ((shift_up - self.depth(x as usize)) - (self.height(y as usize) - shift_down));
+                if clr > 0 {
+                    shift_down += clr;
+                    let clr = (self.math_x_height(self.cur_size) * 4).abs() / 5 - (shift_up - self.depth(x as usize));
+                    if clr > 0 {
+                        shift_up += clr;
+                        shift_down -= clr;
+                    }
+                }
+                self.set_shift_amount(x as usize, delta); // superscript is delta to the right of the subscript
+                let p = self.new_kern((shift_up - self.depth(x as usize)) - (self.height(y as usize) - shift_down));
+                self.set_link(x as usize, p);
+                self.set_link(p as usize, y);
+                x = self.vpack(x, 0, Additional);
+                self.set_shift_amount(x as usize, shift_down);
+            }
+        }
+        if self.new_hlist(q) == Null {
+            self.set_new_hlist(q, x);
+        } else {
+            let mut p = self.new_hlist(q);
+            while self.link(p as usize) != Null {
+                p = self.link(p as usize);
+            }
+            self.set_link(p as usize, x);
+        }
+    }
+
+    // 762
+    // makes the delimiter of a \left or \right noad, given the style and the
+    // height and depth of the formula; returns open_noad or close_noad
+    fn make_left_right(&mut self, q: usize, style: SmallNumber, max_d: Scaled, max_h: Scaled) -> QuarterWord {
+        self.cur_style = style;
+        self.set_cur_size();
+        let delta2 = max_d + self.axis_height(self.cur_size);
+        let delta1 = (max_h + max_d - delta2).max(delta2); // delta1 is max distance from axis
+        let delta = (delta1 / 500) * self.int_par(DelimiterFactorCode);
+        let delta2 = delta1 + delta1 - self.dimen_par(DelimiterShortfallCode);
+        let b = self.var_delimiter(TexState::delimiter(q), self.cur_size, delta.max(delta2));
+        self.set_new_hlist(q, b);
+        self.a_type(q) - (LeftNoad - OpenNoad) // open_noad or close_noad
+    }
+
+    // 726
+    // converts the mlist cur_mlist in style cur_style to an hlist starting at
+    // link(temp_head), inserting penalties if mlist_penalties
+    pub fn mlist_to_hlist(&mut self) {
+        let mlist = self.cur_mlist; // beginning of the given list
+        let penalties = self.mlist_penalties; // should penalty nodes be inserted?
+        let style = self.cur_style; // the given style
+        let mut q = mlist as usize; // runs through the mlist
+        let mut r = Null as usize; // the most recent noad preceding q
+        let mut r_type = OpNoad; // the type of noad r, or op_noad if r=null
+        let mut max_h: Scaled = 0; // maximum height of the list translated so far
+        let mut max_d: Scaled = 0; // maximum depth of the list translated so far
+        self.set_cur_size();
+        while q != Null as usize {
+            // 727: process node-or-noad q as much as possible in preparation for
+            // the second pass of mlist_to_hlist, then move to the next item in the mlist
+            let mut delta: Scaled = 0; // offset between subscript and superscript
+            // 728: do first-pass processing based on type(q)
+            let step = loop {
+                match self.a_type(q) {
+                    BinNoad => match r_type {
+                        BinNoad | OpNoad | RelNoad | OpenNoad | PunctNoad | LeftNoad => {
+                            self.set_type(q, OrdNoad);
+                            continue;
+                        }
+                        _ => break MlistStep::Convert,
+                    },
+                    RelNoad | CloseNoad | PunctNoad | RightNoad => {
+                        // 729: convert a final bin_noad to an ord_noad
+                        if r_type == BinNoad {
+                            self.set_type(r, OrdNoad);
+                        }
+                        if self.a_type(q) == RightNoad {
+                            break MlistStep::DoneWithNoad;
+                        }
+                        break MlistStep::Convert;
+                    }
+                    // 733: cases for noads that can follow a bin_noad
+                    LeftNoad => break MlistStep::DoneWithNoad,
+                    FractionNoad => {
+                        self.make_fraction(q);
+                        break MlistStep::CheckDimensions;
+                    }
+                    OpNoad => {
+                        delta = self.make_op(q);
+                        if self.subtype(q) == Limits {
+                            break MlistStep::CheckDimensions;
+                        }
+                        break MlistStep::Convert;
+                    }
+                    OrdNoad => {
+                        self.make_ord(q);
+                        break MlistStep::Convert;
+                    }
+                    OpenNoad | InnerNoad => break MlistStep::Convert,
+                    RadicalNoad => {
+                        self.make_radical(q);
+                        break MlistStep::Convert;
+                    }
+                    OverNoad => {
+                        self.make_over(q);
+                        break MlistStep::Convert;
+                    }
+                    UnderNoad => {
+                        self.make_under(q);
+                        break MlistStep::Convert;
+                    }
+                    AccentNoad => {
+                        self.make_math_accent(q);
+                        break MlistStep::Convert;
+                    }
+                    VcenterNoad => {
+                        self.make_vcenter(q);
+                        break MlistStep::Convert;
+                    }
+                    // 730: cases for nodes that can appear in an mlist
+                    StyleNode => {
+                        self.cur_style = self.subtype(q) as SmallNumber;
+                        self.set_cur_size();
+                        break MlistStep::DoneWithNode;
+                    }
+                    ChoiceNode => {
+                        // 731: change this node to a style node followed by the correct choice
+                        let mut p = match self.cur_style / 2 {
+                            0 => self.display_mlist(q),
+                            1 => self.text_mlist(q),
+                            2 => self.script_mlist(q),
+                            _ => self.script_script_mlist(q),
+                        };
+                        match self.cur_style / 2 {
+                            0 => self.set_display_mlist(q, Null),
+                            1 => self.set_text_mlist(q, Null),
+                            2 => self.set_script_mlist(q, Null),
+                            _ => self.set_script_script_mlist(q, Null),
+                        }
+                        self.flush_node_list(self.display_mlist(q));
+                        self.flush_node_list(self.text_mlist(q));
+                        self.flush_node_list(self.script_mlist(q));
+                        self.flush_node_list(self.script_script_mlist(q));
+                        self.set_type(q, StyleNode);
+                        self.set_subtype(q, self.cur_style as QuarterWord);
+                        self.set_width(q, 0);
+                        self.set_depth(q, 0);
+                        if p != Null {
+                            let z = self.link(q);
+                            self.set_link(q, p);
+                            while self.link(p as usize) != Null {
+                                p = self.link(p as usize);
+                            }
+                            self.set_link(p as usize, z);
+                        }
+                        break MlistStep::DoneWithNode;
+                    }
+                    InsNode | MarkNode | AdjustNode | WhatsitNode | PenaltyNode | DiscNode => break MlistStep::DoneWithNode,
+                    RuleNode => {
+                        max_h = max_h.max(self.height(q));
+                        max_d = max_d.max(self.depth(q));
+                        break MlistStep::DoneWithNode;
+                    }
+                    GlueNode => {
+                        // 732: convert math glue to ordinary glue
+                        if self.subtype(q) == MuGlue {
+                            let x = self.glue_ptr(q);
+                            let y = self.math_glue(x as usize, self.cur_mu);
+                            self.delete_glue_ref(x);
+                            self.set_glue_ptr(q, y);
+                            self.set_subtype(q, Normal as QuarterWord);
+                        } else if self.cur_size != TextSize && self.subtype(q) == CondMathGlue {
+                            let p = self.link(q);
+                            if p != Null && (self.a_type(p as usize) == GlueNode || self.a_type(p as usize) == KernNode) {
+                                self.set_link(q, self.link(p as usize));
+                                self.set_link(p as usize, Null);
+                                self.flush_node_list(p);
+                            }
+                        }
+                        break MlistStep::DoneWithNode;
+                    }
+                    KernNode => {
+                        self.math_kern(q, self.cur_mu);
+                        break MlistStep::DoneWithNode;
+                    }
+                    _ => self.confusion("mlist1"),
+                }
+            };
+            if step == MlistStep::Convert {
+                // 754: convert nucleus(q) to an hlist and attach the sub/superscripts
+                let n = TexState::nucleus(q);
+                let p = match self.math_type(n) {
+                    MathChar | MathTextChar => {
+                        self.fetch(n);
+                        if self.cur_i.char_exists() {
+                            delta = self.char_italic(self.cur_f, self.cur_i);
+                            let p = self.new_character(self.cur_f, self.cur_c);
+                            if self.math_type(n) == MathTextChar && self.space(self.cur_f) != 0 {
+                                delta = 0; // no italic correction in mid-word of text font
+                            }
+                            if self.math_type(TexState::subscr(q)) == Empty as HalfWord && delta != 0 {
+                                let k = self.new_kern(delta);
+                                self.set_link(p as usize, k);
+                                delta = 0;
+                            }
+                            p
+                        } else {
+                            Null
+                        }
+                    }
+                    0 => Null, // empty
+                    SubBox => self.info(n),
+                    SubMlist => {
+                        self.cur_mlist = self.info(n);
+                        let save_style = self.cur_style;
+                        self.mlist_penalties = false;
+                        self.mlist_to_hlist(); // recursive call
+                        self.cur_style = save_style;
+                        self.set_cur_size();
+                        self.hpack(self.link(TempHead), 0, Additional)
+                    }
+                    _ => self.confusion("mlist2"),
+                };
+                self.set_new_hlist(q, p);
+                if self.math_type(TexState::subscr(q)) != Empty as HalfWord || self.math_type(TexState::supscr(q)) != Empty as HalfWord {
+                    self.make_scripts(q, delta);
+                }
+            }
+            if step == MlistStep::Convert || step == MlistStep::CheckDimensions {
+                let z = self.hpack(self.new_hlist(q), 0, Additional);
+                max_h = max_h.max(self.height(z as usize));
+                max_d = max_d.max(self.depth(z as usize));
+                self.free_node(z, BoxNodeSize);
+            }
+            if step != MlistStep::DoneWithNode {
+                r = q;
+                r_type = self.a_type(r);
+            }
+            q = self.link(q) as usize;
+        }
+        // 729: convert a final bin_noad to an ord_noad
+        if r_type == BinNoad {
+            self.set_type(r, OrdNoad);
+        }
+        // 760: make a second pass over the mlist, removing all noads and inserting
+        // the proper spacing and penalties
+        let mut p = TempHead;
+        self.set_link(p, Null);
+        let mut q = mlist as usize;
+        let mut r_type: QuarterWord = 0;
+        self.cur_style = style;
+        self.set_cur_size();
+        while q != Null as usize {
+            // 761: if node q is a style node, change the style and delete it;
+            // otherwise if it is not a noad, put it into the hlist and advance q;
+            // otherwise set s to the size of noad q, set t to the associated type
+            // (ord_noad..inner_noad), and set pen to the associated penalty
+            let mut t = OrdNoad;
+            let mut s = NoadSize;
+            let mut pen = InfPenalty;
+            match self.a_type(q) {
+                OpNoad | OpenNoad | CloseNoad | PunctNoad | InnerNoad => t = self.a_type(q),
+                BinNoad => {
+                    t = BinNoad;
+                    pen = self.int_par(BinOpPenaltyCode);
+                }
+                RelNoad => {
+                    t = RelNoad;
+                    pen = self.int_par(RelPenaltyCode);
+                }
+                OrdNoad | VcenterNoad | OverNoad | UnderNoad => {}
+                RadicalNoad => s = RadicalNoadSize,
+                AccentNoad => s = AccentNoadSize,
+                FractionNoad => {
+                    t = InnerNoad;
+                    s = FractionNoadSize;
+                }
+                LeftNoad | RightNoad => t = self.make_left_right(q, style, max_d, max_h),
+                StyleNode => {
+                    // 763: change the current style and delete q
+                    self.cur_style = self.subtype(q) as SmallNumber;
+                    self.set_cur_size();
+                    let r = q;
+                    q = self.link(q) as usize;
+                    self.free_node(r as Pointer, StyleNodeSize);
+                    continue;
+                }
+                WhatsitNode | PenaltyNode | RuleNode | DiscNode | AdjustNode | InsNode | MarkNode | GlueNode | KernNode => {
+                    self.set_link(p, q as Pointer);
+                    p = q;
+                    q = self.link(q) as usize;
+                    self.set_link(p, Null);
+                    continue;
+                }
+                _ => self.confusion("mlist3"),
+            }
+            // 766: append inter-element spacing based on r_type and t
+            if r_type > 0 {
+                // not the first noad
+                let x = match MathSpacing[(r_type - OrdNoad) as usize * 8 + (t - OrdNoad) as usize] {
+                    b'0' => 0,
+                    b'1' => if self.cur_style < ScriptStyle { ThinMuSkipCode } else { 0 },
+                    b'2' => ThinMuSkipCode,
+                    b'3' => if self.cur_style < ScriptStyle { MedMuSkipCode } else { 0 },
+                    b'4' => if self.cur_style < ScriptStyle { ThickMuSkipCode } else { 0 },
+                    _ => self.confusion("mlist4"),
+                };
+                if x != 0 {
+                    let y = self.math_glue(self.glue_par(x) as usize, self.cur_mu);
+                    let z = self.new_glue(y);
+                    self.set_glue_ref_count(y as usize, Null);
+                    self.set_link(p, z);
+                    p = z as usize;
+                    self.set_subtype(z as usize, (x + 1) as QuarterWord); // store a symbolic subtype
+                }
+            }
+            // 767: append any new_hlist entries for q, and any appropriate penalties
+            if self.new_hlist(q) != Null {
+                self.set_link(p, self.new_hlist(q));
+                loop {
+                    p = self.link(p) as usize;
+                    if self.link(p) == Null {
+                        break;
+                    }
+                }
+            }
+            if penalties && self.link(q) != Null && pen < InfPenalty {
+                r_type = self.a_type(self.link(q) as usize);
+                if r_type != PenaltyNode && r_type != RelNoad {
+                    let z = self.new_penalty(pen);
+                    self.set_link(p, z);
+                    p = z as usize;
+                }
+            }
+            r_type = t;
+            let r = q;
+            q = self.link(q) as usize;
+            self.free_node(r as Pointer, s);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::opentype::{MathAxisHeight, MathFractionDenominatorDisplayStyleShiftDown,
+                          MathFractionNumeratorDisplayStyleShiftUp, MathFractionRuleThickness};
+    use crate::test_fonts::{math_extension, math_symbols, text_font, Otf};
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    // the log of a document that sets up the four families TeX needs and
+    // shows box 0 after the body; families 2 and 3 use the fonts \sy and \ex
+    fn shown_box(sy: &str, ex: &str, body: &str) -> String {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("sy.tfm", math_symbols().bytes());
+        vfs.add_file("ex.tfm", math_extension().bytes());
+        vfs.add_file("math.otf", Otf::new(1000).glyph(b'x', 500)
+            .math_constant(MathAxisHeight, 250)
+            .math_constant(MathFractionNumeratorDisplayStyleShiftUp, 677)
+            .math_constant(MathFractionDenominatorDisplayStyleShiftDown, 686)
+            .math_constant(MathFractionRuleThickness, 60)
+            .bytes());
+        vfs.add_file("doc.tex", format!("\\catcode`\\{{=1 \\catcode`\\}}=2 \\catcode`\\$=3 \\catcode`\\^=7
+\\font\\rm=text \\font\\sy={} \\font\\ex={}
+\\textfont0=\\rm \\scriptfont0=\\rm \\scriptscriptfont0=\\rm \\textfont1=\\rm \\scriptfont1=\\rm \\scriptscriptfont1=\\rm
+\\textfont2=\\sy \\scriptfont2=\\sy \\scriptscriptfont2=\\sy \\textfont3=\\ex \\scriptfont3=\\ex \\scriptscriptfont3=\\ex
+\\showboxdepth=100 \\showboxbreadth=100 \\scrollmode
+{}\\showbox0
+\\end
+", sy, ex, body));
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        let start = log.find("> \\box0=").expect(&log);
+        log[start..].split("\n\n").next().unwrap().to_string()
+    }
+
+    #[test]
+    fn fractions() {
+        // in display style the shifts are num1 and denom1; in text style the
+        // denominator of \over moves down to clear the rule, and \atop keeps
+        // 3 rule thicknesses between numerator and denominator
+        let b = shown_box("sy", "ex", "\\setbox0\\hbox{$\\displaystyle{a\\over b}\\textstyle{a\\over b}{a\\atop b}$}");
+        assert_eq!(b, concat!(
+            "> \\box0=\n",
+            "\\hbox(13.99997+6.99998)x15.0\n",
+            ".\\mathon\n",
+            ".\\hbox(13.99997+6.99998)x5.0\n",
+            "..\\hbox(13.99997+6.99998)x5.0\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            "...\\vbox(13.99997+6.99998)x5.0\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm a\n",
+            "....\\kern4.29999\n",
+            "....\\rule(0.4+0.0)x*\n",
+            "....\\kern2.3\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm b\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            ".\\hbox(10.99997+5.09998)x5.0\n",
+            "..\\hbox(10.99997+5.09998)x5.0\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            "...\\vbox(10.99997+5.09998)x5.0\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm a\n",
+            "....\\kern1.29999\n",
+            "....\\rule(0.4+0.0)x*\n",
+            "....\\kern0.4\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm b\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            ".\\hbox(11.59996+3.59999)x5.0\n",
+            "..\\hbox(11.59996+3.59999)x5.0\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            "...\\vbox(11.59996+3.59999)x5.0\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm a\n",
+            "....\\kern1.19998\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm b\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            ".\\mathoff",
+        ));
+    }
+
+    #[test]
+    fn radicals() {
+        // a small radical sign is tall enough for the first, but the second
+        // needs the larger of the two signs in the extension font
+        let b = shown_box("sy", "ex", "\\setbox0\\hbox{$\\radical\"270370{a}\\radical\"270370{\\vrule height 15pt}$}");
+        assert_eq!(b, concat!(
+            "> \\box0=\n",
+            "\\hbox(20.84998+4.55)x23.4\n",
+            ".\\mathon\n",
+            ".\\hbox(8.34998+0.05)x13.0\n",
+            "..\\hbox(0.4+7.59999)x8.0, shifted -7.54999\n",
+            "...\\sy p\n",
+            "..\\vbox(8.34998+0.0)x5.0\n",
+            "...\\kern0.4\n",
+            "...\\rule(0.4+0.0)x*\n",
+            "...\\kern0.55\n",
+            "...\\hbox(6.99998+0.0)x5.0\n",
+            "....\\rm a\n",
+            ".\\hbox(20.84998+4.55)x10.4\n",
+            "..\\hbox(0.4+24.59999)x10.0, shifted -20.04999\n",
+            "...\\ex q\n",
+            "..\\vbox(20.84998+0.0)x0.4\n",
+            "...\\kern0.4\n",
+            "...\\rule(0.4+0.0)x*\n",
+            "...\\kern5.04999\n",
+            "...\\hbox(15.0+0.0)x0.4\n",
+            "....\\rule(15.0+*)x0.4\n",
+            ".\\mathoff",
+        ));
+    }
+
+    #[test]
+    fn opentype_math_constants() {
+        // the shifts, the rule and the axis come from the MATH table, in
+        // thousandths of the 10pt size
+        let b = shown_box("\"math.otf\"", "\"math.otf\"", "\\setbox0\\hbox{$\\displaystyle{a\\over b}$}");
+        assert_eq!(b, concat!(
+            "> \\box0=\n",
+            "\\hbox(13.76999+6.86)x5.0\n",
+            ".\\mathon\n",
+            ".\\hbox(13.76999+6.86)x5.0\n",
+            "..\\hbox(13.76999+6.86)x5.0\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            "...\\vbox(13.76999+6.86)x5.0\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm a\n",
+            "....\\kern3.97\n",
+            "....\\rule(0.6+0.0)x*\n",
+            "....\\kern2.06001\n",
+            "....\\hbox(6.99998+0.0)x5.0\n",
+            ".....\\rm b\n",
+            "...\\hbox(0.0+0.0)x0.0, shifted -2.5\n",
+            ".\\mathoff",
+        ));
+    }
+}
@@ -0,0 +1,847 @@
+use crate::*;
+
+// 1178
+pub const AboveCode: HalfWord = 0; // `\above'
+pub const OverCode: HalfWord = 1; // `\over'
+pub const AtopCode: HalfWord = 2; // `\atop'
+pub const DelimitedCode: HalfWord = 3; // `\abovewithdelims', etc.
+
+// 700, 701
+const TotalMathsyParams: usize = 22;
+const TotalMathexParams: usize = 13;
+
+impl TexState {
+    // 1136
+    // starts math mode after a `$' in horizontal mode
+    pub fn init_math(&mut self) {
+        self.get_token(); // get_x_token would fail on \ifmmode!
+        if self.cur_cmd == MathShift && self.mode() > 0 {
+            // 1145: go into display math mode
+            let w = if self.head() == self.tail() {
+                // `\noindent$$' or `$${ }$$'
+                self.pop_nest();
+                -MaxDimen
+            } else {
+                self.line_break(self.int_par(DisplayWidowPenaltyCode));
+                self.natural_width_of_last_line()
+            };
+            // now we are in vertical mode, working on the list that will contain the display
+            // 1149: calculate the length l and the shift amount s of the display lines
+            let (l, s) = if self.par_shape_ptr() == Null {
+                let hang_indent = self.dimen_par(HangIndentCode);
+                let hang_after = self.int_par(HangAfterCode);
+                if hang_indent != 0
+                    && ((hang_after >= 0 && self.prev_graf() + 2 > hang_after) || self.prev_graf() + 1 < -hang_after)
+                {
+                    (self.dimen_par(HsizeCode) - hang_indent.abs(), if hang_indent > 0 { hang_indent } else { 0 })
+                } else {
+                    (self.dimen_par(HsizeCode), 0)
+                }
+            } else {
+                let n = self.info(self.par_shape_ptr() as usize) as i32;
+                let p = if self.prev_graf() + 2 >= n {
+                    self.par_shape_ptr() as usize + 2 * n as usize
+                } else {
+                    self.par_shape_ptr() as usize + 2 * (self.prev_graf() + 2) as usize
+                };
+                (self.mem[p].sc(), self.mem[p - 1].sc())
+            };
+            self.push_math(MathShiftGroup);
+            self.set_mode(Mmode);
+            self.eq_word_define((IntBase + CurFamCode) as Pointer, -1);
+            self.eq_word_define((DimenBase + PreDisplaySizeCode) as Pointer, w);
+            self.eq_word_define((DimenBase + DisplayWidthCode) as Pointer, l);
+            self.eq_word_define((DimenBase + DisplayIndentCode) as Pointer, s);
+            if self.equiv(EveryDisplayLoc as usize) != Null {
+                self.begin_token_list(self.equiv(EveryDisplayLoc as usize), EveryDisplayText);
+            }
+            if self.nest_ptr == 1 {
+                self.build_page();
+            }
+        } else {
+            self.back_input();
+            self.go_into_ordinary_math_mode();
+        }
+    }
+
+    // 1146
+    // the natural width w of the last line of the paragraph just broken,
+    // or max_dimen if it contains glue that was stretched or shrunk, or
+    // -max_dimen if it is empty
+    pub fn natural_width_of_last_line(&mut self) -> Scaled {
+        let jb = self.just_box as usize;
+        let mut v = self.shift_amount(jb) + 2 * self.quad(self.cur_font() as InternalFontNumber);
+        let mut w = -MaxDimen;
+        let mut p = self.list_ptr(jb) as usize;
+        while p != Null as usize {
+            // 1147: let d be the natural width of node p; found is true in the
+            // case of leaders
+            let mut r = p;
+            let (d, found) = if self.is_char_node(r) {
+                let f = self.font(r) as InternalFontNumber;
+                (self.char_width(f, self.char_info(f, self.character(r))), true)
+            } else {
+                match self.a_type(r) {
+                    HlistNode | VlistNode | RuleNode => (self.width(r), true),
+                    LigatureNode => {
+                        // 652: make node p look like a char_node
+                        self.mem[LigTrick] = self.mem[TexState::lig_char(r)];
+                        self.set_link(LigTrick, self.link(r));
+                        r = LigTrick;
+                        let f = self.font(r) as InternalFontNumber;
+                        (self.char_width(f, self.char_info(f, self.character(r))), true)
+                    }
+                    KernNode | MathNode => (self.width(r), false),
+                    GlueNode => {
+                        // 1148: let d be the natural width of this glue; if
+                        // stretching or shrinking, set v:=max_dimen
+                        let q = self.glue_ptr(r) as usize;
+                        if self.glue_sign(jb) as i32 == Stretching {
+                            if self.glue_order(jb) == self.stretch_order(q) && self.stretch(q) != 0 {
+                                v = MaxDimen;
+                            }
+                        } else if self.glue_sign(jb) as i32 == Shrinking
+                            && self.glue_order(jb) == self.shrink_order(q)
+                            && self.shrink(q) != 0
+                        {
+                            v = MaxDimen;
+                        }
+                        (self.width(q), self.subtype(r) >= ALeaders)
+                    }
+                    _ => (0, false), // a whatsit has no width
+                }
+            };
+            if found {
+                if v < MaxDimen {
+                    v += d;
+                    w = v;
+                } else {
+                    return MaxDimen;
+                }
+            } else if v < MaxDimen {
+                v += d;
+            }
+            p = self.link(r) as usize;
+        }
+        w
+    }
+
+    // 1136
+    pub fn push_math(&mut self, c: QuarterWord) {
+        self.push_nest();
+        self.set_mode(-Mmode);
+        self.set_incompleat_noad(Null as i32);
+        self.new_save_level(c);
+    }
+
+    // 1139
+    pub fn go_into_ordinary_math_mode(&mut self) {
+        self.push_math(MathShiftGroup);
+        self.eq_word_define((IntBase + CurFamCode) as Pointer, -1);
+        if self.equiv(EveryMathLoc as usize) != Null {
+            self.begin_token_list(self.equiv(EveryMathLoc as usize), EveryMathText);
+        }
+    }
+
+    // 1142
+    // \eqno and \leqno
+    pub fn start_eq_no(&mut self) {
+        self.set_saved(0, self.cur_chr as i32);
+        self.save_ptr += 1;
+        self.go_into_ordinary_math_mode();
+    }
+
+    // 1151
+    pub fn fam_in_range(&self) -> bool {
+        (0..16).contains(&self.int_par(CurFamCode))
+    }
+
+    // 1152
+    // treat cur_chr as an active character
+    pub fn treat_cur_chr_as_active(&mut self) {
+        self.cur_cs = self.cur_chr + ActiveBase;
+        self.cur_cmd = self.eq_type(self.cur_cs as usize);
+        self.cur_chr = self.equiv(self.cur_cs as usize);
+        self.x_token();
+        self.back_input();
+    }
+
+    // 1151
+    // scans a math field into noad field p
+    pub fn scan_math(&mut self, p: usize) {
+        let c = loop {
+            // 404: get the next non-blank non-relax non-call token
+            loop {
+                self.get_x_token();
+                if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                    break;
+                }
+            }
+            match self.cur_cmd {
+                Letter | OtherChar | CharGiven => {
+                    let c = self.math_code(self.cur_chr) as i32;
+                    if c == 0o100000 {
+                        self.treat_cur_chr_as_active();
+                        continue;
+                    }
+                    break c;
+                }
+                CharNum => {
+                    self.scan_char_num();
+                    self.cur_chr = self.cur_val as HalfWord;
+                    self.cur_cmd = CharGiven;
+                    let c = self.math_code(self.cur_chr) as i32;
+                    if c == 0o100000 {
+                        self.treat_cur_chr_as_active();
+                        continue;
+                    }
+                    break c;
+                }
+                MathCharNum => {
+                    self.scan_fifteen_bit_int();
+                    break self.cur_val;
+                }
+                MathGiven => break self.cur_chr as i32,
+                DelimNum => {
+                    self.scan_twenty_seven_bit_int();
+                    break self.cur_val / 0o10000;
+                }
+                _ => {
+                    // 1153: scan a subformula enclosed in braces and return
+                    self.back_input();
+                    self.scan_left_brace();
+                    self.set_saved(0, p as i32);
+                    self.save_ptr += 1;
+                    self.push_math(MathGroup);
+                    return;
+                }
+            }
+        };
+        self.set_math_type(p, MathChar);
+        self.set_character(p, (c % 256) as u8);
+        if c >= VarCode as i32 && self.fam_in_range() {
+            self.set_fam(p, self.int_par(CurFamCode) as QuarterWord);
+        } else {
+            self.set_fam(p, ((c / 256) % 16) as QuarterWord);
+        }
+    }
+
+    // 1155
+    // appends a math character with math code c to the current mlist
+    pub fn set_math_char(&mut self, c: i32) {
+        if c >= 0o100000 {
+            self.treat_cur_chr_as_active();
+        } else {
+            let p = self.new_noad();
+            let n = TexState::nucleus(p as usize);
+            self.set_math_type(n, MathChar);
+            self.set_character(n, (c % 256) as u8);
+            self.set_fam(n, ((c / 256) % 16) as QuarterWord);
+            if c >= VarCode as i32 {
+                if self.fam_in_range() {
+                    self.set_fam(n, self.int_par(CurFamCode) as QuarterWord);
+                }
+                self.set_type(p as usize, OrdNoad);
+            } else {
+                self.set_type(p as usize, OrdNoad + (c / 0o10000) as QuarterWord);
+            }
+            self.tail_append(p);
+        }
+    }
+
+    // 1159
+    // \limits, \nolimits and \displaylimits
+    pub fn math_limit_switch(&mut self) {
+        if self.head() != self.tail() && self.a_type(self.tail() as usize) == OpNoad {
+            self.set_subtype(self.tail() as usize, self.cur_chr as QuarterWord);
+            return;
+        }
+        self.print_err("Limit controls must follow a math operator");
+        self.help(&["I'm ignoring this misplaced \\limits or \\nolimits command."]);
+        self.error();
+    }
+
+    // 1160
+    // scans a delimiter specification into the four-quarter word p; r means
+    // the delimiter code is given explicitly by \radical
+    pub fn scan_delimiter(&mut self, p: usize, r: bool) {
+        if r {
+            self.scan_twenty_seven_bit_int();
+        } else {
+            loop {
+                self.get_x_token();
+                if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                    break;
+                }
+            }
+            match self.cur_cmd {
+                Letter | OtherChar => self.cur_val = self.del_code(self.cur_chr),
+                DelimNum => self.scan_twenty_seven_bit_int(),
+                _ => self.cur_val = -1,
+            }
+        }
+        if self.cur_val < 0 {
+            // 1161: report that an invalid delimiter code is being changed to null
+            self.print_err("Missing delimiter (. inserted)");
+            self.help(&["I was expecting to see something like `(' or `\\{' or",
+                "`\\}' here. If you typed, e.g., `{' instead of `\\{', you",
+                "should probably delete the `{' by typing `1' now, so that",
+                "braces don't get unbalanced. Otherwise just proceed.",
+                "Acceptable delimiters are characters whose \\delcode is",
+                "nonnegative, or you can use `\\delimiter <delimiter code>'."]);
+            self.back_error();
+            self.cur_val = 0;
+        }
+        let v = self.cur_val;
+        self.mem[p].set_qqqq(FourQuarters {
+            b0: ((v / 0o4000000) % 16) as QuarterWord,
+            b1: ((v / 0o10000) % 256) as QuarterWord,
+            b2: ((v / 256) % 16) as QuarterWord,
+            b3: (v % 256) as QuarterWord,
+        });
+    }
+
+    // 1163
+    // \radical
+    pub fn math_radical(&mut self) {
+        let p = self.get_node(RadicalNoadSize);
+        self.tail_append(p);
+        let t = p as usize;
+        self.set_type(t, RadicalNoad);
+        self.set_subtype(t, Normal as QuarterWord);
+        self.set_empty_field(TexState::nucleus(t));
+        self.set_empty_field(TexState::subscr(t));
+        self.set_empty_field(TexState::supscr(t));
+        self.scan_delimiter(TexState::left_delimiter(t), true);
+        self.scan_math(TexState::nucleus(t));
+    }
+
+    // 1165
+    // \mathaccent, or \accent in math mode
+    pub fn math_ac(&mut self) {
+        if self.cur_cmd == Accent {
+            // 1166: complain that the user should have said \mathaccent
+            self.print_err("Please use ");
+            self.print_esc("mathaccent");
+            self.print(" for accents in math mode");
+            self.help(&["I'm changing \\accent to \\mathaccent here; wish me luck.",
+                "(Accents are not the same in formulas as they are in text.)"]);
+            self.error();
+        }
+        let p = self.get_node(AccentNoadSize);
+        self.tail_append(p);
+        let t = p as usize;
+        self.set_type(t, AccentNoad);
+        self.set_subtype(t, Normal as QuarterWord);
+        self.set_empty_field(TexState::nucleus(t));
+        self.set_empty_field(TexState::subscr(t));
+        self.set_empty_field(TexState::supscr(t));
+        let a = TexState::accent_chr(t);
+        self.set_math_type(a, MathChar);
+        self.scan_fifteen_bit_int();
+        self.set_character(a, (self.cur_val % 256) as u8);
+        if self.cur_val >= VarCode as i32 && self.fam_in_range() {
+            self.set_fam(a, self.int_par(CurFamCode) as QuarterWord);
+        } else {
+            self.set_fam(a, ((self.cur_val / 256) % 16) as QuarterWord);
+        }
+        self.scan_math(TexState::nucleus(t));
+    }
+
+    // 1172
+    // \mathchoice
+    pub fn append_choices(&mut self) {
+        let p = self.new_choice();
+        self.tail_append(p);
+        self.save_ptr += 1;
+        self.set_saved(-1, 0);
+        self.push_math(MathChoiceGroup);
+        self.scan_left_brace();
+    }
+
+    // 1174
+    // finishes one of the four mlists of a \mathchoice
+    pub fn build_choices(&mut self) {
+        self.unsave();
+        let p = self.fin_mlist(Null); // the current mlist
+        let t = self.tail() as usize;
+        match self.saved(-1) {
+            0 => self.set_display_mlist(t, p),
+            1 => self.set_text_mlist(t, p),
+            2 => self.set_script_mlist(t, p),
+            _ => {
+                self.set_script_script_mlist(t, p);
+                self.save_ptr -= 1;
+                return;
+            }
+        } // there are no other cases
+        self.set_saved(-1, self.saved(-1) + 1);
+        self.push_math(MathChoiceGroup);
+        self.scan_left_brace();
+    }
+
+    // 1176
+    // `^' and `_'
+    pub fn sub_sup(&mut self) {
+        let mut t = Empty as HalfWord; // type of previous sub/superscript
+        let mut p = Null as usize; // field to be filled by scan_math
+        if self.tail() != self.head() && self.scripts_allowed(self.tail() as usize) {
+            p = TexState::supscr(self.tail() as usize) + (self.cur_cmd - SupMark) as usize; // supscr or subscr
+            t = self.math_type(p);
+        }
+        if p == Null as usize || t != Empty as HalfWord {
+            // 1177: insert a dummy noad to be sub/superscripted
+            let q = self.new_noad();
+            self.tail_append(q);
+            p = TexState::supscr(self.tail() as usize) + (self.cur_cmd - SupMark) as usize;
+            if t != Empty as HalfWord {
+                if self.cur_cmd == SupMark {
+                    self.print_err("Double superscript");
+                    self.help(&["I treat `x^1^2' essentially like `x^1{}^2'."]);
+                } else {
+                    self.print_err("Double subscript");
+                    self.help(&["I treat `x_1_2' essentially like `x_1{}_2'."]);
+                }
+                self.error();
+            }
+        }
+        self.scan_math(p);
+    }
+
+    // 1181
+    // \above, \over, \atop and their relatives with delimiters
+    pub fn math_fraction(&mut self) {
+        let c = self.cur_chr; // the type of generalized fraction we are scanning
+        if self.incompleat_noad() != Null as i32 {
+            // 1183: ignore the fraction operation and complain about this ambiguous case
+            if c >= DelimitedCode {
+                self.scan_delimiter(Garbage, false);
+                self.scan_delimiter(Garbage, false);
+            }
+            if c % DelimitedCode == AboveCode {
+                self.scan_normal_dimen();
+            }
+            self.print_err("Ambiguous; you need another { and }");
+            self.help(&["I'm ignoring this fraction specification, since I don't",
+                "know whether a construction like `x \\over y \\over z'",
+                "means `{x \\over y} \\over z' or `x \\over {y \\over z}'."]);
+            self.error();
+        } else {
+            let n = self.get_node(FractionNoadSize);
+            self.set_incompleat_noad(n as i32);
+            let n = n as usize;
+            self.set_type(n, FractionNoad);
+            self.set_subtype(n, Normal as QuarterWord);
+            self.set_math_type(TexState::numerator(n), SubMlist);
+            self.set_info(TexState::numerator(n), self.link(self.head() as usize));
+            self.set_empty_field(TexState::denominator(n));
+            self.mem[TexState::left_delimiter(n)].set_qqqq(self.null_delimiter);
+            self.mem[TexState::right_delimiter(n)].set_qqqq(self.null_delimiter);
+            self.set_link(self.head() as usize, Null);
+            self.set_tail(self.head());
+            // 1182: use code c to distinguish between generalized fractions
+            if c >= DelimitedCode {
+                self.scan_delimiter(TexState::left_delimiter(n), false);
+                self.scan_delimiter(TexState::right_delimiter(n), false);
+            }
+            match c % DelimitedCode {
+                AboveCode => {
+                    self.scan_normal_dimen();
+                    self.set_thickness(n, self.cur_val);
+                }
+                OverCode => self.set_thickness(n, DefaultCode),
+                _ => self.set_thickness(n, 0),
+            } // there are no other cases
+        }
+    }
+
+    // 1184
+    // finishes the current mlist, appending p, and pops the nest
+    pub fn fin_mlist(&mut self, p: Pointer) -> Pointer {
+        let q; // the mlist to return
+        let n = self.incompleat_noad() as usize;
+        if n != Null as usize {
+            // 1185: compleat the incompleat noad
+            self.set_math_type(TexState::denominator(n), SubMlist);
+            self.set_info(TexState::denominator(n), self.link(self.head() as usize));
+            if p == Null {
+                q = n as Pointer;
+            } else {
+                q = self.info(TexState::numerator(n));
+                if self.a_type(q as usize) != LeftNoad {
+                    self.confusion("right");
+                }
+                self.set_info(TexState::numerator(n), self.link(q as usize));
+                self.set_link(q as usize, n as Pointer);
+                self.set_link(n, p);
+            }
+        } else {
+            self.set_link(self.tail() as usize, p);
+            q = self.link(self.head() as usize);
+        }
+        self.pop_nest();
+        q
+    }
+
+    // 1186
+    // the math_group case of handle_right_brace
+    pub fn finish_math_group(&mut self) {
+        self.unsave();
+        self.save_ptr -= 1;
+        let s = self.saved(0) as usize;
+        self.set_math_type(s, SubMlist);
+        let p = self.fin_mlist(Null);
+        self.set_info(s, p);
+        if p != Null && self.link(p as usize) == Null {
+            let q = p as usize;
+            if self.a_type(q) == OrdNoad {
+                if self.math_type(TexState::subscr(q)) == Empty as HalfWord
+                    && self.math_type(TexState::supscr(q)) == Empty as HalfWord
+                {
+                    self.mem[s] = self.mem[TexState::nucleus(q)];
+                    self.free_node(p, NoadSize);
+                }
+            } else if self.a_type(q) == AccentNoad
+                && s == TexState::nucleus(self.tail() as usize)
+                && self.a_type(self.tail() as usize) == OrdNoad
+            {
+                // 1187: replace the tail of the list by p
+                let mut r = self.head();
+                while self.link(r as usize) != self.tail() {
+                    r = self.link(r as usize);
+                }
+                self.set_link(r as usize, p);
+                self.free_node(self.tail(), NoadSize);
+                self.set_tail(p);
+            }
+        }
+    }
+
+    // 1191
+    // \left and \right
+    pub fn math_left_right(&mut self) {
+        let t = self.cur_chr as QuarterWord; // left_noad or right_noad
+        if t == RightNoad && self.cur_group.value() != MathLeftGroup {
+            // 1192: try to recover from mismatched \right
+            if self.cur_group.value() == MathShiftGroup {
+                self.scan_delimiter(Garbage, false);
+                self.print_err("Extra ");
+                self.print_esc("right");
+                self.help(&["I'm ignoring a \\right that had no matching \\left."]);
+                self.error();
+            } else {
+                self.off_save();
+            }
+        } else {
+            let mut p = self.new_noad(); // new noad
+            self.set_type(p as usize, t);
+            self.scan_delimiter(TexState::delimiter(p as usize), false);
+            if t == LeftNoad {
+                self.push_math(MathLeftGroup);
+                self.set_link(self.head() as usize, p);
+                self.set_tail(p);
+            } else {
+                p = self.fin_mlist(p);
+                self.unsave(); // end of math_left_group
+                let q = self.new_noad();
+                self.tail_append(q);
+                self.set_type(q as usize, InnerNoad);
+                self.set_math_type(TexState::nucleus(q as usize), SubMlist);
+                self.set_info(TexState::nucleus(q as usize), p);
+            }
+        }
+    }
+
+    // 1195
+    // checks that the necessary fonts for math symbols are present; if not,
+    // flushes the current math lists and returns true
+    pub fn insufficient_math_fonts(&mut self) -> bool {
+        self.check_math_fonts();
+        let lacks = |s: &TexState, k: SmallNumber, n: usize| {
+            [TextSize, ScriptSize, ScriptScriptSize].iter().any(|&z| {
+                let f = s.fam_fnt((k + z) as HalfWord) as InternalFontNumber;
+                s.font_params[f] < n && !matches!(s.font_math[f], MathStatus::Present(_))
+            })
+        };
+        if lacks(self, 2, TotalMathsyParams) {
+            self.print_err("Math formula deleted: Insufficient symbol fonts");
+            self.help(&["Sorry, but I can't typeset math unless \\textfont 2",
+                "and \\scriptfont 2 and \\scriptscriptfont 2 have all",
+                "the \\fontdimen values needed in math symbol fonts."]);
+            self.error();
+            self.flush_math();
+            true
+        } else if lacks(self, 3, TotalMathexParams) {
+            self.print_err("Math formula deleted: Insufficient extension fonts");
+            self.help(&["Sorry, but I can't typeset math unless \\textfont 3",
+                "and \\scriptfont 3 and \\scriptscriptfont 3 have all",
+                "the \\fontdimen values needed in math extension fonts."]);
+            self.error();
+            self.flush_math();
+            true
+        } else {
+            false
+        }
+    }
+
+    // 1197
+    pub fn check_that_another_dollar_follows(&mut self) {
+        self.get_x_token();
+        if self.cur_cmd != MathShift {
+            self.print_err("Display math should end with $$");
+            self.help(&["The `$' that I just saw supposedly matches a previous `$$'.",
+                "So I shall assume that you typed `$$' both times."]);
+            self.back_error();
+        }
+    }
+
+    // 1194
+    // finishes a formula or an equation number at the closing `$'
+    pub fn after_math(&mut self) {
+        let mut danger = self.insufficient_math_fonts(); // not enough symbol fonts are present
+        let mut m = self.mode(); // mmode or -mmode
+        let mut l = false; // `\leqno' instead of `\eqno'
+        let mut p = self.fin_mlist(Null); // the formula; this pops the nest
+        let a; // box containing equation number
+        if self.mode() == -m {
+            // end of equation number
+            self.check_that_another_dollar_follows();
+            self.cur_mlist = p;
+            self.cur_style = TextStyle;
+            self.mlist_penalties = false;
+            self.mlist_to_hlist();
+            a = self.hpack(self.link(TempHead), 0, Additional);
+            self.unsave();
+            self.save_ptr -= 1; // now cur_group=math_shift_group
+            if self.saved(0) == 1 {
+                l = true;
+            }
+            danger = self.insufficient_math_fonts();
+            m = self.mode();
+            p = self.fin_mlist(Null);
+        } else {
+            a = Null;
+        }
+        if m < 0 {
+            // 1196: finish math in text
+            let b = self.new_math(self.dimen_par(MathSurroundCode), Before);
+            self.tail_append(b);
+            self.cur_mlist = p;
+            self.cur_style = TextStyle;
+            self.mlist_penalties = self.mode() > 0;
+            self.mlist_to_hlist();
+            self.set_link(self.tail() as usize, self.link(TempHead));
+            while self.link(self.tail() as usize) != Null {
+                self.set_tail(self.link(self.tail() as usize));
+            }
+            let e = self.new_math(self.dimen_par(MathSurroundCode), After);
+            self.tail_append(e);
+            self.set_space_factor(1000);
+            self.unsave();
+        } else {
+            if a == Null {
+                self.check_that_another_dollar_follows();
+            }
+            self.finish_displayed_math(p, a, l, danger);
+        }
+    }
+
+    // 1199
+    // appends the display p, with equation number a on the left if l, to
+    // the enclosing vertical list
+    pub fn finish_displayed_math(&mut self, p: Pointer, a: Pointer, l: bool, danger: bool) {
+        self.cur_mlist = p;
+        self.cur_style = DisplayStyle;
+        self.mlist_penalties = false;
+        self.mlist_to_hlist();
+        let mut p = self.link(TempHead);
+        self.adjust_tail = AdjustHead as Pointer;
+        let mut b = self.hpack(p, 0, Additional); // box containing the equation
+        p = self.list_ptr(b as usize);
+        let t = self.adjust_tail; // tail of adjustment list
+        self.adjust_tail = Null;
+        let mut w = self.width(b as usize); // width of the equation
+        let z = self.dimen_par(DisplayWidthCode); // width of the line
+        let s = self.dimen_par(DisplayIndentCode); // move the line right this much
+        let (mut e, q) = if a == Null || danger {
+            (0, 0)
+        } else {
+            let e = self.width(a as usize); // width of equation number
+            (e, e + self.math_quad(TextSize)) // e plus math_quad
+        };
+        if w + q > z {
+            // 1201: squeeze the equation as much as possible; if there is an
+            // equation number that should go on a separate line by itself, set e:=0
+            if e != 0
+                && (w - self.total_shrink[Normal as usize] + q <= z
+                    || self.total_shrink[fil as usize] != 0
+                    || self.total_shrink[fill as usize] != 0
+                    || self.total_shrink[filll as usize] != 0)
+            {
+                self.free_node(b, BoxNodeSize);
+                b = self.hpack(p, z - q, Exactly);
+            } else {
+                e = 0;
+                if w > z {
+                    self.free_node(b, BoxNodeSize);
+                    b = self.hpack(p, z, Exactly);
+                }
+            }
+            w = self.width(b as usize);
+        }
+        // 1202: determine the displacement d of the left edge of the
+        // equation, with respect to the line size z, assuming that l=false
+        let mut d = half(z - w); // displacement of equation in the line
+        if e > 0 && d < 2 * e {
+            // too close
+            d = half(z - w - e);
+            if p != Null && !self.is_char_node(p as usize) && self.a_type(p as usize) == GlueNode {
+                d = 0;
+            }
+        }
+        // 1203: append the glue or equation number preceding the display
+        let n = self.new_penalty(self.int_par(PreDisplayPenaltyCode));
+        self.tail_append(n);
+        let (g1, mut g2) = if d + s <= self.dimen_par(PreDisplaySizeCode) || l {
+            // not enough clearance
+            (AboveDisplaySkipCode, BelowDisplaySkipCode)
+        } else {
+            (AboveDisplayShortSkipCode, BelowDisplayShortSkipCode)
+        };
+        if l && e == 0 {
+            // it follows that type(a)=hlist_node
+            self.set_shift_amount(a as usize, s);
+            self.append_to_vlist(a);
+            let n = self.new_penalty(InfPenalty);
+            self.tail_append(n);
+        } else {
+            let g = self.new_param_glue(g1);
+            self.tail_append(g);
+        }
+        // 1204: append the display and perhaps also the equation number
+        if e != 0 {
+            let r = self.new_kern(z - w - e - d);
+            if l {
+                self.set_link(a as usize, r);
+                self.set_link(r as usize, b);
+                b = a;
+                d = 0;
+            } else {
+                self.set_link(b as usize, r);
+                self.set_link(r as usize, a);
+            }
+            b = self.hpack(b, 0, Additional);
+        }
+        self.set_shift_amount(b as usize, s + d);
+        self.append_to_vlist(b);
+        // 1205: append the glue or equation number following the display
+        if a != Null && e == 0 && !l {
+            let n = self.new_penalty(InfPenalty);
+            self.tail_append(n);
+            self.set_shift_amount(a as usize, s + z - self.width(a as usize));
+            self.append_to_vlist(a);
+            g2 = 0;
+        }
+        if t != AdjustHead as Pointer {
+            // migrating material comes after equation number
+            self.set_link(self.tail() as usize, self.link(AdjustHead));
+            self.set_tail(t);
+        }
+        let n = self.new_penalty(self.int_par(PostDisplayPenaltyCode));
+        self.tail_append(n);
+        if g2 > 0 {
+            let g = self.new_param_glue(g2);
+            self.tail_append(g);
+        }
+        self.resume_after_display();
+    }
+
+    // 1200
+    // returns to horizontal mode after a display, where the paragraph goes on
+    pub fn resume_after_display(&mut self) {
+        if self.cur_group.value() != MathShiftGroup {
+            self.confusion("display");
+        }
+        self.unsave();
+        self.set_prev_graf(self.prev_graf() + 3);
+        self.push_nest();
+        self.set_mode(Hmode);
+        self.set_space_factor(1000);
+        self.set_cur_lang();
+        self.set_clang(self.cur_lang as HalfWord);
+        self.set_prev_graf((norm_min(self.int_par(LeftHyphenMinCode)) * 0o100
+            + norm_min(self.int_par(RightHyphenMinCode))) * 0o200000 + self.cur_lang as i32);
+        // 443: scan an optional space
+        self.get_x_token();
+        if self.cur_cmd != Spacer {
+            self.back_input();
+        }
+        if self.nest_ptr == 1 {
+            self.build_page();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::{math_extension, math_symbols, text_font};
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn equation_numbers() {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("sy.tfm", math_symbols().bytes());
+        vfs.add_file("ex.tfm", math_extension().bytes());
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\catcode`\\$=3
+\\font\\rm=text \\font\\sy=sy \\font\\ex=ex
+\\textfont0=\\rm \\scriptfont0=\\rm \\scriptscriptfont0=\\rm \\textfont1=\\rm \\scriptfont1=\\rm \\scriptscriptfont1=\\rm
+\\textfont2=\\sy \\scriptfont2=\\sy \\scriptscriptfont2=\\sy \\textfont3=\\ex \\scriptfont3=\\ex \\scriptscriptfont3=\\ex
+\\showboxdepth=100 \\showboxbreadth=100 \\scrollmode \\hsize=100pt \\parfillskip=0pt plus 1fil \\rm
+\\abovedisplayskip=3pt \\belowdisplayskip=4pt \\abovedisplayshortskip=1pt \\belowdisplayshortskip=2pt
+\\setbox0\\vbox{\\noindent a$$a\\eqno(1)$$ $$aa\\leqno(2)$$}\\showbox0
+\\end
+");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        let start = log.find("> \\box0=").expect(&log);
+        // the first display is short enough to use the short skips, with its
+        // number at the right margin; the second starts a paragraph of its own
+        assert_eq!(log[start..].split("\n\n").next().unwrap(), concat!(
+            "> \\box0=\n",
+            "\\vbox(30.99995+0.0)x100.0\n",
+            ".\\hbox(6.99998+0.0)x100.0, glue set 95.0fil\n",
+            "..\\rm a\n",
+            "..\\penalty 10000\n",
+            "..\\glue(\\parfillskip) 0.0 plus 1.0fil\n",
+            "..\\glue(\\rightskip) 0.0\n",
+            ".\\penalty 0\n",
+            ".\\glue(\\abovedisplayshortskip) 1.0\n",
+            ".\\glue(\\lineskip) 0.0\n",
+            ".\\hbox(6.99998+0.0)x52.5, shifted 47.5\n",
+            "..\\hbox(6.99998+0.0)x5.0\n",
+            "...\\rm a\n",
+            "..\\kern37.5\n",
+            "..\\hbox(6.99998+0.0)x10.0\n",
+            "...\\rm (\n",
+            "...\\rm 1\n",
+            "...\\rm )\n",
+            ".\\penalty 0\n",
+            ".\\glue(\\belowdisplayshortskip) 2.0\n",
+            ".\\penalty 0\n",
+            ".\\glue(\\abovedisplayskip) 3.0\n",
+            ".\\glue(\\lineskip) 0.0\n",
+            ".\\hbox(6.99998+0.0)x55.0\n",
+            "..\\hbox(6.99998+0.0)x10.0\n",
+            "...\\rm (\n",
+            "...\\rm 2\n",
+            "...\\rm )\n",
+            "..\\kern35.0\n",
+            "..\\hbox(6.99998+0.0)x10.0\n",
+            "...\\rm a\n",
+            "...\\rm a\n",
+            ".\\penalty 0\n",
+            ".\\glue(\\belowdisplayskip) 4.0",
+        ));
+    }
+}
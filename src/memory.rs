@@ -0,0 +1,272 @@
+use crate::*;
+
+// 124
+pub const EmptyFlag: HalfWord = MaxHalfWord; // the link of an empty variable-size node
+
+impl TexState {
+    // 124
+    // tests for empty node
+    pub fn is_empty(&self, n: usize) -> bool {
+        self.link(n) == EmptyFlag
+    }
+
+    // the size field in empty variable-size nodes
+    pub fn node_size(&self, n: usize) -> HalfWord {
+        self.info(n)
+    }
+
+    pub fn set_node_size(&mut self, n: usize, value: HalfWord) {
+        self.set_info(n, value);
+    }
+
+    // left link in doubly-linked list of empty nodes
+    pub fn llink(&self, n: usize) -> HalfWord {
+        self.info(n + 1)
+    }
+
+    pub fn set_llink(&mut self, n: usize, value: HalfWord) {
+        self.set_info(n + 1, value);
+    }
+
+    // right link in doubly-linked list of empty nodes
+    pub fn rlink(&self, n: usize) -> HalfWord {
+        self.link(n + 1)
+    }
+
+    pub fn set_rlink(&mut self, n: usize, value: HalfWord) {
+        self.set_link(n + 1, value);
+    }
+
+    // 120
+    // single-word node allocation
+    pub fn get_avail(&mut self) -> Pointer {
+        let mut p = self.avail; // get top location in the avail stack
+        if p != Null {
+            self.avail = self.link(self.avail as usize); // and pop it off
+        } else if (self.mem_end as usize) < MemMax {
+            // or go into virgin territory
+            self.mem_end += 1;
+            p = self.mem_end;
+        } else {
+            self.hi_mem_min -= 1;
+            p = self.hi_mem_min;
+            if self.hi_mem_min <= self.lo_mem_max {
+                self.runaway(); // if memory is exhausted, display possible runaway text
+                self.overflow("main memory size", (MemMax + 1 - MemMin) as i32); // quit; all one-word nodes are busy
+            }
+        }
+        self.set_link(p as usize, Null); // provide an oft-desired initialization of the new node
+        self.dyn_used += 1;
+        p
+    }
+
+    // 121
+    // single-word node liberation
+    pub fn free_avail(&mut self, p: Pointer) {
+        self.set_link(p as usize, self.avail);
+        self.avail = p;
+        self.dyn_used -= 1;
+    }
+
+    // 123
+    // makes list of single-word nodes available
+    pub fn flush_list(&mut self, p: Pointer) {
+        if p != Null {
+            let mut r = p;
+            let mut q;
+            loop {
+                q = r;
+                r = self.link(r as usize);
+                self.dyn_used -= 1;
+                if r == Null {
+                    break;
+                }
+            }
+            // now q is the last node on the list
+            self.set_link(q as usize, self.avail);
+            self.avail = p;
+        }
+    }
+
+    // 125
+    // variable-size node allocation
+    pub fn get_node(&mut self, s: usize) -> Pointer {
+        'restart: loop {
+            let mut p = self.rover as usize; // start at some free node in the ring
+            loop {
+                // 127: try to allocate within node p and its physical successors
+                let mut q = p + self.node_size(p) as usize; // find the physical successor
+                while self.is_empty(q) {
+                    // merge node p with node q
+                    let t = self.rlink(q);
+                    if q == self.rover as usize {
+                        self.rover = t;
+                    }
+                    self.set_llink(t as usize, self.llink(q));
+                    self.set_rlink(self.llink(q) as usize, t);
+                    q += self.node_size(q) as usize;
+                }
+                let r = q as i64 - s as i64;
+                if r > p as i64 + 1 {
+                    // 128: allocate from the top of node p
+                    let r = r as usize;
+                    self.set_node_size(p, (r - p) as HalfWord); // store the remaining size
+                    self.rover = p as HalfWord; // start searching here next time
+                    return self.found_node(r, s);
+                }
+                if r == p as i64 && self.rlink(p) as usize != p {
+                    // 129: allocate entire node p
+                    self.rover = self.rlink(p);
+                    let t = self.llink(p);
+                    self.set_llink(self.rover as usize, t);
+                    self.set_rlink(t as usize, self.rover);
+                    return self.found_node(p, s);
+                }
+                self.set_node_size(p, (q - p) as HalfWord); // reset the size in case it grew
+                p = self.rlink(p) as usize; // move to the next node in the ring
+                if p == self.rover as usize {
+                    break; // repeat until the whole list has been traversed
+                }
+            }
+            if s == 0o10000000000 {
+                return MaxHalfWord;
+            }
+            if (self.lo_mem_max as usize) + 2 < self.hi_mem_min as usize
+                && (self.lo_mem_max as usize) + 2 <= MemBot + MaxHalfWord as usize
+            {
+                // 126: grow more variable-size memory and restart
+                let mut t = if self.hi_mem_min - self.lo_mem_max >= 1998 {
+                    self.lo_mem_max as usize + 1000
+                } else {
+                    // lo_mem_max+2<=t<hi_mem_min
+                    self.lo_mem_max as usize + 1 + (self.hi_mem_min - self.lo_mem_max) as usize / 2
+                };
+                let p = self.llink(self.rover as usize);
+                let q = self.lo_mem_max;
+                self.set_rlink(p as usize, q);
+                self.set_llink(self.rover as usize, q);
+                if t > MemBot + MaxHalfWord as usize {
+                    t = MemBot + MaxHalfWord as usize;
+                }
+                self.set_rlink(q as usize, self.rover);
+                self.set_llink(q as usize, p);
+                self.set_link(q as usize, EmptyFlag);
+                self.set_node_size(q as usize, (t - q as usize) as HalfWord);
+                self.lo_mem_max = t as HalfWord;
+                self.set_link(t, Null);
+                self.set_info(t, Null);
+                self.rover = q;
+                continue 'restart;
+            }
+            self.overflow("main memory size", (MemMax + 1 - MemMin) as i32);
+        }
+    }
+
+    fn found_node(&mut self, r: usize, s: usize) -> Pointer {
+        self.set_link(r, Null); // this node is now nonempty
+        self.var_used += s as i32; // maintain usage statistics
+        r as Pointer
+    }
+
+    // 130
+    // variable-size node liberation
+    pub fn free_node(&mut self, p: Pointer, s: usize) {
+        let p = p as usize;
+        self.set_node_size(p, s as HalfWord);
+        self.set_link(p, EmptyFlag);
+        let q = self.llink(self.rover as usize);
+        self.set_llink(p, q);
+        self.set_rlink(p, self.rover);
+        self.set_llink(self.rover as usize, p as HalfWord);
+        self.set_rlink(q as usize, p as HalfWord);
+        self.var_used -= s as i32;
+    }
+
+    // 131
+    // sorts the available variable-size nodes by location, used just before dumping
+    pub fn sort_avail(&mut self) {
+        self.get_node(0o10000000000); // merge adjacent free areas
+        let mut p = self.rlink(self.rover as usize);
+        self.set_rlink(self.rover as usize, MaxHalfWord);
+        let old_rover = self.rover;
+        while p != old_rover {
+            // 132: sort p into the list starting at rover and advance p to rlink(p)
+            if p < self.rover {
+                let q = p;
+                p = self.rlink(q as usize);
+                self.set_rlink(q as usize, self.rover);
+                self.rover = q;
+            } else {
+                let mut q = self.rover;
+                while self.rlink(q as usize) < p {
+                    q = self.rlink(q as usize);
+                }
+                let r = self.rlink(p as usize);
+                self.set_rlink(p as usize, self.rlink(q as usize));
+                self.set_rlink(q as usize, p);
+                p = r;
+            }
+        }
+        p = self.rover;
+        while self.rlink(p as usize) != MaxHalfWord {
+            self.set_llink(self.rlink(p as usize) as usize, p);
+            p = self.rlink(p as usize);
+        }
+        self.set_rlink(p as usize, self.rover);
+        self.set_llink(self.rover as usize, p);
+    }
+
+    // 164
+    // initialize the dynamic memory, done by INITEX only
+    pub fn init_mem(&mut self) {
+        for k in MemBot + 1..=LoMemStatMax {
+            self.mem[k].set_sc(0); // all glue dimensions are zeroed
+        }
+        let mut k = MemBot;
+        while k <= LoMemStatMax {
+            // set first words of glue specifications
+            self.set_glue_ref_count(k, Null + 1);
+            self.set_stretch_order(k, Normal as u8);
+            self.set_shrink_order(k, Normal as u8);
+            k += GlueSpecSize;
+        }
+        self.set_stretch(FilGlue, unity);
+        self.set_stretch_order(FilGlue, fil as u8);
+        self.set_stretch(FillGlue, unity);
+        self.set_stretch_order(FillGlue, fill as u8);
+        self.set_stretch(SsGlue, unity);
+        self.set_stretch_order(SsGlue, fil as u8);
+        self.set_shrink(SsGlue, unity);
+        self.set_shrink_order(SsGlue, fil as u8);
+        self.set_stretch(FilNegGlue, -unity);
+        self.set_stretch_order(FilNegGlue, fil as u8);
+        self.rover = (LoMemStatMax + 1) as HalfWord;
+        self.set_link(self.rover as usize, EmptyFlag);
+        self.set_node_size(self.rover as usize, 1000); // which is a 1000-word available node
+        self.set_llink(self.rover as usize, self.rover);
+        self.set_rlink(self.rover as usize, self.rover);
+        self.lo_mem_max = self.rover + 1000;
+        self.set_link(self.lo_mem_max as usize, Null);
+        self.set_info(self.lo_mem_max as usize, Null);
+        for k in HiMemStatMin..=MemTop {
+            self.mem[k] = self.mem[self.lo_mem_max as usize]; // clear list heads
+        }
+        // 790, 797, 820, 981, 988: initialize the special list heads and constant nodes
+        self.set_info(OmitTemplate, EndTemplateToken);
+        self.set_link(EndSpan, MaxQuarterWord as HalfWord + 1);
+        self.set_info(EndSpan, Null);
+        self.set_type(LastActive as usize, Hyphenated);
+        self.set_line_number(LastActive as usize, MaxHalfWord);
+        self.set_subtype(LastActive as usize, 0); // the subtype is never examined by the algorithm
+        self.set_subtype(PageInsHead, 255);
+        self.set_type(PageInsHead, SplitUp);
+        self.set_link(PageInsHead, PageInsHead as HalfWord);
+        self.set_type(PageHead, GlueNode);
+        self.set_subtype(PageHead, Normal as u8);
+        self.avail = Null;
+        self.mem_end = MemTop as HalfWord;
+        self.hi_mem_min = HiMemStatMin as HalfWord;
+        self.var_used = (LoMemStatMax + 1 - MemBot) as i32;
+        self.dyn_used = HiMemStatUsage as i32; // initialize statistics
+    }
+}
@@ -0,0 +1,752 @@
+use crate::*;
+
+// 1222
+pub const CharDefCode: HalfWord = 0; // shorthand_def for \chardef
+pub const MathCharDefCode: HalfWord = 1; // shorthand_def for \mathchardef
+pub const CountDefCode: HalfWord = 2; // shorthand_def for \countdef
+pub const DimenDefCode: HalfWord = 3; // shorthand_def for \dimendef
+pub const SkipDefCode: HalfWord = 4; // shorthand_def for \skipdef
+pub const MuSkipDefCode: HalfWord = 5; // shorthand_def for \muskipdef
+pub const ToksDefCode: HalfWord = 6; // shorthand_def for \toksdef
+
+// 1291
+pub const ShowCode: HalfWord = 0; // \show
+pub const ShowBoxCode: HalfWord = 1; // \showbox
+pub const ShowTheCode: HalfWord = 2; // \showthe
+pub const ShowListsCode: HalfWord = 3; // \showlists
+
+impl TexState {
+    // 1214
+    pub fn define(&mut self, global: bool, p: Pointer, t: QuarterWord, e: HalfWord) {
+        if global {
+            self.geq_define(p, t, e);
+        } else {
+            self.eq_define(p, t, e);
+        }
+    }
+
+    pub fn word_define(&mut self, global: bool, p: Pointer, w: i32) {
+        if global {
+            self.geq_word_define(p, w);
+        } else {
+            self.eq_word_define(p, w);
+        }
+    }
+
+    // 1211
+    // carries out an assignment, possibly preceded by \global, \long or \outer
+    pub fn prefixed_command(&mut self) {
+        let mut a: HalfWord = 0; // accumulated prefix codes so far
+        while self.cur_cmd == Prefix {
+            if (a / self.cur_chr).is_multiple_of(2) {
+                a += self.cur_chr;
+            }
+            // 404: get the next non-blank non-relax non-call token
+            loop {
+                self.get_x_token();
+                if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                    break;
+                }
+            }
+            if self.cur_cmd <= MaxNonPrefixedCommand {
+                // 1212: discard erroneous prefixes and return
+                self.print_err("You can't use a prefix with `");
+                self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+                self.print_char('\'' as u8);
+                self.help(&["I'll pretend you didn't say \\long or \\outer or \\global."]);
+                self.back_error();
+                return;
+            }
+        }
+        // 1213: discard the prefixes \long and \outer if they are irrelevant
+        if self.cur_cmd != Def && !a.is_multiple_of(4) {
+            self.print_err("You can't use `");
+            self.print_esc("long");
+            self.print("' or `");
+            self.print_esc("outer");
+            self.print("' with `");
+            self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+            self.print_char('\'' as u8);
+            self.help(&["I'll pretend you didn't say \\long or \\outer here."]);
+            self.error();
+        }
+        // 1214: adjust for the setting of \globaldefs
+        if self.int_par(GlobalDefsCode) != 0 {
+            if self.int_par(GlobalDefsCode) < 0 {
+                if a >= 4 {
+                    a -= 4;
+                }
+            } else if a < 4 {
+                a += 4;
+            }
+        }
+        let global = a >= 4;
+        match self.cur_cmd {
+            // 1217
+            SetFont => self.define(global, CurFontLoc, Data, self.cur_chr),
+            // 1218
+            Def => {
+                let mut global = global;
+                if self.cur_chr % 2 == 1 && !global && self.int_par(GlobalDefsCode) >= 0 {
+                    a += 4;
+                    global = true;
+                }
+                let e = self.cur_chr >= 2; // should the definition be expanded?
+                self.get_r_token();
+                let p = self.cur_cs;
+                self.scan_toks(true, e);
+                self.define(global, p, Call + (a % 4) as QuarterWord, self.def_ref);
+            }
+            // 1221
+            Let => {
+                let n = self.cur_chr;
+                self.get_r_token();
+                let p = self.cur_cs;
+                if n == Normal as HalfWord {
+                    loop {
+                        self.get_token();
+                        if self.cur_cmd != Spacer {
+                            break;
+                        }
+                    }
+                    if self.cur_tok as i32 == other_token + '=' as i32 {
+                        self.get_token();
+                        if self.cur_cmd == Spacer {
+                            self.get_token();
+                        }
+                    }
+                } else {
+                    self.get_token();
+                    let q = self.cur_tok;
+                    self.get_token();
+                    self.back_input();
+                    self.cur_tok = q;
+                    self.back_input(); // look ahead, then back up
+                } // note that back_input doesn't affect cur_cmd, cur_chr
+                if self.cur_cmd >= Call {
+                    self.add_token_ref(self.cur_chr);
+                }
+                self.define(global, p, self.cur_cmd, self.cur_chr);
+            }
+            // 1224
+            ShorthandDef => {
+                let n = self.cur_chr;
+                self.get_r_token();
+                let p = self.cur_cs;
+                self.define(global, p, Relax, 256);
+                self.scan_optional_equals();
+                match n {
+                    CharDefCode => {
+                        self.scan_char_num();
+                        self.define(global, p, CharGiven, self.cur_val as HalfWord);
+                    }
+                    MathCharDefCode => {
+                        self.scan_fifteen_bit_int();
+                        self.define(global, p, MathGiven, self.cur_val as HalfWord);
+                    }
+                    _ => {
+                        self.scan_eight_bit_int();
+                        let v = self.cur_val as HalfWord;
+                        match n {
+                            CountDefCode => self.define(global, p, AssignInt, CountBase + v),
+                            DimenDefCode => self.define(global, p, AssignDimen, ScaledBase + v),
+                            SkipDefCode => self.define(global, p, AssignGlue, SkipBase + v),
+                            MuSkipDefCode => self.define(global, p, AssignMuGlue, MuSkipBase + v),
+                            _ => self.define(global, p, AssignToks, ToksBase + v),
+                        } // there are no other cases
+                    }
+                }
+            }
+            // 1225
+            ReadToCs => self.read_to_cs(global),
+            // 1226
+            ToksRegister | AssignToks => {
+                let mut q = self.cur_cs;
+                let p = if self.cur_cmd == ToksRegister {
+                    self.scan_eight_bit_int();
+                    ToksBase + self.cur_val as HalfWord
+                } else {
+                    self.cur_chr // p=every_par_loc or output_routine_loc or ...
+                };
+                self.scan_optional_equals();
+                loop {
+                    self.get_x_token();
+                    if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                        break;
+                    }
+                }
+                if self.cur_cmd != LeftBrace {
+                    // 1227: if the right-hand side is a token parameter or
+                    // token register, finish the assignment
+                    if self.cur_cmd == ToksRegister {
+                        self.scan_eight_bit_int();
+                        self.cur_cmd = AssignToks;
+                        self.cur_chr = ToksBase + self.cur_val as HalfWord;
+                    }
+                    if self.cur_cmd == AssignToks {
+                        let q = self.equiv(self.cur_chr as usize);
+                        if q == Null {
+                            self.define(global, p, UndefinedCs, Null);
+                        } else {
+                            self.add_token_ref(q);
+                            self.define(global, p, Call, q);
+                        }
+                        self.insert_after_token();
+                        return;
+                    }
+                }
+                self.back_input();
+                self.cur_cs = q;
+                q = self.scan_toks(false, false);
+                if self.link(self.def_ref as usize) == Null {
+                    // empty list: revert to the default
+                    self.define(global, p, UndefinedCs, Null);
+                    self.free_avail(self.def_ref);
+                } else {
+                    if p == OutputRoutineLoc {
+                        // enclose in curlies
+                        let r = self.get_avail();
+                        self.set_link(q as usize, r);
+                        q = r;
+                        self.set_info(q as usize, (right_brace_token + '}' as i32) as HalfWord);
+                        q = self.get_avail();
+                        self.set_info(q as usize, (left_brace_token + '{' as i32) as HalfWord);
+                        self.set_link(q as usize, self.link(self.def_ref as usize));
+                        self.set_link(self.def_ref as usize, q);
+                    }
+                    self.define(global, p, Call, self.def_ref);
+                }
+            }
+            // 1228
+            AssignInt => {
+                let p = self.cur_chr;
+                self.scan_optional_equals();
+                self.scan_int();
+                self.word_define(global, p, self.cur_val);
+            }
+            AssignDimen => {
+                let p = self.cur_chr;
+                self.scan_optional_equals();
+                self.scan_normal_dimen();
+                self.word_define(global, p, self.cur_val);
+            }
+            AssignGlue | AssignMuGlue => {
+                let p = self.cur_chr;
+                let n = self.cur_cmd;
+                self.scan_optional_equals();
+                if n == AssignMuGlue {
+                    self.scan_glue(MuVal);
+                } else {
+                    self.scan_glue(GlueVal);
+                }
+                self.trap_zero_glue();
+                self.define(global, p, GlueRef, self.cur_val as HalfWord);
+            }
+            // 1232
+            DefCode => {
+                // 1233: let n be the largest legal code value, based on cur_chr
+                let n = match self.cur_chr {
+                    CatCodeBase => MaxCharCode as i32,
+                    MathCodeBase => 0o100000,
+                    SfCodeBase => 0o77777,
+                    DelCodeBase => 0o77777777,
+                    _ => 255,
+                };
+                let mut p = self.cur_chr;
+                self.scan_char_num();
+                p += self.cur_val as HalfWord;
+                self.scan_optional_equals();
+                self.scan_int();
+                if (self.cur_val < 0 && p < DelCodeBase) || self.cur_val > n {
+                    self.print_err("Invalid code (");
+                    self.print_int(self.cur_val);
+                    if p < DelCodeBase {
+                        self.print("), should be in the range 0..");
+                    } else {
+                        self.print("), should be at most ");
+                    }
+                    self.print_int(n);
+                    self.help(&["I'm going to use 0 instead of that illegal code value."]);
+                    self.error();
+                    self.cur_val = 0;
+                }
+                if p < DelCodeBase {
+                    self.define(global, p, Data, self.cur_val as HalfWord);
+                } else {
+                    self.word_define(global, p, self.cur_val);
+                }
+            }
+            // 1234
+            DefFamily => {
+                let mut p = self.cur_chr;
+                self.scan_four_bit_int();
+                p += self.cur_val as HalfWord;
+                self.scan_optional_equals();
+                self.scan_font_ident();
+                self.define(global, p, Data, self.cur_val as HalfWord);
+            }
+            // 1235
+            Register | Advance | Multiply | Divide => self.do_register_command(global),
+            // 1241
+            SetBox => {
+                self.scan_eight_bit_int();
+                let n = if global { 256 + self.cur_val } else { self.cur_val };
+                self.scan_optional_equals();
+                if self.set_box_allowed {
+                    self.scan_box(BoxFlag + n);
+                } else {
+                    self.print_err("Improper ");
+                    self.print_esc("setbox");
+                    self.help(&["Sorry, \\setbox is not allowed after \\halign in a display,",
+                        "or between \\accent and an accented character."]);
+                    self.error();
+                }
+            }
+            // 1242
+            SetAux => self.alter_aux(),
+            SetPrevGraf => self.alter_prev_graf(),
+            SetPageDimen => self.alter_page_so_far(),
+            SetPageInt => self.alter_integer(),
+            SetBoxDimen => self.alter_box_dimen(),
+            // 1248
+            SetShape => {
+                self.scan_optional_equals();
+                self.scan_int();
+                let n = self.cur_val;
+                let p = if n <= 0 {
+                    Null
+                } else {
+                    let p = self.get_node(2 * n as usize + 1);
+                    self.set_info(p as usize, n as HalfWord);
+                    for j in 1..=n as usize {
+                        self.scan_normal_dimen();
+                        self.mem[p as usize + 2 * j - 1].set_sc(self.cur_val); // indentation
+                        self.scan_normal_dimen();
+                        self.mem[p as usize + 2 * j].set_sc(self.cur_val); // width
+                    }
+                    p
+                };
+                self.define(global, ParShapeLoc, ShapeRef, p);
+            }
+            // 1252
+            HyphData => self.do_hyph_data(),
+            // 1253
+            AssignFontDimen => self.assign_font_dimen(),
+            AssignFontInt => self.assign_font_int(),
+            // 1256
+            DefFont => self.new_font(global),
+            // 1264
+            SetInteraction => self.new_interaction(),
+            _ => self.confusion("prefix"),
+        }
+        self.insert_after_token();
+    }
+
+    // 1269
+    // insert a token saved by \afterassignment, if any
+    pub fn insert_after_token(&mut self) {
+        if self.after_token != 0 {
+            self.cur_tok = self.after_token;
+            self.back_input();
+            self.after_token = 0;
+        }
+    }
+
+    // 1229
+    // replaces a glue specification of all zeroes by zero_glue
+    pub fn trap_zero_glue(&mut self) {
+        let v = self.cur_val as usize;
+        if self.width(v) == 0 && self.stretch(v) == 0 && self.shrink(v) == 0 {
+            self.add_glue_ref(ZeroGlue as Pointer);
+            self.delete_glue_ref(self.cur_val as Pointer);
+            self.cur_val = ZeroGlue as i32;
+        }
+    }
+
+    // 1236
+    // \advance, \multiply, \divide, or an assignment to a register
+    pub fn do_register_command(&mut self, global: bool) {
+        let q = self.cur_cmd;
+        // 1237: compute the register location l and its type p; but return if invalid
+        let (l, p) = 'found: {
+            if q != Register {
+                self.get_x_token();
+                if self.cur_cmd >= AssignInt && self.cur_cmd <= AssignMuGlue {
+                    break 'found (self.cur_chr, (self.cur_cmd - AssignInt) as i32);
+                }
+                if self.cur_cmd != Register {
+                    self.print_err("You can't use `");
+                    self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+                    self.print("' after ");
+                    self.print_cmd_chr(q, 0);
+                    self.help(&["I'm forgetting what you said and not changing anything."]);
+                    self.error();
+                    return;
+                }
+            }
+            let p = self.cur_chr as i32;
+            self.scan_eight_bit_int();
+            let v = self.cur_val as HalfWord;
+            let l = match p {
+                IntVal => v + CountBase,
+                DimenVal => v + ScaledBase,
+                GlueVal => v + SkipBase,
+                _ => v + MuSkipBase,
+            }; // there are no other cases
+            (l, p)
+        };
+        if q == Register {
+            self.scan_optional_equals();
+        } else {
+            self.scan_keyword("by"); // optional `by'
+        }
+        self.arith_error = false;
+        if q < Multiply {
+            // 1238: compute result of register or advance, put it in cur_val
+            if p < GlueVal {
+                if p == IntVal {
+                    self.scan_int();
+                } else {
+                    self.scan_normal_dimen();
+                }
+                if q == Advance {
+                    self.cur_val = self.cur_val.wrapping_add(self.eqtb[l as usize].int());
+                }
+            } else {
+                self.scan_glue(p);
+                if q == Advance {
+                    // 1239: compute the sum of two glue specs
+                    let q = self.new_spec(self.cur_val as Pointer) as usize;
+                    let r = self.equiv(l as usize) as usize;
+                    self.delete_glue_ref(self.cur_val as Pointer);
+                    self.set_width(q, self.width(q) + self.width(r));
+                    if self.stretch(q) == 0 {
+                        self.set_stretch_order(q, Normal as u8);
+                    }
+                    if self.stretch_order(q) == self.stretch_order(r) {
+                        self.set_stretch(q, self.stretch(q) + self.stretch(r));
+                    } else if self.stretch_order(q) < self.stretch_order(r) && self.stretch(r) != 0 {
+                        self.set_stretch(q, self.stretch(r));
+                        self.set_stretch_order(q, self.stretch_order(r));
+                    }
+                    if self.shrink(q) == 0 {
+                        self.set_shrink_order(q, Normal as u8);
+                    }
+                    if self.shrink_order(q) == self.shrink_order(r) {
+                        self.set_shrink(q, self.shrink(q) + self.shrink(r));
+                    } else if self.shrink_order(q) < self.shrink_order(r) && self.shrink(r) != 0 {
+                        self.set_shrink(q, self.shrink(r));
+                        self.set_shrink_order(q, self.shrink_order(r));
+                    }
+                    self.cur_val = q as i32;
+                }
+            }
+        } else {
+            // 1240: compute result of multiply or divide, put it in cur_val
+            self.scan_int();
+            if p < GlueVal {
+                let x = self.eqtb[l as usize].int();
+                self.cur_val = if q == Multiply {
+                    if p == IntVal {
+                        self.mult_integers(x, self.cur_val)
+                    } else {
+                        self.nx_plus_y(x, self.cur_val, 0)
+                    }
+                } else {
+                    self.x_over_n(x, self.cur_val)
+                };
+            } else {
+                let s = self.equiv(l as usize) as usize;
+                let r = self.new_spec(s as Pointer) as usize;
+                let n = self.cur_val;
+                if q == Multiply {
+                    let w = self.nx_plus_y(self.width(s), n, 0);
+                    self.set_width(r, w);
+                    let w = self.nx_plus_y(self.stretch(s), n, 0);
+                    self.set_stretch(r, w);
+                    let w = self.nx_plus_y(self.shrink(s), n, 0);
+                    self.set_shrink(r, w);
+                } else {
+                    let w = self.x_over_n(self.width(s), n);
+                    self.set_width(r, w);
+                    let w = self.x_over_n(self.stretch(s), n);
+                    self.set_stretch(r, w);
+                    let w = self.x_over_n(self.shrink(s), n);
+                    self.set_shrink(r, w);
+                }
+                self.cur_val = r as i32;
+            }
+        }
+        if self.arith_error {
+            self.print_err("Arithmetic overflow");
+            self.help(&["I can't carry out that multiplication or division,",
+                "since the result is out of range."]);
+            if p >= GlueVal {
+                self.delete_glue_ref(self.cur_val as Pointer);
+            }
+            self.error();
+            return;
+        }
+        if p < GlueVal {
+            self.word_define(global, l, self.cur_val);
+        } else {
+            self.trap_zero_glue();
+            self.define(global, l, GlueRef, self.cur_val as HalfWord);
+        }
+    }
+
+    // 1243
+    // \spacefactor or \prevdepth
+    pub fn alter_aux(&mut self) {
+        if self.cur_chr as i32 != self.mode().abs() {
+            self.report_illegal_case();
+        } else {
+            let c = self.cur_chr as i32; // hmode or vmode
+            self.scan_optional_equals();
+            if c == Vmode {
+                self.scan_normal_dimen();
+                self.set_prev_depth(self.cur_val);
+            } else {
+                self.scan_int();
+                if self.cur_val <= 0 || self.cur_val > 32767 {
+                    self.print_err("Bad space factor");
+                    self.help(&["I allow only values in the range 1..32767 here."]);
+                    self.int_error(self.cur_val);
+                } else {
+                    self.set_space_factor(self.cur_val as HalfWord);
+                }
+            }
+        }
+    }
+
+    // 1244
+    // \prevgraf
+    pub fn alter_prev_graf(&mut self) {
+        self.nest[self.nest_ptr] = self.curlist;
+        let mut p = self.nest_ptr; // index into nest
+        while self.nest[p].mode_field.abs() != Vmode {
+            p -= 1;
+        }
+        self.scan_optional_equals();
+        self.scan_int();
+        if self.cur_val < 0 {
+            self.print_err("Bad ");
+            self.print_esc("prevgraf");
+            self.help(&["I allow only nonnegative values here."]);
+            self.int_error(self.cur_val);
+        } else {
+            self.nest[p].pg_field = self.cur_val;
+            self.curlist = self.nest[self.nest_ptr];
+        }
+    }
+
+    // 1245
+    // \pagegoal, \pagetotal and their relatives
+    pub fn alter_page_so_far(&mut self) {
+        let c = self.cur_chr as usize; // index into page_so_far
+        self.scan_optional_equals();
+        self.scan_normal_dimen();
+        self.page_so_far[c] = self.cur_val;
+    }
+
+    // 1246
+    // \deadcycles or \insertpenalties
+    pub fn alter_integer(&mut self) {
+        let c = self.cur_chr; // 0 for \deadcycles, 1 for \insertpenalties
+        self.scan_optional_equals();
+        self.scan_int();
+        if c == 0 {
+            self.dead_cycles = self.cur_val;
+        } else {
+            self.insert_penalties = self.cur_val;
+        }
+    }
+
+    // 1247
+    // \wd, \ht or \dp
+    pub fn alter_box_dimen(&mut self) {
+        let c = self.cur_chr as usize; // width_offset or height_offset or depth_offset
+        self.scan_eight_bit_int();
+        let b = self.cur_val as HalfWord; // box number
+        self.scan_optional_equals();
+        self.scan_normal_dimen();
+        let p = self.box_reg(b) as usize;
+        if p != Null as usize {
+            self.mem[p + c].set_sc(self.cur_val);
+        }
+    }
+
+    // 1265
+    // \batchmode, \nonstopmode, \scrollmode or \errorstopmode
+    pub fn new_interaction(&mut self) {
+        self.print_ln();
+        self.interaction = match self.cur_chr {
+            0 => Interaction::BatchMode,
+            1 => Interaction::NonstopMode,
+            2 => Interaction::ScrollMode,
+            _ => Interaction::ErrorStopMode,
+        };
+        // 75: initialize the print selector based on interaction
+        self.selector = if self.interaction == Interaction::BatchMode { NoPrint } else { TermOnly };
+        if self.log_opened {
+            self.selector += 2;
+        }
+    }
+
+    // 1270
+    // performs any assignments that come next, leaving the first
+    // non-assignment in cur_cmd
+    pub fn do_assignments(&mut self) {
+        loop {
+            // 404: get the next non-blank non-relax non-call token
+            loop {
+                self.get_x_token();
+                if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                    break;
+                }
+            }
+            if self.cur_cmd <= MaxNonPrefixedCommand {
+                return;
+            }
+            self.set_box_allowed = false;
+            self.prefixed_command();
+            self.set_box_allowed = true;
+        }
+    }
+
+    // 1279
+    // \message and \errmessage
+    pub fn issue_message(&mut self) {
+        let c = self.cur_chr; // identifies \message and \errmessage
+        let p = self.scan_toks(false, true);
+        self.set_link(Garbage, p);
+        let old_setting = self.selector; // holds selector setting
+        self.selector = NewString;
+        self.token_show(self.def_ref);
+        self.selector = old_setting;
+        self.flush_list(self.def_ref);
+        self.str_room(1);
+        let s = self.make_string(); // the message
+        if c == 0 {
+            // 1280: print string s on the terminal
+            if self.term_offset + self.length(s) as i32 > MaxPrintLine - 2 {
+                self.print_ln();
+            } else if self.term_offset > 0 || self.file_offset > 0 {
+                self.print_char(' ' as u8);
+            }
+            self.slow_print(s);
+            self.update_terminal();
+        } else {
+            // 1283: print string s as an error message
+            self.print_err("");
+            self.slow_print(s);
+            if self.equiv(ErrHelpLoc as usize) != Null {
+                self.use_err_help = true;
+            } else if self.long_help_seen {
+                self.help(&["(That was another \\errmessage.)"]);
+            } else {
+                if self.interaction < Interaction::ErrorStopMode {
+                    self.long_help_seen = true;
+                }
+                self.help(&["This error message was generated by an \\errmessage",
+                    "command, so I can't give any explicit help.",
+                    "Pretend that you're Hercule Poirot: Examine all clues,",
+                    "and deduce the truth by order and method."]);
+            }
+            self.error();
+            self.use_err_help = false;
+        }
+        self.flush_string();
+    }
+
+    // 1288
+    // \lowercase and \uppercase
+    pub fn shift_case(&mut self) {
+        let b = self.cur_chr; // lc_code_base or uc_code_base
+        self.scan_toks(false, false);
+        let mut p = self.link(self.def_ref as usize); // runs through the token list
+        while p != Null {
+            // 1289: change the case of the token in p, if a change is appropriate
+            let t = self.info(p as usize); // token
+            if (t as i32) < cs_token_flag + SingleBase as i32 {
+                let c = t % 256; // character code
+                if self.equiv((b + c) as usize) != 0 {
+                    self.set_info(p as usize, t - c + self.equiv((b + c) as usize));
+                }
+            }
+            p = self.link(p as usize);
+        }
+        self.back_list(self.link(self.def_ref as usize));
+        self.free_avail(self.def_ref); // omit reference count
+    }
+
+    // 1293
+    // \show, \showbox, \showthe and \showlists
+    pub fn show_whatever(&mut self) {
+        let common_ending = match self.cur_chr {
+            ShowListsCode => {
+                self.begin_diagnostic();
+                self.show_activities();
+                false
+            }
+            ShowBoxCode => {
+                // 1296: show the current contents of a box
+                self.scan_eight_bit_int();
+                self.begin_diagnostic();
+                self.print_nl("> \\box");
+                self.print_int(self.cur_val);
+                self.print_char('=' as u8);
+                if self.box_reg(self.cur_val as HalfWord) == Null {
+                    self.print("void");
+                } else {
+                    self.show_box(self.box_reg(self.cur_val as HalfWord));
+                }
+                false
+            }
+            ShowCode => {
+                // 1294: show the current meaning of a token
+                self.get_token();
+                self.print_nl("> ");
+                if self.cur_cs != 0 {
+                    self.sprint_cs(self.cur_cs);
+                    self.print_char('=' as u8);
+                }
+                self.print_meaning();
+                true
+            }
+            _ => {
+                // 1297: show the current value of some parameter or register
+                self.the_toks();
+                self.print_nl("> ");
+                self.token_show(TempHead as Pointer);
+                self.flush_list(self.link(TempHead));
+                true
+            }
+        };
+        if !common_ending {
+            // 1298: complete a potentially long \show command
+            self.end_diagnostic(true);
+            self.print_err("OK");
+            if self.selector == TermAndLog && self.int_par(TracingOnlineCode) <= 0 {
+                self.selector = TermOnly;
+                self.print(" (see the transcript file)");
+                self.selector = TermAndLog;
+            }
+        }
+        if self.interaction < Interaction::ErrorStopMode {
+            self.help(&[]);
+            self.error_count -= 1;
+        } else if self.int_par(TracingOnlineCode) > 0 {
+            self.help(&["This isn't an error message; I'm just \\showing something.",
+                "Type `I\\show...' to show more (e.g., \\show\\cs,",
+                "\\showthe\\count10, \\showbox255, \\showlists)."]);
+        } else {
+            self.help(&["This isn't an error message; I'm just \\showing something.",
+                "Type `I\\show...' to show more (e.g., \\show\\cs,",
+                "\\showthe\\count10, \\showbox255, \\showlists).",
+                "And type `I\\tracingonline=1\\show...' to show boxes and",
+                "lists on your terminal as well as in the transcript file."]);
+        }
+        self.error();
+    }
+}
@@ -0,0 +1,297 @@
+use crate::*;
+
+// 211
+pub const Vmode: i32 = 1; // vertical mode
+pub const Hmode: i32 = Vmode + MaxCommand as i32 + 1; // horizontal mode
+pub const Mmode: i32 = Hmode + MaxCommand as i32 + 1; // math mode
+
+// 212
+pub const IgnoreDepth: Scaled = -65536000; // prev_depth value that is ignored
+
+impl TexState {
+    // 211
+    // prints the mode represented by m
+    pub fn print_mode(&mut self, m: i32) {
+        if m > 0 {
+            match m / (MaxCommand as i32 + 1) {
+                0 => self.print("vertical"),
+                1 => self.print("horizontal"),
+                2 => self.print("display math"),
+                _ => {}
+            }
+        } else if m == 0 {
+            self.print("no");
+        } else {
+            match (-m) / (MaxCommand as i32 + 1) {
+                0 => self.print("internal vertical"),
+                1 => self.print("restricted horizontal"),
+                2 => self.print("math"),
+                _ => {}
+            }
+        }
+        self.print(" mode");
+    }
+
+    // 1049
+    // complains that the current command is not allowed in the current mode
+    pub fn you_cant(&mut self) {
+        self.print_err("You can't use `");
+        self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+        self.print("' in ");
+        self.print_mode(self.mode());
+    }
+
+    // 1050
+    pub fn report_illegal_case(&mut self) {
+        self.you_cant();
+        self.help(&["Sorry, but I'm not programmed to handle this case;",
+            "I'll just pretend that you didn't ask for it.",
+            "If you're in the wrong mode, you might be able to",
+            "return to the right one by typing `I}' or `I$' or `I\\par'."]);
+        self.error();
+    }
+
+    // 213
+    // current mode
+    pub fn mode(&self) -> i32 {
+        self.curlist.mode_field
+    }
+
+    pub fn set_mode(&mut self, value: i32) {
+        self.curlist.mode_field = value;
+    }
+
+    // header node of current list
+    pub fn head(&self) -> HalfWord {
+        self.curlist.head_field
+    }
+
+    pub fn set_head(&mut self, value: HalfWord) {
+        self.curlist.head_field = value;
+    }
+
+    // final node on current list
+    pub fn tail(&self) -> HalfWord {
+        self.curlist.tail_field
+    }
+
+    pub fn set_tail(&mut self, value: HalfWord) {
+        self.curlist.tail_field = value;
+    }
+
+    // number of paragraph lines accumulated
+    pub fn prev_graf(&self) -> i32 {
+        self.curlist.pg_field
+    }
+
+    pub fn set_prev_graf(&mut self, value: i32) {
+        self.curlist.pg_field = value;
+    }
+
+    // source file line number at beginning of list
+    pub fn mode_line(&self) -> i32 {
+        self.curlist.ml_field
+    }
+
+    pub fn set_mode_line(&mut self, value: i32) {
+        self.curlist.ml_field = value;
+    }
+
+    // the name of aux in vertical mode
+    pub fn prev_depth(&self) -> Scaled {
+        self.curlist.aux_field.sc()
+    }
+
+    pub fn set_prev_depth(&mut self, value: Scaled) {
+        self.curlist.aux_field.set_sc(value);
+    }
+
+    // part of aux in horizontal mode
+    pub fn space_factor(&self) -> HalfWord {
+        self.curlist.aux_field.hh_lh()
+    }
+
+    pub fn set_space_factor(&mut self, value: HalfWord) {
+        self.curlist.aux_field.set_hh_lh(value);
+    }
+
+    // the other part of aux in horizontal mode
+    pub fn clang(&self) -> HalfWord {
+        self.curlist.aux_field.hh_rh()
+    }
+
+    pub fn set_clang(&mut self, value: HalfWord) {
+        self.curlist.aux_field.set_hh_rh(value);
+    }
+
+    // the name of aux in math mode
+    pub fn incompleat_noad(&self) -> i32 {
+        self.curlist.aux_field.int()
+    }
+
+    pub fn set_incompleat_noad(&mut self, value: i32) {
+        self.curlist.aux_field.set_int(value);
+    }
+
+    // 214
+    // add a node to the end of the current list
+    pub fn tail_append(&mut self, p: Pointer) {
+        self.set_link(self.tail() as usize, p);
+        self.set_tail(self.link(self.tail() as usize));
+    }
+
+    // 216
+    // enter a new semantic level, save the old
+    pub fn push_nest(&mut self) {
+        if self.nest_ptr > self.max_nest_stack {
+            self.max_nest_stack = self.nest_ptr;
+            if self.nest_ptr == NestSize {
+                self.overflow("semantic nest size", NestSize as i32);
+            }
+        }
+        self.nest[self.nest_ptr] = self.curlist; // stack the record
+        self.nest_ptr += 1;
+        let h = self.get_avail();
+        self.set_head(h);
+        self.set_tail(self.head());
+        self.set_prev_graf(0);
+        self.set_mode_line(self.line);
+    }
+
+    // 217
+    // leave a semantic level, re-enter the old
+    pub fn pop_nest(&mut self) {
+        self.free_avail(self.head());
+        self.nest_ptr -= 1;
+        self.curlist = self.nest[self.nest_ptr];
+    }
+
+    // 218
+    // displays what TeX is working on, at all levels
+    pub fn show_activities(&mut self) {
+        self.nest[self.nest_ptr] = self.curlist; // put the top level into the array
+        self.print_nl("");
+        self.print_ln();
+        for p in (0..=self.nest_ptr).rev() {
+            let m = self.nest[p].mode_field; // mode
+            let a = self.nest[p].aux_field; // auxiliary
+            self.print_nl("### ");
+            self.print_mode(m);
+            self.print(" entered at line ");
+            self.print_int(self.nest[p].ml_field.abs());
+            if m == Hmode && self.nest[p].pg_field != 0o40600000 {
+                self.print(" (language");
+                self.print_int(self.nest[p].pg_field % 0o200000);
+                self.print(":hyphenmin");
+                self.print_int(self.nest[p].pg_field / 0o20000000);
+                self.print_char(',' as u8);
+                self.print_int((self.nest[p].pg_field / 0o200000) % 0o100);
+                self.print_char(')' as u8);
+            }
+            if self.nest[p].ml_field < 0 {
+                self.print(" (\\output routine)");
+            }
+            if p == 0 {
+                // 986: show the status of the current page
+                if PageHead as Pointer != self.page_tail {
+                    self.print_nl("### current page:");
+                    if self.output_active {
+                        self.print(" (held over for next output)");
+                    }
+                    self.show_box(self.link(PageHead));
+                    if self.page_contents as i32 > Empty {
+                        self.print_nl("total height ");
+                        self.print_totals();
+                        self.print_nl(" goal height ");
+                        self.print_scaled(self.page_goal());
+                        let mut r = self.link(PageInsHead) as usize;
+                        while r != PageInsHead {
+                            self.print_ln();
+                            self.print_esc("insert");
+                            let mut t = self.subtype(r) as i32;
+                            self.print_int(t);
+                            self.print(" adds ");
+                            if self.count(t as HalfWord) == 1000 {
+                                t = self.height(r);
+                            } else {
+                                t = self.x_over_n(self.height(r), 1000) * self.count(t as HalfWord);
+                            }
+                            self.print_scaled(t);
+                            if self.a_type(r) == SplitUp {
+                                let mut q = PageHead;
+                                t = 0;
+                                loop {
+                                    q = self.link(q) as usize;
+                                    if self.a_type(q) == InsNode && self.subtype(q) == self.subtype(r) {
+                                        t += 1;
+                                    }
+                                    if q == self.broken_ins(r) as usize {
+                                        break;
+                                    }
+                                }
+                                self.print(", #");
+                                self.print_int(t);
+                                self.print(" might split");
+                            }
+                            r = self.link(r) as usize;
+                        }
+                    }
+                }
+                if self.link(ContribHead) != Null {
+                    self.print_nl("### recent contributions:");
+                }
+            }
+            self.show_box(self.link(self.nest[p].head_field as usize));
+            // 219: show the auxiliary field a
+            match m.abs() / (MaxCommand as i32 + 1) {
+                0 => {
+                    self.print_nl("prevdepth ");
+                    if a.sc() <= IgnoreDepth {
+                        self.print("ignored");
+                    } else {
+                        self.print_scaled(a.sc());
+                    }
+                    if self.nest[p].pg_field != 0 {
+                        self.print(", prevgraf ");
+                        self.print_int(self.nest[p].pg_field);
+                        self.print(" line");
+                        if self.nest[p].pg_field != 1 {
+                            self.print_char('s' as u8);
+                        }
+                    }
+                }
+                1 => {
+                    self.print_nl("spacefactor ");
+                    self.print_int(a.hh_lh() as i32);
+                    if m > 0 && a.hh_rh() > 0 {
+                        self.print(", current language ");
+                        self.print_int(a.hh_rh() as i32);
+                    }
+                }
+                2
+                    if a.int() != Null as i32 => {
+                        self.print("this will be denominator of:");
+                        self.show_box(a.int() as Pointer);
+                    }
+                _ => {} // there are no other cases
+            }
+        }
+    }
+
+    // 1070
+    // resets the paragraph shape parameters at the start of a paragraph
+    pub fn normal_paragraph(&mut self) {
+        if self.int_par(LoosenessCode) != 0 {
+            self.eq_word_define((IntBase + LoosenessCode) as Pointer, 0);
+        }
+        if self.dimen_par(HangIndentCode) != 0 {
+            self.eq_word_define((DimenBase + HangIndentCode) as Pointer, 0);
+        }
+        if self.int_par(HangAfterCode) != 1 {
+            self.eq_word_define((IntBase + HangAfterCode) as Pointer, 1);
+        }
+        if self.par_shape_ptr() != Null {
+            self.eq_define(ParShapeLoc, ShapeRef, Null);
+        }
+    }
+}
@@ -0,0 +1,918 @@
+use crate::*;
+
+// 137
+pub const VlistNode: QuarterWord = 1; // type of vlist nodes
+
+// 138
+pub const RuleNode: QuarterWord = 2; // type of rule nodes
+pub const RuleNodeSize: usize = 4; // number of words to allocate for a rule node
+pub const NullFlag: Scaled = -0o10000000000; // -2^30, signifies a missing item
+
+// 140
+pub const InsNode: QuarterWord = 3; // type of insertion nodes
+pub const InsNodeSize: usize = 5; // number of words to allocate for an insertion
+
+// 141
+pub const MarkNode: QuarterWord = 4; // type of a mark node
+pub const SmallNodeSize: usize = 2; // number of words to allocate for most node types
+
+// 142
+pub const AdjustNode: QuarterWord = 5; // type of an adjust node
+
+// 143
+pub const LigatureNode: QuarterWord = 6; // type of a ligature node
+
+// 145
+pub const DiscNode: QuarterWord = 7; // type of a discretionary node
+
+// 146
+pub const WhatsitNode: QuarterWord = 8; // type of special extension nodes
+
+// 147
+pub const MathNode: QuarterWord = 9; // type of a math node
+pub const Before: QuarterWord = 0; // subtype for math node that introduces a formula
+pub const After: QuarterWord = 1; // subtype for math node that winds up a formula
+
+// 149
+pub const GlueNode: QuarterWord = 10; // type of node that points to a glue specification
+pub const CondMathGlue: QuarterWord = 98; // special subtype to suppress glue in the next node
+pub const MuGlue: QuarterWord = 99; // subtype for math glue
+pub const ALeaders: QuarterWord = 100; // subtype for aligned leaders
+pub const CLeaders: QuarterWord = 101; // subtype for centered leaders
+pub const XLeaders: QuarterWord = 102; // subtype for expanded leaders
+
+// 155
+pub const KernNode: QuarterWord = 11; // type of a kern node
+pub const Explicit: QuarterWord = 1; // subtype of kern nodes from \kern and \/
+pub const AccKern: QuarterWord = 2; // subtype of kern nodes from accents
+
+// 157
+pub const PenaltyNode: QuarterWord = 12; // type of a penalty node
+pub const InfPenalty: i32 = 10000; // ``infinite'' penalty value
+pub const EjectPenalty: i32 = -InfPenalty; // ``negatively infinite'' penalty value
+
+// 159
+pub const UnsetNode: QuarterWord = 13; // type for an unset node
+
+impl TexState {
+    // 138
+    // tests for a running dimension
+    pub fn is_running(d: Scaled) -> bool {
+        d == NullFlag
+    }
+
+    // 140
+    // the floating_penalty to be used
+    pub fn float_cost(&self, p: usize) -> i32 {
+        self.mem[p + 1].int()
+    }
+
+    pub fn set_float_cost(&mut self, p: usize, value: i32) {
+        self.mem[p + 1].set_int(value);
+    }
+
+    // the vertical list to be inserted
+    pub fn ins_ptr(&self, p: usize) -> HalfWord {
+        self.info(p + 4)
+    }
+
+    pub fn set_ins_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 4, value);
+    }
+
+    // the split_top_skip to be used
+    pub fn split_top_ptr(&self, p: usize) -> HalfWord {
+        self.link(p + 4)
+    }
+
+    pub fn set_split_top_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 4, value);
+    }
+
+    // 141
+    // head of the token list for a mark
+    pub fn mark_ptr(&self, p: usize) -> HalfWord {
+        self.link(p + 1)
+    }
+
+    pub fn set_mark_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 1, value);
+    }
+
+    // 142
+    // vertical list to be moved out of horizontal list
+    pub fn adjust_ptr(&self, p: usize) -> HalfWord {
+        self.mem[p + 1].int() as HalfWord
+    }
+
+    pub fn set_adjust_ptr(&mut self, p: usize, value: HalfWord) {
+        self.mem[p + 1].set_int(value as i32);
+    }
+
+    // 143
+    // the word where the ligature is to be found
+    pub fn lig_char(p: usize) -> usize {
+        p + 1
+    }
+
+    // the list of characters
+    pub fn lig_ptr(&self, p: usize) -> HalfWord {
+        self.link(TexState::lig_char(p))
+    }
+
+    pub fn set_lig_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_link(TexState::lig_char(p), value);
+    }
+
+    // 145
+    // how many subsequent nodes to replace
+    pub fn replace_count(&self, p: usize) -> QuarterWord {
+        self.subtype(p)
+    }
+
+    pub fn set_replace_count(&mut self, p: usize, value: QuarterWord) {
+        self.set_subtype(p, value);
+    }
+
+    // text that precedes a discretionary break
+    pub fn pre_break(&self, p: usize) -> HalfWord {
+        self.llink(p)
+    }
+
+    pub fn set_pre_break(&mut self, p: usize, value: HalfWord) {
+        self.set_llink(p, value);
+    }
+
+    // text that follows a discretionary break
+    pub fn post_break(&self, p: usize) -> HalfWord {
+        self.rlink(p)
+    }
+
+    pub fn set_post_break(&mut self, p: usize, value: HalfWord) {
+        self.set_rlink(p, value);
+    }
+
+    // 149
+    // pointer to a glue specification
+    pub fn glue_ptr(&self, p: usize) -> HalfWord {
+        self.llink(p)
+    }
+
+    pub fn set_glue_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_llink(p, value);
+    }
+
+    // pointer to box or rule node for leaders
+    pub fn leader_ptr(&self, p: usize) -> HalfWord {
+        self.rlink(p)
+    }
+
+    pub fn set_leader_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_rlink(p, value);
+    }
+
+    // 157
+    // the added cost of breaking a list here
+    pub fn penalty(&self, p: usize) -> i32 {
+        self.mem[p + 1].int()
+    }
+
+    pub fn set_penalty(&mut self, p: usize, value: i32) {
+        self.mem[p + 1].set_int(value);
+    }
+
+    // 159
+    // total stretch in an unset node
+    pub fn glue_stretch(&self, p: usize) -> Scaled {
+        self.mem[p + GlueOffset].sc()
+    }
+
+    pub fn set_glue_stretch(&mut self, p: usize, value: Scaled) {
+        self.mem[p + GlueOffset].set_sc(value);
+    }
+
+    // total shrink in an unset node
+    pub fn glue_shrink(&self, p: usize) -> Scaled {
+        self.shift_amount(p)
+    }
+
+    pub fn set_glue_shrink(&mut self, p: usize, value: Scaled) {
+        self.set_shift_amount(p, value);
+    }
+
+    // indicates the number of spanned columns
+    pub fn span_count(&self, p: usize) -> QuarterWord {
+        self.subtype(p)
+    }
+
+    pub fn set_span_count(&mut self, p: usize, value: QuarterWord) {
+        self.set_subtype(p, value);
+    }
+
+    // 200
+    // the reference count of a token list
+    pub fn token_ref_count(&self, p: usize) -> HalfWord {
+        self.info(p)
+    }
+
+    pub fn set_token_ref_count(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p, value);
+    }
+
+    // new reference to a token list
+    pub fn add_token_ref(&mut self, p: Pointer) {
+        let p = p as usize;
+        self.set_token_ref_count(p, self.token_ref_count(p) + 1);
+    }
+
+    // new reference to a glue spec
+    pub fn add_glue_ref(&mut self, p: Pointer) {
+        let p = p as usize;
+        self.set_glue_ref_count(p, self.glue_ref_count(p) + 1);
+    }
+
+    // p points to the reference count of a token list that is losing one reference
+    pub fn delete_token_ref(&mut self, p: Pointer) {
+        if self.token_ref_count(p as usize) == Null {
+            self.flush_list(p);
+        } else {
+            self.set_token_ref_count(p as usize, self.token_ref_count(p as usize) - 1);
+        }
+    }
+
+    // 201
+    // p points to a glue specification
+    pub fn delete_glue_ref(&mut self, p: Pointer) {
+        if self.glue_ref_count(p as usize) == Null {
+            self.free_node(p, GlueSpecSize);
+        } else {
+            self.set_glue_ref_count(p as usize, self.glue_ref_count(p as usize) - 1);
+        }
+    }
+
+    // 136
+    // creates a new box node
+    pub fn new_null_box(&mut self) -> Pointer {
+        let p = self.get_node(BoxNodeSize);
+        let q = p as usize;
+        self.set_type(q, HlistNode);
+        self.set_subtype(q, MinQuarterWord);
+        self.set_width(q, 0);
+        self.set_depth(q, 0);
+        self.set_height(q, 0);
+        self.set_shift_amount(q, 0);
+        self.set_list_ptr(q, Null);
+        self.set_glue_sign(q, Normal as u8);
+        self.set_glue_order(q, Normal as u8);
+        self.set_glue_set(q, GlueRatio::ZERO);
+        p
+    }
+
+    // 139
+    pub fn new_rule(&mut self) -> Pointer {
+        let p = self.get_node(RuleNodeSize);
+        let q = p as usize;
+        self.set_type(q, RuleNode);
+        self.set_subtype(q, 0); // the subtype is not used
+        self.set_width(q, NullFlag);
+        self.set_depth(q, NullFlag);
+        self.set_height(q, NullFlag);
+        p
+    }
+
+    // 144
+    pub fn new_ligature(&mut self, f: QuarterWord, c: QuarterWord, q: Pointer) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        let r = p as usize;
+        self.set_type(r, LigatureNode);
+        self.set_type(TexState::lig_char(r), f);
+        self.set_subtype(TexState::lig_char(r), c);
+        self.set_lig_ptr(r, q);
+        self.set_subtype(r, 0);
+        p
+    }
+
+    pub fn new_lig_item(&mut self, c: QuarterWord) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        self.set_subtype(p as usize, c);
+        self.set_lig_ptr(p as usize, Null);
+        p
+    }
+
+    // 145
+    // creates an empty disc node
+    pub fn new_disc(&mut self) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        let q = p as usize;
+        self.set_type(q, DiscNode);
+        self.set_replace_count(q, 0);
+        self.set_pre_break(q, Null);
+        self.set_post_break(q, Null);
+        p
+    }
+
+    // 147
+    pub fn new_math(&mut self, w: Scaled, s: QuarterWord) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        self.set_type(p as usize, MathNode);
+        self.set_subtype(p as usize, s);
+        self.set_width(p as usize, w);
+        p
+    }
+
+    // 151
+    // duplicates a glue specification
+    pub fn new_spec(&mut self, p: Pointer) -> Pointer {
+        let q = self.get_node(GlueSpecSize);
+        let (p, r) = (p as usize, q as usize);
+        self.mem[r] = self.mem[p];
+        self.set_glue_ref_count(r, Null);
+        self.set_width(r, self.width(p));
+        self.set_stretch(r, self.stretch(p));
+        self.set_shrink(r, self.shrink(p));
+        q
+    }
+
+    // 152
+    // creates a glue node for a given parameter
+    pub fn new_param_glue(&mut self, n: HalfWord) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        self.set_type(p as usize, GlueNode);
+        self.set_subtype(p as usize, (n + 1) as QuarterWord);
+        self.set_leader_ptr(p as usize, Null);
+        let q = self.glue_par(n);
+        self.set_glue_ptr(p as usize, q);
+        self.add_glue_ref(q);
+        p
+    }
+
+    // 153
+    // creates a glue node for a given specification
+    pub fn new_glue(&mut self, q: Pointer) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        self.set_type(p as usize, GlueNode);
+        self.set_subtype(p as usize, Normal as u8);
+        self.set_leader_ptr(p as usize, Null);
+        self.set_glue_ptr(p as usize, q);
+        self.add_glue_ref(q);
+        p
+    }
+
+    // 154
+    // creates a glue node with a fresh copy of a parameter's specification
+    pub fn new_skip_param(&mut self, n: HalfWord) -> Pointer {
+        self.temp_ptr = self.new_spec(self.glue_par(n));
+        let p = self.new_glue(self.temp_ptr);
+        self.set_glue_ref_count(self.temp_ptr as usize, Null);
+        self.set_subtype(p as usize, (n + 1) as QuarterWord);
+        p
+    }
+
+    // 156
+    pub fn new_kern(&mut self, w: Scaled) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        self.set_type(p as usize, KernNode);
+        self.set_subtype(p as usize, Normal as u8);
+        self.set_width(p as usize, w);
+        p
+    }
+
+    // 158
+    pub fn new_penalty(&mut self, m: i32) -> Pointer {
+        let p = self.get_node(SmallNodeSize);
+        self.set_type(p as usize, PenaltyNode);
+        self.set_subtype(p as usize, 0); // the subtype is not used
+        self.set_penalty(p as usize, m);
+        p
+    }
+
+    // 174
+    // prints highlights of list p
+    pub fn short_display(&mut self, p: Pointer) {
+        let mut p = p;
+        while p as usize > MemMin {
+            let q = p as usize;
+            if self.is_char_node(q) {
+                if p <= self.mem_end {
+                    if self.font(q) as i32 != self.font_in_short_display {
+                        if (self.font(q) as i32) < FontBase || self.font(q) as i32 > FontMax {
+                            self.print_char('*' as u8);
+                        } else {
+                            // 267: print the font identifier for font(p)
+                            self.print_esc_str(font_id_text(&self.hash, self.font(q) as HalfWord) as StrNumber);
+                        }
+                        self.print_char(' ' as u8);
+                        self.font_in_short_display = self.font(q) as i32;
+                    }
+                    self.print_ASCII(self.character(q) as i32);
+                }
+            } else {
+                // 175: print a short indication of the contents of node p
+                match self.a_type(q) {
+                    HlistNode | VlistNode | InsNode | WhatsitNode | MarkNode | AdjustNode | UnsetNode => {
+                        self.print("[]")
+                    }
+                    RuleNode => self.print_char('|' as u8),
+                    GlueNode if self.glue_ptr(q) as usize != ZeroGlue => {
+                        self.print_char(' ' as u8);
+                    }
+                    MathNode => self.print_char('$' as u8),
+                    LigatureNode => self.short_display(self.lig_ptr(q)),
+                    DiscNode => {
+                        self.short_display(self.pre_break(q));
+                        self.short_display(self.post_break(q));
+                    }
+                    _ => {}
+                }
+            }
+            p = self.link(q);
+        }
+    }
+
+    // 176
+    // prints char_node data
+    pub fn print_font_and_char(&mut self, p: usize) {
+        if p > self.mem_end as usize {
+            self.print_esc("CLOBBERED.");
+        } else {
+            if (self.font(p) as i32) < FontBase || self.font(p) as i32 > FontMax {
+                self.print_char('*' as u8);
+            } else {
+                self.print_esc_str(font_id_text(&self.hash, self.font(p) as HalfWord) as StrNumber);
+            }
+            self.print_char(' ' as u8);
+            self.print_ASCII(self.character(p) as i32);
+        }
+    }
+
+    // prints token list data in braces
+    pub fn print_mark(&mut self, p: Pointer) {
+        self.print_char('{' as u8);
+        if p < self.hi_mem_min || p > self.mem_end {
+            self.print_esc("CLOBBERED.");
+        } else {
+            self.show_token_list(self.link(p as usize) as i32, Null as i32, MaxPrintLine - 10);
+        }
+        self.print_char('}' as u8);
+    }
+
+    // prints a dimension
+    pub fn print_rule_dimen(&mut self, d: Scaled) {
+        if TexState::is_running(d) {
+            self.print_char('*' as u8);
+        } else {
+            self.print_scaled(d);
+        }
+    }
+
+    // 177
+    // prints a glue component
+    pub fn print_glue(&mut self, d: Scaled, order: i32, s: &str) {
+        self.print_scaled(d);
+        if !(Normal..=filll).contains(&order) {
+            self.print("foul");
+        } else if order > Normal {
+            self.print("fil");
+            let mut order = order;
+            while order > fil {
+                self.print_char('l' as u8);
+                order -= 1;
+            }
+        } else {
+            self.print(s);
+        }
+    }
+
+    // 178
+    // prints a glue specification
+    pub fn print_spec(&mut self, p: Pointer, s: &str) {
+        let q = p as usize;
+        if p >= self.lo_mem_max {
+            self.print_char('*' as u8);
+        } else {
+            self.print_scaled(self.width(q));
+            self.print(s);
+            if self.stretch(q) != 0 {
+                self.print(" plus ");
+                self.print_glue(self.stretch(q), self.stretch_order(q) as i32, s);
+            }
+            if self.shrink(q) != 0 {
+                self.print(" minus ");
+                self.print_glue(self.shrink(q), self.shrink_order(q) as i32, s);
+            }
+        }
+    }
+
+    // 180
+    // display a node list with one more level of nesting
+    fn node_list_display(&mut self, p: Pointer) {
+        self.append_char('.' as u8);
+        self.show_node_list(p);
+        self.flush_char();
+    }
+
+    // 182
+    // prints a node list symbolically
+    pub fn show_node_list(&mut self, p: Pointer) {
+        if self.cur_length() as i32 > self.depth_threshold {
+            if p > Null {
+                self.print(" []"); // indicate that there's been some truncation
+            }
+            return;
+        }
+        let mut p = p;
+        let mut n = 0;
+        while p as usize > MemMin {
+            self.print_ln();
+            self.print_current_string(); // display the nesting history
+            if p > self.mem_end {
+                // pointer out of range
+                self.print("Bad link, display aborted.");
+                return;
+            }
+            n += 1;
+            if n > self.breadth_max {
+                // time to stop
+                self.print("etc.");
+                return;
+            }
+            // 183: display node p
+            let q = p as usize;
+            if self.is_char_node(q) {
+                self.print_font_and_char(q);
+            } else {
+                self.display_node(q);
+            }
+            p = self.link(q);
+        }
+    }
+
+    // 183
+    fn display_node(&mut self, p: usize) {
+        match self.a_type(p) {
+            HlistNode | VlistNode | UnsetNode => {
+                // 184: display box p
+                if self.a_type(p) == HlistNode {
+                    self.print_esc("h");
+                } else if self.a_type(p) == VlistNode {
+                    self.print_esc("v");
+                } else {
+                    self.print_esc("unset");
+                }
+                self.print("box(");
+                self.print_scaled(self.height(p));
+                self.print_char('+' as u8);
+                self.print_scaled(self.depth(p));
+                self.print(")x");
+                self.print_scaled(self.width(p));
+                if self.a_type(p) == UnsetNode {
+                    // 185: display special fields of the unset node p
+                    if self.span_count(p) != MinQuarterWord {
+                        self.print(" (");
+                        self.print_int(self.span_count(p) as i32 + 1);
+                        self.print(" columns)");
+                    }
+                    if self.glue_stretch(p) != 0 {
+                        self.print(", stretch ");
+                        self.print_glue(self.glue_stretch(p), self.glue_order(p) as i32, "");
+                    }
+                    if self.glue_shrink(p) != 0 {
+                        self.print(", shrink ");
+                        self.print_glue(self.glue_shrink(p), self.glue_sign(p) as i32, "");
+                    }
+                } else {
+                    // 186: display the value of glue_set(p)
+                    let g = self.glue_set(p).to_f64();
+                    if g != 0.0 && self.glue_sign(p) as i32 != Normal {
+                        self.print(", glue set ");
+                        if self.glue_sign(p) as i32 == Shrinking {
+                            self.print("- ");
+                        }
+                        if self.glue_set(p).is_unprintable() {
+                            self.print("?.?");
+                        } else if g.abs() > 20000.0 {
+                            if g > 0.0 {
+                                self.print_char('>' as u8);
+                            } else {
+                                self.print("< -");
+                            }
+                            self.print_glue(20000 * unity, self.glue_order(p) as i32, "");
+                        } else {
+                            self.print_glue((unity as f64 * g).round() as Scaled, self.glue_order(p) as i32, "");
+                        }
+                    }
+                    if self.shift_amount(p) != 0 {
+                        self.print(", shifted ");
+                        self.print_scaled(self.shift_amount(p));
+                    }
+                }
+                self.node_list_display(self.list_ptr(p)); // recursive call
+            }
+            RuleNode => {
+                // 187
+                self.print_esc("rule(");
+                self.print_rule_dimen(self.height(p));
+                self.print_char('+' as u8);
+                self.print_rule_dimen(self.depth(p));
+                self.print(")x");
+                self.print_rule_dimen(self.width(p));
+            }
+            InsNode => {
+                // 188
+                self.print_esc("insert");
+                self.print_int(self.subtype(p) as i32);
+                self.print(", natural size ");
+                self.print_scaled(self.height(p));
+                self.print("; split(");
+                self.print_spec(self.split_top_ptr(p), "");
+                self.print_char(',' as u8);
+                self.print_scaled(self.depth(p));
+                self.print("); float cost ");
+                self.print_int(self.float_cost(p));
+                self.node_list_display(self.ins_ptr(p)); // recursive call
+            }
+            GlueNode => {
+                // 189
+                if self.subtype(p) >= ALeaders {
+                    // 190: display leaders p
+                    self.print_esc("");
+                    if self.subtype(p) == CLeaders {
+                        self.print_char('c' as u8);
+                    } else if self.subtype(p) == XLeaders {
+                        self.print_char('x' as u8);
+                    }
+                    self.print("leaders ");
+                    self.print_spec(self.glue_ptr(p), "");
+                    self.node_list_display(self.leader_ptr(p)); // recursive call
+                } else {
+                    self.print_esc("glue");
+                    if self.subtype(p) as i32 != Normal {
+                        self.print_char('(' as u8);
+                        if self.subtype(p) < CondMathGlue {
+                            self.print_skip_param(self.subtype(p) as HalfWord - 1);
+                        } else if self.subtype(p) == CondMathGlue {
+                            self.print_esc("nonscript");
+                        } else {
+                            self.print_esc("mskip");
+                        }
+                        self.print_char(')' as u8);
+                    }
+                    if self.subtype(p) != CondMathGlue {
+                        self.print_char(' ' as u8);
+                        if self.subtype(p) < CondMathGlue {
+                            self.print_spec(self.glue_ptr(p), "");
+                        } else {
+                            self.print_spec(self.glue_ptr(p), "mu");
+                        }
+                    }
+                }
+            }
+            KernNode => {
+                // 191
+                if self.subtype(p) != MuGlue {
+                    self.print_esc("kern");
+                    if self.subtype(p) as i32 != Normal {
+                        self.print_char(' ' as u8);
+                    }
+                    self.print_scaled(self.width(p));
+                    if self.subtype(p) == AccKern {
+                        self.print(" (for accent)");
+                    }
+                } else {
+                    self.print_esc("mkern");
+                    self.print_scaled(self.width(p));
+                    self.print("mu");
+                }
+            }
+            MathNode => {
+                // 192
+                self.print_esc("math");
+                if self.subtype(p) == Before {
+                    self.print("on");
+                } else {
+                    self.print("off");
+                }
+                if self.width(p) != 0 {
+                    self.print(", surrounded ");
+                    self.print_scaled(self.width(p));
+                }
+            }
+            LigatureNode => {
+                // 193
+                self.print_font_and_char(TexState::lig_char(p));
+                self.print(" (ligature ");
+                if self.subtype(p) > 1 {
+                    self.print_char('|' as u8);
+                }
+                self.font_in_short_display = self.font(TexState::lig_char(p)) as i32;
+                self.short_display(self.lig_ptr(p));
+                if self.subtype(p) % 2 == 1 {
+                    self.print_char('|' as u8);
+                }
+                self.print_char(')' as u8);
+            }
+            PenaltyNode => {
+                // 194
+                self.print_esc("penalty ");
+                self.print_int(self.penalty(p));
+            }
+            DiscNode => {
+                // 195
+                self.print_esc("discretionary");
+                if self.replace_count(p) > 0 {
+                    self.print(" replacing ");
+                    self.print_int(self.replace_count(p) as i32);
+                }
+                self.node_list_display(self.pre_break(p)); // recursive call
+                self.append_char('|' as u8);
+                self.show_node_list(self.post_break(p));
+                self.flush_char(); // recursive call
+            }
+            MarkNode => {
+                // 196
+                self.print_esc("mark");
+                if self.mark_class(p) != 0 {
+                    self.print_char('s' as u8);
+                    self.print_int(self.mark_class(p) as i32);
+                }
+                self.print_mark(self.mark_ptr(p));
+            }
+            AdjustNode => {
+                // 197
+                self.print_esc("vadjust");
+                self.node_list_display(self.adjust_ptr(p)); // recursive call
+            }
+            WhatsitNode => self.display_whatsit(p),
+            StyleNode..=RightNoad => self.display_noad(p), // 690
+            _ => self.print("Unknown node type!"),
+        }
+    }
+
+    // 198
+    // the recursive machinery is started by calling show_box
+    pub fn show_box(&mut self, p: Pointer) {
+        // 236: assign the values depth_threshold and breadth_max
+        self.depth_threshold = self.int_par(ShowBoxDepthCode);
+        self.breadth_max = self.int_par(ShowBoxBreadthCode);
+        if self.breadth_max <= 0 {
+            self.breadth_max = 5;
+        }
+        if self.pool_ptr as i32 + self.depth_threshold >= PoolSize {
+            self.depth_threshold = PoolSize - self.pool_ptr as i32 - 1;
+        } // now there's enough room for prefix string
+        self.show_node_list(p);
+        self.print_ln();
+    }
+
+    // 202
+    // erase list of nodes starting at p
+    pub fn flush_node_list(&mut self, p: Pointer) {
+        let mut p = p;
+        while p != Null {
+            let r = p as usize;
+            let q = self.link(r);
+            if self.is_char_node(r) {
+                self.free_avail(p);
+            } else {
+                match self.a_type(r) {
+                    HlistNode | VlistNode | UnsetNode => {
+                        self.flush_node_list(self.list_ptr(r));
+                        self.free_node(p, BoxNodeSize);
+                    }
+                    RuleNode => {
+                        self.free_node(p, RuleNodeSize);
+                    }
+                    InsNode => {
+                        self.flush_node_list(self.ins_ptr(r));
+                        self.delete_glue_ref(self.split_top_ptr(r));
+                        self.free_node(p, InsNodeSize);
+                    }
+                    GlueNode => {
+                        self.delete_glue_ref(self.glue_ptr(r));
+                        if self.leader_ptr(r) != Null {
+                            self.flush_node_list(self.leader_ptr(r));
+                        }
+                        self.free_node(p, SmallNodeSize);
+                    }
+                    KernNode | MathNode | PenaltyNode => {
+                        self.free_node(p, SmallNodeSize);
+                    }
+                    LigatureNode => {
+                        self.flush_node_list(self.lig_ptr(r));
+                        self.free_node(p, SmallNodeSize);
+                    }
+                    MarkNode => {
+                        self.delete_token_ref(self.mark_ptr(r));
+                        self.free_node(p, SmallNodeSize);
+                    }
+                    DiscNode => {
+                        self.flush_node_list(self.pre_break(r));
+                        self.flush_node_list(self.post_break(r));
+                        self.free_node(p, SmallNodeSize);
+                    }
+                    AdjustNode => {
+                        self.flush_node_list(self.adjust_ptr(r));
+                        self.free_node(p, SmallNodeSize);
+                    }
+                    WhatsitNode => self.flush_whatsit(p),
+                    StyleNode..=RightNoad => self.flush_noad(p), // 698
+                    _ => self.confusion("flushing"),
+                }
+            }
+            p = q;
+        }
+    }
+
+    // 204
+    // makes a duplicate of the node list that starts at p and returns a pointer to the new list
+    pub fn copy_node_list(&mut self, p: Pointer) -> Pointer {
+        let h = self.get_avail(); // temporary head of copied list
+        let mut q = h; // previous position in new list
+        let mut p = p;
+        while p != Null {
+            // 205: make a copy of node p in node r
+            let s = p as usize;
+            let mut words = 1; // this setting occurs in more branches than any other
+            let r;
+            if self.is_char_node(s) {
+                r = self.get_avail();
+            } else {
+                // 206: case statement to copy different types
+                match self.a_type(s) {
+                    HlistNode | VlistNode | UnsetNode => {
+                        r = self.get_node(BoxNodeSize);
+                        for k in ListOffset..BoxNodeSize {
+                            self.mem[r as usize + k] = self.mem[s + k]; // copy the last words
+                        }
+                        let l = self.copy_node_list(self.list_ptr(s));
+                        self.set_list_ptr(r as usize, l); // this affects mem[r+5]
+                        words = 5;
+                    }
+                    RuleNode => {
+                        r = self.get_node(RuleNodeSize);
+                        words = RuleNodeSize;
+                    }
+                    InsNode => {
+                        r = self.get_node(InsNodeSize);
+                        self.mem[r as usize + 4] = self.mem[s + 4];
+                        self.add_glue_ref(self.split_top_ptr(s));
+                        let l = self.copy_node_list(self.ins_ptr(s));
+                        self.set_ins_ptr(r as usize, l); // this affects mem[r+4]
+                        words = InsNodeSize - 1;
+                    }
+                    GlueNode => {
+                        r = self.get_node(SmallNodeSize);
+                        self.add_glue_ref(self.glue_ptr(s));
+                        self.set_glue_ptr(r as usize, self.glue_ptr(s));
+                        let l = self.copy_node_list(self.leader_ptr(s));
+                        self.set_leader_ptr(r as usize, l);
+                    }
+                    KernNode | MathNode | PenaltyNode => {
+                        r = self.get_node(SmallNodeSize);
+                        words = SmallNodeSize;
+                    }
+                    LigatureNode => {
+                        r = self.get_node(SmallNodeSize);
+                        self.mem[TexState::lig_char(r as usize)] = self.mem[TexState::lig_char(s)]; // copy font and character
+                        let l = self.copy_node_list(self.lig_ptr(s));
+                        self.set_lig_ptr(r as usize, l);
+                    }
+                    DiscNode => {
+                        r = self.get_node(SmallNodeSize);
+                        let l = self.copy_node_list(self.pre_break(s));
+                        self.set_pre_break(r as usize, l);
+                        let l = self.copy_node_list(self.post_break(s));
+                        self.set_post_break(r as usize, l);
+                    }
+                    MarkNode => {
+                        r = self.get_node(SmallNodeSize);
+                        self.add_token_ref(self.mark_ptr(s));
+                        words = SmallNodeSize;
+                    }
+                    AdjustNode => {
+                        r = self.get_node(SmallNodeSize);
+                        let l = self.copy_node_list(self.adjust_ptr(s));
+                        self.set_adjust_ptr(r as usize, l);
+                    } // words=1=small_node_size-1
+                    WhatsitNode => {
+                        let (n, w) = self.copy_whatsit(s);
+                        r = n;
+                        words = w;
+                    }
+                    _ => self.confusion("copying"),
+                }
+            }
+            while words > 0 {
+                words -= 1;
+                self.mem[r as usize + words] = self.mem[s + words];
+            }
+            self.set_link(q as usize, r);
+            q = r;
+            p = self.link(s);
+        }
+        self.set_link(q as usize, Null);
+        let q = self.link(h as usize);
+        self.free_avail(h);
+        q
+    }
+}
@@ -0,0 +1,844 @@
+use crate::*;
+use std::collections::BTreeMap;
+use ttf_parser::{Face, GlyphId, Tag};
+
+// OpenType and TrueType fonts are loaded into font_info just as if their
+// metrics came from a TFM file, so the rest of TeX can't tell the difference.
+// A font's 256 character codes stand for the Latin-1 characters; the codes
+// that Latin-1 leaves to control characters hold the font's ligatures.
+
+// the first and last bytes of the two runs of Latin-1 control codes
+const FreeCodes: [(u8, u8); 2] = [(0, 31), (127, 159)];
+
+// the design size given to OpenType fonts, which have none of their own
+const OpenTypeDesignSize: Scaled = 10 * unity;
+
+// true if a font name ends with the extension of an OpenType or TrueType font
+pub fn is_opentype_name(name: &[u8]) -> bool {
+    let name = String::from_utf8_lossy(name).to_lowercase();
+    name.ends_with(".otf") || name.ends_with(".ttf")
+}
+
+fn is_free_code(c: u8) -> bool {
+    FreeCodes.iter().any(|&(a, b)| c >= a && c <= b)
+}
+
+// a big-endian number in an OpenType table
+fn u16_at(d: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*d.get(off)?, *d.get(off + 1)?]))
+}
+
+fn i16_at(d: &[u8], off: usize) -> Option<i16> {
+    Some(u16_at(d, off)? as i16)
+}
+
+fn u32_at(d: &[u8], off: usize) -> Option<u32> {
+    Some(((u16_at(d, off)? as u32) << 16) + u16_at(d, off + 2)? as u32)
+}
+
+// the table at a 16-bit offset from the start of d
+fn sub_table(d: &[u8], off: usize) -> Option<&[u8]> {
+    d.get(u16_at(d, off)? as usize..)
+}
+
+// like sub_table, but a null offset means there is no table
+fn offset_table(d: &[u8], off: usize) -> Option<&[u8]> {
+    match u16_at(d, off)? {
+        0 => None,
+        o => d.get(o as usize..),
+    }
+}
+
+// a length in font units, scaled to a font of size z
+fn scale_units(x: i32, z: Scaled, units_per_em: i32) -> Scaled {
+    let upem = units_per_em as i64;
+    ((x as i64 * z as i64 * 2 + upem) / (2 * upem)) as Scaled
+}
+
+// the glyphs of a coverage table, with their coverage indices
+fn coverage_glyphs(cov: &[u8]) -> Vec<(u16, usize)> {
+    let mut v = Vec::new();
+    match u16_at(cov, 0) {
+        Some(1) => {
+            let n = u16_at(cov, 2).unwrap_or(0) as usize;
+            for i in 0..n {
+                if let Some(g) = u16_at(cov, 4 + 2 * i) {
+                    v.push((g, i));
+                }
+            }
+        }
+        Some(2) => {
+            let n = u16_at(cov, 2).unwrap_or(0) as usize;
+            for i in 0..n {
+                let r = 4 + 6 * i;
+                if let (Some(start), Some(end), Some(index)) = (u16_at(cov, r), u16_at(cov, r + 2), u16_at(cov, r + 4)) {
+                    for g in start..=end {
+                        v.push((g, index as usize + (g - start) as usize));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    v
+}
+
+// the class of glyph g in a class definition table; 0 if it has none
+fn glyph_class(cd: &[u8], g: u16) -> u16 {
+    match u16_at(cd, 0) {
+        Some(1) => {
+            let start = u16_at(cd, 2).unwrap_or(0);
+            let n = u16_at(cd, 4).unwrap_or(0);
+            if g >= start && g - start < n {
+                u16_at(cd, 6 + 2 * (g - start) as usize).unwrap_or(0)
+            } else {
+                0
+            }
+        }
+        Some(2) => {
+            let n = u16_at(cd, 2).unwrap_or(0) as usize;
+            (0..n)
+                .map(|i| 4 + 6 * i)
+                .find(|&r| u16_at(cd, r).is_some_and(|s| s <= g) && u16_at(cd, r + 2).is_some_and(|e| g <= e))
+                .and_then(|r| u16_at(cd, r + 4))
+                .unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+// the size of a GPOS value record, and the offset of its x advance within it
+fn value_record(format: u16) -> (usize, Option<usize>) {
+    let size = 2 * (format & 0xff).count_ones() as usize;
+    let x_advance = if format & 4 != 0 { Some(2 * (format & 3).count_ones() as usize) } else { None };
+    (size, x_advance)
+}
+
+// the subtables of the lookups that a GSUB or GPOS table uses for the given
+// feature, in lookup order; extension subtables are replaced by the ones they
+// point to. Lookups of other types than lookup_type are left out
+fn feature_subtables<'a>(table: &'a [u8], feature: &[u8; 4], lookup_type: u16, extension_type: u16) -> Vec<Vec<&'a [u8]>> {
+    let mut lookups = Vec::new();
+    let (features, lookup_list) = match (sub_table(table, 6), sub_table(table, 8)) {
+        (Some(f), Some(l)) => (f, l),
+        _ => return Vec::new(),
+    };
+    for i in 0..u16_at(features, 0).unwrap_or(0) as usize {
+        let r = 2 + 6 * i;
+        if features.get(r..r + 4) != Some(&feature[..]) {
+            continue;
+        }
+        if let Some(f) = u16_at(features, r + 4).and_then(|o| features.get(o as usize..)) {
+            for j in 0..u16_at(f, 2).unwrap_or(0) as usize {
+                if let Some(l) = u16_at(f, 4 + 2 * j) {
+                    lookups.push(l);
+                }
+            }
+        }
+    }
+    lookups.sort();
+    lookups.dedup();
+    let mut result = Vec::new();
+    for l in lookups {
+        let lookup = match u16_at(lookup_list, 2 + 2 * l as usize).and_then(|o| lookup_list.get(o as usize..)) {
+            Some(t) => t,
+            None => continue,
+        };
+        let t = u16_at(lookup, 0).unwrap_or(0);
+        let mut subtables = Vec::new();
+        for k in 0..u16_at(lookup, 4).unwrap_or(0) as usize {
+            let s = match sub_table(lookup, 6 + 2 * k) {
+                Some(s) => s,
+                None => continue,
+            };
+            if t == extension_type {
+                if u16_at(s, 2) == Some(lookup_type) {
+                    if let Some(e) = u32_at(s, 4).and_then(|o| s.get(o as usize..)) {
+                        subtables.push(e);
+                    }
+                }
+            } else if t == lookup_type {
+                subtables.push(s);
+            }
+        }
+        result.push(subtables);
+    }
+    result
+}
+
+// the metrics of an OpenType font that TeX uses, in font units
+struct OpenTypeMetrics {
+    units_per_em: i32,
+    glyphs: BTreeMap<u8, u16>, // the glyph for each character code in the font
+    widths: BTreeMap<u8, i32>,
+    heights: BTreeMap<u8, i32>,
+    depths: BTreeMap<u8, i32>,
+    kerns: BTreeMap<(u8, u8), i32>, // kerns between pairs of characters
+    ligatures: BTreeMap<(u8, u8), u8>, // the character that replaces a pair of characters
+    italics: BTreeMap<u8, i32>, // italic corrections from the MATH table
+    successors: BTreeMap<u8, u8>, // the next larger variant of each character, from the MATH table
+    extensibles: BTreeMap<u8, [u8; 4]>, // top, middle, bottom and repeated pieces of assembled characters
+    slant: f64, // the slope of the characters, -tan of the italic angle
+    space: i32, // the width of an interword space
+    x_height: i32,
+}
+
+impl OpenTypeMetrics {
+    fn new(data: &[u8]) -> Option<OpenTypeMetrics> {
+        let face = Face::from_slice(data, 0).ok()?;
+        let upem = face.units_per_em()? as i32;
+        let mut m = OpenTypeMetrics {
+            units_per_em: upem,
+            glyphs: BTreeMap::new(),
+            widths: BTreeMap::new(),
+            heights: BTreeMap::new(),
+            depths: BTreeMap::new(),
+            kerns: BTreeMap::new(),
+            ligatures: BTreeMap::new(),
+            italics: BTreeMap::new(),
+            successors: BTreeMap::new(),
+            extensibles: BTreeMap::new(),
+            slant: -(face.italic_angle().unwrap_or(0.0) as f64).to_radians().tan(),
+            space: upem / 3,
+            x_height: upem / 2,
+        };
+        for c in 0..=255u8 {
+            if is_free_code(c) {
+                continue;
+            }
+            if let Some(g) = face.glyph_index(c as char) {
+                m.glyphs.insert(c, g.0);
+            }
+        }
+        m.find_ligatures(&face);
+        m.find_math_variants(&face);
+        for (&c, &g) in m.glyphs.iter() {
+            let g = GlyphId(g);
+            m.widths.insert(c, face.glyph_hor_advance(g).unwrap_or(0) as i32);
+            // glyphs without outlines, such as the space, have no height or depth
+            let (h, d) = match face.glyph_bounding_box(g) {
+                Some(r) => (r.y_max as i32, -(r.y_min as i32)),
+                None => (0, 0),
+            };
+            m.heights.insert(c, h.max(0));
+            m.depths.insert(c, d.max(0));
+        }
+        if let Some(&w) = m.widths.get(&b' ') {
+            m.space = w;
+        }
+        m.x_height = match face.x_height() {
+            Some(x) if x > 0 => x as i32,
+            _ => m.heights.get(&b'x').copied().unwrap_or(upem / 2),
+        };
+        m.find_kerns(&face);
+        Some(m)
+    }
+
+    // gives the font's standard ligatures codes of their own, as long as their
+    // components are characters of the font; a ligature of three characters is
+    // only usable when the ligature of its first two is there too
+    fn find_ligatures(&mut self, face: &Face) {
+        let code_of: BTreeMap<u16, u8> = self.glyphs.iter().map(|(&c, &g)| (g, c)).collect();
+        let mut ligs: Vec<(Vec<u8>, u16)> = Vec::new(); // components and ligature glyph
+        if let Some(gsub) = face.table_data(Tag::from_bytes(b"GSUB")) {
+            for lookup in feature_subtables(gsub, b"liga", 4, 7) {
+                for s in lookup {
+                    // a ligature substitution subtable, format 1
+                    let cov = match sub_table(s, 2) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    for (first, i) in coverage_glyphs(cov) {
+                        let set = match sub_table(s, 6 + 2 * i) {
+                            Some(t) if code_of.contains_key(&first) => t,
+                            _ => continue,
+                        };
+                        for j in 0..u16_at(set, 0).unwrap_or(0) as usize {
+                            let lig = match sub_table(set, 2 + 2 * j) {
+                                Some(l) => l,
+                                None => continue,
+                            };
+                            let (glyph, n) = match (u16_at(lig, 0), u16_at(lig, 2)) {
+                                (Some(g), Some(n)) if n == 2 || n == 3 => (g, n as usize),
+                                _ => continue,
+                            };
+                            let mut comps = vec![code_of[&first]];
+                            for k in 1..n {
+                                match u16_at(lig, 2 + 2 * k).and_then(|g| code_of.get(&g)) {
+                                    Some(&c) => comps.push(c),
+                                    None => break,
+                                }
+                            }
+                            if comps.len() == n && !ligs.iter().any(|(c, _)| *c == comps) {
+                                ligs.push((comps, glyph));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // pairs first, so that the longer ligatures can be built on them
+        ligs.sort_by_key(|(c, _)| c.len());
+        let mut free = FreeCodes.iter().flat_map(|&(a, b)| a..=b);
+        let mut lig_code: BTreeMap<u16, u8> = BTreeMap::new();
+        for (comps, glyph) in ligs {
+            let first = if comps.len() == 2 {
+                comps[0]
+            } else {
+                match self.ligatures.get(&(comps[0], comps[1])) {
+                    Some(&c) => c,
+                    None => continue,
+                }
+            };
+            let last = comps[comps.len() - 1];
+            if self.ligatures.contains_key(&(first, last)) {
+                continue;
+            }
+            let code = match lig_code.get(&glyph) {
+                Some(&c) => c,
+                None => match free.next() {
+                    Some(c) => c,
+                    None => break,
+                },
+            };
+            lig_code.insert(glyph, code);
+            self.glyphs.insert(code, glyph);
+            self.ligatures.insert((first, last), code);
+        }
+    }
+
+    // gives the glyph variants and assemblies of the MATH table the free codes
+    // that the ligatures left over: the larger variants of a character become
+    // its charlist, as in a TFM file, and its vertical assembly becomes an
+    // extensible character at the end of the list. Italic corrections come
+    // from the MATH table too
+    fn find_math_variants(&mut self, face: &Face) {
+        let math = match face.table_data(Tag::from_bytes(b"MATH")) {
+            Some(t) => t,
+            None => return,
+        };
+        // code 0 can't be used, since it means an absent piece in an extensible recipe
+        let mut free = FreeCodes.iter().flat_map(|&(a, b)| a..=b).filter(|&c| c != 0 && !self.glyphs.contains_key(&c))
+            .collect::<Vec<u8>>().into_iter();
+        let bases: BTreeMap<u16, u8> = self.glyphs.iter().map(|(&c, &g)| (g, c)).collect();
+        let mut variant_code: BTreeMap<u16, u8> = BTreeMap::new();
+        if let Some(variants) = offset_table(math, 8) {
+            let n_vert = u16_at(variants, 6).unwrap_or(0) as usize;
+            // the vertical constructions come first, since delimiters need them most
+            'variants: for &(cov, first, vertical) in &[(2, 10, true), (4, 10 + 2 * n_vert, false)] {
+                let cov = match offset_table(variants, cov) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                for (base, i) in coverage_glyphs(cov) {
+                    let mut prev = match bases.get(&base) {
+                        Some(&c) if !self.successors.contains_key(&c) && !self.extensibles.contains_key(&c) => c,
+                        _ => continue,
+                    };
+                    let construction = match offset_table(variants, first + 2 * i) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    let mut chain = vec![prev];
+                    let mut shared = false; // has the list run into another character's list?
+                    for k in 0..u16_at(construction, 2).unwrap_or(0) as usize {
+                        let g = match u16_at(construction, 4 + 4 * k) {
+                            Some(g) if g != base => g,
+                            _ => continue,
+                        };
+                        let c = match variant_code.get(&g) {
+                            Some(&c) => {
+                                if !chain.contains(&c) {
+                                    self.successors.insert(prev, c);
+                                }
+                                shared = true;
+                                break;
+                            }
+                            None => match free.next() {
+                                Some(c) => c,
+                                None => break 'variants,
+                            },
+                        };
+                        variant_code.insert(g, c);
+                        self.glyphs.insert(c, g);
+                        self.successors.insert(prev, c);
+                        chain.push(c);
+                        prev = c;
+                    }
+                    if !vertical || shared {
+                        continue;
+                    }
+                    // 713 builds extensible characters from at most four pieces, so
+                    // an assembly keeps its end pieces, one middle piece and its
+                    // first extender
+                    let assembly = match offset_table(construction, 0) {
+                        Some(a) => a,
+                        None => continue,
+                    };
+                    let parts: Vec<(u16, bool)> = (0..u16_at(assembly, 4).unwrap_or(0) as usize)
+                        .filter_map(|j| Some((u16_at(assembly, 6 + 10 * j)?, u16_at(assembly, 14 + 10 * j)? & 1 != 0)))
+                        .collect();
+                    let rep = match parts.iter().find(|p| p.1) {
+                        Some(p) => p.0,
+                        None => continue,
+                    };
+                    let n = parts.len();
+                    let top = if n > 1 && !parts[n - 1].1 { Some(parts[n - 1].0) } else { None };
+                    let mid = if n > 2 { parts[1..n - 1].iter().find(|p| !p.1).map(|p| p.0) } else { None };
+                    let bot = if !parts[0].1 { Some(parts[0].0) } else { None };
+                    let mut recipe = [0u8; 4];
+                    for (k, &g) in [top, mid, bot, Some(rep)].iter().enumerate() {
+                        if let Some(g) = g {
+                            recipe[k] = match bases.get(&g).or(variant_code.get(&g)) {
+                                Some(&c) => c,
+                                None => match free.next() {
+                                    Some(c) => {
+                                        variant_code.insert(g, c);
+                                        self.glyphs.insert(c, g);
+                                        c
+                                    }
+                                    None => break 'variants,
+                                },
+                            };
+                        }
+                    }
+                    // the extensible character itself looks like its repeated piece
+                    let e = match free.next() {
+                        Some(c) => c,
+                        None => break 'variants,
+                    };
+                    self.glyphs.insert(e, rep);
+                    self.successors.insert(prev, e);
+                    self.extensibles.insert(e, recipe);
+                }
+            }
+        }
+        let info = match offset_table(math, 6).and_then(|i| offset_table(i, 0)) {
+            Some(i) => i,
+            None => return,
+        };
+        let index: BTreeMap<u16, usize> = offset_table(info, 0).map(coverage_glyphs).unwrap_or_default().into_iter().collect();
+        for (&c, g) in self.glyphs.iter() {
+            if let Some(k) = index.get(g).and_then(|&i| i16_at(info, 4 + 4 * i)) {
+                if k != 0 {
+                    self.italics.insert(c, k as i32);
+                }
+            }
+        }
+    }
+
+    // finds the kerns between the characters of the font, from the kerning
+    // feature of the GPOS table, or from the kern table if there isn't one
+    fn find_kerns(&mut self, face: &Face) {
+        let codes: Vec<(u8, u16)> = self.glyphs.iter().map(|(&c, &g)| (c, g)).collect();
+        let code_of: BTreeMap<u16, u8> = self.glyphs.iter().map(|(&c, &g)| (g, c)).collect();
+        let lookups = face
+            .table_data(Tag::from_bytes(b"GPOS"))
+            .map(|gpos| feature_subtables(gpos, b"kern", 2, 9))
+            .unwrap_or_default();
+        if lookups.is_empty() {
+            for t in face.kerning_subtables() {
+                if !t.is_horizontal() || t.is_variable() || t.has_cross_stream() {
+                    continue;
+                }
+                for &(c, g) in codes.iter() {
+                    for &(d, h) in codes.iter() {
+                        if let Some(k) = t.glyphs_kerning(GlyphId(g), GlyphId(h)) {
+                            *self.kerns.entry((c, d)).or_insert(0) += k as i32;
+                        }
+                    }
+                }
+            }
+        }
+        for lookup in lookups {
+            // within a lookup, the first subtable that applies to a pair wins
+            let mut found: BTreeMap<(u8, u8), i32> = BTreeMap::new();
+            for s in lookup {
+                let (cov, vf1, vf2) = match (sub_table(s, 2), u16_at(s, 4), u16_at(s, 6)) {
+                    (Some(c), Some(a), Some(b)) => (c, a, b),
+                    _ => continue,
+                };
+                let (size1, x_advance) = value_record(vf1);
+                let (size2, _) = value_record(vf2);
+                let x_advance = match x_advance {
+                    Some(x) => x,
+                    None => continue,
+                };
+                for (first, i) in coverage_glyphs(cov) {
+                    let c = match code_of.get(&first) {
+                        Some(&c) => c,
+                        None => continue,
+                    };
+                    match u16_at(s, 0) {
+                        Some(1) => {
+                            // pairs of individual glyphs
+                            let set = match sub_table(s, 10 + 2 * i) {
+                                Some(t) => t,
+                                None => continue,
+                            };
+                            for j in 0..u16_at(set, 0).unwrap_or(0) as usize {
+                                let r = 2 + j * (2 + size1 + size2);
+                                if let (Some(second), Some(k)) = (u16_at(set, r), i16_at(set, r + 2 + x_advance)) {
+                                    if let Some(&d) = code_of.get(&second) {
+                                        found.entry((c, d)).or_insert(k as i32);
+                                    }
+                                }
+                            }
+                        }
+                        Some(2) => {
+                            // pairs of glyph classes
+                            let (cd1, cd2, n2) = match (sub_table(s, 8), sub_table(s, 10), u16_at(s, 14)) {
+                                (Some(a), Some(b), Some(n)) => (a, b, n as usize),
+                                _ => continue,
+                            };
+                            let row = 16 + glyph_class(cd1, first) as usize * n2 * (size1 + size2);
+                            for &(d, second) in codes.iter() {
+                                let r = row + glyph_class(cd2, second) as usize * (size1 + size2);
+                                if let Some(k) = i16_at(s, r + x_advance) {
+                                    found.entry((c, d)).or_insert(k as i32);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            for (pair, k) in found {
+                *self.kerns.entry(pair).or_insert(0) += k;
+            }
+        }
+        self.kerns.retain(|_, k| *k != 0);
+    }
+}
+
+// the distinct values of a font dimension, merged until there are at most max of
+// them; a character whose value was merged gets the larger of the two
+fn quantize(values: impl Iterator<Item = Scaled>, max: usize) -> Vec<Scaled> {
+    let mut v: Vec<Scaled> = values.collect();
+    v.sort();
+    v.dedup();
+    while v.len() > max {
+        let k = (1..v.len()).min_by_key(|&k| v[k] as i64 - v[k - 1] as i64).unwrap();
+        v.remove(k - 1);
+    }
+    v
+}
+
+// the index in table of the smallest value at least x, counting from first
+fn quantized_index(table: &[Scaled], x: Scaled, first: usize) -> usize {
+    first + table.iter().position(|&t| t >= x).unwrap_or(table.len() - 1)
+}
+
+impl TexState {
+    // loads an OpenType or TrueType font into font_info, laid out as the TFM
+    // file of section 539 would be; Err means the font can't be used
+    pub fn read_opentype(&mut self, data: &[u8], u: Pointer, nom: StrNumber, aire: StrNumber, s: Scaled)
+                         -> Result<InternalFontNumber, ()> {
+        let m = OpenTypeMetrics::new(data).ok_or(())?;
+        if m.glyphs.is_empty() {
+            return Err(());
+        }
+        let z = if s == -1000 {
+            OpenTypeDesignSize
+        } else if s >= 0 {
+            s
+        } else {
+            self.xn_over_d(OpenTypeDesignSize, -s, 1000)
+        };
+        let upem = m.units_per_em;
+        let scale = |x: i32| -> Scaled { scale_units(x, z, upem) };
+        let bc = *m.glyphs.keys().next().unwrap() as i32;
+        let ec = *m.glyphs.keys().next_back().unwrap() as i32;
+        // the tables of widths, heights and depths start with zero
+        let widths = quantize(m.widths.values().map(|&w| scale(w)), 255);
+        let heights = quantize(m.heights.values().map(|&h| scale(h)).filter(|&h| h > 0), 15);
+        let depths = quantize(m.depths.values().map(|&d| scale(d)).filter(|&d| d > 0), 15);
+        let italics = quantize(m.italics.values().map(|&i| scale(i)), 63);
+        let kerns = quantize(m.kerns.values().map(|&k| scale(k)), 32767);
+        // the lig/kern program: each character's first instruction is a jump
+        // to its steps, since a program can only start in the first 256 words.
+        // Characters with larger variants can't have one
+        let has_variants = |c: &u8| m.successors.contains_key(c) || m.extensibles.contains_key(c);
+        let mut programs: BTreeMap<u8, Vec<FourQuarters>> = BTreeMap::new();
+        for (&(c, d), &l) in m.ligatures.iter() {
+            if !has_variants(&c) {
+                programs.entry(c).or_default().push(FourQuarters { b0: 0, b1: d, b2: 0, b3: l });
+            }
+        }
+        for (&(c, d), &k) in m.kerns.iter() {
+            if m.ligatures.contains_key(&(c, d)) || has_variants(&c) {
+                continue;
+            }
+            let i = quantized_index(&kerns, scale(k), 0);
+            let steps = programs.entry(c).or_default();
+            steps.push(FourQuarters { b0: 0, b1: d, b2: KernFlag + (i / 256) as u8, b3: (i % 256) as u8 });
+        }
+        let mut lig_kern: Vec<FourQuarters> = Vec::new();
+        let mut start = programs.len();
+        for steps in programs.values_mut() {
+            steps.sort_by_key(|q| q.b1);
+            steps.last_mut().unwrap().b0 = StopFlag;
+            lig_kern.push(FourQuarters { b0: 254, b1: 0, b2: (start / 256) as u8, b3: (start % 256) as u8 });
+            start += steps.len();
+        }
+        for steps in programs.values() {
+            lig_kern.extend_from_slice(steps);
+        }
+        let (nw, nh, nd, ni) = (widths.len() as i32 + 1, heights.len() as i32 + 1, depths.len() as i32 + 1, italics.len() as i32 + 1);
+        let (nl, nk, ne, np) = (lig_kern.len() as i32, kerns.len() as i32, m.extensibles.len() as i32, 7);
+        let lf = (ec - bc + 1) + nw + nh + nd + ni + nl + nk + ne + np;
+        if self.font_ptr == FontMax as InternalFontNumber || self.fmem_ptr + lf as usize > FontMemSize as usize {
+            self.no_room_for_font(u, nom, aire, s);
+            return Ok(NullFont);
+        }
+        // allocate font information as in section 566
+        let f = self.font_ptr + 1;
+        let fmem_ptr = self.fmem_ptr as i32;
+        self.char_base[f] = fmem_ptr - bc;
+        self.width_base[f] = self.char_base[f] + ec + 1;
+        self.height_base[f] = self.width_base[f] + nw;
+        self.depth_base[f] = self.height_base[f] + nh;
+        self.italic_base[f] = self.depth_base[f] + nd;
+        self.lig_kern_base[f] = self.italic_base[f] + ni;
+        self.kern_base[f] = self.lig_kern_base[f] + nl - KernBaseOffset;
+        self.exten_base[f] = self.kern_base[f] + KernBaseOffset + nk;
+        self.param_base[f] = self.exten_base[f] + ne;
+        self.font_check[f] = FourQuarters::default();
+        self.font_dsize[f] = OpenTypeDesignSize;
+        self.font_size[f] = z;
+        let mut j: usize = 0; // the number of the next lig/kern jump; there is at most one per character
+        for c in bc..=ec {
+            let c = c as u8;
+            let mut ci = FourQuarters::default();
+            if let Some(&w) = m.widths.get(&c) {
+                let h = m.heights.get(&c).map_or(0, |&h| scale(h));
+                let d = m.depths.get(&c).map_or(0, |&d| scale(d));
+                ci.b0 = quantized_index(&widths, scale(w), 1) as u8;
+                ci.b1 = if h > 0 { quantized_index(&heights, h, 1) as u8 * 16 } else { 0 };
+                ci.b1 += if d > 0 { quantized_index(&depths, d, 1) as u8 } else { 0 };
+                if let Some(&i) = m.italics.get(&c) {
+                    ci.b2 = quantized_index(&italics, scale(i), 1) as u8 * 4;
+                }
+                if let Some(&d) = m.successors.get(&c) {
+                    ci.b2 += ListTag;
+                    ci.b3 = d;
+                } else if m.extensibles.contains_key(&c) {
+                    ci.b2 += ExtTag;
+                    ci.b3 = m.extensibles.range(..c).count() as u8;
+                } else if programs.contains_key(&c) && j < 256 {
+                    ci.b2 += LigTag;
+                    ci.b3 = j as u8;
+                    j += 1;
+                }
+            }
+            self.font_info[(self.char_base[f] + c as i32) as usize].set_qqqq(ci);
+        }
+        let tables = [(self.width_base[f], &widths), (self.height_base[f], &heights), (self.depth_base[f], &depths),
+            (self.italic_base[f], &italics)];
+        for &(base, table) in tables.iter() {
+            self.font_info[base as usize].set_sc(0);
+            for (k, &x) in table.iter().enumerate() {
+                self.font_info[base as usize + 1 + k].set_sc(x);
+            }
+        }
+        for (k, &q) in lig_kern.iter().enumerate() {
+            self.font_info[self.lig_kern_base[f] as usize + k].set_qqqq(q);
+        }
+        for (k, &x) in kerns.iter().enumerate() {
+            self.font_info[(self.kern_base[f] + KernBaseOffset) as usize + k].set_sc(x);
+        }
+        for (k, r) in m.extensibles.values().enumerate() {
+            self.font_info[self.exten_base[f] as usize + k].set_qqqq(FourQuarters { b0: r[0], b1: r[1], b2: r[2], b3: r[3] });
+        }
+        let params = [(m.slant * unity as f64).round() as Scaled, scale(m.space), scale(m.space) / 2,
+            scale(m.space) / 3, scale(m.x_height), z, scale(m.space) / 3];
+        for (k, &x) in params.iter().enumerate() {
+            self.font_info[self.param_base[f] as usize + k].set_sc(x);
+        }
+        // make final adjustments as in section 576
+        self.font_params[f] = np as FontIndex;
+        self.hyphen_char[f] = self.int_par(DefaultHyphenCharCode);
+        self.skew_char[f] = self.int_par(DefaultSkewCharCode);
+        self.bchar_label[f] = NonAddress;
+        self.font_bchar[f] = NonChar;
+        self.font_false_bchar[f] = NonChar;
+        self.font_name[f] = nom;
+        self.font_area[f] = aire;
+        self.font_bc[f] = bc as u8;
+        self.font_ec[f] = ec as u8;
+        self.font_glue[f] = Null;
+        self.param_base[f] -= 1;
+        self.fmem_ptr += lf as usize;
+        self.font_ptr = f;
+        Ok(f)
+    }
+}
+
+// what an output driver needs to know to embed an OpenType font; dimensions
+// are in thousandths of the em, as PDF font descriptors want them
+pub struct OpenTypeEmbedding {
+    pub glyphs: BTreeMap<u8, u16>, // the glyph for each character code, as read_opentype assigned them
+    pub postscript_name: String,
+    pub cff: bool, // are the outlines PostScript ones, rather than TrueType ones?
+    pub bbox: [i32; 4],
+    pub ascent: i32,
+    pub descent: i32,
+    pub cap_height: i32,
+    pub italic_angle: f64,
+}
+
+pub fn opentype_embedding(data: &[u8]) -> Option<OpenTypeEmbedding> {
+    let m = OpenTypeMetrics::new(data)?;
+    let face = Face::from_slice(data, 0).ok()?;
+    let units_per_em = m.units_per_em;
+    let em = |x: i32| x * 1000 / units_per_em;
+    let b = face.global_bounding_box();
+    let name = face.names().find(|n| n.name_id() == ttf_parser::name_id::POST_SCRIPT_NAME)
+        .and_then(|n| n.to_string());
+    Some(OpenTypeEmbedding {
+        glyphs: m.glyphs,
+        postscript_name: name.unwrap_or_default(),
+        cff: data.starts_with(b"OTTO"),
+        bbox: [em(b.x_min as i32), em(b.y_min as i32), em(b.x_max as i32), em(b.y_max as i32)],
+        ascent: em(face.ascender() as i32),
+        descent: em(face.descender() as i32),
+        cap_height: em(face.capital_height().unwrap_or(face.ascender()) as i32),
+        italic_angle: face.italic_angle().unwrap_or(0.0) as f64,
+    })
+}
+
+// the outlines of a font's characters as SVG path data, in font units with y
+// growing upward
+pub struct OpenTypeOutlines {
+    pub units_per_em: i32,
+    pub paths: BTreeMap<u8, String>, // characters whose glyphs draw nothing are left out
+}
+
+// collects the path of one glyph
+struct SvgPath(String);
+
+impl ttf_parser::OutlineBuilder for SvgPath {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.push_str(&format!("M{} {}", x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.push_str(&format!("L{} {}", x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.push_str(&format!("Q{} {} {} {}", x1, y1, x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.push_str(&format!("C{} {} {} {} {} {}", x1, y1, x2, y2, x, y));
+    }
+
+    fn close(&mut self) {
+        self.0.push('Z');
+    }
+}
+
+pub fn opentype_outlines(data: &[u8]) -> Option<OpenTypeOutlines> {
+    let m = OpenTypeMetrics::new(data)?;
+    let face = Face::from_slice(data, 0).ok()?;
+    let mut paths = BTreeMap::new();
+    for (&c, &g) in m.glyphs.iter() {
+        let mut path = SvgPath(String::new());
+        if face.outline_glyph(GlyphId(g), &mut path).is_some() {
+            paths.insert(c, path.0);
+        }
+    }
+    Some(OpenTypeOutlines { units_per_em: m.units_per_em, paths })
+}
+
+// the MATH table constants that the math routines use, numbered as the math
+// value records of the MathConstants table, from mathLeading on
+pub const MathAxisHeight: usize = 1;
+pub const MathSubscriptShiftDown: usize = 4;
+pub const MathSubscriptBaselineDropMin: usize = 6;
+pub const MathSuperscriptShiftUp: usize = 7;
+pub const MathSuperscriptShiftUpCramped: usize = 8;
+pub const MathSuperscriptBaselineDropMax: usize = 10;
+pub const MathUpperLimitGapMin: usize = 14;
+pub const MathUpperLimitBaselineRiseMin: usize = 15;
+pub const MathLowerLimitGapMin: usize = 16;
+pub const MathLowerLimitBaselineDropMin: usize = 17;
+pub const MathStackTopShiftUp: usize = 18;
+pub const MathFractionNumeratorShiftUp: usize = 28;
+pub const MathFractionNumeratorDisplayStyleShiftUp: usize = 29;
+pub const MathFractionDenominatorShiftDown: usize = 30;
+pub const MathFractionDenominatorDisplayStyleShiftDown: usize = 31;
+pub const MathFractionRuleThickness: usize = 34;
+const MathValueRecords: usize = 51;
+
+// the parts of a font's MATH table that aren't turned into TFM data by
+// read_opentype, in font units
+pub struct OpenTypeMath {
+    units_per_em: i32,
+    constants: Vec<i32>,
+    top_accents: BTreeMap<u8, i32>, // where an accent over each character should be centered
+}
+
+impl OpenTypeMath {
+    // constant k, for a font of size z
+    pub fn constant(&self, k: usize, z: Scaled) -> Scaled {
+        scale_units(self.constants[k], z, self.units_per_em)
+    }
+
+    // the top accent attachment of character c, for a font of size z
+    pub fn top_accent(&self, c: u8, z: Scaled) -> Option<Scaled> {
+        self.top_accents.get(&c).map(|&a| scale_units(a, z, self.units_per_em))
+    }
+}
+
+pub fn opentype_math(data: &[u8]) -> Option<OpenTypeMath> {
+    let m = OpenTypeMetrics::new(data)?;
+    let face = Face::from_slice(data, 0).ok()?;
+    let math = face.table_data(Tag::from_bytes(b"MATH"))?;
+    let values = offset_table(math, 4)?;
+    let constants = (0..MathValueRecords).map(|k| i16_at(values, 8 + 4 * k).unwrap_or(0) as i32).collect();
+    let mut top_accents = BTreeMap::new();
+    if let Some(t) = offset_table(math, 6).and_then(|i| offset_table(i, 2)) {
+        let index: BTreeMap<u16, usize> = offset_table(t, 0).map(coverage_glyphs).unwrap_or_default().into_iter().collect();
+        for (&c, g) in m.glyphs.iter() {
+            if let Some(a) = index.get(g).and_then(|&i| i16_at(t, 4 + 4 * i)) {
+                top_accents.insert(c, a as i32);
+            }
+        }
+    }
+    Some(OpenTypeMath { units_per_em: m.units_per_em, constants, top_accents })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::Otf;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    // a font in which f, i, l, A and V have ligatures and kerns, at 1000 units to the em
+    fn ligature_font() -> Vec<u8> {
+        Otf::new(1000)
+            .glyph(b'f', 300).glyph(b'i', 250).glyph(b'l', 250).glyph(b'A', 600).glyph(b'V', 600)
+            .ligature(b"fi", 500).ligature(b"ff", 550).ligature(b"ffi", 800).ligature(b"fl", 520)
+            .kern(b'A', b'V', -80).kern(b'V', b'A', -60)
+            .bytes()
+    }
+
+    #[test]
+    fn ligatures_and_kerns_come_from_gsub_and_gpos() {
+        let vfs = Vfs::new();
+        vfs.add_file("lig.ttf", ligature_font());
+        vfs.add_file("doc.tex", &br#"\catcode`\{=1 \catcode`\}=2 \font\f="lig.ttf" \f
+\setbox0\hbox{fi}\showthe\wd0 \setbox0\hbox{ffi}\showthe\wd0 \setbox0\hbox{fl}\showthe\wd0
+\setbox0\hbox{AVA}\showthe\wd0 \setbox0\hbox{if}\showthe\wd0
+\showboxdepth=1 \showboxbreadth=10 \setbox0\hbox{ffiAV}\showbox0
+\end
+"#[..]);
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]);
+        for w in &["> 5.0pt.", "> 8.0pt.", "> 5.2pt.", "> 16.60002pt.", "> 5.5pt."] {
+            assert!(log.contains(w), "{} {}", w, log);
+        }
+        assert!(log.contains(".\\f ^^C (ligature ffi)\n.\\f A\n.\\kern-0.79999\n.\\f V\n"), "{}", log);
+    }
+}
@@ -0,0 +1,499 @@
+use crate::*;
+
+// 644
+// the two ways hpack and vpack can be told the size of the box; a box of its
+// natural size is made with w=0 and m=Additional
+pub const Exactly: QuarterWord = 0; // a box dimension pre-specified
+pub const Additional: QuarterWord = 1; // a box dimension increment
+
+impl TexState {
+    // 645
+    // scans a box specification and the opening left brace; when three_codes is
+    // true, the value already in saved(0) is kept below the two new entries
+    pub fn scan_spec(&mut self, c: QuarterWord, three_codes: bool) {
+        let s = if three_codes { self.saved(0) } else { 0 }; // the value of saved(0) to be kept
+        let spec_code;
+        if self.scan_keyword("to") {
+            spec_code = Exactly;
+            self.scan_normal_dimen();
+        } else if self.scan_keyword("spread") {
+            spec_code = Additional;
+            self.scan_normal_dimen();
+        } else {
+            spec_code = Additional;
+            self.cur_val = 0;
+        }
+        if three_codes {
+            self.set_saved(0, s);
+            self.save_ptr += 1;
+        }
+        self.set_saved(0, spec_code as i32);
+        self.set_saved(1, self.cur_val);
+        self.save_ptr += 2;
+        self.new_save_level(c);
+        self.scan_left_brace();
+    }
+
+    // 649
+    // packages the hlist p into a box of width w, or of its natural width plus
+    // w, setting the glue as well as possible and complaining if it can't
+    pub fn hpack(&mut self, p: Pointer, w: Scaled, m: QuarterWord) -> Pointer {
+        let mut p = p;
+        let mut w = w;
+        self.last_badness = 0;
+        let r = self.get_node(BoxNodeSize);
+        self.set_type(r as usize, HlistNode);
+        self.set_subtype(r as usize, MinQuarterWord);
+        self.set_shift_amount(r as usize, 0);
+        let mut q = r as usize + ListOffset; // trails behind p
+        self.set_link(q, p);
+        let mut h: Scaled = 0; // height
+        let mut d: Scaled = 0; // depth
+        let mut x: Scaled = 0; // natural width
+        // 650: clear dimensions to zero
+        self.total_stretch = [0; 4];
+        self.total_shrink = [0; 4];
+        while p != Null {
+            // 651: examine node p in the hlist, taking account of its effect on the
+            // dimensions of the new box, or moving it to the adjustment list; then
+            // advance p to the next node
+            if self.is_char_node(p as usize) || self.a_type(p as usize) == LigatureNode {
+                // 654: incorporate character dimensions into the dimensions of the box
+                // that will contain it; a ligature is measured by its lig_char
+                let c = if self.is_char_node(p as usize) { p as usize } else { TexState::lig_char(p as usize) };
+                let f = self.font(c) as InternalFontNumber;
+                let i = self.char_info(f, self.character(c));
+                let hd = i.height_depth();
+                x += self.char_width(f, i);
+                h = h.max(self.char_height(f, hd));
+                d = d.max(self.char_depth(f, hd));
+                q = p as usize;
+                p = self.link(p as usize);
+                continue;
+            }
+            let t = p as usize;
+            match self.a_type(t) {
+                HlistNode | VlistNode | RuleNode | UnsetNode => {
+                    // 653: incorporate box dimensions
+                    x += self.width(t);
+                    let s = if self.a_type(t) >= RuleNode { 0 } else { self.shift_amount(t) };
+                    h = h.max(self.height(t) - s);
+                    d = d.max(self.depth(t) + s);
+                }
+                InsNode | MarkNode | AdjustNode if self.adjust_tail != Null => {
+                    // 655: transfer node p to the adjustment list
+                    if self.a_type(t) == AdjustNode {
+                        self.set_link(self.adjust_tail as usize, self.adjust_ptr(t));
+                        while self.link(self.adjust_tail as usize) != Null {
+                            self.adjust_tail = self.link(self.adjust_tail as usize);
+                        }
+                        p = self.link(t);
+                        self.free_node(t as Pointer, SmallNodeSize);
+                    } else {
+                        self.set_link(self.adjust_tail as usize, p);
+                        self.adjust_tail = p;
+                        p = self.link(t);
+                    }
+                    self.set_link(q, p);
+                    continue;
+                }
+                WhatsitNode => {} // 1360
+                GlueNode => {
+                    // 656: incorporate glue into the horizontal totals
+                    let g = self.glue_ptr(t) as usize;
+                    x += self.width(g);
+                    self.total_stretch[self.stretch_order(g) as usize] += self.stretch(g);
+                    self.total_shrink[self.shrink_order(g) as usize] += self.shrink(g);
+                    if self.subtype(t) >= ALeaders {
+                        let g = self.leader_ptr(t) as usize;
+                        h = h.max(self.height(g));
+                        d = d.max(self.depth(g));
+                    }
+                }
+                KernNode | MathNode => x += self.width(t),
+                _ => {}
+            }
+            q = t;
+            p = self.link(t);
+        }
+        if self.adjust_tail != Null {
+            self.set_link(self.adjust_tail as usize, Null);
+        }
+        self.set_height(r as usize, h);
+        self.set_depth(r as usize, d);
+        // 657: determine the value of width(r) and the appropriate glue setting;
+        // then return or report an overfull or underfull box
+        if m == Additional {
+            w += x;
+        }
+        self.set_width(r as usize, w);
+        x = w - x; // now x is the excess to be made up
+        if x == 0 {
+            self.set_glue_sign(r as usize, Normal as u8);
+            self.set_glue_order(r as usize, Normal as u8);
+            self.set_glue_set(r as usize, GlueRatio::ZERO);
+            return r;
+        }
+        let reported = if x > 0 {
+            // 658: determine horizontal glue stretch setting
+            let o = TexState::glue_total_order(&self.total_stretch);
+            self.set_glue_order(r as usize, o as u8);
+            self.set_glue_sign(r as usize, Stretching as u8);
+            if self.total_stretch[o as usize] != 0 {
+                self.set_glue_set(r as usize, GlueRatio::new(x, self.total_stretch[o as usize]));
+            } else {
+                self.set_glue_sign(r as usize, Normal as u8);
+                self.set_glue_set(r as usize, GlueRatio::ZERO); // there's nothing to stretch
+            }
+            if o == Normal && self.list_ptr(r as usize) != Null {
+                // 660: report an underfull hbox, if this box is sufficiently bad
+                self.last_badness = badness(x, self.total_stretch[Normal as usize]);
+                if self.last_badness > self.int_par(HbadnessCode) {
+                    self.print_ln();
+                    if self.last_badness > 100 {
+                        self.print_nl("Underfull");
+                    } else {
+                        self.print_nl("Loose");
+                    }
+                    self.print(" \\hbox (badness ");
+                    self.print_int(self.last_badness);
+                    true
+                } else {
+                    false
+                }
+            } else {
+                false
+            }
+        } else {
+            // 664: determine horizontal glue shrink setting
+            let o = TexState::glue_total_order(&self.total_shrink);
+            self.set_glue_order(r as usize, o as u8);
+            self.set_glue_sign(r as usize, Shrinking as u8);
+            if self.total_shrink[o as usize] != 0 {
+                self.set_glue_set(r as usize, GlueRatio::new(-x, self.total_shrink[o as usize]));
+            } else {
+                self.set_glue_sign(r as usize, Normal as u8);
+                self.set_glue_set(r as usize, GlueRatio::ZERO); // there's nothing to shrink
+            }
+            let total = self.total_shrink[Normal as usize];
+            if total < -x && o == Normal && self.list_ptr(r as usize) != Null {
+                self.last_badness = 1000000;
+                self.set_glue_set(r as usize, GlueRatio::ONE); // use the maximum shrinkage
+                // 666: report an overfull hbox, if this box is sufficiently bad
+                if -x - total > self.dimen_par(HfuzzCode) || self.int_par(HbadnessCode) < 100 {
+                    if self.dimen_par(OverfullRuleCode) > 0 && -x - total > self.dimen_par(HfuzzCode) {
+                        while self.link(q) != Null {
+                            q = self.link(q) as usize;
+                        }
+                        let rule = self.new_rule();
+                        self.set_link(q, rule);
+                        self.set_width(rule as usize, self.dimen_par(OverfullRuleCode));
+                    }
+                    self.print_ln();
+                    self.print_nl("Overfull \\hbox (");
+                    self.print_scaled(-x - total);
+                    self.print("pt too wide");
+                    true
+                } else {
+                    false
+                }
+            } else if o == Normal && self.list_ptr(r as usize) != Null {
+                // 667: report a tight hbox, if this box is sufficiently bad
+                self.last_badness = badness(-x, total);
+                if self.last_badness > self.int_par(HbadnessCode) {
+                    self.print_ln();
+                    self.print_nl("Tight \\hbox (badness ");
+                    self.print_int(self.last_badness);
+                    true
+                } else {
+                    false
+                }
+            } else {
+                false
+            }
+        };
+        if reported {
+            // 663: finish issuing a diagnostic message for an overfull or underfull hbox
+            if self.output_active {
+                self.print(") has occurred while \\output is active");
+            } else {
+                if self.pack_begin_line != 0 {
+                    if self.pack_begin_line > 0 {
+                        self.print(") in paragraph at lines ");
+                    } else {
+                        self.print(") in alignment at lines ");
+                    }
+                    self.print_int(self.pack_begin_line.abs());
+                    self.print("--");
+                } else {
+                    self.print(") detected at line ");
+                }
+                self.print_int(self.line);
+            }
+            self.print_ln();
+            self.font_in_short_display = NullFont as i32;
+            self.short_display(self.list_ptr(r as usize));
+            self.print_ln();
+            self.begin_diagnostic();
+            self.show_box(r);
+            self.end_diagnostic(true);
+        }
+        r
+    }
+
+    // 668
+    // packages the vlist p into a box of height h, or of its natural height
+    // plus h, whose depth is at most l
+    pub fn vpackage(&mut self, p: Pointer, h: Scaled, m: QuarterWord, l: Scaled) -> Pointer {
+        let mut p = p;
+        let mut h = h;
+        self.last_badness = 0;
+        let r = self.get_node(BoxNodeSize);
+        self.set_type(r as usize, VlistNode);
+        self.set_subtype(r as usize, MinQuarterWord);
+        self.set_shift_amount(r as usize, 0);
+        self.set_list_ptr(r as usize, p);
+        let mut w: Scaled = 0; // width
+        let mut d: Scaled = 0; // depth
+        let mut x: Scaled = 0; // natural height
+        self.total_stretch = [0; 4];
+        self.total_shrink = [0; 4];
+        while p != Null {
+            // 669: examine node p in the vlist, taking account of its effect on the
+            // dimensions of the new box; then advance p to the next node
+            let t = p as usize;
+            if self.is_char_node(t) {
+                self.confusion("vpack");
+            }
+            match self.a_type(t) {
+                HlistNode | VlistNode | RuleNode | UnsetNode => {
+                    // 670: incorporate box dimensions into the dimensions of the vbox
+                    x += d + self.height(t);
+                    d = self.depth(t);
+                    let s = if self.a_type(t) >= RuleNode { 0 } else { self.shift_amount(t) };
+                    w = w.max(self.width(t) + s);
+                }
+                WhatsitNode => {} // 1359
+                GlueNode => {
+                    // 671: incorporate glue into the vertical totals
+                    x += d;
+                    d = 0;
+                    let g = self.glue_ptr(t) as usize;
+                    x += self.width(g);
+                    self.total_stretch[self.stretch_order(g) as usize] += self.stretch(g);
+                    self.total_shrink[self.shrink_order(g) as usize] += self.shrink(g);
+                    if self.subtype(t) >= ALeaders {
+                        w = w.max(self.width(self.leader_ptr(t) as usize));
+                    }
+                }
+                KernNode => {
+                    x += d + self.width(t);
+                    d = 0;
+                }
+                _ => {}
+            }
+            p = self.link(t);
+        }
+        self.set_width(r as usize, w);
+        if d > l {
+            x += d - l;
+            self.set_depth(r as usize, l);
+        } else {
+            self.set_depth(r as usize, d);
+        }
+        // 672: determine the value of height(r) and the appropriate glue setting;
+        // then return or report an overfull or underfull box
+        if m == Additional {
+            h += x;
+        }
+        self.set_height(r as usize, h);
+        x = h - x; // now x is the excess to be made up
+        if x == 0 {
+            self.set_glue_sign(r as usize, Normal as u8);
+            self.set_glue_order(r as usize, Normal as u8);
+            self.set_glue_set(r as usize, GlueRatio::ZERO);
+            return r;
+        }
+        let reported = if x > 0 {
+            // 673: determine vertical glue stretch setting
+            let o = TexState::glue_total_order(&self.total_stretch);
+            self.set_glue_order(r as usize, o as u8);
+            self.set_glue_sign(r as usize, Stretching as u8);
+            if self.total_stretch[o as usize] != 0 {
+                self.set_glue_set(r as usize, GlueRatio::new(x, self.total_stretch[o as usize]));
+            } else {
+                self.set_glue_sign(r as usize, Normal as u8);
+                self.set_glue_set(r as usize, GlueRatio::ZERO); // there's nothing to stretch
+            }
+            if o == Normal && self.list_ptr(r as usize) != Null {
+                // 674: report an underfull vbox, if this box is sufficiently bad
+                self.last_badness = badness(x, self.total_stretch[Normal as usize]);
+                if self.last_badness > self.int_par(VbadnessCode) {
+                    self.print_ln();
+                    if self.last_badness > 100 {
+                        self.print_nl("Underfull");
+                    } else {
+                        self.print_nl("Loose");
+                    }
+                    self.print(" \\vbox (badness ");
+                    self.print_int(self.last_badness);
+                    true
+                } else {
+                    false
+                }
+            } else {
+                false
+            }
+        } else {
+            // 676: determine vertical glue shrink setting
+            let o = TexState::glue_total_order(&self.total_shrink);
+            self.set_glue_order(r as usize, o as u8);
+            self.set_glue_sign(r as usize, Shrinking as u8);
+            if self.total_shrink[o as usize] != 0 {
+                self.set_glue_set(r as usize, GlueRatio::new(-x, self.total_shrink[o as usize]));
+            } else {
+                self.set_glue_sign(r as usize, Normal as u8);
+                self.set_glue_set(r as usize, GlueRatio::ZERO); // there's nothing to shrink
+            }
+            let total = self.total_shrink[Normal as usize];
+            if total < -x && o == Normal && self.list_ptr(r as usize) != Null {
+                self.last_badness = 1000000;
+                self.set_glue_set(r as usize, GlueRatio::ONE); // use the maximum shrinkage
+                // 677: report an overfull vbox, if this box is sufficiently bad
+                if -x - total > self.dimen_par(VfuzzCode) || self.int_par(VbadnessCode) < 100 {
+                    self.print_ln();
+                    self.print_nl("Overfull \\vbox (");
+                    self.print_scaled(-x - total);
+                    self.print("pt too high");
+                    true
+                } else {
+                    false
+                }
+            } else if o == Normal && self.list_ptr(r as usize) != Null {
+                // 678: report a tight vbox, if this box is sufficiently bad
+                self.last_badness = badness(-x, total);
+                if self.last_badness > self.int_par(VbadnessCode) {
+                    self.print_ln();
+                    self.print_nl("Tight \\vbox (badness ");
+                    self.print_int(self.last_badness);
+                    true
+                } else {
+                    false
+                }
+            } else {
+                false
+            }
+        };
+        if reported {
+            // 675: finish issuing a diagnostic message for an overfull or underfull vbox
+            if self.output_active {
+                self.print(") has occurred while \\output is active");
+            } else {
+                if self.pack_begin_line != 0 {
+                    // it's actually negative
+                    self.print(") in alignment at lines ");
+                    self.print_int(self.pack_begin_line.abs());
+                    self.print("--");
+                } else {
+                    self.print(") detected at line ");
+                }
+                self.print_int(self.line);
+                self.print_ln();
+            }
+            self.begin_diagnostic();
+            self.show_box(r);
+            self.end_diagnostic(true);
+        }
+        r
+    }
+
+    // 668
+    // a vbox of natural height or of height h, with unlimited depth
+    pub fn vpack(&mut self, p: Pointer, h: Scaled, m: QuarterWord) -> Pointer {
+        self.vpackage(p, h, m, MaxDimen)
+    }
+
+    // 679
+    // appends box b to the current vertical list, with interline glue before
+    // it that makes the baselines \baselineskip apart when that is possible
+    pub fn append_to_vlist(&mut self, b: Pointer) {
+        if self.prev_depth() > IgnoreDepth {
+            let d = self.width(self.glue_par(BaselineSkipCode) as usize) - self.prev_depth() - self.height(b as usize);
+            let p = if d < self.dimen_par(LineSkipLimitCode) {
+                self.new_param_glue(LineSkipCode)
+            } else {
+                let p = self.new_skip_param(BaselineSkipCode);
+                self.set_width(self.temp_ptr as usize, d); // temp_ptr=glue_ptr(p)
+                p
+            };
+            self.tail_append(p);
+        }
+        self.tail_append(b);
+        self.set_prev_depth(self.depth(b as usize));
+    }
+
+    // 659
+    // the highest order of infinity with a nonzero total
+    pub fn glue_total_order(total: &[Scaled; 4]) -> GlueOrd {
+        if total[filll as usize] != 0 {
+            filll
+        } else if total[fill as usize] != 0 {
+            fill
+        } else if total[fil as usize] != 0 {
+            fil
+        } else {
+            Normal
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::text_font;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    fn log_of(body: &str) -> String {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("doc.tex", format!("\\catcode`\\{{=1 \\catcode`\\}}=2 \\font\\f=text \\f\n{}\n\\end\n", body));
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        String::from_utf8_lossy(&outputs["doc.log"]).into_owned()
+    }
+
+    #[test]
+    fn bad_hboxes_are_reported() {
+        let log = log_of("\\hbadness=10 \\hfuzz=0.5pt
+\\setbox0\\hbox to 20pt{a\\hskip 0pt plus 1pt}
+\\setbox0\\hbox to 6pt{a\\hskip 0pt plus 2pt}
+\\setbox0\\hbox to 4pt{a\\hskip 0pt minus 2pt}
+\\setbox0\\hbox to 2pt{aa}
+\\hbadness=100 \\setbox0\\hbox to 9.6pt{aa}
+\\setbox0\\hbox to 6pt{a\\hskip 0pt plus 2pt}
+\\setbox0\\hbox to 4pt{a\\hskip 0pt minus 2pt}");
+        assert!(log.contains("Underfull \\hbox (badness 10000) detected at line 3\n"), "{}", log);
+        assert!(log.contains("Loose \\hbox (badness 12) detected at line 4\n"), "{}", log);
+        assert!(log.contains("Tight \\hbox (badness 12) detected at line 5\n"), "{}", log);
+        assert!(log.contains("Overfull \\hbox (8.0pt too wide) detected at line 6\n"), "{}", log);
+        // within \hfuzz once \hbadness is 100 or more, and within \hbadness
+        assert!(!log.contains("line 7"), "{}", log);
+        assert!(!log.contains("line 8"), "{}", log);
+        assert!(!log.contains("line 9"), "{}", log);
+    }
+
+    #[test]
+    fn bad_vboxes_are_reported() {
+        let log = log_of("\\vbadness=10 \\vfuzz=0.5pt
+\\setbox0\\vbox to 20pt{\\hrule height 1pt\\vskip 0pt plus 1pt}
+\\setbox0\\vbox to 2pt{\\hrule height 1pt\\vskip 0pt plus 2pt}
+\\setbox0\\vbox to 3pt{\\hrule height 4pt\\vskip 0pt minus 2pt}
+\\setbox0\\vbox to 2pt{\\hrule height 10pt}
+\\vbadness=100 \\setbox0\\vbox to 9.6pt{\\hrule height 10pt}
+\\vbadness=10000 \\setbox0\\vbox to 20pt{\\hrule height 1pt\\vskip 0pt plus 1pt}");
+        assert!(log.contains("Underfull \\vbox (badness 10000) detected at line 3\n"), "{}", log);
+        assert!(log.contains("Loose \\vbox (badness 12) detected at line 4\n"), "{}", log);
+        assert!(log.contains("Tight \\vbox (badness 12) detected at line 5\n"), "{}", log);
+        assert!(log.contains("Overfull \\vbox (8.0pt too high) detected at line 6\n"), "{}", log);
+        assert!(!log.contains("line 7"), "{}", log);
+        assert!(!log.contains("line 8"), "{}", log);
+    }
+}
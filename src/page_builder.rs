@@ -0,0 +1,971 @@
+use crate::*;
+
+// 833
+pub const AwfulBad: i32 = 0o7777777777; // more than a billion demerits
+
+// 974
+pub const Deplorable: i32 = 100000; // more than inf_bad, but less than awful_bad
+
+// 980
+pub const InsertsOnly: u8 = 1; // page_contents when an insert node has been contributed, but no boxes
+pub const BoxThere: u8 = 2; // page_contents when a box or rule has been contributed
+
+// 981
+pub const Inserting: QuarterWord = 0; // an insertion class that has not yet been split
+pub const SplitUp: QuarterWord = 1; // an overflowing insertion class
+pub const PageInsNodeSize: usize = 4; // number of words for a page insertion node
+
+impl TexState {
+    // 981
+    // an insertion for this class will break here if anywhere
+    pub fn broken_ptr(&self, p: usize) -> HalfWord {
+        self.link(p + 1)
+    }
+
+    pub fn set_broken_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 1, value);
+    }
+
+    // this insertion might break at broken_ptr
+    pub fn broken_ins(&self, p: usize) -> HalfWord {
+        self.info(p + 1)
+    }
+
+    pub fn set_broken_ins(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 1, value);
+    }
+
+    // the most recent insertion for this subtype
+    pub fn last_ins_ptr(&self, p: usize) -> HalfWord {
+        self.link(p + 2)
+    }
+
+    pub fn set_last_ins_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_link(p + 2, value);
+    }
+
+    // the optimum most recent insertion
+    pub fn best_ins_ptr(&self, p: usize) -> HalfWord {
+        self.info(p + 2)
+    }
+
+    pub fn set_best_ins_ptr(&mut self, p: usize, value: HalfWord) {
+        self.set_info(p + 2, value);
+    }
+
+    // 982
+    // desired height of information on page being built
+    pub fn page_goal(&self) -> Scaled {
+        self.page_so_far[0]
+    }
+
+    pub fn set_page_goal(&mut self, value: Scaled) {
+        self.page_so_far[0] = value;
+    }
+
+    // height of the current page
+    pub fn page_total(&self) -> Scaled {
+        self.page_so_far[1]
+    }
+
+    pub fn set_page_total(&mut self, value: Scaled) {
+        self.page_so_far[1] = value;
+    }
+
+    // shrinkability of the current page
+    pub fn page_shrink(&self) -> Scaled {
+        self.page_so_far[6]
+    }
+
+    pub fn set_page_shrink(&mut self, value: Scaled) {
+        self.page_so_far[6] = value;
+    }
+
+    // depth of the current page
+    pub fn page_depth(&self) -> Scaled {
+        self.page_so_far[7]
+    }
+
+    pub fn set_page_depth(&mut self, value: Scaled) {
+        self.page_so_far[7] = value;
+    }
+
+    // 995
+    // the tail of the contribution list
+    pub fn set_contrib_tail(&mut self, value: HalfWord) {
+        self.nest[0].tail_field = value;
+    }
+
+    // 968
+    // adjust top after page break
+    pub fn prune_page_top(&mut self, p: Pointer) -> Pointer {
+        let mut p = p;
+        let mut prev_p = TempHead as Pointer;
+        self.set_link(TempHead, p);
+        while p != Null {
+            match self.a_type(p as usize) {
+                HlistNode | VlistNode | RuleNode => {
+                    // 969: insert glue for split_top_skip and set p:=null
+                    let q = self.new_skip_param(SplitTopSkipCode);
+                    self.set_link(prev_p as usize, q);
+                    self.set_link(q as usize, p); // now temp_ptr=glue_ptr(q)
+                    let t = self.temp_ptr as usize;
+                    if self.width(t) > self.height(p as usize) {
+                        self.set_width(t, self.width(t) - self.height(p as usize));
+                    } else {
+                        self.set_width(t, 0);
+                    }
+                    p = Null;
+                }
+                WhatsitNode | MarkNode | InsNode => {
+                    prev_p = p;
+                    p = self.link(prev_p as usize);
+                }
+                GlueNode | KernNode | PenaltyNode => {
+                    let q = p;
+                    p = self.link(q as usize);
+                    self.set_link(q as usize, Null);
+                    self.set_link(prev_p as usize, p);
+                    self.flush_node_list(q);
+                }
+                _ => self.confusion("pruning"),
+            }
+        }
+        self.link(TempHead)
+    }
+
+    // 970
+    // finds optimum page break
+    pub fn vert_break(&mut self, p: Pointer, h: Scaled, d: Scaled) -> Pointer {
+        let mut p = p;
+        let mut prev_p = p; // an initial glue node is not a legal breakpoint
+        let mut least_cost = AwfulBad;
+        let mut best_place = Null;
+        for k in 1..=6 {
+            self.active_width[k] = 0;
+        }
+        let mut prev_dp = 0;
+        loop {
+            // 972: if node p is a legal breakpoint, check if this break is the best known,
+            // and goto done if p is null or if the page-so-far is already too full
+            let pi;
+            let mut update_heights = false;
+            if p == Null {
+                pi = EjectPenalty;
+            } else {
+                // 973: use node p to update the current height and depth measurements;
+                // if this node is not a legal breakpoint, goto not_found or update_heights,
+                // otherwise set pi to the associated penalty at the break
+                match self.a_type(p as usize) {
+                    HlistNode | VlistNode | RuleNode => {
+                        self.active_width[1] += prev_dp + self.height(p as usize);
+                        prev_dp = self.depth(p as usize);
+                        pi = InfPenalty;
+                    }
+                    WhatsitNode | MarkNode | InsNode => pi = InfPenalty,
+                    GlueNode => {
+                        if self.a_type(prev_p as usize) < MathNode {
+                            pi = 0;
+                        } else {
+                            pi = InfPenalty;
+                            update_heights = true;
+                        }
+                    }
+                    KernNode => {
+                        let t = if self.link(p as usize) == Null {
+                            PenaltyNode
+                        } else {
+                            self.a_type(self.link(p as usize) as usize)
+                        };
+                        if t == GlueNode {
+                            pi = 0;
+                        } else {
+                            pi = InfPenalty;
+                            update_heights = true;
+                        }
+                    }
+                    PenaltyNode => pi = self.penalty(p as usize),
+                    _ => self.confusion("vertbreak"),
+                }
+            }
+            // 974: check if node p is a new champion breakpoint; then goto done
+            // if p is a forced break or if the page-so-far is already too full
+            if pi < InfPenalty {
+                // 975: compute the badness, b, using awful_bad if the box is too full
+                let cur_height = self.active_width[1];
+                let mut b = if cur_height < h {
+                    if self.active_width[3] != 0 || self.active_width[4] != 0 || self.active_width[5] != 0 {
+                        0
+                    } else {
+                        badness(h - cur_height, self.active_width[2])
+                    }
+                } else if cur_height - h > self.active_width[6] {
+                    AwfulBad
+                } else {
+                    badness(cur_height - h, self.active_width[6])
+                };
+                if b < AwfulBad {
+                    if pi <= EjectPenalty {
+                        b = pi;
+                    } else if b < InfBad {
+                        b += pi;
+                    } else {
+                        b = Deplorable;
+                    }
+                }
+                if b <= least_cost {
+                    best_place = p;
+                    least_cost = b;
+                    self.best_height_plus_depth = cur_height + prev_dp;
+                }
+                if b == AwfulBad || pi <= EjectPenalty {
+                    return best_place;
+                }
+                let t = self.a_type(p as usize);
+                update_heights = (GlueNode..=KernNode).contains(&t);
+            }
+            if update_heights {
+                // 976: update the current height and depth measurements with
+                // respect to a glue or kern node p
+                let q = if self.a_type(p as usize) == KernNode {
+                    p
+                } else {
+                    let mut q = self.glue_ptr(p as usize);
+                    let o = 2 + self.stretch_order(q as usize) as usize;
+                    self.active_width[o] += self.stretch(q as usize);
+                    self.active_width[6] += self.shrink(q as usize);
+                    if self.shrink_order(q as usize) != Normal as u8 && self.shrink(q as usize) != 0 {
+                        self.print_err("Infinite glue shrinkage found in box being split");
+                        self.help(&["The box you are \\vsplitting contains some infinitely",
+                            "shrinkable glue, e.g., `\\vss' or `\\vskip 0pt minus 1fil'.",
+                            "Such glue doesn't belong there; but you can safely proceed,",
+                            "since the offensive shrinkability has been made finite."]);
+                        self.error();
+                        let r = self.new_spec(q);
+                        self.set_shrink_order(r as usize, Normal as u8);
+                        self.delete_glue_ref(q);
+                        self.set_glue_ptr(p as usize, r);
+                        q = r;
+                    }
+                    q
+                };
+                self.active_width[1] += prev_dp + self.width(q as usize);
+                prev_dp = 0;
+            }
+            // not_found:
+            if prev_dp > d {
+                self.active_width[1] += prev_dp - d;
+                prev_dp = d;
+            }
+            prev_p = p;
+            p = self.link(prev_p as usize);
+        }
+    }
+
+    // 977
+    // extracts a page of height h from box n
+    pub fn vsplit(&mut self, n: HalfWord, h: Scaled) -> Pointer {
+        let v = self.box_reg(n);
+        self.clear_split_marks();
+        // 978: dispense with trivial cases of void or bad boxes
+        if v == Null {
+            return Null;
+        }
+        if self.a_type(v as usize) != VlistNode {
+            self.print_err("");
+            self.print_esc("vsplit");
+            self.print(" needs a ");
+            self.print_esc("vbox");
+            self.help(&["The box you are trying to split is an \\hbox.",
+                "I can't split such a box, so I'll leave it alone."]);
+            self.error();
+            return Null;
+        }
+        let q = self.vert_break(self.list_ptr(v as usize), h, self.dimen_par(SplitMaxDepthCode));
+        // 979: look at all the marks in nodes before the break, and set the final
+        // link to null at the break
+        let mut p = self.list_ptr(v as usize);
+        if p == q {
+            self.set_list_ptr(v as usize, Null);
+        } else {
+            loop {
+                if self.a_type(p as usize) == MarkNode {
+                    self.update_split_marks(p);
+                }
+                if self.link(p as usize) == q {
+                    self.set_link(p as usize, Null);
+                    break;
+                }
+                p = self.link(p as usize);
+            }
+        }
+        let q = self.prune_page_top(q);
+        let p = self.list_ptr(v as usize);
+        self.free_node(v, BoxNodeSize);
+        if q == Null {
+            self.set_box_reg(n, Null); // the eq_level of the box stays the same
+        } else {
+            let b = self.vpack(q, 0, Additional);
+            self.set_box_reg(n, b);
+        }
+        self.vpackage(p, h, Exactly, self.dimen_par(SplitMaxDepthCode))
+    }
+
+    // 985
+    pub fn print_totals(&mut self) {
+        self.print_scaled(self.page_total());
+        self.print_plus(2, "");
+        self.print_plus(3, "fil");
+        self.print_plus(4, "fill");
+        self.print_plus(5, "filll");
+        if self.page_shrink() != 0 {
+            self.print(" minus ");
+            self.print_scaled(self.page_shrink());
+        }
+    }
+
+    fn print_plus(&mut self, i: usize, s: &str) {
+        if self.page_so_far[i] != 0 {
+            self.print(" plus ");
+            self.print_scaled(self.page_so_far[i]);
+            self.print(s);
+        }
+    }
+
+    // 987
+    // initializes the page specifications when the first box or insertion arrives
+    pub fn freeze_page_specs(&mut self, s: u8) {
+        self.page_contents = s;
+        self.set_page_goal(self.dimen_par(VsizeCode));
+        self.page_max_depth = self.dimen_par(MaxDepthCode);
+        self.set_page_depth(0);
+        for k in 1..=6 {
+            self.page_so_far[k] = 0;
+        }
+        self.least_page_cost = AwfulBad;
+        if self.int_par(TracingPagesCode) > 0 {
+            self.begin_diagnostic();
+            self.print_nl("%% goal height=");
+            self.print_scaled(self.page_goal());
+            self.print(", max depth=");
+            self.print_scaled(self.page_max_depth);
+            self.end_diagnostic(false);
+        }
+    }
+
+    // 992
+    pub fn box_error(&mut self, n: HalfWord) {
+        self.error();
+        self.begin_diagnostic();
+        self.print_nl("The following box has been deleted:");
+        self.show_box(self.box_reg(n));
+        self.end_diagnostic(true);
+        self.flush_node_list(self.box_reg(n));
+        self.set_box_reg(n, Null);
+    }
+
+    // 993
+    pub fn ensure_vbox(&mut self, n: HalfWord) {
+        let p = self.box_reg(n);
+        if p != Null && self.a_type(p as usize) == HlistNode {
+            self.print_err("Insertions can only be added to a vbox");
+            self.help(&["Tut tut: You're trying to \\insert into a",
+                "\\box register that now contains an \\hbox.",
+                "Proceed, and I'll discard its present contents."]);
+            self.box_error(n);
+        }
+    }
+
+    // 994
+    // append contributions to the current page
+    pub fn build_page(&mut self) {
+        if self.link(ContribHead) == Null || self.output_active {
+            return;
+        }
+        loop {
+            // continue:
+            let p = self.link(ContribHead);
+            // 996: update the values of last_glue, last_penalty, and last_kern
+            if self.last_glue != MaxHalfWord {
+                self.delete_glue_ref(self.last_glue);
+            }
+            self.last_penalty = 0;
+            self.last_kern = 0;
+            if self.a_type(p as usize) == GlueNode {
+                self.last_glue = self.glue_ptr(p as usize);
+                self.add_glue_ref(self.last_glue);
+            } else {
+                self.last_glue = MaxHalfWord;
+                if self.a_type(p as usize) == PenaltyNode {
+                    self.last_penalty = self.penalty(p as usize);
+                } else if self.a_type(p as usize) == KernNode {
+                    self.last_kern = self.width(p as usize);
+                }
+            }
+            // 997: move node p to the current page; if it is time for a page break,
+            // put the nodes following the break back onto the contribution list,
+            // and return to the user's output routine if there is one
+            match self.contribute_node(p) {
+                PageStep::Continue => continue,
+                PageStep::Return => return,
+                PageStep::Contribute => {
+                    // 1003: make sure that page_max_depth is not exceeded
+                    if self.page_depth() > self.page_max_depth {
+                        self.set_page_total(self.page_total() + self.page_depth() - self.page_max_depth);
+                        self.set_page_depth(self.page_max_depth);
+                    }
+                    // 998: link node p into the current page and goto done
+                    self.set_link(self.page_tail as usize, p);
+                    self.page_tail = p;
+                    self.set_link(ContribHead, self.link(p as usize));
+                    self.set_link(p as usize, Null);
+                }
+                PageStep::Recycle => {
+                    // 999: recycle node p
+                    self.set_link(ContribHead, self.link(p as usize));
+                    self.set_link(p as usize, Null);
+                    self.flush_node_list(p);
+                }
+                PageStep::Done => {}
+            }
+            // done:
+            if self.link(ContribHead) == Null {
+                break;
+            }
+        }
+        // 995: make the contribution list empty by setting its tail to contrib_head
+        if self.nest_ptr == 0 {
+            self.set_tail(ContribHead as HalfWord); // vertical mode
+        } else {
+            self.set_contrib_tail(ContribHead as HalfWord); // other modes
+        }
+    }
+
+    // 1000
+    // if the current page is empty and node p is to be deleted, report Recycle;
+    // otherwise use node p to update the state of the current page, and either
+    // report Contribute or fire up a page at the best break
+    fn contribute_node(&mut self, p: Pointer) -> PageStep {
+        let pi;
+        let mut update_heights = false;
+        match self.a_type(p as usize) {
+            HlistNode | VlistNode | RuleNode => {
+                if self.page_contents < BoxThere {
+                    // 1001: initialize the current page, insert the \topskip glue
+                    // ahead of p, and goto continue
+                    if self.page_contents == Empty as u8 {
+                        self.freeze_page_specs(BoxThere);
+                    } else {
+                        self.page_contents = BoxThere;
+                    }
+                    let q = self.new_skip_param(TopSkipCode); // now temp_ptr=glue_ptr(q)
+                    let t = self.temp_ptr as usize;
+                    if self.width(t) > self.height(p as usize) {
+                        self.set_width(t, self.width(t) - self.height(p as usize));
+                    } else {
+                        self.set_width(t, 0);
+                    }
+                    self.set_link(q as usize, p);
+                    self.set_link(ContribHead, q);
+                    return PageStep::Continue;
+                }
+                // 1002: prepare to move a box or rule node to the current page,
+                // then goto contribute
+                self.set_page_total(self.page_total() + self.page_depth() + self.height(p as usize));
+                self.set_page_depth(self.depth(p as usize));
+                return PageStep::Contribute;
+            }
+            WhatsitNode | MarkNode => return PageStep::Contribute,
+            GlueNode => {
+                if self.page_contents < BoxThere {
+                    return PageStep::Recycle;
+                } else if self.a_type(self.page_tail as usize) < MathNode {
+                    pi = 0;
+                } else {
+                    pi = InfPenalty;
+                    update_heights = true;
+                }
+            }
+            KernNode => {
+                if self.page_contents < BoxThere {
+                    return PageStep::Recycle;
+                } else if self.link(p as usize) == Null {
+                    return PageStep::Return;
+                } else if self.a_type(self.link(p as usize) as usize) == GlueNode {
+                    pi = 0;
+                } else {
+                    pi = InfPenalty;
+                    update_heights = true;
+                }
+            }
+            PenaltyNode => {
+                if self.page_contents < BoxThere {
+                    return PageStep::Recycle;
+                }
+                pi = self.penalty(p as usize);
+            }
+            InsNode => {
+                self.append_insertion(p);
+                return PageStep::Contribute;
+            }
+            _ => self.confusion("page"),
+        }
+        // 1005: check if node p is a new champion breakpoint; then if it is time for
+        // a page break, prepare for output, and either fire up the user's
+        // output routine and return or ship out the page and goto done
+        if pi < InfPenalty {
+            // 1007: compute the badness, b, of the current page,
+            // using awful_bad if the box is too full
+            let b = if self.page_total() < self.page_goal() {
+                if self.page_so_far[3] != 0 || self.page_so_far[4] != 0 || self.page_so_far[5] != 0 {
+                    0
+                } else {
+                    badness(self.page_goal() - self.page_total(), self.page_so_far[2])
+                }
+            } else if self.page_total() - self.page_goal() > self.page_shrink() {
+                AwfulBad
+            } else {
+                badness(self.page_total() - self.page_goal(), self.page_shrink())
+            };
+            let mut c = if b < AwfulBad {
+                if pi <= EjectPenalty {
+                    pi
+                } else if b < InfBad {
+                    b + pi + self.insert_penalties
+                } else {
+                    Deplorable
+                }
+            } else {
+                b
+            };
+            if self.insert_penalties >= 10000 {
+                c = AwfulBad;
+            }
+            if self.int_par(TracingPagesCode) > 0 {
+                // 1006: display the page break cost
+                self.begin_diagnostic();
+                self.print_nl("%");
+                self.print(" t=");
+                self.print_totals();
+                self.print(" g=");
+                self.print_scaled(self.page_goal());
+                self.print(" b=");
+                if b == AwfulBad {
+                    self.print_char('*' as u8);
+                } else {
+                    self.print_int(b);
+                }
+                self.print(" p=");
+                self.print_int(pi);
+                self.print(" c=");
+                if c == AwfulBad {
+                    self.print_char('*' as u8);
+                } else {
+                    self.print_int(c);
+                }
+                if c <= self.least_page_cost {
+                    self.print_char('#' as u8);
+                }
+                self.end_diagnostic(false);
+            }
+            if c <= self.least_page_cost {
+                self.best_page_break = p;
+                self.best_size = self.page_goal();
+                self.least_page_cost = c;
+                let mut r = self.link(PageInsHead);
+                while r != PageInsHead as HalfWord {
+                    self.set_best_ins_ptr(r as usize, self.last_ins_ptr(r as usize));
+                    r = self.link(r as usize);
+                }
+            }
+            if c == AwfulBad || pi <= EjectPenalty {
+                self.fire_up(p); // output the current page at the best place
+                if self.output_active {
+                    return PageStep::Return; // user's output routine will act
+                }
+                return PageStep::Done; // the page has been shipped out by default output routine
+            }
+            let t = self.a_type(p as usize);
+            update_heights = (GlueNode..=KernNode).contains(&t);
+        }
+        if update_heights {
+            // 1004: update the current page measurements with respect to the
+            // glue or kern specified by node p
+            let q = if self.a_type(p as usize) == KernNode {
+                p
+            } else {
+                let mut q = self.glue_ptr(p as usize);
+                let o = 2 + self.stretch_order(q as usize) as usize;
+                self.page_so_far[o] += self.stretch(q as usize);
+                self.set_page_shrink(self.page_shrink() + self.shrink(q as usize));
+                if self.shrink_order(q as usize) != Normal as u8 && self.shrink(q as usize) != 0 {
+                    self.print_err("Infinite glue shrinkage found on current page");
+                    self.help(&["The page about to be output contains some infinitely",
+                        "shrinkable glue, e.g., `\\vss' or `\\vskip 0pt minus 1fil'.",
+                        "Such glue doesn't belong there; but you can safely proceed,",
+                        "since the offensive shrinkability has been made finite."]);
+                    self.error();
+                    let r = self.new_spec(q);
+                    self.set_shrink_order(r as usize, Normal as u8);
+                    self.delete_glue_ref(q);
+                    self.set_glue_ptr(p as usize, r);
+                    q = r;
+                }
+                q
+            };
+            self.set_page_total(self.page_total() + self.page_depth() + self.width(q as usize));
+            self.set_page_depth(0);
+        }
+        PageStep::Contribute
+    }
+
+    // 1008
+    // append an insertion to the current page
+    fn append_insertion(&mut self, p: Pointer) {
+        if self.page_contents == Empty as u8 {
+            self.freeze_page_specs(InsertsOnly);
+        }
+        let n = self.subtype(p as usize);
+        let mut r = PageInsHead as Pointer;
+        while n >= self.subtype(self.link(r as usize) as usize) {
+            r = self.link(r as usize);
+        }
+        let count = self.count(n as HalfWord);
+        if self.subtype(r as usize) != n {
+            // 1009: create a page insertion node with subtype(r)=n, and include the
+            // glue correction for box n in the current page state
+            let q = self.get_node(PageInsNodeSize);
+            self.set_link(q as usize, self.link(r as usize));
+            self.set_link(r as usize, q);
+            r = q;
+            self.set_subtype(r as usize, n);
+            self.set_type(r as usize, Inserting);
+            self.ensure_vbox(n as HalfWord);
+            let b = self.box_reg(n as HalfWord);
+            if b == Null {
+                self.set_height(r as usize, 0);
+            } else {
+                self.set_height(r as usize, self.height(b as usize) + self.depth(b as usize));
+            }
+            self.set_best_ins_ptr(r as usize, Null);
+            let q = self.skip(n as HalfWord) as usize;
+            let h = if count == 1000 {
+                self.height(r as usize)
+            } else {
+                self.x_over_n(self.height(r as usize), 1000) * count
+            };
+            self.set_page_goal(self.page_goal() - h - self.width(q));
+            let o = 2 + self.stretch_order(q) as usize;
+            self.page_so_far[o] += self.stretch(q);
+            self.set_page_shrink(self.page_shrink() + self.shrink(q));
+            if self.shrink_order(q) != Normal as u8 && self.shrink(q) != 0 {
+                self.print_err("Infinite glue shrinkage inserted from ");
+                self.print_esc("skip");
+                self.print_int(n as i32);
+                self.help(&["The correction glue for page breaking with insertions",
+                    "must have finite shrinkability. But you may proceed,",
+                    "since the offensive shrinkability has been made finite."]);
+                self.error();
+            }
+        }
+        if self.a_type(r as usize) == SplitUp {
+            self.insert_penalties += self.float_cost(p as usize);
+        } else {
+            self.set_last_ins_ptr(r as usize, p);
+            let delta = self.page_goal() - self.page_total() - self.page_depth() + self.page_shrink();
+            // this much room is left if we shrink the maximum
+            let h = if count == 1000 {
+                self.height(p as usize)
+            } else {
+                self.x_over_n(self.height(p as usize), 1000) * count
+            }; // this much room is needed
+            if (h <= 0 || h <= delta)
+                && self.height(p as usize) + self.height(r as usize) <= self.dimen(n as HalfWord)
+            {
+                self.set_page_goal(self.page_goal() - h);
+                self.set_height(r as usize, self.height(r as usize) + self.height(p as usize));
+            } else {
+                // 1010: find the best way to split the insertion, and change type(r) to split_up
+                let mut w = if count <= 0 {
+                    MaxDimen
+                } else {
+                    let w = self.page_goal() - self.page_total() - self.page_depth();
+                    if count != 1000 {
+                        self.x_over_n(w, count) * 1000
+                    } else {
+                        w
+                    }
+                };
+                if w > self.dimen(n as HalfWord) - self.height(r as usize) {
+                    w = self.dimen(n as HalfWord) - self.height(r as usize);
+                }
+                let q = self.vert_break(self.ins_ptr(p as usize), w, self.depth(p as usize));
+                self.set_height(r as usize, self.height(r as usize) + self.best_height_plus_depth);
+                if self.int_par(TracingPagesCode) > 0 {
+                    // 1011: display the insertion split cost
+                    self.begin_diagnostic();
+                    self.print_nl("% split");
+                    self.print_int(n as i32);
+                    self.print(" to ");
+                    self.print_scaled(w);
+                    self.print_char(',' as u8);
+                    self.print_scaled(self.best_height_plus_depth);
+                    self.print(" p=");
+                    if q == Null {
+                        self.print_int(EjectPenalty);
+                    } else if self.a_type(q as usize) == PenaltyNode {
+                        self.print_int(self.penalty(q as usize));
+                    } else {
+                        self.print_char('0' as u8);
+                    }
+                    self.end_diagnostic(false);
+                }
+                if count != 1000 {
+                    self.best_height_plus_depth = self.x_over_n(self.best_height_plus_depth, 1000) * count;
+                }
+                self.set_page_goal(self.page_goal() - self.best_height_plus_depth);
+                self.set_type(r as usize, SplitUp);
+                self.set_broken_ptr(r as usize, q);
+                self.set_broken_ins(r as usize, p);
+                if q == Null {
+                    self.insert_penalties += EjectPenalty;
+                } else if self.a_type(q as usize) == PenaltyNode {
+                    self.insert_penalties += self.penalty(q as usize);
+                }
+            }
+        }
+    }
+
+    // 1012
+    // output the current page at the best place
+    pub fn fire_up(&mut self, c: Pointer) {
+        // 1013: set the value of output_penalty
+        let bpb = self.best_page_break as usize;
+        if self.a_type(bpb) == PenaltyNode {
+            self.geq_word_define((IntBase + OutputPenaltyCode) as Pointer, self.penalty(bpb));
+            self.set_penalty(bpb, InfPenalty);
+        } else {
+            self.geq_word_define((IntBase + OutputPenaltyCode) as Pointer, InfPenalty);
+        }
+        self.start_page_marks();
+        // 1014: put the optimal current page into box 255, update first_mark and
+        // bot_mark, append insertions to their boxes, and put the
+        // remaining nodes back on the contribution list
+        if c == self.best_page_break {
+            self.best_page_break = Null; // c not yet linked in
+        }
+        // 1015: ensure that box 255 is empty before output
+        if self.box_reg(255) != Null {
+            self.print_err("");
+            self.print_esc("box");
+            self.print("255 is not void");
+            self.help(&["You shouldn't use \\box255 except in \\output routines.",
+                "Proceed, and I'll discard its present contents."]);
+            self.box_error(255);
+        }
+        self.insert_penalties = 0; // this will count the number of insertions held over
+        let save_split_top_skip = self.glue_par(SplitTopSkipCode);
+        if self.int_par(HoldingInsertsCode) <= 0 {
+            // 1018: prepare all the boxes involved in insertions to act as queues
+            let mut r = self.link(PageInsHead);
+            while r != PageInsHead as HalfWord {
+                if self.best_ins_ptr(r as usize) != Null {
+                    let n = self.subtype(r as usize) as HalfWord;
+                    self.ensure_vbox(n);
+                    if self.box_reg(n) == Null {
+                        let b = self.new_null_box();
+                        self.set_box_reg(n, b);
+                    }
+                    let mut p = self.box_reg(n) + ListOffset as HalfWord;
+                    while self.link(p as usize) != Null {
+                        p = self.link(p as usize);
+                    }
+                    self.set_last_ins_ptr(r as usize, p);
+                }
+                r = self.link(r as usize);
+            }
+        }
+        let mut q = HoldHead as Pointer;
+        self.set_link(q as usize, Null);
+        let mut prev_p = PageHead as Pointer;
+        let mut p = self.link(prev_p as usize);
+        while p != self.best_page_break {
+            if self.a_type(p as usize) == InsNode {
+                if self.int_par(HoldingInsertsCode) <= 0 {
+                    // 1020: either insert the material specified by node p into the
+                    // appropriate box, or hold it for the next page;
+                    // also delete node p from the current page
+                    let mut r = self.link(PageInsHead);
+                    while self.subtype(r as usize) != self.subtype(p as usize) {
+                        r = self.link(r as usize);
+                    }
+                    let mut wait;
+                    if self.best_ins_ptr(r as usize) == Null {
+                        wait = true;
+                    } else {
+                        wait = false;
+                        let mut s = self.last_ins_ptr(r as usize);
+                        self.set_link(s as usize, self.ins_ptr(p as usize));
+                        if self.best_ins_ptr(r as usize) == p {
+                            // 1021: wrap up the box specified by node r, splitting node p if
+                            // called for; set wait:=true if node p holds a remainder after splitting
+                            if self.a_type(r as usize) == SplitUp
+                                && self.broken_ins(r as usize) == p
+                                && self.broken_ptr(r as usize) != Null
+                            {
+                                while self.link(s as usize) != self.broken_ptr(r as usize) {
+                                    s = self.link(s as usize);
+                                }
+                                self.set_link(s as usize, Null);
+                                self.set_equiv((GlueBase + SplitTopSkipCode) as usize, self.split_top_ptr(p as usize));
+                                let t = self.prune_page_top(self.broken_ptr(r as usize));
+                                self.set_ins_ptr(p as usize, t);
+                                if t != Null {
+                                    self.temp_ptr = self.vpack(t, 0, Additional);
+                                    let tp = self.temp_ptr as usize;
+                                    self.set_height(p as usize, self.height(tp) + self.depth(tp));
+                                    self.free_node(self.temp_ptr, BoxNodeSize);
+                                    wait = true;
+                                }
+                            }
+                            self.set_best_ins_ptr(r as usize, Null);
+                            let n = self.subtype(r as usize) as HalfWord;
+                            self.temp_ptr = self.list_ptr(self.box_reg(n) as usize);
+                            self.free_node(self.box_reg(n), BoxNodeSize);
+                            let b = self.vpack(self.temp_ptr, 0, Additional);
+                            self.set_box_reg(n, b);
+                        } else {
+                            while self.link(s as usize) != Null {
+                                s = self.link(s as usize);
+                            }
+                            self.set_last_ins_ptr(r as usize, s);
+                        }
+                    }
+                    // 1022: either append the insertion node p after node q, and remove it
+                    // from the current page, or delete node(p)
+                    self.set_link(prev_p as usize, self.link(p as usize));
+                    self.set_link(p as usize, Null);
+                    if wait {
+                        self.set_link(q as usize, p);
+                        q = p;
+                        self.insert_penalties += 1;
+                    } else {
+                        self.delete_glue_ref(self.split_top_ptr(p as usize));
+                        self.free_node(p, InsNodeSize);
+                    }
+                    p = prev_p;
+                }
+            } else if self.a_type(p as usize) == MarkNode {
+                self.update_page_marks(p);
+            }
+            prev_p = p;
+            p = self.link(prev_p as usize);
+        }
+        self.set_equiv((GlueBase + SplitTopSkipCode) as usize, save_split_top_skip);
+        // 1017: break the current page at node p, put it in box 255,
+        // and put the remaining nodes on the contribution list
+        if p != Null {
+            if self.link(ContribHead) == Null {
+                if self.nest_ptr == 0 {
+                    self.set_tail(self.page_tail);
+                } else {
+                    self.set_contrib_tail(self.page_tail);
+                }
+            }
+            self.set_link(self.page_tail as usize, self.link(ContribHead));
+            self.set_link(ContribHead, p);
+            self.set_link(prev_p as usize, Null);
+        }
+        let save_vbadness = self.int_par(VbadnessCode);
+        self.set_int_par(VbadnessCode, InfBad);
+        let save_vfuzz = self.dimen_par(VfuzzCode);
+        self.set_dimen_par(VfuzzCode, MaxDimen); // inhibit error messages
+        let b = self.vpackage(self.link(PageHead), self.best_size, Exactly, self.page_max_depth);
+        self.set_box_reg(255, b);
+        self.set_int_par(VbadnessCode, save_vbadness);
+        self.set_dimen_par(VfuzzCode, save_vfuzz);
+        if self.last_glue != MaxHalfWord {
+            self.delete_glue_ref(self.last_glue);
+        }
+        // 991: start a new current page
+        self.start_new_page(); // this sets last_glue:=max_halfword
+        if q != HoldHead as Pointer {
+            self.set_link(PageHead, self.link(HoldHead));
+            self.page_tail = q;
+        }
+        // 1019: delete the page-insertion nodes
+        let mut r = self.link(PageInsHead);
+        while r != PageInsHead as HalfWord {
+            q = self.link(r as usize);
+            self.free_node(r, PageInsNodeSize);
+            r = q;
+        }
+        self.set_link(PageInsHead, PageInsHead as HalfWord);
+        self.finish_page_marks();
+        if self.output_routine() != Null {
+            if self.dead_cycles >= self.int_par(MaxDeadCyclesCode) {
+                // 1024: explain that too many dead cycles have occurred in a row
+                self.print_err("Output loop---");
+                self.print_int(self.dead_cycles);
+                self.print(" consecutive dead cycles");
+                self.help(&["I've concluded that your \\output is awry; it never does a",
+                    "\\shipout, so I'm shipping \\box255 out myself. Next time",
+                    "increase \\maxdeadcycles if you want me to be more patient!"]);
+                self.error();
+            } else {
+                // 1025: fire up the user's output routine and return
+                self.output_active = true;
+                self.dead_cycles += 1;
+                self.push_nest();
+                self.set_mode(-Vmode);
+                self.set_prev_depth(IgnoreDepth);
+                self.set_mode_line(-self.line);
+                self.begin_token_list(self.output_routine(), OutputText);
+                self.new_save_level(OutputGroup);
+                self.normal_paragraph();
+                self.scan_left_brace();
+                return;
+            }
+        }
+        // 1023: perform the default output routine
+        if self.link(PageHead) != Null {
+            if self.link(ContribHead) == Null {
+                if self.nest_ptr == 0 {
+                    self.set_tail(self.page_tail);
+                } else {
+                    self.set_contrib_tail(self.page_tail);
+                }
+            } else {
+                self.set_link(self.page_tail as usize, self.link(ContribHead));
+            }
+            self.set_link(ContribHead, self.link(PageHead));
+            self.set_link(PageHead, Null);
+            self.page_tail = PageHead as HalfWord;
+        }
+        self.ship_out(self.box_reg(255));
+        self.set_box_reg(255, Null);
+    }
+
+    // 991
+    // start a new current page
+    pub fn start_new_page(&mut self) {
+        self.page_contents = Empty as u8;
+        self.page_tail = PageHead as HalfWord;
+        self.set_link(PageHead, Null);
+        self.last_glue = MaxHalfWord;
+        self.last_penalty = 0;
+        self.last_kern = 0;
+        self.set_page_depth(0);
+        self.page_max_depth = 0;
+    }
+}
+
+// 997
+// where build_page goes after looking at a contribution
+enum PageStep {
+    Continue, // the contribution list has changed, look at it again
+    Contribute, // link the node into the current page
+    Recycle, // the page is empty and the node is discarded
+    Done, // the page has been shipped out
+    Return, // leave build_page at once
+}
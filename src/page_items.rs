@@ -0,0 +1,184 @@
+use crate::*;
+
+// Output formats other than DVI see a shipped-out box as a flat list of the
+// characters, rules and specials on the page, made by the same hlist_out and
+// vlist_out that write the DVI file. Positions are in
+// the DVI file's coordinates: h grows to the right and v downward from the
+// reference point one inch in from the top left corner of the paper.
+
+// the reference point's distance from the edges of the paper
+pub const OneInch: Scaled = 4736286;
+
+// a length in big points as the output formats write it, to four places
+pub fn format_bp(x: f64) -> String {
+    let s = format!("{:.4}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+// one mark on the page; characters of virtual fonts have already been replaced
+// by the packets that make them up
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageItem {
+    Char { f: InternalFontNumber, c: u8, h: Scaled, v: Scaled }, // the reference point of the character
+    Rule { h: Scaled, v: Scaled, width: Scaled, height: Scaled }, // h and v give the bottom left corner
+    Special { h: Scaled, v: Scaled, text: Vec<u8> },
+}
+
+// the formats ship_out can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Dvi,
+    Pdf,
+    Svg,
+}
+
+impl OutputFormat {
+    // the format named by TEXOUTPUT, which is dvi, pdf or svg; DVI if it names none of them
+    pub fn from_env() -> OutputFormat {
+        match std::env::var("TEXOUTPUT") {
+            Ok(ref v) if v.eq_ignore_ascii_case("pdf") => OutputFormat::Pdf,
+            Ok(ref v) if v.eq_ignore_ascii_case("svg") => OutputFormat::Svg,
+            _ => OutputFormat::Dvi,
+        }
+    }
+}
+
+impl TexState {
+    // chooses what ship_out writes; this must be done before the first page goes out
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    // a length in sp on the page as a length in big points, magnified by \mag
+    pub fn page_bp(&self, x: Scaled) -> f64 {
+        x as f64 / 65536.0 * 72.0 / 72.27 * self.int_par(MagCode) as f64 / 1000.0
+    }
+
+    // the size of the paper for box p: the box and its offsets, with an inch of margin all round
+    pub fn page_size(&self, p: Pointer) -> (Scaled, Scaled) {
+        let w = self.width(p as usize) + self.dimen_par(HOffsetCode) + 2 * OneInch;
+        let h = self.height(p as usize) + self.depth(p as usize) + self.dimen_par(VOffsetCode) + 2 * OneInch;
+        (w, h)
+    }
+
+    // the marks made by box p when it is shipped out; hlist_out and vlist_out
+    // collect them instead of writing the DVI file
+    pub fn page_items(&mut self, p: Pointer) -> Vec<PageItem> {
+        self.page_marks = Some(Vec::new());
+        self.cur_h = self.dimen_par(HOffsetCode);
+        self.cur_v = self.height(p as usize) + self.dimen_par(VOffsetCode);
+        self.dvi_h = self.cur_h;
+        self.dvi_v = self.cur_v;
+        if self.a_type(p as usize) == VlistNode {
+            self.vlist_out(p);
+        } else {
+            self.hlist_out(p);
+        }
+        self.page_marks.take().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::Otf;
+    use crate::{typeset_to, OutputFormat, Vfs};
+    use miniz_oxide::inflate::decompress_to_vec_zlib;
+    use std::collections::BTreeMap;
+
+    // the position of the first b in a at or after k
+    fn find(a: &[u8], b: &[u8], k: usize) -> Option<usize> {
+        a[k..].windows(b.len()).position(|w| w == b).map(|p| k + p)
+    }
+
+    // the number after key in the dictionary at the start of s
+    fn dict_int(s: &[u8], key: &str) -> usize {
+        let k = find(s, key.as_bytes(), 0).unwrap() + key.len();
+        let n: String = s[k..].iter().map(|&c| c as char).skip_while(|c| *c == ' ').take_while(char::is_ascii_digit).collect();
+        n.parse().unwrap()
+    }
+
+    // the decompressed data of the stream object at offset k of the file
+    fn stream_at(pdf: &[u8], k: usize) -> Vec<u8> {
+        let start = find(pdf, b">>\nstream\n", k).unwrap() + 10;
+        let len = dict_int(&pdf[k..start], "/Length");
+        decompress_to_vec_zlib(&pdf[start..start + len]).unwrap()
+    }
+
+    // every object of the file, found through the cross-reference stream
+    fn pdf_objects(pdf: &[u8]) -> BTreeMap<usize, Vec<u8>> {
+        let tail = find(pdf, b"startxref\n", pdf.len() - 40).unwrap() + 10;
+        let xref: usize = String::from_utf8_lossy(&pdf[tail..]).split_whitespace().next().unwrap().parse().unwrap();
+        let xref_dict = &pdf[xref..find(pdf, b"stream\n", xref).unwrap()];
+        assert!(find(xref_dict, b"/W [1 4 2]", 0).is_some());
+        let size = dict_int(xref_dict, "/Size");
+        let table = stream_at(pdf, xref);
+        assert_eq!(table.len(), 7 * size);
+        let entry = |n: usize| {
+            let e = &table[7 * n..7 * n + 7];
+            (e[0], u32::from_be_bytes([e[1], e[2], e[3], e[4]]) as usize, u16::from_be_bytes([e[5], e[6]]) as usize)
+        };
+        let mut objects = BTreeMap::new();
+        for n in 1..size {
+            match entry(n) {
+                (1, offset, _) => {
+                    let head = format!("{} 0 obj\n", n);
+                    assert!(pdf[offset..].starts_with(head.as_bytes()), "object {}", n);
+                    let end = find(pdf, b"endobj", offset).unwrap();
+                    objects.insert(n, pdf[offset + head.len()..end].to_vec());
+                }
+                (2, objstm, i) => {
+                    // the object stream's index must give this object number at position i
+                    let (t, offset, _) = entry(objstm);
+                    assert_eq!(t, 1);
+                    assert!(find(&pdf[offset..offset + 40], b"/Type /ObjStm", 0).is_some());
+                    let count = dict_int(&pdf[offset..], "/N");
+                    assert!(i < count && count <= 100);
+                    let first = dict_int(&pdf[offset..], "/First");
+                    let data = stream_at(pdf, offset);
+                    let index: Vec<usize> = String::from_utf8_lossy(&data[..first]).split_whitespace()
+                        .map(|x| x.parse().unwrap()).collect();
+                    assert_eq!(index[2 * i], n);
+                    let end = if i + 1 < count { index[2 * i + 3] } else { data.len() - first };
+                    objects.insert(n, data[first + index[2 * i + 1]..first + end].to_vec());
+                }
+                e => panic!("object {} has cross-reference entry {:?}", n, e),
+            }
+        }
+        objects
+    }
+
+    #[test]
+    fn typeset_to_pdf_writes_a_pdf_file_instead_of_dvi() {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\shipout\\hbox{\\vrule width 1pt}\\end\n");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Pdf);
+        assert!(!outputs.contains_key("doc.dvi"));
+        assert!(outputs["doc.pdf"].starts_with(b"%PDF-"));
+        let log = String::from_utf8_lossy(&outputs["doc.log"]);
+        assert!(log.contains("Output written on doc.pdf (1 page"), "{}", log);
+    }
+
+    #[test]
+    fn pdf_objects_are_found_through_the_cross_reference_stream() {
+        let vfs = Vfs::new();
+        vfs.add_file("font.ttf", Otf::new(1000).glyph(b'a', 500).bytes());
+        // enough pages that their dictionaries fill more than one object stream
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\font\\f=\"font.ttf\" \\f
+\\shipout\\hbox{\\special{pdf:dest (top)}\\special{pdf:bann <</Subtype/Link/A<</S/GoTo/D(top)>>>>}a\\special{pdf:eann}}
+\\count1=1 \\def\\pages{\\shipout\\hbox{a}\\advance\\count1 by 1 \\ifnum\\count1<150 \\expandafter\\pages\\fi}\\pages
+\\end
+");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Pdf);
+        let objects = pdf_objects(&outputs["doc.pdf"]);
+        let with = |s: &str| objects.values().filter(|o| find(o, s.as_bytes(), 0).is_some()).count();
+        assert_eq!(with("/Type /Page "), 150);
+        assert!(with("/Type /ObjStm") >= 2);
+        assert_eq!(with("/Subtype /Type0"), 1);
+        assert_eq!(with("/FontFile2"), 1);
+        assert_eq!(with("/Subtype/Link"), 1);
+        assert_eq!(with("/Dests"), 1);
+        assert_eq!(with("/top ["), 1);
+    }
+}
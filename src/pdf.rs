@@ -0,0 +1,607 @@
+use crate::*;
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+// The PDF backend writes each shipped-out page from its page items. Fonts
+// loaded from TFM files are embedded from the Type 1 (.pfb) file of the same
+// name, and OpenType fonts are embedded whole as CID-keyed fonts addressed
+// by glyph number. Dictionaries are packed into a compressed object stream,
+// so the cross-reference table is a cross-reference stream, as PDF 1.5 allows.
+//
+// Link annotations and destinations are made with specials in the style of
+// dvipdfmx:
+//   \special{pdf:ann width 2in height 8pt depth 2pt <</Subtype/Link ...>>}
+//   \special{pdf:bann <</Subtype/Link ...>>} ... \special{pdf:eann}
+//   \special{pdf:dest (name)}
+// where bann and eann surround the material to be made active.
+
+// the most objects put into one object stream, as in pdfTeX; it keeps the
+// indices within object streams small enough for their two-byte field in the
+// cross-reference stream
+const ObjStmSize: usize = 100;
+
+// one indirect object of the PDF file
+enum PdfObject {
+    Dict(Vec<u8>), // any object other than a stream; these go into the object stream
+    Stream(String, Vec<u8>), // the entries of a stream's dictionary, apart from its length, and its data
+}
+
+// how the characters of a font are shown
+#[derive(Clone)]
+enum PdfFontCodes {
+    Bytes, // one byte per character, the font's own code
+    Glyphs(BTreeMap<u8, u16>), // two bytes per character, its glyph number
+}
+
+#[derive(Clone)]
+struct PdfFont {
+    resource: String, // the font's name in the resource dictionaries, like /F1
+    object: usize,
+    codes: PdfFontCodes,
+}
+
+// a link annotation begun by pdf:bann, and the extent of the marks it covers so far
+struct PdfLink {
+    dict: Vec<u8>,
+    rect: Option<[f64; 4]>,
+}
+
+// everything written to the PDF file so far
+#[derive(Default)]
+pub struct PdfDocument {
+    file: Option<Box<dyn Write>>,
+    objects: Vec<Option<PdfObject>>, // object n is objects[n-1]
+    pages: Vec<usize>, // the page objects, in order
+    pages_root: usize, // the root of the page tree, or 0 before the first page
+    fonts: BTreeMap<InternalFontNumber, PdfFont>,
+    dests: Vec<(Vec<u8>, String)>, // named destinations, with their explicit destinations
+    link: Option<PdfLink>,
+    needs_pdf_16: bool, // an embedded font is a kind that PDF 1.5 doesn't know
+}
+
+impl PdfDocument {
+    // a new object number, whose object will be given later
+    fn alloc(&mut self) -> usize {
+        self.objects.push(None);
+        self.objects.len()
+    }
+
+    fn set(&mut self, n: usize, obj: PdfObject) {
+        self.objects[n - 1] = Some(obj);
+    }
+
+    fn add(&mut self, obj: PdfObject) -> usize {
+        let n = self.alloc();
+        self.set(n, obj);
+        n
+    }
+
+    fn add_dict(&mut self, s: String) -> usize {
+        self.add(PdfObject::Dict(s.into_bytes()))
+    }
+
+    // a compressed stream
+    fn add_stream(&mut self, dict: String, data: &[u8]) -> usize {
+        let dict = format!("/Filter /FlateDecode{}{}", if dict.is_empty() { "" } else { " " }, dict);
+        self.add(PdfObject::Stream(dict, compress_to_vec_zlib(data, 6)))
+    }
+
+    // the bytes of the whole file, given the catalog and the document information
+    fn serialize(&mut self, root: usize, info: usize) -> Vec<u8> {
+        let version = if self.needs_pdf_16 { 6 } else { 5 };
+        let mut out = format!("%PDF-1.{}\n%", version).into_bytes();
+        out.extend_from_slice(&[0xe2, 0xe3, 0xcf, 0xd3, b'\n']);
+        // the dictionaries go into object streams of at most ObjStmSize objects,
+        // numbered after the other objects, and the cross-reference stream comes last
+        let dicts = self.objects.iter().filter(|obj| matches!(obj, Some(PdfObject::Dict(_)))).count();
+        let first_objstm = self.objects.len() + 1;
+        let xref = first_objstm + dicts.div_ceil(ObjStmSize);
+        // (type, field 2, field 3) for each object, as in a cross-reference stream
+        let mut entries = vec![(0u8, 0u32, 0xffffu16); xref + 1];
+        // the object numbers and offsets within each object stream, and its objects
+        let mut streams: Vec<(Vec<String>, Vec<u8>)> = Vec::new();
+        for (k, obj) in self.objects.iter().enumerate() {
+            let n = k + 1;
+            match obj {
+                Some(PdfObject::Stream(dict, data)) => {
+                    entries[n] = (1, out.len() as u32, 0);
+                    out.extend(format!("{} 0 obj\n<< {} /Length {} >>\nstream\n", n, dict, data.len()).bytes());
+                    out.extend_from_slice(data);
+                    out.extend_from_slice(b"\nendstream\nendobj\n");
+                }
+                Some(PdfObject::Dict(d)) => {
+                    if streams.last().is_none_or(|(index, _)| index.len() == ObjStmSize) {
+                        streams.push((Vec::new(), Vec::new()));
+                    }
+                    let objstm = first_objstm + streams.len() - 1;
+                    let (index, packed) = streams.last_mut().unwrap();
+                    entries[n] = (2, objstm as u32, index.len() as u16);
+                    index.push(format!("{} {}", n, packed.len()));
+                    packed.extend_from_slice(d);
+                    packed.push(b'\n');
+                }
+                None => entries[n] = (0, 0, 0), // never given, so treated as free
+            }
+        }
+        for (k, (index, packed)) in streams.iter().enumerate() {
+            let objstm = first_objstm + k;
+            let head = index.join(" ") + "\n";
+            let mut body = head.clone().into_bytes();
+            body.extend_from_slice(packed);
+            let data = compress_to_vec_zlib(&body, 6);
+            entries[objstm] = (1, out.len() as u32, 0);
+            out.extend(format!("{} 0 obj\n<< /Type /ObjStm /N {} /First {} /Filter /FlateDecode /Length {} >>\nstream\n",
+                               objstm, index.len(), head.len(), data.len()).bytes());
+            out.extend_from_slice(&data);
+            out.extend_from_slice(b"\nendstream\nendobj\n");
+        }
+        // the cross-reference stream
+        let start = out.len();
+        entries[xref] = (1, start as u32, 0);
+        let mut table = Vec::new();
+        for (t, f2, f3) in entries.iter() {
+            table.push(*t);
+            table.extend_from_slice(&f2.to_be_bytes());
+            table.extend_from_slice(&f3.to_be_bytes());
+        }
+        let data = compress_to_vec_zlib(&table, 6);
+        out.extend(format!("{} 0 obj\n<< /Type /XRef /Size {} /W [1 4 2] /Root {} 0 R /Info {} 0 R \
+                            /Filter /FlateDecode /Length {} >>\nstream\n", xref, xref + 1, root, info, data.len()).bytes());
+        out.extend_from_slice(&data);
+        out.extend_from_slice(b"\nendstream\nendobj\n");
+        out.extend(format!("startxref\n{}\n%%EOF\n", start).bytes());
+        out
+    }
+}
+
+// a PDF string in hexadecimal form
+fn pdf_hex(b: &[u8]) -> String {
+    let mut s = String::from("<");
+    for c in b {
+        s.push_str(&format!("{:02x}", c));
+    }
+    s.push('>');
+    s
+}
+
+// a PDF name, with the characters that names can't hold escaped
+fn pdf_name(s: &str) -> String {
+    let mut n = String::from("/");
+    for c in s.bytes() {
+        if c > b' ' && c < 127 && !b"()<>[]{}/%#".contains(&c) {
+            n.push(c as char);
+        } else {
+            n.push_str(&format!("#{:02x}", c));
+        }
+    }
+    n
+}
+
+// the parts of a Type 1 font in PFB form: the cleartext, the encrypted portion
+// and the trailer of zeros
+fn pfb_segments(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let (mut clear, mut binary, mut trailer) = (Vec::new(), Vec::new(), Vec::new());
+    let mut pos = 0;
+    loop {
+        if *data.get(pos)? != 0x80 {
+            return None;
+        }
+        let t = *data.get(pos + 1)?;
+        if t == 3 {
+            break;
+        }
+        let len = u32::from_le_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?, *data.get(pos + 4)?, *data.get(pos + 5)?]);
+        let seg = data.get(pos + 6..pos + 6 + len as usize)?;
+        match t {
+            1 if binary.is_empty() => clear.extend_from_slice(seg),
+            1 => trailer.extend_from_slice(seg),
+            2 => binary.extend_from_slice(seg),
+            _ => return None,
+        }
+        pos += 6 + len as usize;
+        if pos >= data.len() {
+            break;
+        }
+    }
+    if clear.is_empty() || binary.is_empty() { None } else { Some((clear, binary, trailer)) }
+}
+
+// the text following a key such as /FontName in the cleartext of a Type 1 font
+fn type1_entry<'a>(clear: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let k = clear.windows(key.len()).position(|w| w == key)?;
+    let rest = &clear[k + key.len()..];
+    let end = rest.iter().position(|&c| c == b'\n' || c == b'\r').unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+// the font's name and bounding box, from the cleartext of a Type 1 font
+fn type1_header(clear: &[u8]) -> (Option<String>, [i32; 4]) {
+    let name = type1_entry(clear, b"/FontName").and_then(|s| {
+        let s = String::from_utf8_lossy(s);
+        s.split_whitespace().next().map(|n| n.trim_start_matches('/').to_string())
+    });
+    let mut bbox = [0, -250, 1000, 750];
+    if let Some(s) = type1_entry(clear, b"/FontBBox") {
+        let s = String::from_utf8_lossy(s).replace(['{', '}', '[', ']'], " ");
+        let v: Vec<i32> = s.split_whitespace().filter_map(|t| t.parse::<f64>().ok()).map(|x| x as i32).collect();
+        if v.len() >= 4 {
+            bbox.copy_from_slice(&v[..4]);
+        }
+    }
+    (name, bbox)
+}
+
+// a dimension in a special, like 10pt or 2.5cm, in sp
+fn special_dimen(s: &str) -> Option<Scaled> {
+    let s = s.trim();
+    let k = s.find(|c: char| c.is_ascii_alphabetic())?;
+    let x: f64 = s[..k].parse().ok()?;
+    let pt = match &s[k..] {
+        "pt" => 1.0,
+        "bp" => 72.27 / 72.0,
+        "in" => 72.27,
+        "cm" => 72.27 / 2.54,
+        "mm" => 72.27 / 25.4,
+        "pc" => 12.0,
+        "sp" => 1.0 / 65536.0,
+        _ => return None,
+    };
+    Some((x * pt * 65536.0).round() as Scaled)
+}
+
+// splits the dictionary <<...>> at the end of a special from the words before it
+fn split_special_dict(s: &str) -> (&str, Option<&str>) {
+    match s.find("<<") {
+        Some(k) => (&s[..k], s[k..].trim().strip_prefix("<<").and_then(|d| d.strip_suffix(">>"))),
+        None => (s, None),
+    }
+}
+
+impl TexState {
+    // opens the PDF file, if it isn't open already; like the DVI file, it is
+    // named after the job
+    fn ensure_pdf_open(&mut self) {
+        if self.output_file_name == 0 {
+            if self.job_name == 0 {
+                self.open_log_file();
+            }
+            self.pack_job_name(ExtPdf);
+            loop {
+                self.pdf.file = self.a_open_out();
+                if self.pdf.file.is_some() {
+                    break;
+                }
+                self.prompt_file_name("file name for output", ExtPdf);
+            }
+            self.output_file_name = self.make_name_string();
+        }
+    }
+
+    // 640
+    // ships box p out as a page of the PDF file
+    pub fn pdf_ship_out(&mut self, p: Pointer) {
+        self.ensure_pdf_open();
+        if self.total_pages == 0 {
+            self.prepare_mag(); // the magnification is frozen as the first page goes out
+        }
+        if self.pdf.pages_root == 0 {
+            self.pdf.pages_root = self.pdf.alloc();
+        }
+        let (pw, ph) = self.page_size(p);
+        let page_height = self.page_bp(ph);
+        let items = self.page_items(p);
+        let page = self.pdf.alloc();
+        let mut content = String::new();
+        let mut annots = Vec::new();
+        let mut used_fonts = BTreeMap::new();
+        let mut in_text = false;
+        let mut text_font = None;
+        if let Some(link) = self.pdf.link.as_mut() {
+            link.rect = None; // a link continued from the previous page
+        }
+        for item in items {
+            match item {
+                PageItem::Char { f, c, h, v } => {
+                    let font = match self.pdf_font(f) {
+                        Some(font) => font,
+                        None => continue,
+                    };
+                    let x = self.page_bp(OneInch + h);
+                    let y = page_height - self.page_bp(OneInch + v);
+                    if !in_text {
+                        content.push_str("BT\n");
+                        in_text = true;
+                        text_font = None;
+                    }
+                    if text_font != Some(f) {
+                        content.push_str(&format!("{} {} Tf\n", font.resource, format_bp(self.page_bp(self.font_size[f]))));
+                        text_font = Some(f);
+                    }
+                    let code = match &font.codes {
+                        PdfFontCodes::Bytes => pdf_hex(&[c]),
+                        PdfFontCodes::Glyphs(g) => pdf_hex(&g.get(&c).copied().unwrap_or(0).to_be_bytes()),
+                    };
+                    content.push_str(&format!("1 0 0 1 {} {} Tm {} Tj\n", format_bp(x), format_bp(y), code));
+                    used_fonts.insert(font.resource.clone(), font.object);
+                    let ci = self.char_info(f, c);
+                    let w = self.page_bp(self.char_width(f, ci));
+                    let ht = self.page_bp(self.char_height(f, ci.height_depth()));
+                    let dp = self.page_bp(self.char_depth(f, ci.height_depth()));
+                    self.pdf_cover(x, y - dp, x + w, y + ht);
+                }
+                PageItem::Rule { h, v, width, height } => {
+                    if in_text {
+                        content.push_str("ET\n");
+                        in_text = false;
+                    }
+                    let x = self.page_bp(OneInch + h);
+                    let y = page_height - self.page_bp(OneInch + v);
+                    let w = self.page_bp(width);
+                    let ht = self.page_bp(height);
+                    content.push_str(&format!("{} {} {} {} re f\n", format_bp(x), format_bp(y), format_bp(w), format_bp(ht)));
+                    self.pdf_cover(x, y, x + w, y + ht);
+                }
+                PageItem::Special { h, v, text } => {
+                    let x = self.page_bp(OneInch + h);
+                    let y = page_height - self.page_bp(OneInch + v);
+                    self.pdf_special(&text, x, y, page, &mut annots);
+                }
+            }
+        }
+        if in_text {
+            content.push_str("ET\n");
+        }
+        // a link that goes on past the end of the page is broken here
+        if let Some(link) = self.pdf.link.as_ref() {
+            if let Some(r) = link.rect {
+                let dict = link.dict.clone();
+                annots.push(self.pdf_annotation(&dict, r));
+            }
+        }
+        let contents = self.pdf.add_stream(String::new(), content.as_bytes());
+        let fonts: Vec<String> = used_fonts.iter().map(|(r, n)| format!("{} {} 0 R", r, n)).collect();
+        let annots = if annots.is_empty() {
+            String::new()
+        } else {
+            format!(" /Annots [{}]", annots.iter().map(|n| format!("{} 0 R", n)).collect::<Vec<_>>().join(" "))
+        };
+        let dict = format!("<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << {} >> >> \
+                            /Contents {} 0 R{} >>", self.pdf.pages_root, format_bp(self.page_bp(pw)), format_bp(page_height),
+                           fonts.join(" "), contents, annots);
+        self.pdf.set(page, PdfObject::Dict(dict.into_bytes()));
+        self.pdf.pages.push(page);
+        self.total_pages += 1;
+    }
+
+    // widens the area covered by an open link to include the given rectangle
+    fn pdf_cover(&mut self, llx: f64, lly: f64, urx: f64, ury: f64) {
+        if let Some(link) = self.pdf.link.as_mut() {
+            link.rect = Some(match link.rect {
+                Some(r) => [r[0].min(llx), r[1].min(lly), r[2].max(urx), r[3].max(ury)],
+                None => [llx, lly, urx, ury],
+            });
+        }
+    }
+
+    // an annotation object with the given dictionary entries and rectangle
+    fn pdf_annotation(&mut self, dict: &[u8], r: [f64; 4]) -> usize {
+        let mut d = format!("<< /Type /Annot /Rect [{} {} {} {}] ", format_bp(r[0]), format_bp(r[1]), format_bp(r[2]),
+                            format_bp(r[3])).into_bytes();
+        d.extend_from_slice(dict);
+        d.extend_from_slice(b" >>");
+        self.pdf.add(PdfObject::Dict(d))
+    }
+
+    // carries out a special at (x,y) on the page whose object number is page
+    fn pdf_special(&mut self, text: &[u8], x: f64, y: f64, page: usize, annots: &mut Vec<usize>) {
+        let text = String::from_utf8_lossy(text).to_string();
+        let s = match text.trim_start().strip_prefix("pdf:") {
+            Some(s) => s.trim(),
+            None => return, // specials for other drivers are ignored
+        };
+        let (cmd, rest) = match s.find(|c: char| c.is_whitespace() || c == '<' || c == '(') {
+            Some(k) => (&s[..k], s[k..].trim()),
+            None => (s, ""),
+        };
+        match cmd {
+            "ann" => {
+                let (words, dict) = split_special_dict(rest);
+                let words: Vec<&str> = words.split_whitespace().collect();
+                let (mut wd, mut ht, mut dp) = (0, 0, 0);
+                for pair in words.chunks(2) {
+                    let d = pair.get(1).and_then(|d| special_dimen(d));
+                    match (pair[0], d) {
+                        ("width", Some(d)) => wd = d,
+                        ("height", Some(d)) => ht = d,
+                        ("depth", Some(d)) => dp = d,
+                        _ => return self.pdf_special_warning(&text),
+                    }
+                }
+                match dict {
+                    Some(dict) => {
+                        let r = [x, y - self.page_bp(dp), x + self.page_bp(wd), y + self.page_bp(ht)];
+                        annots.push(self.pdf_annotation(dict.as_bytes(), r));
+                    }
+                    None => self.pdf_special_warning(&text),
+                }
+            }
+            "bann" => match split_special_dict(rest).1 {
+                Some(dict) if self.pdf.link.is_none() => {
+                    self.pdf.link = Some(PdfLink { dict: dict.as_bytes().to_vec(), rect: None });
+                }
+                _ => self.pdf_special_warning(&text),
+            },
+            "eann" => match self.pdf.link.take() {
+                Some(PdfLink { dict, rect: Some(r) }) => annots.push(self.pdf_annotation(&dict, r)),
+                Some(_) => {} // the link covered nothing on this page
+                None => self.pdf_special_warning(&text),
+            },
+            "dest" => {
+                if rest.starts_with('(') && rest.contains(')') {
+                    let name = rest.as_bytes()[1..rest.find(')').unwrap()].to_vec();
+                    let dest = format!("[{} 0 R /XYZ {} {} null]", page, format_bp(x), format_bp(y));
+                    self.pdf.dests.push((name, dest));
+                } else {
+                    self.pdf_special_warning(&text);
+                }
+            }
+            _ => self.pdf_special_warning(&text),
+        }
+    }
+
+    fn pdf_special_warning(&mut self, text: &str) {
+        self.print_nl("PDF special ignored: ");
+        self.print(text);
+    }
+
+    // the PDF font for font f, embedding it the first time; None if f can't be shown
+    fn pdf_font(&mut self, f: InternalFontNumber) -> Option<PdfFont> {
+        if let Some(font) = self.pdf.fonts.get(&f) {
+            return Some(font.clone());
+        }
+        if f == NullFont {
+            return None;
+        }
+        let name = String::from_utf8_lossy(&self.str_bytes(self.font_name[f])).to_string();
+        let object = self.pdf.alloc();
+        let codes = if is_opentype_name(name.as_bytes()) {
+            self.pdf_opentype_font(f, &name, object)
+        } else {
+            self.pdf_type1_font(f, &name, object)
+        };
+        let font = PdfFont { resource: format!("/F{}", self.pdf.fonts.len() + 1), object, codes };
+        self.pdf.fonts.insert(f, font.clone());
+        Some(font)
+    }
+
+    // the width of character c of font f, in thousandths of the font size
+    fn pdf_char_width(&self, f: InternalFontNumber, c: u8) -> String {
+        let w = self.char_width(f, self.char_info(f, c)) as f64 / self.font_size[f] as f64 * 1000.0;
+        format_bp(w)
+    }
+
+    // a font from a TFM file, embedding the Type 1 font of the same name if there is one
+    fn pdf_type1_font(&mut self, f: InternalFontNumber, name: &str, object: usize) -> PdfFontCodes {
+        let (bc, ec) = (self.font_bc[f], self.font_ec[f]);
+        let widths: Vec<String> = (bc..=ec).map(|c| {
+            if self.char_info(f, c).char_exists() { self.pdf_char_width(f, c) } else { "0".to_string() }
+        }).collect();
+        let pfb = self.read_font_file(f, ExtPfb, FileKind::Type1).and_then(|d| pfb_segments(&d));
+        let (base_font, descriptor) = match pfb {
+            Some((clear, binary, trailer)) => {
+                let (font_name, bbox) = type1_header(&clear);
+                let base_font = font_name.unwrap_or_else(|| name.to_string());
+                let mut data = clear.clone();
+                data.extend_from_slice(&binary);
+                data.extend_from_slice(&trailer);
+                let file = self.pdf.add_stream(format!("/Length1 {} /Length2 {} /Length3 {}", clear.len(), binary.len(),
+                                                       trailer.len()), &data);
+                let d = self.pdf.add_dict(format!(
+                    "<< /Type /FontDescriptor /FontName {} /Flags 4 /FontBBox [{} {} {} {}] /ItalicAngle 0 \
+                     /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile {} 0 R >>",
+                    pdf_name(&base_font), bbox[0], bbox[1], bbox[2], bbox[3], bbox[3], bbox[1], bbox[3], file));
+                (base_font, format!(" /FontDescriptor {} 0 R", d))
+            }
+            None => {
+                self.print_nl("Warning: no Type 1 font for ");
+                self.print(name);
+                self.print(", so it won't be embedded");
+                (name.to_string(), String::new())
+            }
+        };
+        let dict = format!("<< /Type /Font /Subtype /Type1 /BaseFont {} /FirstChar {} /LastChar {} /Widths [{}]{} >>",
+                           pdf_name(&base_font), bc, ec, widths.join(" "), descriptor);
+        self.pdf.set(object, PdfObject::Dict(dict.into_bytes()));
+        PdfFontCodes::Bytes
+    }
+
+    // an OpenType or TrueType font, embedded whole and shown by glyph number
+    fn pdf_opentype_font(&mut self, f: InternalFontNumber, name: &str, object: usize) -> PdfFontCodes {
+        let data = self.read_font_file(f, EmptyString, FileKind::OpenType);
+        let e = match data.as_ref().and_then(|d| opentype_embedding(d)) {
+            Some(e) => e,
+            None => {
+                self.print_nl("Warning: the font file ");
+                self.print(name);
+                self.print(" can no longer be read, so its characters are left out");
+                self.pdf.set(object, PdfObject::Dict(b"null".to_vec()));
+                return PdfFontCodes::Glyphs(BTreeMap::new());
+            }
+        };
+        let data = data.unwrap();
+        let base_font = if e.postscript_name.is_empty() {
+            name.rsplitn(2, '.').last().unwrap_or(name).to_string()
+        } else {
+            e.postscript_name.clone()
+        };
+        let (file_key, file) = if e.cff {
+            self.pdf.needs_pdf_16 = true;
+            ("/FontFile3", self.pdf.add_stream("/Subtype /OpenType".to_string(), &data))
+        } else {
+            ("/FontFile2", self.pdf.add_stream(format!("/Length1 {}", data.len()), &data))
+        };
+        let flags = if e.italic_angle != 0.0 { 4 + 64 } else { 4 };
+        let descriptor = self.pdf.add_dict(format!(
+            "<< /Type /FontDescriptor /FontName {} /Flags {} /FontBBox [{} {} {} {}] /ItalicAngle {} /Ascent {} \
+             /Descent {} /CapHeight {} /StemV 80 {} {} 0 R >>",
+            pdf_name(&base_font), flags, e.bbox[0], e.bbox[1], e.bbox[2], e.bbox[3], format_bp(e.italic_angle),
+            e.ascent, e.descent, e.cap_height, file_key, file));
+        let widths: Vec<String> = e.glyphs.iter().filter(|(&c, _)| c >= self.font_bc[f] && c <= self.font_ec[f])
+            .map(|(&c, &g)| format!("{} [{}]", g, self.pdf_char_width(f, c))).collect();
+        let (subtype, gid_map) = if e.cff { ("CIDFontType0", "") } else { ("CIDFontType2", " /CIDToGIDMap /Identity") };
+        let cid_font = self.pdf.add_dict(format!(
+            "<< /Type /Font /Subtype /{} /BaseFont {} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) \
+             /Supplement 0 >> /FontDescriptor {} 0 R /W [{}]{} >>",
+            subtype, pdf_name(&base_font), descriptor, widths.join(" "), gid_map));
+        let dict = format!("<< /Type /Font /Subtype /Type0 /BaseFont {} /Encoding /Identity-H /DescendantFonts [{} 0 R] >>",
+                           pdf_name(&base_font), cid_font);
+        self.pdf.set(object, PdfObject::Dict(dict.into_bytes()));
+        PdfFontCodes::Glyphs(e.glyphs)
+    }
+
+    // 642
+    // finish the PDF file
+    pub fn finish_pdf_file(&mut self) {
+        if self.total_pages == 0 {
+            self.print_nl("No pages of output.");
+            return;
+        }
+        let kids: Vec<String> = self.pdf.pages.iter().map(|n| format!("{} 0 R", n)).collect();
+        let pages = format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), self.pdf.pages.len());
+        let pages_root = self.pdf.pages_root;
+        self.pdf.set(pages_root, PdfObject::Dict(pages.into_bytes()));
+        let mut catalog = format!("<< /Type /Catalog /Pages {} 0 R", pages_root);
+        if !self.pdf.dests.is_empty() {
+            let mut dests = String::from("<<");
+            for (name, dest) in self.pdf.dests.iter() {
+                dests.push_str(&format!(" {} {}", pdf_name(&String::from_utf8_lossy(name)), dest));
+            }
+            dests.push_str(" >>");
+            let d = self.pdf.add_dict(dests);
+            catalog.push_str(&format!(" /Dests {} 0 R", d));
+        }
+        catalog.push_str(" >>");
+        let root = self.pdf.add_dict(catalog);
+        let info = self.pdf.add_dict(format!("<< /Producer ({}) >>", banner));
+        let bytes = self.pdf.serialize(root, info);
+        let written = match self.pdf.file.take() {
+            Some(mut f) => f.write_all(&bytes).and_then(|_| f.flush()).is_ok(),
+            None => false,
+        };
+        if !written {
+            self.fatal_error("*** (cannot write on the PDF file)");
+        }
+        self.print_nl("Output written on ");
+        self.slow_print(self.output_file_name);
+        self.print(" (");
+        self.print_int(self.total_pages);
+        self.print(" page");
+        if self.total_pages != 1 {
+            self.print_char('s' as u8);
+        }
+        self.print(", ");
+        self.print_int(bytes.len() as i32);
+        self.print(" bytes).");
+    }
+}
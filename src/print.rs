@@ -0,0 +1,467 @@
+use crate::*;
+use std::io::{BufRead, Write};
+
+// 54
+pub const NoPrint: i32 = 16; // selector setting that makes data disappear
+pub const TermOnly: i32 = 17; // printing is destined for the terminal only
+pub const LogOnly: i32 = 18; // printing is destined for the transcript file only
+pub const TermAndLog: i32 = 19; // normal selector setting
+pub const Pseudo: i32 = 20; // special selector setting for show_context
+pub const NewString: i32 = 21; // printing is deflected to the string pool
+
+// 31
+// reads the next line of f without its end-of-line bytes, or None at end of file
+pub fn read_line_bytes<R: BufRead + ?Sized>(f: &mut R) -> Option<Vec<u8>> {
+    let mut line = Vec::new();
+    match f.read_until(b'\n', &mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            Some(line)
+        }
+    }
+}
+
+impl TexState {
+    // 34
+    // makes the terminal read from input and write to output; TeX starts out with
+    // a terminal that has nothing to say and discards what it is told
+    pub fn set_terminal(&mut self, input: Box<dyn BufRead>, output: Box<dyn Write>) {
+        self.term_in = input;
+        self.term_out = output;
+    }
+
+    // makes sure that the terminal output has been flushed
+    pub fn update_terminal(&mut self) {
+        let _ = self.term_out.flush();
+    }
+
+    // 56
+    pub fn wterm(&mut self, text: &str) {
+        let _ = self.term_out.write_all(text.as_bytes());
+    }
+
+    pub fn wterm_ln(&mut self, text: &str) {
+        let _ = self.term_out.write_all(text.as_bytes());
+        let _ = self.term_out.write_all(b"\n");
+    }
+
+    pub fn wterm_char(&mut self, c: u8) {
+        let _ = self.term_out.write_all(&[c]);
+    }
+
+    pub fn wterm_cr(&mut self) {
+        let _ = self.term_out.write_all(b"\n");
+    }
+
+    // 31
+    // inputs the next line of f into buffer[first..last), removing trailing spaces
+    pub fn input_ln<R: BufRead + ?Sized>(&mut self, f: &mut R) -> bool {
+        self.last = self.first;
+        match read_line_bytes(f) {
+            None => false,
+            Some(line) => {
+                self.input_ln_bytes(&line);
+                true
+            }
+        }
+    }
+
+    // input_ln for the terminal
+    fn input_term_ln(&mut self) -> bool {
+        let mut term_in = std::mem::replace(&mut self.term_in, Box::new(std::io::empty()));
+        let ok = self.input_ln(&mut term_in);
+        self.term_in = term_in;
+        ok
+    }
+
+    // the part of input_ln that moves an already read line into the buffer
+    pub fn input_ln_bytes(&mut self, line: &[u8]) {
+        self.last = self.first;
+        let mut last_nonblank = self.first;
+        for &c in line {
+            if self.last >= self.max_buf_stack {
+                self.max_buf_stack = self.last + 1;
+                if self.max_buf_stack == BufSize as usize {
+                    // 35: report overflow of the input buffer, and abort
+                    self.cur_input.loc_field = self.first as HalfWord;
+                    self.cur_input.limit_field = self.last as HalfWord - 1;
+                    self.overflow("buffer size", BufSize);
+                }
+            }
+            self.buffer[self.last] = self.xord[c as usize];
+            self.last += 1;
+            if self.buffer[self.last - 1] != ' ' as u8 {
+                last_nonblank = self.last;
+            }
+        }
+        self.last = last_nonblank;
+    }
+
+    // 37
+    // gets the first line of input into the buffer, from the command line when it
+    // supplied one and otherwise from the terminal; loc is left at its first
+    // nonblank character, and false means the terminal has nothing to say
+    pub fn init_terminal(&mut self) -> bool {
+        loop {
+            match self.first_line.take() {
+                Some(line) => self.input_ln_bytes(&line),
+                None => {
+                    self.wterm("**");
+                    self.update_terminal();
+                    if !self.input_term_ln() {
+                        // this shouldn't happen
+                        self.wterm_cr();
+                        self.wterm_ln("! End of file on the terminal... why?");
+                        return false;
+                    }
+                }
+            }
+            let mut loc = self.first;
+            while loc < self.last && self.buffer[loc] == ' ' as u8 {
+                loc += 1;
+            }
+            self.cur_input.loc_field = loc as HalfWord;
+            if loc < self.last {
+                return true;
+            }
+            self.wterm_ln("Please type the name of your input file.");
+        }
+    }
+
+    // 57
+    // prints an end-of-line
+    pub fn print_ln(&mut self) {
+        match self.selector {
+            TermAndLog => {
+                self.wterm_cr();
+                self.wlog_cr();
+                self.term_offset = 0;
+                self.file_offset = 0;
+            }
+            LogOnly => {
+                self.wlog_cr();
+                self.file_offset = 0;
+            }
+            TermOnly => {
+                self.wterm_cr();
+                self.term_offset = 0;
+            }
+            NoPrint | Pseudo | NewString => {}
+            _ => self.write_ln_file(self.selector as usize),
+        }
+    }
+
+    pub fn wlog(&mut self, c: u8) {
+        if let Some(f) = self.log_file.as_mut() {
+            let _ = f.write_all(&[c]);
+        }
+    }
+
+    pub fn wlog_cr(&mut self) {
+        if let Some(f) = self.log_file.as_mut() {
+            let _ = f.write_all(b"\n");
+        }
+    }
+
+    fn write_file_char(&mut self, j: usize, c: u8) {
+        if let Some(f) = self.write_file[j].as_mut() {
+            let _ = f.write_all(&[c]);
+        }
+    }
+
+    fn write_ln_file(&mut self, j: usize) {
+        if let Some(f) = self.write_file[j].as_mut() {
+            let _ = f.write_all(b"\n");
+        }
+    }
+
+    // 58
+    // prints a single character
+    pub fn print_char(&mut self, s: ASCIICode) {
+        if s as i32 == self.new_line_char() && self.selector < Pseudo {
+            self.print_ln();
+            return;
+        }
+        let c = self.xchr[s as usize];
+        match self.selector {
+            TermAndLog => {
+                self.wterm_char(c);
+                self.wlog(c);
+                self.term_offset += 1;
+                self.file_offset += 1;
+                if self.term_offset == MaxPrintLine {
+                    self.wterm_cr();
+                    self.term_offset = 0;
+                }
+                if self.file_offset == MaxPrintLine {
+                    self.wlog_cr();
+                    self.file_offset = 0;
+                }
+            }
+            LogOnly => {
+                self.wlog(c);
+                self.file_offset += 1;
+                if self.file_offset == MaxPrintLine {
+                    self.print_ln();
+                }
+            }
+            TermOnly => {
+                self.wterm_char(c);
+                self.term_offset += 1;
+                if self.term_offset == MaxPrintLine {
+                    self.print_ln();
+                }
+            }
+            NoPrint => {}
+            Pseudo => {
+                if self.tally < self.trick_count {
+                    self.trick_buf[(self.tally % ErrorLine) as usize] = s;
+                }
+            }
+            NewString => {
+                // we drop characters if the string space is full
+                if self.pool_ptr < PoolSize as usize {
+                    self.append_char(s);
+                }
+            }
+            _ => self.write_file_char(self.selector as usize, c),
+        }
+        self.tally += 1;
+    }
+
+    // 59
+    // prints string s from the pool
+    pub fn print_str(&mut self, s: StrNumber) {
+        if s >= self.str_ptr || s < 0 {
+            self.print("???");
+            return;
+        }
+        if s < 256 {
+            if self.selector > Pseudo {
+                self.print_char(s as u8);
+                return;
+            }
+            if s == self.new_line_char() && self.selector < Pseudo {
+                self.print_ln();
+                return;
+            }
+            // temporarily disable new-line character
+            let nl = self.new_line_char();
+            self.set_int_par(NewLineCharCode, -1);
+            for j in self.str_start[s as usize]..self.str_start[s as usize + 1] {
+                self.print_char(self.str_pool[j]);
+            }
+            self.set_int_par(NewLineCharCode, nl);
+            return;
+        }
+        let mut j = self.str_start[s as usize];
+        while j < self.str_start[s as usize + 1] {
+            self.print_char(self.str_pool[j]);
+            j += 1;
+        }
+    }
+
+    // prints a string that TeX itself supplies
+    pub fn print(&mut self, s: &str) {
+        for c in s.bytes() {
+            self.print_char(c);
+        }
+    }
+
+    // 68
+    // prints a character in its printable form
+    pub fn print_ASCII(&mut self, c: i32) {
+        self.print_str(c);
+    }
+
+    // 60
+    // prints string s, converting unprintable characters
+    pub fn slow_print(&mut self, s: StrNumber) {
+        if s >= self.str_ptr || s < 256 {
+            self.print_str(s);
+        } else {
+            for j in self.str_start[s as usize]..self.str_start[s as usize + 1] {
+                self.print_str(self.str_pool[j] as StrNumber);
+            }
+        }
+    }
+
+    // 62
+    // prints string s at beginning of line
+    pub fn print_nl(&mut self, s: &str) {
+        if (self.term_offset > 0 && self.selector % 2 == 1)
+            || (self.file_offset > 0 && self.selector >= LogOnly)
+        {
+            self.print_ln();
+        }
+        self.print(s);
+    }
+
+    // 63
+    // prints escape character, then s
+    pub fn print_esc(&mut self, s: &str) {
+        let c = self.escape_char();
+        if (0..256).contains(&c) {
+            self.print_str(c);
+        }
+        self.print(s);
+    }
+
+    pub fn print_esc_str(&mut self, s: StrNumber) {
+        let c = self.escape_char();
+        if (0..256).contains(&c) {
+            self.print_str(c);
+        }
+        self.slow_print(s);
+    }
+
+    // 64
+    // prints dig[k-1]...dig[0]
+    pub fn print_the_digs(&mut self, k: usize) {
+        let mut k = k;
+        while k > 0 {
+            k -= 1;
+            if self.dig[k] < 10 {
+                self.print_char('0' as u8 + self.dig[k]);
+            } else {
+                self.print_char('A' as u8 - 10 + self.dig[k]);
+            }
+        }
+    }
+
+    // 65
+    // prints an integer in decimal form
+    pub fn print_int(&mut self, n: i32) {
+        let mut k = 0;
+        let mut n = n as i64;
+        if n < 0 {
+            self.print_char('-' as u8);
+            n = -n;
+        }
+        loop {
+            self.dig[k] = (n % 10) as u8;
+            n /= 10;
+            k += 1;
+            if n == 0 {
+                break;
+            }
+        }
+        self.print_the_digs(k);
+    }
+
+    // 66
+    // prints two least significant digits
+    pub fn print_two(&mut self, n: i32) {
+        let n = n.abs() % 100;
+        self.print_char('0' as u8 + (n / 10) as u8);
+        self.print_char('0' as u8 + (n % 10) as u8);
+    }
+
+    // 67
+    // prints a positive integer in hexadecimal form
+    pub fn print_hex(&mut self, n: i32) {
+        let mut k = 0;
+        let mut n = n;
+        self.print_char('"' as u8);
+        loop {
+            self.dig[k] = (n % 16) as u8;
+            n /= 16;
+            k += 1;
+            if n == 0 {
+                break;
+            }
+        }
+        self.print_the_digs(k);
+    }
+
+    // 69
+    pub fn print_roman_int(&mut self, n: i32) {
+        let s = b"m2d5c2l5x2v5i";
+        let mut n = n;
+        let mut j = 0;
+        let mut v = 1000;
+        loop {
+            while n >= v {
+                self.print_char(s[j]);
+                n -= v;
+            }
+            if n <= 0 {
+                // nonpositive input produces no output
+                return;
+            }
+            let mut k = j + 2;
+            let mut u = v / (s[k - 1] - '0' as u8) as i32;
+            if s[k - 1] == '2' as u8 {
+                k += 2;
+                u /= (s[k - 1] - '0' as u8) as i32;
+            }
+            if n + u >= v {
+                self.print_char(s[k]);
+                n += u;
+            } else {
+                j += 2;
+                v /= (s[j - 1] - '0' as u8) as i32;
+            }
+        }
+    }
+
+    // 70
+    // prints a yet-unmade string
+    pub fn print_current_string(&mut self) {
+        let mut j = self.str_start[self.str_ptr as usize];
+        while j < self.pool_ptr {
+            self.print_char(self.str_pool[j]);
+            j += 1;
+        }
+    }
+
+    // 71
+    // gets a line from the terminal
+    pub fn term_input(&mut self) {
+        self.update_terminal();
+        if !self.input_term_ln() {
+            self.fatal_error("*** (job aborted, no legal \\end found)");
+        }
+        self.term_offset = 0; // the user's line ended with <return>
+        self.selector -= 1; // prepare to echo the input
+        if self.last != self.first {
+            for k in self.first..self.last {
+                self.print_str(self.buffer[k] as StrNumber);
+            }
+        }
+        self.print_ln();
+        self.selector += 1; // restore previous status
+    }
+
+    pub fn prompt_input(&mut self, s: &str) {
+        self.print(s);
+        self.term_input();
+    }
+
+    // 245
+    // prepare to do some tracing
+    pub fn begin_diagnostic(&mut self) {
+        self.old_setting = self.selector;
+        if self.tracing_online() <= 0 && self.selector == TermAndLog {
+            self.selector -= 1;
+            if self.history == History::Spotless {
+                self.history = History::WarningIssued;
+            }
+        }
+    }
+
+    // restore proper conditions after tracing
+    pub fn end_diagnostic(&mut self, blank_line: bool) {
+        self.print_nl("");
+        if blank_line {
+            self.print_ln();
+        }
+        self.selector = self.old_setting;
+    }
+}
@@ -0,0 +1,221 @@
+use crate::*;
+
+impl TexState {
+    // 482
+    // reads a token list for \read n from the file or terminal, making a macro
+    // body for the control sequence r
+    pub fn read_toks(&mut self, n: i32, r: Pointer) {
+        let mut n = n;
+        self.scanner_status = Defining;
+        self.warning_index = r;
+        self.def_ref = self.get_avail();
+        self.set_token_ref_count(self.def_ref as usize, Null);
+        let mut p = self.def_ref; // tail of the token list
+        p = self.store_new_token(p, end_match_token as HalfWord);
+        let m = if !(0..=15).contains(&n) { 16 } else { n as usize }; // stream number
+        let s = self.align_state; // saved value of align_state
+        self.align_state = 1000000; // disable tab marks, etc.
+        loop {
+            // 483: input and store tokens from the next line of the file
+            self.begin_file_reading();
+            self.cur_input.name_field = m as HalfWord + 1;
+            if self.read_open[m] == closed {
+                // 484: input for \read from the terminal
+                if self.interaction > Interaction::NonstopMode {
+                    if n < 0 {
+                        self.prompt_input("");
+                    } else {
+                        self.print_ln();
+                        self.sprint_cs(r);
+                        self.prompt_input("=");
+                        n = -1;
+                    }
+                } else {
+                    self.fatal_error("*** (cannot \\read from terminal in nonstop modes)");
+                }
+            } else if self.read_open[m] == just_open {
+                // 485: input the first line of read_file[m]
+                if self.read_ln(m) {
+                    self.read_open[m] = Normal;
+                } else {
+                    self.read_file[m] = None;
+                    self.read_open[m] = closed;
+                }
+            } else {
+                // 486: input the next line of read_file[m]
+                if !self.read_ln(m) {
+                    self.read_file[m] = None;
+                    self.read_open[m] = closed;
+                    if self.align_state != 1000000 {
+                        self.runaway();
+                        self.print_err("File ended within ");
+                        self.print_esc("read");
+                        self.help(&["This \\read has unbalanced braces."]);
+                        self.align_state = 1000000;
+                        self.cur_input.limit_field = 0;
+                        self.error();
+                    }
+                }
+            }
+            self.cur_input.limit_field = self.last as HalfWord;
+            if self.end_line_char_inactive() {
+                self.cur_input.limit_field -= 1;
+            } else {
+                self.buffer[self.cur_input.limit_field as usize] = self.end_line_char() as ASCIICode;
+            }
+            self.first = self.cur_input.limit_field as usize + 1;
+            self.cur_input.loc_field = self.cur_input.start_field;
+            self.cur_input.state_field = NewLine;
+            loop {
+                self.get_token();
+                if self.cur_tok == 0 {
+                    break; // cur_cmd=cur_chr=0 will occur at the end of the line
+                }
+                if self.align_state < 1000000 {
+                    // unmatched `}' aborts the line
+                    loop {
+                        self.get_token();
+                        if self.cur_tok == 0 {
+                            break;
+                        }
+                    }
+                    self.align_state = 1000000;
+                    break;
+                }
+                p = self.store_new_token(p, self.cur_tok);
+            }
+            self.end_file_reading();
+            if self.align_state == 1000000 {
+                break;
+            }
+        }
+        self.cur_val = self.def_ref as i32;
+        self.scanner_status = Normal;
+        self.align_state = s;
+    }
+
+    // inputs the next line of read_file[m] into the buffer
+    fn read_ln(&mut self, m: usize) -> bool {
+        match self.read_file[m].take() {
+            Some(mut f) => {
+                let b = self.input_ln(&mut f);
+                self.read_file[m] = Some(f);
+                b
+            }
+            None => false,
+        }
+    }
+
+    // 1215
+    // gets a control sequence that can be redefined
+    pub fn get_r_token(&mut self) {
+        loop {
+            loop {
+                self.get_token();
+                if self.cur_tok as i32 != space_token {
+                    break;
+                }
+            }
+            if self.cur_cs != 0 && self.cur_cs <= FrozenControlSequence {
+                break;
+            }
+            self.print_err("Missing control sequence inserted");
+            self.help(&["Please don't say `\\def cs{...}', say `\\def\\cs{...}'.",
+                "I've inserted an inaccessible control sequence so that your",
+                "definition will be completed without mixing me up too badly.",
+                "You can recover graciously from this error, if you're",
+                "careful; see exercise 27.2 in The TeXbook."]);
+            if self.cur_cs == 0 {
+                self.back_input();
+            }
+            self.cur_tok = cs_token_flag as HalfWord + FrozenProtection;
+            self.ins_error();
+        }
+    }
+
+    // 1225
+    // \read n to \cs, defining the control sequence globally if global is set
+    pub fn read_to_cs(&mut self, global: bool) {
+        self.scan_int();
+        let n = self.cur_val;
+        if !self.scan_keyword("to") {
+            self.print_err("Missing `to' inserted");
+            self.help(&["You should have said `\\read<number> to \\cs'.",
+                "I'm going to look for the \\cs now."]);
+            self.error();
+        }
+        self.get_r_token();
+        let p = self.cur_cs;
+        self.read_toks(n, p);
+        if global {
+            self.geq_define(p, Call, self.def_ref);
+        } else {
+            self.eq_define(p, Call, self.def_ref);
+        }
+    }
+
+    // 1275
+    // \openin and \closein
+    pub fn open_or_close_in(&mut self) {
+        let c = self.cur_chr; // 1 for \openin, 0 for \closein
+        self.scan_four_bit_int();
+        let n = self.cur_val as usize; // stream number
+        if self.read_open[n] != closed {
+            self.read_file[n] = None;
+            self.read_open[n] = closed;
+        }
+        if c != 0 {
+            self.scan_optional_equals();
+            self.scan_file_name();
+            if self.cur_ext == EmptyString {
+                self.cur_ext = ExtTex;
+            }
+            self.pack_cur_name();
+            if let Some(f) = self.a_open_in() {
+                self.read_file[n] = Some(f);
+                self.read_open[n] = just_open;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    fn log_of(doc: &str) -> String {
+        let vfs = Vfs::new();
+        vfs.add_file("data.tex", "first line\nsecond {line}\n");
+        vfs.add_file("doc.tex", format!("\\catcode`\\{{=1 \\catcode`\\}}=2\n{}\n\\end\n", doc));
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        String::from_utf8_lossy(&outputs["doc.log"]).into_owned()
+    }
+
+    #[test]
+    fn lines_are_read_until_the_end_of_the_file() {
+        let log = log_of("\\openin3=data \\ifeof3 \\message{[closed]}\\else \\message{[open]}\\fi
+\\read3 to\\a \\show\\a \\read3 to\\b \\show\\b
+\\ifeof3 \\message{[eof]}\\else \\message{[more]}\\fi
+\\read3 to\\c \\show\\c \\ifeof3 \\message{[eof]}\\else \\message{[more]}\\fi");
+        assert!(log.contains("[open]"), "{}", log);
+        assert!(log.contains("> \\a=macro:\n->first line .\n"), "{}", log);
+        assert!(log.contains("> \\b=macro:\n->second {line} .\n"), "{}", log);
+        assert!(log.contains("[more]"), "{}", log);
+        assert!(log.contains("> \\c=macro:\n->\\par .\n"), "{}", log);
+        assert!(log.contains("[eof]"), "{}", log);
+    }
+
+    #[test]
+    fn closed_and_missing_files_are_at_their_end() {
+        let log = log_of("\\openin3=data \\closein3 \\ifeof3 \\message{[closed]}\\fi
+\\openin4=missing \\ifeof4 \\message{[missing]}\\fi
+\\ifeof5 \\message{[unused]}\\fi
+\\read5 to\\x");
+        for m in &["[closed]", "[missing]", "[unused]"] {
+            assert!(log.contains(m), "{} {}", m, log);
+        }
+        // a \read from the terminal can't be answered in \nonstopmode
+        assert!(log.contains("! Emergency stop."), "{}", log);
+        assert!(log.contains("*** (cannot \\read from terminal in nonstop modes)"), "{}", log);
+    }
+}
@@ -0,0 +1,213 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// 514
+// the kinds of files TeX looks for, each with its own search path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    Tex, // \input and \openin files, searched along TEXINPUTS
+    Tfm, // font metric files, searched along TFMFONTS
+    Fmt, // format files, searched along TEXFORMATS
+    OpenType, // OpenType and TrueType fonts, searched along OPENTYPEFONTS
+    VirtualFont, // virtual fonts, searched along VFFONTS
+    Type1, // Type 1 fonts to embed in PDF files, searched along T1FONTS
+}
+
+impl FileKind {
+    // the environment variable holding the search path for this kind of file
+    pub fn path_var(&self) -> &'static str {
+        match self {
+            FileKind::Tex => "TEXINPUTS",
+            FileKind::Tfm => "TFMFONTS",
+            FileKind::Fmt => "TEXFORMATS",
+            FileKind::OpenType => "OPENTYPEFONTS",
+            FileKind::VirtualFont => "VFFONTS",
+            FileKind::Type1 => "T1FONTS",
+        }
+    }
+
+    // the search path used when the environment variable isn't set; fonts are
+    // also looked for among the ones installed on the system
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            FileKind::OpenType if cfg!(windows) => ".;C:\\Windows\\Fonts",
+            FileKind::OpenType => ".:/usr/share/fonts//:/usr/local/share/fonts//:/Library/Fonts//:/System/Library/Fonts//",
+            FileKind::Type1 => ".:/usr/share/texmf/fonts/type1//:/usr/share/texlive/texmf-dist/fonts/type1//:/usr/share/fonts/type1//",
+            _ => ".",
+        }
+    }
+}
+
+// how TeX opens the files it reads and writes; the system-dependent part of
+// a_open_in, b_open_in, w_open_in and their output counterparts
+pub trait FileResolver {
+    // finds an input file, returning the name it was found under and its contents
+    fn open_in(&mut self, name: &str, kind: FileKind) -> Option<(String, Box<dyn Read>)>;
+
+    // creates an output file in the current directory
+    fn open_out(&mut self, name: &str) -> Option<Box<dyn Write>>;
+}
+
+// one directory of a search path; a trailing `//' asks for all its subdirectories too
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SearchDir {
+    dir: PathBuf,
+    recursive: bool,
+}
+
+// searches the disk along kpathsea-style path lists, such as TEXINPUTS=.:./chapters//:
+// where an empty element stands for the default path of the kind of file, so that
+// OPENTYPEFONTS=./fonts: also looks among the fonts installed on the system
+pub struct PathResolver {
+    paths: Vec<(FileKind, Vec<SearchDir>)>,
+}
+
+impl PathResolver {
+    // a resolver that looks only in the current directory, and among the system's fonts
+    pub fn new() -> PathResolver {
+        PathResolver { paths: Vec::new() }
+    }
+
+    // a resolver using the search paths in TEXINPUTS, TFMFONTS, TEXFORMATS,
+    // OPENTYPEFONTS, VFFONTS and T1FONTS
+    pub fn from_env() -> PathResolver {
+        let mut r = PathResolver::new();
+        for &kind in &[FileKind::Tex, FileKind::Tfm, FileKind::Fmt, FileKind::OpenType, FileKind::VirtualFont,
+                       FileKind::Type1] {
+            if let Some(v) = env::var_os(kind.path_var()) {
+                r.set_path(kind, &v.to_string_lossy());
+            }
+        }
+        r
+    }
+
+    // sets the search path for one kind of file, in the syntax of the environment variables
+    pub fn set_path(&mut self, kind: FileKind, path: &str) {
+        let dirs = PathResolver::parse_path(kind, path);
+        self.paths.retain(|(k, _)| *k != kind);
+        self.paths.push((kind, dirs));
+    }
+
+    fn parse_path(kind: FileKind, path: &str) -> Vec<SearchDir> {
+        let mut dirs = Vec::new();
+        for elt in env::split_paths(path) {
+            let s = elt.to_string_lossy();
+            if s.is_empty() {
+                // the default paths have no empty elements, so this doesn't recurse again
+                dirs.extend(PathResolver::parse_path(kind, kind.default_path()));
+            } else if s.ends_with("//") {
+                let base = s.trim_end_matches('/');
+                let base = if base.is_empty() { "/" } else { base };
+                dirs.push(SearchDir { dir: PathBuf::from(base), recursive: true });
+            } else {
+                dirs.push(SearchDir { dir: elt.clone(), recursive: false });
+            }
+        }
+        dirs
+    }
+
+    fn search_path(&self, kind: FileKind) -> Vec<SearchDir> {
+        match self.paths.iter().find(|(k, _)| *k == kind) {
+            Some((_, dirs)) => dirs.clone(),
+            None => PathResolver::parse_path(kind, kind.default_path()),
+        }
+    }
+
+    // the full name of the file, if it can be found along the search path for its kind
+    pub fn find(&self, name: &str, kind: FileKind) -> Option<PathBuf> {
+        let p = Path::new(name);
+        if p.is_absolute() || name.starts_with("./") || name.starts_with("../") {
+            // explicitly located names are not searched for
+            return if p.is_file() { Some(p.to_path_buf()) } else { None };
+        }
+        for d in self.search_path(kind) {
+            let found = if d.recursive { find_below(&d.dir, p) } else { found_in(&d.dir, p) };
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+}
+
+impl Default for PathResolver {
+    fn default() -> PathResolver {
+        PathResolver::from_env()
+    }
+}
+
+fn found_in(dir: &Path, name: &Path) -> Option<PathBuf> {
+    let f = dir.join(name);
+    if f.is_file() {
+        Some(f)
+    } else {
+        None
+    }
+}
+
+// looks for name in dir and then in its subdirectories, in alphabetical order so
+// that the same file is found on every system
+fn find_below(dir: &Path, name: &Path) -> Option<PathBuf> {
+    if let Some(f) = found_in(dir, name) {
+        return Some(f);
+    }
+    let mut subdirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|e| e.path())
+            .collect(),
+        Err(_) => return None,
+    };
+    subdirs.sort();
+    subdirs.iter().find_map(|d| find_below(d, name))
+}
+
+impl FileResolver for PathResolver {
+    fn open_in(&mut self, name: &str, kind: FileKind) -> Option<(String, Box<dyn Read>)> {
+        let path = self.find(name, kind)?;
+        let f = File::open(&path).ok()?;
+        Some((path.to_string_lossy().into_owned(), Box::new(f)))
+    }
+
+    fn open_out(&mut self, name: &str) -> Option<Box<dyn Write>> {
+        let f = File::create(name).ok()?;
+        Some(Box::new(BufWriter::new(f)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a search path made of the given elements, with the system's separator
+    fn path(elts: &[&str]) -> String {
+        env::join_paths(elts).unwrap().to_string_lossy().into_owned()
+    }
+
+    fn dir(d: &str, recursive: bool) -> SearchDir {
+        SearchDir { dir: PathBuf::from(d), recursive }
+    }
+
+    #[test]
+    fn empty_elements_expand_to_the_default_path() {
+        let mut r = PathResolver::new();
+        let defaults = r.search_path(FileKind::OpenType);
+        assert!(defaults.len() > 1);
+        r.set_path(FileKind::OpenType, &path(&["fonts", ""]));
+        let mut expected = vec![dir("fonts", false)];
+        expected.extend(defaults.iter().cloned());
+        assert_eq!(r.search_path(FileKind::OpenType), expected);
+        r.set_path(FileKind::Tex, &path(&["", "chapters"]));
+        assert_eq!(r.search_path(FileKind::Tex), vec![dir(".", false), dir("chapters", false)]);
+    }
+
+    #[test]
+    fn double_slashes_ask_for_subdirectories() {
+        let mut r = PathResolver::new();
+        r.set_path(FileKind::Tfm, &path(&["fonts//", "."]));
+        assert_eq!(r.search_path(FileKind::Tfm), vec![dir("fonts", true), dir(".", false)]);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::*;
+use std::fmt;
+use std::ops::Neg;
+
+// 108
+pub const InfBad: i32 = 10000; // infinitely bad value
+
+// 101
+// a scaled value whose arithmetic follows tex.web exactly and reports overflow
+// instead of wrapping; the TexState methods below are written in terms of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct Sp(pub Scaled);
+
+impl Sp {
+    pub const ZERO: Sp = Sp(0);
+    pub const UNITY: Sp = Sp(unity);
+    pub const MAX: Sp = Sp(MaxDimen);
+
+    // n points, if that is a legal dimension
+    pub fn from_pt(n: i32) -> Option<Sp> {
+        Sp::UNITY.checked_mul(n)
+    }
+
+    // 102
+    // the decimal fraction .d0d1...d(k-1) given by the digits, correctly rounded
+    pub fn from_decimals(digits: &[u8]) -> Sp {
+        let mut a = 0;
+        for &d in digits.iter().rev() {
+            a = (a + d as i32 * two) / 10;
+        }
+        Sp((a + 1) / 2)
+    }
+
+    // 100
+    // divide by two, rounding odd values upward
+    pub fn half(self) -> Sp {
+        if self.0 % 2 != 0 {
+            Sp((self.0 + 1) / 2)
+        } else {
+            Sp(self.0 / 2)
+        }
+    }
+
+    // 105
+    // n*self+y, or None if the magnitude of the answer would exceed max_answer
+    pub fn mult_and_add(self, n: i32, y: Sp, max_answer: Sp) -> Option<Sp> {
+        let (mut n, mut x) = (n as i64, self.0 as i64);
+        let (y, max_answer) = (y.0 as i64, max_answer.0 as i64);
+        if n < 0 {
+            x = -x;
+            n = -n;
+        }
+        if n == 0 {
+            Some(Sp(y as Scaled))
+        } else if x <= (max_answer - y) / n && -x <= (max_answer + y) / n {
+            Some(Sp((n * x + y) as Scaled))
+        } else {
+            None
+        }
+    }
+
+    pub fn checked_add(self, other: Sp) -> Option<Sp> {
+        self.mult_and_add(1, other, Sp::MAX)
+    }
+
+    pub fn checked_sub(self, other: Sp) -> Option<Sp> {
+        other.mult_and_add(-1, self, Sp::MAX)
+    }
+
+    pub fn checked_mul(self, n: i32) -> Option<Sp> {
+        self.mult_and_add(n, Sp::ZERO, Sp::MAX)
+    }
+
+    // 106
+    // the quotient and remainder of self/n, truncated toward zero, or None if n is zero
+    pub fn checked_div(self, n: i32) -> Option<(Sp, Sp)> {
+        if n == 0 {
+            return None;
+        }
+        let (mut x, mut n) = (self.0, n);
+        let mut negative = false;
+        if n < 0 {
+            x = -x;
+            n = -n;
+            negative = true;
+        }
+        let (q, mut r) = if x >= 0 {
+            (x / n, x % n)
+        } else {
+            (-((-x) / n), -((-x) % n))
+        };
+        if negative {
+            r = -r;
+        }
+        Some((Sp(q), Sp(r)))
+    }
+
+    // 107
+    // the quotient and remainder of self*n/d, or None if the quotient is 2^30 or more
+    pub fn checked_scale(self, n: i32, d: i32) -> Option<(Sp, Sp)> {
+        match self.scale_parts(n, d) {
+            (_, _, true) => None,
+            (u, r, false) => Some((u, r)),
+        }
+    }
+
+    // computes self*n/d exactly, as TeX does with 15-bit pieces; the last component
+    // tells whether the quotient overflowed, in which case the first is meaningless
+    fn scale_parts(self, n: i32, d: i32) -> (Sp, Sp, bool) {
+        let positive = self.0 >= 0;
+        let x = (self.0 as i64).abs();
+        let (n, d) = (n as i64, d as i64);
+        let t = (x % 0o100000) * n;
+        let mut u = (x / 0o100000) * n + (t / 0o100000);
+        let v = (u % d) * 0o100000 + (t % 0o100000);
+        let overflow = u / d >= 0o100000;
+        if !overflow {
+            u = 0o100000 * (u / d) + (v / d);
+        }
+        if positive {
+            (Sp(u as Scaled), Sp((v % d) as Scaled), overflow)
+        } else {
+            (Sp(-u as Scaled), Sp(-(v % d) as Scaled), overflow)
+        }
+    }
+
+    // 108
+    // the badness of stretching or shrinking by self when the total stretchability
+    // or shrinkability is s, given self>=0
+    pub fn badness(self, s: Sp) -> i32 {
+        let (t, s) = (self.0, s.0);
+        if t == 0 {
+            0
+        } else if s <= 0 {
+            InfBad
+        } else {
+            let r = if t <= 7230584 {
+                (t * 297) / s // 297^3=99.94*2^18
+            } else if s >= 1663497 {
+                t / (s / 297)
+            } else {
+                t
+            };
+            if r > 1290 {
+                InfBad // 1290^3<2^31<1291^3
+            } else {
+                (r * r * r + 0o400000) / 0o1000000 // that was r^3/2^18, rounded to the nearest integer
+            }
+        }
+    }
+}
+
+impl Neg for Sp {
+    type Output = Sp;
+
+    fn neg(self) -> Sp {
+        Sp(-self.0)
+    }
+}
+
+// 103
+// the decimal form of print_scaled, rounded to five digits
+impl fmt::Display for Sp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = self.0 as i64;
+        let u = unity as i64;
+        if s < 0 {
+            write!(f, "-")?;
+            s = -s; // print the sign, if negative
+        }
+        write!(f, "{}.", s / u)?; // print the integer part
+        s = 10 * (s % u) + 5;
+        let mut delta = 10;
+        loop {
+            if delta > u {
+                s = s + 0o100000 - 50000; // round the last digit
+            }
+            write!(f, "{}", s / u)?;
+            s = 10 * (s % u);
+            delta *= 10;
+            if s <= delta {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 100
+pub fn half(x: i32) -> i32 {
+    Sp(x).half().0
+}
+
+// 108
+pub fn badness(t: Scaled, s: Scaled) -> i32 {
+    Sp(t).badness(Sp(s))
+}
+
+impl TexState {
+    // 102
+    // converts the decimal fraction .d0d1...d(k-1) in dig to a scaled value
+    pub fn round_decimals(&mut self, k: usize) -> Scaled {
+        Sp::from_decimals(&self.dig[..k]).0
+    }
+
+    // 103
+    // prints scaled real, rounded to five digits
+    pub fn print_scaled(&mut self, s: Scaled) {
+        self.print(&Sp(s).to_string());
+    }
+
+    // 105
+    pub fn mult_and_add(&mut self, n: i32, x: Scaled, y: Scaled, max_answer: Scaled) -> Scaled {
+        match Sp(x).mult_and_add(n, Sp(y), Sp(max_answer)) {
+            Some(a) => a.0,
+            None => {
+                self.arith_error = true;
+                0
+            }
+        }
+    }
+
+    pub fn nx_plus_y(&mut self, n: i32, x: Scaled, y: Scaled) -> Scaled {
+        self.mult_and_add(n, x, y, 0o7777777777)
+    }
+
+    pub fn mult_integers(&mut self, n: i32, x: i32) -> i32 {
+        self.mult_and_add(n, x, 0, 0o17777777777)
+    }
+
+    // 106
+    pub fn x_over_n(&mut self, x: Scaled, n: i32) -> Scaled {
+        match Sp(x).checked_div(n) {
+            Some((q, r)) => {
+                self.remainder = r.0;
+                q.0
+            }
+            None => {
+                self.arith_error = true;
+                self.remainder = x;
+                0
+            }
+        }
+    }
+
+    // 107
+    pub fn xn_over_d(&mut self, x: Scaled, n: i32, d: i32) -> Scaled {
+        let (u, r, overflow) = Sp(x).scale_parts(n, d);
+        if overflow {
+            self.arith_error = true;
+        }
+        self.remainder = r.0;
+        u.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn sp_arithmetic_reports_overflow() {
+        assert_eq!(Sp::from_pt(3), Some(Sp(3 * unity)));
+        assert_eq!(Sp::from_pt(16384), None);
+        assert_eq!(Sp::MAX.checked_add(Sp(1)), None);
+        assert_eq!(Sp::MAX.checked_sub(Sp(1)), Some(Sp(MaxDimen - 1)));
+        assert_eq!((-Sp::MAX).checked_sub(Sp(1)), None);
+        assert_eq!(Sp(100).checked_mul(-3), Some(Sp(-300)));
+        assert_eq!(Sp(-7).checked_div(2), Some((Sp(-3), Sp(-1))));
+        assert_eq!(Sp(7).checked_div(0), None);
+        assert_eq!(Sp::UNITY.checked_scale(7227, 100), Some((Sp(4736286), Sp(72))));
+        assert_eq!(Sp(0o7777777777).checked_scale(0o100000, 1), None);
+        assert_eq!(Sp(-7).half(), Sp(-3));
+        assert_eq!(Sp::from_decimals(&[1, 2, 3, 4, 5]), Sp(8090));
+        assert_eq!(Sp(unity).badness(Sp(2 * unity)), 12);
+    }
+
+    #[test]
+    fn sp_displays_like_print_scaled() {
+        assert_eq!(Sp(65536 * 3 / 7).to_string(), "0.42856");
+        assert_eq!((-Sp::UNITY.half()).to_string(), "-0.5");
+        assert_eq!(Sp::MAX.to_string(), "16383.99998");
+    }
+
+    #[test]
+    fn badness_matches_tex() {
+        assert_eq!(badness(0, 0), 0);
+        assert_eq!(badness(100, 0), InfBad);
+        assert_eq!(badness(100, 1), InfBad);
+        assert_eq!(badness(unity, unity), 100);
+        assert_eq!(badness(unity, 2 * unity), 12);
+        assert_eq!(badness(7230584, 1663497), 8189);
+        assert_eq!(badness(7230585, 1663497), 8189);
+    }
+
+    #[test]
+    fn half_rounds_odd_values_upward() {
+        assert_eq!(half(7), 4);
+        assert_eq!(half(-7), -3);
+        assert_eq!(half(8), 4);
+    }
+
+    #[test]
+    fn division_follows_the_sign_rules() {
+        let mut state = TexState::new();
+        assert_eq!(state.x_over_n(7, 2), 3);
+        assert_eq!(state.remainder, 1);
+        assert_eq!(state.x_over_n(-7, 2), -3);
+        assert_eq!(state.remainder, -1);
+        assert_eq!(state.x_over_n(7, -2), -3);
+        assert_eq!(state.remainder, 1);
+        assert_eq!(state.x_over_n(-7, -2), 3);
+        assert_eq!(state.remainder, -1);
+        assert!(!state.arith_error);
+        assert_eq!(state.x_over_n(7, 0), 0);
+        assert_eq!(state.remainder, 7);
+        assert!(state.arith_error);
+
+        state.arith_error = false;
+        assert_eq!(state.xn_over_d(7, 3, 2), 10);
+        assert_eq!(state.remainder, 1);
+        assert_eq!(state.xn_over_d(-7, 3, 2), -10);
+        assert_eq!(state.remainder, -1);
+        assert_eq!(state.xn_over_d(unity, 7227, 100), 4736286);
+        assert_eq!(state.remainder, 72);
+        assert!(!state.arith_error);
+        state.xn_over_d(0o7777777777, 0o100000, 1);
+        assert!(state.arith_error);
+    }
+
+    #[test]
+    fn nx_plus_y_reports_overflow() {
+        let mut state = TexState::new();
+        assert_eq!(state.nx_plus_y(3, 100, 5), 305);
+        assert_eq!(state.nx_plus_y(-3, 100, 5), -295);
+        assert!(!state.arith_error);
+        assert_eq!(state.nx_plus_y(1, 0o7777777777, 0), 0o7777777777);
+        assert!(!state.arith_error);
+        assert_eq!(state.nx_plus_y(1, 0o7777777777, 1), 0);
+        assert!(state.arith_error);
+        state.arith_error = false;
+        assert_eq!(state.nx_plus_y(2, 0o4000000000, 0), 0);
+        assert!(state.arith_error);
+    }
+
+    #[test]
+    fn round_decimals_matches_tex() {
+        let mut state = TexState::new();
+        state.dig[0] = 5;
+        assert_eq!(state.round_decimals(1), 32768);
+        for (k, d) in [1, 2, 3, 4, 5].iter().enumerate() {
+            state.dig[k] = *d;
+        }
+        assert_eq!(state.round_decimals(5), 8090);
+        for k in 0..17 {
+            state.dig[k] = 9;
+        }
+        assert_eq!(state.round_decimals(17), unity);
+    }
+
+    fn printed_scaled(state: &mut TexState, s: Scaled) -> String {
+        state.selector = NewString;
+        state.print_scaled(s);
+        let n = state.make_string();
+        let (a, b) = (state.str_start[n as usize], state.str_start[n as usize + 1]);
+        (a..b).map(|k| state.str_pool[k] as char).collect()
+    }
+
+    #[test]
+    fn print_scaled_rounds_to_five_digits() {
+        let mut state = TexState::new();
+        assert_eq!(printed_scaled(&mut state, 65536 * 3 / 7), "0.42856");
+        assert_eq!(printed_scaled(&mut state, unity), "1.0");
+        assert_eq!(printed_scaled(&mut state, -unity / 2), "-0.5");
+        assert_eq!(printed_scaled(&mut state, 1), "0.00002");
+        assert_eq!(printed_scaled(&mut state, 0o7777777777), "16383.99998");
+    }
+}
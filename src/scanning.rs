@@ -0,0 +1,989 @@
+use crate::*;
+
+// 416
+pub const InputLineNoCode: HalfWord = GlueVal as HalfWord + 1; // code for \inputlineno
+pub const BadnessCode: HalfWord = GlueVal as HalfWord + 2; // code for \badness
+
+// 421
+pub const MaxDimen: Scaled = 0o7777777777; // 2^30-1
+
+// 438
+pub const Infinity: i32 = 0o17777777777; // the largest positive value that TeX knows
+
+// 445
+pub const A_token: i32 = letter_token + 65; // the smallest special hex digit
+pub const other_A_token: i32 = other_token + 65; // special hex digit of type other_char
+
+// 463
+pub const DefaultRule: Scaled = 26214; // 0.4pt
+
+// 468
+pub const NumberCode: HalfWord = 0; // command code for \number
+pub const RomanNumeralCode: HalfWord = 1; // command code for \romannumeral
+pub const StringCode: HalfWord = 2; // command code for \string
+pub const MeaningCode: HalfWord = 3; // command code for \meaning
+pub const FontNameCode: HalfWord = 4; // command code for \fontname
+pub const JobNameCode: HalfWord = 5; // command code for \jobname
+
+impl TexState {
+    // 403
+    // reads a mandatory left_brace
+    pub fn scan_left_brace(&mut self) {
+        // 404: get the next non-blank non-relax non-call token
+        loop {
+            self.get_x_token();
+            if self.cur_cmd != Spacer && self.cur_cmd != Relax {
+                break;
+            }
+        }
+        if self.cur_cmd != LeftBrace {
+            self.print_err("Missing { inserted");
+            self.help(&["A left brace was mandatory here, so I've put one in.",
+                "You might want to delete and/or insert some corrections",
+                "so that I will find a matching right brace soon.",
+                "(If you're confused by all this, try typing `I}' now.)"]);
+            self.back_error();
+            self.cur_tok = (left_brace_token + '{' as i32) as HalfWord;
+            self.cur_cmd = LeftBrace;
+            self.cur_chr = '{' as HalfWord;
+            self.align_state += 1;
+        }
+    }
+
+    // 406
+    // get the next non-blank non-call token
+    pub fn get_x_non_blank(&mut self) {
+        loop {
+            self.get_x_token();
+            if self.cur_cmd != Spacer {
+                break;
+            }
+        }
+    }
+
+    // 405
+    // look for an optional `=' sign
+    pub fn scan_optional_equals(&mut self) {
+        self.get_x_non_blank();
+        if self.cur_tok as i32 != other_token + '=' as i32 {
+            self.back_input();
+        }
+    }
+
+    // 443
+    // scan an optional space
+    pub fn scan_optional_space(&mut self) {
+        self.get_x_token();
+        if self.cur_cmd != Spacer {
+            self.back_input();
+        }
+    }
+
+    // 441
+    // get the next non-blank non-sign token, returning true if it was preceded
+    // by an odd number of minus signs
+    fn scan_sign(&mut self) -> bool {
+        let mut negative = false;
+        loop {
+            self.get_x_non_blank();
+            if self.cur_tok as i32 == other_token + '-' as i32 {
+                negative = !negative;
+                self.cur_tok = (other_token + '+' as i32) as HalfWord;
+            }
+            if self.cur_tok as i32 != other_token + '+' as i32 {
+                break;
+            }
+        }
+        negative
+    }
+
+    // is the current command one that can follow \the?
+    fn is_internal_cmd(&self) -> bool {
+        self.cur_cmd >= MinInternal && self.cur_cmd <= MaxInternal
+    }
+
+    // 407
+    // look for a given string, ignoring case and skipping leading blanks
+    pub fn scan_keyword(&mut self, s: &str) -> bool {
+        let mut p = BackupHead as Pointer;
+        self.set_link(p as usize, Null);
+        let s = s.as_bytes();
+        let mut k = 0;
+        while k < s.len() {
+            self.get_x_token(); // recursion is possible here
+            if self.cur_cs == 0
+                && (self.cur_chr == s[k] as HalfWord || self.cur_chr == (s[k] - b'a' + b'A') as HalfWord)
+            {
+                p = self.store_new_token(p, self.cur_tok);
+                k += 1;
+            } else if self.cur_cmd != Spacer || p != BackupHead as Pointer {
+                self.back_input();
+                if p != BackupHead as Pointer {
+                    self.back_list(self.link(BackupHead));
+                }
+                return false;
+            }
+        }
+        self.flush_list(self.link(BackupHead));
+        true
+    }
+
+    // 408
+    pub fn mu_error(&mut self) {
+        self.print_err("Incompatible glue units");
+        self.help(&["I'm going to assume that 1mu=1pt when they're mixed."]);
+        self.error();
+    }
+
+    // 413
+    // sets cur_val to the quantity named by the current command,
+    // at a level no higher than level
+    pub fn scan_something_internal(&mut self, level: i32, negative: bool) {
+        let mut m = self.cur_chr;
+        match self.cur_cmd {
+            DefCode => {
+                // 414: fetch a character code from some table
+                self.scan_char_num();
+                let v = self.cur_val as HalfWord;
+                if m == MathCodeBase {
+                    self.scanned_result(self.math_code(v) as i32, IntVal);
+                } else if m < MathCodeBase {
+                    self.scanned_result(self.equiv((m + v) as usize) as i32, IntVal);
+                } else {
+                    self.scanned_result(self.eqtb[(m + v) as usize].int(), IntVal);
+                }
+            }
+            ToksRegister | AssignToks | DefFamily | SetFont | DefFont => {
+                // 415: fetch a token list or font identifier, provided that level=tok_val
+                if level != TokVal {
+                    self.print_err("Missing number, treated as zero");
+                    self.help(&["A number should have been here; I inserted `0'.",
+                        "(If you can't figure out why I needed to see a number,",
+                        "look up `weird error' in the index to The TeXbook.)"]);
+                    self.back_error();
+                    self.scanned_result(0, DimenVal);
+                } else if self.cur_cmd <= AssignToks {
+                    if self.cur_cmd < AssignToks {
+                        // cur_cmd=toks_register
+                        self.scan_eight_bit_int();
+                        m = ToksBase + self.cur_val as HalfWord;
+                    }
+                    self.scanned_result(self.equiv(m as usize) as i32, TokVal);
+                } else {
+                    self.back_input();
+                    self.scan_font_ident();
+                    self.scanned_result(FontIdBase as i32 + self.cur_val, IdentVal);
+                }
+            }
+            AssignFontDimen => {
+                // 425: fetch a font dimension
+                self.find_font_dimen(false);
+                self.font_info[self.fmem_ptr].set_sc(0);
+                self.scanned_result(self.font_info[self.cur_val as usize].sc(), DimenVal);
+            }
+            AssignFontInt => {
+                // 426: fetch a font integer
+                self.scan_font_ident();
+                if m == 0 {
+                    self.scanned_result(self.hyphen_char[self.cur_val as usize], IntVal);
+                } else {
+                    self.scanned_result(self.skew_char[self.cur_val as usize], IntVal);
+                }
+            }
+            AssignInt => self.scanned_result(self.eqtb[m as usize].int(), IntVal),
+            AssignDimen => self.scanned_result(self.eqtb[m as usize].sc(), DimenVal),
+            AssignGlue => self.scanned_result(self.equiv(m as usize) as i32, GlueVal),
+            AssignMuGlue => self.scanned_result(self.equiv(m as usize) as i32, MuVal),
+            SetAux => {
+                // 418: fetch the space_factor or the prev_depth
+                if self.mode().abs() != m as i32 {
+                    self.print_err("Improper ");
+                    self.print_cmd_chr(SetAux, m);
+                    self.help(&["You can refer to \\spacefactor only in horizontal mode;",
+                        "you can refer to \\prevdepth only in vertical mode; and",
+                        "neither of these is meaningful inside \\write. So",
+                        "I'm forgetting what you said and using zero instead."]);
+                    self.error();
+                    if level != TokVal {
+                        self.scanned_result(0, DimenVal);
+                    } else {
+                        self.scanned_result(0, IntVal);
+                    }
+                } else if m as i32 == Vmode {
+                    self.scanned_result(self.prev_depth(), DimenVal);
+                } else {
+                    self.scanned_result(self.space_factor() as i32, IntVal);
+                }
+            }
+            SetPrevGraf => {
+                // 422: fetch the prev_graf
+                if self.mode() == 0 {
+                    self.scanned_result(0, IntVal); // prev_graf=0 within \write
+                } else {
+                    self.nest[self.nest_ptr] = self.curlist;
+                    let mut p = self.nest_ptr;
+                    while self.nest[p].mode_field.abs() != Vmode {
+                        p -= 1;
+                    }
+                    self.scanned_result(self.nest[p].pg_field, IntVal);
+                }
+            }
+            SetPageInt => {
+                // 419: fetch the dead_cycles or the insert_penalties
+                if m == 0 {
+                    self.cur_val = self.dead_cycles;
+                } else {
+                    self.cur_val = self.insert_penalties;
+                }
+                self.cur_val_level = IntVal;
+            }
+            SetPageDimen => {
+                // 421: fetch something on the page_so_far
+                if self.page_contents == Empty as u8 && !self.output_active {
+                    if m == 0 {
+                        self.cur_val = MaxDimen;
+                    } else {
+                        self.cur_val = 0;
+                    }
+                } else {
+                    self.cur_val = self.page_so_far[m as usize];
+                }
+                self.cur_val_level = DimenVal;
+            }
+            SetShape => {
+                // 423: fetch the par_shape size
+                if self.par_shape_ptr() == Null {
+                    self.cur_val = 0;
+                } else {
+                    self.cur_val = self.info(self.par_shape_ptr() as usize) as i32;
+                }
+                self.cur_val_level = IntVal;
+            }
+            SetBoxDimen => {
+                // 420: fetch a box dimension
+                self.scan_eight_bit_int();
+                let b = self.box_reg(self.cur_val as HalfWord);
+                if b == Null {
+                    self.cur_val = 0;
+                } else {
+                    self.cur_val = self.mem[(b + m) as usize].sc();
+                }
+                self.cur_val_level = DimenVal;
+            }
+            CharGiven | MathGiven => self.scanned_result(self.cur_chr as i32, IntVal),
+            Register => {
+                // 427: fetch a register
+                self.scan_eight_bit_int();
+                let v = self.cur_val as HalfWord;
+                self.cur_val = match m as i32 {
+                    IntVal => self.count(v),
+                    DimenVal => self.dimen(v),
+                    GlueVal => self.skip(v) as i32,
+                    _ => self.mu_skip(v) as i32,
+                };
+                self.cur_val_level = m as i32;
+            }
+            LastItem => {
+                // 424: fetch an item in the current node, if appropriate
+                if self.cur_chr > GlueVal as HalfWord {
+                    if self.cur_chr == InputLineNoCode {
+                        self.cur_val = self.line;
+                    } else {
+                        self.cur_val = self.last_badness; // cur_chr=badness_code
+                    }
+                    self.cur_val_level = IntVal;
+                } else {
+                    if self.cur_chr == GlueVal as HalfWord {
+                        self.cur_val = ZeroGlue as i32;
+                    } else {
+                        self.cur_val = 0;
+                    }
+                    self.cur_val_level = self.cur_chr as i32;
+                    let t = self.tail() as usize;
+                    if !self.is_char_node(t) && self.mode() != 0 {
+                        match self.cur_chr as i32 {
+                            IntVal => {
+                                if self.a_type(t) == PenaltyNode {
+                                    self.cur_val = self.penalty(t);
+                                }
+                            }
+                            DimenVal => {
+                                if self.a_type(t) == KernNode {
+                                    self.cur_val = self.width(t);
+                                }
+                            }
+                            _ => {
+                                if self.a_type(t) == GlueNode {
+                                    self.cur_val = self.glue_ptr(t) as i32;
+                                    if self.subtype(t) == MuGlue {
+                                        self.cur_val_level = MuVal;
+                                    }
+                                }
+                            }
+                        }
+                    } else if self.mode() == Vmode && self.tail() == self.head() {
+                        match self.cur_chr as i32 {
+                            IntVal => self.cur_val = self.last_penalty,
+                            DimenVal => self.cur_val = self.last_kern,
+                            _ => {
+                                if self.last_glue != MaxHalfWord {
+                                    self.cur_val = self.last_glue as i32;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {
+                // 428: complain that \the can't do this; give zero result
+                self.print_err("You can't use `");
+                self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+                self.print("' after ");
+                self.print_esc("the");
+                self.help(&["I'm forgetting what you said and using zero instead."]);
+                self.error();
+                if level != TokVal {
+                    self.scanned_result(0, DimenVal);
+                } else {
+                    self.scanned_result(0, IntVal);
+                }
+            }
+        }
+        while self.cur_val_level > level {
+            // 429: convert cur_val to a lower level
+            if self.cur_val_level == GlueVal {
+                self.cur_val = self.width(self.cur_val as usize);
+            } else if self.cur_val_level == MuVal {
+                self.mu_error();
+            }
+            self.cur_val_level -= 1;
+        }
+        // 430: fix the reference count, if any, and negate cur_val if negative
+        if negative {
+            if self.cur_val_level >= GlueVal {
+                self.cur_val = self.new_spec(self.cur_val as Pointer) as i32;
+                // 431: negate all three glue components of cur_val
+                let v = self.cur_val as usize;
+                self.set_width(v, -self.width(v));
+                self.set_stretch(v, -self.stretch(v));
+                self.set_shrink(v, -self.shrink(v));
+            } else {
+                self.cur_val = -self.cur_val;
+            }
+        } else if self.cur_val_level >= GlueVal && self.cur_val_level <= MuVal {
+            self.add_glue_ref(self.cur_val as Pointer);
+        }
+    }
+
+    // 413
+    fn scanned_result(&mut self, v: i32, l: i32) {
+        self.cur_val = v;
+        self.cur_val_level = l;
+    }
+
+    // 433
+    pub fn scan_eight_bit_int(&mut self) {
+        self.scan_int();
+        if self.cur_val < 0 || self.cur_val > 255 {
+            self.print_err("Bad register code");
+            self.help(&["A register number must be between 0 and 255.",
+                "I changed this one to zero."]);
+            self.int_error(self.cur_val);
+            self.cur_val = 0;
+        }
+    }
+
+    // 434
+    pub fn scan_char_num(&mut self) {
+        self.scan_int();
+        if self.cur_val < 0 || self.cur_val > 255 {
+            self.print_err("Bad character code");
+            self.help(&["A character number must be between 0 and 255.",
+                "I changed this one to zero."]);
+            self.int_error(self.cur_val);
+            self.cur_val = 0;
+        }
+    }
+
+    // 435
+    pub fn scan_four_bit_int(&mut self) {
+        self.scan_int();
+        if self.cur_val < 0 || self.cur_val > 15 {
+            self.print_err("Bad number");
+            self.help(&["Since I expected to read a number between 0 and 15,",
+                "I changed this one to zero."]);
+            self.int_error(self.cur_val);
+            self.cur_val = 0;
+        }
+    }
+
+    // 436
+    pub fn scan_fifteen_bit_int(&mut self) {
+        self.scan_int();
+        if self.cur_val < 0 || self.cur_val > 0o77777 {
+            self.print_err("Bad mathchar");
+            self.help(&["A mathchar number must be between 0 and 32767.",
+                "I changed this one to zero."]);
+            self.int_error(self.cur_val);
+            self.cur_val = 0;
+        }
+    }
+
+    // 437
+    pub fn scan_twenty_seven_bit_int(&mut self) {
+        self.scan_int();
+        if self.cur_val < 0 || self.cur_val > 0o777777777 {
+            self.print_err("Bad delimiter code");
+            self.help(&["A numeric delimiter code must be between 0 and 2^{27}-1.",
+                "I changed this one to zero."]);
+            self.int_error(self.cur_val);
+            self.cur_val = 0;
+        }
+    }
+
+    // 440
+    // sets cur_val to an integer
+    pub fn scan_int(&mut self) {
+        self.radix = 0;
+        let mut OK_so_far = true;
+        let negative = self.scan_sign();
+        if self.cur_tok as i32 == alpha_token {
+            // 442: scan an alphabetic character code into cur_val
+            self.get_token(); // suppress macro expansion
+            let t = self.cur_tok as i32;
+            if t < cs_token_flag {
+                self.cur_val = self.cur_chr as i32;
+                if self.cur_cmd <= RightBrace {
+                    if self.cur_cmd == RightBrace {
+                        self.align_state += 1;
+                    } else {
+                        self.align_state -= 1;
+                    }
+                }
+            } else if t < cs_token_flag + SingleBase as i32 {
+                self.cur_val = t - cs_token_flag - ActiveBase as i32;
+            } else {
+                self.cur_val = t - cs_token_flag - SingleBase as i32;
+            }
+            if self.cur_val > 255 {
+                self.print_err("Improper alphabetic constant");
+                self.help(&["A one-character control sequence belongs after a ` mark.",
+                    "So I'm essentially inserting \\0 here."]);
+                self.cur_val = '0' as i32;
+                self.back_error();
+            } else {
+                self.scan_optional_space();
+            }
+        } else if self.is_internal_cmd() {
+            self.scan_something_internal(IntVal, false);
+        } else {
+            // 444: scan a numeric constant
+            self.radix = 10;
+            let mut m = 214748364;
+            if self.cur_tok as i32 == octal_token {
+                self.radix = 8;
+                m = 0o2000000000;
+                self.get_x_token();
+            } else if self.cur_tok as i32 == hex_token {
+                self.radix = 16;
+                m = 0o1000000000;
+                self.get_x_token();
+            }
+            let mut vacuous = true;
+            self.cur_val = 0;
+            // 445: accumulate the constant until cur_tok is not a suitable digit
+            loop {
+                let t = self.cur_tok as i32;
+                let d;
+                if t < zero_token + self.radix && (zero_token..=zero_token + 9).contains(&t) {
+                    d = t - zero_token;
+                } else if self.radix == 16 {
+                    if (A_token..=A_token + 5).contains(&t) {
+                        d = t - A_token + 10;
+                    } else if (other_A_token..=other_A_token + 5).contains(&t) {
+                        d = t - other_A_token + 10;
+                    } else {
+                        break;
+                    }
+                } else {
+                    break;
+                }
+                vacuous = false;
+                if self.cur_val >= m && (self.cur_val > m || d > 7 || self.radix != 10) {
+                    if OK_so_far {
+                        self.print_err("Number too big");
+                        self.help(&["I can only go up to 2147483647='17777777777=\"7FFFFFFF,",
+                            "so I'm using that number instead of yours."]);
+                        self.error();
+                        self.cur_val = Infinity;
+                        OK_so_far = false;
+                    }
+                } else {
+                    self.cur_val = self.cur_val * self.radix + d;
+                }
+                self.get_x_token();
+            }
+            if vacuous {
+                // 446: express astonishment that no number was here
+                self.print_err("Missing number, treated as zero");
+                self.help(&["A number should have been here; I inserted `0'.",
+                    "(If you can't figure out why I needed to see a number,",
+                    "look up `weird error' in the index to The TeXbook.)"]);
+                self.back_error();
+            } else if self.cur_cmd != Spacer {
+                self.back_input();
+            }
+        }
+        if negative {
+            self.cur_val = -self.cur_val;
+        }
+    }
+
+    // 448
+    // sets cur_val to a dimension; mu selects math units, inf allows fil orders,
+    // and shortcut means that cur_val already holds the integer part
+    pub fn scan_dimen(&mut self, mu: bool, inf: bool, shortcut: bool) {
+        let mut f = 0; // numerator of a fraction whose denominator is 2^16
+        self.arith_error = false;
+        self.cur_order = Normal;
+        let mut negative = false;
+        'attach_sign: {
+            if !shortcut {
+                negative = self.scan_sign();
+                if self.is_internal_cmd() {
+                    // 449: fetch an internal dimension and goto attach_sign,
+                    // or fetch an internal integer
+                    if mu {
+                        self.scan_something_internal(MuVal, false);
+                        self.coerce_glue();
+                        if self.cur_val_level == MuVal {
+                            break 'attach_sign;
+                        }
+                        if self.cur_val_level != IntVal {
+                            self.mu_error();
+                        }
+                    } else {
+                        self.scan_something_internal(DimenVal, false);
+                        if self.cur_val_level == DimenVal {
+                            break 'attach_sign;
+                        }
+                    }
+                } else {
+                    self.back_input();
+                    if self.cur_tok as i32 == continental_point_token {
+                        self.cur_tok = point_token as HalfWord;
+                    }
+                    if self.cur_tok as i32 != point_token {
+                        self.scan_int();
+                    } else {
+                        self.radix = 10;
+                        self.cur_val = 0;
+                    }
+                    if self.cur_tok as i32 == continental_point_token {
+                        self.cur_tok = point_token as HalfWord;
+                    }
+                    if self.radix == 10 && self.cur_tok as i32 == point_token {
+                        f = self.scan_decimal_fraction();
+                    }
+                }
+            }
+            if self.cur_val < 0 {
+                // in this case f=0
+                negative = !negative;
+                self.cur_val = -self.cur_val;
+            }
+            // 453: scan units and set cur_val to x*(cur_val+f/2^16), where there
+            // are x sp per unit; goto attach_sign if the units are internal
+            'done: {
+                'attach_fraction: {
+                    if inf {
+                        // 454: scan for fil units; goto attach_fraction if found
+                        if self.scan_keyword("fil") {
+                            self.cur_order = fil;
+                            while self.scan_keyword("l") {
+                                if self.cur_order == filll {
+                                    self.print_err("Illegal unit of measure (");
+                                    self.print("replaced by filll)");
+                                    self.help(&["I dddon't go any higher than filll."]);
+                                    self.error();
+                                } else {
+                                    self.cur_order += 1;
+                                }
+                            }
+                            break 'attach_fraction;
+                        }
+                    }
+                    // 455: scan for units that are internal dimensions;
+                    // goto attach_sign with cur_val set if found
+                    let save_cur_val = self.cur_val;
+                    self.get_x_non_blank();
+                    'not_found: {
+                        let v;
+                        if !self.is_internal_cmd() {
+                            self.back_input();
+                            if mu {
+                                break 'not_found;
+                            }
+                            if self.scan_keyword("em") {
+                                v = self.quad(self.cur_font() as InternalFontNumber); // 558: the em width for cur_font
+                            } else if self.scan_keyword("ex") {
+                                v = self.x_height(self.cur_font() as InternalFontNumber); // 559: the x-height for cur_font
+                            } else {
+                                break 'not_found;
+                            }
+                            self.scan_optional_space();
+                        } else {
+                            if mu {
+                                self.scan_something_internal(MuVal, false);
+                                self.coerce_glue();
+                                if self.cur_val_level != MuVal {
+                                    self.mu_error();
+                                }
+                            } else {
+                                self.scan_something_internal(DimenVal, false);
+                            }
+                            v = self.cur_val;
+                        }
+                        // found:
+                        let y = self.xn_over_d(v, f, 0o200000);
+                        self.cur_val = self.nx_plus_y(save_cur_val, v, y);
+                        break 'attach_sign;
+                    }
+                    if mu {
+                        // 456: scan for mu units and goto attach_fraction
+                        if !self.scan_keyword("mu") {
+                            self.print_err("Illegal unit of measure (");
+                            self.print("mu inserted)");
+                            self.help(&["The unit of measurement in math glue must be mu.",
+                                "To recover gracefully from this error, it's best to",
+                                "delete the erroneous units; e.g., type `2' to delete",
+                                "two letters. (See Chapter 27 of The TeXbook.)"]);
+                            self.error();
+                        }
+                        break 'attach_fraction;
+                    }
+                    if self.scan_keyword("true") {
+                        // 457: adjust for the magnification ratio
+                        self.prepare_mag();
+                        let mag = self.int_par(MagCode);
+                        if mag != 1000 {
+                            self.cur_val = self.xn_over_d(self.cur_val, 1000, mag);
+                            f = (1000 * f + 0o200000 * self.remainder) / mag;
+                            self.cur_val += f / 0o200000;
+                            f %= 0o200000;
+                        }
+                    }
+                    if self.scan_keyword("pt") {
+                        break 'attach_fraction; // the easy case
+                    }
+                    // 458: scan for all other units and adjust cur_val and f accordingly;
+                    // goto done in the case of scaled points
+                    let (num, denom) = if self.scan_keyword("in") {
+                        (7227, 100)
+                    } else if self.scan_keyword("pc") {
+                        (12, 1)
+                    } else if self.scan_keyword("cm") {
+                        (7227, 254)
+                    } else if self.scan_keyword("mm") {
+                        (7227, 2540)
+                    } else if self.scan_keyword("bp") {
+                        (7227, 7200)
+                    } else if self.scan_keyword("dd") {
+                        (1238, 1157)
+                    } else if self.scan_keyword("cc") {
+                        (14856, 1157)
+                    } else if self.scan_keyword("sp") {
+                        break 'done;
+                    } else {
+                        // 459: complain about unknown unit and goto done2
+                        self.print_err("Illegal unit of measure (");
+                        self.print("pt inserted)");
+                        self.help(&["Dimensions can be in units of em, ex, in, pt, pc,",
+                            "cm, mm, dd, cc, bp, or sp; but yours is a new one!",
+                            "I'll assume that you meant to say pt, for printer's points.",
+                            "To recover gracefully from this error, it's best to",
+                            "delete the erroneous units; e.g., type `2' to delete",
+                            "two letters. (See Chapter 27 of The TeXbook.)"]);
+                        self.error();
+                        break 'attach_fraction;
+                    };
+                    self.cur_val = self.xn_over_d(self.cur_val, num, denom);
+                    f = (num * f + 0o200000 * self.remainder) / denom;
+                    self.cur_val += f / 0o200000;
+                    f %= 0o200000;
+                }
+                // attach_fraction:
+                if self.cur_val >= 0o40000 {
+                    self.arith_error = true;
+                } else {
+                    self.cur_val = self.cur_val * unity + f;
+                }
+            }
+            // done:
+            self.scan_optional_space();
+        }
+        // attach_sign:
+        if self.arith_error || self.cur_val.abs() >= 0o10000000000 {
+            // 460: report that this dimension is out of range
+            self.print_err("Dimension too large");
+            self.help(&["I can't work with sizes bigger than about 19 feet.",
+                "Continue and I'll use the largest value I can."]);
+            self.error();
+            self.cur_val = MaxDimen;
+            self.arith_error = false;
+        }
+        if negative {
+            self.cur_val = -self.cur_val;
+        }
+    }
+
+    // 448
+    pub fn scan_normal_dimen(&mut self) {
+        self.scan_dimen(false, false, false);
+    }
+
+    // 451
+    // coerce glue to a dimension
+    fn coerce_glue(&mut self) {
+        if self.cur_val_level >= GlueVal {
+            let v = self.width(self.cur_val as usize);
+            self.delete_glue_ref(self.cur_val as Pointer);
+            self.cur_val = v;
+        }
+    }
+
+    // 452
+    // scan decimal fraction, returning its value in units of 2^-16
+    fn scan_decimal_fraction(&mut self) -> Scaled {
+        let mut k = 0;
+        let mut p = Null;
+        self.get_token(); // point_token is being re-scanned
+        loop {
+            self.get_x_token();
+            let t = self.cur_tok as i32;
+            if !(zero_token..=zero_token + 9).contains(&t) {
+                break;
+            }
+            if k < 17 {
+                // digits for k>=17 cannot affect the result
+                let q = self.get_avail();
+                self.set_link(q as usize, p);
+                self.set_info(q as usize, (t - zero_token) as HalfWord);
+                p = q;
+                k += 1;
+            }
+        }
+        for kk in (1..=k).rev() {
+            self.dig[kk - 1] = self.info(p as usize) as u8;
+            let q = p;
+            p = self.link(p as usize);
+            self.free_avail(q);
+        }
+        let f = self.round_decimals(k);
+        if self.cur_cmd != Spacer {
+            self.back_input();
+        }
+        f
+    }
+
+    // 461
+    // sets cur_val to a glue spec pointer
+    pub fn scan_glue(&mut self, level: i32) {
+        let mu = level == MuVal;
+        let negative = self.scan_sign();
+        if self.is_internal_cmd() {
+            self.scan_something_internal(level, negative);
+            if self.cur_val_level >= GlueVal {
+                if self.cur_val_level != level {
+                    self.mu_error();
+                }
+                return;
+            }
+            if self.cur_val_level == IntVal {
+                self.scan_dimen(mu, false, true);
+            } else if level == MuVal {
+                self.mu_error();
+            }
+        } else {
+            self.back_input();
+            self.scan_dimen(mu, false, false);
+            if negative {
+                self.cur_val = -self.cur_val;
+            }
+        }
+        // 462: create a new glue specification whose width is cur_val; scan for its
+        // stretch and shrink components
+        let q = self.new_spec(ZeroGlue as Pointer) as usize;
+        self.set_width(q, self.cur_val);
+        if self.scan_keyword("plus") {
+            self.scan_dimen(mu, true, false);
+            self.set_stretch(q, self.cur_val);
+            self.set_stretch_order(q, self.cur_order as u8);
+        }
+        if self.scan_keyword("minus") {
+            self.scan_dimen(mu, true, false);
+            self.set_shrink(q, self.cur_val);
+            self.set_shrink_order(q, self.cur_order as u8);
+        }
+        self.cur_val = q as i32;
+    }
+
+    // 463
+    pub fn scan_rule_spec(&mut self) -> Pointer {
+        let q = self.new_rule(); // width, depth, and height all equal null_flag now
+        let u = q as usize;
+        if self.cur_cmd == Vrule {
+            self.set_width(u, DefaultRule);
+        } else {
+            self.set_height(u, DefaultRule);
+            self.set_depth(u, 0);
+        }
+        loop {
+            if self.scan_keyword("width") {
+                self.scan_normal_dimen();
+                self.set_width(u, self.cur_val);
+            } else if self.scan_keyword("height") {
+                self.scan_normal_dimen();
+                self.set_height(u, self.cur_val);
+            } else if self.scan_keyword("depth") {
+                self.scan_normal_dimen();
+                self.set_depth(u, self.cur_val);
+            } else {
+                return q;
+            }
+        }
+    }
+
+    // 465
+    // implements \the, returning a pointer to the tail of a new token list
+    pub fn the_toks(&mut self) -> Pointer {
+        self.get_x_token();
+        self.scan_something_internal(TokVal, false);
+        if self.cur_val_level >= IdentVal {
+            // 466: copy the token list
+            let mut p = TempHead as Pointer;
+            self.set_link(p as usize, Null);
+            if self.cur_val_level == IdentVal {
+                p = self.store_new_token(p, (cs_token_flag + self.cur_val) as HalfWord);
+            } else if self.cur_val != Null as i32 {
+                let mut r = self.link(self.cur_val as usize); // do not copy the reference count
+                while r != Null {
+                    p = self.store_new_token(p, self.info(r as usize));
+                    r = self.link(r as usize);
+                }
+            }
+            p
+        } else {
+            let old_setting = self.selector;
+            self.selector = NewString;
+            let b = self.pool_ptr;
+            match self.cur_val_level {
+                IntVal => self.print_int(self.cur_val),
+                DimenVal => {
+                    self.print_scaled(self.cur_val);
+                    self.print("pt");
+                }
+                GlueVal => {
+                    self.print_spec(self.cur_val as Pointer, "pt");
+                    self.delete_glue_ref(self.cur_val as Pointer);
+                }
+                _ => {
+                    self.print_spec(self.cur_val as Pointer, "mu");
+                    self.delete_glue_ref(self.cur_val as Pointer);
+                }
+            }
+            self.selector = old_setting;
+            self.str_toks(b)
+        }
+    }
+
+    // 467
+    pub fn ins_the_toks(&mut self) {
+        let p = self.the_toks();
+        self.set_link(Garbage, p);
+        self.ins_list(self.link(TempHead));
+    }
+
+    // 470
+    // implements \number, \romannumeral, \string and \meaning
+    pub fn conv_toks(&mut self) {
+        let c = self.cur_chr;
+        // 471: scan the argument for command c
+        match c {
+            NumberCode | RomanNumeralCode => self.scan_int(),
+            FontNameCode => self.scan_font_ident(),
+            JobNameCode => {
+                if self.job_name == 0 {
+                    self.open_log_file();
+                }
+            }
+            _ => {
+                let save_scanner_status = self.scanner_status;
+                self.scanner_status = Normal;
+                self.get_token();
+                self.scanner_status = save_scanner_status;
+            }
+        }
+        let old_setting = self.selector;
+        self.selector = NewString;
+        let b = self.pool_ptr;
+        // 472: print the result of command c
+        match c {
+            NumberCode => self.print_int(self.cur_val),
+            RomanNumeralCode => self.print_roman_int(self.cur_val),
+            StringCode => {
+                if self.cur_cs != 0 {
+                    self.sprint_cs(self.cur_cs);
+                } else {
+                    self.print_char(self.cur_chr as u8);
+                }
+            }
+            FontNameCode => self.print_font_name_and_size(self.cur_val as InternalFontNumber),
+            JobNameCode => self.print_str(self.job_name),
+            _ => self.print_meaning(),
+        }
+        self.selector = old_setting;
+        let p = self.str_toks(b);
+        self.set_link(Garbage, p);
+        self.ins_list(self.link(TempHead));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::text_font;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    // the values that \showthe gives for the lines of a document, in order
+    fn shown(body: &str) -> (Vec<String>, String) {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("doc.tex", format!("\\catcode`\\{{=1 \\catcode`\\}}=2 \\font\\f=text \\f\n{}\n\\end\n", body));
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        let values = log.lines().filter(|l| l.starts_with("> ")).map(|l| l[2..].to_string()).collect();
+        (values, log)
+    }
+
+    #[test]
+    fn integer_constants() {
+        let (values, log) = shown("\\count1='777 \\showthe\\count1 \\count1=\"FF \\showthe\\count1
+\\count1=`a \\showthe\\count1 \\count1=`\\a \\showthe\\count1 \\count1=-'17 \\showthe\\count1
+\\count1=2147483647 \\showthe\\count1 \\count1=2147483648 \\showthe\\count1
+\\count1=\"8000000F \\showthe\\count1");
+        assert_eq!(values, ["511.", "255.", "97.", "97.", "-15.", "2147483647.", "2147483647.", "2147483647."], "{}", log);
+        assert_eq!(log.matches("! Number too big.").count(), 2, "{}", log);
+    }
+
+    #[test]
+    fn dimension_units() {
+        let units = ["1pt", "1pc", "1in", "1bp", "1cm", "1mm", "1dd", "1cc", "65536sp", "1em", "1ex", "-.5em",
+                     "1.5\\dimen1", "2\\count1", "1truein"];
+        let body: String = units.iter().map(|u| format!("\\dimen0={} \\showthe\\dimen0\n", u)).collect();
+        let (values, log) = shown(&format!("\\dimen1=2pt \\count1=3 \\mag=2000\n{}\\dimen0=16384pt", body));
+        assert_eq!(values, ["1.0pt.", "12.0pt.", "72.26999pt.", "1.00374pt.", "28.45274pt.", "2.84526pt.", "1.07pt.",
+                            "12.8401pt.", "1.0pt.", "10.0pt.", "5.0pt.", "-5.0pt.", "3.0pt.", "0.00009pt.", "36.135pt."],
+                   "{}", log);
+        assert!(log.contains("! Dimension too large."), "{}", log);
+    }
+}
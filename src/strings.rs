@@ -0,0 +1,141 @@
+use crate::*;
+
+// 48
+pub const EmptyString: StrNumber = 256; // the string of length zero
+
+// 40
+impl TexState {
+    // the number of characters in string number s
+    pub fn length(&self, s: StrNumber) -> usize {
+        self.str_start[s as usize + 1] - self.str_start[s as usize]
+    }
+
+    // the number of characters in the current string
+    pub fn cur_length(&self) -> usize {
+        self.pool_ptr - self.str_start[self.str_ptr as usize]
+    }
+
+    // 42
+    // put ASCII code c at the end of str_pool
+    pub fn append_char(&mut self, c: ASCIICode) {
+        self.str_pool[self.pool_ptr] = c;
+        self.pool_ptr += 1;
+    }
+
+    // forget the last character in the pool
+    pub fn flush_char(&mut self) {
+        self.pool_ptr -= 1;
+    }
+
+    // make sure that the pool hasn't overflowed
+    pub fn str_room(&mut self, n: usize) {
+        if self.pool_ptr + n > PoolSize as usize {
+            self.overflow("pool size", PoolSize - self.init_pool_ptr as i32);
+        }
+    }
+
+    // 43
+    // current string enters the pool
+    pub fn make_string(&mut self) -> StrNumber {
+        if self.str_ptr == MaxStrings {
+            self.overflow("number of strings", MaxStrings - self.init_str_ptr);
+        }
+        self.str_ptr += 1;
+        self.str_start[self.str_ptr as usize] = self.pool_ptr;
+        self.str_ptr - 1
+    }
+
+    // 44
+    // destroy the most recently made string
+    pub fn flush_string(&mut self) {
+        self.str_ptr -= 1;
+        self.pool_ptr = self.str_start[self.str_ptr as usize];
+    }
+
+    // 45
+    // test if string s matches the buffer, starting at position k
+    pub fn str_eq_buf(&self, s: StrNumber, k: usize) -> bool {
+        let mut j = self.str_start[s as usize];
+        let mut k = k;
+        while j < self.str_start[s as usize + 1] {
+            if self.str_pool[j] != self.buffer[k] {
+                return false;
+            }
+            j += 1;
+            k += 1;
+        }
+        true
+    }
+
+    // 46
+    // test if two strings are equal
+    pub fn str_eq_str(&self, s: StrNumber, t: StrNumber) -> bool {
+        if self.length(s) != self.length(t) {
+            return false;
+        }
+        let mut j = self.str_start[s as usize];
+        let mut k = self.str_start[t as usize];
+        while j < self.str_start[s as usize + 1] {
+            if self.str_pool[j] != self.str_pool[k] {
+                return false;
+            }
+            j += 1;
+            k += 1;
+        }
+        true
+    }
+
+    // 47
+    // initializes the string pool; the strings that tex.pool would supply are
+    // written as Rust literals throughout, so only the 256 character strings and
+    // the names TeX gives to the files it makes are made here
+    pub fn get_strings_started(&mut self) -> bool {
+        self.pool_ptr = 0;
+        self.str_ptr = 0;
+        self.str_start[0] = 0;
+        // 48: make the first 256 strings
+        for k in 0..=255usize {
+            if k < ' ' as usize || k > '~' as usize {
+                // 49: character k cannot be printed, use ^^ notation
+                self.append_char('^' as u8);
+                self.append_char('^' as u8);
+                if k < 64 {
+                    self.append_char(k as u8 + 64);
+                } else if k < 128 {
+                    self.append_char(k as u8 - 64);
+                } else {
+                    let hex = |l: u8| if l < 10 { l + '0' as u8 } else { l - 10 + 'a' as u8 };
+                    self.append_char(hex(k as u8 / 16));
+                    self.append_char(hex(k as u8 % 16));
+                }
+            } else {
+                self.append_char(k as u8);
+            }
+            self.make_string();
+        }
+        self.make_string(); // the empty string, EmptyString
+        for s in FileNameStrings.iter() {
+            self.make_str(s);
+        }
+        true
+    }
+
+    // copies a Rust string into the pool and returns its number; used for the
+    // names that TeX itself introduces, such as file extensions and font names
+    pub fn make_str(&mut self, s: &str) -> StrNumber {
+        self.str_room(s.len());
+        for c in s.bytes() {
+            self.append_char(c);
+        }
+        self.make_string()
+    }
+
+    // the contents of pool string s, as bytes
+    pub fn str_bytes(&self, s: StrNumber) -> Vec<u8> {
+        let mut v = Vec::with_capacity(self.length(s));
+        for k in self.str_start[s as usize]..self.str_start[s as usize + 1] {
+            v.push(self.str_pool[k]);
+        }
+        v
+    }
+}
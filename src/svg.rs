@@ -0,0 +1,176 @@
+use crate::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+// The SVG backend writes each shipped-out page to a file of its own, named
+// after the job and the page's sequence number, as in story-1.svg. The paper
+// is the page box with its \hoffset and \voffset and an inch of margin all
+// round, and the viewBox measures it in big points from its top left corner.
+// Characters of OpenType fonts are drawn from the glyph outlines in the font
+// file; other characters become text in a font family named after the font.
+
+// how the characters of a font are drawn
+enum SvgFont {
+    Outlines(OpenTypeOutlines),
+    Text,
+}
+
+// the state of the SVG output
+#[derive(Default)]
+pub struct SvgOutput {
+    fonts: BTreeMap<InternalFontNumber, SvgFont>,
+    last_file: String, // the name of the file holding the latest page
+    bytes: usize, // the number of bytes written to all the files so far
+}
+
+// text with the characters that XML treats specially escaped; control codes
+// 0--31 may not appear in an XML document even as references, so they are
+// moved into the Private Use Area at U+E000+c
+fn xml_escape(s: &[u8]) -> String {
+    let mut t = String::new();
+    for &c in s {
+        match c {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b' ' | b'-' | b'.' | b',' | b':' | b'_' => t.push(c as char),
+            0..=31 => t.push_str(&format!("&#x{:X};", 0xE000 + c as u32)),
+            _ => t.push_str(&format!("&#x{:X};", c)),
+        }
+    }
+    t
+}
+
+impl TexState {
+    // opens the file for the next page, whose name has the page number after the job name
+    fn open_svg_page(&mut self) -> Box<dyn Write> {
+        if self.job_name == 0 {
+            self.open_log_file();
+        }
+        self.pack_job_name(ExtSvg);
+        let k = self.name_of_file.len() - self.str_bytes(ExtSvg).len();
+        self.name_of_file.insert_str(k, &format!("-{}", self.total_pages + 1));
+        let file = loop {
+            if let Some(file) = self.a_open_out() {
+                break file;
+            }
+            self.prompt_file_name("file name for output", ExtSvg);
+        };
+        if self.output_file_name == 0 {
+            self.output_file_name = self.make_name_string();
+        }
+        self.svg.last_file = self.name_of_file.clone();
+        file
+    }
+
+    // how the characters of font f are drawn, looking at its font file the first time
+    fn svg_font(&mut self, f: InternalFontNumber) -> &SvgFont {
+        if !self.svg.fonts.contains_key(&f) {
+            let mut font = SvgFont::Text;
+            if is_opentype_name(&self.str_bytes(self.font_name[f])) {
+                if let Some(o) = self.read_font_file(f, EmptyString, FileKind::OpenType).and_then(|d| opentype_outlines(&d)) {
+                    font = SvgFont::Outlines(o);
+                }
+            }
+            self.svg.fonts.insert(f, font);
+        }
+        &self.svg.fonts[&f]
+    }
+
+    // 640
+    // ships box p out as an SVG file
+    pub fn svg_ship_out(&mut self, p: Pointer) {
+        if self.total_pages == 0 {
+            self.prepare_mag(); // the magnification is frozen as the first page goes out
+        }
+        let mut file = self.open_svg_page();
+        let (pw, ph) = self.page_size(p);
+        let items = self.page_items(p);
+        let mut body = String::new();
+        let mut glyphs = BTreeSet::new(); // the outlines the page uses
+        for item in items {
+            match item {
+                PageItem::Char { f, c, h, v } => {
+                    let x = format_bp(self.page_bp(OneInch + h));
+                    let y = format_bp(self.page_bp(OneInch + v));
+                    let size = self.page_bp(self.font_size[f]);
+                    let family = xml_escape(&self.str_bytes(self.font_name[f]));
+                    match self.svg_font(f) {
+                        SvgFont::Outlines(o) => {
+                            if o.paths.contains_key(&c) {
+                                let s = format_bp(size / o.units_per_em as f64);
+                                body.push_str(&format!("<use xlink:href=\"#f{}-{}\" transform=\"translate({} {}) scale({} -{})\"/>\n",
+                                                       f, c, x, y, s, s));
+                                glyphs.insert((f, c));
+                            }
+                        }
+                        SvgFont::Text => {
+                            body.push_str(&format!("<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\">{}</text>\n",
+                                                   x, y, family, format_bp(size), xml_escape(&[c])));
+                        }
+                    }
+                }
+                PageItem::Rule { h, v, width, height } => {
+                    body.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                                           format_bp(self.page_bp(OneInch + h)), format_bp(self.page_bp(OneInch + v - height)),
+                                           format_bp(self.page_bp(width)), format_bp(self.page_bp(height))));
+                }
+                PageItem::Special { .. } => {} // specials are meant for other drivers
+            }
+        }
+        let (w, h) = (format_bp(self.page_bp(pw)), format_bp(self.page_bp(ph)));
+        let mut svg = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                               <svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                               width=\"{}pt\" height=\"{}pt\" viewBox=\"0 0 {} {}\">\n", w, h, w, h);
+        if !glyphs.is_empty() {
+            svg.push_str("<defs>\n");
+            for (f, c) in glyphs {
+                if let Some(SvgFont::Outlines(o)) = self.svg.fonts.get(&f) {
+                    svg.push_str(&format!("<path id=\"f{}-{}\" d=\"{}\"/>\n", f, c, o.paths[&c]));
+                }
+            }
+            svg.push_str("</defs>\n");
+        }
+        svg.push_str(&body);
+        svg.push_str("</svg>\n");
+        if file.write_all(svg.as_bytes()).and_then(|_| file.flush()).is_err() {
+            self.fatal_error("*** (cannot write on the SVG file)");
+        }
+        self.svg.bytes += svg.len();
+        self.total_pages += 1;
+    }
+
+    // 642
+    // reports on the SVG files
+    pub fn finish_svg_files(&mut self) {
+        if self.total_pages == 0 {
+            self.print_nl("No pages of output.");
+            return;
+        }
+        self.print_nl("Output written on ");
+        self.slow_print(self.output_file_name);
+        if self.total_pages > 1 {
+            self.print(" to ");
+            let last = self.svg.last_file.clone();
+            self.print(&last);
+        }
+        self.print(" (");
+        self.print_int(self.total_pages);
+        self.print(" page");
+        if self.total_pages != 1 {
+            self.print_char('s' as u8);
+        }
+        self.print(", ");
+        self.print_int(self.svg.bytes as i32);
+        self.print(" bytes).");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::xml_escape;
+
+    #[test]
+    fn control_codes_become_private_use_characters() {
+        assert_eq!(xml_escape(b"cmr10"), "cmr10");
+        assert_eq!(xml_escape(b"<&>"), "&#x3C;&#x26;&#x3E;");
+        assert_eq!(xml_escape(&[0, 9, 31, 127, 200]), "&#xE000;&#xE009;&#xE01F;&#x7F;&#xC8;");
+    }
+}
@@ -0,0 +1,397 @@
+// font metric files made up for the tests, since no real fonts come with the
+// sources; dimensions are given in units of the design size, as in a TFM file
+
+use std::collections::BTreeMap;
+
+// one step of a character's ligature/kerning program
+#[derive(Clone, Copy)]
+pub enum LigKern {
+    Kern(u8, f64), // a kern before the next character
+    Lig(u8, u8), // the next character and this one become the given ligature: =:
+}
+
+#[derive(Clone, Default)]
+struct TfmChar {
+    width: f64,
+    height: f64,
+    depth: f64,
+    italic: f64,
+    program: Vec<LigKern>,
+    next_larger: Option<u8>,
+}
+
+// builds a TFM file; param(1) is the slant, param(5) the x-height, param(6) the quad
+pub struct Tfm {
+    design_size: f64,
+    chars: BTreeMap<u8, TfmChar>,
+    params: Vec<f64>,
+}
+
+impl Tfm {
+    // a font with the given design size in points and the seven text parameters
+    // slant, space, stretch, shrink, x-height, quad and extra space
+    pub fn new(design_size: f64, params: &[f64]) -> Tfm {
+        Tfm { design_size, chars: BTreeMap::new(), params: params.to_vec() }
+    }
+
+    pub fn char(mut self, c: u8, width: f64, height: f64, depth: f64) -> Tfm {
+        self.chars.insert(c, TfmChar { width, height, depth, ..TfmChar::default() });
+        self
+    }
+
+    pub fn italic(mut self, c: u8, italic: f64) -> Tfm {
+        self.chars.get_mut(&c).unwrap().italic = italic;
+        self
+    }
+
+    pub fn lig_kern(mut self, c: u8, program: &[LigKern]) -> Tfm {
+        self.chars.get_mut(&c).unwrap().program = program.to_vec();
+        self
+    }
+
+    // makes c the first of a chain of larger and larger characters, as delimiters have
+    pub fn next_larger(mut self, c: u8, larger: u8) -> Tfm {
+        self.chars.get_mut(&c).unwrap().next_larger = Some(larger);
+        self
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let fix = |x: f64| ((x * (1 << 20) as f64).round() as i32).to_be_bytes();
+        // the widths, heights, depths and italic corrections, each table starting with zero
+        let mut tables: [Vec<f64>; 4] = [vec![0.0], vec![0.0], vec![0.0], vec![0.0]];
+        // a character with width index zero doesn't exist, even when its width is zero
+        let mut index = |t: usize, x: f64| match tables[t].iter().skip((t == 0) as usize).position(|&y| y == x) {
+            Some(k) => k + (t == 0) as usize,
+            None => {
+                tables[t].push(x);
+                tables[t].len() - 1
+            }
+        };
+        let mut lig_kern: Vec<[u8; 4]> = Vec::new();
+        let mut kerns: Vec<f64> = Vec::new();
+        let (bc, ec) = match (self.chars.keys().next(), self.chars.keys().last()) {
+            (Some(&bc), Some(&ec)) => (bc, ec),
+            _ => (1, 0),
+        };
+        let mut char_info = Vec::new();
+        for c in bc as u16..=ec as u16 {
+            let info = match self.chars.get(&(c as u8)) {
+                None => [0, 0, 0, 0],
+                Some(ch) => {
+                    let (w, h, d, i) = (index(0, ch.width), index(1, ch.height), index(2, ch.depth), index(3, ch.italic));
+                    let (tag, rem) = if !ch.program.is_empty() {
+                        let start = lig_kern.len() as u8;
+                        for (k, step) in ch.program.iter().enumerate() {
+                            let skip = if k + 1 == ch.program.len() { 128 } else { 0 };
+                            lig_kern.push(match *step {
+                                LigKern::Kern(next, x) => {
+                                    kerns.push(x);
+                                    [skip, next, 128, kerns.len() as u8 - 1]
+                                }
+                                LigKern::Lig(next, lig) => [skip, next, 0, lig],
+                            });
+                        }
+                        (1, start)
+                    } else if let Some(larger) = ch.next_larger {
+                        (2, larger)
+                    } else {
+                        (0, 0)
+                    };
+                    [w as u8, (h << 4 | d) as u8, (i << 2 | tag) as u8, rem]
+                }
+            };
+            char_info.push(info);
+        }
+        let lh = 2;
+        let lf = 6 + lh + char_info.len() + tables.iter().map(|t| t.len()).sum::<usize>() + lig_kern.len()
+            + kerns.len() + self.params.len();
+        let mut tfm = Vec::new();
+        for &h in &[lf, lh, bc as usize, ec as usize, tables[0].len(), tables[1].len(), tables[2].len(),
+                    tables[3].len(), lig_kern.len(), kerns.len(), 0, self.params.len()] {
+            tfm.extend_from_slice(&(h as u16).to_be_bytes());
+        }
+        tfm.extend_from_slice(&0x1234_5678u32.to_be_bytes()); // the checksum
+        tfm.extend_from_slice(&fix(self.design_size)); // the design size, in points
+        for info in &char_info {
+            tfm.extend_from_slice(info);
+        }
+        for t in &tables {
+            for &x in t {
+                tfm.extend_from_slice(&fix(x));
+            }
+        }
+        for step in &lig_kern {
+            tfm.extend_from_slice(step);
+        }
+        for &x in kerns.iter().chain(self.params.iter()) {
+            tfm.extend_from_slice(&fix(x));
+        }
+        tfm
+    }
+}
+
+// a 10pt text font with the letters a to z and A to Z, digits and some punctuation,
+// all half an em wide and 0.7em high, with a space of a third of an em
+pub fn text_font() -> Tfm {
+    let mut tfm = Tfm::new(10.0, &[0.0, 1.0 / 3.0, 1.0 / 6.0, 1.0 / 9.0, 0.5, 1.0, 1.0 / 9.0]);
+    for c in (b'a'..=b'z').chain(b'A'..=b'Z').chain(b'0'..=b'9') {
+        tfm = tfm.char(c, 0.5, 0.7, 0.0);
+    }
+    for &c in b".,-=+()" {
+        tfm = tfm.char(c, 0.25, 0.7, 0.0);
+    }
+    tfm
+}
+
+// a 10pt math symbol font, with the 22 parameters of family 2: num1, num2, num3,
+// denom1, denom2, sup1, sup2, sup3, sub1, sub2, sup_drop, sub_drop, delim1,
+// delim2 and axis_height follow the seven text parameters; "70 is a radical sign
+// that continues in the extension font
+pub fn math_symbols() -> Tfm {
+    Tfm::new(10.0, &[0.0, 0.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.7, 0.4, 0.45, 0.7, 0.35, 0.4, 0.35, 0.3, 0.15,
+                     0.25, 0.4, 0.05, 2.4, 1.0, 0.25])
+        .char(0x70, 0.8, 0.04, 0.76)
+}
+
+// a 10pt math extension font, with default_rule_thickness and the five
+// big_op_spacing parameters of family 3; "70 is a radical sign 1.5em tall,
+// followed by one 2.5em tall
+pub fn math_extension() -> Tfm {
+    Tfm::new(10.0, &[0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.04, 0.1, 0.15, 0.2, 0.6, 0.1])
+        .char(0x70, 1.0, 0.04, 1.46)
+        .char(0x71, 1.0, 0.04, 2.46)
+        .next_larger(0x70, 0x71)
+}
+
+// builds a TrueType font with no outlines: just the glyph advances, a byte
+// encoding cmap, and GSUB ligatures and GPOS kerns for the standard features
+pub struct Otf {
+    units_per_em: u16,
+    glyphs: Vec<(Option<u8>, u16)>, // the character mapped to each glyph, and its advance
+    ligatures: Vec<(Vec<u8>, u16)>, // components and ligature glyph
+    kerns: Vec<(u8, u8, i16)>,
+    math_constants: Vec<(usize, i16)>, // math value records of a MATH table
+}
+
+fn be16(v: &mut Vec<u8>, x: u16) {
+    v.extend_from_slice(&x.to_be_bytes());
+}
+
+// a coverage table of format 1 for glyphs in ascending order
+fn coverage(glyphs: &[u16]) -> Vec<u8> {
+    let mut t = Vec::new();
+    be16(&mut t, 1);
+    be16(&mut t, glyphs.len() as u16);
+    for &g in glyphs {
+        be16(&mut t, g);
+    }
+    t
+}
+
+// a GSUB or GPOS table with one feature that uses one lookup with one subtable
+// of the given type; the subtable is given offsets relative to its own start
+fn layout_table(feature: &[u8; 4], lookup_type: u16, subtable: &[u8]) -> Vec<u8> {
+    let mut t = Vec::new();
+    be16(&mut t, 1); // version 1.0
+    be16(&mut t, 0);
+    for &off in &[10u16, 12, 26] {
+        be16(&mut t, off); // the script, feature and lookup lists
+    }
+    be16(&mut t, 0); // no scripts
+    be16(&mut t, 1); // one feature
+    t.extend_from_slice(feature);
+    be16(&mut t, 8); // the feature table follows its record
+    for &x in &[0u16, 1, 0] {
+        be16(&mut t, x); // no parameters, lookup 0
+    }
+    be16(&mut t, 1); // one lookup
+    be16(&mut t, 4);
+    for &x in &[lookup_type, 0, 1, 8] {
+        be16(&mut t, x); // the lookup, and the offset of its subtable
+    }
+    t.extend_from_slice(subtable);
+    t
+}
+
+impl Otf {
+    pub fn new(units_per_em: u16) -> Otf {
+        Otf { units_per_em, glyphs: vec![(None, 0)], ligatures: Vec::new(), kerns: Vec::new(), math_constants: Vec::new() }
+    }
+
+    pub fn glyph(mut self, c: u8, advance: u16) -> Otf {
+        self.glyphs.push((Some(c), advance));
+        self
+    }
+
+    // an unencoded glyph that the liga feature makes of the components
+    pub fn ligature(mut self, components: &[u8], advance: u16) -> Otf {
+        self.glyphs.push((None, advance));
+        self.ligatures.push((components.to_vec(), self.glyphs.len() as u16 - 1));
+        self
+    }
+
+    pub fn kern(mut self, c: u8, d: u8, x: i16) -> Otf {
+        self.kerns.push((c, d, x));
+        self
+    }
+
+    // gives the font a MATH table, in which math value record k of the
+    // MathConstants table is x
+    pub fn math_constant(mut self, k: usize, x: i16) -> Otf {
+        self.math_constants.push((k, x));
+        self
+    }
+
+    fn glyph_of(&self, c: u8) -> u16 {
+        self.glyphs.iter().position(|&(d, _)| d == Some(c)).unwrap() as u16
+    }
+
+    fn gsub(&self) -> Vec<u8> {
+        // a LigatureSubst subtable of format 1
+        let mut firsts: Vec<u16> = self.ligatures.iter().map(|(c, _)| self.glyph_of(c[0])).collect();
+        firsts.sort();
+        firsts.dedup();
+        let mut sets = Vec::new();
+        let mut set_offsets = Vec::new();
+        let head = 6 + 2 * firsts.len();
+        let cov = coverage(&firsts);
+        for &first in &firsts {
+            set_offsets.push((head + cov.len() + sets.len()) as u16);
+            let ligs: Vec<&(Vec<u8>, u16)> = self.ligatures.iter().filter(|(c, _)| self.glyph_of(c[0]) == first).collect();
+            let mut set = Vec::new();
+            be16(&mut set, ligs.len() as u16);
+            let mut body = Vec::new();
+            for (comps, g) in ligs.iter() {
+                be16(&mut set, (2 + 2 * ligs.len() + body.len()) as u16);
+                be16(&mut body, *g);
+                be16(&mut body, comps.len() as u16);
+                for &c in &comps[1..] {
+                    be16(&mut body, self.glyph_of(c));
+                }
+            }
+            sets.extend_from_slice(&set);
+            sets.extend_from_slice(&body);
+        }
+        let mut s = Vec::new();
+        be16(&mut s, 1);
+        be16(&mut s, head as u16);
+        be16(&mut s, firsts.len() as u16);
+        for off in set_offsets {
+            be16(&mut s, off);
+        }
+        s.extend_from_slice(&cov);
+        s.extend_from_slice(&sets);
+        layout_table(b"liga", 4, &s)
+    }
+
+    fn gpos(&self) -> Vec<u8> {
+        // a PairPos subtable of format 1, with x advances for the first glyphs
+        let mut firsts: Vec<u16> = self.kerns.iter().map(|&(c, _, _)| self.glyph_of(c)).collect();
+        firsts.sort();
+        firsts.dedup();
+        let head = 10 + 2 * firsts.len();
+        let cov = coverage(&firsts);
+        let mut sets = Vec::new();
+        let mut set_offsets = Vec::new();
+        for &first in &firsts {
+            set_offsets.push((head + cov.len() + sets.len()) as u16);
+            let pairs: Vec<&(u8, u8, i16)> = self.kerns.iter().filter(|&&(c, _, _)| self.glyph_of(c) == first).collect();
+            be16(&mut sets, pairs.len() as u16);
+            for &&(_, d, x) in pairs.iter() {
+                be16(&mut sets, self.glyph_of(d));
+                be16(&mut sets, x as u16);
+            }
+        }
+        let mut s = Vec::new();
+        for &x in &[1, head as u16, 4, 0, firsts.len() as u16] {
+            be16(&mut s, x);
+        }
+        for off in set_offsets {
+            be16(&mut s, off);
+        }
+        s.extend_from_slice(&cov);
+        s.extend_from_slice(&sets);
+        layout_table(b"kern", 2, &s)
+    }
+
+    fn math(&self) -> Vec<u8> {
+        // the header, with MathConstants and no glyph info or variants, then
+        // the four scalars, the 51 math value records and the radical degree percentage
+        let mut constants = vec![0u8; 8 + 4 * 51 + 2];
+        for &(k, x) in &self.math_constants {
+            constants[8 + 4 * k..10 + 4 * k].copy_from_slice(&x.to_be_bytes());
+        }
+        let mut m = Vec::new();
+        m.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        for &x in &[10u16, 0, 0] {
+            be16(&mut m, x);
+        }
+        m.extend_from_slice(&constants);
+        m
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let n = self.glyphs.len() as u16;
+        let mut head = vec![0u8; 54];
+        head[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        head[12..16].copy_from_slice(&0x5F0F_3CF5u32.to_be_bytes());
+        head[18..20].copy_from_slice(&self.units_per_em.to_be_bytes());
+        let mut hhea = vec![0u8; 36];
+        hhea[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        hhea[34..36].copy_from_slice(&n.to_be_bytes());
+        let mut maxp = Vec::new();
+        maxp.extend_from_slice(&0x0000_5000u32.to_be_bytes());
+        be16(&mut maxp, n);
+        let mut hmtx = Vec::new();
+        for &(_, advance) in &self.glyphs {
+            be16(&mut hmtx, advance);
+            be16(&mut hmtx, 0);
+        }
+        // a byte encoding table for the Unicode platform
+        let mut cmap = Vec::new();
+        for &x in &[0u16, 1, 0, 3] {
+            be16(&mut cmap, x);
+        }
+        cmap.extend_from_slice(&12u32.to_be_bytes());
+        for &x in &[0u16, 262, 0] {
+            be16(&mut cmap, x);
+        }
+        let mut map = [0u8; 256];
+        for (g, &(c, _)) in self.glyphs.iter().enumerate() {
+            if let Some(c) = c {
+                map[c as usize] = g as u8;
+            }
+        }
+        cmap.extend_from_slice(&map);
+        let mut tables: Vec<(&[u8; 4], Vec<u8>)> = vec![(b"cmap", cmap), (b"head", head), (b"hhea", hhea),
+                                                         (b"hmtx", hmtx), (b"maxp", maxp)];
+        if !self.kerns.is_empty() {
+            tables.insert(0, (b"GPOS", self.gpos()));
+        }
+        if !self.ligatures.is_empty() {
+            tables.insert(0, (b"GSUB", self.gsub()));
+        }
+        if !self.math_constants.is_empty() {
+            tables.insert(0, (b"MATH", self.math()));
+        }
+        tables.sort_by_key(|(tag, _)| **tag);
+        let mut font = Vec::new();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        for &x in &[tables.len() as u16, 0, 0, 0] {
+            be16(&mut font, x);
+        }
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, t) in &tables {
+            font.extend_from_slice(&tag[..]);
+            font.extend_from_slice(&0u32.to_be_bytes());
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(t.len() as u32).to_be_bytes());
+            offset += t.len().div_ceil(4) * 4;
+        }
+        for (_, t) in &tables {
+            font.extend_from_slice(t);
+            font.resize(font.len().div_ceil(4) * 4, 0);
+        }
+        font
+    }
+}
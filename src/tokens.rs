@@ -0,0 +1,270 @@
+use crate::*;
+
+// 445
+pub const zero_token: i32 = other_token + 48; // zero, the smallest digit
+
+impl TexState {
+    // 292
+    // displays the token list starting at p, stopping after about l characters;
+    // q marks the place where the second line of show_context begins
+    pub fn show_token_list(&mut self, p: i32, q: i32, l: i32) {
+        let mut p = p;
+        let mut match_chr = '#' as u8;
+        let mut n = '0' as u8;
+        self.tally = 0;
+        while p != Null as i32 && self.tally < l {
+            if p == q {
+                // 320: do magic computation
+                self.set_trick_count();
+            }
+            // 293: display token p, and return if there are problems
+            if p < self.hi_mem_min as i32 || p > self.mem_end as i32 {
+                self.print_esc("CLOBBERED.");
+                return;
+            }
+            let t = self.info(p as usize) as i32;
+            if t >= cs_token_flag {
+                self.print_cs(t - cs_token_flag);
+            } else {
+                let m = (t / 0o400) as QuarterWord;
+                let c = (t % 0o400) as ASCIICode;
+                match m {
+                    LeftBrace | RightBrace | MathShift | TabMark | SupMark | SubMark | Spacer
+                    | Letter | OtherChar => self.print_str(c as StrNumber),
+                    MacParam => {
+                        self.print_str(c as StrNumber);
+                        self.print_str(c as StrNumber);
+                    }
+                    OutParam => {
+                        self.print_str(match_chr as StrNumber);
+                        if c <= 9 {
+                            self.print_char(c + '0' as u8);
+                        } else {
+                            self.print_char('!' as u8);
+                            return;
+                        }
+                    }
+                    Match => {
+                        match_chr = c;
+                        self.print_str(c as StrNumber);
+                        n += 1;
+                        self.print_char(n);
+                        if n > '9' as u8 {
+                            return;
+                        }
+                    }
+                    EndMatch => self.print("->"),
+                    _ => self.print_esc("BAD."),
+                }
+            }
+            p = self.link(p as usize) as i32;
+        }
+        if p != Null as i32 {
+            self.print_esc("ETC.");
+        }
+    }
+
+    // 295
+    // displays a token list with its reference count omitted
+    pub fn token_show(&mut self, p: Pointer) {
+        if p != Null {
+            self.show_token_list(self.link(p as usize) as i32, Null as i32, 10000000);
+        }
+    }
+
+    // 296
+    // displays cur_cmd and cur_chr in symbolic form, including the expansion of a macro or mark
+    pub fn print_meaning(&mut self) {
+        self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+        if self.cur_cmd >= Call {
+            self.print_char(':' as u8);
+            self.print_ln();
+            self.token_show(self.cur_chr);
+        } else if self.cur_cmd == TopBotMark && (self.cur_chr as usize) < MarksCode {
+            self.print_char(':' as u8);
+            self.print_ln();
+            self.token_show(self.cur_mark[self.cur_chr as usize]);
+        }
+    }
+
+    // 299
+    // display the current command and character, with the mode if it has changed
+    pub fn show_cur_cmd_chr(&mut self) {
+        self.begin_diagnostic();
+        self.print_nl("{");
+        if self.mode() != self.shown_mode {
+            self.print_mode(self.mode());
+            self.print(": ");
+            self.shown_mode = self.mode();
+        }
+        self.print_cmd_chr(self.cur_cmd, self.cur_chr);
+        self.print_char('}' as u8);
+        self.end_diagnostic(false);
+    }
+
+    // 464
+    // converts str_pool[b..pool_ptr] to a token list, returning a pointer to its tail
+    pub fn str_toks(&mut self, b: PoolPointer) -> Pointer {
+        self.str_room(1);
+        let mut p = TempHead as Pointer;
+        self.set_link(p as usize, Null);
+        let mut k = b;
+        while k < self.pool_ptr {
+            let mut t = self.str_pool[k] as HalfWord;
+            if t == ' ' as HalfWord {
+                t = space_token as HalfWord;
+            } else {
+                t += other_token as HalfWord;
+            }
+            // fast_store_new_token(t)
+            let q = self.get_avail();
+            self.set_link(p as usize, q);
+            self.set_info(q as usize, t);
+            p = q;
+            k += 1;
+        }
+        self.pool_ptr = b;
+        p
+    }
+
+    // 473
+    // scans a balanced text, macro_def selects a \def-style parameter text
+    // and xpand selects \edef-style expansion; returns a pointer to the tail
+    pub fn scan_toks(&mut self, macro_def: bool, xpand: bool) -> Pointer {
+        if macro_def {
+            self.scanner_status = Defining;
+        } else {
+            self.scanner_status = Absorbing;
+        }
+        self.warning_index = self.cur_cs;
+        self.def_ref = self.get_avail();
+        self.set_token_ref_count(self.def_ref as usize, Null);
+        let mut p = self.def_ref;
+        let mut hash_brace = 0;
+        let mut t = zero_token;
+        let mut unbalance;
+        'found: {
+            if macro_def {
+                // 474: scan and build the parameter part of the macro definition
+                loop {
+                    self.get_token(); // set cur_cmd, cur_chr, cur_tok
+                    if (self.cur_tok as i32) < right_brace_limit {
+                        break;
+                    }
+                    if self.cur_cmd == MacParam {
+                        // 476: if the next character is a parameter number, make cur_tok a
+                        // match token; but if it is a left brace, store `left_brace, end_match',
+                        // set hash_brace, and goto done
+                        let s = match_token + self.cur_chr as i32;
+                        self.get_token();
+                        if self.cur_cmd == LeftBrace {
+                            hash_brace = self.cur_tok;
+                            p = self.store_new_token(p, self.cur_tok);
+                            p = self.store_new_token(p, end_match_token as HalfWord);
+                            break 'found;
+                        }
+                        if t == zero_token + 9 {
+                            self.print_err("You already have nine parameters");
+                            self.help(&["I'm going to ignore the # sign you just used."]);
+                            self.error();
+                        } else {
+                            t += 1;
+                            if self.cur_tok as i32 != t {
+                                self.print_err("Parameters must be numbered consecutively");
+                                self.help(&["I've inserted the digit you should have used after the #.",
+                                    "Type `1' to delete what you did use."]);
+                                self.back_error();
+                            }
+                            self.cur_tok = s as HalfWord;
+                        }
+                    }
+                    p = self.store_new_token(p, self.cur_tok);
+                }
+                p = self.store_new_token(p, end_match_token as HalfWord);
+                if self.cur_cmd == RightBrace {
+                    // 475: express shock at the missing left brace; goto found
+                    self.print_err("Missing { inserted");
+                    self.align_state += 1;
+                    self.help(&["Where was the left brace? You said something like `\\def\\a}',",
+                        "which I'm going to interpret as `\\def\\a{}'."]);
+                    self.error();
+                    self.scanner_status = Normal;
+                    return p;
+                }
+            } else {
+                self.scan_left_brace(); // remove the compulsory left brace
+            }
+        }
+        // 477: scan and build the body of the token list; goto found when finished
+        unbalance = 1;
+        loop {
+            if xpand {
+                // 478: expand the next part of the input
+                loop {
+                    self.get_next();
+                    if self.cur_cmd <= MaxCommand {
+                        break;
+                    }
+                    if self.cur_cmd != The {
+                        self.expand();
+                    } else {
+                        let q = self.the_toks();
+                        if self.link(TempHead) != Null {
+                            self.set_link(p as usize, self.link(TempHead));
+                            p = q;
+                        }
+                    }
+                }
+                self.x_token();
+            } else {
+                self.get_token();
+            }
+            if (self.cur_tok as i32) < right_brace_limit {
+                if self.cur_cmd < RightBrace {
+                    unbalance += 1;
+                } else {
+                    unbalance -= 1;
+                    if unbalance == 0 {
+                        break;
+                    }
+                }
+            } else if self.cur_cmd == MacParam && macro_def {
+                // 479: look for parameter number or ##
+                let s = self.cur_tok;
+                if xpand {
+                    self.get_x_token();
+                } else {
+                    self.get_token();
+                }
+                if self.cur_cmd != MacParam {
+                    if (self.cur_tok as i32) <= zero_token || self.cur_tok as i32 > t {
+                        self.print_err("Illegal parameter number in definition of ");
+                        self.sprint_cs(self.warning_index);
+                        self.help(&["You meant to type ## instead of #, right?",
+                            "Or maybe a } was forgotten somewhere earlier, and things",
+                            "are all screwed up? I'm going to assume that you meant ##."]);
+                        self.back_error();
+                        self.cur_tok = s;
+                    } else {
+                        self.cur_tok = (out_param_token - '0' as i32 + self.cur_chr as i32) as HalfWord;
+                    }
+                }
+            }
+            p = self.store_new_token(p, self.cur_tok);
+        }
+        self.scanner_status = Normal;
+        if hash_brace != 0 {
+            p = self.store_new_token(p, hash_brace);
+        }
+        p
+    }
+
+    // 371
+    // appends token t after p, returning the new tail
+    pub fn store_new_token(&mut self, p: Pointer, t: HalfWord) -> Pointer {
+        let q = self.get_avail();
+        self.set_link(p as usize, q);
+        self.set_info(q as usize, t);
+        q
+    }
+}
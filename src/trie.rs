@@ -0,0 +1,508 @@
+use crate::*;
+
+// 920
+pub type TriePointer = usize; // an index into trie
+
+impl TexState {
+    // 921
+    // "downward" link in a trie
+    pub fn trie_link(&self, p: TriePointer) -> HalfWord {
+        self.trie[p].rh()
+    }
+
+    pub fn set_trie_link(&mut self, p: TriePointer, value: HalfWord) {
+        self.trie[p].set_rh(value);
+    }
+
+    // character matched at this trie location
+    pub fn trie_char(&self, p: TriePointer) -> QuarterWord {
+        self.trie[p].b1()
+    }
+
+    pub fn set_trie_char(&mut self, p: TriePointer, value: QuarterWord) {
+        self.trie[p].set_b1(value);
+    }
+
+    // program for hyphenation at this trie location
+    pub fn trie_op(&self, p: TriePointer) -> QuarterWord {
+        self.trie[p].b0()
+    }
+
+    pub fn set_trie_op(&mut self, p: TriePointer, value: QuarterWord) {
+        self.trie[p].set_b0(value);
+    }
+
+    // 950
+    // backward links in trie holes
+    pub fn trie_back(&self, p: TriePointer) -> HalfWord {
+        self.trie[p].lh()
+    }
+
+    pub fn set_trie_back(&mut self, p: TriePointer, value: HalfWord) {
+        self.trie[p].set_lh(value);
+    }
+
+    // 944
+    // the op code for the quadruple (d,n,v,cur_lang), creating it if necessary
+    fn new_trie_op(&mut self, d: SmallNumber, n: SmallNumber, v: QuarterWord) -> QuarterWord {
+        let mut h = (n + 313 * d + 361 * v as i32 + 1009 * self.cur_lang as i32).abs() % (TrieOpSize + TrieOpSize) - TrieOpSize; // trial hash location
+        loop {
+            let l = self.trie_op_hash[(h + TrieOpSize) as usize] as usize; // pointer to stored data
+            if l == 0 {
+                // empty position found for a new op
+                if self.trie_op_ptr == TrieOpSize {
+                    self.overflow("pattern memory ops", TrieOpSize);
+                }
+                let mut u = self.trie_used[self.cur_lang as usize]; // trial op code
+                if u == MaxQuarterWord {
+                    self.overflow("pattern memory ops per language", (MaxQuarterWord - MinQuarterWord) as i32);
+                }
+                self.trie_op_ptr += 1;
+                u += 1;
+                self.trie_used[self.cur_lang as usize] = u;
+                let p = self.trie_op_ptr as usize;
+                self.hyf_distance[p] = d;
+                self.hyf_num[p] = n;
+                self.hyf_next[p] = v;
+                self.trie_op_lang[p] = self.cur_lang;
+                self.trie_op_hash[(h + TrieOpSize) as usize] = self.trie_op_ptr;
+                self.trie_op_val[p] = u;
+                return u;
+            }
+            if self.hyf_distance[l] == d && self.hyf_num[l] == n && self.hyf_next[l] == v && self.trie_op_lang[l] == self.cur_lang {
+                return self.trie_op_val[l];
+            }
+            if h > -TrieOpSize {
+                h -= 1;
+            } else {
+                h = TrieOpSize;
+            }
+        }
+    }
+
+    // 945
+    // sorts the hyphenation op tables into proper order
+    fn sort_trie_ops(&mut self) {
+        let o = TrieOpSize as usize;
+        self.op_start[0] = -(MinQuarterWord as i32);
+        for j in 1..=255 {
+            self.op_start[j] = self.op_start[j - 1] + self.trie_used[j - 1] as i32;
+        }
+        for j in 1..=self.trie_op_ptr as usize {
+            self.trie_op_hash[j + o] = self.op_start[self.trie_op_lang[j] as usize] + self.trie_op_val[j] as i32; // destination
+        }
+        for j in 1..=self.trie_op_ptr as usize {
+            while self.trie_op_hash[j + o] > j as i32 {
+                let k = self.trie_op_hash[j + o] as usize;
+                let t = self.hyf_distance[k];
+                self.hyf_distance[k] = self.hyf_distance[j];
+                self.hyf_distance[j] = t;
+                let t = self.hyf_num[k];
+                self.hyf_num[k] = self.hyf_num[j];
+                self.hyf_num[j] = t;
+                let t = self.hyf_next[k];
+                self.hyf_next[k] = self.hyf_next[j];
+                self.hyf_next[j] = t;
+                self.trie_op_hash[j + o] = self.trie_op_hash[k + o];
+                self.trie_op_hash[k + o] = k as i32;
+            }
+        }
+    }
+
+    // 948
+    // converts the trie rooted at p into an equivalent one that has been seen before
+    fn trie_node(&mut self, p: TriePointer) -> TriePointer {
+        let mut h = ((self.trie_c[p] as i32 + 1009 * self.trie_o[p] as i32 + 2718 * self.trie_l[p] as i32 + 3142 * self.trie_r[p] as i32).abs() % TrieSize) as usize; // trial hash location
+        loop {
+            let q = self.trie_hash[h]; // trial trie node
+            if q == 0 {
+                self.trie_hash[h] = p;
+                return p;
+            }
+            if self.trie_c[q] == self.trie_c[p] && self.trie_o[q] == self.trie_o[p] && self.trie_l[q] == self.trie_l[p] && self.trie_r[q] == self.trie_r[p] {
+                return q;
+            }
+            if h > 0 {
+                h -= 1;
+            } else {
+                h = TrieSize as usize;
+            }
+        }
+    }
+
+    // 949
+    // compresses the trie rooted at p by identifying equivalent subtries
+    fn compress_trie(&mut self, p: TriePointer) -> TriePointer {
+        if p == 0 {
+            return 0;
+        }
+        let l = self.compress_trie(self.trie_l[p]);
+        self.trie_l[p] = l;
+        let r = self.compress_trie(self.trie_r[p]);
+        self.trie_r[p] = r;
+        self.trie_node(p)
+    }
+
+    // 953
+    // finds a place in trie for the family beginning at p and records it in trie_ref[p]
+    fn first_fit(&mut self, p: TriePointer) {
+        let c = self.trie_c[p] as usize; // smallest character in the family
+        let mut z = self.trie_min[c]; // runs through holes
+        let h; // candidate for trie_ref[p]
+        loop {
+            let trial = z - c;
+            // 954: ensure that trie_max >= h+256
+            if self.trie_max < trial + 256 {
+                if TrieSize as usize <= trial + 256 {
+                    self.overflow("pattern memory", TrieSize);
+                }
+                loop {
+                    self.trie_max += 1;
+                    self.trie_taken[self.trie_max] = false;
+                    self.set_trie_link(self.trie_max, (self.trie_max + 1) as HalfWord);
+                    self.set_trie_back(self.trie_max, (self.trie_max - 1) as HalfWord);
+                    if self.trie_max == trial + 256 {
+                        break;
+                    }
+                }
+            }
+            // 955: if all characters of the family fit relative to h, stop
+            if !self.trie_taken[trial] {
+                let mut q = self.trie_r[p];
+                while q > 0 && self.trie_link(trial + self.trie_c[q] as usize) != 0 {
+                    q = self.trie_r[q];
+                }
+                if q == 0 {
+                    h = trial;
+                    break;
+                }
+            }
+            // not_found:
+            z = self.trie_link(z) as usize; // move to the next hole
+        }
+        // 956: pack the family into trie relative to h
+        self.trie_taken[h] = true;
+        self.trie_hash[p] = h; // trie_ref
+        let mut q = p;
+        loop {
+            let z = h + self.trie_c[q] as usize;
+            let mut l = self.trie_back(z) as usize;
+            let r = self.trie_link(z);
+            self.set_trie_back(r as usize, l as HalfWord);
+            self.set_trie_link(l, r);
+            self.set_trie_link(z, 0);
+            if l < 256 {
+                let ll = if z < 256 { z } else { 256 };
+                loop {
+                    self.trie_min[l] = r as TriePointer;
+                    l += 1;
+                    if l == ll {
+                        break;
+                    }
+                }
+            }
+            q = self.trie_r[q];
+            if q == 0 {
+                break;
+            }
+        }
+    }
+
+    // 957
+    // packs the families of the subtries rooted at p
+    fn trie_pack(&mut self, p: TriePointer) {
+        let mut p = p;
+        loop {
+            let q = self.trie_l[p];
+            if q > 0 && self.trie_hash[q] == 0 {
+                self.first_fit(q);
+                self.trie_pack(q);
+            }
+            p = self.trie_r[p];
+            if p == 0 {
+                break;
+            }
+        }
+    }
+
+    // 959
+    // moves p and its siblings into their final positions in trie
+    fn trie_fix(&mut self, p: TriePointer) {
+        let z = self.trie_hash[p]; // trie_ref[p]
+        let mut p = p;
+        loop {
+            let q = self.trie_l[p];
+            let c = self.trie_c[p];
+            let loc = z + c as usize;
+            self.set_trie_link(loc, self.trie_hash[q] as HalfWord);
+            self.set_trie_char(loc, c);
+            self.set_trie_op(loc, self.trie_o[p]);
+            if q > 0 {
+                self.trie_fix(q);
+            }
+            p = self.trie_r[p];
+            if p == 0 {
+                break;
+            }
+        }
+    }
+
+    // 960
+    // enters the patterns of a \patterns list into the linked trie; a trie that
+    // has already been packed is unpacked first, so that patterns for further
+    // languages can be loaded while typesetting
+    pub fn new_patterns(&mut self) {
+        if !self.trie_not_ready {
+            self.unpack_trie();
+        }
+        self.set_cur_lang();
+        self.scan_left_brace(); // a left brace must follow \patterns
+        // 961: enter all of the patterns into a linked trie, until coming to a right brace
+        let mut k: usize = 0;
+        self.hyf[0] = 0;
+        let mut digit_sensed = false;
+        loop {
+            self.get_x_token();
+            match self.cur_cmd {
+                Letter | OtherChar => {
+                    // 962: append a new letter or a hyphen level
+                    if digit_sensed || self.cur_chr < '0' as HalfWord || self.cur_chr > '9' as HalfWord {
+                        if self.cur_chr == '.' as HalfWord {
+                            self.cur_chr = 0; // edge-of-word delimiter
+                        } else {
+                            self.cur_chr = self.lc_code(self.cur_chr);
+                            if self.cur_chr == 0 {
+                                self.print_err("Nonletter");
+                                self.help(&["(See Appendix H.)"]);
+                                self.error();
+                            }
+                        }
+                        if k < 63 {
+                            k += 1;
+                            self.hc[k] = self.cur_chr;
+                            self.hyf[k] = 0;
+                            digit_sensed = false;
+                        }
+                    } else if k < 63 {
+                        self.hyf[k] = (self.cur_chr - '0' as HalfWord) as u8;
+                        digit_sensed = true;
+                    }
+                }
+                Spacer | RightBrace => {
+                    if k > 0 {
+                        self.insert_pattern(k);
+                    }
+                    if self.cur_cmd == RightBrace {
+                        break;
+                    }
+                    k = 0;
+                    self.hyf[0] = 0;
+                    digit_sensed = false;
+                }
+                _ => {
+                    self.print_err("Bad ");
+                    self.print_esc("patterns");
+                    self.help(&["(See Appendix H.)"]);
+                    self.error();
+                }
+            }
+        }
+    }
+
+    // 963
+    // inserts the pattern hc[1..k] with hyphen levels hyf[0..k] into the linked trie
+    fn insert_pattern(&mut self, k: usize) {
+        // 965: compute the trie op code, v, and set l:=0
+        if self.hc[1] == 0 {
+            self.hyf[0] = 0;
+        }
+        if self.hc[k] == 0 {
+            self.hyf[k] = 0;
+        }
+        let mut l = k;
+        let mut v = MinQuarterWord;
+        loop {
+            if self.hyf[l] != 0 {
+                v = self.new_trie_op((k - l) as SmallNumber, self.hyf[l] as SmallNumber, v);
+            }
+            if l > 0 {
+                l -= 1;
+            } else {
+                break;
+            }
+        }
+        let mut q: TriePointer = 0;
+        self.hc[0] = self.cur_lang as HalfWord;
+        while l <= k {
+            let c = self.hc[l] as PackedASCIICode;
+            l += 1;
+            let mut p = self.trie_l[q];
+            let mut first_child = true;
+            while p > 0 && c > self.trie_c[p] {
+                q = p;
+                p = self.trie_r[q];
+                first_child = false;
+            }
+            if p == 0 || c < self.trie_c[p] {
+                // 964: insert a new trie node between q and p, and make p point to it
+                if self.trie_ptr == TrieSize as usize {
+                    self.overflow("pattern memory", TrieSize);
+                }
+                self.trie_ptr += 1;
+                self.trie_r[self.trie_ptr] = p;
+                p = self.trie_ptr;
+                self.trie_l[p] = 0;
+                if first_child {
+                    self.trie_l[q] = p;
+                } else {
+                    self.trie_r[q] = p;
+                }
+                self.trie_c[p] = c;
+                self.trie_o[p] = MinQuarterWord;
+            }
+            q = p; // now node q represents hc[0..l-1]
+        }
+        if self.trie_o[q] != MinQuarterWord {
+            self.print_err("Duplicate pattern");
+            self.help(&["(See Appendix H.)"]);
+            self.error();
+        }
+        self.trie_o[q] = v;
+    }
+
+    // 966
+    // packs the linked trie into the form used by hyphenate
+    pub fn init_trie(&mut self) {
+        // 952: get ready to compress the trie
+        self.sort_trie_ops();
+        for p in 0..=TrieSize as usize {
+            self.trie_hash[p] = 0;
+        }
+        let root = self.compress_trie(self.trie_l[0]); // identify equivalent subtries
+        self.trie_l[0] = root;
+        for p in 0..=self.trie_ptr {
+            self.trie_hash[p] = 0; // trie_ref[p]=0 for each p
+        }
+        for p in 0..=255 {
+            self.trie_min[p] = p + 1;
+        }
+        self.set_trie_link(0, 1);
+        self.trie_max = 0;
+        if root != 0 {
+            self.first_fit(root);
+            self.trie_pack(root);
+        }
+        // 958: move the data into trie
+        let h = TwoHalves::new(); // trie_link=0, trie_op=min_quarterword, trie_char=min_quarterword
+        if root == 0 {
+            // no patterns were given
+            for r in 0..=256 {
+                self.trie[r] = h;
+            }
+            self.trie_max = 256;
+        } else {
+            self.trie_fix(root); // this fixes the non-holes in trie
+            let mut r = 0; // used to clean up the packed trie
+            loop {
+                let s = self.trie_link(r) as usize;
+                self.trie[r] = h;
+                r = s;
+                if r > self.trie_max {
+                    break;
+                }
+            }
+        }
+        self.set_trie_char(0, '?' as QuarterWord); // make trie_char(c)<>c for all c
+        self.trie_not_ready = false;
+    }
+
+    // converts the packed trie back into the linked trie of new_patterns, and
+    // rebuilds the hash of trie ops, so that more patterns can be inserted
+    fn unpack_trie(&mut self) {
+        let o = TrieOpSize as usize;
+        for k in 0..=2 * o {
+            self.trie_op_hash[k] = 0;
+        }
+        for l in 0..=255 {
+            for u in 1..=self.trie_used[l] as i32 {
+                let j = (self.op_start[l] + u) as usize; // ops are sorted by language in the packed trie
+                self.trie_op_lang[j] = l as ASCIICode;
+                self.trie_op_val[j] = u as QuarterWord;
+                let mut h = (self.hyf_num[j] + 313 * self.hyf_distance[j] + 361 * self.hyf_next[j] as i32 + 1009 * l as i32).abs() % (TrieOpSize + TrieOpSize) - TrieOpSize;
+                while self.trie_op_hash[(h + TrieOpSize) as usize] != 0 {
+                    if h > -TrieOpSize {
+                        h -= 1;
+                    } else {
+                        h = TrieOpSize;
+                    }
+                }
+                self.trie_op_hash[(h + TrieOpSize) as usize] = j as i32;
+            }
+        }
+        self.trie_ptr = 0;
+        self.trie_c[0] = 0;
+        self.trie_o[0] = MinQuarterWord;
+        self.trie_r[0] = 0;
+        let root = self.unpack_family(1); // the language roots are packed relative to 1
+        self.trie_l[0] = root;
+        self.trie_not_ready = true;
+    }
+
+    // the linked siblings for the packed family relative to h
+    fn unpack_family(&mut self, h: TriePointer) -> TriePointer {
+        let mut first = 0; // the first sibling
+        let mut last = 0; // the most recent sibling
+        for c in 0..=255 {
+            let z = h + c;
+            // a hole looks like a node for character 0 with neither op nor children
+            if z > self.trie_max || self.trie_char(z) as usize != c || (self.trie_link(z) == 0 && self.trie_op(z) == MinQuarterWord) {
+                continue;
+            }
+            if self.trie_ptr == TrieSize as usize {
+                self.overflow("pattern memory", TrieSize);
+            }
+            self.trie_ptr += 1;
+            let p = self.trie_ptr;
+            self.trie_c[p] = c as PackedASCIICode;
+            self.trie_o[p] = self.trie_op(z);
+            self.trie_r[p] = 0;
+            let l = if self.trie_link(z) == 0 { 0 } else { self.unpack_family(self.trie_link(z) as usize) };
+            self.trie_l[p] = l;
+            if last == 0 {
+                first = p;
+            } else {
+                self.trie_r[last] = p;
+            }
+            last = p;
+        }
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::text_font;
+    use crate::{typeset_to, OutputFormat, Vfs};
+
+    #[test]
+    fn patterns_can_be_added_after_the_trie_is_packed() {
+        let vfs = Vfs::new();
+        vfs.add_file("text.tfm", text_font().bytes());
+        vfs.add_file("doc.tex", "\\catcode`\\{=1 \\catcode`\\}=2 \\catcode`\\#=6
+\\defaulthyphenchar=`- \\font\\rm=text \\rm \\lefthyphenmin=2 \\righthyphenmin=2
+\\def\\showhyphens#1{\\setbox0\\vbox{\\parfillskip=0pt \\hsize=1000pt \\pretolerance=-1 \\tolerance=-1 \\hbadness=0 \\showboxdepth=0 \\ #1}}
+\\patterns{1na}\\showhyphens{banana}
+\\language=1 \\patterns{n1a}\\showhyphens{banana}
+\\language=0 \\showhyphens{banana}
+\\end
+");
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]).into_owned();
+        // the first paragraph packs the trie; language 1 gets patterns of its
+        // own afterwards, and language 0 keeps the ones it had
+        for (line, word) in &[(4, "ba-na-na"), (5, "ban-ana"), (6, "ba-na-na")] {
+            let report = format!("in paragraph at lines {}--{}\n[] \\rm {}\n", line, line, word);
+            assert!(log.contains(&report), "{} {}", report, log);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{FileKind, FileResolver};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read, Write};
+use std::rc::Rc;
+
+// the files of a Vfs; inputs are shared with the readers handed out to TeX
+#[derive(Default)]
+struct VfsFiles {
+    inputs: BTreeMap<String, Rc<[u8]>>,
+    outputs: BTreeMap<String, Vec<u8>>,
+}
+
+// an in-memory file system: TeX reads the files registered with add_file and
+// everything it writes, such as the log, DVI and \write files, is kept in memory.
+// Clones share the same files, so the caller keeps one to collect the outputs
+// after giving another to set_resolver.
+#[derive(Clone, Default)]
+pub struct Vfs {
+    files: Rc<RefCell<VfsFiles>>,
+}
+
+// strips a leading `./', which TeX's names for files in the current directory may have
+fn vfs_name(name: &str) -> &str {
+    name.trim_start_matches("./")
+}
+
+impl Vfs {
+    pub fn new() -> Vfs {
+        Vfs::default()
+    }
+
+    // makes data available to TeX under the given name, replacing any earlier file
+    pub fn add_file(&self, name: &str, data: impl Into<Vec<u8>>) {
+        let data: Vec<u8> = data.into();
+        self.files.borrow_mut().inputs.insert(vfs_name(name).to_string(), data.into());
+    }
+
+    // the contents of an output file written so far
+    pub fn output(&self, name: &str) -> Option<Vec<u8>> {
+        self.files.borrow().outputs.get(vfs_name(name)).cloned()
+    }
+
+    // the names of the files written so far
+    pub fn output_names(&self) -> Vec<String> {
+        self.files.borrow().outputs.keys().cloned().collect()
+    }
+
+    // removes and returns all output files
+    pub fn take_outputs(&self) -> BTreeMap<String, Vec<u8>> {
+        std::mem::take(&mut self.files.borrow_mut().outputs)
+    }
+}
+
+// a file being written into a Vfs
+struct VfsWriter {
+    files: Rc<RefCell<VfsFiles>>,
+    name: String,
+}
+
+impl Write for VfsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut files = self.files.borrow_mut();
+        files.outputs.entry(self.name.clone()).or_default().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl FileResolver for Vfs {
+    // files TeX has written in this run are found before the registered inputs,
+    // so that a file can be written and then read back
+    fn open_in(&mut self, name: &str, _kind: FileKind) -> Option<(String, Box<dyn Read>)> {
+        let name = vfs_name(name);
+        let files = self.files.borrow();
+        let data: Rc<[u8]> = match files.outputs.get(name) {
+            Some(d) => d.as_slice().into(),
+            None => files.inputs.get(name)?.clone(),
+        };
+        Some((name.to_string(), Box::new(Cursor::new(data))))
+    }
+
+    fn open_out(&mut self, name: &str) -> Option<Box<dyn Write>> {
+        let name = vfs_name(name).to_string();
+        self.files.borrow_mut().outputs.insert(name.clone(), Vec::new());
+        Some(Box::new(VfsWriter { files: self.files.clone(), name }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dvi_reader::{DviCommand, DviFile};
+    use crate::{typeset_to, OutputFormat, TerminalOutput};
+
+    #[test]
+    fn typesets_from_memory() {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", &br"\catcode`\{=1 \catcode`\}=2
+\immediate\openout1=notes \immediate\write1{first}
+\shipout\hbox{\vrule width 1pt height 2pt\special{hello}\write1{page \the\count0}}
+\immediate\closeout1 \end
+"[..]);
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), [TerminalOutput, "doc.dvi", "doc.log", "notes.tex"]);
+        let terminal = String::from_utf8_lossy(&outputs[TerminalOutput]);
+        assert!(terminal.contains("(doc.tex [0] )"), "{}", terminal);
+        assert_eq!(outputs["notes.tex"], b"first\npage 0\n");
+        let log = String::from_utf8_lossy(&outputs["doc.log"]);
+        assert!(log.contains("(doc.tex [0] )"), "{}", log);
+        assert!(log.contains("Output written on doc.dvi (1 page, "), "{}", log);
+        let dvi = DviFile::parse(&outputs["doc.dvi"]).unwrap();
+        assert_eq!(dvi.pages.len(), 1);
+        let commands: Vec<_> = dvi.pages[0].commands.iter().map(|(_, c)| c.clone()).collect();
+        assert!(commands.contains(&DviCommand::SetRule { height: 2 * 65536, width: 65536 }));
+        assert!(commands.contains(&DviCommand::Special(b"hello".to_vec())));
+    }
+
+    #[test]
+    fn errors_do_not_wait_for_the_terminal() {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", &br"\errorstopmode \undefined \end"[..]);
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let terminal = String::from_utf8_lossy(&outputs[TerminalOutput]);
+        assert!(terminal.contains("! Undefined control sequence."), "{}", terminal);
+        assert!(terminal.contains("! Emergency stop."), "{}", terminal);
+    }
+
+    #[test]
+    fn typesetting_runs_nonstop() {
+        let vfs = Vfs::new();
+        vfs.add_file("doc.tex", &br"\undefined \undefined \end"[..]);
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let log = String::from_utf8_lossy(&outputs["doc.log"]);
+        assert_eq!(log.matches("! Undefined control sequence.").count(), 2, "{}", log);
+        assert!(!log.contains("job aborted"), "{}", log);
+    }
+}
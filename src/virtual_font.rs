@@ -0,0 +1,425 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::rc::Rc;
+
+// A virtual font has a TFM file like any other, which is all TeX needs while
+// typesetting, and a VF file that gives each of its characters as a packet of
+// DVI commands in terms of other fonts, rules and specials. The VF file is read
+// when the font is first shipped out, and hlist_out plays the packets back in
+// place of the characters, so only the fonts they refer to reach the DVI file.
+
+// the identification byte of VF files, and the opcode of a long character packet
+const VfId: u8 = 202;
+const LongChar: u8 = 242;
+
+// how deeply virtual fonts may be built out of other virtual fonts
+const MaxVfNesting: usize = 10;
+
+// one command of a character packet; w, x, y and z have already been resolved
+// into plain movements, and dimensions scaled to the size of the font
+#[derive(Debug, Clone, PartialEq)]
+pub enum VfCommand {
+    Char { f: InternalFontNumber, c: u8, advance: bool }, // set_char or put_char
+    Rule { height: Scaled, width: Scaled, advance: bool }, // set_rule or put_rule
+    Push,
+    Pop,
+    Right(Scaled),
+    Down(Scaled),
+    Special(Vec<u8>), // the bytes of an xxx command
+}
+
+// the character packets of a virtual font, in terms of internal font numbers
+#[derive(Debug)]
+pub struct VirtualFont {
+    packets: BTreeMap<u8, Vec<VfCommand>>,
+}
+
+// what is known about a font's VF file
+#[derive(Debug, Clone, Default)]
+pub enum VfStatus {
+    #[default]
+    Unchecked, // not looked for yet
+    Real, // there is none, so the font's characters go straight into the DVI file
+    Virtual(Rc<VirtualFont>),
+}
+
+// the amounts of the w, x, y and z movements within a packet
+#[derive(Debug, Clone, Copy, Default)]
+struct VfRegisters {
+    w: Scaled,
+    x: Scaled,
+    y: Scaled,
+    z: Scaled,
+}
+
+// a fix_word in units of the font size z; VF files use the full range of fix_words,
+// so this is done in 64 bits rather than with the TFM method of section 571
+fn fix_scaled(x: i32, z: Scaled) -> Scaled {
+    (x as i64 * z as i64 / 0o4000000) as Scaled
+}
+
+// the bytes of a VF file or of one of its packets, read in order; running off
+// the end is reported like any other malformation
+struct VfFile<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> VfFile<'a> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, ()> {
+        let b = *self.data.get(self.pos).ok_or(())?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], ()> {
+        let b = self.data.get(self.pos..self.pos + n).ok_or(())?;
+        self.pos += n;
+        Ok(b)
+    }
+
+    // an unsigned quantity of n bytes
+    fn unsigned(&mut self, n: u8) -> Result<u32, ()> {
+        let mut x = 0;
+        for _ in 0..n {
+            x = (x << 8) + self.byte()? as u32;
+        }
+        Ok(x)
+    }
+
+    // a signed quantity of n bytes, in two's complement
+    fn signed(&mut self, n: u8) -> Result<i32, ()> {
+        let x = self.unsigned(n)?;
+        let shift = 32 - 8 * n as u32;
+        Ok(((x << shift) as i32) >> shift)
+    }
+
+    // a font number, which is signed only in its four-byte form
+    fn font_number(&mut self, n: u8) -> Result<i32, ()> {
+        if n == 4 { self.signed(4) } else { Ok(self.unsigned(n)? as i32) }
+    }
+}
+
+impl TexState {
+    // the packets of font f if it is a virtual font, reading its VF file the first time
+    pub fn virtual_font(&mut self, f: InternalFontNumber) -> Option<Rc<VirtualFont>> {
+        if let VfStatus::Unchecked = self.font_vf[f] {
+            self.font_vf[f] = self.load_virtual_font(f);
+        }
+        match &self.font_vf[f] {
+            VfStatus::Virtual(vf) => Some(vf.clone()),
+            _ => None,
+        }
+    }
+
+    // the DVI commands that typeset character c of font f, if f is a virtual font.
+    // Characters of local fonts that are virtual themselves are expanded in turn,
+    // so the commands refer only to real fonts. They are enclosed in push and pop,
+    // so a set_char is completed by moving right by the character's width
+    pub fn vf_packet(&mut self, f: InternalFontNumber, c: u8) -> Option<Vec<VfCommand>> {
+        let vf = self.virtual_font(f)?;
+        let mut cmds = vec![VfCommand::Push];
+        self.expand_vf_char(&vf, f, c, 0, &mut cmds);
+        cmds.push(VfCommand::Pop);
+        Some(cmds)
+    }
+
+    fn expand_vf_char(&mut self, vf: &VirtualFont, f: InternalFontNumber, c: u8, depth: usize,
+                      cmds: &mut Vec<VfCommand>) {
+        let packet = match vf.packets.get(&c) {
+            Some(p) => p,
+            None => {
+                self.char_warning(f, c);
+                return;
+            }
+        };
+        for cmd in packet {
+            if let VfCommand::Char { f: g, c: d, advance } = *cmd {
+                if let Some(inner) = self.virtual_font(g) {
+                    if depth + 1 >= MaxVfNesting {
+                        // most likely a virtual font that refers to itself
+                        self.char_warning(g, d);
+                        continue;
+                    }
+                    cmds.push(VfCommand::Push);
+                    self.expand_vf_char(&inner, g, d, depth + 1, cmds);
+                    cmds.push(VfCommand::Pop);
+                    if advance {
+                        cmds.push(VfCommand::Right(self.char_width(g, self.char_info(g, d))));
+                    }
+                    continue;
+                }
+            }
+            cmds.push(cmd.clone());
+        }
+    }
+
+    fn load_virtual_font(&mut self, f: InternalFontNumber) -> VfStatus {
+        if f == NullFont || is_opentype_name(&self.str_bytes(self.font_name[f])) {
+            return VfStatus::Real;
+        }
+        self.pack_file_name(self.font_name[f], self.font_area[f], ExtVf);
+        let mut data = Vec::new();
+        let file_opened = match self.vf_open_in() {
+            Some(mut file) => file.read_to_end(&mut data).is_ok(),
+            None => false,
+        };
+        if !file_opened {
+            return VfStatus::Real;
+        }
+        match self.read_vf(&data, f) {
+            Ok(vf) => VfStatus::Virtual(Rc::new(vf)),
+            Err(()) => {
+                self.print_err("Bad VF file for font ");
+                self.print_font_name_and_size(f);
+                self.help(&["I couldn't make sense of the virtual font file for this font,",
+                    "so its characters will go into the DVI file as they are."]);
+                self.error();
+                VfStatus::Real
+            }
+        }
+    }
+
+    // reads the preamble, font definitions and character packets of a VF file
+    fn read_vf(&mut self, data: &[u8], f: InternalFontNumber) -> Result<VirtualFont, ()> {
+        let mut vf = VfFile { data, pos: 0 };
+        if vf.byte()? != Pre || vf.byte()? != VfId {
+            return Err(());
+        }
+        let k = vf.byte()? as usize;
+        vf.bytes(k)?; // the comment
+        let cs = vf.unsigned(4)?;
+        vf.signed(4)?; // the design size, which the TFM file has already given
+        self.vf_check_sum(cs, f, f);
+        let z = self.font_size[f];
+        // the local fonts; the first one is current when each packet begins
+        let mut fonts = BTreeMap::new();
+        let mut first = None;
+        loop {
+            let o = vf.byte()?;
+            if !(FntDef1..=FntDef1 + 3).contains(&o) {
+                vf.pos -= 1;
+                break;
+            }
+            let k = vf.font_number(o - FntDef1 + 1)?;
+            let c = vf.unsigned(4)?;
+            let s = fix_scaled(vf.signed(4)?, z);
+            vf.signed(4)?; // the local font's design size
+            let a = vf.byte()? as usize;
+            let l = vf.byte()? as usize;
+            let area = vf.bytes(a)?;
+            let name = vf.bytes(l)?;
+            if s <= 0 {
+                return Err(());
+            }
+            let g = self.vf_local_font(f, area, name, s);
+            if g != NullFont {
+                self.vf_check_sum(c, g, f);
+            }
+            first.get_or_insert(k);
+            fonts.insert(k, g);
+        }
+        // the character packets
+        let mut packets = BTreeMap::new();
+        loop {
+            let o = vf.byte()?;
+            if o == Post {
+                break;
+            }
+            let (pl, cc) = if o == LongChar {
+                let pl = vf.unsigned(4)?;
+                let cc = vf.unsigned(4)?;
+                vf.unsigned(4)?; // the width, which the TFM file has already given
+                (pl as usize, cc)
+            } else if o < LongChar {
+                let cc = vf.byte()? as u32;
+                vf.unsigned(3)?;
+                (o as usize, cc)
+            } else {
+                return Err(());
+            };
+            let dvi = vf.bytes(pl)?;
+            let cmds = self.read_vf_packet(dvi, &fonts, first, z)?;
+            if cc <= 255 {
+                packets.insert(cc as u8, cmds);
+            }
+        }
+        Ok(VirtualFont { packets })
+    }
+
+    // loads a font that a virtual font refers to, at size s, unless it is
+    // already loaded; f is the virtual font, whose identifier is used in messages
+    fn vf_local_font(&mut self, f: InternalFontNumber, area: &[u8], name: &[u8], s: Scaled) -> InternalFontNumber {
+        for g in FontBase as InternalFontNumber + 1..=self.font_ptr {
+            if self.str_bytes(self.font_name[g]) == name && self.str_bytes(self.font_area[g]) == area
+                && self.font_size[g] == s {
+                return g;
+            }
+        }
+        let nom = self.make_str(&String::from_utf8_lossy(name));
+        let aire = if area.is_empty() { EmptyString } else { self.make_str(&String::from_utf8_lossy(area)) };
+        let u = (FontIdBase as InternalFontNumber + f) as Pointer;
+        let g = self.read_font_info(u, nom, aire, s);
+        if g != NullFont {
+            // the local font is known by the virtual font's identifier
+            self.eqtb[FontIdBase as usize + g] = self.eqtb[u as usize];
+            self.set_equiv(FontIdBase as usize + g, g as HalfWord);
+            let t = font_id_text(&self.hash, f as HalfWord);
+            set_font_id_text(&mut self.hash, g as HalfWord, t);
+        }
+        g
+    }
+
+    // warns if a check sum in a VF file disagrees with font g's TFM file
+    fn vf_check_sum(&mut self, cs: u32, g: InternalFontNumber, f: InternalFontNumber) {
+        let fc = self.font_check[g];
+        let tfm = u32::from_be_bytes([fc.b0, fc.b1, fc.b2, fc.b3]);
+        if cs != 0 && tfm != 0 && cs != tfm {
+            self.print_nl("Checksum mismatch in virtual font ");
+            self.slow_print(self.font_name[f]);
+            if g != f {
+                self.print(" for font ");
+                self.slow_print(self.font_name[g]);
+            }
+        }
+    }
+
+    // decodes the DVI commands of one packet, whose w, x, y and z start at zero
+    fn read_vf_packet(&mut self, dvi: &[u8], fonts: &BTreeMap<i32, InternalFontNumber>, first: Option<i32>,
+                      z: Scaled) -> Result<Vec<VfCommand>, ()> {
+        let mut p = VfFile { data: dvi, pos: 0 };
+        let mut cmds = Vec::new();
+        let mut f = first.and_then(|k| fonts.get(&k).copied());
+        let mut r = VfRegisters::default();
+        let mut stack = Vec::new();
+        while !p.at_end() {
+            let o = p.byte()?;
+            let (c, advance) = match o {
+                _ if o < Set1 => (o as u32, true),
+                _ if o < SetRule => (p.unsigned(o - Set1 + 1)?, true),
+                _ if (Put1..PutRule).contains(&o) => (p.unsigned(o - Put1 + 1)?, false),
+                SetRule | PutRule => {
+                    let height = fix_scaled(p.signed(4)?, z);
+                    let width = fix_scaled(p.signed(4)?, z);
+                    cmds.push(VfCommand::Rule { height, width, advance: o == SetRule });
+                    continue;
+                }
+                Nop => continue,
+                Push => {
+                    stack.push(r);
+                    cmds.push(VfCommand::Push);
+                    continue;
+                }
+                Pop => {
+                    r = stack.pop().ok_or(())?;
+                    cmds.push(VfCommand::Pop);
+                    continue;
+                }
+                _ if (Right1..W0).contains(&o) => {
+                    cmds.push(VfCommand::Right(fix_scaled(p.signed(o - Right1 + 1)?, z)));
+                    continue;
+                }
+                W0 | X0 => {
+                    cmds.push(VfCommand::Right(if o == W0 { r.w } else { r.x }));
+                    continue;
+                }
+                _ if o > W0 && o < Down1 => {
+                    let d = if o < X0 { &mut r.w } else { &mut r.x };
+                    *d = fix_scaled(p.signed(if o < X0 { o - W1 } else { o - X1 } + 1)?, z);
+                    cmds.push(VfCommand::Right(*d));
+                    continue;
+                }
+                _ if (Down1..Y0).contains(&o) => {
+                    cmds.push(VfCommand::Down(fix_scaled(p.signed(o - Down1 + 1)?, z)));
+                    continue;
+                }
+                Y0 | Z0 => {
+                    cmds.push(VfCommand::Down(if o == Y0 { r.y } else { r.z }));
+                    continue;
+                }
+                _ if o > Y0 && o < FntNum0 => {
+                    let d = if o < Z0 { &mut r.y } else { &mut r.z };
+                    *d = fix_scaled(p.signed(if o < Z0 { o - Y1 } else { o - Z1 } + 1)?, z);
+                    cmds.push(VfCommand::Down(*d));
+                    continue;
+                }
+                _ if (FntNum0..Xxx1).contains(&o) => {
+                    let k = if o < Fnt1 { (o - FntNum0) as i32 } else { p.font_number(o - Fnt1 + 1)? };
+                    f = Some(*fonts.get(&k).ok_or(())?);
+                    continue;
+                }
+                _ if (Xxx1..=Xxx4).contains(&o) => {
+                    let k = p.unsigned(o - Xxx1 + 1)? as usize;
+                    cmds.push(VfCommand::Special(p.bytes(k)?.to_vec()));
+                    continue;
+                }
+                _ => return Err(()), // bop, eop, font definitions and the like don't belong in packets
+            };
+            match f {
+                None => return Err(()),
+                Some(g) if g != NullFont && c <= 255 => cmds.push(VfCommand::Char { f: g, c: c as u8, advance }),
+                Some(_) => {} // a font that couldn't be loaded, or a character TeX can't have
+            }
+        }
+        if !stack.is_empty() {
+            return Err(());
+        }
+        Ok(cmds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fonts::{text_font, Tfm};
+    use crate::{typeset_to, DviCommand, DviFile, OutputFormat, Vfs};
+
+    // a VF file for a 10pt font whose A is the a of raw followed by a kern and a
+    // rule, and whose B is a special
+    fn vf() -> Vec<u8> {
+        let fix = |x: i32| x.to_be_bytes();
+        let mut vf = vec![247, 202, 0]; // pre, the VF identification and an empty comment
+        vf.extend_from_slice(&fix(0)); // no check sum
+        vf.extend_from_slice(&fix(10 << 20)); // the design size
+        vf.extend_from_slice(&[243, 0]); // fnt_def1 0
+        vf.extend_from_slice(&fix(0));
+        vf.extend_from_slice(&fix(1 << 20)); // at the size of the virtual font
+        vf.extend_from_slice(&fix(10 << 20));
+        vf.extend_from_slice(&[0, 3]);
+        vf.extend_from_slice(b"raw");
+        let a = [b'a', 145, 2, 0, 0, 137, 0, 4, 0, 0, 0, 1, 0, 0]; // set a, right3, put_rule
+        vf.extend_from_slice(&[a.len() as u8, b'A', 0, 0, 0]);
+        vf.extend_from_slice(&a);
+        let b = [239, 2, b'v', b'f']; // xxx1
+        vf.extend_from_slice(&[b.len() as u8, b'B', 0, 0, 0]);
+        vf.extend_from_slice(&b);
+        vf.push(248); // post
+        while vf.len() % 4 != 0 {
+            vf.push(248);
+        }
+        vf
+    }
+
+    #[test]
+    fn virtual_characters_are_replaced_by_their_packets() {
+        let vfs = Vfs::new();
+        vfs.add_file("raw.tfm", text_font().bytes());
+        vfs.add_file("vt.tfm", Tfm::new(10.0, &[0.0; 7]).char(b'A', 0.8, 0.7, 0.0).char(b'B', 0.0, 0.0, 0.0).bytes());
+        vfs.add_file("vt.vf", vf());
+        vfs.add_file("doc.tex", &br"\catcode`\{=1 \catcode`\}=2 \font\v=vt \shipout\hbox{\v AB} \end"[..]);
+        let outputs = typeset_to(&vfs, "doc", OutputFormat::Dvi);
+        let dvi = DviFile::parse(&outputs["doc.dvi"]).unwrap();
+        let fonts: Vec<_> = dvi.postamble.fonts.iter().map(|d| d.name.clone()).collect();
+        assert_eq!(fonts, [b"raw".to_vec()]);
+        let commands: Vec<_> = dvi.pages[0].commands.iter().map(|(_, c)| c.clone())
+            .filter(|c| !matches!(c, DviCommand::FontDef(_))).collect();
+        let pt = 65536;
+        assert_eq!(commands[1..], [DviCommand::Font(1), DviCommand::SetChar(b'a' as u32), DviCommand::Right(5 * pt / 4),
+                                   DviCommand::PutRule { height: 5 * pt / 2, width: 5 * pt / 8 },
+                                   DviCommand::Right(7 * pt / 4), DviCommand::Special(b"vf".to_vec())]);
+    }
+}